@@ -54,6 +54,10 @@ box.schema.func.create('libperf_test.bench_custom_encode', {language = 'C'})
 box.schema.func.create('libperf_test.bench_custom_decode', {language = 'C'})
 box.schema.func.create('libperf_test.bench_serde_encode', {language = 'C'})
 box.schema.func.create('libperf_test.bench_serde_decode', {language = 'C'})
+box.schema.func.create('libperf_test.bench_scan_lua_numeric', {language = 'C'})
+box.schema.func.create('libperf_test.bench_scan_rust_numeric', {language = 'C'})
+box.schema.func.create('libperf_test.bench_scan_lua_string', {language = 'C'})
+box.schema.func.create('libperf_test.bench_scan_rust_string', {language = 'C'})
 box.schema.func.create('libperf_test.l_print_stats', {language = 'C'})
 box.schema.func.create('libperf_test.l_n_iters', {language = 'C'})
 
@@ -95,4 +99,10 @@ box.func['libperf_test.bench_custom_encode']:call()
 box.func['libperf_test.bench_serde_encode']:call()
 box.func['libperf_test.bench_custom_decode']:call()
 box.func['libperf_test.bench_serde_decode']:call()
+print()
+print("=================== full_scan ===================")
+box.func['libperf_test.bench_scan_lua_numeric']:call()
+box.func['libperf_test.bench_scan_rust_numeric']:call()
+box.func['libperf_test.bench_scan_lua_string']:call()
+box.func['libperf_test.bench_scan_rust_string']:call()
 os.exit(0)
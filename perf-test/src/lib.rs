@@ -128,6 +128,80 @@ mod msgpack_serialization {
     }
 }
 
+mod full_scan {
+    use super::{harness_iter, print_stats};
+    use tarantool::proc;
+    use tarantool::space::{FieldType, Space};
+
+    const N_TUPLES: u64 = 1_000;
+
+    fn space() -> Space {
+        let space = Space::builder("perf_full_scan")
+            .if_not_exists(true)
+            .field(("id", FieldType::Unsigned))
+            .field(("value", FieldType::Unsigned))
+            .field(("name", FieldType::String))
+            .create()
+            .unwrap();
+        space
+            .index_builder("pk")
+            .if_not_exists(true)
+            .create()
+            .unwrap();
+        if space.is_empty().unwrap() {
+            for id in 0..N_TUPLES {
+                let name = format!("user_{}", id);
+                space.insert(&(id, id * 7 % N_TUPLES, name)).unwrap();
+            }
+        }
+        space
+    }
+
+    #[proc]
+    fn bench_scan_lua_numeric() {
+        let space = space();
+        let samples = harness_iter(|| {
+            let scan = space.scan().filter_lua("tuple[2] > 900").unwrap();
+            let _count = scan.iter().unwrap().count();
+        });
+        print_stats("scan_lua_numeric", samples);
+    }
+
+    #[proc]
+    fn bench_scan_rust_numeric() {
+        let space = space();
+        let samples = harness_iter(|| {
+            let scan = space
+                .scan()
+                .filter(|t| t.get::<_, u64>(1).map_or(false, |v| v > 900));
+            let _count = scan.iter().unwrap().count();
+        });
+        print_stats("scan_rust_numeric", samples);
+    }
+
+    #[proc]
+    fn bench_scan_lua_string() {
+        let space = space();
+        let samples = harness_iter(|| {
+            let scan = space.scan().filter_lua("tuple[3]:sub(-2) == '42'").unwrap();
+            let _count = scan.iter().unwrap().count();
+        });
+        print_stats("scan_lua_string", samples);
+    }
+
+    #[proc]
+    fn bench_scan_rust_string() {
+        let space = space();
+        let samples = harness_iter(|| {
+            let scan = space
+                .scan()
+                .filter(|t| t.get::<_, &str>(2).map_or(false, |v| v.ends_with("42")));
+            let _count = scan.iter().unwrap().count();
+        });
+        print_stats("scan_rust_string", samples);
+    }
+}
+
 #[proc]
 fn l_print_stats(fn_name: &str, samples: Vec<i64>) {
     assert_eq!(samples.len(), N_ITERS);
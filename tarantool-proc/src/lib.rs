@@ -31,6 +31,16 @@ pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
     test::impl_macro_attribute(attr, item)
 }
 
+mod lua;
+
+/// Create a lua chunk, which is syntax checked at compile time.
+///
+/// See `tarantool::lua!` doc-comments in tarantool crate for details.
+#[proc_macro]
+pub fn lua(input: TokenStream) -> TokenStream {
+    lua::impl_macro(input)
+}
+
 mod msgpack {
     use darling::FromDeriveInput;
     use proc_macro2::TokenStream;
@@ -42,7 +52,7 @@ mod msgpack {
     };
 
     #[derive(Default, FromDeriveInput)]
-    #[darling(attributes(encode), default)]
+    #[darling(attributes(encode, decode), default)]
     pub struct Args {
         /// Whether this struct should be serialized as MP_MAP instead of MP_ARRAY.
         pub as_map: bool,
@@ -52,6 +62,35 @@ mod msgpack {
         pub allow_array_optionals: bool,
         /// <https://serde.rs/enum-representations.html#untagged>
         pub untagged: bool,
+        /// Decode using `DecodeMode::Strict` unless overridden by the context.
+        pub strict: bool,
+        /// Decode using `DecodeMode::Lenient` unless overridden by the context.
+        pub lenient: bool,
+        /// Decode using `DecodeMode::Truncate` unless overridden by the context.
+        pub truncate: bool,
+    }
+
+    impl Args {
+        /// Returns an expression evaluating to the `DecodeMode` which should
+        /// be used for decoding.
+        fn decode_mode(
+            &self,
+            tarantool_crate: &Path,
+            attrs_span: impl Fn() -> SpanRange,
+        ) -> TokenStream {
+            let mode = match (self.strict, self.lenient, self.truncate) {
+                (true, false, false) => quote! { Strict },
+                (false, true, false) => quote! { Lenient },
+                (false, false, _) => quote! { Truncate },
+                _ => abort!(
+                    attrs_span(),
+                    "only one of `strict`, `lenient` or `truncate` attributes can be specified"
+                ),
+            };
+            quote! {
+                context.decode_mode().unwrap_or(#tarantool_crate::msgpack::DecodeMode::#mode)
+            }
+        }
     }
 
     pub fn add_trait_bounds(mut generics: Generics, tarantool_crate: &Path) -> Generics {
@@ -92,6 +131,9 @@ mod msgpack {
         Map,
         /// TODO: Field should be serialized as MP_ARRAY, ignoring struct-level serialization type.
         Vec,
+        /// Field collects all of the MP_MAP entries with keys which don't
+        /// correspond to any other field of the struct.
+        FlattenUnknown,
     }
 
     impl FieldAttr {
@@ -103,7 +145,10 @@ mod msgpack {
 
             let mut encode_attr = None;
 
-            for attr in attrs.iter().filter(|attr| attr.path.is_ident("encode")) {
+            for attr in attrs
+                .iter()
+                .filter(|attr| attr.path.is_ident("encode") || attr.path.is_ident("decode"))
+            {
                 if encode_attr.is_some() {
                     return Err(syn::Error::new(
                         attr.span(),
@@ -147,6 +192,14 @@ mod msgpack {
                         Ok(Some(Self::Map))
                     } else if ident == "as_vec" {
                         Ok(Some(Self::Vec))
+                    } else if ident == "flatten_unknown" {
+                        if !attr.path.is_ident("decode") {
+                            return Err(syn::Error::new(
+                                ident.span(),
+                                "`flatten_unknown` is only allowed in `#[decode(...)]`",
+                            ));
+                        }
+                        Ok(Some(Self::FlattenUnknown))
                     } else {
                         Err(syn::Error::new(ident.span(), "unknown encoding type"))
                     }
@@ -156,6 +209,45 @@ mod msgpack {
         }
     }
 
+    /// Returns a byte string literal with the msgpack encoding of `s`, so that
+    /// the constant strings (e.g. the field names) aren't encoded at runtime.
+    fn encoded_str(s: &str, span: proc_macro2::Span) -> syn::LitByteStr {
+        let len = s.len();
+        let mut bytes = Vec::with_capacity(len + 5);
+        if len < 32 {
+            bytes.push(0xa0 | len as u8);
+        } else if len < 0x100 {
+            bytes.push(0xd9);
+            bytes.push(len as u8);
+        } else if len < 0x10000 {
+            bytes.push(0xda);
+            bytes.extend_from_slice(&(len as u16).to_be_bytes());
+        } else {
+            bytes.push(0xdb);
+            bytes.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        bytes.extend_from_slice(s.as_bytes());
+        syn::LitByteStr::new(&bytes, span)
+    }
+
+    /// Returns the field marked with `#[decode(flatten_unknown)]` if there is
+    /// one, or errors if there are several of them.
+    fn flatten_unknown_field(fields: &FieldsNamed) -> Result<Option<&Field>, syn::Error> {
+        let mut res = None;
+        for field in &fields.named {
+            if let Some(FieldAttr::FlattenUnknown) = FieldAttr::from_field(field)? {
+                if res.is_some() {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        "only one field can be marked with `flatten_unknown`",
+                    ));
+                }
+                res = Some(field);
+            }
+        }
+        Ok(res)
+    }
+
     fn encode_named_fields(
         fields: &FieldsNamed,
         tarantool_crate: &Path,
@@ -167,6 +259,7 @@ mod msgpack {
             .flat_map(|f| {
                 let field_name = f.ident.as_ref().expect("only named fields here");
                 let field_repr = format_ident!("{}", field_name).to_string();
+                let field_key = encoded_str(&field_repr, f.span());
                 let field_attr = unwrap_or_compile_error!(FieldAttr::from_field(f));
 
                 let s = if add_self {
@@ -177,7 +270,7 @@ mod msgpack {
 
                 let write_key = quote_spanned! {f.span()=>
                     if as_map {
-                        #tarantool_crate::msgpack::rmp::encode::write_str(w, #field_repr)?;
+                        w.write_all(#field_key)?;
                     }
                 };
                 if let Some(attr) = field_attr {
@@ -186,6 +279,20 @@ mod msgpack {
                             #write_key
                             w.write_all(#s #field_name)?;
                         },
+                        FieldAttr::FlattenUnknown if !add_self => syn::Error::new(
+                            f.span(),
+                            "`flatten_unknown` is only supported for structs",
+                        )
+                        .to_compile_error(),
+                        FieldAttr::FlattenUnknown => quote_spanned! {f.span()=>
+                            if as_map {
+                                for (key, value) in #s #field_name {
+                                    let key: &str = ::std::convert::AsRef::as_ref(key);
+                                    #tarantool_crate::msgpack::rmp::encode::write_str(w, key)?;
+                                    #tarantool_crate::msgpack::Encode::encode(value, w, context)?;
+                                }
+                            }
+                        },
                         // TODO: encode with `#[encode(as_map)]` and `#[encode(as_vec)]`
                         FieldAttr::Map => {
                             syn::Error::new(f.span(), "`as_map` is not currently supported")
@@ -223,6 +330,11 @@ mod msgpack {
                         FieldAttr::Raw => quote_spanned! {f.span()=>
                             w.write_all(&self.#index)?;
                         },
+                        FieldAttr::FlattenUnknown => syn::Error::new(
+                            f.span(),
+                            "`flatten_unknown` is only supported for named fields",
+                        )
+                        .to_compile_error(),
                         // TODO: encode with `#[encode(as_map)]` and `#[encode(as_vec)]`
                         FieldAttr::Map => {
                             syn::Error::new(f.span(), "`as_map` is not currently supported")
@@ -260,7 +372,16 @@ mod msgpack {
                 }
                 match data.fields {
                     Fields::Named(ref fields) => {
-                        let field_count = fields.named.len() as u32;
+                        let mut field_count = fields.named.len() as u32;
+                        let flatten_unknown =
+                            unwrap_or_compile_error!(flatten_unknown_field(fields));
+                        let unknown_count = if let Some(field) = flatten_unknown {
+                            field_count -= 1;
+                            let field_name = &field.ident;
+                            quote! { + self.#field_name.len() as u32 }
+                        } else {
+                            quote! {}
+                        };
                         let fields = encode_named_fields(fields, tarantool_crate, true);
                         quote! {
                             let as_map = match context.struct_style() {
@@ -269,7 +390,7 @@ mod msgpack {
                                 StructStyle::ForceAsArray => false,
                             };
                             if as_map {
-                                #tarantool_crate::msgpack::rmp::encode::write_map_len(w, #field_count)?;
+                                #tarantool_crate::msgpack::rmp::encode::write_map_len(w, #field_count #unknown_count)?;
                             } else {
                                 #tarantool_crate::msgpack::rmp::encode::write_array_len(w, #field_count)?;
                             }
@@ -308,6 +429,7 @@ mod msgpack {
                     .flat_map(|variant| {
                         let variant_name = &variant.ident;
                         let variant_repr = format_ident!("{}", variant_name).to_string();
+                        let variant_key = encoded_str(&variant_repr, variant.span());
                         match variant.fields {
                             Fields::Named(ref fields) => {
                                 let field_count = fields.named.len() as u32;
@@ -325,7 +447,7 @@ mod msgpack {
                                 } else {
                                     quote! {
                                         Self::#variant_name { #(#field_names),*} => {
-                                            #tarantool_crate::msgpack::rmp::encode::write_str(w, #variant_repr)?;
+                                            w.write_all(#variant_key)?;
                                             #tarantool_crate::msgpack::rmp::encode::write_array_len(w, #field_count)?;
                                             let as_map = false;
                                             #fields
@@ -351,7 +473,7 @@ mod msgpack {
                                 } else {
                                     quote! {
                                         Self::#variant_name ( #(#field_names),*) => {
-                                            #tarantool_crate::msgpack::rmp::encode::write_str(w, #variant_repr)?;
+                                            w.write_all(#variant_key)?;
                                             #tarantool_crate::msgpack::rmp::encode::write_array_len(w, #field_count)?;
                                             #fields
                                         }
@@ -366,7 +488,7 @@ mod msgpack {
                                 } else {
                                     quote! {
                                         Self::#variant_name => {
-                                            #tarantool_crate::msgpack::rmp::encode::write_str(w, #variant_repr)?;
+                                            w.write_all(#variant_key)?;
                                             #tarantool_crate::msgpack::Encode::encode(&(), w, context)?;
                                         }
                                     }
@@ -402,15 +524,35 @@ mod msgpack {
     ) -> TokenStream {
         let allow_array_optionals = args.allow_array_optionals;
 
+        let flatten_unknown = unwrap_or_compile_error!(flatten_unknown_field(fields));
+        if let (Some(field), Some(_)) = (flatten_unknown, enum_variant) {
+            return syn::Error::new(
+                field.span(),
+                "`flatten_unknown` is only supported for structs",
+            )
+            .to_compile_error();
+        }
+
         let mut var_names = Vec::with_capacity(fields.named.len());
         let mut met_option = false;
-        let fields_amount = fields.named.len();
+        let fields_amount = fields.named.len() - flatten_unknown.is_some() as usize;
         let mut fields_passed = fields_amount;
         let code: TokenStream = fields
             .named
             .iter()
             .map(|f| {
-                if f.ty.is_option() {
+                if flatten_unknown.map_or(false, |field| std::ptr::eq(field, f)) {
+                    // Unknown keys can only be present in MP_MAP, which is
+                    // decoded separately (see `decode_named_fields_by_key`).
+                    let field_type = &f.ty;
+                    let field_ident = f.ident.as_ref().expect("only named fields here");
+                    let var_name = format_ident!("_field_{}", field_ident);
+                    let out = quote_spanned! {f.span()=>
+                        let #var_name: #field_type = ::std::default::Default::default();
+                    };
+                    var_names.push(var_name);
+                    out
+                } else if f.ty.is_option() {
                     met_option = true;
                     fields_passed -= 1;
                     decode_named_optional_field(f, tarantool_crate, &mut var_names, allow_array_optionals, fields_amount, fields_passed)
@@ -423,7 +565,7 @@ mod msgpack {
                         .to_compile_error();
                     }
                     fields_passed -= 1;
-                    decode_named_required_field(f, tarantool_crate, &mut var_names)
+                    decode_named_required_field(f, tarantool_crate, &mut var_names, fields_amount)
                 }
             })
             .collect();
@@ -433,14 +575,173 @@ mod msgpack {
         } else {
             quote! {}
         };
+        let decode_by_key = if let Some(field) = flatten_unknown {
+            decode_named_fields_by_key(fields, field, tarantool_crate)
+        } else {
+            quote! {}
+        };
+        let extra_fields = decode_extra_fields(fields_amount, tarantool_crate, true);
         quote! {
+            #decode_by_key
+            #[allow(unused_mut)]
+            let mut consumed: u32 = 0;
             #code
+            #extra_fields
             Ok(Self #enum_variant {
                 #(#field_names: #var_names),*
             })
         }
     }
 
+    /// Generates code which handles the MP_ARRAY elements (or MP_MAP entries
+    /// if `named` is `true`) left after all of the fields are decoded
+    /// according to the `DecodeMode`.
+    ///
+    /// Expects the `len`, `consumed` and `decode_mode` variables to be
+    /// defined, as well as `as_map` if `named` is `true`.
+    fn decode_extra_fields(
+        fields_amount: usize,
+        tarantool_crate: &Path,
+        named: bool,
+    ) -> TokenStream {
+        let as_map = if named {
+            quote! { as_map }
+        } else {
+            quote! { false }
+        };
+        quote! {
+            if consumed < len {
+                let as_map = #as_map;
+                if decode_mode == #tarantool_crate::msgpack::DecodeMode::Strict {
+                    let message = match #tarantool_crate::msgpack::str_bounds(r) {
+                        Ok((start, end)) if as_map => {
+                            format!("unknown field {}", String::from_utf8_lossy(&r[start..end]))
+                        }
+                        _ => format!("too many fields, expected {}, got {}", #fields_amount, len),
+                    };
+                    return Err(#tarantool_crate::msgpack::DecodeError::new::<Self>(message));
+                }
+                let mut cursor = ::std::io::Cursor::new(*r);
+                for _ in consumed..len {
+                    if as_map {
+                        #tarantool_crate::msgpack::skip_value(&mut cursor)
+                            .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part("field name"))?;
+                    }
+                    #tarantool_crate::msgpack::skip_value(&mut cursor)
+                        .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part("extra field"))?;
+                }
+                *r = &r[(cursor.position() as usize)..];
+            }
+        }
+    }
+
+    /// Generates code which decodes an MP_MAP into a struct with a
+    /// `#[decode(flatten_unknown)]` field. Unlike the regular MP_MAP decoding
+    /// the keys may come in any order and the entries with unknown keys are
+    /// collected into the `flatten_unknown` field.
+    ///
+    /// Expects the map length to be stored in the `len` variable.
+    fn decode_named_fields_by_key(
+        fields: &FieldsNamed,
+        flatten_unknown: &Field,
+        tarantool_crate: &Path,
+    ) -> TokenStream {
+        let mut declarations = TokenStream::new();
+        let mut match_arms = TokenStream::new();
+        let mut checks = TokenStream::new();
+        let mut var_names = Vec::with_capacity(fields.named.len());
+        for field in &fields.named {
+            let field_type = &field.ty;
+            let field_ident = field.ident.as_ref().expect("only named fields here");
+            let field_repr = format_ident!("{}", field_ident).to_string();
+            let field_name = proc_macro2::Literal::byte_string(field_repr.as_bytes());
+            let var_name = format_ident!("_field_{}", field_ident);
+
+            if std::ptr::eq(field, flatten_unknown) {
+                declarations.extend(quote_spanned! {field.span()=>
+                    let mut #var_name: #field_type = ::std::default::Default::default();
+                });
+                var_names.push(var_name);
+                continue;
+            }
+
+            let field_attr = unwrap_or_compile_error!(FieldAttr::from_field(field));
+            let decode = match field_attr {
+                Some(FieldAttr::Raw) => quote_spanned! {field.span()=>
+                    #tarantool_crate::msgpack::preserve_read(r)
+                        .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part(format!("field {}", #field_repr)))?
+                },
+                Some(FieldAttr::Map) => {
+                    return syn::Error::new(field.span(), "`as_map` is not currently supported")
+                        .to_compile_error()
+                }
+                Some(FieldAttr::Vec) => {
+                    return syn::Error::new(field.span(), "`as_vec` is not currently supported")
+                        .to_compile_error()
+                }
+                Some(FieldAttr::FlattenUnknown) => unreachable!("checked by flatten_unknown_field"),
+                None => quote_spanned! {field.span()=>
+                    #tarantool_crate::msgpack::Decode::decode(r, context)
+                        .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part(format!("field {}", #field_repr)))?
+                },
+            };
+
+            declarations.extend(quote_spanned! {field.span()=>
+                let mut #var_name: Option<#field_type> = None;
+            });
+            match_arms.extend(quote_spanned! {field.span()=>
+                #field_name => #var_name = Some(#decode),
+            });
+            if field_type.is_option() {
+                checks.extend(quote_spanned! {field.span()=>
+                    let #var_name = #var_name.flatten();
+                });
+            } else {
+                checks.extend(quote_spanned! {field.span()=>
+                    let #var_name = #var_name.ok_or_else(|| {
+                        #tarantool_crate::msgpack::DecodeError::new::<Self>(format!("missing field {}", #field_repr))
+                    })?;
+                });
+            }
+            var_names.push(var_name);
+        }
+
+        let unknown_var_name = format_ident!(
+            "_field_{}",
+            flatten_unknown
+                .ident
+                .as_ref()
+                .expect("only named fields here")
+        );
+        let field_names = fields.named.iter().map(|f| &f.ident);
+        quote! {
+            if as_map {
+                #declarations
+                for _ in 0..len {
+                    let key_len = #tarantool_crate::msgpack::rmp::decode::read_str_len(r)
+                        .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err).with_part("field name"))?;
+                    let key = r.get(0..(key_len as usize))
+                        .ok_or_else(|| #tarantool_crate::msgpack::DecodeError::new::<Self>("not enough data").with_part("field name"))?;
+                    *r = &r[(key_len as usize)..]; // advance
+                    match key {
+                        #match_arms
+                        _ => {
+                            let key = String::from_utf8(key.to_vec())
+                                .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part("field name"))?;
+                            let value = #tarantool_crate::msgpack::Decode::decode(r, context)
+                                .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part(format!("field {}", key)))?;
+                            #unknown_var_name.insert(key, value);
+                        }
+                    }
+                }
+                #checks
+                return Ok(Self {
+                    #(#field_names: #var_names),*
+                });
+            }
+        }
+    }
+
     #[inline]
     fn decode_named_optional_field(
         field: &Field,
@@ -474,29 +775,43 @@ mod msgpack {
             }
         };
 
+        let check_missing = quote_spanned! {field.span()=>
+            else if !as_map && !#allow_array_optionals && decode_mode != #tarantool_crate::msgpack::DecodeMode::Lenient {
+                let message = format!("not enough fields, expected {}, got {} (note: optional fields must be explicitly null unless `allow_array_optionals` attribute is passed)", #fields_amount, len);
+                Err(#tarantool_crate::msgpack::DecodeError::new::<Self>(message))?;
+            }
+        };
+
         // TODO: allow `#[encode(as_map)]` and `#[encode(as_vec)]` for struct fields
         let out = match field_attr {
             Some(FieldAttr::Map) => unimplemented!("`as_map` is not currently supported"),
             Some(FieldAttr::Vec) => unimplemented!("`as_vec` is not currently supported"),
+            Some(FieldAttr::FlattenUnknown) => unreachable!("handled in decode_named_fields"),
             Some(FieldAttr::Raw) => quote_spanned! {field.span()=>
                     let mut #var_name: #field_type = None;
-                    let mut is_none = false;
+                    let mut is_none = consumed >= len;
 
-                    #read_key
                     if !is_none {
-                        #var_name = Some(#tarantool_crate::msgpack::preserve_read(r).expect("only valid msgpack here"));
+                        #read_key
                     }
+                    if !is_none {
+                        consumed += 1;
+                        #var_name = Some(#tarantool_crate::msgpack::preserve_read(r).expect("only valid msgpack here"));
+                    } #check_missing
             },
             None => quote_spanned! {field.span()=>
                 let mut #var_name: #field_type = None;
-                let mut is_none = false;
+                let mut is_none = consumed >= len;
 
-                #read_key
                 if !is_none {
+                    #read_key
+                }
+                if !is_none {
+                    consumed += 1;
                     match #tarantool_crate::msgpack::Decode::decode(r, context) {
                         Ok(val) => #var_name = Some(val),
                         Err(err) => {
-                            let markered = err.source.get(err.source.len() - 33..).unwrap_or("") == "failed to read MessagePack marker";
+                            let markered = err.is_marker_read_error();
                             let nulled = if err.part.is_some() {
                                 err.part.as_ref().expect("Can't fail after a conditional check") == "got Null"
                             } else {
@@ -511,7 +826,7 @@ mod msgpack {
                             }
                         },
                     }
-                }
+                } #check_missing
             },
         };
 
@@ -524,6 +839,7 @@ mod msgpack {
         field: &Field,
         tarantool_crate: &Path,
         names: &mut Vec<Ident>,
+        fields_amount: usize,
     ) -> TokenStream {
         let field_attr = unwrap_or_compile_error!(FieldAttr::from_field(field));
 
@@ -533,6 +849,11 @@ mod msgpack {
         let var_name = format_ident!("_field_{}", field_ident);
 
         let read_key = quote_spanned! {field.span()=>
+            if consumed >= len {
+                let message = format!("missing field {} (expected {} fields, got {})", #field_repr, #fields_amount, len);
+                return Err(#tarantool_crate::msgpack::DecodeError::new::<Self>(message));
+            }
+            consumed += 1;
             if as_map {
                 let len = rmp::decode::read_str_len(r)
                     .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err).with_part("field name"))?;
@@ -583,6 +904,7 @@ mod msgpack {
 
         let mut var_names = Vec::with_capacity(fields.unnamed.len());
         let mut met_option = false;
+        let fields_amount = fields.unnamed.len();
         let code: proc_macro2::TokenStream = fields
             .unnamed
             .iter()
@@ -599,7 +921,7 @@ mod msgpack {
                     )
                     .to_compile_error();
                 } else {
-                    decode_unnamed_required_field(f, i, tarantool_crate, &mut var_names)
+                    decode_unnamed_required_field(f, i, tarantool_crate, &mut var_names, fields_amount)
                 }
             })
             .collect();
@@ -608,8 +930,12 @@ mod msgpack {
         } else {
             quote! {}
         };
+        let extra_fields = decode_extra_fields(fields.unnamed.len(), tarantool_crate, false);
         quote! {
+            #[allow(unused_mut)]
+            let mut consumed: u32 = 0;
             #code
+            #extra_fields
             Ok(Self #enum_variant (
                 #(#var_names),*
             ))
@@ -631,15 +957,25 @@ mod msgpack {
         let out = match field_attr {
             Some(FieldAttr::Map) => unimplemented!("`as_map` is not currently supported"),
             Some(FieldAttr::Vec) => unimplemented!("`as_vec` is not currently supported"),
+            Some(FieldAttr::FlattenUnknown) => {
+                return syn::Error::new(
+                    field.span(),
+                    "`flatten_unknown` is only supported for named fields",
+                )
+                .to_compile_error()
+            }
             Some(FieldAttr::Raw) => quote_spanned! {field.span()=>
+                consumed += 1;
                 let #var_name = #tarantool_crate::msgpack::preserve_read(r).expect("only valid msgpack here");
             },
             None => quote_spanned! {field.span()=>
                 let mut #var_name: #field_type = None;
+                if consumed < len {
+                consumed += 1;
                 match #tarantool_crate::msgpack::Decode::decode(r, context) {
                     Ok(val) => #var_name = Some(val),
                     Err(err) => {
-                        let markered = err.source.get(err.source.len() - 33..).unwrap_or("")== "failed to read MessagePack marker";
+                        let markered = err.is_marker_read_error();
                         let nulled = if err.part.is_some() {
                             err.part.as_ref().expect("Can't fail after a conditional check") == "got Null"
                         } else {
@@ -651,6 +987,7 @@ mod msgpack {
                         }
                     },
                 }
+                }
             },
         };
 
@@ -663,22 +1000,39 @@ mod msgpack {
         index: usize,
         tarantool_crate: &Path,
         names: &mut Vec<Ident>,
+        fields_amount: usize,
     ) -> TokenStream {
         let field_attr = unwrap_or_compile_error!(FieldAttr::from_field(field));
 
         let field_index = Index::from(index);
         let var_name = quote::format_ident!("_field_{}", field_index);
 
+        let check_missing = quote_spanned! {field.span()=>
+            if consumed >= len {
+                let message = format!("missing field {} (expected {} fields, got {})", #index, #fields_amount, len);
+                return Err(#tarantool_crate::msgpack::DecodeError::new::<Self>(message));
+            }
+            consumed += 1;
+        };
+
         let out = if let Some(FieldAttr::Raw) = field_attr {
             quote_spanned! {field.span()=>
+                #check_missing
                 let #var_name = #tarantool_crate::msgpack::preserve_read(r).expect("only valid msgpack here");
             }
         } else if let Some(FieldAttr::Map) = field_attr {
             unimplemented!("`as_map` is not currently supported");
         } else if let Some(FieldAttr::Vec) = field_attr {
             unimplemented!("`as_vec` is not currently supported");
+        } else if let Some(FieldAttr::FlattenUnknown) = field_attr {
+            return syn::Error::new(
+                field.span(),
+                "`flatten_unknown` is only supported for named fields",
+            )
+            .to_compile_error();
         } else {
             quote_spanned! {field.span()=>
+                #check_missing
                 let #var_name = #tarantool_crate::msgpack::Decode::decode(r, context)
                     .map_err(|err| #tarantool_crate::msgpack::DecodeError::new::<Self>(err).with_part(format!("field {}", #index)))?;
             }
@@ -701,73 +1055,74 @@ mod msgpack {
             return decode_untagged(data, tarantool_crate, attrs_span);
         }
 
+        let decode_mode = args.decode_mode(tarantool_crate, &attrs_span);
+
         match *data {
-            Data::Struct(ref data) => {
-                match data.fields {
-                    Fields::Named(ref fields) => {
-                        let first_field_name = fields
-                            .named
-                            .first()
-                            .expect("not a unit struct")
-                            .ident
-                            .as_ref()
-                            .expect("not an unnamed struct")
-                            .to_string();
-                        let fields = decode_named_fields(fields, tarantool_crate, None, args);
-                        quote! {
-                            let as_map = match context.struct_style() {
-                                StructStyle::Default => #as_map,
-                                StructStyle::ForceAsMap => true,
-                                StructStyle::ForceAsArray => false,
-                            };
-                            // TODO: Assert map and array len with number of struct fields
-                            if as_map {
-                                #tarantool_crate::msgpack::rmp::decode::read_map_len(r)
-                                    .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?;
-                            } else {
-                                #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
-                                    .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre_with_field::<Self>(err, #first_field_name))?;
-                            }
-                            #fields
-                        }
+            Data::Struct(ref data) => match data.fields {
+                Fields::Named(ref fields) => {
+                    let first_field_name = fields
+                        .named
+                        .first()
+                        .expect("not a unit struct")
+                        .ident
+                        .as_ref()
+                        .expect("not an unnamed struct")
+                        .to_string();
+                    let fields = decode_named_fields(fields, tarantool_crate, None, args);
+                    quote! {
+                        let as_map = match context.struct_style() {
+                            StructStyle::Default => #as_map,
+                            StructStyle::ForceAsMap => true,
+                            StructStyle::ForceAsArray => false,
+                        };
+                        let decode_mode = #decode_mode;
+                        let len = if as_map {
+                            #tarantool_crate::msgpack::rmp::decode::read_map_len(r)
+                                .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?
+                        } else {
+                            #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
+                                .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre_with_field::<Self>(err, #first_field_name))?
+                        };
+                        #fields
                     }
-                    Fields::Unnamed(ref fields) => {
-                        if as_map {
-                            abort!(
+                }
+                Fields::Unnamed(ref fields) => {
+                    if as_map {
+                        abort!(
                                 attrs_span(),
                                 "`as_map` attribute can be specified only for structs with named fields"
                             );
-                        }
+                    }
 
-                        let mut option_key = TokenStream::new();
-                        if fields.unnamed.len() == 1 {
-                            let first_field = fields.unnamed.first().expect("len is sufficient");
-                            let is_option = first_field.ty.is_option();
-                            if is_option {
-                                option_key = quote! {
-                                    if r.is_empty() {
-                                        return Ok(Self(None));
-                                    }
-                                };
-                            }
+                    let mut option_key = TokenStream::new();
+                    if fields.unnamed.len() == 1 {
+                        let first_field = fields.unnamed.first().expect("len is sufficient");
+                        let is_option = first_field.ty.is_option();
+                        if is_option {
+                            option_key = quote! {
+                                if r.is_empty() {
+                                    return Ok(Self(None));
+                                }
+                            };
                         }
+                    }
 
-                        let fields = decode_unnamed_fields(fields, tarantool_crate, None, args);
-                        quote! {
-                            #option_key
-                            #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
-                                .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?;
-                            #fields
-                        }
+                    let fields = decode_unnamed_fields(fields, tarantool_crate, None, args);
+                    quote! {
+                        #option_key
+                        let decode_mode = #decode_mode;
+                        let len = #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
+                            .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?;
+                        #fields
                     }
-                    Fields::Unit => {
-                        quote! {
-                            let () = #tarantool_crate::msgpack::Decode::decode(r, context)?;
-                            Ok(Self)
-                        }
+                }
+                Fields::Unit => {
+                    quote! {
+                        let () = #tarantool_crate::msgpack::Decode::decode(r, context)?;
+                        Ok(Self)
                     }
                 }
-            }
+            },
             Data::Enum(ref variants) => {
                 if as_map {
                     abort!(
@@ -791,7 +1146,7 @@ mod msgpack {
                                 // TODO: allow `#[encode(as_map)]` for struct variants
                                 quote! {
                                     #variant_repr => {
-                                        #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
+                                        let len = #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
                                             .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?;
                                         let as_map = false;
                                         #fields
@@ -802,7 +1157,7 @@ mod msgpack {
                                 let fields = decode_unnamed_fields(fields, tarantool_crate, Some(&variant.ident), args);
                                 quote! {
                                     #variant_repr => {
-                                        #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
+                                        let len = #tarantool_crate::msgpack::rmp::decode::read_array_len(r)
                                             .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?;
                                         let as_map = false;
                                         #fields
@@ -822,6 +1177,8 @@ mod msgpack {
                     })
                     .collect();
                 quote! {
+                    #[allow(unused_variables)]
+                    let decode_mode = #decode_mode;
                     // TODO: assert map len 1
                     #tarantool_crate::msgpack::rmp::decode::read_map_len(r)
                         .map_err(|err| #tarantool_crate::msgpack::DecodeError::from_vre::<Self>(err))?;
@@ -1024,7 +1381,7 @@ fn collect_lifetimes(generics: &syn::Generics) -> Punctuated<syn::Lifetime, Toke
 ///
 /// For more information see `tarantool::msgpack::Encode`
 #[proc_macro_error]
-#[proc_macro_derive(Encode, attributes(encode))]
+#[proc_macro_derive(Encode, attributes(encode, decode))]
 pub fn derive_encode(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
@@ -1073,7 +1430,7 @@ pub fn derive_encode(input: TokenStream) -> TokenStream {
 ///
 /// For more information see `tarantool::msgpack::Decode`
 #[proc_macro_error]
-#[proc_macro_derive(Decode, attributes(encode))]
+#[proc_macro_derive(Decode, attributes(encode, decode))]
 pub fn derive_decode(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
@@ -1163,6 +1520,7 @@ pub fn stored_proc(attr: TokenStream, item: TokenStream) -> TokenStream {
         input_idents,
         inject_inputs,
         n_actual_arguments,
+        last_input_type,
     } = Inputs::parse(&ctx, inputs);
 
     if ctx.is_packed && n_actual_arguments > 1 {
@@ -1175,9 +1533,40 @@ pub fn stored_proc(attr: TokenStream, item: TokenStream) -> TokenStream {
         section,
         debug_tuple,
         wrap_ret,
+        is_packed,
+        is_msgpack,
         ..
     } = ctx;
 
+    // If the last parameter is a `RawArgsTail`, the arguments are decoded
+    // with `DecodeArgsWithRawTail`, see `ArgsDecoder` for details.
+    let decode_args = match last_input_type {
+        Some(ty) if !is_packed && !is_msgpack => quote! {
+            {
+                use #tarantool::proc::{DecodeArgs as _, DecodeArgsWithRawTail as _};
+                (&&#tarantool::proc::ArgsDecoder::<#ty>(::std::marker::PhantomData))
+                    .decode_args(&__tp_args)
+            }
+        },
+        _ if is_msgpack => quote! { __tp_args.decode_msgpack() },
+        _ => quote! { __tp_args.decode() },
+    };
+
+    let decode_inputs = quote! {
+        let #input_pattern =
+            match #decode_args {
+                ::std::result::Result::Ok(__tp_args) => __tp_args,
+                ::std::result::Result::Err(__tp_err) => {
+                    #tarantool::set_error!(
+                        #tarantool::error::TarantoolErrorCode::ProcC,
+                        "{}",
+                        __tp_err
+                    );
+                    return -1;
+                }
+            };
+    };
+
     let inner_fn_name = syn::Ident::new("__tp_inner", ident.span());
     let desc_name = ident.to_string();
     let desc_ident = syn::Ident::new(&desc_name.to_uppercase(), ident.span());
@@ -1202,18 +1591,7 @@ pub fn stored_proc(attr: TokenStream, item: TokenStream) -> TokenStream {
             __tp_args: #tarantool::tuple::FunctionArgs,
         ) -> ::std::os::raw::c_int {
             #debug_tuple
-            let #input_pattern =
-                match __tp_args.decode() {
-                    ::std::result::Result::Ok(__tp_args) => __tp_args,
-                    ::std::result::Result::Err(__tp_err) => {
-                        #tarantool::set_error!(
-                            #tarantool::error::TarantoolErrorCode::ProcC,
-                            "{}",
-                            __tp_err
-                        );
-                        return -1;
-                    }
-                };
+            #decode_inputs
 
             #inject_inputs
 
@@ -1237,6 +1615,7 @@ struct Context {
     linkme: syn::Path,
     debug_tuple: TokenStream2,
     is_packed: bool,
+    is_msgpack: bool,
     public: Option<bool>,
     wrap_ret: TokenStream2,
 }
@@ -1248,6 +1627,7 @@ impl Context {
         let mut section = None;
         let mut debug_tuple_needed = false;
         let mut is_packed = false;
+        let mut is_msgpack = false;
         let mut public = None;
         let mut wrap_ret = quote! {};
 
@@ -1265,15 +1645,31 @@ impl Context {
                 continue;
             }
             if imp::is_path_eq_to(&arg, "custom_ret") {
+                if !wrap_ret.is_empty() {
+                    panic!("`custom_ret` and `rows` can't be used together")
+                }
                 wrap_ret = quote! {
                     let __tp_res = #tarantool::proc::ReturnMsgpack(__tp_res);
                 };
                 continue;
             }
+            if imp::is_path_eq_to(&arg, "rows") {
+                if !wrap_ret.is_empty() {
+                    panic!("`custom_ret` and `rows` can't be used together")
+                }
+                wrap_ret = quote! {
+                    let __tp_res = #tarantool::proc::ReturnRows(__tp_res);
+                };
+                continue;
+            }
             if imp::is_path_eq_to(&arg, "packed_args") {
                 is_packed = true;
                 continue;
             }
+            if imp::is_path_eq_to(&arg, "msgpack_args") {
+                is_msgpack = true;
+                continue;
+            }
             if imp::is_path_eq_to(&arg, "debug") {
                 debug_tuple_needed = true;
                 continue;
@@ -1303,6 +1699,7 @@ impl Context {
             section,
             debug_tuple,
             is_packed,
+            is_msgpack,
             wrap_ret,
             public,
         }
@@ -1315,6 +1712,9 @@ struct Inputs {
     input_idents: Vec<syn::Pat>,
     inject_inputs: TokenStream2,
     n_actual_arguments: usize,
+    /// Type of the last parameter which isn't injected, with the named
+    /// lifetimes replaced by `'_`.
+    last_input_type: Option<syn::Type>,
 }
 
 impl Inputs {
@@ -1323,10 +1723,12 @@ impl Inputs {
         let mut actual_inputs = vec![];
         let mut injected_inputs = vec![];
         let mut injected_exprs = vec![];
+        let mut last_input_type = None;
         for i in &mut inputs {
             let syn::PatType {
                 ref pat,
                 ref mut attrs,
+                ref ty,
                 ..
             } = match i {
                 FnArg::Receiver(_) => {
@@ -1355,6 +1757,9 @@ impl Inputs {
                 injected_exprs.push(expr);
             } else {
                 actual_inputs.push(pat.clone());
+                let mut ty = (**ty).clone();
+                elide_lifetimes(&mut ty);
+                last_input_type = Some(ty);
             }
             input_idents.push((**pat).clone());
         }
@@ -1377,7 +1782,75 @@ impl Inputs {
             input_idents,
             inject_inputs,
             n_actual_arguments: actual_inputs.len(),
+            last_input_type,
+        }
+    }
+}
+
+/// Replaces the named lifetimes (except for `'static`) in `ty` with `'_`, so
+/// that the type of a parameter can be named outside of the function, which
+/// declares them.
+fn elide_lifetimes(ty: &mut syn::Type) {
+    fn elide(lifetime: &mut syn::Lifetime) {
+        if lifetime.ident != "static" {
+            *lifetime = syn::Lifetime::new("'_", lifetime.span());
+        }
+    }
+
+    fn elide_in_path(path: &mut syn::Path) {
+        for segment in &mut path.segments {
+            match &mut segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => {
+                    for arg in &mut args.args {
+                        match arg {
+                            syn::GenericArgument::Lifetime(lifetime) => elide(lifetime),
+                            syn::GenericArgument::Type(ty) => elide_lifetimes(ty),
+                            syn::GenericArgument::Binding(binding) => {
+                                elide_lifetimes(&mut binding.ty)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                syn::PathArguments::Parenthesized(args) => {
+                    args.inputs.iter_mut().for_each(elide_lifetimes);
+                    if let syn::ReturnType::Type(_, ty) = &mut args.output {
+                        elide_lifetimes(ty)
+                    }
+                }
+                syn::PathArguments::None => {}
+            }
+        }
+    }
+
+    match ty {
+        syn::Type::Reference(r) => {
+            if let Some(lifetime) = &mut r.lifetime {
+                elide(lifetime)
+            }
+            elide_lifetimes(&mut r.elem)
+        }
+        syn::Type::Path(p) => {
+            if let Some(qself) = &mut p.qself {
+                elide_lifetimes(&mut qself.ty)
+            }
+            elide_in_path(&mut p.path)
+        }
+        syn::Type::TraitObject(t) => {
+            for bound in &mut t.bounds {
+                match bound {
+                    syn::TypeParamBound::Lifetime(lifetime) => elide(lifetime),
+                    syn::TypeParamBound::Trait(t) => elide_in_path(&mut t.path),
+                }
+            }
         }
+        syn::Type::Tuple(t) => t.elems.iter_mut().for_each(elide_lifetimes),
+        syn::Type::Slice(s) => elide_lifetimes(&mut s.elem),
+        syn::Type::Array(a) => elide_lifetimes(&mut a.elem),
+        syn::Type::Ptr(p) => elide_lifetimes(&mut p.elem),
+        syn::Type::Paren(p) => elide_lifetimes(&mut p.elem),
+        syn::Type::Group(g) => elide_lifetimes(&mut g.elem),
+        _ => {}
     }
 }
 
@@ -0,0 +1,1092 @@
+//! Syntax checker for the lua chunks embedded via the `lua!` macro.
+//!
+//! Follows the grammar of LuaJIT 2.1 (lua 5.1 with `goto` and labels from lua
+//! 5.2 and the LuaJIT specific number literals). Only the syntax is checked,
+//! the errors which LuaJIT reports at runtime (e.g. too many local variables)
+//! are not detected.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use quote::quote;
+
+use crate::default_tarantool_crate_path;
+
+pub fn impl_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (tarantool, code) = match parse_args(input.into()) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    if let Err(e) = check(&code.value()) {
+        return syn::Error::new(code.span(), e).to_compile_error().into();
+    }
+
+    quote! {
+        {
+            static CHUNK: #tarantool::lua::Chunk = #tarantool::lua::Chunk::new(
+                ::std::concat!("lua!(", ::std::file!(), ":", ::std::line!(), ")"),
+                #code,
+            );
+            &CHUNK
+        }
+    }
+    .into()
+}
+
+/// Parses `[tarantool = "path",] "code"`.
+fn parse_args(tokens: proc_macro2::TokenStream) -> Result<(syn::Path, syn::LitStr), syn::Error> {
+    syn::parse::Parser::parse2(
+        |input: syn::parse::ParseStream| {
+            let mut tarantool = default_tarantool_crate_path();
+            if input.peek(syn::Ident) {
+                let ident: syn::Ident = input.parse()?;
+                if ident != "tarantool" {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("unknown argument `{ident}`, expected `tarantool`"),
+                    ));
+                }
+                input.parse::<syn::Token![=]>()?;
+                let value: syn::LitStr = input.parse()?;
+                tarantool = value.parse()?;
+                input.parse::<syn::Token![,]>()?;
+            }
+            let code: syn::LitStr = input.parse()?;
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+            Ok((tarantool, code))
+        },
+        tokens,
+    )
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "lua syntax error at line {}: {}",
+            self.line, self.message
+        )
+    }
+}
+
+/// Checks that `code` is a syntactically valid lua chunk.
+pub fn check(code: &str) -> Result<(), SyntaxError> {
+    let tokens = Lexer::new(code).tokenize()?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        functions: vec![],
+    };
+    parser.chunk()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lexer
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Name(String),
+    /// Keywords and operators.
+    Sym(&'static str),
+    Number,
+    String,
+    Eof,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    tok: Tok,
+    line: usize,
+    /// The text of the token for the error messages.
+    text: String,
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Longest ones first, so that the first match is the right one.
+const OPERATORS: &[&str] = &[
+    "...", "..", "==", "~=", "<=", ">=", "::", "+", "-", "*", "/", "%", "^", "#", "<", ">", "=",
+    "(", ")", "{", "}", "[", "]", ";", ":", ",", ".",
+];
+
+struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(code: &'a str) -> Self {
+        let mut src = code.as_bytes();
+        // A shebang line is skipped by `loadstring` too.
+        if src.starts_with(b"#") {
+            let end = src.iter().position(|&c| c == b'\n').unwrap_or(src.len());
+            src = &src[end..];
+        }
+        Self {
+            src,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn peek(&self, offset: usize) -> u8 {
+        self.src.get(self.pos + offset).copied().unwrap_or(0)
+    }
+
+    fn error(&self, message: impl Into<String>) -> SyntaxError {
+        SyntaxError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn text(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, SyntaxError> {
+        let mut tokens = vec![];
+        loop {
+            self.skip_whitespace_and_comments()?;
+            let line = self.line;
+            let start = self.pos;
+            let tok = match self.peek(0) {
+                0 if self.pos >= self.src.len() => {
+                    tokens.push(Token {
+                        tok: Tok::Eof,
+                        line,
+                        text: "<eof>".into(),
+                    });
+                    return Ok(tokens);
+                }
+                c if c == b'_' || c.is_ascii_alphabetic() => {
+                    while self.peek(0) == b'_' || self.peek(0).is_ascii_alphanumeric() {
+                        self.pos += 1;
+                    }
+                    let name = self.text(start);
+                    match KEYWORDS.iter().find(|&&k| k == name) {
+                        Some(keyword) => Tok::Sym(keyword),
+                        None => Tok::Name(name),
+                    }
+                }
+                c if c.is_ascii_digit() || (c == b'.' && self.peek(1).is_ascii_digit()) => {
+                    self.number()?;
+                    Tok::Number
+                }
+                b'"' | b'\'' => {
+                    self.string()?;
+                    Tok::String
+                }
+                b'[' if matches!(self.peek(1), b'[' | b'=') => {
+                    if let Some(level) = self.long_bracket_level() {
+                        self.long_string(level, "string")?;
+                        Tok::String
+                    } else {
+                        // `[=` is not a long string, e.g. `t[=`, which is an
+                        // error reported by the parser.
+                        self.pos += 1;
+                        Tok::Sym("[")
+                    }
+                }
+                c if c >= 0x80 => return Err(self.error("unexpected symbol")),
+                _ => {
+                    let rest = &self.src[self.pos..];
+                    match OPERATORS.iter().find(|op| rest.starts_with(op.as_bytes())) {
+                        Some(op) => {
+                            self.pos += op.len();
+                            Tok::Sym(op)
+                        }
+                        None => {
+                            let c = self.peek(0) as char;
+                            return Err(self.error(format!("unexpected symbol near '{}'", c)));
+                        }
+                    }
+                }
+            };
+            tokens.push(Token {
+                tok,
+                line,
+                text: self.text(start),
+            });
+        }
+    }
+
+    fn newline(&mut self) {
+        let c = self.peek(0);
+        self.pos += 1;
+        // `\r\n` and `\n\r` are a single line break.
+        let next = self.peek(0);
+        if (next == b'\n' || next == b'\r') && next != c {
+            self.pos += 1;
+        }
+        self.line += 1;
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), SyntaxError> {
+        loop {
+            match self.peek(0) {
+                b'\n' | b'\r' => self.newline(),
+                b' ' | b'\t' | 0x0b | 0x0c => self.pos += 1,
+                b'-' if self.peek(1) == b'-' => {
+                    self.pos += 2;
+                    if self.peek(0) == b'[' {
+                        if let Some(level) = self.long_bracket_level() {
+                            self.long_string(level, "comment")?;
+                            continue;
+                        }
+                    }
+                    while !matches!(self.peek(0), b'\n' | b'\r') && self.pos < self.src.len() {
+                        self.pos += 1;
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// If the lexer is at `[[` or `[==[`, returns the number of `=`.
+    fn long_bracket_level(&self) -> Option<usize> {
+        let level = (1..).take_while(|&i| self.peek(i) == b'=').count();
+        if self.peek(level + 1) == b'[' {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    fn long_string(&mut self, level: usize, what: &str) -> Result<(), SyntaxError> {
+        let start_line = self.line;
+        self.pos += level + 2;
+        loop {
+            match self.peek(0) {
+                0 if self.pos >= self.src.len() => {
+                    return Err(SyntaxError {
+                        line: start_line,
+                        message: format!("unfinished long {} near '<eof>'", what),
+                    });
+                }
+                b'\n' | b'\r' => self.newline(),
+                b']' if (1..=level).all(|i| self.peek(i) == b'=')
+                    && self.peek(level + 1) == b']' =>
+                {
+                    self.pos += level + 2;
+                    return Ok(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+        let quote = self.peek(0);
+        self.pos += 1;
+        loop {
+            match self.peek(0) {
+                c if c == quote => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                b'\n' | b'\r' => {
+                    return Err(
+                        self.error(format!("unfinished string near '{}'", self.text(start)))
+                    );
+                }
+                0 if self.pos >= self.src.len() => {
+                    return Err(self.error("unfinished string near '<eof>'"));
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    self.escape_sequence()?;
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn escape_sequence(&mut self) -> Result<(), SyntaxError> {
+        let invalid = |lexer: &Self| lexer.error("invalid escape sequence");
+        match self.peek(0) {
+            b'a' | b'b' | b'f' | b'n' | b'r' | b't' | b'v' | b'\\' | b'"' | b'\'' => self.pos += 1,
+            b'\n' | b'\r' => self.newline(),
+            b'x' => {
+                if !(self.peek(1).is_ascii_hexdigit() && self.peek(2).is_ascii_hexdigit()) {
+                    return Err(invalid(self));
+                }
+                self.pos += 3;
+            }
+            b'z' => {
+                self.pos += 1;
+                loop {
+                    match self.peek(0) {
+                        b'\n' | b'\r' => self.newline(),
+                        b' ' | b'\t' | 0x0b | 0x0c => self.pos += 1,
+                        _ => break,
+                    }
+                }
+            }
+            b'u' => {
+                if self.peek(1) != b'{' {
+                    return Err(invalid(self));
+                }
+                self.pos += 2;
+                let mut value: u32 = 0;
+                let mut digits = 0;
+                while self.peek(0).is_ascii_hexdigit() {
+                    let digit = (self.peek(0) as char).to_digit(16).unwrap();
+                    value = value.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                    self.pos += 1;
+                }
+                if digits == 0 || self.peek(0) != b'}' || value >= 0x110000 {
+                    return Err(invalid(self));
+                }
+                self.pos += 1;
+            }
+            c if c.is_ascii_digit() => {
+                let mut value = 0;
+                for _ in 0..3 {
+                    if !self.peek(0).is_ascii_digit() {
+                        break;
+                    }
+                    value = value * 10 + (self.peek(0) - b'0') as u32;
+                    self.pos += 1;
+                }
+                if value > 255 {
+                    return Err(invalid(self));
+                }
+            }
+            _ => return Err(invalid(self)),
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+        // Same as in LuaJIT: consume everything which may be a part of a
+        // number and then check the whole thing.
+        loop {
+            let c = self.peek(0);
+            if c.is_ascii_alphanumeric() || c == b'.' || c == b'_' {
+                self.pos += 1;
+                let is_exponent =
+                    if self.text(start).starts_with("0x") || self.text(start).starts_with("0X") {
+                        matches!(c, b'p' | b'P')
+                    } else {
+                        matches!(c, b'e' | b'E')
+                    };
+                if is_exponent && matches!(self.peek(0), b'+' | b'-') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let text = self.text(start);
+        if !is_valid_number(&text) {
+            return Err(self.error(format!("malformed number near '{}'", text)));
+        }
+        Ok(())
+    }
+}
+
+fn is_valid_number(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    let (body, is_integer_suffix) = if let Some(body) = lower.strip_suffix("ull") {
+        (body, true)
+    } else if let Some(body) = lower.strip_suffix("ll") {
+        (body, true)
+    } else if let Some(body) = lower.strip_suffix('i') {
+        (body, false)
+    } else {
+        (lower.as_str(), false)
+    };
+
+    let (digits, exponent_char, is_digit): (&str, char, fn(char) -> bool) =
+        match body.strip_prefix("0x") {
+            Some(hex) => (hex, 'p', |c| c.is_ascii_hexdigit()),
+            None => (body, 'e', |c| c.is_ascii_digit()),
+        };
+    let (mantissa, exponent) = match digits.split_once(exponent_char) {
+        Some((m, e)) => (m, Some(e)),
+        None => (digits, None),
+    };
+    let (int_part, frac_part) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+    if int_part.is_empty() && frac_part.map_or(true, str::is_empty) {
+        return false;
+    }
+    if !int_part.chars().all(is_digit) || !frac_part.unwrap_or("").chars().all(is_digit) {
+        return false;
+    }
+    if let Some(exponent) = exponent {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if exponent.is_empty() || !exponent.chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+    }
+    // The 64-bit integer literals can't have a fraction or an exponent.
+    !(is_integer_suffix && (frac_part.is_some() || exponent.is_some()))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Parser
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Block {
+    labels: HashSet<String>,
+    /// Gotos of this block and of the nested ones which aren't resolved yet.
+    gotos: Vec<(String, usize)>,
+    is_loop: bool,
+}
+
+struct Function {
+    is_vararg: bool,
+    blocks: Vec<Block>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    functions: Vec<Function>,
+}
+
+/// Priorities of the binary operators (left, right) as in the lua parser.
+fn binary_priority(tok: &Tok) -> Option<(u8, u8)> {
+    let op = match tok {
+        Tok::Sym(op) => op,
+        _ => return None,
+    };
+    let priority = match *op {
+        "or" => (1, 1),
+        "and" => (2, 2),
+        "<" | ">" | "<=" | ">=" | "~=" | "==" => (3, 3),
+        ".." => (5, 4),
+        "+" | "-" => (6, 6),
+        "*" | "/" | "%" => (7, 7),
+        "^" => (10, 9),
+        _ => return None,
+    };
+    Some(priority)
+}
+
+const UNARY_PRIORITY: u8 = 8;
+
+/// What kind of expression was parsed, to check the assignments.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExprKind {
+    /// A variable or a table field.
+    Var,
+    Call,
+    Other,
+}
+
+impl Parser {
+    fn current(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn is(&self, sym: &str) -> bool {
+        matches!(&self.current().tok, Tok::Sym(s) if *s == sym)
+    }
+
+    fn is_at(&self, offset: usize, sym: &str) -> bool {
+        match self.tokens.get(self.pos + offset) {
+            Some(Token {
+                tok: Tok::Sym(s), ..
+            }) => *s == sym,
+            _ => false,
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+    }
+
+    fn accept(&mut self, sym: &str) -> bool {
+        if self.is(sym) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error_near(&self, message: impl fmt::Display) -> SyntaxError {
+        let token = self.current();
+        SyntaxError {
+            line: token.line,
+            message: format!("{} near '{}'", message, token.text),
+        }
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), SyntaxError> {
+        if !self.accept(sym) {
+            return Err(self.error_near(format_args!("'{}' expected", sym)));
+        }
+        Ok(())
+    }
+
+    /// Expects the closing `what` of the construct started with `who` at
+    /// line `line`.
+    fn expect_match(&mut self, what: &str, who: &str, line: usize) -> Result<(), SyntaxError> {
+        if self.accept(what) {
+            return Ok(());
+        }
+        if line == self.current().line {
+            Err(self.error_near(format_args!("'{}' expected", what)))
+        } else {
+            Err(self.error_near(format_args!(
+                "'{}' expected (to close '{}' at line {})",
+                what, who, line
+            )))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, SyntaxError> {
+        match &self.current().tok {
+            Tok::Name(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.error_near("<name> expected")),
+        }
+    }
+
+    fn chunk(&mut self) -> Result<(), SyntaxError> {
+        self.open_function(true);
+        self.block(false)?;
+        if self.current().tok != Tok::Eof {
+            return Err(self.error_near("'<eof>' expected"));
+        }
+        self.close_function()
+    }
+
+    fn open_function(&mut self, is_vararg: bool) {
+        self.functions.push(Function {
+            is_vararg,
+            blocks: vec![],
+        });
+    }
+
+    fn close_function(&mut self) -> Result<(), SyntaxError> {
+        self.functions.pop().expect("function is open");
+        Ok(())
+    }
+
+    fn function(&mut self) -> &mut Function {
+        self.functions.last_mut().expect("function is open")
+    }
+
+    fn is_block_end(&self) -> bool {
+        self.current().tok == Tok::Eof
+            || ["else", "elseif", "end", "until"]
+                .iter()
+                .any(|sym| self.is(sym))
+    }
+
+    /// Parses a block until one of the block ending keywords. Resolves the
+    /// gotos to the labels of the block, the unresolved ones are passed to
+    /// the enclosing block or reported as errors.
+    fn block(&mut self, is_loop: bool) -> Result<(), SyntaxError> {
+        self.function().blocks.push(Block {
+            is_loop,
+            ..Default::default()
+        });
+        while !self.is_block_end() {
+            if self.is("return") {
+                self.return_statement()?;
+                break;
+            }
+            self.statement()?;
+        }
+        let block = self.function().blocks.pop().expect("block is open");
+        let unresolved = block
+            .gotos
+            .into_iter()
+            .filter(|(label, _)| !block.labels.contains(label));
+        match self.function().blocks.last_mut() {
+            Some(outer) => outer.gotos.extend(unresolved),
+            None => {
+                if let Some((label, line)) = unresolved.into_iter().next() {
+                    return Err(SyntaxError {
+                        line,
+                        message: format!("undefined label '{}'", label),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn return_statement(&mut self) -> Result<(), SyntaxError> {
+        self.advance();
+        if !self.is_block_end() && !self.is(";") {
+            self.expression_list()?;
+        }
+        self.accept(";");
+        if !self.is_block_end() {
+            return Err(self.error_near("'<eof>' expected"));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), SyntaxError> {
+        let line = self.current().line;
+        let sym = match self.current().tok {
+            Tok::Sym(sym) => sym,
+            _ => return self.expression_statement(),
+        };
+        match sym {
+            ";" => self.advance(),
+            "if" => {
+                self.advance();
+                self.condition_then()?;
+                while self.is("elseif") {
+                    self.advance();
+                    self.condition_then()?;
+                }
+                if self.accept("else") {
+                    self.block(false)?;
+                }
+                self.expect_match("end", "if", line)?;
+            }
+            "while" => {
+                self.advance();
+                self.expression()?;
+                self.expect("do")?;
+                self.block(true)?;
+                self.expect_match("end", "while", line)?;
+            }
+            "do" => {
+                self.advance();
+                self.block(false)?;
+                self.expect_match("end", "do", line)?;
+            }
+            "for" => {
+                self.advance();
+                self.name()?;
+                if self.accept("=") {
+                    self.expression()?;
+                    self.expect(",")?;
+                    self.expression()?;
+                    if self.accept(",") {
+                        self.expression()?;
+                    }
+                } else if self.is(",") || self.is("in") {
+                    while self.accept(",") {
+                        self.name()?;
+                    }
+                    self.expect("in")?;
+                    self.expression_list()?;
+                } else {
+                    return Err(self.error_near("'=' or 'in' expected"));
+                }
+                self.expect("do")?;
+                self.block(true)?;
+                self.expect_match("end", "for", line)?;
+            }
+            "repeat" => {
+                self.advance();
+                self.block(true)?;
+                self.expect_match("until", "repeat", line)?;
+                self.expression()?;
+            }
+            "function" => {
+                self.advance();
+                self.name()?;
+                while self.accept(".") {
+                    self.name()?;
+                }
+                let is_method = self.accept(":");
+                if is_method {
+                    self.name()?;
+                }
+                self.function_body(line)?;
+            }
+            "local" => {
+                self.advance();
+                if self.accept("function") {
+                    self.name()?;
+                    self.function_body(line)?;
+                } else {
+                    self.name()?;
+                    while self.accept(",") {
+                        self.name()?;
+                    }
+                    if self.accept("=") {
+                        self.expression_list()?;
+                    }
+                }
+            }
+            "::" => {
+                self.advance();
+                let label = self.name()?;
+                self.expect("::")?;
+                let function = self.function();
+                let is_duplicate = function.blocks.iter().any(|b| b.labels.contains(&label));
+                if is_duplicate {
+                    return Err(SyntaxError {
+                        line,
+                        message: format!("duplicate label '{}'", label),
+                    });
+                }
+                let block = function.blocks.last_mut().expect("block is open");
+                block.labels.insert(label);
+            }
+            "goto" => {
+                self.advance();
+                let label = self.name()?;
+                let block = self.function().blocks.last_mut().expect("block is open");
+                block.gotos.push((label, line));
+            }
+            "break" => {
+                if !self.function().blocks.iter().any(|b| b.is_loop) {
+                    return Err(self.error_near("no loop to break"));
+                }
+                self.advance();
+            }
+            _ => return self.expression_statement(),
+        }
+        Ok(())
+    }
+
+    fn condition_then(&mut self) -> Result<(), SyntaxError> {
+        self.expression()?;
+        self.expect("then")?;
+        self.block(false)
+    }
+
+    /// A function call or an assignment.
+    fn expression_statement(&mut self) -> Result<(), SyntaxError> {
+        let kind = self.suffixed_expression()?;
+        if self.is("=") || self.is(",") {
+            if kind != ExprKind::Var {
+                return Err(self.error_near("syntax error"));
+            }
+            while self.accept(",") {
+                if self.suffixed_expression()? != ExprKind::Var {
+                    return Err(self.error_near("syntax error"));
+                }
+            }
+            self.expect("=")?;
+            self.expression_list()?;
+        } else if kind != ExprKind::Call {
+            return Err(self.error_near("syntax error"));
+        }
+        Ok(())
+    }
+
+    fn function_body(&mut self, line: usize) -> Result<(), SyntaxError> {
+        self.expect("(")?;
+        let mut is_vararg = false;
+        if !self.is(")") {
+            loop {
+                if self.accept("...") {
+                    is_vararg = true;
+                    break;
+                }
+                self.name()?;
+                if !self.accept(",") {
+                    break;
+                }
+            }
+        }
+        self.expect(")")?;
+        self.open_function(is_vararg);
+        self.block(false)?;
+        self.expect_match("end", "function", line)?;
+        self.close_function()
+    }
+
+    fn expression_list(&mut self) -> Result<(), SyntaxError> {
+        self.expression()?;
+        while self.accept(",") {
+            self.expression()?;
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<ExprKind, SyntaxError> {
+        self.subexpression(0)
+    }
+
+    /// Parses an expression whose binary operators have a higher priority
+    /// than `limit`.
+    fn subexpression(&mut self, limit: u8) -> Result<ExprKind, SyntaxError> {
+        let mut kind = if self.is("not") || self.is("-") || self.is("#") {
+            self.advance();
+            self.subexpression(UNARY_PRIORITY)?;
+            ExprKind::Other
+        } else {
+            self.simple_expression()?
+        };
+        while let Some((left, right)) = binary_priority(&self.current().tok) {
+            if left <= limit {
+                break;
+            }
+            self.advance();
+            self.subexpression(right)?;
+            kind = ExprKind::Other;
+        }
+        Ok(kind)
+    }
+
+    fn simple_expression(&mut self) -> Result<ExprKind, SyntaxError> {
+        let line = self.current().line;
+        match self.current().tok {
+            Tok::Number | Tok::String => self.advance(),
+            Tok::Sym("nil" | "true" | "false") => self.advance(),
+            Tok::Sym("...") => {
+                if !self.function().is_vararg {
+                    return Err(self.error_near("cannot use '...' outside a vararg function"));
+                }
+                self.advance();
+            }
+            Tok::Sym("{") => self.table_constructor()?,
+            Tok::Sym("function") => {
+                self.advance();
+                self.function_body(line)?;
+            }
+            _ => return self.suffixed_expression(),
+        }
+        Ok(ExprKind::Other)
+    }
+
+    fn primary_expression(&mut self) -> Result<ExprKind, SyntaxError> {
+        match self.current().tok {
+            Tok::Name(_) => {
+                self.advance();
+                Ok(ExprKind::Var)
+            }
+            Tok::Sym("(") => {
+                let line = self.current().line;
+                self.advance();
+                self.expression()?;
+                self.expect_match(")", "(", line)?;
+                Ok(ExprKind::Other)
+            }
+            _ => Err(self.error_near("unexpected symbol")),
+        }
+    }
+
+    fn suffixed_expression(&mut self) -> Result<ExprKind, SyntaxError> {
+        let mut kind = self.primary_expression()?;
+        loop {
+            match self.current().tok {
+                Tok::Sym(".") => {
+                    self.advance();
+                    self.name()?;
+                    kind = ExprKind::Var;
+                }
+                Tok::Sym("[") => {
+                    self.advance();
+                    self.expression()?;
+                    self.expect("]")?;
+                    kind = ExprKind::Var;
+                }
+                Tok::Sym(":") => {
+                    self.advance();
+                    self.name()?;
+                    self.call_arguments()?;
+                    kind = ExprKind::Call;
+                }
+                Tok::Sym("(" | "{") | Tok::String => {
+                    self.call_arguments()?;
+                    kind = ExprKind::Call;
+                }
+                _ => return Ok(kind),
+            }
+        }
+    }
+
+    fn call_arguments(&mut self) -> Result<(), SyntaxError> {
+        match self.current().tok {
+            Tok::String => self.advance(),
+            Tok::Sym("{") => self.table_constructor()?,
+            Tok::Sym("(") => {
+                let line = self.current().line;
+                if let Some(prev) = self.pos.checked_sub(1).map(|i| &self.tokens[i]) {
+                    if prev.line != line {
+                        return Err(
+                            self.error_near("ambiguous syntax (function call x new statement)")
+                        );
+                    }
+                }
+                self.advance();
+                if !self.is(")") {
+                    self.expression_list()?;
+                }
+                self.expect_match(")", "(", line)?;
+            }
+            _ => return Err(self.error_near("function arguments expected")),
+        }
+        Ok(())
+    }
+
+    fn table_constructor(&mut self) -> Result<(), SyntaxError> {
+        let line = self.current().line;
+        self.expect("{")?;
+        while !self.is("}") {
+            if self.is("[") {
+                self.advance();
+                self.expression()?;
+                self.expect("]")?;
+                self.expect("=")?;
+                self.expression()?;
+            } else if matches!(self.current().tok, Tok::Name(_)) && self.is_at(1, "=") {
+                self.advance();
+                self.advance();
+                self.expression()?;
+            } else {
+                self.expression()?;
+            }
+            if !self.accept(",") && !self.accept(";") {
+                break;
+            }
+        }
+        self.expect_match("}", "{", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn error(code: &str) -> String {
+        check(code).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn valid() {
+        let code = r#"
+            local a, b = ...
+            local t = {1, 2; x = 3, ['y'] = 4, [5] = {}, }
+            t.x, t['y'] = a + b * 2 ^ -3 ^ 2, #t .. "s\n\x41\65\u{48}\z
+                          tring" .. 'str' .. [[long
+            string]] .. [==[ ]] ]==]
+            --[[ long
+            comment ]] -- short comment
+            local function f(x, ...)
+                return select('#', ...), x
+            end
+            function t.a.b:c(...) return ... end
+            for i = 1, 10, 2 do if i > 5 then break elseif i then else end end
+            for k, v in pairs(t) do
+                goto continue
+                print(k, v)
+                ::continue::
+            end
+            while not a and b or a ~= b do a = nil end
+            repeat local x = 0x1F + 1e10 + .5 + 3. + 0x1p-2 + 1ULL + 2ll + 3i until true;
+            do f{} f"" f[[]] t:m() (f)() end
+            local x = function() return end
+            return (f(1)), x
+        "#;
+        check(code).unwrap();
+        check("").unwrap();
+        check("#!/usr/bin/env tarantool\nreturn 1").unwrap();
+        check("return").unwrap();
+        check("return;").unwrap();
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            error("local x = "),
+            "lua syntax error at line 1: unexpected symbol near '<eof>'"
+        );
+        assert_eq!(
+            error("if x then\n\nreturn 1"),
+            "lua syntax error at line 3: 'end' expected (to close 'if' at line 1) near '<eof>'"
+        );
+        assert_eq!(
+            error("x = = 1"),
+            "lua syntax error at line 1: unexpected symbol near '='"
+        );
+        assert_eq!(
+            error("f() = 1"),
+            "lua syntax error at line 1: syntax error near '='"
+        );
+        assert_eq!(
+            error("x"),
+            "lua syntax error at line 1: syntax error near '<eof>'"
+        );
+        assert_eq!(
+            error("return 1 x()"),
+            "lua syntax error at line 1: '<eof>' expected near 'x'"
+        );
+        assert_eq!(
+            error("local s = 'abc\nx'"),
+            "lua syntax error at line 1: unfinished string near ''abc'"
+        );
+        assert_eq!(
+            error("x = 1.2.3"),
+            "lua syntax error at line 1: malformed number near '1.2.3'"
+        );
+        assert_eq!(
+            error("x = 1.5LL"),
+            "lua syntax error at line 1: malformed number near '1.5LL'"
+        );
+        assert_eq!(
+            error("x = '\\q'"),
+            "lua syntax error at line 1: invalid escape sequence"
+        );
+        assert_eq!(
+            error("x = [[abc"),
+            "lua syntax error at line 1: unfinished long string near '<eof>'"
+        );
+        assert_eq!(
+            error("break"),
+            "lua syntax error at line 1: no loop to break near 'break'"
+        );
+        assert_eq!(
+            error("function f() return ... end"),
+            "lua syntax error at line 1: cannot use '...' outside a vararg function near '...'"
+        );
+        assert_eq!(
+            error("do goto skip end"),
+            "lua syntax error at line 1: undefined label 'skip'"
+        );
+        assert_eq!(
+            error("::a:: do ::a:: end"),
+            "lua syntax error at line 1: duplicate label 'a'"
+        );
+        assert_eq!(
+            error("local t = {x = 1 y = 2}"),
+            "lua syntax error at line 1: '}' expected near 'y'"
+        );
+        assert_eq!(
+            error("x = a\n(f)()"),
+            "lua syntax error at line 2: ambiguous syntax (function call x new statement) near '('"
+        );
+        assert_eq!(
+            error("x = a ! b"),
+            "lua syntax error at line 1: unexpected symbol near '!'"
+        );
+        assert_eq!(
+            error("x = 1 // 2"),
+            "lua syntax error at line 1: unexpected symbol near '/'"
+        );
+    }
+}
@@ -0,0 +1,721 @@
+//! Pool of reusable byte buffers.
+//!
+//! Encoding the tuples and keys passed to the box api and the net_box send
+//! and receive queues need temporary byte buffers, allocating and freeing
+//! which under high request rates puts a lot of pressure on the allocator.
+//! [`Pool`] keeps the released buffers and hands them out again:
+//!
+//! - the buffers ([`Buffer`]) are aligned to the page boundary
+//!   ([`PAGE_SIZE`]),
+//! - the capacities of the pooled buffers are rounded up to a power of 2
+//!   number of pages, so a released buffer can be reused for any request of
+//!   its size class, the larger requests are allocated exactly and aren't
+//!   pooled,
+//! - [`Pool::lease`] returns a [`Lease`] which gives the buffer back to the
+//!   pool when dropped, [`Pool::take`] and [`Pool::recycle`] can be used for
+//!   the buffers which are stored in other data structures,
+//! - the total size of the pooled buffers is limited (see
+//!   [`Pool::set_max_pooled_bytes`]), the buffers which don't fit are freed,
+//! - [`Pool::stats`] returns the pool occupancy and the hit rate.
+//!
+//! The pool isn't thread safe, [`pool`] returns the pool of the current
+//! thread, which is used by this crate.
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use tarantool::buffer;
+//!
+//! let mut buf = buffer::pool().lease(100);
+//! buf.write_all(b"hello").unwrap();
+//! assert!(buf.capacity() >= buffer::PAGE_SIZE);
+//! assert_eq!(buf.as_ptr() as usize % buffer::PAGE_SIZE, 0);
+//! drop(buf); // the buffer goes back to the pool
+//! assert!(buffer::pool().stats().pooled_buffers > 0);
+//! ```
+
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::rc::Rc;
+
+/// The alignment of the buffers and the granularity of the size classes of
+/// the pooled buffers.
+pub const PAGE_SIZE: usize = 4096;
+
+/// The default value of [`Pool::max_pooled_bytes`].
+pub const DEFAULT_MAX_POOLED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Buffers of up to `PAGE_SIZE << (SIZE_CLASSES - 1)` bytes (64MiB) are
+/// pooled.
+const SIZE_CLASSES: usize = 15;
+
+////////////////////////////////////////////////////////////////////////////////
+// Pool
+////////////////////////////////////////////////////////////////////////////////
+
+/// A pool of reusable byte buffers, see the [module level documentation]
+/// for details.
+///
+/// Cloning the pool is cheap, the clones refer to the same pool.
+///
+/// [module level documentation]: self
+#[derive(Clone)]
+pub struct Pool {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    /// Released buffers by size class, the buffers of class `i` have at least
+    /// `PAGE_SIZE << i` bytes of capacity.
+    classes: RefCell<[Vec<Buffer>; SIZE_CLASSES]>,
+    max_pooled_bytes: Cell<usize>,
+    pooled_buffers: Cell<usize>,
+    pooled_bytes: Cell<usize>,
+    leased_buffers: Cell<usize>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+impl Pool {
+    /// Creates an empty pool which keeps at most `max_pooled_bytes` bytes of
+    /// released buffers.
+    pub fn new(max_pooled_bytes: usize) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                classes: Default::default(),
+                max_pooled_bytes: Cell::new(max_pooled_bytes),
+                pooled_buffers: Cell::new(0),
+                pooled_bytes: Cell::new(0),
+                leased_buffers: Cell::new(0),
+                hits: Cell::new(0),
+                misses: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Returns an empty buffer with the capacity of at least `min_capacity`
+    /// bytes, which is given back to the pool once the lease is dropped.
+    #[inline]
+    pub fn lease(&self, min_capacity: usize) -> Lease {
+        let buf = self.take(min_capacity);
+        let inner = &self.inner;
+        inner.leased_buffers.set(inner.leased_buffers.get() + 1);
+        Lease {
+            buf,
+            pool: self.clone(),
+        }
+    }
+
+    /// Returns an empty buffer with the capacity of at least `min_capacity`
+    /// bytes. The buffer can be given back to the pool via
+    /// [`Pool::recycle`].
+    ///
+    /// The buffers too large to be pooled are allocated with the capacity of
+    /// exactly `min_capacity` bytes.
+    pub fn take(&self, min_capacity: usize) -> Buffer {
+        let inner = &self.inner;
+        let Some(class) = size_class(min_capacity) else {
+            inner.misses.set(inner.misses.get() + 1);
+            return Buffer::with_capacity(min_capacity);
+        };
+        if let Some(buf) = inner.classes.borrow_mut()[class].pop() {
+            inner.pooled_buffers.set(inner.pooled_buffers.get() - 1);
+            inner
+                .pooled_bytes
+                .set(inner.pooled_bytes.get() - buf.capacity());
+            inner.hits.set(inner.hits.get() + 1);
+            return buf;
+        }
+        inner.misses.set(inner.misses.get() + 1);
+        Buffer::with_capacity(PAGE_SIZE << class)
+    }
+
+    /// Gives the buffer back to the pool. The buffer is freed if it's too
+    /// small or too large to be pooled or if the pool is full.
+    pub fn recycle(&self, mut buf: Buffer) {
+        let inner = &self.inner;
+        let capacity = buf.capacity();
+        if capacity < PAGE_SIZE {
+            return;
+        }
+        // The largest class the buffer can serve.
+        let class = (usize::BITS - 1 - (capacity / PAGE_SIZE).leading_zeros()) as usize;
+        if class >= SIZE_CLASSES {
+            return;
+        }
+        let pooled_bytes = inner.pooled_bytes.get() + capacity;
+        if pooled_bytes > inner.max_pooled_bytes.get() {
+            return;
+        }
+        buf.clear();
+        inner.classes.borrow_mut()[class].push(buf);
+        inner.pooled_buffers.set(inner.pooled_buffers.get() + 1);
+        inner.pooled_bytes.set(pooled_bytes);
+    }
+
+    /// Returns the maximum total capacity of the buffers kept in the pool.
+    #[inline(always)]
+    pub fn max_pooled_bytes(&self) -> usize {
+        self.inner.max_pooled_bytes.get()
+    }
+
+    /// Sets the maximum total capacity of the buffers kept in the pool. The
+    /// pooled buffers which don't fit are freed (larger ones first).
+    pub fn set_max_pooled_bytes(&self, max_pooled_bytes: usize) {
+        let inner = &self.inner;
+        inner.max_pooled_bytes.set(max_pooled_bytes);
+        let mut classes = inner.classes.borrow_mut();
+        for class in classes.iter_mut().rev() {
+            while inner.pooled_bytes.get() > max_pooled_bytes {
+                let Some(buf) = class.pop() else {
+                    break;
+                };
+                inner.pooled_buffers.set(inner.pooled_buffers.get() - 1);
+                inner
+                    .pooled_bytes
+                    .set(inner.pooled_bytes.get() - buf.capacity());
+            }
+        }
+    }
+
+    /// Frees all of the pooled buffers.
+    #[inline]
+    pub fn clear(&self) {
+        let max_pooled_bytes = self.max_pooled_bytes();
+        self.set_max_pooled_bytes(0);
+        self.set_max_pooled_bytes(max_pooled_bytes);
+    }
+
+    /// Returns the current occupancy of the pool.
+    pub fn stats(&self) -> PoolStats {
+        let inner = &self.inner;
+        PoolStats {
+            pooled_buffers: inner.pooled_buffers.get(),
+            pooled_bytes: inner.pooled_bytes.get(),
+            max_pooled_bytes: inner.max_pooled_bytes.get(),
+            leased_buffers: inner.leased_buffers.get(),
+            hits: inner.hits.get(),
+            misses: inner.misses.get(),
+        }
+    }
+}
+
+impl Default for Pool {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_POOLED_BYTES)
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the size class of the buffer with the capacity of at least
+/// `min_capacity` bytes or `None` if it's too large to be pooled.
+fn size_class(min_capacity: usize) -> Option<usize> {
+    let pages = min_capacity / PAGE_SIZE + (min_capacity % PAGE_SIZE != 0) as usize;
+    let class = pages.max(1).checked_next_power_of_two()?.trailing_zeros() as usize;
+    (class < SIZE_CLASSES).then_some(class)
+}
+
+thread_local! {
+    static POOL: Pool = Pool::default();
+}
+
+/// Returns the buffer pool of the current thread.
+///
+/// The pool is used by this crate for encoding the tuples and keys passed to
+/// the box api and for the net_box send and receive buffers, use
+/// [`Pool::set_max_pooled_bytes`] to configure how much memory it may keep.
+#[inline]
+pub fn pool() -> Pool {
+    POOL.with(Pool::clone)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PoolStats
+////////////////////////////////////////////////////////////////////////////////
+
+/// The occupancy of a [`Pool`], see [`Pool::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PoolStats {
+    /// Number of the buffers kept in the pool.
+    pub pooled_buffers: usize,
+    /// Total capacity of the buffers kept in the pool.
+    pub pooled_bytes: usize,
+    /// See [`Pool::max_pooled_bytes`].
+    pub max_pooled_bytes: usize,
+    /// Number of the currently alive [`Lease`]s.
+    pub leased_buffers: usize,
+    /// Number of the buffers taken from the pool.
+    pub hits: u64,
+    /// Number of the buffers allocated because there was no pooled buffer of
+    /// the requested size.
+    pub misses: u64,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lease
+////////////////////////////////////////////////////////////////////////////////
+
+/// A buffer leased from a [`Pool`], which is given back to the pool when
+/// dropped.
+///
+/// Derefs to the [`Buffer`], so it can be written to and grown as usual.
+pub struct Lease {
+    buf: Buffer,
+    pool: Pool,
+}
+
+impl Lease {
+    /// Detaches the buffer from the pool.
+    #[inline]
+    pub fn into_inner(mut self) -> Buffer {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for Lease {
+    type Target = Buffer;
+
+    #[inline(always)]
+    fn deref(&self) -> &Buffer {
+        &self.buf
+    }
+}
+
+impl DerefMut for Lease {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Buffer {
+        &mut self.buf
+    }
+}
+
+impl AsRef<[u8]> for Lease {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Write for Lease {
+    #[inline(always)]
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.write(data)
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.buf.write_all(data)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Lease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lease")
+            .field("len", &self.buf.len())
+            .field("capacity", &self.buf.capacity())
+            .finish()
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let inner = &self.pool.inner;
+        inner.leased_buffers.set(inner.leased_buffers.get() - 1);
+        self.pool.recycle(std::mem::take(&mut self.buf));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Buffer
+////////////////////////////////////////////////////////////////////////////////
+
+/// A growable byte buffer, the memory of which is aligned to [`PAGE_SIZE`].
+///
+/// Derefs to `[u8]` and implements [`Write`], which appends to the buffer.
+pub struct Buffer {
+    ptr: NonNull<u8>,
+    len: usize,
+    capacity: usize,
+}
+
+impl Buffer {
+    /// Creates an empty buffer, which doesn't allocate.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            // Aligned dangling pointer, same as `NonNull::dangling` would be
+            // for a page aligned type.
+            ptr: unsafe { NonNull::new_unchecked(PAGE_SIZE as *mut u8) },
+            len: 0,
+            capacity: 0,
+        }
+    }
+
+    /// Creates an empty buffer with the capacity of exactly `capacity` bytes.
+    ///
+    /// # Panics
+    /// If the capacity exceeds `isize::MAX` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut buf = Self::new();
+        buf.grow_to(capacity);
+        buf
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr.as_ptr()
+    }
+
+    #[inline(always)]
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: the first `len` bytes are initialized.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    #[inline(always)]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: the first `len` bytes are initialized.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Removes the contents of the buffer keeping the allocated memory.
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Shortens the buffer to `len` bytes, does nothing if it's not longer
+    /// than that.
+    #[inline(always)]
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Reserves the capacity for at least `additional` more bytes, the
+    /// capacity is at least doubled to amortize the reallocations.
+    ///
+    /// # Panics
+    /// If the capacity exceeds `isize::MAX` bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        let Some(required) = self.len.checked_add(additional) else {
+            capacity_overflow();
+        };
+        if required <= self.capacity {
+            return;
+        }
+        let doubled = self.capacity.saturating_mul(2).max(PAGE_SIZE);
+        self.grow_to(required.max(doubled));
+    }
+
+    /// Resizes the buffer to `new_len` bytes filling the added ones with
+    /// `value`.
+    pub fn resize(&mut self, new_len: usize, value: u8) {
+        if new_len > self.len {
+            self.reserve(new_len - self.len);
+            // SAFETY: the capacity has been reserved.
+            unsafe { ptr::write_bytes(self.ptr.as_ptr().add(self.len), value, new_len - self.len) }
+        }
+        self.len = new_len;
+    }
+
+    /// Appends the `data` to the buffer.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.reserve(data.len());
+        // SAFETY: the capacity has been reserved and the `data` can't be
+        // borrowed from the spare capacity.
+        unsafe {
+            let end = self.ptr.as_ptr().add(self.len);
+            ptr::copy_nonoverlapping(data.as_ptr(), end, data.len());
+        }
+        self.len += data.len();
+    }
+
+    fn grow_to(&mut self, capacity: usize) {
+        debug_assert!(capacity >= self.capacity);
+        if capacity == self.capacity {
+            return;
+        }
+        let Ok(new_layout) = Layout::from_size_align(capacity, PAGE_SIZE) else {
+            capacity_overflow();
+        };
+        // SAFETY: the layout is non zero sized, the old layout is the one the
+        // memory was allocated with.
+        let ptr = unsafe {
+            if self.capacity == 0 {
+                alloc::alloc(new_layout)
+            } else {
+                alloc::realloc(self.ptr.as_ptr(), self.layout(), capacity)
+            }
+        };
+        let Some(ptr) = NonNull::new(ptr) else {
+            alloc::handle_alloc_error(new_layout);
+        };
+        self.ptr = ptr;
+        self.capacity = capacity;
+    }
+
+    #[inline(always)]
+    fn layout(&self) -> Layout {
+        // SAFETY: the layout has been checked when the memory was allocated.
+        unsafe { Layout::from_size_align_unchecked(self.capacity, PAGE_SIZE) }
+    }
+}
+
+#[cold]
+fn capacity_overflow() -> ! {
+    panic!("capacity overflow")
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.capacity != 0 {
+            // SAFETY: the memory was allocated with this layout.
+            unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout()) }
+        }
+    }
+}
+
+// SAFETY: the buffer owns its memory same as `Vec<u8>`.
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Default for Buffer {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        let mut buf = Self::with_capacity(self.capacity);
+        buf.extend_from_slice(self);
+        buf
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for Buffer {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl AsRef<[u8]> for Buffer {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl AsMut<[u8]> for Buffer {
+    #[inline(always)]
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl From<&[u8]> for Buffer {
+    #[inline]
+    fn from(data: &[u8]) -> Self {
+        let mut buf = Self::with_capacity(data.len());
+        buf.extend_from_slice(data);
+        buf
+    }
+}
+
+impl From<Buffer> for Vec<u8> {
+    #[inline(always)]
+    fn from(buf: Buffer) -> Self {
+        buf.as_slice().to_vec()
+    }
+}
+
+impl PartialEq for Buffer {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Buffer {}
+
+impl Write for Buffer {
+    #[inline(always)]
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Buffer")
+            .field("len", &self.len)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn lease_and_recycle() {
+        let pool = Pool::new(4 * PAGE_SIZE);
+
+        let mut buf = pool.lease(1);
+        assert_eq!(buf.capacity(), PAGE_SIZE);
+        buf.write_all(b"foo").unwrap();
+        let ptr = buf.as_ptr();
+        assert_eq!(pool.stats().leased_buffers, 1);
+        drop(buf);
+        let stats = pool.stats();
+        assert_eq!(stats.leased_buffers, 0);
+        assert_eq!(stats.pooled_buffers, 1);
+        assert_eq!(stats.pooled_bytes, PAGE_SIZE);
+
+        // The same buffer is reused and it's empty.
+        let buf = pool.lease(PAGE_SIZE);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert!(buf.is_empty());
+        assert_eq!((pool.stats().hits, pool.stats().misses), (1, 1));
+
+        // Doesn't fit into the smallest class.
+        let big = pool.lease(PAGE_SIZE + 1);
+        assert_eq!(big.capacity(), 2 * PAGE_SIZE);
+        assert_eq!(pool.stats().misses, 2);
+        drop(big);
+        drop(buf);
+        assert_eq!(pool.stats().pooled_bytes, 3 * PAGE_SIZE);
+
+        // The pool is full.
+        pool.recycle(Buffer::with_capacity(2 * PAGE_SIZE));
+        assert_eq!(pool.stats().pooled_buffers, 2);
+        // Too small to be pooled.
+        pool.recycle(Buffer::with_capacity(10));
+        assert_eq!(pool.stats().pooled_buffers, 2);
+
+        // Larger buffers are freed first.
+        pool.set_max_pooled_bytes(PAGE_SIZE);
+        assert_eq!(pool.stats().pooled_bytes, PAGE_SIZE);
+        pool.recycle(Buffer::with_capacity(PAGE_SIZE));
+        pool.clear();
+        assert_eq!(pool.stats().pooled_buffers, 0);
+        assert_eq!(pool.max_pooled_bytes(), PAGE_SIZE);
+
+        let buf = pool.lease(1).into_inner();
+        assert_eq!(buf.capacity(), PAGE_SIZE);
+        assert_eq!(pool.stats().pooled_buffers, 0);
+
+        // A grown buffer serves the larger class.
+        let pool = Pool::default();
+        let mut buf = pool.take(1);
+        buf.reserve(4 * PAGE_SIZE);
+        assert_eq!(buf.capacity(), 4 * PAGE_SIZE);
+        let ptr = buf.as_ptr();
+        pool.recycle(buf);
+        let buf = pool.take(3 * PAGE_SIZE);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(pool.stats().hits, 1);
+
+        // The buffers too large to be pooled are allocated exactly.
+        let size = (PAGE_SIZE << SIZE_CLASSES) + 1;
+        let buf = pool.take(size);
+        assert_eq!(buf.capacity(), size);
+        assert_eq!(buf.as_ptr() as usize % PAGE_SIZE, 0);
+        pool.recycle(buf);
+        assert_eq!(pool.stats().pooled_buffers, 0);
+        assert_eq!(
+            size_class(PAGE_SIZE << (SIZE_CLASSES - 1)),
+            Some(SIZE_CLASSES - 1)
+        );
+        assert_eq!(size_class(usize::MAX), None);
+        assert_eq!(size_class(usize::MAX - PAGE_SIZE), None);
+        assert_eq!(size_class(0), Some(0));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn buffer() {
+        let mut buf = Buffer::new();
+        assert_eq!(buf.capacity(), 0);
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_slice(), b"");
+
+        buf.write_all(b"foo").unwrap();
+        assert_eq!(buf.as_slice(), b"foo");
+        assert_eq!(buf.capacity(), PAGE_SIZE);
+        assert_eq!(buf.as_ptr() as usize % PAGE_SIZE, 0);
+
+        // The contents are kept and the memory stays aligned when grown.
+        buf.resize(PAGE_SIZE + 1, b'x');
+        assert_eq!(buf.capacity(), 2 * PAGE_SIZE);
+        assert_eq!(buf.as_ptr() as usize % PAGE_SIZE, 0);
+        assert_eq!(&buf[..4], b"foox");
+        assert_eq!(buf.len(), PAGE_SIZE + 1);
+        buf[0] = b'b';
+        buf.truncate(3);
+        assert_eq!(buf.as_slice(), b"boo");
+        assert_eq!(Vec::from(buf.clone()), b"boo");
+
+        let buf = Buffer::with_capacity(10);
+        assert_eq!(buf.capacity(), 10);
+        assert_eq!(buf.as_ptr() as usize % PAGE_SIZE, 0);
+    }
+}
@@ -0,0 +1,606 @@
+//! Typed instance configuration (`box.cfg`).
+//!
+//! [`Cfg`] contains all of the commonly used `box.cfg` options, each of which
+//! is optional, so that only the explicitly set options are passed to
+//! tarantool:
+//!
+//! ```no_run
+//! use tarantool::cfg::{Cfg, WalMode};
+//!
+//! Cfg::new()
+//!     .listen("127.0.0.1:3301")
+//!     .memtx_memory(256 * 1024 * 1024)
+//!     .wal_mode(WalMode::Write)
+//!     .replication(["replicator:secret@127.0.0.1:3302"])
+//!     .apply()
+//!     .unwrap();
+//! ```
+//!
+//! After the initial configuration only some of the options can be changed,
+//! use [`Cfg::reconfigure`] to check this before calling `box.cfg`, and
+//! [`Cfg::current`] to read the effective values. The dynamic changes can be
+//! audited or rejected with the [`on_change`] triggers.
+//!
+//! See also [box.cfg reference](https://www.tarantool.io/en/doc/latest/reference/configuration/).
+
+use crate::auth::AuthMethod;
+use crate::error::{BoxError, IntoBoxError, TarantoolErrorCode};
+use crate::log::SayLevel;
+use crate::trigger::TriggerHandle;
+use crate::util::NumOrStr;
+
+crate::define_str_enum! {
+    /// See [`Cfg::wal_mode`].
+    pub enum WalMode {
+        /// Write-ahead log is not maintained.
+        None = "none",
+        /// Fibers wait for their data to be written to the write-ahead log.
+        Write = "write",
+        /// Fibers wait for their data, `fsync` follows each write.
+        Fsync = "fsync",
+    }
+}
+
+crate::define_str_enum! {
+    /// See [`Cfg::election_mode`].
+    pub enum ElectionMode {
+        Off = "off",
+        Voter = "voter",
+        Manual = "manual",
+        Candidate = "candidate",
+    }
+}
+
+crate::define_str_enum! {
+    /// See [`Cfg::election_fencing_mode`].
+    pub enum ElectionFencingMode {
+        Off = "off",
+        Soft = "soft",
+        Strict = "strict",
+    }
+}
+
+crate::define_str_enum! {
+    /// See [`Cfg::log_format`].
+    pub enum LogFormat {
+        Plain = "plain",
+        Json = "json",
+    }
+}
+
+crate::define_str_enum! {
+    /// See [`Cfg::memtx_allocator`].
+    pub enum MemtxAllocator {
+        Small = "small",
+        System = "system",
+    }
+}
+
+/// Lua function which converts a `box.cfg` table into the form readable as
+/// [`Cfg`].
+const NORMALIZE: &str = "
+local function normalize(cfg)
+    local res = {}
+    for k, v in pairs(cfg) do
+        -- skip box.NULL
+        if v ~= nil then res[k] = v end
+    end
+    if type(res.replication) == 'string' then
+        res.replication = {res.replication}
+    end
+    if type(res.listen) == 'number' then
+        res.listen = tostring(res.listen)
+    elseif type(res.listen) == 'table' then
+        local uris = {}
+        for _, uri in ipairs(res.listen) do
+            table.insert(uris, type(uri) == 'table' and uri.uri or tostring(uri))
+        end
+        res.listen = table.concat(uris, ',')
+    end
+    return res
+end
+";
+
+////////////////////////////////////////////////////////////////////////////////
+// Cfg
+////////////////////////////////////////////////////////////////////////////////
+
+/// Instance configuration passed to `box.cfg`.
+///
+/// The options which are `None` are not passed to tarantool, so they keep
+/// their current (or default) values.
+///
+/// For the meaning of each option see the
+/// [configuration reference](https://www.tarantool.io/en/doc/latest/reference/configuration/).
+#[derive(Clone, Debug, Default, PartialEq, tlua::Push, tlua::LuaRead)]
+pub struct Cfg {
+    // basic
+    /// URI (or a comma separated list of URIs) to listen on for the iproto
+    /// connections.
+    pub listen: Option<String>,
+    pub background: Option<bool>,
+    pub custom_proc_title: Option<String>,
+    pub instance_uuid: Option<String>,
+    pub replicaset_uuid: Option<String>,
+    pub memtx_dir: Option<String>,
+    pub wal_dir: Option<String>,
+    pub vinyl_dir: Option<String>,
+    pub work_dir: Option<String>,
+    pub pid_file: Option<String>,
+    pub read_only: Option<bool>,
+    pub username: Option<String>,
+    pub auth_type: Option<AuthMethod>,
+    pub too_long_threshold: Option<f64>,
+    pub feedback_enabled: Option<bool>,
+    pub sql_cache_size: Option<u64>,
+
+    // logging
+    pub log: Option<String>,
+    pub log_level: Option<SayLevel>,
+    pub log_format: Option<LogFormat>,
+    pub log_nonblock: Option<bool>,
+
+    // memtx
+    pub memtx_memory: Option<u64>,
+    pub memtx_max_tuple_size: Option<u64>,
+    pub memtx_allocator: Option<MemtxAllocator>,
+    pub memtx_use_mvcc_engine: Option<bool>,
+    pub slab_alloc_factor: Option<f64>,
+    pub slab_alloc_granularity: Option<u64>,
+
+    // vinyl
+    pub vinyl_memory: Option<u64>,
+    pub vinyl_cache: Option<u64>,
+    pub vinyl_max_tuple_size: Option<u64>,
+    pub vinyl_read_threads: Option<u32>,
+    pub vinyl_write_threads: Option<u32>,
+    pub vinyl_timeout: Option<f64>,
+    pub vinyl_page_size: Option<u64>,
+    pub vinyl_range_size: Option<u64>,
+    pub vinyl_run_count_per_level: Option<u32>,
+    pub vinyl_run_size_ratio: Option<f64>,
+    pub vinyl_bloom_fpr: Option<f64>,
+
+    // write-ahead log & checkpoints
+    pub wal_mode: Option<WalMode>,
+    pub wal_max_size: Option<u64>,
+    pub wal_dir_rescan_delay: Option<f64>,
+    pub wal_queue_max_size: Option<u64>,
+    pub wal_cleanup_delay: Option<f64>,
+    pub checkpoint_interval: Option<f64>,
+    pub checkpoint_count: Option<u32>,
+    pub checkpoint_wal_threshold: Option<u64>,
+    pub force_recovery: Option<bool>,
+
+    // replication
+    pub replication: Option<Vec<String>>,
+    pub replication_timeout: Option<f64>,
+    pub replication_connect_timeout: Option<f64>,
+    pub replication_connect_quorum: Option<u32>,
+    pub replication_sync_lag: Option<f64>,
+    pub replication_sync_timeout: Option<f64>,
+    pub replication_skip_conflict: Option<bool>,
+    pub replication_anon: Option<bool>,
+    /// Either a number or a formula like `"N / 2 + 1"`.
+    pub replication_synchro_quorum: Option<NumOrStr>,
+    pub replication_synchro_timeout: Option<f64>,
+
+    // election
+    pub election_mode: Option<ElectionMode>,
+    pub election_timeout: Option<f64>,
+    pub election_fencing_mode: Option<ElectionFencingMode>,
+
+    // networking
+    pub net_msg_max: Option<u32>,
+    pub readahead: Option<u32>,
+    pub iproto_threads: Option<u32>,
+}
+
+macro_rules! define_setters {
+    ($( $setter:ident ( $field:ident : $ty:ty ) )+) => {
+        $(
+            #[inline(always)]
+            pub fn $setter(mut self, $field: $ty) -> Self {
+                self.$field = Some($field.into());
+                self
+            }
+        )+
+    }
+}
+
+impl Cfg {
+    /// Creates a configuration with all of the options unset.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    define_setters! {
+        listen(listen: impl Into<String>)
+        background(background: bool)
+        custom_proc_title(custom_proc_title: impl Into<String>)
+        instance_uuid(instance_uuid: impl Into<String>)
+        replicaset_uuid(replicaset_uuid: impl Into<String>)
+        memtx_dir(memtx_dir: impl Into<String>)
+        wal_dir(wal_dir: impl Into<String>)
+        vinyl_dir(vinyl_dir: impl Into<String>)
+        work_dir(work_dir: impl Into<String>)
+        pid_file(pid_file: impl Into<String>)
+        read_only(read_only: bool)
+        username(username: impl Into<String>)
+        auth_type(auth_type: AuthMethod)
+        too_long_threshold(too_long_threshold: f64)
+        feedback_enabled(feedback_enabled: bool)
+        sql_cache_size(sql_cache_size: u64)
+        log(log: impl Into<String>)
+        log_level(log_level: SayLevel)
+        log_format(log_format: LogFormat)
+        log_nonblock(log_nonblock: bool)
+        memtx_memory(memtx_memory: u64)
+        memtx_max_tuple_size(memtx_max_tuple_size: u64)
+        memtx_allocator(memtx_allocator: MemtxAllocator)
+        memtx_use_mvcc_engine(memtx_use_mvcc_engine: bool)
+        slab_alloc_factor(slab_alloc_factor: f64)
+        slab_alloc_granularity(slab_alloc_granularity: u64)
+        vinyl_memory(vinyl_memory: u64)
+        vinyl_cache(vinyl_cache: u64)
+        vinyl_max_tuple_size(vinyl_max_tuple_size: u64)
+        vinyl_read_threads(vinyl_read_threads: u32)
+        vinyl_write_threads(vinyl_write_threads: u32)
+        vinyl_timeout(vinyl_timeout: f64)
+        vinyl_page_size(vinyl_page_size: u64)
+        vinyl_range_size(vinyl_range_size: u64)
+        vinyl_run_count_per_level(vinyl_run_count_per_level: u32)
+        vinyl_run_size_ratio(vinyl_run_size_ratio: f64)
+        vinyl_bloom_fpr(vinyl_bloom_fpr: f64)
+        wal_mode(wal_mode: WalMode)
+        wal_max_size(wal_max_size: u64)
+        wal_dir_rescan_delay(wal_dir_rescan_delay: f64)
+        wal_queue_max_size(wal_queue_max_size: u64)
+        wal_cleanup_delay(wal_cleanup_delay: f64)
+        checkpoint_interval(checkpoint_interval: f64)
+        checkpoint_count(checkpoint_count: u32)
+        checkpoint_wal_threshold(checkpoint_wal_threshold: u64)
+        force_recovery(force_recovery: bool)
+        replication_timeout(replication_timeout: f64)
+        replication_connect_timeout(replication_connect_timeout: f64)
+        replication_connect_quorum(replication_connect_quorum: u32)
+        replication_sync_lag(replication_sync_lag: f64)
+        replication_sync_timeout(replication_sync_timeout: f64)
+        replication_skip_conflict(replication_skip_conflict: bool)
+        replication_anon(replication_anon: bool)
+        replication_synchro_quorum(replication_synchro_quorum: impl Into<NumOrStr>)
+        replication_synchro_timeout(replication_synchro_timeout: f64)
+        election_mode(election_mode: ElectionMode)
+        election_timeout(election_timeout: f64)
+        election_fencing_mode(election_fencing_mode: ElectionFencingMode)
+        net_msg_max(net_msg_max: u32)
+        readahead(readahead: u32)
+        iproto_threads(iproto_threads: u32)
+    }
+
+    /// Sets the URIs of the replication peers.
+    #[inline(always)]
+    pub fn replication(mut self, peers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.replication = Some(peers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Returns the names of the options which are set in `self` and can't be
+    /// changed after the initial configuration.
+    pub fn static_options(&self) -> Vec<&'static str> {
+        let mut res = Vec::new();
+        macro_rules! check {
+            ($($field:ident)+) => {
+                $(
+                    if self.$field.is_some() {
+                        res.push(stringify!($field));
+                    }
+                )+
+            }
+        }
+        check! {
+            background instance_uuid replicaset_uuid memtx_dir wal_dir
+            vinyl_dir work_dir pid_file username log log_nonblock
+            memtx_allocator memtx_use_mvcc_engine slab_alloc_factor
+            slab_alloc_granularity vinyl_read_threads vinyl_write_threads
+            vinyl_page_size vinyl_range_size vinyl_run_count_per_level
+            vinyl_run_size_ratio vinyl_bloom_fpr wal_mode wal_max_size
+            iproto_threads
+        }
+        res
+    }
+
+    /// Calls `box.cfg` with the options set in `self`.
+    ///
+    /// This is meant for the initial configuration of the instance, but it
+    /// can also be used for changing the dynamic options later, in which case
+    /// tarantool returns an error if a static option is changed. See also
+    /// [`Self::reconfigure`].
+    pub fn apply(&self) -> crate::Result<()> {
+        crate::lua_state()
+            .exec_with("box.cfg(...)", self)
+            .map_err(tlua::LuaError::from)?;
+        Ok(())
+    }
+
+    /// Changes the dynamic options of an already configured instance.
+    ///
+    /// Returns an error without changing anything if `box.cfg{ .. }` was not
+    /// called yet or if any of the options returned by
+    /// [`Self::static_options`] is set.
+    pub fn reconfigure(&self) -> crate::Result<()> {
+        if !is_configured() {
+            return Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                "box.cfg{} was not called yet",
+            )
+            .into());
+        }
+        let static_options = self.static_options();
+        if !static_options.is_empty() {
+            return Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                format!(
+                    "options can't be changed after the initial configuration: {}",
+                    static_options.join(", ")
+                ),
+            )
+            .into());
+        }
+        self.apply()
+    }
+
+    /// Returns the effective configuration of the instance.
+    ///
+    /// Options unknown to the current tarantool version are `None`.
+    ///
+    /// Returns an error if `box.cfg{ .. }` was not called yet.
+    pub fn current() -> crate::Result<Self> {
+        let cfg = crate::lua_state().eval(&format!(
+            "{NORMALIZE}
+            if type(box.cfg) == 'function' then
+                error('box.cfg{{}} was not called yet', 0)
+            end
+            return normalize(box.cfg)"
+        ))?;
+        Ok(cfg)
+    }
+}
+
+/// Returns `true` if `box.cfg{ .. }` was already called.
+#[inline]
+pub fn is_configured() -> bool {
+    crate::lua_state()
+        .eval("return type(box.cfg) ~= 'function'")
+        .unwrap_or(false)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// on_change
+////////////////////////////////////////////////////////////////////////////////
+
+/// A dynamic configuration change passed to the [`on_change`] triggers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CfgChange {
+    /// Sorted names of the options whose requested values differ from the
+    /// current ones, including the options unknown to [`Cfg`].
+    pub changed: Vec<String>,
+    /// The configuration before the change.
+    pub current: Cfg,
+    /// The options passed to `box.cfg`.
+    pub requested: Cfg,
+}
+
+impl CfgChange {
+    /// Returns `true` if the value of `option` is being changed.
+    #[inline]
+    pub fn is_changed(&self, option: &str) -> bool {
+        self.changed.iter().any(|o| o == option)
+    }
+}
+
+/// Sets a trigger which is called before every dynamic configuration change,
+/// i.e. a `box.cfg{ .. }` call after the initial configuration, which changes
+/// the value of at least one option. This includes the calls made from lua
+/// and via [`Cfg::reconfigure`].
+///
+/// If the closure returns an error, the change is rejected: `box.cfg` fails
+/// with this error and a warning is written to the log. This can be used to
+/// enforce a policy for the changes, or just to audit them:
+///
+/// ```no_run
+/// use tarantool::cfg::{self, CfgChange};
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// let failover = Rc::new(Cell::new(false));
+/// let handle = cfg::on_change(move |change: &CfgChange| {
+///     tarantool::say_info!("box.cfg change: {}", change.changed.join(", "));
+///     if change.is_changed("read_only") && !failover.get() {
+///         return Err("read_only can only be changed during failover");
+///     }
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+///
+/// Returns an error if `box.cfg{ .. }` was not called yet.
+///
+/// The trigger is unregistered via the returned [`TriggerHandle`].
+pub fn on_change<F, E>(mut f: F) -> crate::Result<TriggerHandle>
+where
+    F: FnMut(&CfgChange) -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    if !is_configured() {
+        return Err(BoxError::new(
+            TarantoolErrorCode::IllegalParams,
+            "box.cfg{} was not called yet",
+        )
+        .into());
+    }
+    crate::lua_state().exec(&format!("{NORMALIZE}{INSTALL_ON_CFG}"))?;
+
+    crate::trigger::on_cfg(move |changed, current, requested| {
+        let change = CfgChange {
+            changed,
+            current,
+            requested,
+        };
+        f(&change).map_err(|e| {
+            let e = e.into_box_error();
+            crate::say_warn!(
+                "box.cfg change of {} rejected: {}",
+                change.changed.join(", "),
+                e.message()
+            );
+            e
+        })
+    })
+}
+
+/// Wraps the `box.cfg` call so that it runs the triggers set via
+/// [`on_change`], and stores the function for setting these triggers in the
+/// lua registry.
+const INSTALL_ON_CFG: &str = "
+local registry = debug.getregistry()
+if registry.tarantool_rust_on_cfg ~= nil then
+    return
+end
+
+local json = require('json')
+local function same(a, b)
+    if type(a) == 'table' or type(b) == 'table' then
+        return json.encode(a) == json.encode(b)
+    end
+    return a == b
+end
+
+local triggers = {}
+local mt = getmetatable(box.cfg)
+local call = mt.__call
+mt.__call = function(cfg, new_cfg, ...)
+    if type(new_cfg) == 'table' and #triggers > 0 then
+        local changed = {}
+        for k, v in pairs(new_cfg) do
+            if not same(v, cfg[k]) then
+                table.insert(changed, k)
+            end
+        end
+        if #changed > 0 then
+            table.sort(changed)
+            local current, requested = normalize(cfg), normalize(new_cfg)
+            -- a trigger may unregister itself
+            for _, trigger in ipairs(table.copy(triggers)) do
+                trigger(changed, current, requested)
+            end
+        end
+    end
+    return call(cfg, new_cfg, ...)
+end
+
+registry.tarantool_rust_on_cfg = function(new, old)
+    if old ~= nil then
+        for i, trigger in ipairs(triggers) do
+            if trigger == old then
+                table.remove(triggers, i)
+                break
+            end
+        end
+    end
+    if new ~= nil then
+        table.insert(triggers, new)
+    end
+end
+";
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn current() {
+        assert!(is_configured());
+        let cfg = Cfg::current().unwrap();
+        let lua = crate::lua_state();
+        let memtx_memory: u64 = lua.eval("return box.cfg.memtx_memory").unwrap();
+        assert_eq!(cfg.memtx_memory, Some(memtx_memory));
+        let wal_mode: String = lua.eval("return box.cfg.wal_mode").unwrap();
+        assert_eq!(cfg.wal_mode.map(|m| m.as_str()), Some(&*wal_mode));
+        assert!(cfg.log_level.is_some());
+        assert!(cfg.read_only.is_some());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn reconfigure() {
+        let old = Cfg::current().unwrap().too_long_threshold.unwrap();
+        Cfg::new().too_long_threshold(13.5).reconfigure().unwrap();
+        assert_eq!(Cfg::current().unwrap().too_long_threshold, Some(13.5));
+        Cfg::new().too_long_threshold(old).reconfigure().unwrap();
+
+        let cfg = Cfg::new().wal_mode(WalMode::None).work_dir("/tmp");
+        assert_eq!(cfg.static_options(), ["work_dir", "wal_mode"]);
+        let e = cfg.reconfigure().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IllegalParams: options can't be changed after the initial configuration: work_dir, wal_mode"
+        );
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn on_change_policy() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let changes = Rc::new(RefCell::new(vec![]));
+        let changes_clone = changes.clone();
+        let handle = on_change(move |change: &CfgChange| {
+            changes_clone.borrow_mut().push(change.clone());
+            if change.is_changed("read_only") {
+                return Err("read_only can't be changed");
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        let old = Cfg::current().unwrap().too_long_threshold.unwrap();
+        Cfg::new()
+            .too_long_threshold(old + 1.0)
+            .reconfigure()
+            .unwrap();
+        // Nothing is changed, the trigger isn't called.
+        Cfg::new()
+            .too_long_threshold(old + 1.0)
+            .reconfigure()
+            .unwrap();
+        {
+            let changes = changes.borrow();
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].changed, ["too_long_threshold"]);
+            assert_eq!(changes[0].current.too_long_threshold, Some(old));
+            assert_eq!(changes[0].requested.too_long_threshold, Some(old + 1.0));
+            assert_eq!(changes[0].requested.read_only, None);
+        }
+
+        let e = crate::lua_state()
+            .exec("box.cfg{read_only = true, too_long_threshold = 1}")
+            .unwrap_err();
+        assert!(
+            e.to_string().contains("read_only can't be changed"),
+            "{}",
+            e
+        );
+        assert_eq!(
+            changes.borrow()[1].changed,
+            ["read_only", "too_long_threshold"]
+        );
+        let cfg = Cfg::current().unwrap();
+        assert_eq!(cfg.read_only, Some(false));
+        assert_eq!(cfg.too_long_threshold, Some(old + 1.0));
+
+        assert!(handle.unregister().unwrap());
+        Cfg::new().too_long_threshold(old).reconfigure().unwrap();
+        assert_eq!(changes.borrow().len(), 2);
+    }
+}
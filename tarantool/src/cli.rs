@@ -0,0 +1,654 @@
+//! Command line interface of a module for the operators.
+//!
+//! A [`Cli`] is a set of [`Command`]s, each of which has a name, a
+//! description of the arguments and a handler. The commands are called
+//! uniformly via a single lua function (see [`Cli::expose`]) or a stored
+//! procedure (see [`Cli::run`]) and the usage is generated from the
+//! descriptions, so the operators don't need to remember the bespoke lua
+//! snippets for each module.
+//!
+//! ```no_run
+//! use tarantool::cli::{Arg, Cli, Command};
+//!
+//! Cli::new("my_app")
+//!     .about("Administration of my_app")
+//!     .command(
+//!         Command::new("stats", |args| {
+//!             let space = args.value("space").unwrap();
+//!             let limit: u32 = args.required("limit")?;
+//!             Ok(format!("stats of {} (top {})", space, limit))
+//!         })
+//!         .about("Show the statistics of a space")
+//!         .arg(Arg::positional("space").help("Name of the space"))
+//!         .arg(Arg::option("limit").default("10").help("Number of entries")),
+//!     )
+//!     .expose("my_app_cli")
+//!     .unwrap();
+//! ```
+//!
+//! Then in the console:
+//! ```text
+//! tarantool> my_app_cli('stats users --limit 3')
+//! ---
+//! - stats of users (top 3)
+//! ...
+//!
+//! tarantool> my_app_cli('help stats')
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+/// Error returned by the handler of a [`Command`].
+pub type HandlerError = Box<dyn std::error::Error>;
+
+type Handler = Box<dyn Fn(&Args) -> Result<String, HandlerError>>;
+
+////////////////////////////////////////////////////////////////////////////////
+// Error
+////////////////////////////////////////////////////////////////////////////////
+
+/// Error of parsing the arguments or running a command.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no command specified, see 'help'")]
+    MissingCommand,
+
+    #[error("unknown command '{0}', see 'help'")]
+    UnknownCommand(String),
+
+    #[error("missing required argument '{0}'")]
+    MissingArgument(String),
+
+    #[error("unexpected argument '{0}'")]
+    UnexpectedArgument(String),
+
+    #[error("option '--{0}' requires a value")]
+    MissingValue(String),
+
+    #[error("invalid value '{value}' of argument '{name}': {reason}")]
+    InvalidValue {
+        name: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("unterminated quote in the command line")]
+    UnterminatedQuote,
+
+    #[error("{0}")]
+    Handler(HandlerError),
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Arg
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArgKind {
+    Positional,
+    Option,
+    Flag,
+}
+
+/// Description of an argument of a [`Command`].
+#[derive(Clone, Debug)]
+pub struct Arg {
+    name: String,
+    kind: ArgKind,
+    help: String,
+    required: bool,
+    default: Option<String>,
+}
+
+impl Arg {
+    fn new(name: impl Into<String>, kind: ArgKind, required: bool) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            help: String::new(),
+            required,
+            default: None,
+        }
+    }
+
+    /// A positional argument, required unless it has a [default](Self::default)
+    /// or is explicitly made optional.
+    #[inline(always)]
+    pub fn positional(name: impl Into<String>) -> Self {
+        Self::new(name, ArgKind::Positional, true)
+    }
+
+    /// An optional argument with a value: `--name value` or `--name=value`.
+    #[inline(always)]
+    pub fn option(name: impl Into<String>) -> Self {
+        Self::new(name, ArgKind::Option, false)
+    }
+
+    /// An optional argument without a value: `--name`.
+    #[inline(always)]
+    pub fn flag(name: impl Into<String>) -> Self {
+        Self::new(name, ArgKind::Flag, false)
+    }
+
+    #[inline(always)]
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = help.into();
+        self
+    }
+
+    /// Ignored for flags.
+    #[inline(always)]
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Value used if the argument isn't specified, makes the argument
+    /// optional. Ignored for flags.
+    #[inline(always)]
+    pub fn default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self.required = false;
+        self
+    }
+
+    fn is_required(&self) -> bool {
+        self.required && self.kind != ArgKind::Flag
+    }
+
+    fn syntax(&self) -> String {
+        match self.kind {
+            ArgKind::Positional => format!("<{}>", self.name),
+            ArgKind::Option => format!("--{} <{}>", self.name, self.name),
+            ArgKind::Flag => format!("--{}", self.name),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Args
+////////////////////////////////////////////////////////////////////////////////
+
+/// Arguments of a [`Command`] parsed according to its [`Arg`]s.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    values: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    /// Returns the value of a positional argument or an option, or `None`
+    /// if it wasn't specified and has no default.
+    #[inline]
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Returns the parsed value of a positional argument or an option, or
+    /// `None` if it wasn't specified and has no default.
+    pub fn get<T>(&self, name: &str) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = crate::unwrap_or!(self.value(name), return Ok(None));
+        let res = value.parse().map_err(|e: T::Err| Error::InvalidValue {
+            name: name.into(),
+            value: value.into(),
+            reason: e.to_string(),
+        })?;
+        Ok(Some(res))
+    }
+
+    /// Same as [`Self::get`], but returns an error if the argument wasn't
+    /// specified.
+    #[inline]
+    pub fn required<T>(&self, name: &str) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get(name)?
+            .ok_or_else(|| Error::MissingArgument(name.into()))
+    }
+
+    /// Returns `true` if the flag was specified.
+    #[inline]
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Command
+////////////////////////////////////////////////////////////////////////////////
+
+/// A command of a [`Cli`].
+pub struct Command {
+    name: String,
+    about: String,
+    args: Vec<Arg>,
+    handler: Handler,
+}
+
+impl Command {
+    /// Creates a command which calls `handler` with the parsed arguments.
+    /// The string returned by the handler is the output of the command.
+    pub fn new<F>(name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&Args) -> Result<String, HandlerError> + 'static,
+    {
+        Self {
+            name: name.into(),
+            about: String::new(),
+            args: Vec::new(),
+            handler: Box::new(handler),
+        }
+    }
+
+    #[inline(always)]
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.about = about.into();
+        self
+    }
+
+    /// Adds an argument. The positional arguments are matched in the order
+    /// they're added.
+    #[inline(always)]
+    pub fn arg(mut self, arg: Arg) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parses `args` according to the arguments of the command.
+    pub fn parse<S: AsRef<str>>(&self, args: &[S]) -> Result<Args, Error> {
+        let mut res = Args::default();
+        let mut positional = self.args.iter().filter(|a| a.kind == ArgKind::Positional);
+        let mut only_positional = false;
+        let mut args = args.iter().map(AsRef::as_ref);
+        while let Some(token) = args.next() {
+            let option = token.strip_prefix("--").filter(|_| !only_positional);
+            let option = crate::unwrap_or!(option, {
+                let arg = positional
+                    .next()
+                    .ok_or_else(|| Error::UnexpectedArgument(token.into()))?;
+                res.values.insert(arg.name.clone(), token.into());
+                continue;
+            });
+            if option.is_empty() {
+                only_positional = true;
+                continue;
+            }
+
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (option, None),
+            };
+            let arg = self
+                .args
+                .iter()
+                .find(|a| a.name == name && a.kind != ArgKind::Positional)
+                .ok_or_else(|| Error::UnexpectedArgument(token.into()))?;
+            if arg.kind == ArgKind::Flag {
+                if value.is_some() {
+                    return Err(Error::UnexpectedArgument(token.into()));
+                }
+                res.flags.insert(arg.name.clone());
+                continue;
+            }
+            let value = value
+                .or_else(|| args.next())
+                .ok_or_else(|| Error::MissingValue(arg.name.clone()))?;
+            res.values.insert(arg.name.clone(), value.into());
+        }
+
+        for arg in &self.args {
+            if res.values.contains_key(&arg.name) {
+                continue;
+            }
+            if let Some(default) = &arg.default {
+                res.values.insert(arg.name.clone(), default.clone());
+            } else if arg.is_required() {
+                return Err(Error::MissingArgument(arg.name.clone()));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Parses `args` and calls the handler.
+    pub fn run<S: AsRef<str>>(&self, args: &[S]) -> Result<String, Error> {
+        let args = self.parse(args)?;
+        (self.handler)(&args).map_err(Error::Handler)
+    }
+
+    /// Returns the usage of the command. `cli` is the name of the [`Cli`].
+    pub fn usage(&self, cli: &str) -> String {
+        let mut res = format!("Usage: {} {}", cli, self.name);
+        for arg in &self.args {
+            if arg.is_required() {
+                write!(res, " {}", arg.syntax()).unwrap();
+            } else {
+                write!(res, " [{}]", arg.syntax()).unwrap();
+            }
+        }
+        res.push('\n');
+        if !self.about.is_empty() {
+            write!(res, "\n{}\n", self.about).unwrap();
+        }
+
+        let describe = |arg: &Arg| {
+            let mut help = arg.help.clone();
+            if let Some(default) = &arg.default {
+                if !help.is_empty() {
+                    help.push(' ');
+                }
+                write!(help, "[default: {}]", default).unwrap();
+            }
+            (arg.syntax(), help)
+        };
+        let (positional, options): (Vec<_>, Vec<_>) = self
+            .args
+            .iter()
+            .partition(|a| a.kind == ArgKind::Positional);
+        if !positional.is_empty() {
+            res.push_str("\nArguments:\n");
+            write_table(&mut res, positional.into_iter().map(describe));
+        }
+        if !options.is_empty() {
+            res.push_str("\nOptions:\n");
+            write_table(&mut res, options.into_iter().map(describe));
+        }
+        res
+    }
+}
+
+impl std::fmt::Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("about", &self.about)
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Cli
+////////////////////////////////////////////////////////////////////////////////
+
+/// A set of [`Command`]s callable by name, see the [module docs](self).
+///
+/// A `help` command is always available, it returns the usage of the whole
+/// interface or of the specified command. Any command also returns its usage
+/// when called with `--help`.
+#[derive(Debug)]
+pub struct Cli {
+    name: String,
+    about: String,
+    commands: Vec<Command>,
+}
+
+impl Cli {
+    /// `name` is only used in the usage, it should be the name under which
+    /// the interface is [exposed](Self::expose).
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            about: String::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.about = about.into();
+        self
+    }
+
+    /// Adds a command. A command with the same name as an existing one
+    /// replaces it.
+    #[inline]
+    pub fn command(mut self, command: Command) -> Self {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+        self
+    }
+
+    /// Runs the command specified by the first of `args` with the rest of
+    /// them, and returns its output.
+    ///
+    /// Can be used to implement a stored procedure:
+    /// ```no_run
+    /// # use tarantool::cli::Cli;
+    /// # fn cli() -> Cli { unimplemented!() }
+    /// #[tarantool::proc]
+    /// fn my_app_cli(args: Vec<String>) -> Result<String, String> {
+    ///     cli().run(&args).map_err(|e| e.to_string())
+    /// }
+    /// ```
+    pub fn run<S: AsRef<str>>(&self, args: &[S]) -> Result<String, Error> {
+        let (command, args) = args.split_first().ok_or(Error::MissingCommand)?;
+        let command = command.as_ref();
+        if command == "help" || command == "--help" {
+            return match args {
+                [] => Ok(self.usage()),
+                [command] => Ok(self.find(command.as_ref())?.usage(&self.name)),
+                [_, arg, ..] => Err(Error::UnexpectedArgument(arg.as_ref().into())),
+            };
+        }
+        let command = self.find(command)?;
+        if args.iter().any(|a| a.as_ref() == "--help") {
+            return Ok(command.usage(&self.name));
+        }
+        command.run(args)
+    }
+
+    /// Splits `line` into the arguments like a shell does (the arguments
+    /// are separated by whitespace and can be quoted with `'` or `"`) and
+    /// calls [`Self::run`].
+    #[inline]
+    pub fn run_line(&self, line: &str) -> Result<String, Error> {
+        self.run(&split_line(line)?)
+    }
+
+    fn find(&self, name: &str) -> Result<&Command, Error> {
+        self.commands
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| Error::UnknownCommand(name.into()))
+    }
+
+    /// Returns the usage of the whole interface.
+    pub fn usage(&self) -> String {
+        let mut res = format!("Usage: {} <command> [<args>...]\n", self.name);
+        if !self.about.is_empty() {
+            write!(res, "\n{}\n", self.about).unwrap();
+        }
+        res.push_str("\nCommands:\n");
+        let commands = self
+            .commands
+            .iter()
+            .map(|c| (c.name.clone(), c.about.clone()));
+        let help = (
+            "help".to_string(),
+            "Show this message or the usage of the given command".to_string(),
+        );
+        write_table(&mut res, commands.chain(std::iter::once(help)));
+        res
+    }
+
+    /// Defines a global lua function `name` which runs the commands.
+    ///
+    /// The function accepts either a single string which is split into the
+    /// arguments (see [`Self::run_line`]) or a table of the arguments. It
+    /// returns the output of the command or throws an error.
+    ///
+    /// The function can also be called via iproto once it's registered with
+    /// `box.schema.func.create(name)` and the users have the permission to
+    /// execute it.
+    pub fn expose(self, name: &str) -> crate::Result<()> {
+        crate::lua_state().set(
+            name,
+            tlua::function1(move |args: tlua::Either<String, Vec<String>>| {
+                let res = match args {
+                    tlua::Either::Left(line) => self.run_line(&line),
+                    tlua::Either::Right(args) => self.run(&args),
+                };
+                res.map_err(|e| tlua::Throw(e.to_string()))
+            }),
+        );
+        Ok(())
+    }
+}
+
+/// Writes the rows of a two column table, aligning the second column.
+fn write_table(out: &mut String, rows: impl Iterator<Item = (String, String)>) {
+    let rows: Vec<_> = rows.collect();
+    let width = rows.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+    for (left, right) in rows {
+        let line = format!("  {:width$}  {}", left, right, width = width);
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+}
+
+/// Splits `line` into whitespace separated arguments, which can be quoted
+/// with `'` or `"`.
+pub fn split_line(line: &str) -> Result<Vec<String>, Error> {
+    let mut res = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.get_or_insert_with(String::new).push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => res.extend(current.take()),
+            None => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(Error::UnterminatedQuote);
+    }
+    res.extend(current);
+    Ok(res)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    fn cli() -> Cli {
+        Cli::new("test_cli")
+            .about("Testing the cli")
+            .command(
+                Command::new("greet", |args| {
+                    let name = args.value("name").unwrap();
+                    let times: usize = args.required("times")?;
+                    let mut res = vec![format!("hello {}", name); times].join(", ");
+                    if args.flag("loud") {
+                        res = res.to_uppercase();
+                    }
+                    Ok(res)
+                })
+                .about("Greet somebody")
+                .arg(Arg::positional("name").help("Who to greet"))
+                .arg(Arg::option("times").default("1"))
+                .arg(Arg::flag("loud").help("Greet loudly")),
+            )
+            .command(Command::new("fail", |_| Err("oops".into())))
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn run() {
+        let cli = cli();
+        assert_eq!(cli.run(&["greet", "bob"]).unwrap(), "hello bob");
+        assert_eq!(
+            cli.run_line("greet 'bob the builder' --times=2 --loud")
+                .unwrap(),
+            "HELLO BOB THE BUILDER, HELLO BOB THE BUILDER"
+        );
+        assert_eq!(
+            cli.run_line("greet --times 2 -- --bob").unwrap(),
+            "hello --bob, hello --bob"
+        );
+
+        let err = |line| cli.run_line(line).unwrap_err().to_string();
+        assert_eq!(err(""), "no command specified, see 'help'");
+        assert_eq!(err("nope"), "unknown command 'nope', see 'help'");
+        assert_eq!(err("greet"), "missing required argument 'name'");
+        assert_eq!(err("greet bob alice"), "unexpected argument 'alice'");
+        assert_eq!(
+            err("greet bob --loud=yes"),
+            "unexpected argument '--loud=yes'"
+        );
+        assert_eq!(
+            err("greet bob --times"),
+            "option '--times' requires a value"
+        );
+        assert_eq!(
+            err("greet bob --times x"),
+            "invalid value 'x' of argument 'times': invalid digit found in string"
+        );
+        assert_eq!(err("greet 'bob"), "unterminated quote in the command line");
+        assert_eq!(err("fail"), "oops");
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn usage() {
+        let cli = cli();
+        assert_eq!(
+            cli.run(&["help"]).unwrap(),
+            "\
+Usage: test_cli <command> [<args>...]
+
+Testing the cli
+
+Commands:
+  greet  Greet somebody
+  fail
+  help   Show this message or the usage of the given command
+"
+        );
+        let usage = "\
+Usage: test_cli greet <name> [--times <times>] [--loud]
+
+Greet somebody
+
+Arguments:
+  <name>  Who to greet
+
+Options:
+  --times <times>  [default: 1]
+  --loud           Greet loudly
+";
+        assert_eq!(cli.run_line("help greet").unwrap(), usage);
+        assert_eq!(cli.run_line("greet --help").unwrap(), usage);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn expose() {
+        cli().expose("test_cli").unwrap();
+        let lua = crate::lua_state();
+        let res: String = lua.eval("return test_cli('greet bob')").unwrap();
+        assert_eq!(res, "hello bob");
+        let res: String = lua
+            .eval("return test_cli({'greet', 'bob', '--times', '2'})")
+            .unwrap();
+        assert_eq!(res, "hello bob, hello bob");
+        let e = lua.exec("test_cli('nope')").unwrap_err().to_string();
+        assert!(e.contains("unknown command 'nope'"), "{}", e);
+        lua.exec("test_cli = nil").unwrap();
+    }
+}
@@ -9,7 +9,10 @@ use std::ffi::c_void;
 use std::io::{self, Read, Write};
 use std::mem::forget;
 use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
-use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -17,9 +20,9 @@ use core::ptr::null_mut;
 
 use crate::error::{Error, TarantoolError};
 use crate::ffi::tarantool as ffi;
-use crate::fiber::{unpack_callback, Cond};
+use crate::fiber::{self, unpack_callback, Cond};
 
-const TIMEOUT_INFINITY: f64 = 365.0 * 86400.0 * 100.0;
+pub(crate) const TIMEOUT_INFINITY: f64 = 365.0 * 86400.0 * 100.0;
 
 /// Uses CoIO main loop to poll read/write events from wrapped socket
 pub struct CoIOStream {
@@ -63,6 +66,23 @@ impl CoIOStream {
         })
     }
 
+    /// Connect to a unix domain socket at `path`.
+    ///
+    /// The connection is established without blocking the event loop, the
+    /// current fiber yields while the listener's backlog is full.
+    pub fn connect_unix<P: AsRef<Path>>(path: P) -> Result<CoIOStream, io::Error> {
+        connect_unix(path.as_ref(), None)
+    }
+
+    /// Connect to a unix domain socket at `path` with a timeout, see
+    /// [`CoIOStream::connect_unix`].
+    pub fn connect_unix_timeout<P: AsRef<Path>>(
+        path: P,
+        timeout: Duration,
+    ) -> Result<CoIOStream, io::Error> {
+        connect_unix(path.as_ref(), Some(timeout))
+    }
+
     /// Pull some bytes from this source into the specified buffer. Returns how many bytes were read or 0 on timeout.
     pub fn read_with_timeout(
         &mut self,
@@ -118,6 +138,86 @@ impl Drop for CoIOStream {
     }
 }
 
+/// The interval between the connection attempts while the backlog of the unix
+/// socket listener is full.
+const UNIX_CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+fn connect_unix(path: &Path, timeout: Option<Duration>) -> Result<CoIOStream, io::Error> {
+    let deadline = timeout.map(|timeout| fiber::clock().saturating_add(timeout));
+    let remaining = || match deadline {
+        Some(deadline) => deadline.duration_since(fiber::clock()),
+        None => Duration::from_secs_f64(TIMEOUT_INFINITY),
+    };
+
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as _;
+    let path = path.as_os_str().as_bytes();
+    if path.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path must be shorter than SUN_LEN",
+        ));
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(path) {
+        *dst = *src as _;
+    }
+    let addr_len = std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Closes the socket on error.
+    let stream = CoIOStream::new(unsafe { UnixStream::from_raw_fd(fd) })?;
+
+    loop {
+        let rc = unsafe {
+            libc::connect(
+                stream.fd,
+                &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+                addr_len,
+            )
+        };
+        if rc == 0 {
+            return Ok(stream);
+        }
+        let e = io::Error::last_os_error();
+        match e.raw_os_error() {
+            Some(libc::EINTR) => continue,
+            // The backlog is full, the connection must be retried.
+            Some(libc::EAGAIN) => {
+                let remaining = remaining();
+                if remaining.is_zero() {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                fiber::sleep(remaining.min(UNIX_CONNECT_RETRY_INTERVAL));
+            }
+            Some(libc::EINPROGRESS) => {
+                coio_wait(stream.fd, ffi::CoIOFlags::WRITE, remaining().as_secs_f64())?;
+                let mut error: libc::c_int = 0;
+                let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+                let rc = unsafe {
+                    libc::getsockopt(
+                        stream.fd,
+                        libc::SOL_SOCKET,
+                        libc::SO_ERROR,
+                        &mut error as *mut libc::c_int as *mut c_void,
+                        &mut len,
+                    )
+                };
+                if rc < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if error != 0 {
+                    return Err(io::Error::from_raw_os_error(error));
+                }
+                return Ok(stream);
+            }
+            _ => return Err(e),
+        }
+    }
+}
+
 /// Uses CoIO main loop to poll incoming connections from wrapped socket listener
 pub struct CoIOListener {
     inner: TcpListener,
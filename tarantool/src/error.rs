@@ -220,6 +220,20 @@ where
     }
 }
 
+impl From<crate::fiber::WaitError> for Error {
+    #[inline]
+    #[track_caller]
+    fn from(e: crate::fiber::WaitError) -> Self {
+        use crate::fiber::WaitError;
+        match e {
+            WaitError::Timeout => BoxError::new(TarantoolErrorCode::Timeout, "timeout").into(),
+            WaitError::Cancelled => {
+                BoxError::new(TarantoolErrorCode::ProcLua, "fiber is cancelled").into()
+            }
+        }
+    }
+}
+
 impl From<std::string::FromUtf8Error> for Error {
     #[inline(always)]
     fn from(error: std::string::FromUtf8Error) -> Self {
@@ -291,6 +305,25 @@ impl BoxError {
         }
     }
 
+    /// Adds a custom payload field to the error. The fields are transmitted
+    /// to the remote clients along with the error and are available in lua
+    /// as `error.<key>`.
+    ///
+    /// **NOTE**: the payload fields are only supported since tarantool 2.10.
+    #[inline]
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<rmpv::Value>) -> Self {
+        self.fields
+            .insert(key.into().into_boxed_str(), value.into());
+        self
+    }
+
+    /// Sets the error which caused this one.
+    #[inline]
+    pub fn with_cause(mut self, cause: BoxError) -> Self {
+        self.cause = Some(Box::new(cause));
+        self
+    }
+
     /// Tries to get the information about the last API call error. If error was not set
     /// returns `Ok(())`
     #[inline]
@@ -347,11 +380,72 @@ impl BoxError {
         Self::maybe_last().err().unwrap()
     }
 
+    /// Get the information about the last API call error including the
+    /// [`cause`](Self::cause) chain and the custom payload
+    /// [`fields`](Self::fields), which [`Self::last`] doesn't retrieve.
+    ///
+    /// This is slower than [`Self::last`], because it goes through lua.
+    ///
+    /// Returns `None` if the error was not set.
+    pub fn last_with_details() -> Option<Self> {
+        let lua = crate::lua_state();
+        let res: crate::Result<Option<tlua::AnyLuaString>> =
+            lua.eval(LAST_ERROR_AS_MP_ERROR_LUA).map_err(Into::into);
+        let data = match res {
+            Ok(Some(data)) => data,
+            Ok(None) => return None,
+            Err(e) => {
+                crate::say_verbose!("failed getting last error details: {}", e);
+                return Self::maybe_last().err();
+            }
+        };
+        let res = crate::network::protocol::codec::decode_extended_error(
+            &mut std::io::Cursor::new(data.as_bytes()),
+        );
+        match res {
+            Ok(Some(error)) => Some(error),
+            Ok(None) => Self::maybe_last().err(),
+            Err(e) => {
+                crate::say_verbose!("failed decoding last error details: {}", e);
+                Self::maybe_last().err()
+            }
+        }
+    }
+
     /// Set `self` as the last API call error.
     /// Useful when returning errors from stored prcoedures.
+    ///
+    /// If the error has a [`cause`](Self::cause) or custom payload
+    /// [`fields`](Self::fields), those are also set, so that they reach the
+    /// remote clients intact. If that fails, the reason of the failure is set
+    /// as the last error instead, use [`Self::try_set_last`] to handle it
+    /// explicitly.
     #[inline(always)]
     #[track_caller]
     pub fn set_last(&self) {
+        if let Err(e) = self.try_set_last() {
+            e.into_box_error().set_last_via_c_api();
+        }
+    }
+
+    /// Set `self` along with its [`cause`](Self::cause) and custom payload
+    /// [`fields`](Self::fields) as the last API call error.
+    ///
+    /// Returns an error if the details couldn't be set, in which case the last
+    /// error is left unchanged.
+    #[inline]
+    #[track_caller]
+    pub fn try_set_last(&self) -> crate::Result<()> {
+        if self.cause.is_some() || !self.fields.is_empty() {
+            return self.set_last_via_lua();
+        }
+        self.set_last_via_c_api();
+        Ok(())
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    fn set_last_via_c_api(&self) {
         let mut loc = None;
         if let Some(f) = self.file() {
             debug_assert!(self.line().is_some());
@@ -416,6 +510,115 @@ impl BoxError {
     pub fn fields(&self) -> &HashMap<Box<str>, rmpv::Value> {
         &self.fields
     }
+
+    /// Sets the error with its causes and payload fields as the last error by
+    /// passing it to lua via the MP_ERROR msgpack extension.
+    fn set_last_via_lua(&self) -> crate::Result<()> {
+        let mut data = vec![];
+        crate::network::protocol::codec::encode_extended_error(&mut data, self)?;
+        let mut ext = Vec::with_capacity(data.len() + 6);
+        rmp::encode::write_ext_meta(&mut ext, data.len() as _, MP_ERROR_EXT_TYPE)?;
+        ext.extend_from_slice(&data);
+        crate::lua_state()
+            .exec_with(
+                "local err = require('msgpack').decode(...)
+                box.error.set(err)",
+                tlua::AnyLuaString(ext),
+            )
+            .map_err(LuaError::from)?;
+        Ok(())
+    }
+}
+
+/// Msgpack extension type of the errors (MP_ERROR).
+///
+/// See enum mp_extension_type in \<tarantool>/src/lib/core/mp_extension_types.h
+const MP_ERROR_EXT_TYPE: i8 = 3;
+
+/// Converts the last error with its causes into the extended error info format
+/// (see [`crate::network::protocol::codec::decode_extended_error`]).
+const LAST_ERROR_AS_MP_ERROR_LUA: &str = r#"
+local err = box.error.last()
+if err == nil then
+    return nil
+end
+local known = {
+    type = true, base_type = true, code = true, message = true, trace = true,
+    errno = true, prev = true, name = true,
+}
+local function map(t)
+    return setmetatable(t, { __serialize = 'map' })
+end
+local stack = {}
+while err ~= nil do
+    local unpacked = err:unpack()
+    local fields = map({})
+    for k, v in pairs(unpacked) do
+        if not known[k] then
+            fields[k] = v
+        end
+    end
+    local trace = unpacked.trace and unpacked.trace[1] or {}
+    table.insert(stack, map({
+        -- for custom errors `type` is the custom type
+        [0] = unpacked.base_type or unpacked.type,
+        [1] = trace.file,
+        [2] = trace.line,
+        [3] = err.message,
+        [4] = err.errno,
+        [5] = err.code,
+        [6] = fields,
+    }))
+    err = err.prev
+end
+return require('msgpack').encode(map({ [0] = stack }))
+"#;
+
+impl crate::msgpack::Encode for BoxError {
+    /// Encodes the error with its causes and payload fields as the MP_ERROR
+    /// msgpack extension.
+    fn encode(
+        &self,
+        w: &mut impl std::io::Write,
+        _context: &crate::msgpack::Context,
+    ) -> std::result::Result<(), crate::msgpack::EncodeError> {
+        let mut data = vec![];
+        crate::network::protocol::codec::encode_extended_error(&mut data, self)?;
+        rmp::encode::write_ext_meta(w, data.len() as _, MP_ERROR_EXT_TYPE)?;
+        w.write_all(&data)?;
+        Ok(())
+    }
+}
+
+impl<'de> crate::msgpack::Decode<'de> for BoxError {
+    /// Decodes the error with its causes and payload fields from the MP_ERROR
+    /// msgpack extension.
+    fn decode(
+        r: &mut &'de [u8],
+        _context: &crate::msgpack::Context,
+    ) -> std::result::Result<Self, crate::msgpack::DecodeError> {
+        use crate::msgpack::DecodeError;
+
+        let meta = rmp::decode::read_ext_meta(r).map_err(DecodeError::from_vre::<Self>)?;
+        if meta.typeid != MP_ERROR_EXT_TYPE {
+            return Err(DecodeError::new::<Self>(format!(
+                "expected MP_ERROR extension (type {}), got type {}",
+                MP_ERROR_EXT_TYPE, meta.typeid
+            )));
+        }
+        let size = meta.size as usize;
+        if r.len() < size {
+            return Err(DecodeError::new::<Self>("unexpected end of data"));
+        }
+        let (data, tail) = r.split_at(size);
+        *r = tail;
+        let mut data = std::io::Cursor::new(data);
+        match crate::network::protocol::codec::decode_extended_error(&mut data) {
+            Ok(Some(error)) => Ok(error),
+            Ok(None) => Err(DecodeError::new::<Self>("error stack is empty")),
+            Err(e) => Err(DecodeError::new::<Self>(e)),
+        }
+    }
 }
 
 impl Display for BoxError {
@@ -1053,6 +1256,54 @@ fn tarantool_error_doesnt_depend_on_link_error() {
     assert!(!format!("{}", err).is_empty());
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mp_error_encode() {
+        let e = BoxError::with_location(69105_u32, "too many leaves", "tree.rs", 13)
+            .with_field("leaves", 69105)
+            .with_cause(BoxError::with_location(
+                TarantoolErrorCode::Unknown,
+                "root cause",
+                "root.rs",
+                37,
+            ));
+        let data = crate::msgpack::encode(&e);
+        let mut rd = &data[..];
+        let value = rmpv::decode::read_value(&mut rd).unwrap();
+        assert!(rd.is_empty(), "trailing data: {:x?}", rd);
+        let rmpv::Value::Ext(MP_ERROR_EXT_TYPE, payload) = value else {
+            panic!("expected MP_ERROR, got {}", value);
+        };
+
+        let mut rd = &payload[..];
+        let value = rmpv::decode::read_value(&mut rd).unwrap();
+        assert!(rd.is_empty(), "trailing data: {:x?}", rd);
+        // {STACK: [{TYPE, FILE, LINE, MESSAGE, CODE, FIELDS}, {TYPE, FILE, LINE, MESSAGE, CODE}]}
+        let stack = &value.as_map().unwrap()[0];
+        assert_eq!(stack.0, rmpv::Value::from(0x00));
+        let stack = stack.1.as_array().unwrap();
+        assert_eq!(stack.len(), 2);
+        let node = stack[0].as_map().unwrap();
+        assert_eq!(node.len(), 6);
+        assert_eq!(node[3].1, rmpv::Value::from("too many leaves"));
+        assert_eq!(node[4].1, rmpv::Value::from(69105));
+        assert_eq!(
+            node[5].1,
+            rmpv::Value::Map(vec![("leaves".into(), 69105.into())])
+        );
+        let node = stack[1].as_map().unwrap();
+        assert_eq!(node.len(), 5);
+        assert_eq!(node[3].1, rmpv::Value::from("root cause"));
+        assert_eq!(
+            node[4].1,
+            rmpv::Value::from(TarantoolErrorCode::Unknown as u32)
+        );
+    }
+}
+
 #[cfg(feature = "internal_test")]
 mod tests {
     use super::*;
@@ -1178,6 +1429,124 @@ mod tests {
            // put ; inside both branches instead.
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn mp_error_roundtrip() {
+        let e = BoxError::with_location(69105_u32, "too many leaves", "tree.rs", 13)
+            .with_field("leaves", 69105)
+            .with_cause(BoxError::with_location(
+                TarantoolErrorCode::Unknown,
+                "root cause",
+                "root.rs",
+                37,
+            ));
+        let data = crate::msgpack::encode(&e);
+        assert_eq!(data[0], 0xc7); // ext 8
+        assert_eq!(data[2], MP_ERROR_EXT_TYPE as u8);
+
+        let e: BoxError = crate::msgpack::decode(&data).unwrap();
+        assert_eq!(e.error_code(), 69105);
+        assert_eq!(e.message(), "too many leaves");
+        assert_eq!(e.error_type(), "ClientError");
+        assert_eq!(e.file(), Some("tree.rs"));
+        assert_eq!(e.line(), Some(13));
+        assert_eq!(e.fields().len(), 1);
+        assert_eq!(e.fields()["leaves"], rmpv::Value::from(69105));
+        let cause = e.cause().unwrap();
+        assert_eq!(cause.error_code(), TarantoolErrorCode::Unknown as u32);
+        assert_eq!(cause.message(), "root cause");
+        assert_eq!(cause.file(), Some("root.rs"));
+        assert_eq!(cause.line(), Some(37));
+        assert!(cause.cause().is_none());
+
+        let e = crate::msgpack::decode::<BoxError>(b"\xd4\x01\x00").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "failed decoding tarantool::error::BoxError: expected MP_ERROR extension (type 3), got type 1"
+        );
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn mp_error_unknown_keys_and_bad_fields() {
+        use crate::network::protocol::codec::decode_extended_error;
+        use rmpv::Value;
+
+        let encode = |fields: Value| {
+            let node = Value::Map(vec![
+                (Value::from(0), Value::from("ClientError")),
+                (Value::from(1), Value::from("tree.rs")),
+                (Value::from(2), Value::from(13)),
+                (Value::from(3), Value::from("too many leaves")),
+                // Unknown keys with compound values are skipped.
+                (
+                    Value::from(0x7f),
+                    Value::Array(vec![Value::from(1), Value::Map(vec![])]),
+                ),
+                (Value::from(5), Value::from(69105)),
+                (Value::from(6), fields),
+            ]);
+            let error = Value::Map(vec![
+                (Value::from(0x7e), Value::from("unknown")),
+                (Value::from(0), Value::Array(vec![node])),
+            ]);
+            let mut data = vec![];
+            rmpv::encode::write_value(&mut data, &error).unwrap();
+            data
+        };
+
+        let data = encode(Value::Map(vec![(Value::from("leaves"), Value::from(1))]));
+        let e = decode_extended_error(&mut std::io::Cursor::new(&data))
+            .unwrap()
+            .unwrap();
+        assert_eq!(e.error_code(), 69105);
+        assert_eq!(e.message(), "too many leaves");
+        assert_eq!(e.fields()["leaves"], Value::from(1));
+
+        // Fields which can't be decoded are reported.
+        let data = encode(Value::Array(vec![Value::from(1)]));
+        decode_extended_error(&mut std::io::Cursor::new(&data)).unwrap_err();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn set_last_with_details() {
+        BoxError::new(69105_u32, "too many leaves")
+            .with_field("leaves", 69105)
+            .with_field("tree", "oak")
+            .with_cause(BoxError::new(TarantoolErrorCode::Unknown, "root cause"))
+            .try_set_last()
+            .unwrap();
+
+        let (leaves, tree, cause): (u32, String, String) = crate::lua_state()
+            .eval(
+                "local e = box.error.last()
+                return e.leaves, e.tree, e.prev.message",
+            )
+            .unwrap();
+        assert_eq!(leaves, 69105);
+        assert_eq!(tree, "oak");
+        assert_eq!(cause, "root cause");
+
+        let e = BoxError::last_with_details().unwrap();
+        assert_eq!(e.error_code(), 69105);
+        assert_eq!(e.message(), "too many leaves");
+        assert_eq!(e.fields().len(), 2);
+        assert_eq!(e.fields()["leaves"], rmpv::Value::from(69105));
+        assert_eq!(e.fields()["tree"], rmpv::Value::from("oak"));
+        let cause = e.cause().unwrap();
+        assert_eq!(cause.error_code(), TarantoolErrorCode::Unknown as u32);
+        assert_eq!(cause.message(), "root cause");
+        assert!(cause.fields().is_empty());
+        assert!(cause.cause().is_none());
+
+        // Without the details the error is set via the C api.
+        BoxError::new(69105_u32, "too many leaves").set_last();
+        let e = BoxError::last_with_details().unwrap();
+        assert!(e.fields().is_empty());
+        assert!(e.cause().is_none());
+
+        clear_error();
+        assert!(BoxError::last_with_details().is_none());
+    }
+
     #[crate::test(tarantool = "crate")]
     fn tarantool_error_use_after_free() {
         set_error!(TarantoolErrorCode::Unknown, "foo");
@@ -7,6 +7,9 @@ pub mod datetime;
 #[doc(hidden)]
 pub mod decimal;
 #[doc(hidden)]
+#[cfg(feature = "tls")]
+pub mod openssl;
+#[doc(hidden)]
 pub mod sql;
 #[doc(hidden)]
 pub mod tarantool;
@@ -41,6 +44,16 @@ pub fn has_fiber_channel() -> bool {
     }
 }
 
+/// Check whether the OpenSSL api required for the TLS connections in
+/// [`net_box`](crate::net_box) can be found in the current process.
+#[cfg(feature = "tls")]
+pub fn has_openssl() -> bool {
+    unsafe {
+        let name = crate::c_str!("SSL_new");
+        helper::tnt_internal_symbol::<*const ()>(name).is_some() || helper::has_dyn_symbol(name)
+    }
+}
+
 /// Check whether the current tarantool executable supports getting tuple fields
 /// by json pattern.
 /// If this function returns `false` then
@@ -119,3 +132,14 @@ pub unsafe fn has_fiber_id() -> bool {
     }
     RESULT.unwrap()
 }
+
+/// Check whether the current tarantool executable supports sending custom
+/// iproto packets to the client sessions.
+///
+/// If this function returns `false` then [`session::Packet::send`] will
+/// always return an error.
+///
+/// [`session::Packet::send`]: crate::session::Packet::send
+pub fn has_iproto_send() -> bool {
+    unsafe { helper::has_dyn_symbol(crate::c_str!("box_iproto_send")) }
+}
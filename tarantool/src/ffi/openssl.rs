@@ -0,0 +1,81 @@
+//! Subset of the OpenSSL api used by the TLS transport of
+//! [`net_box`](crate::net_box).
+//!
+//! The symbols are looked up at runtime in the tarantool executable (which is
+//! linked with OpenSSL) or in the libraries loaded into the process, see
+//! [`has_openssl`](crate::ffi::has_openssl).
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::os::raw::{c_char, c_int, c_long, c_ulong, c_void};
+
+#[repr(C)]
+pub struct SSL_CTX {
+    _unused: [u8; 0],
+}
+
+#[repr(C)]
+pub struct SSL {
+    _unused: [u8; 0],
+}
+
+#[repr(C)]
+pub struct SSL_METHOD {
+    _unused: [u8; 0],
+}
+
+pub const SSL_ERROR_NONE: c_int = 0;
+pub const SSL_ERROR_SSL: c_int = 1;
+pub const SSL_ERROR_WANT_READ: c_int = 2;
+pub const SSL_ERROR_WANT_WRITE: c_int = 3;
+pub const SSL_ERROR_SYSCALL: c_int = 5;
+pub const SSL_ERROR_ZERO_RETURN: c_int = 6;
+
+pub const SSL_FILETYPE_PEM: c_int = 1;
+
+pub const SSL_VERIFY_NONE: c_int = 0;
+pub const SSL_VERIFY_PEER: c_int = 1;
+
+pub const SSL_OP_NO_RENEGOTIATION: u64 = 0x40000000;
+
+pub const SSL_CTRL_SET_TLSEXT_HOSTNAME: c_int = 55;
+pub const TLSEXT_NAMETYPE_HOST_NAME: c_long = 0;
+
+crate::define_dlsym_reloc! {
+    pub fn TLS_client_method() -> *const SSL_METHOD;
+    pub fn TLS_server_method() -> *const SSL_METHOD;
+    pub fn SSL_CTX_new(method: *const SSL_METHOD) -> *mut SSL_CTX;
+    pub fn SSL_CTX_free(ctx: *mut SSL_CTX);
+    pub fn SSL_CTX_use_certificate_chain_file(ctx: *mut SSL_CTX, file: *const c_char) -> c_int;
+    pub fn SSL_CTX_use_PrivateKey_file(ctx: *mut SSL_CTX, file: *const c_char, kind: c_int) -> c_int;
+    pub fn SSL_CTX_check_private_key(ctx: *const SSL_CTX) -> c_int;
+    pub fn SSL_CTX_load_verify_locations(
+        ctx: *mut SSL_CTX,
+        ca_file: *const c_char,
+        ca_path: *const c_char,
+    ) -> c_int;
+    pub fn SSL_CTX_set_default_verify_paths(ctx: *mut SSL_CTX) -> c_int;
+    /// `callback` must be null, custom verification callbacks are not
+    /// supported.
+    pub fn SSL_CTX_set_verify(ctx: *mut SSL_CTX, mode: c_int, callback: *const c_void);
+    pub fn SSL_CTX_set_cipher_list(ctx: *mut SSL_CTX, list: *const c_char) -> c_int;
+    /// The options are `unsigned long` before OpenSSL 3.0, which is the same
+    /// as `u64` on the supported platforms.
+    pub fn SSL_CTX_set_options(ctx: *mut SSL_CTX, options: u64) -> u64;
+
+    pub fn SSL_new(ctx: *mut SSL_CTX) -> *mut SSL;
+    pub fn SSL_free(ssl: *mut SSL);
+    pub fn SSL_set_fd(ssl: *mut SSL, fd: c_int) -> c_int;
+    pub fn SSL_ctrl(ssl: *mut SSL, cmd: c_int, larg: c_long, parg: *mut c_void) -> c_long;
+    pub fn SSL_set1_host(ssl: *mut SSL, hostname: *const c_char) -> c_int;
+    pub fn SSL_connect(ssl: *mut SSL) -> c_int;
+    pub fn SSL_accept(ssl: *mut SSL) -> c_int;
+    pub fn SSL_read(ssl: *mut SSL, buf: *mut c_void, num: c_int) -> c_int;
+    pub fn SSL_write(ssl: *mut SSL, buf: *const c_void, num: c_int) -> c_int;
+    pub fn SSL_get_error(ssl: *const SSL, ret: c_int) -> c_int;
+    pub fn SSL_shutdown(ssl: *mut SSL) -> c_int;
+
+    pub fn ERR_get_error() -> c_ulong;
+    pub fn ERR_peek_last_error() -> c_ulong;
+    pub fn ERR_clear_error();
+    pub fn ERR_error_string_n(e: c_ulong, buf: *mut c_char, len: usize);
+}
@@ -24,6 +24,13 @@ extern "C" {
     pub fn clock_thread64() -> u64;
 }
 
+// Random.
+extern "C" {
+    /// Fills `buf` with `size` bytes from the system entropy source, which
+    /// is the same one used by `digest.urandom`.
+    pub fn random_bytes(buf: *mut c_char, size: usize);
+}
+
 // COIO.
 bitflags! {
     /// Event type(s) to wait. Can be `READ` or/and `WRITE`
@@ -737,6 +744,21 @@ extern "C" {
     pub fn box_session_push(data: *const c_char, data_end: *const c_char) -> c_int;
 }
 
+crate::define_dlsym_reloc! {
+    /// Sends a packet with the given header and body to the client of the
+    /// IPROTO session `sid`. The header and body must be encoded as msgpack
+    /// maps. Available since tarantool 2.11, see [`has_iproto_send`].
+    ///
+    /// [`has_iproto_send`]: crate::ffi::has_iproto_send
+    pub fn box_iproto_send(
+        sid: u64,
+        header: *const c_char,
+        header_end: *const c_char,
+        body: *const c_char,
+        body_end: *const c_char,
+    ) -> c_int;
+}
+
 // Sequence.
 extern "C" {
     pub fn box_sequence_next(seq_id: u32, result: *mut i64) -> c_int;
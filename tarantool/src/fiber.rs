@@ -20,6 +20,7 @@ use crate::tlua::{self as tlua, AsLua};
 use crate::unwrap_ok_or;
 use crate::{c_ptr, set_error};
 use ::va_list::VaList;
+pub use batch::{batch_mode, maybe_yield, set_batch_mode, BatchMode};
 pub use channel::Channel;
 pub use channel::RecvError;
 pub use channel::RecvTimeout;
@@ -29,8 +30,13 @@ pub use channel::TryRecvError;
 pub use channel::TrySendError;
 pub use csw::check_yield;
 pub use csw::YieldResult;
+pub use join_set::JoinSet;
 pub use mutex::Mutex;
 pub use r#async::block_on;
+pub use rwlock::RwLock;
+pub use scheduler::Scheduler;
+pub use semaphore::Semaphore;
+pub use stack::{check_stack, debug_assert_stack, remaining_stack};
 use std::cell::UnsafeCell;
 use std::ffi::CString;
 use std::future::Future;
@@ -40,13 +46,21 @@ use std::os::raw::c_void;
 use std::ptr::NonNull;
 use std::rc::Rc;
 use std::time::Duration;
+pub use wait_group::WaitGroup;
 
 pub mod r#async;
 pub mod safety;
 pub use safety::*;
+pub mod batch;
 pub mod channel;
 mod csw;
+pub mod join_set;
 pub mod mutex;
+pub mod rwlock;
+pub mod scheduler;
+pub mod semaphore;
+pub mod stack;
+pub mod wait_group;
 
 /// Type alias for a fiber id.
 pub type FiberId = u64;
@@ -398,12 +412,14 @@ impl<F> Builder<F> {
     /// Sets the size of the stack (in bytes) for the new fiber.
     ///
     /// This function performs some runtime tests to validate the given stack
-    /// size. If `stack_size` is invalid then [`Error::Tarantool`] will be
-    /// returned.
+    /// size. If `stack_size` is invalid (e.g. is out of the
+    /// [`stack::MIN_STACK_SIZE`]..=[`stack::MAX_STACK_SIZE`] range) then
+    /// [`Error::Tarantool`] will be returned.
     ///
     /// [`Error::Tarantool`]: crate::error::Error::Tarantool
     #[inline(always)]
     pub fn stack_size(mut self, stack_size: usize) -> crate::Result<Self> {
+        stack::validate_stack_size(stack_size)?;
         let mut attr = FiberAttr::new();
         attr.set_stack_size(stack_size)?;
         self.attr = Some(attr);
@@ -430,7 +446,7 @@ where
     /// happen when the join handle is dropped.
     #[inline(always)]
     pub fn start(self) -> crate::Result<JoinHandle<'f, T>> {
-        let (name, f, attr) = self.into_fiber_args();
+        let (name, f, attr) = self.into_fiber_args(false);
 
         let res = Fyber::spawn_and_yield(name, f, true, attr.as_ref())?;
         let Ok(jh) = res else {
@@ -457,10 +473,11 @@ where
     /// [`ffi::has_fiber_set_ctx`]: crate::ffi::has_fiber_set_ctx
     #[inline(always)]
     pub fn defer(self) -> crate::Result<JoinHandle<'f, T>> {
-        let (name, f, attr) = self.into_fiber_args();
-
         // SAFETY this is safe as long as we only call this from the tx thread.
-        if !unsafe { crate::ffi::has_fiber_set_ctx() } {
+        let is_lua = !unsafe { crate::ffi::has_fiber_set_ctx() };
+        let (name, f, attr) = self.into_fiber_args(is_lua);
+
+        if is_lua {
             return Fyber::spawn_lua(name, f, attr.as_ref());
         }
 
@@ -487,7 +504,7 @@ where
     /// [`ffi::has_fiber_set_ctx`]: crate::ffi::has_fiber_set_ctx
     #[inline(always)]
     pub fn defer_ffi(self) -> crate::Result<JoinHandle<'f, T>> {
-        let (name, f, attr) = self.into_fiber_args();
+        let (name, f, attr) = self.into_fiber_args(false);
 
         let res = Fyber::spawn_deferred(name, f, true, attr.as_ref())?;
         let Ok(jh) = res else {
@@ -505,17 +522,24 @@ where
     /// Consider using [`Self::defer`] instead.
     #[inline(always)]
     pub fn defer_lua(self) -> crate::Result<JoinHandle<'f, T>> {
-        let (name, f, attr) = self.into_fiber_args();
+        let (name, f, attr) = self.into_fiber_args(true);
 
         Fyber::spawn_lua(name, f, attr.as_ref())
     }
 
-    fn into_fiber_args(self) -> (String, F, Option<FiberAttr>) {
+    fn into_fiber_args(self, is_lua: bool) -> (String, impl FnOnce() -> T + 'f, Option<FiberAttr>) {
         #[rustfmt::skip]
         let Self { name, attr, f } = self;
 
         let name = name.unwrap_or_else(|| "<rust>".into());
 
+        // Lua fibers are always created with the default attributes.
+        let stack_size = match &attr {
+            Some(attr) if !is_lua => attr.stack_size(),
+            _ => stack::default_stack_size(),
+        };
+        let f = stack::tracked(stack_size, f);
+
         (name, f, attr)
     }
 }
@@ -541,7 +565,7 @@ where
     /// to the new fiber immediately.
     #[inline(always)]
     pub fn start_non_joinable(self) -> crate::Result<FiberId> {
-        let (name, f, attr) = self.into_fiber_args();
+        let (name, f, attr) = self.into_fiber_args(false);
 
         let res = Fyber::spawn_and_yield(name, f, false, attr.as_ref())?;
         let Err(id) = res else {
@@ -569,7 +593,7 @@ where
     /// [`ffi::has_fiber_set_ctx`]: crate::ffi::has_fiber_set_ctx
     #[inline(always)]
     pub fn defer_non_joinable(self) -> crate::Result<Option<FiberId>> {
-        let (name, f, attr) = self.into_fiber_args();
+        let (name, f, attr) = self.into_fiber_args(false);
 
         // SAFETY this is safe as long as we only call this from the tx thread.
         if !unsafe { crate::ffi::has_fiber_set_ctx() } {
@@ -1991,6 +2015,45 @@ impl Drop for Cond {
     }
 }
 
+/// Error returned from the blocking operations of fiber synchronization
+/// primitives like [`RwLock`], [`Semaphore`], [`WaitGroup`] and [`JoinSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum WaitError {
+    /// The deadline was reached before the operation could complete.
+    #[error("timed out")]
+    Timeout,
+    /// The current fiber was cancelled while waiting.
+    #[error("fiber is cancelled")]
+    Cancelled,
+}
+
+/// Wait on the `cond` until it's signalled, the optional `deadline` is reached
+/// or the current fiber is cancelled.
+///
+/// Spurious wakeups are possible, so the caller must check the condition
+/// they're waiting for in a loop.
+pub(crate) fn cond_wait_maybe_deadline(
+    cond: &Cond,
+    deadline: Option<Instant>,
+) -> Result<(), WaitError> {
+    let signalled = match deadline {
+        Some(deadline) => {
+            if clock() >= deadline {
+                return Err(WaitError::Timeout);
+            }
+            cond.wait_deadline(deadline)
+        }
+        None => cond.wait(),
+    };
+    if is_cancelled() {
+        return Err(WaitError::Cancelled);
+    }
+    if !signalled && deadline.is_some() {
+        return Err(WaitError::Timeout);
+    }
+    Ok(())
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Latch
 ////////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,297 @@
+//! Throttling of the long running background fibers.
+//!
+//! Tarantool fibers are scheduled cooperatively, so a fiber which does a lot
+//! of work without yielding (e.g. a batch job iterating over a large space)
+//! increases the latency of all of the other requests handled by the instance.
+//!
+//! A fiber can switch to the *batch mode* via [`set_batch_mode`] and call
+//! [`maybe_yield`] periodically, e.g. once per processed tuple. In the batch
+//! mode [`maybe_yield`] yields once the fiber has been running for longer than
+//! the current time slice and measures how long it takes the event loop to
+//! resume the fiber. If this latency grows beyond
+//! [`BatchMode::target_latency`], the time slice is shrunk and the fiber
+//! additionally pauses to give way to the other fibers. Once the latency goes
+//! back to normal the time slice is gradually restored.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::{self, BatchMode};
+//! use tarantool::index::IteratorType;
+//! use tarantool::space::Space;
+//!
+//! fiber::Builder::new()
+//!     .name("cleanup")
+//!     .func(|| {
+//!         fiber::set_batch_mode(Some(BatchMode::default()));
+//!         let space = Space::find("sessions").unwrap();
+//!         for tuple in space.select(IteratorType::All, &()).unwrap() {
+//!             // process the tuple ...
+//!             if fiber::maybe_yield().is_err() {
+//!                 // fiber was cancelled
+//!                 break;
+//!             }
+//!         }
+//!         fiber::set_batch_mode(None);
+//!     })
+//!     .start_non_joinable()
+//!     .unwrap();
+//! ```
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber::{self, FiberId};
+use crate::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// BatchMode
+////////////////////////////////////////////////////////////////////////////////
+
+/// Parameters of the batch mode, see [`set_batch_mode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BatchMode {
+    /// Maximal time the fiber runs without yielding when the instance isn't
+    /// loaded.
+    pub max_time_slice: Duration,
+    /// Minimal time the fiber runs without yielding no matter how loaded the
+    /// instance is.
+    pub min_time_slice: Duration,
+    /// Event loop latency (i.e. the time it takes to resume a yielded fiber)
+    /// above which the instance is considered loaded.
+    pub target_latency: Duration,
+    /// Maximal time the fiber pauses for in a single [`maybe_yield`] call
+    /// when the instance is loaded.
+    pub max_pause: Duration,
+}
+
+impl Default for BatchMode {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_time_slice: Duration::from_millis(10),
+            min_time_slice: Duration::from_micros(500),
+            target_latency: Duration::from_millis(2),
+            max_pause: Duration::from_millis(100),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// State
+////////////////////////////////////////////////////////////////////////////////
+
+struct State {
+    mode: BatchMode,
+    time_slice: Duration,
+    slice_start: Instant,
+}
+
+impl State {
+    fn new(mode: BatchMode) -> Self {
+        Self {
+            mode,
+            time_slice: mode.max_time_slice,
+            slice_start: Instant::now_accurate(),
+        }
+    }
+
+    /// Adjusts the time slice according to the observed event loop `latency`
+    /// and returns for how long the fiber should pause.
+    fn adapt(&mut self, latency: Duration) -> Duration {
+        let mode = &self.mode;
+        if latency > mode.target_latency {
+            self.time_slice = (self.time_slice / 2).max(mode.min_time_slice);
+            (latency - mode.target_latency).min(mode.max_pause)
+        } else {
+            self.time_slice = (self.time_slice * 2).min(mode.max_time_slice);
+            Duration::ZERO
+        }
+    }
+}
+
+thread_local! {
+    static STATES: RefCell<HashMap<FiberId, State>> = RefCell::new(HashMap::new());
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// API
+////////////////////////////////////////////////////////////////////////////////
+
+/// Enables the batch mode with the given parameters for the current fiber if
+/// `mode` is `Some`, or disables it otherwise. See the [module level
+/// documentation](self) for details.
+///
+/// Returns the previous batch mode parameters of the current fiber.
+///
+/// **NOTE**: the batch mode must be disabled before the fiber exits,
+/// otherwise the state is only cleaned up on the next call to this function
+/// from any fiber.
+pub fn set_batch_mode(mode: Option<BatchMode>) -> Option<BatchMode> {
+    let id = fiber::id();
+    STATES.with(|states| {
+        let mut states = states.borrow_mut();
+        states.retain(|&other, _| other == id || fiber::exists(other));
+        let old = match mode {
+            Some(mode) => states.insert(id, State::new(mode)),
+            None => states.remove(&id),
+        };
+        old.map(|state| state.mode)
+    })
+}
+
+/// Returns the batch mode parameters of the current fiber or `None` if the
+/// fiber is not in the batch mode.
+pub fn batch_mode() -> Option<BatchMode> {
+    let id = fiber::id();
+    STATES.with(|states| states.borrow().get(&id).map(|state| state.mode))
+}
+
+/// Yields if the current fiber is in the batch mode and its time slice is
+/// exhausted, pausing for longer if the instance is loaded. Does nothing if
+/// the fiber isn't in the batch mode.
+///
+/// Returns `Ok(true)` if the fiber yielded.
+///
+/// Returns an error if the fiber was cancelled.
+///
+/// > **Note:** this is a cancellation point (See also: [`fiber::is_cancelled`])
+pub fn maybe_yield() -> crate::Result<bool> {
+    let id = fiber::id();
+    let exhausted = STATES.with(|states| match states.borrow().get(&id) {
+        Some(state) => state.slice_start.elapsed() >= state.time_slice,
+        None => false,
+    });
+    if !exhausted {
+        return Ok(false);
+    }
+
+    let start = Instant::now_accurate();
+    fiber::reschedule();
+    let latency = start.elapsed();
+
+    let pause = STATES.with(|states| match states.borrow_mut().get_mut(&id) {
+        Some(state) => state.adapt(latency),
+        None => Duration::ZERO,
+    });
+    if !pause.is_zero() {
+        fiber::sleep(pause);
+    }
+
+    STATES.with(|states| {
+        if let Some(state) = states.borrow_mut().get_mut(&id) {
+            state.slice_start = Instant::now_accurate();
+        }
+    });
+
+    if fiber::is_cancelled() {
+        return Err(BoxError::new(TarantoolErrorCode::ProcLua, "fiber is cancelled").into());
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn busy_wait(duration: Duration) {
+        let start = Instant::now_accurate();
+        while start.elapsed() < duration {}
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn yields_after_time_slice() {
+        assert_eq!(batch_mode(), None);
+        assert!(!maybe_yield().unwrap());
+
+        let mode = BatchMode {
+            max_time_slice: Duration::from_millis(1),
+            min_time_slice: Duration::from_micros(100),
+            ..Default::default()
+        };
+        assert_eq!(set_batch_mode(Some(mode)), None);
+        assert_eq!(batch_mode(), Some(mode));
+
+        let other_fiber_runs = Rc::new(Cell::new(0));
+        let jh = fiber::start({
+            let other_fiber_runs = other_fiber_runs.clone();
+            move || {
+                while !fiber::is_cancelled() {
+                    other_fiber_runs.set(other_fiber_runs.get() + 1);
+                    fiber::reschedule();
+                }
+            }
+        });
+        let runs_before = other_fiber_runs.get();
+
+        let mut yields = 0;
+        let start = Instant::now_accurate();
+        while start.elapsed() < Duration::from_millis(20) {
+            busy_wait(Duration::from_micros(100));
+            if maybe_yield().unwrap() {
+                yields += 1;
+            }
+        }
+        assert!(yields > 0);
+        assert!(other_fiber_runs.get() > runs_before);
+
+        jh.cancel();
+        jh.join();
+
+        assert_eq!(set_batch_mode(None), Some(mode));
+        assert_eq!(batch_mode(), None);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn adapts_to_latency() {
+        let mode = BatchMode {
+            max_time_slice: Duration::from_millis(8),
+            min_time_slice: Duration::from_millis(1),
+            target_latency: Duration::from_millis(2),
+            max_pause: Duration::from_millis(5),
+        };
+        let mut state = State::new(mode);
+
+        assert_eq!(state.adapt(Duration::from_millis(12)), mode.max_pause);
+        assert_eq!(state.time_slice, Duration::from_millis(4));
+        assert_eq!(
+            state.adapt(Duration::from_millis(3)),
+            Duration::from_millis(1)
+        );
+        assert_eq!(state.time_slice, Duration::from_millis(2));
+        state.adapt(Duration::from_millis(3));
+        state.adapt(Duration::from_millis(3));
+        assert_eq!(state.time_slice, mode.min_time_slice);
+
+        assert_eq!(state.adapt(Duration::from_micros(100)), Duration::ZERO);
+        assert_eq!(state.time_slice, Duration::from_millis(2));
+        state.adapt(Duration::ZERO);
+        state.adapt(Duration::ZERO);
+        state.adapt(Duration::ZERO);
+        assert_eq!(state.time_slice, mode.max_time_slice);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancellation() {
+        let jh = fiber::start(|| {
+            set_batch_mode(Some(BatchMode {
+                max_time_slice: Duration::ZERO,
+                ..Default::default()
+            }));
+            let res = loop {
+                match maybe_yield() {
+                    Ok(_) => continue,
+                    Err(e) => break e,
+                }
+            };
+            set_batch_mode(None);
+            res
+        });
+        fiber::reschedule();
+        jh.cancel();
+        let e = jh.join();
+        assert_eq!(e.to_string(), "box error: ProcLua: fiber is cancelled");
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    fmt,
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::fiber::{self, Cond, FiberId, WaitError};
+use crate::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// JoinSet
+////////////////////////////////////////////////////////////////////////////////
+
+/// A collection of fibers spawned in the current cord which can be awaited in
+/// the order of their completion.
+///
+/// When the `JoinSet` is dropped, all of the fibers in it which are still
+/// running are cancelled (see [`fiber::cancel`]).
+///
+/// # Examples
+///
+/// ```no_run
+/// use tarantool::fiber::JoinSet;
+///
+/// let mut set = JoinSet::new();
+/// for i in 0..10 {
+///     set.spawn(move || i * 2).unwrap();
+/// }
+///
+/// let mut results = set.join_all().unwrap();
+/// results.sort();
+/// assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+/// ```
+///
+/// [`fiber::cancel`]: crate::fiber::cancel
+pub struct JoinSet<T> {
+    inner: Rc<Inner<T>>,
+}
+
+struct Inner<T> {
+    running: RefCell<HashSet<FiberId>>,
+    finished: RefCell<VecDeque<T>>,
+    cond: Cond,
+}
+
+impl<T: 'static> JoinSet<T> {
+    /// Creates a new empty `JoinSet`.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                running: RefCell::default(),
+                finished: RefCell::default(),
+                cond: Cond::new(),
+            }),
+        }
+    }
+
+    /// Spawns a new fiber executing `f` and adds it to the set.
+    ///
+    /// The current fiber performs a **yield** and the execution is transfered
+    /// to the new fiber immediately.
+    ///
+    /// Returns the new fiber's id.
+    #[inline(always)]
+    pub fn spawn<F>(&mut self, f: F) -> crate::Result<FiberId>
+    where
+        F: FnOnce() -> T + 'static,
+    {
+        self.spawn_with(fiber::Builder::new(), f)
+    }
+
+    /// Spawns a new fiber executing `f` and adds it to the set. The fiber is
+    /// configured with the given `builder`, which can be used to set the
+    /// fiber's name or stack size.
+    pub fn spawn_with<F>(
+        &mut self,
+        builder: fiber::Builder<fiber::NoFunc>,
+        f: F,
+    ) -> crate::Result<FiberId>
+    where
+        F: FnOnce() -> T + 'static,
+    {
+        let inner = self.inner.clone();
+        builder
+            .func(move || {
+                let id = fiber::id();
+                inner.running.borrow_mut().insert(id);
+                let res = f();
+                inner.running.borrow_mut().remove(&id);
+                inner.finished.borrow_mut().push_back(res);
+                inner.cond.broadcast();
+            })
+            .start_non_joinable()
+    }
+
+    /// Returns the number of fibers in the set which are still running.
+    #[inline(always)]
+    pub fn running(&self) -> usize {
+        self.inner.running.borrow().len()
+    }
+
+    /// Returns the number of fibers in the set, including the ones which have
+    /// finished but haven't been joined yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.running() + self.inner.finished.borrow().len()
+    }
+
+    /// Returns `true` if there are no fibers in the set.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Waits until one of the fibers in the set finishes and returns its
+    /// result.
+    ///
+    /// Returns `Ok(None)` if the set is empty.
+    #[inline(always)]
+    pub fn join_next(&mut self) -> Result<Option<T>, WaitError> {
+        self.join_next_maybe_deadline(None)
+    }
+
+    /// Same as [`Self::join_next`], but returns [`WaitError::Timeout`] if
+    /// none of the fibers finish in the given `timeout`.
+    #[inline(always)]
+    pub fn join_next_timeout(&mut self, timeout: Duration) -> Result<Option<T>, WaitError> {
+        self.join_next_maybe_deadline(Some(fiber::clock().saturating_add(timeout)))
+    }
+
+    /// Same as [`Self::join_next`], but returns [`WaitError::Timeout`] if
+    /// none of the fibers finish until the given `deadline`.
+    #[inline(always)]
+    pub fn join_next_deadline(&mut self, deadline: Instant) -> Result<Option<T>, WaitError> {
+        self.join_next_maybe_deadline(Some(deadline))
+    }
+
+    fn join_next_maybe_deadline(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<Option<T>, WaitError> {
+        loop {
+            if let Some(res) = self.inner.finished.borrow_mut().pop_front() {
+                return Ok(Some(res));
+            }
+            if self.inner.running.borrow().is_empty() {
+                return Ok(None);
+            }
+            fiber::cond_wait_maybe_deadline(&self.inner.cond, deadline)?;
+        }
+    }
+
+    /// Waits until all of the fibers in the set finish and returns their
+    /// results in the order of completion.
+    pub fn join_all(&mut self) -> Result<Vec<T>, WaitError> {
+        let mut results = Vec::with_capacity(self.len());
+        while let Some(res) = self.join_next()? {
+            results.push(res);
+        }
+        Ok(results)
+    }
+
+    /// Cancels all of the fibers in the set which are still running.
+    ///
+    /// Note that fiber cancellation is cooperative, so the fibers will only
+    /// stop at the next cancellation point (see [`fiber::is_cancelled`]).
+    ///
+    /// **Does NOT yield**.
+    ///
+    /// [`fiber::is_cancelled`]: crate::fiber::is_cancelled
+    pub fn abort_all(&mut self) {
+        for &id in self.inner.running.borrow().iter() {
+            fiber::cancel(id);
+        }
+    }
+}
+
+impl<T: 'static> Default for JoinSet<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for JoinSet<T> {
+    fn drop(&mut self) {
+        for &id in self.inner.running.borrow().iter() {
+            fiber::cancel(id);
+        }
+    }
+}
+
+impl<T> fmt::Debug for JoinSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinSet")
+            .field("running", &self.inner.running.borrow().len())
+            .field("finished", &self.inner.finished.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn completion_order() {
+        let mut set = JoinSet::new();
+        assert!(set.is_empty());
+        for i in [3, 1, 2] {
+            set.spawn(move || {
+                fiber::sleep(Duration::from_millis(i * 5));
+                i
+            })
+            .unwrap();
+        }
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.join_next().unwrap(), Some(1));
+        assert_eq!(set.join_all().unwrap(), [2, 3]);
+        assert_eq!(set.join_next().unwrap(), None);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn abort_on_drop() {
+        let cancelled = Rc::new(std::cell::Cell::new(false));
+        let mut set = JoinSet::new();
+        let cancelled_clone = cancelled.clone();
+        set.spawn(move || {
+            while !fiber::is_cancelled() {
+                fiber::sleep(Duration::from_millis(1));
+            }
+            cancelled_clone.set(true);
+        })
+        .unwrap();
+        assert_eq!(
+            set.join_next_timeout(Duration::from_millis(5)).unwrap_err(),
+            WaitError::Timeout
+        );
+        drop(set);
+        fiber::sleep(Duration::from_millis(5));
+        assert!(cancelled.get());
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+use crate::fiber::{self, Cond, WaitError};
+use crate::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// RwLock
+////////////////////////////////////////////////////////////////////////////////
+
+/// A reader-writer lock for fibers.
+///
+/// This type of lock allows a number of readers or at most one writer at any
+/// point in time. The write portion of this lock typically allows modification
+/// of the underlying data (exclusive access) and the read portion of this lock
+/// typically allows for read-only access (shared access).
+///
+/// Writers are preferred over readers: once a fiber starts waiting for write
+/// access, no new readers can acquire the lock until the writer is done. This
+/// way writers are never starved.
+///
+/// All of the blocking methods of this type return [`WaitError::Cancelled`] if
+/// the current fiber is cancelled while waiting for the lock.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tarantool::fiber::RwLock;
+///
+/// let lock = RwLock::new(5);
+/// {
+///     let r1 = lock.read().unwrap();
+///     let r2 = lock.read().unwrap();
+///     assert_eq!(*r1 + *r2, 10);
+/// }
+/// {
+///     let mut w = lock.write().unwrap();
+///     *w += 1;
+/// }
+/// assert_eq!(*lock.read().unwrap(), 6);
+/// ```
+pub struct RwLock<T: ?Sized> {
+    /// Number of active readers, or [`WRITE_LOCKED`] if locked for writing.
+    state: Cell<usize>,
+    /// Number of fibers waiting for write access.
+    writers_waiting: Cell<usize>,
+    cond: Cond,
+    data: UnsafeCell<T>,
+}
+
+const WRITE_LOCKED: usize = usize::MAX;
+
+impl<T: ?Sized> RwLock<T> {
+    /// Creates a new instance of an `RwLock<T>` which is unlocked.
+    pub fn new(t: T) -> RwLock<T>
+    where
+        T: Sized,
+    {
+        RwLock {
+            state: Cell::new(0),
+            writers_waiting: Cell::new(0),
+            cond: Cond::new(),
+            data: UnsafeCell::new(t),
+        }
+    }
+
+    /// Locks this rwlock with shared read access, yielding the current fiber
+    /// until it can be acquired.
+    ///
+    /// Returns an RAII guard which will release this fiber's shared access
+    /// once it is dropped.
+    #[inline(always)]
+    pub fn read(&self) -> Result<RwLockReadGuard<'_, T>, WaitError> {
+        self.read_maybe_deadline(None)
+    }
+
+    /// Same as [`Self::read`], but returns [`WaitError::Timeout`] if the lock
+    /// could not be acquired in the given `timeout`.
+    #[inline(always)]
+    pub fn read_timeout(&self, timeout: Duration) -> Result<RwLockReadGuard<'_, T>, WaitError> {
+        self.read_maybe_deadline(Some(fiber::clock().saturating_add(timeout)))
+    }
+
+    /// Same as [`Self::read`], but returns [`WaitError::Timeout`] if the lock
+    /// could not be acquired until the given `deadline`.
+    #[inline(always)]
+    pub fn read_deadline(&self, deadline: Instant) -> Result<RwLockReadGuard<'_, T>, WaitError> {
+        self.read_maybe_deadline(Some(deadline))
+    }
+
+    /// Attempts to acquire this rwlock with shared read access.
+    ///
+    /// Returns `None` if the lock could not be acquired at this time.
+    ///
+    /// This function does not yield.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        if !self.can_read() {
+            return None;
+        }
+        self.state.set(self.state.get() + 1);
+        Some(RwLockReadGuard { lock: self })
+    }
+
+    fn read_maybe_deadline(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<RwLockReadGuard<'_, T>, WaitError> {
+        loop {
+            if let Some(guard) = self.try_read() {
+                return Ok(guard);
+            }
+            fiber::cond_wait_maybe_deadline(&self.cond, deadline)?;
+        }
+    }
+
+    #[inline(always)]
+    fn can_read(&self) -> bool {
+        self.state.get() != WRITE_LOCKED && self.writers_waiting.get() == 0
+    }
+
+    /// Locks this rwlock with exclusive write access, yielding the current
+    /// fiber until it can be acquired.
+    ///
+    /// Returns an RAII guard which will drop the write access of this rwlock
+    /// when dropped.
+    #[inline(always)]
+    pub fn write(&self) -> Result<RwLockWriteGuard<'_, T>, WaitError> {
+        self.write_maybe_deadline(None)
+    }
+
+    /// Same as [`Self::write`], but returns [`WaitError::Timeout`] if the lock
+    /// could not be acquired in the given `timeout`.
+    #[inline(always)]
+    pub fn write_timeout(&self, timeout: Duration) -> Result<RwLockWriteGuard<'_, T>, WaitError> {
+        self.write_maybe_deadline(Some(fiber::clock().saturating_add(timeout)))
+    }
+
+    /// Same as [`Self::write`], but returns [`WaitError::Timeout`] if the lock
+    /// could not be acquired until the given `deadline`.
+    #[inline(always)]
+    pub fn write_deadline(&self, deadline: Instant) -> Result<RwLockWriteGuard<'_, T>, WaitError> {
+        self.write_maybe_deadline(Some(deadline))
+    }
+
+    /// Attempts to lock this rwlock with exclusive write access.
+    ///
+    /// Returns `None` if the lock could not be acquired at this time.
+    ///
+    /// This function does not yield.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        if self.state.get() != 0 {
+            return None;
+        }
+        self.state.set(WRITE_LOCKED);
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    fn write_maybe_deadline(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<RwLockWriteGuard<'_, T>, WaitError> {
+        if let Some(guard) = self.try_write() {
+            return Ok(guard);
+        }
+
+        self.writers_waiting.set(self.writers_waiting.get() + 1);
+        let res = loop {
+            if self.state.get() == 0 {
+                self.state.set(WRITE_LOCKED);
+                break Ok(RwLockWriteGuard { lock: self });
+            }
+            if let Err(e) = fiber::cond_wait_maybe_deadline(&self.cond, deadline) {
+                break Err(e);
+            }
+        };
+        self.writers_waiting.set(self.writers_waiting.get() - 1);
+
+        if res.is_err() && self.writers_waiting.get() == 0 {
+            // Readers may have been blocked because of us.
+            self.cond.broadcast();
+        }
+        res
+    }
+
+    /// Returns the number of fibers currently holding the read lock.
+    #[inline(always)]
+    pub fn reader_count(&self) -> usize {
+        match self.state.get() {
+            WRITE_LOCKED => 0,
+            n => n,
+        }
+    }
+
+    /// Returns `true` if the lock is currently held for writing.
+    #[inline(always)]
+    pub fn is_write_locked(&self) -> bool {
+        self.state.get() == WRITE_LOCKED
+    }
+
+    /// Consumes this `RwLock`, returning the underlying data.
+    pub fn into_inner(self) -> T
+    where
+        T: Sized,
+    {
+        self.data.into_inner()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `RwLock` mutably, no actual locking needs to
+    /// take place -- the mutable borrow statically guarantees no locks exist.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    /// Creates a new instance of an `RwLock<T>` which is unlocked.
+    /// This is equivalent to [`RwLock::new`].
+    fn from(t: T) -> Self {
+        RwLock::new(t)
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    /// Creates a new `RwLock<T>`, with the `Default` value for T.
+    fn default() -> RwLock<T> {
+        RwLock::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("RwLock");
+        if self.is_write_locked() {
+            struct LockedPlaceholder;
+            impl fmt::Debug for LockedPlaceholder {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("<locked>")
+                }
+            }
+            d.field("data", &LockedPlaceholder);
+        } else {
+            d.field("data", &unsafe { &*self.data.get() });
+        }
+        d.finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// RwLockReadGuard
+////////////////////////////////////////////////////////////////////////////////
+
+/// RAII structure used to release the shared read access of a lock when
+/// dropped.
+///
+/// This structure is created by the [`read`] and [`try_read`] methods on
+/// [`RwLock`].
+///
+/// [`read`]: RwLock::read
+/// [`try_read`]: RwLock::try_read
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        let readers = self.lock.state.get() - 1;
+        self.lock.state.set(readers);
+        if readers == 0 {
+            self.lock.cond.broadcast();
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// RwLockWriteGuard
+////////////////////////////////////////////////////////////////////////////////
+
+/// RAII structure used to release the exclusive write access of a lock when
+/// dropped.
+///
+/// This structure is created by the [`write`] and [`try_write`] methods on
+/// [`RwLock`].
+///
+/// [`write`]: RwLock::write
+/// [`try_write`]: RwLock::try_write
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.set(0);
+        self.lock.cond.broadcast();
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::rc::Rc;
+
+    #[crate::test(tarantool = "crate")]
+    fn many_readers() {
+        let lock = RwLock::new(13);
+        let r1 = lock.read().unwrap();
+        let r2 = lock.try_read().unwrap();
+        assert_eq!(*r1 + *r2, 26);
+        assert_eq!(lock.reader_count(), 2);
+        assert!(lock.try_write().is_none());
+        drop(r1);
+        drop(r2);
+        assert_eq!(lock.reader_count(), 0);
+
+        let mut w = lock.try_write().unwrap();
+        *w = 37;
+        assert!(lock.is_write_locked());
+        assert!(lock.try_read().is_none());
+        drop(w);
+
+        assert_eq!(*lock.read().unwrap(), 37);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn writer_waits_for_readers() {
+        let lock = Rc::new(RwLock::new(Vec::<i32>::new()));
+        let r = lock.read().unwrap();
+
+        let lock_clone = lock.clone();
+        let jh = fiber::start(move || {
+            lock_clone.write().unwrap().push(1);
+        });
+
+        // Writer is waiting, new readers are not allowed.
+        assert!(lock.try_read().is_none());
+        assert!(r.is_empty());
+        drop(r);
+
+        jh.join();
+        assert_eq!(*lock.read().unwrap(), [1]);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn timeout_and_cancel() {
+        let lock = Rc::new(RwLock::new(()));
+        let w = lock.write().unwrap();
+        assert_eq!(
+            lock.read_timeout(Duration::from_millis(10)).unwrap_err(),
+            WaitError::Timeout
+        );
+        assert_eq!(
+            lock.write_timeout(Duration::ZERO).unwrap_err(),
+            WaitError::Timeout
+        );
+
+        let lock_clone = lock.clone();
+        let jh = fiber::start(move || lock_clone.read().map(drop));
+        jh.cancel();
+        assert_eq!(jh.join(), Err(WaitError::Cancelled));
+
+        drop(w);
+        assert!(lock.try_read().is_some());
+    }
+}
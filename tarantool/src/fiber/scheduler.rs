@@ -0,0 +1,680 @@
+//! Background scheduler for periodic jobs.
+//!
+//! A [`Scheduler`] runs each registered job in a dedicated fiber according to
+//! the job's [`Schedule`]: at a fixed interval, by a [cron expression](Cron)
+//! or once at a given moment. Panics and errors returned from the jobs are
+//! isolated and recorded in the job's [`JobStats`], so a failing job doesn't
+//! affect the other jobs or its own next runs. A job which keeps failing can
+//! be slowed down with [`JobBuilder::backoff`].
+//!
+//! The runs of a job never overlap: the next run is scheduled only after the
+//! current one finishes, the cron runs missed in the meantime are skipped.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tarantool::fiber::scheduler::{Schedule, Scheduler};
+//!
+//! let scheduler = Scheduler::new();
+//!
+//! let cleanup = scheduler
+//!     .job("cleanup")
+//!     .every(Duration::from_secs(60))
+//!     .jitter(Duration::from_secs(5))
+//!     .start(|| -> Result<(), String> {
+//!         // remove the expired records
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//!
+//! scheduler
+//!     .job("report")
+//!     .schedule(Schedule::cron("0 3 * * *").unwrap())
+//!     .start(|| -> Result<(), String> {
+//!         // send the daily report
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//!
+//! // ...
+//!
+//! println!("cleanup ran {} times", cleanup.stats().runs);
+//!
+//! // Wait for the jobs which are currently running to finish and stop the
+//! // scheduler.
+//! scheduler.shutdown(Duration::from_secs(10)).unwrap();
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Display};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber::{self, Cond, FiberId, WaitError};
+
+mod cron;
+pub use cron::{Cron, CronParseError};
+
+////////////////////////////////////////////////////////////////////////////////
+// Scheduler
+////////////////////////////////////////////////////////////////////////////////
+
+/// A collection of periodic jobs running in the background fibers.
+///
+/// When the `Scheduler` is dropped, it stops scheduling the new runs of the
+/// jobs (like [`Scheduler::shutdown`], but without waiting).
+///
+/// See the [module level documentation](self) for examples.
+pub struct Scheduler {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    jobs: RefCell<Vec<JobHandle>>,
+    /// Number of the job fibers which haven't exited yet.
+    alive: Cell<usize>,
+    shutting_down: Cell<bool>,
+    /// Signalled when a job fiber exits.
+    exited: Cond,
+}
+
+impl Inner {
+    /// Stops scheduling the new runs of the jobs and wakes up the sleeping
+    /// job fibers so that they exit.
+    fn start_shutdown(&self) {
+        self.shutting_down.set(true);
+        for job in self.jobs.borrow().iter() {
+            job.state.wakeup.broadcast();
+        }
+    }
+}
+
+impl Scheduler {
+    /// Creates a new scheduler with no jobs.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                jobs: RefCell::default(),
+                alive: Cell::new(0),
+                shutting_down: Cell::new(false),
+                exited: Cond::new(),
+            }),
+        }
+    }
+
+    /// Returns a builder for a new job named `name`. The name is also used as
+    /// the name of the job's fiber.
+    #[inline(always)]
+    pub fn job(&self, name: impl Into<String>) -> JobBuilder<'_> {
+        JobBuilder {
+            scheduler: self,
+            name: name.into(),
+            schedule: None,
+            jitter: Duration::ZERO,
+            backoff: None,
+        }
+    }
+
+    /// Returns handles to all of the jobs registered in the scheduler.
+    #[inline]
+    pub fn jobs(&self) -> Vec<JobHandle> {
+        self.inner.jobs.borrow().clone()
+    }
+
+    /// Returns `true` if [`Scheduler::shutdown`] was called.
+    #[inline(always)]
+    pub fn is_shutting_down(&self) -> bool {
+        self.inner.shutting_down.get()
+    }
+
+    /// Stops scheduling the new runs of the jobs and waits until the runs
+    /// which are already in progress finish.
+    ///
+    /// Returns [`WaitError::Timeout`] if the jobs don't finish in the given
+    /// `timeout`. Note that the jobs aren't cancelled in this case and
+    /// will exit once their current run is finished.
+    pub fn shutdown(&self, timeout: Duration) -> Result<(), WaitError> {
+        self.inner.start_shutdown();
+        let deadline = fiber::clock().saturating_add(timeout);
+        while self.inner.alive.get() > 0 {
+            fiber::cond_wait_maybe_deadline(&self.inner.exited, Some(deadline))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Scheduler {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.inner.start_shutdown();
+    }
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("jobs", &self.inner.jobs.borrow())
+            .field("shutting_down", &self.inner.shutting_down.get())
+            .finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Schedule
+////////////////////////////////////////////////////////////////////////////////
+
+/// Defines when a job should run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run repeatedly with the given interval between the end of a run and
+    /// the start of the next one. The first run happens after the interval
+    /// passes.
+    Every(Duration),
+    /// Run each time the wall clock time matches the cron expression.
+    Cron(Cron),
+    /// Run once at the given moment, or immediately if it has already passed.
+    At(SystemTime),
+}
+
+impl Schedule {
+    /// Parses a cron expression, see [`Cron`] for the syntax.
+    #[inline(always)]
+    pub fn cron(expr: &str) -> Result<Self, CronParseError> {
+        Cron::parse(expr).map(Self::Cron)
+    }
+
+    /// Returns the delay until the next run or `None` if the job shouldn't
+    /// run anymore.
+    fn next_delay(&self, runs: u64) -> Option<Duration> {
+        match self {
+            Self::Every(interval) => Some(*interval),
+            Self::Cron(cron) => {
+                let now = SystemTime::now();
+                let next = cron.next_after(now)?;
+                Some(next.duration_since(now).unwrap_or_default())
+            }
+            Self::At(_) if runs > 0 => None,
+            Self::At(at) => Some(at.duration_since(SystemTime::now()).unwrap_or_default()),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// JobBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// A builder for a scheduler job, see [`Scheduler::job`].
+#[must_use = "the job isn't started until `start` is called"]
+pub struct JobBuilder<'a> {
+    scheduler: &'a Scheduler,
+    name: String,
+    schedule: Option<Schedule>,
+    jitter: Duration,
+    backoff: Option<(Duration, Duration)>,
+}
+
+impl JobBuilder<'_> {
+    /// Sets the schedule of the job.
+    #[inline(always)]
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Same as `.schedule(Schedule::Every(interval))`.
+    #[inline(always)]
+    pub fn every(self, interval: Duration) -> Self {
+        self.schedule(Schedule::Every(interval))
+    }
+
+    /// Same as `.schedule(Schedule::At(at))`.
+    #[inline(always)]
+    pub fn at(self, at: SystemTime) -> Self {
+        self.schedule(Schedule::At(at))
+    }
+
+    /// Sets the maximum random delay added to each run of the job. This
+    /// helps to avoid running the same jobs on different instances at
+    /// exactly the same time.
+    #[inline(always)]
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the delay after a failed run: the first retry happens no sooner
+    /// than after `initial`, and the delay is doubled after each consecutive
+    /// failure up to `max`. If the job's schedule defines a longer delay, it
+    /// is used instead. The delay is reset after a successful run.
+    ///
+    /// By default the failed runs don't affect the schedule.
+    #[inline(always)]
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Some((initial, max.max(initial)));
+        self
+    }
+
+    /// Starts the job's fiber, which will call `f` according to the job's
+    /// schedule.
+    ///
+    /// Returns an error if the schedule wasn't set or if the scheduler is
+    /// shutting down.
+    pub fn start<F, E>(self, mut f: F) -> crate::Result<JobHandle>
+    where
+        F: FnMut() -> Result<(), E> + 'static,
+        E: Display,
+    {
+        let Some(schedule) = self.schedule else {
+            return Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                format!("schedule of job '{}' is not specified", self.name),
+            )
+            .into());
+        };
+        let inner = self.scheduler.inner.clone();
+        if inner.shutting_down.get() {
+            return Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                "scheduler is shutting down",
+            )
+            .into());
+        }
+
+        let job = JobHandle {
+            state: Rc::new(JobState {
+                name: self.name,
+                stats: RefCell::default(),
+                running: Cell::new(false),
+                cancelled: Cell::new(false),
+                finished: Cell::new(false),
+                fiber_id: Cell::new(None),
+                wakeup: Cond::new(),
+            }),
+        };
+        let timing = Timing {
+            jitter: self.jitter,
+            backoff: self.backoff,
+        };
+        let state = job.state.clone();
+        inner.alive.set(inner.alive.get() + 1);
+        inner.jobs.borrow_mut().push(job.clone());
+
+        let res = fiber::Builder::new()
+            .name(state.name.clone())
+            .func({
+                let inner = inner.clone();
+                move || {
+                    run_job(&inner, &state, &schedule, timing, &mut f);
+                    state.finished.set(true);
+                    inner.alive.set(inner.alive.get() - 1);
+                    inner.exited.broadcast();
+                }
+            })
+            .start_non_joinable();
+        match res {
+            Ok(id) => {
+                job.state.fiber_id.set(Some(id));
+                Ok(job)
+            }
+            Err(e) => {
+                inner.alive.set(inner.alive.get() - 1);
+                inner
+                    .jobs
+                    .borrow_mut()
+                    .retain(|j| !Rc::ptr_eq(&j.state, &job.state));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Delays of a job in addition to its schedule.
+#[derive(Clone, Copy)]
+struct Timing {
+    jitter: Duration,
+    backoff: Option<(Duration, Duration)>,
+}
+
+impl Timing {
+    /// Returns the minimal delay before the next run after
+    /// `consecutive_failures` failed runs.
+    fn backoff_delay(&self, consecutive_failures: u64) -> Duration {
+        let Some((initial, max)) = self.backoff else {
+            return Duration::ZERO;
+        };
+        if consecutive_failures == 0 {
+            return Duration::ZERO;
+        }
+        let exponent = (consecutive_failures - 1).min(31) as u32;
+        initial.saturating_mul(1 << exponent).min(max)
+    }
+}
+
+/// The body of a job's fiber.
+fn run_job<F, E>(inner: &Inner, state: &JobState, schedule: &Schedule, timing: Timing, f: &mut F)
+where
+    F: FnMut() -> Result<(), E>,
+    E: Display,
+{
+    let should_stop = || inner.shutting_down.get() || state.cancelled.get();
+    loop {
+        let (runs, consecutive_failures) = {
+            let stats = state.stats.borrow();
+            (stats.runs, stats.consecutive_failures)
+        };
+        let Some(delay) = schedule.next_delay(runs) else {
+            return;
+        };
+        let delay = delay
+            .max(timing.backoff_delay(consecutive_failures))
+            .saturating_add(random_duration(timing.jitter));
+        let deadline = fiber::clock().saturating_add(delay);
+        state.stats.borrow_mut().next_run = Some(SystemTime::now() + delay);
+        loop {
+            if should_stop() {
+                return;
+            }
+            match fiber::cond_wait_maybe_deadline(&state.wakeup, Some(deadline)) {
+                Ok(()) => continue,
+                Err(WaitError::Timeout) => break,
+                Err(WaitError::Cancelled) => return,
+            }
+        }
+        if should_stop() {
+            return;
+        }
+
+        let started_at = SystemTime::now();
+        let start = fiber::clock();
+        state.running.set(true);
+        let res = panic::catch_unwind(AssertUnwindSafe(&mut *f));
+        state.running.set(false);
+        let error = match res {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(payload) => {
+                let message = crate::util::panic_message(&*payload);
+                Some(format!("job panicked: {}", message))
+            }
+        };
+
+        let duration = fiber::clock().duration_since(start);
+        let mut stats = state.stats.borrow_mut();
+        stats.runs += 1;
+        stats.last_run = Some(started_at);
+        stats.last_duration = Some(duration);
+        stats.total_duration = stats.total_duration.saturating_add(duration);
+        stats.next_run = None;
+        if let Some(error) = error {
+            crate::say_warn!("job '{}' failed: {}", state.name, error);
+            stats.failures += 1;
+            stats.consecutive_failures += 1;
+            stats.last_error = Some(error);
+        } else {
+            stats.consecutive_failures = 0;
+        }
+    }
+}
+
+/// Returns a pseudo-random duration in the range `0..=max`.
+fn random_duration(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    let max_nanos = max.as_nanos().min(u64::MAX as _) as u64;
+    Duration::from_nanos(crate::random::range(0..max_nanos.saturating_add(1)))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// JobHandle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle to a job registered in a [`Scheduler`].
+#[derive(Clone)]
+pub struct JobHandle {
+    state: Rc<JobState>,
+}
+
+struct JobState {
+    name: String,
+    stats: RefCell<JobStats>,
+    running: Cell<bool>,
+    cancelled: Cell<bool>,
+    finished: Cell<bool>,
+    fiber_id: Cell<Option<FiberId>>,
+    /// Signalled when the job's fiber needs to wake up before the next run,
+    /// e.g. on cancellation or shutdown.
+    wakeup: Cond,
+}
+
+impl JobHandle {
+    /// Returns the name of the job.
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Returns the statistics of the job's runs.
+    #[inline(always)]
+    pub fn stats(&self) -> JobStats {
+        self.state.stats.borrow().clone()
+    }
+
+    /// Returns the id of the job's fiber.
+    #[inline(always)]
+    pub fn fiber_id(&self) -> Option<FiberId> {
+        self.state.fiber_id.get()
+    }
+
+    /// Returns `true` if the job is running right now.
+    #[inline(always)]
+    pub fn is_running(&self) -> bool {
+        self.state.running.get()
+    }
+
+    /// Returns `true` if the job's fiber has exited, i.e. the job won't run
+    /// anymore.
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.get()
+    }
+
+    /// Stops scheduling the new runs of the job. If the job is currently
+    /// running, the run is not interrupted.
+    ///
+    /// **Does NOT yield**.
+    #[inline]
+    pub fn cancel(&self) {
+        self.state.cancelled.set(true);
+        // Wake up the job's fiber if it's waiting for the next run. If the
+        // job is running right now, the flag is checked after the run
+        // finishes, so the run itself is not interrupted.
+        self.state.wakeup.broadcast();
+    }
+}
+
+impl fmt::Debug for JobHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobHandle")
+            .field("name", &self.state.name)
+            .field("stats", &self.state.stats.borrow())
+            .field("running", &self.state.running.get())
+            .field("cancelled", &self.state.cancelled.get())
+            .field("finished", &self.state.finished.get())
+            .finish()
+    }
+}
+
+/// Statistics of a job's runs, see [`JobHandle::stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JobStats {
+    /// Number of finished runs including the failed ones.
+    pub runs: u64,
+    /// Number of runs which returned an error or panicked.
+    pub failures: u64,
+    /// Number of the failed runs since the last successful one.
+    pub consecutive_failures: u64,
+    /// Time when the last run started.
+    pub last_run: Option<SystemTime>,
+    /// Duration of the last run.
+    pub last_duration: Option<Duration>,
+    /// Total duration of all of the runs.
+    pub total_duration: Duration,
+    /// Error returned from the last failed run.
+    pub last_error: Option<String>,
+    /// Approximate time of the next run, `None` if the job is currently
+    /// running or won't run anymore.
+    pub next_run: Option<SystemTime>,
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn every() {
+        let scheduler = Scheduler::new();
+        let calls = Rc::new(Cell::new(0));
+        let calls_clone = calls.clone();
+        let job = scheduler
+            .job("every")
+            .every(Duration::from_millis(1))
+            .start(move || -> Result<(), String> {
+                calls_clone.set(calls_clone.get() + 1);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(job.name(), "every");
+        fiber::sleep(Duration::from_millis(20));
+        scheduler.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(job.is_finished());
+
+        let stats = job.stats();
+        assert!(stats.runs > 1);
+        assert_eq!(stats.runs, calls.get());
+        assert_eq!(stats.failures, 0);
+        assert!(stats.last_run.is_some());
+        assert!(stats.last_error.is_none());
+
+        // No new jobs after shutdown
+        let res = scheduler
+            .job("late")
+            .every(Duration::from_millis(1))
+            .start(|| -> Result<(), String> { Ok(()) });
+        assert!(res.is_err());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn errors_and_panics() {
+        let scheduler = Scheduler::new();
+        let calls = Rc::new(Cell::new(0));
+        let calls_clone = calls.clone();
+        let job = scheduler
+            .job("failing")
+            .every(Duration::from_millis(1))
+            .start(move || {
+                calls_clone.set(calls_clone.get() + 1);
+                match calls_clone.get() {
+                    1 => Err("oops"),
+                    2 => panic!("boom"),
+                    _ => Ok(()),
+                }
+            })
+            .unwrap();
+        fiber::sleep(Duration::from_millis(20));
+        job.cancel();
+        fiber::sleep(Duration::from_millis(1));
+        assert!(job.is_finished());
+
+        let stats = job.stats();
+        assert!(stats.runs > 2);
+        assert_eq!(stats.failures, 2);
+        assert_eq!(stats.last_error.as_deref(), Some("job panicked: boom"));
+        assert_eq!(scheduler.jobs().len(), 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn backoff() {
+        let timing = Timing {
+            jitter: Duration::ZERO,
+            backoff: Some((Duration::from_millis(10), Duration::from_millis(35))),
+        };
+        let delays: Vec<_> = (0..5).map(|n| timing.backoff_delay(n)).collect();
+        let ms = Duration::from_millis;
+        assert_eq!(delays, [ms(0), ms(10), ms(20), ms(35), ms(35)]);
+        assert_eq!(timing.backoff_delay(u64::MAX), ms(35));
+
+        let scheduler = Scheduler::new();
+        let fail = Rc::new(Cell::new(true));
+        let fail_clone = fail.clone();
+        let job = scheduler
+            .job("backoff")
+            .every(Duration::from_millis(1))
+            .backoff(Duration::from_millis(10), Duration::from_millis(20))
+            .start(move || {
+                fiber::sleep(Duration::from_millis(1));
+                if fail_clone.get() {
+                    return Err("oops");
+                }
+                Ok(())
+            })
+            .unwrap();
+        fiber::sleep(Duration::from_millis(2));
+        assert!(job.is_running());
+        fiber::sleep(Duration::from_millis(50));
+        let stats = job.stats();
+        // Without the backoff there would be around 25 runs.
+        assert!(stats.runs <= 5, "{:?}", stats);
+        assert_eq!(stats.consecutive_failures, stats.runs);
+        assert!(stats.total_duration >= Duration::from_millis(stats.runs));
+
+        fail.set(false);
+        fiber::sleep(Duration::from_millis(40));
+        let stats = job.stats();
+        assert_eq!(stats.consecutive_failures, 0);
+        assert!(stats.failures < stats.runs);
+        scheduler.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(!job.is_running());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn at_and_jitter() {
+        let scheduler = Scheduler::new();
+        let job = scheduler
+            .job("once")
+            .at(SystemTime::now())
+            .jitter(Duration::from_millis(5))
+            .start(|| -> Result<(), String> { Ok(()) })
+            .unwrap();
+        fiber::sleep(Duration::from_millis(20));
+        assert!(job.is_finished());
+        assert_eq!(job.stats().runs, 1);
+
+        let res = scheduler
+            .job("no schedule")
+            .start(|| -> Result<(), String> { Ok(()) });
+        assert!(res.is_err());
+
+        assert!(Schedule::cron("* * *").is_err());
+        let job = scheduler
+            .job("cron")
+            .schedule(Schedule::cron("@yearly").unwrap())
+            .start(|| -> Result<(), String> { Ok(()) })
+            .unwrap();
+        assert!(job.stats().next_run.is_some());
+        scheduler.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(job.is_finished());
+        assert_eq!(job.stats().runs, 0);
+    }
+}
@@ -0,0 +1,307 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use time::OffsetDateTime;
+
+/// The search for the next matching time stops after this many seconds, so
+/// that impossible expressions like `0 0 31 2 *` don't loop forever.
+const MAX_LOOKAHEAD_SECS: i64 = 5 * 366 * 24 * 60 * 60;
+
+////////////////////////////////////////////////////////////////////////////////
+// Cron
+////////////////////////////////////////////////////////////////////////////////
+
+/// A parsed cron expression.
+///
+/// The expression consists of 5 whitespace separated fields:
+///
+/// | field        | allowed values                 |
+/// |--------------|--------------------------------|
+/// | minute       | `0-59`                         |
+/// | hour         | `0-23`                         |
+/// | day of month | `1-31`                         |
+/// | month        | `1-12`                         |
+/// | day of week  | `0-7` (both `0` and `7` are Sunday) |
+///
+/// Each field is either `*` or a comma separated list of values (`1,5`),
+/// ranges (`1-5`) and steps (`*/15`, `0-30/10`). As in the traditional cron,
+/// if both day of month and day of week are restricted, the time matches if
+/// either of them matches.
+///
+/// The following shortcuts are also supported: `@yearly` (`@annually`),
+/// `@monthly`, `@weekly`, `@daily` (`@midnight`) and `@hourly`.
+///
+/// All of the times are in UTC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    day_of_month_any: bool,
+    day_of_week_any: bool,
+}
+
+impl Cron {
+    /// Parses a cron expression, see [`Cron`] for the syntax.
+    pub fn parse(expr: &str) -> Result<Self, CronParseError> {
+        let error = |reason: String| CronParseError {
+            expr: expr.into(),
+            reason,
+        };
+
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields: Vec<_> = expanded.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(error(format!("expected 5 fields, got {}", fields.len())));
+        };
+
+        let minutes = parse_field(minute, 0, 59).map_err(|e| error(format!("minute: {}", e)))?;
+        let hours = parse_field(hour, 0, 23).map_err(|e| error(format!("hour: {}", e)))?;
+        let days_of_month =
+            parse_field(day_of_month, 1, 31).map_err(|e| error(format!("day of month: {}", e)))?;
+        let months = parse_field(month, 1, 12).map_err(|e| error(format!("month: {}", e)))?;
+        let mut days_of_week =
+            parse_field(day_of_week, 0, 7).map_err(|e| error(format!("day of week: {}", e)))?;
+        // 7 is an alias for sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            minutes,
+            hours: hours as _,
+            days_of_month: days_of_month as _,
+            months: months as _,
+            days_of_week: (days_of_week & 0x7f) as _,
+            day_of_month_any: day_of_month == "*",
+            day_of_week_any: day_of_week == "*",
+        })
+    }
+
+    /// Returns the first time matching the expression which is strictly
+    /// after `time`, or `None` if there's no such time in the next 5 years.
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let start = match time.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => since_epoch.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64) - 1,
+        };
+        // Round up to the next whole minute.
+        let mut ts = start - start.rem_euclid(60) + 60;
+        while ts - start <= MAX_LOOKAHEAD_SECS {
+            let t = OffsetDateTime::from_unix_timestamp(ts).ok()?;
+            if !self.matches_day(&t) {
+                ts = ts - ts.rem_euclid(24 * 60 * 60) + 24 * 60 * 60;
+            } else if self.hours & (1 << t.hour()) == 0 {
+                ts = ts - ts.rem_euclid(60 * 60) + 60 * 60;
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                ts += 60;
+            } else if ts >= 0 {
+                return Some(UNIX_EPOCH + Duration::from_secs(ts as _));
+            } else {
+                return Some(UNIX_EPOCH - Duration::from_secs(-ts as _));
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, t: &OffsetDateTime) -> bool {
+        if self.months & (1 << u8::from(t.month())) == 0 {
+            return false;
+        }
+        let day_of_month = self.days_of_month & (1 << t.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << t.weekday().number_days_from_sunday()) != 0;
+        match (self.day_of_month_any, self.day_of_week_any) {
+            (true, true) => true,
+            (false, true) => day_of_month,
+            (true, false) => day_of_week,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = CronParseError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Parses a single field of a cron expression into a bitmask of the allowed
+/// values.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let parse_value = |s: &str| -> Result<u32, String> {
+        let v: u32 = s.parse().map_err(|_| format!("invalid value '{}'", s))?;
+        if v < min || v > max {
+            return Err(format!("value {} is out of range {}-{}", v, min, max));
+        }
+        Ok(v)
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must not be 0".into());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (from, to) = if range == "*" {
+            (min, max)
+        } else if let Some((from, to)) = range.split_once('-') {
+            (parse_value(from)?, parse_value(to)?)
+        } else {
+            let v = parse_value(range)?;
+            // `5/10` means starting from 5 with step 10
+            (v, if step > 1 { max } else { v })
+        };
+        if from > to {
+            return Err(format!("invalid range {}-{}", from, to));
+        }
+        for v in (from..=to).step_by(step as _) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// CronParseError
+////////////////////////////////////////////////////////////////////////////////
+
+/// An error returned when a cron expression is invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronParseError {
+    expr: String,
+    reason: String,
+}
+
+impl fmt::Display for CronParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid cron expression '{}': {}",
+            self.expr, self.reason
+        )
+    }
+}
+
+impl std::error::Error for CronParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ts: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(ts)
+    }
+
+    // 2024-01-01T00:00:00Z, monday
+    const JAN_1_2024: u64 = 1704067200;
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    #[test]
+    fn parse() {
+        assert!(Cron::parse("* * * * *").is_ok());
+        assert!(Cron::parse("*/15 0-6,12 1 1-12/2 mon").is_err());
+        assert!(Cron::parse("*/15 0-6,12 1 1-12/2 1-5").is_ok());
+        assert!(Cron::parse("@daily").is_ok());
+        assert_eq!(
+            Cron::parse("* * * *").unwrap_err().to_string(),
+            "invalid cron expression '* * * *': expected 5 fields, got 4"
+        );
+        assert_eq!(
+            Cron::parse("60 * * * *").unwrap_err().to_string(),
+            "invalid cron expression '60 * * * *': minute: value 60 is out of range 0-59"
+        );
+        assert_eq!(
+            Cron::parse("* * * * */0").unwrap_err().to_string(),
+            "invalid cron expression '* * * * */0': day of week: step must not be 0"
+        );
+        assert_eq!(
+            Cron::parse("* 5-1 * * *").unwrap_err().to_string(),
+            "invalid cron expression '* 5-1 * * *': hour: invalid range 5-1"
+        );
+    }
+
+    #[test]
+    fn next_after() {
+        let cron: Cron = "* * * * *".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024)),
+            Some(at(JAN_1_2024 + MINUTE))
+        );
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024 + 30)),
+            Some(at(JAN_1_2024 + MINUTE))
+        );
+
+        let cron: Cron = "*/15 * * * *".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024 + MINUTE)),
+            Some(at(JAN_1_2024 + 15 * MINUTE))
+        );
+
+        let cron: Cron = "30 12 * * *".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024 + 13 * HOUR)),
+            Some(at(JAN_1_2024 + DAY + 12 * HOUR + 30 * MINUTE))
+        );
+
+        // saturday
+        let cron: Cron = "0 0 * * 6".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024)),
+            Some(at(JAN_1_2024 + 5 * DAY))
+        );
+        // 7 is sunday
+        let cron: Cron = "0 0 * * 7".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024)),
+            Some(at(JAN_1_2024 + 6 * DAY))
+        );
+
+        // either day of month or day of week
+        let cron: Cron = "0 0 3 * 6".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024)),
+            Some(at(JAN_1_2024 + 2 * DAY))
+        );
+
+        let cron: Cron = "@monthly".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024)),
+            Some(at(JAN_1_2024 + 31 * DAY))
+        );
+
+        // leap day
+        let cron: Cron = "0 0 29 2 *".parse().unwrap();
+        assert_eq!(
+            cron.next_after(at(JAN_1_2024)),
+            Some(at(JAN_1_2024 + 59 * DAY))
+        );
+
+        let cron: Cron = "0 0 31 2 *".parse().unwrap();
+        assert_eq!(cron.next_after(at(JAN_1_2024)), None);
+    }
+}
@@ -0,0 +1,511 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::{fmt, time::Duration};
+
+use crate::fiber::{self, Cond, WaitError};
+use crate::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// Semaphore
+////////////////////////////////////////////////////////////////////////////////
+
+/// A counting semaphore for fibers.
+///
+/// A semaphore maintains a set of permits. Permits are used to limit the
+/// number of fibers concurrently accessing some resource (e.g. the number of
+/// concurrent requests to an external service).
+///
+/// The semaphore is fair: the permits are handed out in the order in which
+/// they were requested, so a fiber waiting for many permits isn't starved by
+/// the fibers requesting fewer permits. This also means that the
+/// non-blocking [`try_acquire`] fails while there're other fibers waiting
+/// for the permits.
+///
+/// The permits can be acquired both from the regular fibers ([`acquire`])
+/// and from the async code ([`acquire_async`]), the waiters of both kinds
+/// share the same queue.
+///
+/// All of the blocking methods of this type return [`WaitError::Cancelled`] if
+/// the current fiber is cancelled while waiting for the permits.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use tarantool::fiber::{self, Semaphore};
+///
+/// // At most 2 fibers will be doing the work at the same time.
+/// let semaphore = Rc::new(Semaphore::new(2));
+/// let mut fibers = vec![];
+/// for _ in 0..10 {
+///     let semaphore = semaphore.clone();
+///     fibers.push(fiber::start(move || {
+///         let _permit = semaphore.acquire().unwrap();
+///         // do the work
+///     }));
+/// }
+/// for f in fibers {
+///     f.join();
+/// }
+/// ```
+///
+/// [`acquire`]: Self::acquire
+/// [`acquire_async`]: Self::acquire_async
+/// [`try_acquire`]: Self::try_acquire
+pub struct Semaphore {
+    permits: Cell<usize>,
+    /// The waiting acquirers, the first one gets the permits first.
+    waiters: RefCell<VecDeque<Waiter>>,
+    next_waiter_id: Cell<u64>,
+    /// Signalled when the first waiter changes or new permits are added.
+    cond: Cond,
+}
+
+struct Waiter {
+    id: u64,
+    /// Waker of an async waiter, `None` for the blocking ones, which wait for
+    /// [`Semaphore::cond`].
+    waker: Option<Waker>,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given number of permits.
+    #[inline]
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: Cell::new(permits),
+            waiters: Default::default(),
+            next_waiter_id: Cell::new(0),
+            cond: Cond::new(),
+        }
+    }
+
+    /// Returns the current number of available permits.
+    #[inline(always)]
+    pub fn available_permits(&self) -> usize {
+        self.permits.get()
+    }
+
+    /// Returns the number of fibers (and futures) waiting for the permits.
+    #[inline(always)]
+    pub fn waiters_count(&self) -> usize {
+        self.waiters.borrow().len()
+    }
+
+    /// Adds `n` new permits to the semaphore, waking up the waiting fibers.
+    #[inline]
+    pub fn add_permits(&self, n: usize) {
+        self.permits.set(self.permits.get() + n);
+        self.notify();
+    }
+
+    /// Acquires a permit from the semaphore, yielding the current fiber until
+    /// one is available.
+    ///
+    /// Returns an RAII guard which returns the permit to the semaphore once it
+    /// is dropped.
+    #[inline(always)]
+    pub fn acquire(&self) -> Result<SemaphorePermit<'_>, WaitError> {
+        self.acquire_many_maybe_deadline(1, None)
+    }
+
+    /// Same as [`Self::acquire`], but returns [`WaitError::Timeout`] if a
+    /// permit could not be acquired in the given `timeout`.
+    #[inline(always)]
+    pub fn acquire_timeout(&self, timeout: Duration) -> Result<SemaphorePermit<'_>, WaitError> {
+        let deadline = fiber::clock().saturating_add(timeout);
+        self.acquire_many_maybe_deadline(1, Some(deadline))
+    }
+
+    /// Same as [`Self::acquire`], but returns [`WaitError::Timeout`] if a
+    /// permit could not be acquired until the given `deadline`.
+    #[inline(always)]
+    pub fn acquire_deadline(&self, deadline: Instant) -> Result<SemaphorePermit<'_>, WaitError> {
+        self.acquire_many_maybe_deadline(1, Some(deadline))
+    }
+
+    /// Acquires `n` permits from the semaphore, yielding the current fiber
+    /// until they are available.
+    #[inline(always)]
+    pub fn acquire_many(&self, n: usize) -> Result<SemaphorePermit<'_>, WaitError> {
+        self.acquire_many_maybe_deadline(n, None)
+    }
+
+    /// Acquires a permit from the semaphore asynchronously.
+    ///
+    /// If the returned future is dropped before it completes, the place in
+    /// the queue is released, e.g. when the waiting is limited with a
+    /// [`timeout`].
+    ///
+    /// [`timeout`]: crate::fiber::async::timeout::timeout
+    #[inline(always)]
+    pub fn acquire_async(&self) -> Acquire<'_> {
+        self.acquire_many_async(1)
+    }
+
+    /// Acquires `n` permits from the semaphore asynchronously, see
+    /// [`Self::acquire_async`].
+    #[inline(always)]
+    pub fn acquire_many_async(&self, n: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            permits: n,
+            waiter_id: None,
+        }
+    }
+
+    /// Tries to acquire a permit from the semaphore.
+    ///
+    /// Returns `None` if there are no permits available at this time or if
+    /// other fibers are already waiting for the permits.
+    ///
+    /// This function does not yield.
+    #[inline(always)]
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    /// Tries to acquire `n` permits from the semaphore.
+    ///
+    /// Returns `None` if there are not enough permits available at this time
+    /// or if other fibers are already waiting for the permits.
+    ///
+    /// This function does not yield.
+    #[inline]
+    pub fn try_acquire_many(&self, n: usize) -> Option<SemaphorePermit<'_>> {
+        if !self.waiters.borrow().is_empty() {
+            return None;
+        }
+        self.take(n)
+    }
+
+    fn take(&self, n: usize) -> Option<SemaphorePermit<'_>> {
+        let permits = self.permits.get();
+        if permits < n {
+            return None;
+        }
+        self.permits.set(permits - n);
+        Some(SemaphorePermit {
+            semaphore: self,
+            permits: n,
+        })
+    }
+
+    /// Takes `n` permits if the waiter `id` is the first one in the queue.
+    fn take_as_waiter(&self, id: u64, n: usize) -> Option<SemaphorePermit<'_>> {
+        let mut waiters = self.waiters.borrow_mut();
+        if waiters.front().map(|w| w.id) != Some(id) {
+            return None;
+        }
+        let permit = self.take(n)?;
+        waiters.pop_front();
+        drop(waiters);
+        // The next waiter may also be satisfied.
+        self.notify();
+        Some(permit)
+    }
+
+    fn enqueue(&self, waker: Option<Waker>) -> u64 {
+        let id = self.next_waiter_id.get();
+        self.next_waiter_id.set(id + 1);
+        self.waiters.borrow_mut().push_back(Waiter { id, waker });
+        id
+    }
+
+    /// Removes the waiter which gave up waiting.
+    fn dequeue(&self, id: u64) {
+        let mut waiters = self.waiters.borrow_mut();
+        let was_first = waiters.front().map(|w| w.id) == Some(id);
+        waiters.retain(|w| w.id != id);
+        drop(waiters);
+        if was_first {
+            self.notify();
+        }
+    }
+
+    /// Wakes up the first waiter.
+    fn notify(&self) {
+        let waker = self
+            .waiters
+            .borrow_mut()
+            .front_mut()
+            .and_then(|w| w.waker.take());
+        match waker {
+            Some(waker) => waker.wake(),
+            // The first waiter is blocking, but we don't know which fiber it
+            // is, so wake up all of them.
+            None => self.cond.broadcast(),
+        }
+    }
+
+    fn acquire_many_maybe_deadline(
+        &self,
+        n: usize,
+        deadline: Option<Instant>,
+    ) -> Result<SemaphorePermit<'_>, WaitError> {
+        if let Some(permit) = self.try_acquire_many(n) {
+            return Ok(permit);
+        }
+
+        struct Dequeue<'a>(&'a Semaphore, u64);
+        impl Drop for Dequeue<'_> {
+            fn drop(&mut self) {
+                self.0.dequeue(self.1);
+            }
+        }
+
+        let id = self.enqueue(None);
+        let _dequeue_on_error = Dequeue(self, id);
+        loop {
+            if let Some(permit) = self.take_as_waiter(id, n) {
+                return Ok(permit);
+            }
+            fiber::cond_wait_maybe_deadline(&self.cond, deadline)?;
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.permits.get())
+            .field("waiters", &self.waiters.borrow().len())
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Acquire
+////////////////////////////////////////////////////////////////////////////////
+
+/// Future returned by [`Semaphore::acquire_async`] and
+/// [`Semaphore::acquire_many_async`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    /// Place in the queue, `None` until the first time the future is
+    /// pending.
+    waiter_id: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        let Some(id) = self.waiter_id else {
+            if let Some(permit) = semaphore.try_acquire_many(self.permits) {
+                return Poll::Ready(permit);
+            }
+            self.waiter_id = Some(semaphore.enqueue(Some(cx.waker().clone())));
+            return Poll::Pending;
+        };
+
+        if let Some(permit) = semaphore.take_as_waiter(id, self.permits) {
+            self.waiter_id = None;
+            return Poll::Ready(permit);
+        }
+        let mut waiters = semaphore.waiters.borrow_mut();
+        if let Some(waiter) = waiters.iter_mut().find(|w| w.id == id) {
+            waiter.waker = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.waiter_id {
+            self.semaphore.dequeue(id);
+        }
+    }
+}
+
+impl fmt::Debug for Acquire<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Acquire")
+            .field("permits", &self.permits)
+            .field("waiter_id", &self.waiter_id)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// SemaphorePermit
+////////////////////////////////////////////////////////////////////////////////
+
+/// RAII structure which returns the acquired permits to the semaphore when
+/// dropped.
+///
+/// This structure is created by the [`acquire`], [`acquire_async`] and
+/// [`try_acquire`] methods on [`Semaphore`].
+///
+/// [`acquire`]: Semaphore::acquire
+/// [`acquire_async`]: Semaphore::acquire_async
+/// [`try_acquire`]: Semaphore::try_acquire
+#[must_use = "the permit is released immediately if unused"]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Returns the number of permits held by this guard.
+    #[inline(always)]
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Forgets the permits without returning them to the semaphore. This
+    /// effectively reduces the semaphore's capacity.
+    #[inline(always)]
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits != 0 {
+            self.semaphore.add_permits(self.permits);
+        }
+    }
+}
+
+impl fmt::Debug for SemaphorePermit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphorePermit")
+            .field("permits", &self.permits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[crate::test(tarantool = "crate")]
+    fn try_acquire() {
+        let s = Semaphore::new(2);
+        let p1 = s.try_acquire().unwrap();
+        let p2 = s.try_acquire().unwrap();
+        assert!(s.try_acquire().is_none());
+        assert_eq!(s.available_permits(), 0);
+        drop(p1);
+        assert_eq!(s.available_permits(), 1);
+        p2.forget();
+        assert_eq!(s.available_permits(), 1);
+
+        assert!(s.try_acquire_many(2).is_none());
+        s.add_permits(1);
+        let p = s.try_acquire_many(2).unwrap();
+        assert_eq!(p.num_permits(), 2);
+        drop(p);
+        assert_eq!(s.available_permits(), 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn limits_concurrency() {
+        let s = Rc::new(Semaphore::new(2));
+        let active = Rc::new(Cell::new(0));
+        let max_active = Rc::new(Cell::new(0));
+        let mut fibers = vec![];
+        for _ in 0..6 {
+            let s = s.clone();
+            let active = active.clone();
+            let max_active = max_active.clone();
+            fibers.push(fiber::start(move || {
+                let _permit = s.acquire().unwrap();
+                active.set(active.get() + 1);
+                max_active.set(max_active.get().max(active.get()));
+                fiber::sleep(Duration::from_millis(1));
+                active.set(active.get() - 1);
+            }));
+        }
+        for f in fibers {
+            f.join();
+        }
+        assert_eq!(max_active.get(), 2);
+        assert_eq!(s.available_permits(), 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn timeout_and_cancel() {
+        let s = Rc::new(Semaphore::new(1));
+        let _p = s.acquire().unwrap();
+        assert_eq!(
+            s.acquire_timeout(Duration::from_millis(10)).unwrap_err(),
+            WaitError::Timeout
+        );
+
+        let s_clone = s.clone();
+        let jh = fiber::start(move || s_clone.acquire().map(drop));
+        jh.cancel();
+        assert_eq!(jh.join(), Err(WaitError::Cancelled));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn fairness() {
+        let s = Rc::new(Semaphore::new(2));
+        let p = s.acquire().unwrap();
+        let order = Rc::new(RefCell::new(vec![]));
+
+        // Waits for 2 permits, while only 1 is available.
+        let s_clone = s.clone();
+        let order_clone = order.clone();
+        let many = fiber::start(move || {
+            let _p = s_clone.acquire_many(2).unwrap();
+            order_clone.borrow_mut().push("many");
+        });
+        // Needs 1 permit which is available, but must wait its turn.
+        assert!(s.try_acquire().is_none());
+        let s_clone = s.clone();
+        let order_clone = order.clone();
+        let one = fiber::start(move || {
+            let _p = s_clone.acquire().unwrap();
+            order_clone.borrow_mut().push("one");
+        });
+        assert_eq!(s.waiters_count(), 2);
+
+        drop(p);
+        many.join();
+        one.join();
+        assert_eq!(*order.borrow(), ["many", "one"]);
+        assert_eq!(s.waiters_count(), 0);
+        assert_eq!(s.available_permits(), 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn acquire_async() {
+        use crate::fiber::r#async::timeout::IntoTimeout;
+
+        let s = Rc::new(Semaphore::new(1));
+        let p = fiber::block_on(s.acquire_async());
+        assert_eq!(s.available_permits(), 0);
+
+        let s_clone = s.clone();
+        let jh = fiber::start_async(async move {
+            let p = s_clone.acquire_async().await;
+            p.num_permits()
+        });
+        assert_eq!(s.waiters_count(), 1);
+        drop(p);
+        assert_eq!(jh.join(), 1);
+
+        // Dropped futures leave the queue.
+        let p = s.try_acquire().unwrap();
+        let acquire = async { Ok::<_, ()>(s.acquire_async().await) };
+        let res = fiber::block_on(acquire.timeout(Duration::from_millis(10)));
+        assert!(res.is_err());
+        assert_eq!(s.waiters_count(), 0);
+        drop(p);
+        assert!(s.try_acquire().is_some());
+    }
+}
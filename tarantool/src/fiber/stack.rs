@@ -0,0 +1,275 @@
+//! Fiber stack sizes and stack overflow checks.
+//!
+//! Fibers run on relatively small stacks allocated by tarantool (512KB by
+//! default), which can be exhausted by a deep recursion or large values
+//! allocated on the stack. The overflow is detected only when the guard page
+//! at the end of the stack is hit, which crashes the whole process with a
+//! `SIGSEGV`.
+//!
+//! Functions in this module allow checking how much of the stack is left, so
+//! that the code which may recurse deeply can return an error instead:
+//!
+//! ```no_run
+//! use tarantool::fiber;
+//!
+//! fn walk(depth: usize) -> tarantool::Result<()> {
+//!     fiber::check_stack(16 * 1024)?;
+//!     if depth > 0 {
+//!         walk(depth - 1)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The stack bounds are only known for the fibers started via
+//! [`fiber::Builder`] (or [`fiber::start`], [`fiber::defer`], etc.). In the
+//! other fibers (e.g. the ones serving the iproto requests or started from
+//! lua) the checks always pass.
+//!
+//! [`fiber::Builder`]: crate::fiber::Builder
+//! [`fiber::start`]: crate::fiber::start
+//! [`fiber::defer`]: crate::fiber::defer
+
+use std::cell::{Cell, RefCell};
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber::FiberAttr;
+
+/// Minimal stack size of a fiber accepted by tarantool.
+pub const MIN_STACK_SIZE: usize = 16 * 1024;
+
+/// Maximal stack size of a fiber accepted by [`Builder::stack_size`].
+///
+/// Fiber stacks are allocated in the tx thread's memory, so sizes larger than
+/// this are most likely a mistake (e.g. a unit mix-up).
+///
+/// [`Builder::stack_size`]: crate::fiber::Builder::stack_size
+pub const MAX_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Checks that `stack_size` is within [`MIN_STACK_SIZE`] and
+/// [`MAX_STACK_SIZE`].
+pub fn validate_stack_size(stack_size: usize) -> crate::Result<()> {
+    if stack_size < MIN_STACK_SIZE {
+        return Err(BoxError::new(
+            TarantoolErrorCode::IllegalParams,
+            format!(
+                "fiber stack size {} is too small, minimum is {}",
+                stack_size, MIN_STACK_SIZE
+            ),
+        )
+        .into());
+    }
+    if stack_size > MAX_STACK_SIZE {
+        return Err(BoxError::new(
+            TarantoolErrorCode::IllegalParams,
+            format!(
+                "fiber stack size {} is too large, maximum is {}",
+                stack_size, MAX_STACK_SIZE
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the stack size of the fibers created with the default attributes.
+pub fn default_stack_size() -> usize {
+    thread_local! {
+        static DEFAULT_STACK_SIZE: Cell<usize> = const { Cell::new(0) };
+    }
+    DEFAULT_STACK_SIZE.with(|size| {
+        if size.get() == 0 {
+            size.set(FiberAttr::new().stack_size());
+        }
+        size.get()
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// stack bounds
+////////////////////////////////////////////////////////////////////////////////
+
+thread_local! {
+    /// Usable address ranges of the stacks of the fibers started from rust.
+    static STACKS: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+#[inline(always)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+fn page_size() -> usize {
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if size > 0 {
+        size as _
+    } else {
+        4096
+    }
+}
+
+/// Remembers the stack bounds of the current fiber while it's alive.
+struct StackRegistration {
+    bounds: (usize, usize),
+}
+
+impl StackRegistration {
+    #[inline(never)]
+    fn new(stack_size: usize) -> Self {
+        let high = stack_pointer();
+        // The stack grows down and the lowest page is protected.
+        let low = high.saturating_sub(stack_size) + page_size();
+        let bounds = (low, high);
+        STACKS.with(|stacks| stacks.borrow_mut().push(bounds));
+        Self { bounds }
+    }
+}
+
+impl Drop for StackRegistration {
+    fn drop(&mut self) {
+        STACKS.with(|stacks| {
+            let mut stacks = stacks.borrow_mut();
+            if let Some(i) = stacks.iter().position(|b| *b == self.bounds) {
+                stacks.swap_remove(i);
+            }
+        })
+    }
+}
+
+/// Wraps the fiber function `f`, so that the bounds of the fiber's stack are
+/// known while it's running.
+pub(crate) fn tracked<F, T>(stack_size: usize, f: F) -> impl FnOnce() -> T
+where
+    F: FnOnce() -> T,
+{
+    move || {
+        let _registration = StackRegistration::new(stack_size);
+        f()
+    }
+}
+
+/// Returns the approximate number of bytes left on the current fiber's stack
+/// before the guard page is hit.
+///
+/// Returns `None` if the stack bounds of the current fiber are unknown, see
+/// the [module level documentation](self).
+#[inline(never)]
+pub fn remaining_stack() -> Option<usize> {
+    let sp = stack_pointer();
+    STACKS.with(|stacks| {
+        stacks
+            .borrow()
+            .iter()
+            .find(|(low, high)| *low <= sp && sp <= *high)
+            .map(|(low, _)| sp - low)
+    })
+}
+
+/// Returns an error if less than `required` bytes are left on the current
+/// fiber's stack, see [`remaining_stack`].
+///
+/// Call this at the start of a function which may recurse deeply, so that
+/// the stack overflow results in an error instead of a crash.
+#[track_caller]
+pub fn check_stack(required: usize) -> crate::Result<()> {
+    match remaining_stack() {
+        Some(remaining) if remaining < required => Err(BoxError::new(
+            TarantoolErrorCode::MemoryIssue,
+            format!(
+                "fiber stack overflow: {} bytes required, {} bytes left",
+                required, remaining
+            ),
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Panics with a descriptive message if less than `required` bytes are left
+/// on the current fiber's stack. Does nothing in release builds.
+///
+/// Unlike the stack overflow, the panic can be caught and it points to the
+/// offending code.
+#[track_caller]
+#[inline(always)]
+pub fn debug_assert_stack(required: usize) {
+    if cfg!(debug_assertions) {
+        if let Err(e) = check_stack(required) {
+            panic!("{}", e);
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+
+    #[crate::test(tarantool = "crate")]
+    fn validate() {
+        let e = fiber::Builder::new()
+            .func(|| ())
+            .stack_size(1024)
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IllegalParams: fiber stack size 1024 is too small, minimum is 16384"
+        );
+        let e = fiber::Builder::new()
+            .func(|| ())
+            .stack_size(MAX_STACK_SIZE + 1)
+            .unwrap_err();
+        assert!(e.to_string().contains("is too large"), "{}", e);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn remaining() {
+        let remaining = fiber::start(remaining_stack).join().unwrap();
+        assert!(remaining <= default_stack_size());
+        assert!(remaining > default_stack_size() / 2);
+
+        let stack_size = 1024 * 1024;
+        let remaining = fiber::Builder::new()
+            .stack_size(stack_size)
+            .unwrap()
+            .func(remaining_stack)
+            .start()
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(remaining <= stack_size);
+        assert!(remaining > stack_size / 2);
+
+        // Bounds are forgotten once the fiber finishes.
+        let n_stacks = || STACKS.with(|stacks| stacks.borrow().len());
+        let n_stacks_before = n_stacks();
+        let n_stacks_inside = fiber::start(n_stacks).join();
+        assert_eq!(n_stacks_inside, n_stacks_before + 1);
+        assert_eq!(n_stacks(), n_stacks_before);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn deep_recursion() {
+        fn recurse(depth: usize) -> crate::Result<usize> {
+            check_stack(16 * 1024)?;
+            let buf = std::hint::black_box([0u8; 1024]);
+            Ok(recurse(depth + 1)? + buf[depth % buf.len()] as usize)
+        }
+
+        let e = fiber::Builder::new()
+            .stack_size(256 * 1024)
+            .unwrap()
+            .func(|| recurse(0))
+            .start()
+            .unwrap()
+            .join()
+            .unwrap_err();
+        assert!(
+            e.to_string()
+                .starts_with("box error: MemoryIssue: fiber stack overflow: 16384 bytes required"),
+            "{}",
+            e
+        );
+    }
+}
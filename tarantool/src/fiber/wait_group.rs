@@ -0,0 +1,166 @@
+use std::{cell::Cell, fmt, rc::Rc, time::Duration};
+
+use crate::fiber::{self, Cond, WaitError};
+use crate::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// WaitGroup
+////////////////////////////////////////////////////////////////////////////////
+
+/// Enables fibers to synchronize the beginning or end of some computation.
+///
+/// Each clone of a wait group represents a unit of work. Once all of the
+/// clones are dropped, the fiber which called [`WaitGroup::wait`] on the
+/// original wait group is woken up.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tarantool::fiber::{self, WaitGroup};
+///
+/// let wg = WaitGroup::new();
+/// for i in 0..4 {
+///     let wg = wg.clone();
+///     fiber::Builder::new()
+///         .func(move || {
+///             // do the work
+///             drop(wg);
+///         })
+///         .start_non_joinable()
+///         .unwrap();
+/// }
+/// // Block until all fibers have finished their work.
+/// wg.wait().unwrap();
+/// ```
+pub struct WaitGroup {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    count: Cell<usize>,
+    cond: Cond,
+}
+
+impl WaitGroup {
+    /// Creates a new wait group and returns the single reference to it.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                count: Cell::new(1),
+                cond: Cond::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of outstanding references to this wait group
+    /// (including `self`).
+    #[inline(always)]
+    pub fn count(&self) -> usize {
+        self.inner.count.get()
+    }
+
+    /// Drops this reference and yields the current fiber until all other
+    /// references are dropped.
+    #[inline(always)]
+    pub fn wait(self) -> Result<(), WaitError> {
+        self.wait_maybe_deadline(None)
+    }
+
+    /// Same as [`Self::wait`], but returns [`WaitError::Timeout`] if other
+    /// references are not dropped in the given `timeout`.
+    #[inline(always)]
+    pub fn wait_timeout(self, timeout: Duration) -> Result<(), WaitError> {
+        self.wait_maybe_deadline(Some(fiber::clock().saturating_add(timeout)))
+    }
+
+    /// Same as [`Self::wait`], but returns [`WaitError::Timeout`] if other
+    /// references are not dropped until the given `deadline`.
+    #[inline(always)]
+    pub fn wait_deadline(self, deadline: Instant) -> Result<(), WaitError> {
+        self.wait_maybe_deadline(Some(deadline))
+    }
+
+    fn wait_maybe_deadline(self, deadline: Option<Instant>) -> Result<(), WaitError> {
+        let inner = self.inner.clone();
+        drop(self);
+        while inner.count.get() != 0 {
+            fiber::cond_wait_maybe_deadline(&inner.cond, deadline)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for WaitGroup {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for WaitGroup {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.inner.count.set(self.inner.count.get() + 1);
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl Drop for WaitGroup {
+    fn drop(&mut self) {
+        let count = self.inner.count.get() - 1;
+        self.inner.count.set(count);
+        if count == 0 {
+            self.inner.cond.broadcast();
+        }
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitGroup")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+
+    #[crate::test(tarantool = "crate")]
+    fn wait_for_fibers() {
+        let done = Rc::new(Cell::new(0));
+        let wg = WaitGroup::new();
+        for i in 0..3 {
+            let wg = wg.clone();
+            let done = done.clone();
+            fiber::Builder::new()
+                .func(move || {
+                    fiber::sleep(Duration::from_millis(i));
+                    done.set(done.get() + 1);
+                    drop(wg);
+                })
+                .start_non_joinable()
+                .unwrap();
+        }
+        assert_eq!(wg.count(), 4);
+        wg.wait().unwrap();
+        assert_eq!(done.get(), 3);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn wait_timeout() {
+        let wg = WaitGroup::new();
+        let other = wg.clone();
+        assert_eq!(
+            wg.wait_timeout(Duration::from_millis(10)),
+            Err(WaitError::Timeout)
+        );
+        assert_eq!(other.count(), 1);
+        other.wait().unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+//! Protection of the checkpoint and WAL files from garbage collection.
+//!
+//! Tarantool removes the old checkpoints (see `box.cfg.checkpoint_count`) and
+//! the WAL files which aren't needed for recovery from the remaining ones.
+//! A backup which copies the files or a reader of the WAL files (e.g. for
+//! change data capture) needs them to stay in place while it's working,
+//! which is what [`checkpoint_ref`] is for.
+//!
+//! ```no_run
+//! use tarantool::gc;
+//!
+//! let checkpoint = gc::checkpoint_ref().unwrap();
+//! for file in checkpoint.files() {
+//!     // Copy the file somewhere ...
+//! }
+//! // The files may be removed once the reference is dropped.
+//! drop(checkpoint);
+//! ```
+//!
+//! See [`info::gc`](crate::info::gc) for the current state of the garbage
+//! collector.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+thread_local! {
+    static BACKUP: RefCell<Option<Weak<Backup>>> = const { RefCell::new(None) };
+}
+
+/// Started via `box.backup.start()`, stopped once dropped.
+#[derive(Debug)]
+struct Backup {
+    files: Vec<String>,
+}
+
+impl Drop for Backup {
+    fn drop(&mut self) {
+        if let Err(e) = crate::lua_state().exec("box.backup.stop()") {
+            crate::say_error!("failed to stop backup: {}", e);
+        }
+    }
+}
+
+/// A reference to the latest checkpoint which prevents it and the WAL files
+/// written after it from being garbage collected, see [`checkpoint_ref`].
+///
+/// The files are released once all of the references (including the clones)
+/// are dropped.
+#[derive(Clone, Debug)]
+pub struct CheckpointRef {
+    backup: Rc<Backup>,
+}
+
+impl CheckpointRef {
+    /// Returns the paths of the files of the checkpoint (the `.snap` file and
+    /// the vinyl files).
+    #[inline(always)]
+    pub fn files(&self) -> &[String] {
+        &self.backup.files
+    }
+}
+
+/// Returns a reference to the latest checkpoint, which prevents it and the
+/// WAL files after it from being removed until the reference is dropped.
+///
+/// The reference is implemented via `box.backup.start()`, which only allows
+/// one backup at a time, so all of the references obtained while another
+/// one exists share the same checkpoint. Returns an error if a backup was
+/// started by someone else, e.g. from lua.
+pub fn checkpoint_ref() -> crate::Result<CheckpointRef> {
+    if let Some(backup) = BACKUP.with(|b| b.borrow().as_ref().and_then(Weak::upgrade)) {
+        return Ok(CheckpointRef { backup });
+    }
+    let files: Vec<String> = crate::lua_state().eval("return box.backup.start()")?;
+    let backup = Rc::new(Backup { files });
+    BACKUP.with(|b| *b.borrow_mut() = Some(Rc::downgrade(&backup)));
+    Ok(CheckpointRef { backup })
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::info;
+
+    fn is_referenced() -> bool {
+        let gc = info::gc().unwrap();
+        let latest = gc.checkpoints.last().unwrap();
+        latest.references.iter().any(|r| r == "backup")
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn checkpoint_references() {
+        assert!(!is_referenced());
+
+        let first = checkpoint_ref().unwrap();
+        assert!(first.files().iter().any(|f| f.ends_with(".snap")));
+        assert!(is_referenced());
+
+        let second = checkpoint_ref().unwrap();
+        assert_eq!(first.files(), second.files());
+        let third = second.clone();
+
+        drop(first);
+        drop(second);
+        assert!(is_referenced());
+
+        drop(third);
+        assert!(!is_referenced());
+
+        // The checkpoint can be referenced again.
+        let again = checkpoint_ref().unwrap();
+        assert!(is_referenced());
+        drop(again);
+        assert!(!is_referenced());
+    }
+}
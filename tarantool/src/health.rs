@@ -0,0 +1,424 @@
+//! Health checks of the instance.
+//!
+//! Modules register named checks with [`register`], each of which is a
+//! closure returning a [`CheckResult`]. [`report`] runs all of the checks and
+//! aggregates their results into a [`Report`], the status of which is the
+//! worst status of the checks.
+//!
+//! Some commonly needed checks are provided out of the box, see
+//! [`register_builtin_checks`] and [`register_conn`].
+//!
+//! The report can be exposed to the monitoring systems via a stored procedure
+//! (see [`expose`]) or an iproto watcher key (see [`start_broadcast`]):
+//!
+//! ```no_run
+//! use tarantool::health::{self, CheckResult, Thresholds};
+//! use std::time::Duration;
+//!
+//! health::register_builtin_checks(&Thresholds::default()).unwrap();
+//! health::register("queue", || {
+//!     let len = 10; // get the queue length somehow
+//!     if len > 1000 {
+//!         return CheckResult::warning(format!("queue is too long: {}", len));
+//!     }
+//!     CheckResult::ok()
+//! })
+//! .unwrap();
+//!
+//! health::expose("health_report").unwrap();
+//! health::start_broadcast("my_app.health", Duration::from_secs(1)).unwrap();
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber::{self, FiberId};
+
+crate::define_str_enum! {
+    /// Status of a health check or of the whole instance. The statuses are
+    /// ordered from the best to the worst.
+    pub enum Status {
+        Ok = "ok",
+        /// The instance works, but requires attention.
+        Warning = "warning",
+        /// The instance doesn't work properly.
+        Critical = "critical",
+    }
+}
+
+/// Result of a single health check.
+#[derive(
+    Clone, Debug, PartialEq, Eq, serde::Serialize, tlua::Push, tlua::PushInto, tlua::LuaRead,
+)]
+pub struct CheckResult {
+    pub status: Status,
+    /// Human readable explanation of the status, empty if there's nothing
+    /// to say.
+    pub details: String,
+}
+
+impl CheckResult {
+    #[inline]
+    pub fn ok() -> Self {
+        Self {
+            status: Status::Ok,
+            details: String::new(),
+        }
+    }
+
+    #[inline]
+    pub fn warning(details: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warning,
+            details: details.into(),
+        }
+    }
+
+    #[inline]
+    pub fn critical(details: impl Into<String>) -> Self {
+        Self {
+            status: Status::Critical,
+            details: details.into(),
+        }
+    }
+}
+
+/// Aggregated result of all of the registered checks, see [`report`].
+#[derive(
+    Clone, Debug, PartialEq, Eq, serde::Serialize, tlua::Push, tlua::PushInto, tlua::LuaRead,
+)]
+pub struct Report {
+    /// The worst status of the checks, [`Status::Ok`] if there're no checks.
+    pub status: Status,
+    /// Results of the checks indexed by their names.
+    pub checks: HashMap<String, CheckResult>,
+}
+
+type Check = Rc<RefCell<dyn FnMut() -> CheckResult>>;
+
+thread_local! {
+    static CHECKS: RefCell<Vec<(String, Check)>> = RefCell::new(Vec::new());
+}
+
+/// Registers a health check called `name`.
+///
+/// The check is called from [`report`], it may yield, but must not take too
+/// long. If the check panics its status is [`Status::Critical`].
+///
+/// Returns an error if a check with the same name is already registered.
+pub fn register<F>(name: impl Into<String>, f: F) -> crate::Result<()>
+where
+    F: FnMut() -> CheckResult + 'static,
+{
+    let name = name.into();
+    CHECKS.with(|checks| {
+        let mut checks = checks.borrow_mut();
+        if checks.iter().any(|(n, _)| *n == name) {
+            return Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                format!("health check '{}' is already registered", name),
+            )
+            .into());
+        }
+        checks.push((name, Rc::new(RefCell::new(f))));
+        Ok(())
+    })
+}
+
+/// Unregisters the health check called `name`. Returns `false` if there's no
+/// such check.
+pub fn unregister(name: &str) -> bool {
+    CHECKS.with(|checks| {
+        let mut checks = checks.borrow_mut();
+        let len = checks.len();
+        checks.retain(|(n, _)| n != name);
+        checks.len() != len
+    })
+}
+
+/// Runs all of the registered health checks and returns the aggregated
+/// report.
+pub fn report() -> Report {
+    // The checks may yield, so other fibers may register new checks
+    // meanwhile.
+    let checks = CHECKS.with(|checks| checks.borrow().clone());
+    let mut report = Report {
+        status: Status::Ok,
+        checks: HashMap::with_capacity(checks.len()),
+    };
+    for (name, check) in checks {
+        let res = match check.try_borrow_mut() {
+            Ok(mut f) => run_check(&mut *f),
+            // Another fiber is running this check right now.
+            Err(_) => CheckResult::warning("check is already running"),
+        };
+        report.status = report.status.max(res.status);
+        report.checks.insert(name, res);
+    }
+    report
+}
+
+fn run_check(f: &mut dyn FnMut() -> CheckResult) -> CheckResult {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => {
+            let message = crate::util::panic_message(&*payload);
+            CheckResult::critical(format!("check panicked: {}", message))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// built-in checks
+////////////////////////////////////////////////////////////////////////////////
+
+/// Thresholds of the built-in checks, see [`register_builtin_checks`].
+#[derive(Clone, Debug)]
+pub struct Thresholds {
+    /// Replication lag (in seconds) above which the status is
+    /// [`Status::Warning`].
+    ///
+    /// Default: 1 second
+    pub replication_lag_warning: f64,
+    /// Replication lag (in seconds) above which the status is
+    /// [`Status::Critical`].
+    ///
+    /// Default: 10 seconds
+    pub replication_lag_critical: f64,
+    /// Fraction of the memtx memory quota (see
+    /// [`SlabInfo::quota_used_ratio`]) above which the status is
+    /// [`Status::Warning`].
+    ///
+    /// Default: `0.8`
+    ///
+    /// [`SlabInfo::quota_used_ratio`]: crate::slab::SlabInfo::quota_used_ratio
+    pub slab_quota_warning: f64,
+    /// Fraction of the memtx memory quota above which the status is
+    /// [`Status::Critical`].
+    ///
+    /// Default: `0.95`
+    pub slab_quota_critical: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            replication_lag_warning: 1.0,
+            replication_lag_critical: 10.0,
+            slab_quota_warning: 0.8,
+            slab_quota_critical: 0.95,
+        }
+    }
+}
+
+/// Registers the built-in health checks:
+///
+/// - `"box.status"` - the instance is running,
+/// - `"box.replication"` - the upstreams are following without lagging more
+///   than the [thresholds](Thresholds::replication_lag_warning),
+/// - `"box.slab"` - the memtx memory quota is not
+///   [exhausted](Thresholds::slab_quota_warning).
+///
+/// Returns an error if any of these checks is already registered.
+pub fn register_builtin_checks(thresholds: &Thresholds) -> crate::Result<()> {
+    register("box.status", check_box_status)?;
+    let t = thresholds.clone();
+    register("box.replication", move || check_replication(&t))?;
+    let t = thresholds.clone();
+    register("box.slab", move || check_slab(&t))?;
+    Ok(())
+}
+
+fn check_box_status() -> CheckResult {
+    let status: String = match crate::lua_state().eval("return box.info.status") {
+        Ok(status) => status,
+        Err(e) => return CheckResult::critical(e.to_string()),
+    };
+    match status.as_str() {
+        "running" => CheckResult::ok(),
+        "loading" | "orphan" | "hot_standby" => {
+            CheckResult::warning(format!("instance is {}", status))
+        }
+        _ => CheckResult::critical(format!("instance is {}", status)),
+    }
+}
+
+fn check_replication(thresholds: &Thresholds) -> CheckResult {
+    let replication = match crate::info::replication() {
+        Ok(replication) => replication,
+        Err(e) => return CheckResult::critical(e.to_string()),
+    };
+    let mut ids: Vec<_> = replication.keys().copied().collect();
+    ids.sort_unstable();
+
+    let mut res = CheckResult::ok();
+    let mut problems = Vec::new();
+    for id in ids {
+        let Some(upstream) = &replication[&id].upstream else {
+            continue;
+        };
+        let lag = upstream.lag.unwrap_or(0.0);
+        let (status, problem) = if !upstream.is_follow() {
+            let mut problem = format!("replica {}: upstream is {}", id, upstream.status);
+            if let Some(message) = &upstream.message {
+                problem.push_str(": ");
+                problem.push_str(message);
+            }
+            (Status::Critical, problem)
+        } else if lag > thresholds.replication_lag_critical {
+            (
+                Status::Critical,
+                format!("replica {}: lag is {:.3}s", id, lag),
+            )
+        } else if lag > thresholds.replication_lag_warning {
+            (
+                Status::Warning,
+                format!("replica {}: lag is {:.3}s", id, lag),
+            )
+        } else {
+            continue;
+        };
+        res.status = res.status.max(status);
+        problems.push(problem);
+    }
+    res.details = problems.join("; ");
+    res
+}
+
+fn check_slab(thresholds: &Thresholds) -> CheckResult {
+    let info = match crate::slab::info() {
+        Ok(info) => info,
+        Err(e) => return CheckResult::critical(e.to_string()),
+    };
+    let ratio = info.quota_used_ratio();
+    let details = || format!("memtx memory quota is {:.1}% used", ratio * 100.0);
+    if ratio > thresholds.slab_quota_critical {
+        CheckResult::critical(details())
+    } else if ratio > thresholds.slab_quota_warning {
+        CheckResult::warning(details())
+    } else {
+        CheckResult::ok()
+    }
+}
+
+/// Registers a health check called `name` which is [`Status::Critical`] if
+/// the connection is not established.
+///
+/// The check doesn't keep the connection alive, once it's dropped the check
+/// is [`Status::Critical`] too.
+#[cfg(feature = "net_box")]
+pub fn register_conn(
+    name: impl Into<String>,
+    conn: &Rc<crate::net_box::Conn>,
+) -> crate::Result<()> {
+    let conn = Rc::downgrade(conn);
+    register(name, move || match conn.upgrade() {
+        Some(conn) if conn.is_connected() => CheckResult::ok(),
+        Some(_) => CheckResult::critical("not connected"),
+        None => CheckResult::critical("connection is closed"),
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// exposing
+////////////////////////////////////////////////////////////////////////////////
+
+/// Defines a global lua function `name` which returns the [`report`].
+///
+/// The function can be called via iproto once it's registered with
+/// `box.schema.func.create(name)` and the users have the permission to
+/// execute it.
+pub fn expose(name: &str) -> crate::Result<()> {
+    crate::lua_state().set(name, tlua::function0(report));
+    Ok(())
+}
+
+/// Starts a fiber which builds the [`report`] every `interval` and
+/// broadcasts it to the iproto watchers of `key` (see `box.broadcast`) each
+/// time it changes.
+///
+/// The fiber stops once it's cancelled (see [`fiber::cancel`]).
+///
+/// Returns the id of the started fiber.
+pub fn start_broadcast(key: impl Into<String>, interval: Duration) -> crate::Result<FiberId> {
+    let key = key.into();
+    fiber::Builder::new()
+        .name("health_broadcast")
+        .func(move || {
+            let mut last = None;
+            while !fiber::is_cancelled() {
+                let report = report();
+                if last.as_ref() != Some(&report) {
+                    let res = crate::lua_state()
+                        .exec_with("box.broadcast(...)", (&key, &report))
+                        .map_err(tlua::LuaError::from);
+                    match res {
+                        Ok(()) => last = Some(report),
+                        Err(e) => crate::say_warn!("failed broadcasting health report: {}", e),
+                    }
+                }
+                fiber::sleep(interval);
+            }
+        })
+        .start_non_joinable()
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn custom_checks() {
+        register("test.ok", CheckResult::ok).unwrap();
+        register("test.warning", || CheckResult::warning("hmm")).unwrap();
+        let e = register("test.ok", CheckResult::ok).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IllegalParams: health check 'test.ok' is already registered"
+        );
+
+        let report = report();
+        assert_eq!(report.status, Status::Warning);
+        assert_eq!(report.checks["test.ok"], CheckResult::ok());
+        assert_eq!(report.checks["test.warning"].details, "hmm");
+
+        register("test.panic", || panic!("oh no")).unwrap();
+        let report = super::report();
+        assert_eq!(report.status, Status::Critical);
+        assert_eq!(
+            report.checks["test.panic"],
+            CheckResult::critical("check panicked: oh no")
+        );
+
+        assert!(unregister("test.ok"));
+        assert!(unregister("test.warning"));
+        assert!(unregister("test.panic"));
+        assert!(!unregister("test.ok"));
+        assert!(super::report().checks.is_empty());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn builtin_checks() {
+        register_builtin_checks(&Thresholds::default()).unwrap();
+        let _guard = crate::test::util::on_scope_exit(|| {
+            unregister("box.status");
+            unregister("box.replication");
+            unregister("box.slab");
+        });
+
+        let report = report();
+        assert_eq!(report.status, Status::Ok, "{:?}", report);
+        assert_eq!(report.checks.len(), 3);
+
+        expose("test_health_report").unwrap();
+        let status: Status = crate::lua_state()
+            .eval("return test_health_report().status")
+            .unwrap();
+        assert_eq!(status, Status::Ok);
+        crate::lua_state().exec("test_health_report = nil").unwrap();
+    }
+}
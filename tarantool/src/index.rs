@@ -8,9 +8,10 @@
 //! - [Indexes](https://www.tarantool.io/en/doc/latest/book/box/data_model/#indexes)
 //! - [Lua reference: Submodule box.index](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_index/)
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::mem::MaybeUninit;
-use std::ops::Range;
+use std::ops::{Bound, Range, RangeBounds};
 use std::ptr::null_mut;
 
 use serde::{Deserialize, Serialize};
@@ -18,8 +19,10 @@ use serde::{Deserialize, Serialize};
 use crate::error::{Error, TarantoolError, TarantoolErrorCode};
 use crate::ffi::tarantool as ffi;
 use crate::msgpack;
+use crate::scan::Scan;
 use crate::space::{Space, SpaceId, SystemSpace};
-use crate::tuple::{Encode, ToTupleBuffer, Tuple, TupleBuffer};
+use crate::stream::TupleStream;
+use crate::tuple::{DecodeOwned, Encode, ToTupleBuffer, Tuple, TupleBuffer};
 use crate::tuple::{KeyDef, KeyDefPart};
 use crate::tuple_from_box_api;
 use crate::unwrap_or;
@@ -139,7 +142,33 @@ impl<'a> Builder<'a> {
         run_count_per_level(run_count_per_level: u32)
         run_size_ratio(run_size_ratio: f32)
         sequence(sequence: impl Into<SequenceOpt>)
-        func(func: String)
+        func(func: impl Into<String>)
+        hint(hint: bool)
+    }
+
+    /// Set the index type to [`IndexType::Tree`].
+    #[inline(always)]
+    pub fn tree(self) -> Self {
+        self.index_type(IndexType::Tree)
+    }
+
+    /// Set the index type to [`IndexType::Hash`].
+    #[inline(always)]
+    pub fn hash(self) -> Self {
+        self.index_type(IndexType::Hash)
+    }
+
+    /// Set the index type to [`IndexType::Bitset`].
+    #[inline(always)]
+    pub fn bitset(self) -> Self {
+        self.index_type(IndexType::Bitset)
+    }
+
+    /// Set the index type to [`IndexType::Rtree`]. Use [`Self::dimension`]
+    /// and [`Self::distance`] to configure it.
+    #[inline(always)]
+    pub fn rtree(self) -> Self {
+        self.index_type(IndexType::Rtree)
     }
 
     /// Add a part to the index's parts list.
@@ -224,9 +253,10 @@ pub struct IndexOptions {
     pub run_count_per_level: Option<u32>,
     pub run_size_ratio: Option<f32>,
     pub sequence: Option<SequenceOpt>,
+    /// Name of the function for a functional index.
     pub func: Option<String>,
-    // Only for Tarantool >= 2.6
-    // pub hint: Option<bool>,
+    /// Only for Tarantool >= 2.6
+    pub hint: Option<bool>,
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -386,12 +416,18 @@ pub struct Part {
     pub field: NumOrStr,
     #[serde(default)]
     pub r#type: Option<FieldType>,
-    #[serde(default)]
+    /// Name of the collation. Note that the `_index` system space stores
+    /// collation ids rather than names, these are converted into names when
+    /// deserializing.
+    #[serde(default, deserialize_with = "deserialize_collation")]
     pub collation: Option<String>,
     #[serde(default)]
     pub is_nullable: Option<bool>,
     #[serde(default)]
     pub path: Option<String>,
+    /// Only for Tarantool >= 2.8
+    #[serde(default)]
+    pub exclude_null: Option<bool>,
 }
 
 macro_rules! define_setters {
@@ -415,6 +451,7 @@ impl Part {
             collation: None,
             is_nullable: None,
             path: None,
+            exclude_null: None,
         }
     }
 
@@ -423,12 +460,56 @@ impl Part {
         collation(collation: impl Into<String>)
         is_nullable(is_nullable: bool)
         path(path: impl Into<String>)
+        exclude_null(exclude_null: bool)
     }
 
     #[inline(always)]
     pub fn new(fi: impl Into<NumOrStr>, ft: FieldType) -> Self {
         Self::field(fi).field_type(ft)
     }
+
+    /// Returns the field number of the part or `None` if the field is
+    /// specified by name.
+    ///
+    /// Parts of the [`Metadata`] returned from [`Index::meta`] always have
+    /// field numbers.
+    #[inline(always)]
+    pub fn field_no(&self) -> Option<u32> {
+        match self.field {
+            NumOrStr::Num(field_no) => Some(field_no),
+            NumOrStr::Str(_) => None,
+        }
+    }
+}
+
+/// Accepts either a collation name or a collation id, in which case the name
+/// is looked up in the `_collation` system space.
+fn deserialize_collation<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NameOrId {
+        Name(String),
+        Id(u32),
+    }
+
+    let id = match Option::<NameOrId>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(NameOrId::Name(name)) => return Ok(Some(name)),
+        Some(NameOrId::Id(id)) => id,
+    };
+    let sys_space: Space = SystemSpace::Collation.into();
+    let tuple = sys_space
+        .get(&[id])
+        .map_err(serde::de::Error::custom)?
+        .ok_or_else(|| serde::de::Error::custom(format!("collation #{} not found", id)))?;
+    let name = tuple
+        .field(1)
+        .map_err(serde::de::Error::custom)?
+        .ok_or_else(|| serde::de::Error::custom(format!("collation #{} has no name", id)))?;
+    Ok(Some(name))
 }
 
 impl From<&str> for Part {
@@ -552,9 +633,8 @@ impl Index {
     {
         let buf;
         let data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = key.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(key)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         tuple_from_box_api!(
@@ -603,6 +683,101 @@ impl Index {
         })
     }
 
+    /// Iterates over the tuples with the keys within the `range` in ascending
+    /// order. The range bounds are mapped onto the iterator types, e.g.
+    /// `index.range((10,)..(20,))` starts a `GE` iteration from `(10,)` and
+    /// stops at the first tuple with the key not less than `(20,)`.
+    ///
+    /// The bounds can be partial keys, in which case only the given parts are
+    /// compared, e.g. `index.range((1, 10)..=(1,))` returns all tuples
+    /// with the first key part equal to `1` and the second one not less than
+    /// `10`.
+    ///
+    /// Only makes sense for the `TREE` indexes.
+    ///
+    /// ```no_run
+    /// use tarantool::space::Space;
+    ///
+    /// let space = Space::find("orders").unwrap();
+    /// for tuple in space.range((10,)..=(20,)).unwrap() {
+    ///     println!("{:?}", tuple);
+    /// }
+    /// // The newest orders first.
+    /// let last = space.range_rev((100,)..).unwrap().take(10);
+    /// # drop(last);
+    /// ```
+    #[inline]
+    pub fn range<K, R>(&self, range: R) -> Result<RangeIterator, Error>
+    where
+        K: ToTupleBuffer,
+        R: RangeBounds<K>,
+    {
+        let iter = match range.start_bound() {
+            Bound::Included(key) => self.select(IteratorType::GE, key)?,
+            Bound::Excluded(key) => self.select(IteratorType::GT, key)?,
+            Bound::Unbounded => self.select(IteratorType::GE, &())?,
+        };
+        RangeIterator::new(self, iter, range.end_bound(), Ordering::Greater)
+    }
+
+    /// Same as [`Index::range`] but iterates in descending order. E.g.
+    /// `index.range_rev((10,)..=(20,))` starts a `LE` iteration from `(20,)`
+    /// and stops at the first tuple with the key less than `(10,)`.
+    #[inline]
+    pub fn range_rev<K, R>(&self, range: R) -> Result<RangeIterator, Error>
+    where
+        K: ToTupleBuffer,
+        R: RangeBounds<K>,
+    {
+        let iter = match range.end_bound() {
+            Bound::Included(key) => self.select(IteratorType::LE, key)?,
+            Bound::Excluded(key) => self.select(IteratorType::LT, key)?,
+            Bound::Unbounded => self.select(IteratorType::LE, &())?,
+        };
+        RangeIterator::new(self, iter, range.start_bound(), Ordering::Less)
+    }
+
+    /// Iterates over all of the tuples in descending order, the reverse of
+    /// `index.select(IteratorType::All, &())`.
+    #[inline(always)]
+    pub fn pairs_reverse(&self) -> Result<IndexIterator, Error> {
+        self.select(IteratorType::LE, &())
+    }
+
+    /// Returns an async stream of the tuples with the keys within the `range`
+    /// in ascending order (see [`Index::range`]) decoded into `T`. The tuples
+    /// are fetched in batches, see [`TupleStream`] for details.
+    ///
+    /// ```no_run
+    /// use tarantool::fiber;
+    /// use tarantool::fiber::r#async::futures::TryStreamExt;
+    /// use tarantool::index::Index;
+    ///
+    /// # let index: Index = unimplemented!();
+    /// let names: Vec<(u64, String)> = fiber::block_on(
+    ///     index
+    ///         .stream::<(u64, String), _>((10,)..(20,))
+    ///         .unwrap()
+    ///         .try_collect(),
+    /// )
+    /// .unwrap();
+    /// ```
+    #[inline]
+    pub fn stream<T, K>(&self, range: impl RangeBounds<K>) -> Result<TupleStream<T>, Error>
+    where
+        T: DecodeOwned,
+        K: ToTupleBuffer,
+    {
+        Ok(TupleStream::new(self.range(range)?))
+    }
+
+    /// Returns a scan of the index which can filter the tuples with a lua
+    /// expression or a rust closure, see [`crate::scan`].
+    #[inline(always)]
+    pub fn scan(&self) -> Scan {
+        Scan::new(self.clone())
+    }
+
     /// Delete a tuple identified by a key.
     ///
     /// Same as [space.delete()](../space/struct.Space.html#method.delete), but a key is searched in this index instead
@@ -618,9 +793,8 @@ impl Index {
     {
         let buf;
         let data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = key.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(key)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         tuple_from_box_api!(
@@ -654,9 +828,8 @@ impl Index {
     {
         let key_buf;
         let key_data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            key_buf = key.to_tuple_buffer()?;
-            key_buf.as_ref()
+            key_buf = crate::tuple::lease_tuple_data(key)?;
+            key_buf.as_slice()
         });
         let mut ops_buf = Vec::with_capacity(4 + ops.as_ref().len() * 4);
         msgpack::write_array(&mut ops_buf, ops.as_ref())?;
@@ -672,9 +845,8 @@ impl Index {
     {
         let key_buf;
         let key_data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            key_buf = key.to_tuple_buffer()?;
-            key_buf.as_ref()
+            key_buf = crate::tuple::lease_tuple_data(key)?;
+            key_buf.as_slice()
         });
         let mut ops_buf = Vec::with_capacity(128);
         msgpack::write_array(&mut ops_buf, ops)?;
@@ -716,9 +888,8 @@ impl Index {
     {
         let value_buf;
         let value_data = unwrap_or!(value.tuple_data(), {
-            // TODO: use region allocation for this
-            value_buf = value.to_tuple_buffer()?;
-            value_buf.as_ref()
+            value_buf = crate::tuple::lease_tuple_data(value)?;
+            value_buf.as_slice()
         });
         let mut ops_buf = Vec::with_capacity(4 + ops.as_ref().len() * 4);
         msgpack::write_array(&mut ops_buf, ops.as_ref())?;
@@ -734,9 +905,8 @@ impl Index {
     {
         let value_buf;
         let value_data = unwrap_or!(value.tuple_data(), {
-            // TODO: use region allocation for this
-            value_buf = value.to_tuple_buffer()?;
-            value_buf.as_ref()
+            value_buf = crate::tuple::lease_tuple_data(value)?;
+            value_buf.as_slice()
         });
         let mut ops_buf = Vec::with_capacity(128);
         msgpack::write_array(&mut ops_buf, ops)?;
@@ -767,6 +937,77 @@ impl Index {
         })
     }
 
+    /// Delete a tuple identified by a `key` and return the deleted tuple.
+    ///
+    /// Same as [`Index::delete`], but returns an error with code
+    /// [`TarantoolErrorCode::TupleNotFound`] if there's no such tuple.
+    #[inline]
+    #[track_caller]
+    pub fn delete_ret<K>(&self, key: &K) -> Result<Tuple, Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        let res = self.delete(key)?;
+        res.ok_or_else(|| self.tuple_not_found())
+    }
+
+    /// Update a tuple and return the updated tuple.
+    ///
+    /// Same as [`Index::update`], but returns an error with code
+    /// [`TarantoolErrorCode::TupleNotFound`] if there's no such tuple.
+    #[inline]
+    #[track_caller]
+    pub fn update_ret<K, Op>(&self, key: &K, ops: impl AsRef<[Op]>) -> Result<Tuple, Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+        Op: ToTupleBuffer,
+    {
+        let res = self.update(key, ops)?;
+        res.ok_or_else(|| self.tuple_not_found())
+    }
+
+    /// Execute an UPSERT request and return the resulting tuple, i.e. either
+    /// the inserted or the updated one.
+    ///
+    /// Tarantool doesn't return the resulting tuple from UPSERT requests, so
+    /// this is emulated by looking up the tuple by its key right after the
+    /// upsert. Both operations are done within a single transaction, which
+    /// is started if there's no active one already.
+    ///
+    /// This index must be the primary one.
+    ///
+    /// See also: [index.upsert()](#method.upsert)
+    pub fn upsert_ret<T, Op>(&self, value: &T, ops: impl AsRef<[Op]>) -> Result<Tuple, Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+        Op: ToTupleBuffer,
+    {
+        let value = Tuple::new(value)?;
+        let key = self.meta()?.to_key_def().extract_key(&value)?;
+        let upsert_and_get = || -> Result<Tuple, Error> {
+            self.upsert(&value, ops)?;
+            let res = self.get(&key)?;
+            res.ok_or_else(|| self.tuple_not_found())
+        };
+        if crate::transaction::is_in_transaction() {
+            upsert_and_get()
+        } else {
+            Ok(crate::transaction::transaction(upsert_and_get)?)
+        }
+    }
+
+    #[track_caller]
+    fn tuple_not_found(&self) -> Error {
+        crate::error::BoxError::new(
+            TarantoolErrorCode::TupleNotFound,
+            format!(
+                "tuple doesn't exist in index #{} of space #{}",
+                self.index_id, self.space_id
+            ),
+        )
+        .into()
+    }
+
     /// Return the number of elements in the index.
     #[inline(always)]
     pub fn len(&self) -> Result<usize, Error> {
@@ -823,9 +1064,8 @@ impl Index {
     {
         let buf;
         let data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = key.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(key)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         tuple_from_box_api!(
@@ -851,9 +1091,8 @@ impl Index {
     {
         let buf;
         let data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = key.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(key)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         tuple_from_box_api!(
@@ -878,9 +1117,8 @@ impl Index {
     {
         let buf;
         let data = unwrap_or!(key.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = key.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(key)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         let result = unsafe {
@@ -900,6 +1138,84 @@ impl Index {
         }
     }
 
+    /// Estimate the number of tuples that match the provided key without
+    /// iterating over all of them, which is what [`Index::count`] does.
+    ///
+    /// Useful for choosing between the indexes or the iterator types when
+    /// planning a query.
+    ///
+    /// - `type` - iterator type
+    /// - `key` - encoded key in MsgPack Array format (`[part1, part2, ...]`).
+    ///
+    /// If at most [`CountEstimate::SCAN_LIMIT`] tuples match the key, the
+    /// exact count is returned. Otherwise the fraction of the matching tuples
+    /// is estimated from [`CountEstimate::SAMPLES`] random tuples of the
+    /// index (see [`Index::random`]). If the index doesn't support random
+    /// sampling (e.g. vinyl indexes or the iterator types other than `Eq`,
+    /// `Req`, `All`, `LT`, `LE`, `GE` or `GT`) the total number of tuples
+    /// in the index is returned as an upper bound.
+    pub fn estimate_count<K>(
+        &self,
+        iterator_type: IteratorType,
+        key: &K,
+    ) -> Result<CountEstimate, Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        let key = key.to_tuple_buffer()?;
+        let key_len = rmp::decode::read_array_len(&mut key.as_ref()).map_err(Error::other)?;
+        // An empty key matches all of the tuples regardless of iterator type.
+        let iterator_type = if key_len == 0 {
+            IteratorType::All
+        } else {
+            iterator_type
+        };
+
+        let len = self.len()?;
+        if iterator_type == IteratorType::All {
+            return Ok(CountEstimate::exact(len));
+        }
+
+        let scanned = self
+            .select(iterator_type, &key)?
+            .take(CountEstimate::SCAN_LIMIT + 1)
+            .count();
+        if scanned <= CountEstimate::SCAN_LIMIT {
+            return Ok(CountEstimate::exact(scanned));
+        }
+
+        let upper_bound = CountEstimate {
+            count: len,
+            is_exact: false,
+        };
+        let matches: fn(Ordering) -> bool = match iterator_type {
+            IteratorType::Eq | IteratorType::Req => Ordering::is_eq,
+            IteratorType::LT => Ordering::is_lt,
+            IteratorType::LE => Ordering::is_le,
+            IteratorType::GE => Ordering::is_ge,
+            IteratorType::GT => Ordering::is_gt,
+            _ => return Ok(upper_bound),
+        };
+        let key_def = self.meta()?.to_key_def();
+        let mut matched = 0;
+        for _ in 0..CountEstimate::SAMPLES {
+            let Ok(Some(tuple)) = self.random(crate::random::u32()) else {
+                return Ok(upper_bound);
+            };
+            if matches(key_def.compare_with_key(&tuple, &key)) {
+                matched += 1;
+            }
+        }
+
+        let count = len * matched / CountEstimate::SAMPLES;
+        Ok(CountEstimate {
+            // The scan has shown there are more tuples than the sampling may
+            // suggest.
+            count: count.clamp(CountEstimate::SCAN_LIMIT + 1, len),
+            is_exact: false,
+        })
+    }
+
     /// Extract key from `tuple` according to key definition of given
     /// index.
     ///
@@ -924,6 +1240,31 @@ impl Index {
     }
 }
 
+/// The number of tuples matching a key as returned from
+/// [`Index::estimate_count`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CountEstimate {
+    pub count: usize,
+    /// `true` if `count` is the exact number of the matching tuples.
+    pub is_exact: bool,
+}
+
+impl CountEstimate {
+    /// The maximum number of tuples which are counted exactly.
+    pub const SCAN_LIMIT: usize = 64;
+
+    /// The number of random tuples the estimation is based on.
+    pub const SAMPLES: usize = 256;
+
+    #[inline(always)]
+    fn exact(count: usize) -> Self {
+        Self {
+            count,
+            is_exact: true,
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Metadata
 ////////////////////////////////////////////////////////////////////////////////
@@ -945,6 +1286,13 @@ impl Encode for Metadata<'_> {}
 pub struct FieldMustBeNumber(pub String);
 
 impl Metadata<'_> {
+    /// Returns `true` if the index is unique.
+    #[inline]
+    pub fn is_unique(&self) -> bool {
+        // Tarantool treats the index as unique if the option is omitted.
+        !matches!(self.opts.get("unique"), Some(Value::Bool(false)))
+    }
+
     /// Construct a [`KeyDef`] instance from index parts.
     ///
     /// # Panicking
@@ -1035,6 +1383,88 @@ impl Drop for IndexIterator {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// RangeIterator
+////////////////////////////////////////////////////////////////////////////////
+
+/// An iterator over the tuples with the keys within a range, see
+/// [`Index::range`] and [`Index::range_rev`].
+pub struct RangeIterator {
+    iter: IndexIterator,
+    end: Option<RangeEnd>,
+    done: bool,
+}
+
+struct RangeEnd {
+    key_def: KeyDef,
+    key: TupleBuffer,
+    inclusive: bool,
+    /// The result of the comparison of a tuple with `key` which means the
+    /// tuple is past the end of the range.
+    past: Ordering,
+}
+
+impl RangeIterator {
+    fn new<K>(
+        index: &Index,
+        iter: IndexIterator,
+        end: Bound<&K>,
+        past: Ordering,
+    ) -> Result<Self, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        let (key, inclusive) = match end {
+            Bound::Included(key) => (key, true),
+            Bound::Excluded(key) => (key, false),
+            Bound::Unbounded => {
+                return Ok(Self {
+                    iter,
+                    end: None,
+                    done: false,
+                })
+            }
+        };
+        let end = RangeEnd {
+            key_def: index.meta()?.to_key_def(),
+            key: key.to_tuple_buffer()?,
+            inclusive,
+            past,
+        };
+        Ok(Self {
+            iter,
+            end: Some(end),
+            done: false,
+        })
+    }
+}
+
+impl Iterator for RangeIterator {
+    type Item = Tuple;
+
+    #[inline]
+    fn next(&mut self) -> Option<Tuple> {
+        if self.done {
+            return None;
+        }
+        let tuple = self.iter.next()?;
+        if let Some(end) = &self.end {
+            let ord = end.key_def.compare_with_key(&tuple, &end.key);
+            if ord == end.past || ord == Ordering::Equal && !end.inclusive {
+                self.done = true;
+                return None;
+            }
+        }
+        Some(tuple)
+    }
+}
+
+impl std::fmt::Debug for RangeIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RangeIterator").finish_non_exhaustive()
+    }
+}
+
 #[cfg(feature = "internal_test")]
 mod tests {
     use super::*;
@@ -1116,6 +1546,25 @@ mod tests {
                 ],
             }
         );
+        assert!(!meta.is_unique());
+        assert_eq!(meta.parts[1].field_no(), Some(2));
+
+        let index = space
+            .index_builder("collated")
+            .part(Part::new("s", FieldType::String).collation("unicode_ci"))
+            .create()
+            .unwrap();
+        let meta = index.meta().unwrap();
+        assert!(meta.is_unique());
+        assert_eq!(
+            meta.parts,
+            vec![Part {
+                field: 1.into(),
+                r#type: Some(FieldType::String),
+                collation: Some("unicode_ci".into()),
+                ..Default::default()
+            }]
+        );
 
         space.drop().unwrap();
     }
@@ -1166,6 +1615,61 @@ mod tests {
         space.drop().unwrap();
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn estimate_count() {
+        let space = Space::builder("test_estimate_count_space")
+            .field(("id", space::FieldType::Unsigned))
+            .field(("group", space::FieldType::Unsigned))
+            .create()
+            .unwrap();
+        let pk = space.index_builder("pk").create().unwrap();
+        let by_group = space
+            .index_builder("by_group")
+            .unique(false)
+            .part("group")
+            .create()
+            .unwrap();
+        for id in 0..1000 {
+            space.insert(&(id, id % 10)).unwrap();
+        }
+        crate::random::seed(42);
+
+        let exact = |count| CountEstimate {
+            count,
+            is_exact: true,
+        };
+        assert_eq!(
+            pk.estimate_count(IteratorType::All, &()).unwrap(),
+            exact(1000)
+        );
+        assert_eq!(
+            pk.estimate_count(IteratorType::LT, &()).unwrap(),
+            exact(1000)
+        );
+        assert_eq!(
+            pk.estimate_count(IteratorType::Eq, &(5,)).unwrap(),
+            exact(1)
+        );
+        assert_eq!(
+            pk.estimate_count(IteratorType::GE, &(990,)).unwrap(),
+            exact(10)
+        );
+        assert_eq!(
+            pk.estimate_count(IteratorType::GT, &(2000,)).unwrap(),
+            exact(0)
+        );
+
+        let estimate = pk.estimate_count(IteratorType::LT, &(500,)).unwrap();
+        assert!(!estimate.is_exact);
+        assert!((350..650).contains(&estimate.count), "{:?}", estimate);
+
+        let estimate = by_group.estimate_count(IteratorType::Eq, &(3,)).unwrap();
+        assert!(!estimate.is_exact);
+        assert!((65..200).contains(&estimate.count), "{:?}", estimate);
+
+        space.drop().unwrap();
+    }
+
     #[crate::test(tarantool = "crate")]
     fn sys_index_metadata() {
         let sys_index = Space::from(SystemSpace::Index);
@@ -1174,4 +1678,85 @@ mod tests {
             let _meta: Metadata = tuple.decode().unwrap();
         }
     }
+
+    #[crate::test(tarantool = "crate")]
+    fn range() {
+        let space = Space::builder(&crate::temp_space_name!())
+            .field(("a", space::FieldType::Unsigned))
+            .field(("b", space::FieldType::Unsigned))
+            .create()
+            .unwrap();
+        let pk = space
+            .index_builder("pk")
+            .part("a")
+            .part("b")
+            .create()
+            .unwrap();
+        for a in 1..=3 {
+            for b in 1..=3 {
+                space.insert(&(a, b)).unwrap();
+            }
+        }
+        let keys = |iter: RangeIterator| -> Vec<(u32, u32)> {
+            iter.map(|t| t.decode().unwrap()).collect()
+        };
+
+        assert_eq!(
+            keys(pk.range((1, 3)..(2, 3)).unwrap()),
+            [(1, 3), (2, 1), (2, 2)]
+        );
+        assert_eq!(
+            keys(pk.range((1, 3)..=(2, 3)).unwrap()),
+            [(1, 3), (2, 1), (2, 2), (2, 3)]
+        );
+        // Partial keys.
+        assert_eq!(
+            keys(pk.range((2,)..=(2,)).unwrap()),
+            [(2, 1), (2, 2), (2, 3)]
+        );
+        assert_eq!(
+            keys(pk.range((2,)..(3,)).unwrap()),
+            [(2, 1), (2, 2), (2, 3)]
+        );
+        assert_eq!(keys(pk.range((3, 2)..).unwrap()), [(3, 2), (3, 3)]);
+        assert_eq!(keys(pk.range(..(1, 3)).unwrap()), [(1, 1), (1, 2)]);
+        assert_eq!(keys(pk.range((3,)..(1,)).unwrap()), []);
+        let excluded = (Bound::Excluded((1,)), Bound::Excluded((3,)));
+        assert_eq!(keys(pk.range(excluded).unwrap()), [(2, 1), (2, 2), (2, 3)]);
+
+        assert_eq!(
+            keys(pk.range_rev((1, 3)..(2, 3)).unwrap()),
+            [(2, 2), (2, 1), (1, 3)]
+        );
+        assert_eq!(
+            keys(pk.range_rev((1, 3)..=(2, 3)).unwrap()),
+            [(2, 3), (2, 2), (2, 1), (1, 3)]
+        );
+        assert_eq!(
+            keys(pk.range_rev((2,)..=(2,)).unwrap()),
+            [(2, 3), (2, 2), (2, 1)]
+        );
+        assert_eq!(keys(pk.range_rev((3, 2)..).unwrap()), [(3, 3), (3, 2)]);
+        assert_eq!(keys(pk.range_rev(..(1, 3)).unwrap()), [(1, 2), (1, 1)]);
+        assert_eq!(
+            keys(space.range_rev(excluded).unwrap()),
+            [(2, 3), (2, 2), (2, 1)]
+        );
+
+        // The iterator is fused at the end of the range.
+        let mut iter = space.range((1,)..(2,)).unwrap();
+        assert_eq!(iter.by_ref().count(), 3);
+        assert!(iter.next().is_none());
+
+        let all: Vec<(u32, u32)> = space
+            .pairs_reverse()
+            .unwrap()
+            .map(|t| t.decode().unwrap())
+            .collect();
+        assert_eq!(all.len(), 9);
+        assert_eq!(all[0], (3, 3));
+        assert_eq!(all[8], (1, 1));
+
+        space.drop().unwrap();
+    }
 }
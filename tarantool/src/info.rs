@@ -0,0 +1,372 @@
+//! Instance state introspection (`box.info`) and leadership control
+//! (`box.ctl`).
+//!
+//! [`Info::current`] returns the whole `box.info` decoded into rust structs,
+//! [`replication`], [`election`] and [`synchro`] return the corresponding
+//! parts of it. [`gc`] returns the state of the checkpoint and WAL garbage
+//! collection.
+//!
+//! ```no_run
+//! use tarantool::info;
+//!
+//! for (id, replica) in info::replication().unwrap() {
+//!     if let Some(upstream) = &replica.upstream {
+//!         println!("replica {id}: {} (lag {:?})", upstream.status, upstream.lag);
+//!     }
+//! }
+//! ```
+//!
+//! See also [box.info reference](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_info/).
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber::WaitError;
+use crate::vclock::Vclock;
+
+/// Lua function which returns a deep copy of the table without the `box.NULL`
+/// values, which can't be read as rust `Option`s.
+const STRIP_NULLS: &str = "
+local function strip_nulls(v)
+    if type(v) ~= 'table' then return v end
+    local res = {}
+    for k, x in pairs(v) do
+        if x ~= nil then res[k] = strip_nulls(x) end
+    end
+    return res
+end
+";
+
+crate::define_str_enum! {
+    /// RAFT state of the instance, see [`ElectionInfo::state`].
+    pub enum ElectionState {
+        Follower = "follower",
+        Candidate = "candidate",
+        Leader = "leader",
+    }
+}
+
+/// Contents of `box.info` as returned by [`Info::current`].
+///
+/// Fields which are not supported by the current tarantool version are
+/// `None`.
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct Info {
+    /// Id of the instance in the replica set, `None` for the anonymous
+    /// replicas.
+    pub id: Option<u32>,
+    pub uuid: String,
+    pub name: Option<String>,
+    /// LSN of the last record written by this instance to its WAL.
+    pub lsn: u64,
+    /// `true` if the instance is in read-only mode.
+    pub ro: bool,
+    /// Why the instance is in read-only mode, e.g. `"config"`,
+    /// `"election"` or `"synchro"`.
+    pub ro_reason: Option<String>,
+    /// Instance status, e.g. `"running"`, `"loading"` or `"orphan"`.
+    pub status: String,
+    pub version: String,
+    pub pid: u32,
+    /// Number of seconds since the instance started.
+    pub uptime: u64,
+    pub vclock: Vclock,
+    /// Replication state of the replica set members indexed by their ids.
+    pub replication: HashMap<u32, ReplicaInfo>,
+    pub election: Option<ElectionInfo>,
+    pub synchro: Option<SynchroInfo>,
+    /// See [`schema::version`](crate::schema::version).
+    pub schema_version: u64,
+}
+
+impl Info {
+    /// Returns the current state of the instance.
+    ///
+    /// Returns an error if `box.cfg{ .. }` was not called yet.
+    pub fn current() -> crate::Result<Self> {
+        let info = crate::lua_state()
+            .eval_with(
+                &format!(
+                    "{STRIP_NULLS}
+                    local res = strip_nulls(box.info())
+                    res.schema_version = ...
+                    return res"
+                ),
+                crate::schema::version(),
+            )
+            .map_err(tlua::LuaError::from)?;
+        Ok(info)
+    }
+}
+
+/// State of a replica set member, an element of `box.info.replication`.
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct ReplicaInfo {
+    pub id: u32,
+    pub uuid: String,
+    pub name: Option<String>,
+    /// LSN of the last record received from this replica.
+    pub lsn: u64,
+    /// Replication from the replica to this instance, `None` for this
+    /// instance itself or if it isn't configured.
+    pub upstream: Option<UpstreamInfo>,
+    /// Replication from this instance to the replica.
+    pub downstream: Option<DownstreamInfo>,
+}
+
+/// See [`ReplicaInfo::upstream`].
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct UpstreamInfo {
+    /// Replication status, e.g. `"follow"`, `"sync"`, `"connect"`,
+    /// `"disconnected"` or `"stopped"`.
+    pub status: String,
+    /// URI of the replica.
+    pub peer: Option<String>,
+    /// Seconds since the last event received from the replica.
+    pub idle: Option<f64>,
+    /// Replication lag in seconds.
+    pub lag: Option<f64>,
+    /// The last replication error.
+    pub message: Option<String>,
+}
+
+impl UpstreamInfo {
+    /// Returns `true` if the replication is working normally.
+    #[inline(always)]
+    pub fn is_follow(&self) -> bool {
+        self.status == "follow"
+    }
+}
+
+/// See [`ReplicaInfo::downstream`].
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct DownstreamInfo {
+    /// Replication status, e.g. `"follow"` or `"stopped"`.
+    pub status: String,
+    /// Seconds since the last event received from the replica.
+    pub idle: Option<f64>,
+    /// Replication lag in seconds.
+    pub lag: Option<f64>,
+    /// Vclock acknowledged by the replica.
+    pub vclock: Option<Vclock>,
+    /// The last replication error.
+    pub message: Option<String>,
+}
+
+/// RAFT election state, `box.info.election`.
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct ElectionInfo {
+    pub state: ElectionState,
+    pub term: u64,
+    /// Id of the instance this instance voted for in the current term, `0`
+    /// if it hasn't voted.
+    pub vote: u32,
+    /// Id of the current leader, `0` if it's unknown.
+    pub leader: u32,
+    pub leader_name: Option<String>,
+    /// Seconds since the last event received from the leader.
+    pub leader_idle: Option<f64>,
+}
+
+/// Synchronous replication state, `box.info.synchro`.
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct SynchroInfo {
+    /// Number of replicas which must confirm a synchronous transaction.
+    pub quorum: u32,
+    pub queue: SynchroQueueInfo,
+}
+
+/// Synchronous transaction queue state, `box.info.synchro.queue`.
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct SynchroQueueInfo {
+    /// Number of transactions waiting for the quorum.
+    pub len: u64,
+    /// Id of the instance owning the queue, `0` if it isn't claimed.
+    pub owner: Option<u32>,
+    pub term: Option<u64>,
+    /// `true` if the queue is being promoted or demoted.
+    pub busy: Option<bool>,
+}
+
+/// Garbage collector state, `box.info.gc()`.
+///
+/// See also [`crate::gc::checkpoint_ref`].
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct GcInfo {
+    /// Vclock of the oldest WAL row which is still needed by someone.
+    pub vclock: Vclock,
+    /// Sum of the components of [`Self::vclock`].
+    pub signature: u64,
+    pub checkpoint_is_in_progress: bool,
+    /// The checkpoints kept on disk from the oldest to the newest.
+    pub checkpoints: Vec<GcCheckpoint>,
+    /// The users of the WAL files, e.g. the replicas.
+    pub consumers: Vec<GcConsumer>,
+}
+
+/// A checkpoint kept on disk, see [`GcInfo::checkpoints`].
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct GcCheckpoint {
+    pub vclock: Vclock,
+    pub signature: u64,
+    /// Names of the users of the checkpoint, e.g. `"backup"`. The checkpoint
+    /// and the WAL files after it can't be removed while it's referenced.
+    pub references: Vec<String>,
+}
+
+/// A user of the WAL files, see [`GcInfo::consumers`].
+#[derive(Clone, Debug, PartialEq, tlua::LuaRead)]
+pub struct GcConsumer {
+    /// Name of the consumer, e.g. `"replica <uuid>"`.
+    pub name: String,
+    /// Vclock of the oldest WAL row needed by the consumer.
+    pub vclock: Vclock,
+    pub signature: u64,
+}
+
+fn eval_field<T>(field: &str) -> crate::Result<T>
+where
+    T: for<'l> tlua::LuaRead<
+        tlua::PushGuard<tlua::LuaFunction<tlua::PushGuard<&'l tlua::LuaThread>>>,
+    >,
+{
+    let value = crate::lua_state().eval(&format!(
+        "{STRIP_NULLS}
+        return strip_nulls(box.info.{field})"
+    ))?;
+    Ok(value)
+}
+
+/// Returns the replication state of the replica set members indexed by their
+/// ids.
+pub fn replication() -> crate::Result<HashMap<u32, ReplicaInfo>> {
+    eval_field("replication")
+}
+
+/// Returns the RAFT election state.
+pub fn election() -> crate::Result<ElectionInfo> {
+    eval_field("election")
+}
+
+/// Returns the synchronous replication state.
+pub fn synchro() -> crate::Result<SynchroInfo> {
+    eval_field("synchro")
+}
+
+/// Returns the state of the garbage collector of the checkpoint and WAL
+/// files.
+pub fn gc() -> crate::Result<GcInfo> {
+    eval_field("gc()")
+}
+
+/// Returns `true` if the instance is in read-only mode.
+pub fn is_ro() -> crate::Result<bool> {
+    eval_field("ro")
+}
+
+/// Makes the instance the leader of the replica set, see `box.ctl.promote`.
+///
+/// With the elections enabled this starts a new election round and waits for
+/// its result, otherwise the instance claims the synchronous transaction
+/// queue.
+pub fn promote() -> crate::Result<()> {
+    crate::lua_state().exec("box.ctl.promote()")?;
+    Ok(())
+}
+
+/// Revokes the leadership of the instance, see `box.ctl.demote`.
+pub fn demote() -> crate::Result<()> {
+    crate::lua_state().exec("box.ctl.demote()")?;
+    Ok(())
+}
+
+fn wait_mode(function: &str, timeout: Option<Duration>) -> crate::Result<()> {
+    let timed_out: bool = crate::lua_state()
+        .eval_with(
+            &format!(
+                "local ok, err = pcall(box.ctl.{function}, ...)
+                if ok then return false end
+                if type(err) == 'cdata' and err.code == box.error.TIMEOUT then return true end
+                error(err)"
+            ),
+            timeout.map(|t| t.as_secs_f64()),
+        )
+        .map_err(tlua::LuaError::from)?;
+    if timed_out {
+        return Err(WaitError::Timeout.into());
+    }
+    Ok(())
+}
+
+/// Blocks the current fiber until the instance becomes read-only or until
+/// `timeout` expires (if it's `Some`).
+///
+/// Returns an error with [`TarantoolErrorCode::Timeout`] on timeout.
+#[inline]
+pub fn wait_ro(timeout: Option<Duration>) -> crate::Result<()> {
+    wait_mode("wait_ro", timeout)
+}
+
+/// Blocks the current fiber until the instance becomes writable or until
+/// `timeout` expires (if it's `Some`).
+///
+/// Returns an error with [`TarantoolErrorCode::Timeout`] on timeout.
+#[inline]
+pub fn wait_rw(timeout: Option<Duration>) -> crate::Result<()> {
+    wait_mode("wait_rw", timeout)
+}
+
+/// Returns an error if the instance isn't the RAFT leader.
+///
+/// Use this in the stored procedures which must only run on the leader.
+pub fn check_leader() -> crate::Result<()> {
+    let election = election()?;
+    if election.state != ElectionState::Leader {
+        return Err(BoxError::new(
+            TarantoolErrorCode::Readonly,
+            format!(
+                "instance is not the leader, current leader is {}",
+                election.leader
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn current() {
+        let info = Info::current().unwrap();
+        assert_eq!(info.status, "running");
+        assert!(!info.ro);
+        assert_eq!(info.pid, std::process::id());
+        assert_eq!(info.vclock, Vclock::current());
+        assert_eq!(info.schema_version, crate::schema::version());
+
+        let id = info.id.unwrap();
+        let me = &info.replication[&id];
+        assert_eq!(me.uuid, info.uuid);
+        assert!(me.upstream.is_none());
+        assert_eq!(replication().unwrap(), info.replication);
+
+        assert_eq!(election().unwrap().state, ElectionState::Follower);
+        assert_eq!(synchro().unwrap().queue.len, 0);
+        assert!(!is_ro().unwrap());
+
+        let gc = gc().unwrap();
+        assert!(!gc.checkpoints.is_empty());
+        assert!(!gc.checkpoint_is_in_progress);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn wait() {
+        wait_rw(Some(Duration::ZERO)).unwrap();
+        let e = wait_ro(Some(Duration::from_millis(10))).unwrap_err();
+        assert_eq!(e.to_string(), "box error: Timeout: timeout");
+    }
+}
@@ -57,8 +57,11 @@
 //! [stored procedure]: macro@crate::proc
 pub mod access_control;
 pub mod auth;
+pub mod buffer;
 #[cfg(feature = "picodata")]
 pub mod cbus;
+pub mod cfg;
+pub mod cli;
 pub mod clock;
 pub mod coio;
 pub mod datetime;
@@ -68,29 +71,49 @@ pub mod define_str_enum;
 pub mod error;
 pub mod ffi;
 pub mod fiber;
+pub mod gc;
+pub mod health;
 pub mod index;
+pub mod info;
 pub mod log;
+pub mod lua;
 #[doc(hidden)]
 pub mod msgpack;
 pub mod net_box;
 pub mod network;
+pub mod outbox;
+pub mod pipeline;
 pub mod proc;
-#[cfg(feature = "picodata")]
+pub mod process;
+pub mod random;
 pub mod read_view;
+pub mod region;
+pub mod registry;
+pub mod scan;
 pub mod schema;
 pub mod sequence;
 pub mod session;
+pub mod slab;
+pub mod snapshot;
+pub mod soft_delete;
 pub mod space;
 pub mod sql;
+pub mod stat;
+pub mod stream;
 #[cfg(feature = "test")]
 pub mod test;
 pub mod time;
 pub mod transaction;
 pub mod trigger;
 pub mod tuple;
+#[cfg(feature = "picodata")]
+pub mod tx;
 pub mod util;
 pub mod uuid;
 pub mod vclock;
+#[cfg(feature = "picodata")]
+pub mod watchdog;
+pub mod write_combiner;
 
 /// `#[tarantool::proc]` is a macro attribute for creating stored procedure
 /// functions.
@@ -283,6 +306,24 @@ pub mod vclock;
 /// }
 /// ```
 ///
+/// # Returning multiple rows
+///
+/// A `Vec<T>` is returned as a single msgpack array. Lua stored procedures on
+/// the other hand often return multiple values (e.g. `return
+/// unpack(space:select())`), in which case each value is a separate row of the
+/// response. To return each element of the vector as a separate row use the
+/// [`ReturnRows`] wrapper type or the `rows` attribute parameter. The elements
+/// must implement [`msgpack::Encode`] (e.g. [`Tuple`](crate::tuple::Tuple)).
+/// ```no_run
+/// use tarantool::{index::IteratorType, space::Space, tuple::Tuple};
+///
+/// #[tarantool::proc(rows)]
+/// fn all_users() -> tarantool::Result<Vec<Tuple>> {
+///     let space = Space::find("users").unwrap();
+///     Ok(space.select(IteratorType::All, &())?.collect())
+/// }
+/// ```
+///
 /// # Packed arguments
 ///
 /// By default the stored procedure unpacks the received tuple and assigns the
@@ -307,6 +348,71 @@ pub mod vclock;
 /// In the above example `sum_all` will sum all the inputs values it received
 /// whereas `sum_first_3` will only sum up the first 3 values
 ///
+/// # Raw arguments
+///
+/// Decoding the arguments into owned values may be expensive, e.g. for
+/// procedures receiving large binary blobs. With `packed_args` you can
+/// instead accept [`&RawBytes`] which borrows the arguments from the request
+/// buffer, and return pre-encoded msgpack with `&RawBytes` or [`RawByteBuf`]
+/// to skip the serialization of the result:
+/// ```no_run
+/// use tarantool::tuple::{RawByteBuf, RawBytes};
+///
+/// #[tarantool::proc(packed_args)]
+/// fn blob_len(args: &RawBytes) -> tarantool::Result<usize> {
+///     let blob: &serde_bytes::Bytes = args.array_iter()?.decode_next().unwrap()?;
+///     Ok(blob.len())
+/// }
+///
+/// #[tarantool::proc(packed_args)]
+/// fn echo_first(args: &RawBytes) -> tarantool::Result<&RawBytes> {
+///     Ok(RawBytes::new(args.array_iter()?.next().unwrap_or(&[0xc0])))
+/// }
+///
+/// #[tarantool::proc]
+/// fn cached() -> RawByteBuf {
+///     // E.g. encoded once and cached by the module.
+///     RawByteBuf::from(rmp_serde::to_vec(&("a lot", "of", "data")).unwrap())
+/// }
+/// ```
+///
+/// If only the first arguments need to be decoded, make [`RawArgsTail`] the
+/// last parameter of the procedure (without `packed_args`), it will capture
+/// the rest of the arguments without decoding them:
+/// ```no_run
+/// use tarantool::proc::RawArgsTail;
+///
+/// #[tarantool::proc]
+/// fn count_rest(kind: String, rest: RawArgsTail) -> String {
+///     format!("{}: {} more arguments", kind, rest.len())
+/// }
+/// ```
+///
+/// # Msgpack arguments
+///
+/// The arguments are decoded with `serde` by default. Use the `msgpack_args`
+/// attribute parameter to decode them with [`msgpack::Decode`] instead, e.g.
+/// to collect the unknown keys of a struct argument passed as an MP_MAP with
+/// `#[decode(flatten_unknown)]`. In this case the number of the arguments
+/// must match the number of the parameters exactly.
+/// ```no_run
+/// use std::collections::HashMap;
+/// use tarantool::msgpack::{self, Decode};
+///
+/// #[derive(Decode)]
+/// #[encode(as_map)]
+/// struct Order {
+///     id: u64,
+///     #[decode(flatten_unknown)]
+///     extra: HashMap<String, msgpack::Value>,
+/// }
+///
+/// #[tarantool::proc(msgpack_args)]
+/// fn extra_keys(order: Order) -> Vec<String> {
+///     order.extra.into_keys().collect()
+/// }
+/// ```
+///
 /// # Injecting arguments
 ///
 /// Because the return value of the stored procedure is immediately serialized
@@ -352,10 +458,43 @@ pub mod vclock;
 /// [`TarantoolError::last`]: crate::error::TarantoolError::last
 /// [`Return`]: crate::proc::Return
 /// [`ReturnMsgpack`]: crate::proc::ReturnMsgpack
+/// [`ReturnRows`]: crate::proc::ReturnRows
+/// [`&RawBytes`]: crate::tuple::RawBytes
+/// [`RawByteBuf`]: crate::tuple::RawByteBuf
+/// [`RawArgsTail`]: crate::proc::RawArgsTail
 /// [`Proc::is_public`]: crate::proc::Proc::is_public
 pub use tarantool_proc::stored_proc as proc;
 pub use tlua;
 
+/// Create a lua chunk which is syntax checked at compile time. Returns a
+/// `&'static` [`lua::Chunk`], which loads the code into the lua state the
+/// first time it's called and reuses the loaded function afterwards.
+///
+/// # Example
+/// ```no_run
+/// let chunk = tarantool::lua!(r#"
+///     local space_name = ...
+///     return box.space[space_name]:len()
+/// "#);
+/// let len: usize = chunk.call_with_args("my_space").unwrap();
+/// ```
+///
+/// A syntax error in the code is reported by the compiler:
+/// ```compile_fail
+/// let chunk = tarantool::lua!("return 1 +");
+/// ```
+///
+/// The errors which lua only detects at runtime (e.g. calling a nil value)
+/// are not caught.
+///
+/// When using the macro from inside the tarantool crate or through a
+/// re-export, the path to the crate can be specified explicitly:
+/// ```no_run
+/// # use tarantool as my_tarantool;
+/// let chunk = my_tarantool::lua!(tarantool = "my_tarantool", "return 1");
+/// ```
+pub use tarantool_proc::lua;
+
 /// A re-export of [linkme] crate used inside #[`[tarantool::test]`]
 /// and #[`[tarantool::proc]`] macro attributes.
 pub use linkme;
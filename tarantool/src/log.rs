@@ -2,14 +2,16 @@
 //!
 //! Example:
 //! ```no_run
-//! use log::{info, LevelFilter};
+//! use log::info;
 //! use tarantool::log::{TarantoolLogger, SayLevel};
 //!
 //! static LOGGER: TarantoolLogger = TarantoolLogger::new();
-//! log::set_logger(&LOGGER).unwrap();
-//! log::set_max_level(LevelFilter::Debug);
+//! LOGGER.install().unwrap();
 //! # let username = "Dave";
 //! info!("Hello {}", username);
+//! // Key-value pairs are appended to the message as a JSON object:
+//! // Hello Dave {"user_id":42}
+//! info!(user_id = 42; "Hello {}", username);
 //!
 //! // Or you can write to Tarantool logger directly
 //! tarantool::say_verbose!("Logging some messages...");
@@ -17,12 +19,18 @@
 //! tarantool::say_warn!("Watch out!");
 //! ```
 //!
+//! The records are filtered according to the current level of the tarantool
+//! logger, so changing `box.cfg.log_level` (or calling [`set_level`]) affects
+//! both the tarantool's own logs and the logs of the module. Events of the
+//! `tracing` crate end up here as well if its `log` feature is enabled.
+//!
 //! See also:
 //! - [Lua reference: Module log](https://www.tarantool.io/en/doc/latest/reference/reference_lua/log/)
 //! - [C API reference: Module say (logging)](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/say/)
 use std::ptr::null;
 
-use log::{Level, Log, Metadata, Record};
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
 
 use crate::ffi::tarantool as ffi;
 use crate::util::into_cstring_lossy;
@@ -48,6 +56,17 @@ impl TarantoolLogger {
     pub fn convert_level(&self, level: Level) -> SayLevel {
         (self.0)(level)
     }
+
+    /// Sets `self` as the global logger of the [`log`] crate.
+    ///
+    /// The maximum level of the `log` crate is set to
+    /// [`LevelFilter::Trace`], so that the records are only filtered by the
+    /// current level of the tarantool logger, which can be changed at runtime.
+    pub fn install(&'static self) -> Result<(), log::SetLoggerError> {
+        log::set_logger(self)?;
+        log::set_max_level(LevelFilter::Trace);
+        Ok(())
+    }
 }
 
 impl Log for TarantoolLogger {
@@ -59,12 +78,20 @@ impl Log for TarantoolLogger {
 
     #[inline]
     fn log(&self, record: &Record) {
+        // The `log` crate doesn't call `enabled` before `log`.
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let file = record
+            .file()
+            .or_else(|| record.module_path())
+            .unwrap_or_default();
         say(
             self.convert_level(record.level()),
-            record.file().unwrap_or_default(),
+            file,
             record.line().unwrap_or(0) as i32,
             None,
-            record.args().to_string().as_str(),
+            &format_message(record),
         )
     }
 
@@ -72,6 +99,44 @@ impl Log for TarantoolLogger {
     fn flush(&self) {}
 }
 
+/// Returns the message of the record followed by its key-value pairs if
+/// there are any.
+fn format_message(record: &Record) -> String {
+    let mut message = record.args().to_string();
+    let fields = record.key_values();
+    if fields.count() > 0 {
+        let mut visitor = JsonFields(serde_json::Map::new());
+        // Our visitor never fails.
+        let _ = fields.visit(&mut visitor);
+        message.push(' ');
+        message.push_str(&serde_json::Value::Object(visitor.0).to_string());
+    }
+    message
+}
+
+/// Collects the key-value pairs of a [`Record`] into a JSON object.
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(v) = value.to_bool() {
+            v.into()
+        } else if let Some(v) = value.to_i64() {
+            v.into()
+        } else if let Some(v) = value.to_u64() {
+            v.into()
+        } else if let Some(v) = value.to_f64() {
+            v.into()
+        } else if let Some(v) = value.to_borrowed_str() {
+            v.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.as_str().into(), value);
+        Ok(())
+    }
+}
+
 crate::define_enum_with_introspection! {
     /// Tarantool-native logging levels (use it with [say()](fn.say.html))
     #[repr(u32)]
@@ -99,6 +164,9 @@ pub fn current_level() -> SayLevel {
 
 /// Set current level of the default tarantool logger.
 ///
+/// **NOTE**: this doesn't change `box.cfg.log_level`, use [`set_level`] to
+/// keep them in sync.
+///
 /// See also <https://www.tarantool.io/en/doc/latest/reference/configuration/#cfg-logging-log-level>.
 #[inline(always)]
 pub fn set_current_level(level: SayLevel) {
@@ -107,6 +175,22 @@ pub fn set_current_level(level: SayLevel) {
     }
 }
 
+/// Set the level of the default tarantool logger keeping `box.cfg.log_level`
+/// in sync with it, unlike [`set_current_level`].
+///
+/// If `box.cfg{ .. }` was not called yet, only the level of the logger is
+/// changed.
+pub fn set_level(level: SayLevel) -> crate::Result<()> {
+    if !crate::cfg::is_configured() {
+        set_current_level(level);
+        return Ok(());
+    }
+    crate::lua_state()
+        .exec_with("box.cfg { log_level = ... }", level)
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
 impl From<Level> for SayLevel {
     fn from(level: Level) -> Self {
         match level {
@@ -508,4 +592,44 @@ mod tests {
         say(SayLevel::Crit, "a\0b\0c\0d", 0, Some("e\0f\0g"), "\0h\0j\0k\0");
         say_format_args(SayLevel::Info, format_args!("m\0s\0g\0"));
     }
+
+    #[crate::test(tarantool = "crate")]
+    fn set_level_syncs_box_cfg() {
+        let level_before = super::current_level();
+        let _guard = crate::test::util::on_scope_exit(|| super::set_level(level_before).unwrap());
+
+        super::set_level(SayLevel::Verbose).unwrap();
+        assert_eq!(super::current_level(), SayLevel::Verbose);
+        let lua = lua_state();
+        let level: SayLevel = lua.eval("return box.cfg.log_level").unwrap();
+        assert_eq!(level, SayLevel::Verbose);
+
+        let logger = TarantoolLogger::new();
+        assert!(!logger.enabled(&log::Metadata::builder().level(Level::Debug).build()));
+        lua.exec("box.cfg { log_level = 'debug' }").unwrap();
+        assert!(logger.enabled(&log::Metadata::builder().level(Level::Debug).build()));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn structured_fields() {
+        let fields: &[(&str, log::kv::Value)] = &[
+            ("user", "Dave".into()),
+            ("id", 42.into()),
+            ("ok", true.into()),
+            ("ratio", 0.5.into()),
+        ];
+        let message = format_message(
+            &Record::builder()
+                .args(format_args!("hello {}", 1))
+                .key_values(&fields)
+                .build(),
+        );
+        assert_eq!(
+            message,
+            r#"hello 1 {"id":42,"ok":true,"ratio":0.5,"user":"Dave"}"#
+        );
+
+        let message = format_message(&Record::builder().args(format_args!("no fields")).build());
+        assert_eq!(message, "no fields");
+    }
 }
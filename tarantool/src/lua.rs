@@ -0,0 +1,157 @@
+//! Lua chunks checked at compile time.
+//!
+//! The code passed to [`lua!`](crate::lua!) is syntax checked during the
+//! compilation of the rust code, so a typo in it results in a compile error
+//! instead of a runtime one. The macro expands to a `&'static` [`Chunk`],
+//! which is loaded into the lua state the first time it's used and cached
+//! afterwards.
+//!
+//! ```no_run
+//! use tarantool::lua;
+//!
+//! let add = lua!("local a, b = ... return a + b");
+//! let sum: i32 = add.call_with_args((1, 2)).unwrap();
+//! assert_eq!(sum, 3);
+//!
+//! // error: lua syntax error at line 1: unexpected symbol near '+'
+//! // let broken = lua!("local a, b = ... return a + + b");
+//! ```
+
+use std::ffi::CString;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use tlua::{
+    ffi, AsLua, CallError, LuaError, LuaFunction, LuaRead, LuaState, LuaThread, PushGuard, PushInto,
+};
+
+/// A lua chunk created via the [`lua!`](crate::lua!) macro.
+///
+/// The chunk is loaded on the first call to [`Chunk::function`] (or any of
+/// the `call` methods) and is stored in the lua registry, so the following
+/// calls don't parse the code again.
+#[derive(Debug)]
+pub struct Chunk {
+    name: &'static str,
+    code: &'static str,
+    /// Reference to the loaded function in the lua registry.
+    func_ref: AtomicI32,
+}
+
+impl Chunk {
+    /// Creates a chunk without checking the syntax of the code. Use the
+    /// [`lua!`](crate::lua!) macro instead.
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, code: &'static str) -> Self {
+        Self {
+            name,
+            code,
+            func_ref: AtomicI32::new(ffi::LUA_NOREF),
+        }
+    }
+
+    /// Returns the name of the chunk, which is used in the lua error
+    /// messages and tracebacks.
+    #[inline(always)]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the lua code of the chunk.
+    #[inline(always)]
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Returns the function of the chunk, loading it if it wasn't yet.
+    ///
+    /// The function is pushed onto the stack of a new lua thread, so it's
+    /// safe to yield from it.
+    pub fn function(&self) -> Result<LuaFunction<PushGuard<LuaThread>>, LuaError> {
+        let lua = crate::lua_state();
+        let l = lua.as_lua();
+        unsafe {
+            let mut func_ref = self.func_ref.load(Ordering::Relaxed);
+            if func_ref == ffi::LUA_NOREF {
+                // The name must not contain nul bytes, the macro makes sure
+                // of it.
+                let name = CString::new(format!("={}", self.name)).expect("no nul bytes");
+                let code = self.code.as_bytes();
+                let rc = ffi::luaL_loadbuffer(l, code.as_ptr().cast(), code.len(), name.as_ptr());
+                if rc != ffi::LUA_OK {
+                    let message: String = LuaRead::lua_read(PushGuard::new(l, 1))
+                        .unwrap_or_else(|_| "unknown error".into());
+                    return Err(LuaError::SyntaxError(message));
+                }
+                func_ref = ffi::luaL_ref(l, ffi::LUA_REGISTRYINDEX);
+                self.func_ref.store(func_ref, Ordering::Relaxed);
+            }
+            ffi::lua_rawgeti(l, ffi::LUA_REGISTRYINDEX, func_ref);
+            let guard = PushGuard::new(lua, 1);
+            Ok(LuaFunction::lua_read(guard).expect("a function is stored by the reference"))
+        }
+    }
+
+    /// Runs the chunk without arguments.
+    ///
+    /// See [`LuaFunction::into_call`] for details.
+    #[inline]
+    pub fn call<V>(&self) -> Result<V, LuaError>
+    where
+        V: LuaRead<PushGuard<LuaFunction<PushGuard<LuaThread>>>>,
+    {
+        self.function()?.into_call()
+    }
+
+    /// Runs the chunk with the `args`, which are accessible from lua via
+    /// `...`.
+    ///
+    /// See [`LuaFunction::into_call_with_args`] for details.
+    #[inline]
+    pub fn call_with_args<V, A>(&self, args: A) -> Result<V, CallError<A::Err>>
+    where
+        A: PushInto<LuaState>,
+        V: LuaRead<PushGuard<LuaFunction<PushGuard<LuaThread>>>>,
+    {
+        self.function()?.into_call_with_args(args)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    #[crate::test(tarantool = "crate")]
+    fn call() {
+        let chunk = crate::lua!(tarantool = "crate", "return 1 + 2");
+        assert!(chunk.name().starts_with("lua!(tarantool/src/lua.rs:"));
+        assert_eq!(chunk.code(), "return 1 + 2");
+        let result: i32 = chunk.call().unwrap();
+        assert_eq!(result, 3);
+
+        let chunk = crate::lua!(
+            tarantool = "crate",
+            r#"
+            local a, b = ...
+            return a .. b, select('#', ...)
+            "#
+        );
+        let result: (String, i32) = chunk.call_with_args(("foo", "bar")).unwrap();
+        assert_eq!(result, ("foobar".into(), 2));
+        // The loaded function is cached.
+        let result: (String, i32) = chunk.call_with_args(("x", 1)).unwrap();
+        assert_eq!(result, ("x1".into(), 2));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn runtime_error() {
+        let chunk = crate::lua!(tarantool = "crate", "\nerror('oops')");
+        let e = chunk.call::<()>().unwrap_err().to_string();
+        assert!(e.starts_with("lua!(tarantool/src/lua.rs:"), "{}", e);
+        assert!(e.ends_with("):2: oops"), "{}", e);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn yields() {
+        let chunk = crate::lua!(tarantool = "crate", "require('fiber').sleep(0) return true");
+        let result: bool = chunk.call().unwrap();
+        assert!(result);
+    }
+}
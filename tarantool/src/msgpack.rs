@@ -6,6 +6,7 @@ use std::io::{Cursor, Read, Seek, SeekFrom};
 pub mod encode;
 pub use encode::*;
 pub use rmp::{self, Marker};
+pub use rmpv::Value;
 
 /// Msgpack encoding of `null`.
 pub const MARKER_NULL: u8 = 0xc0;
@@ -51,6 +51,8 @@ pub fn decode<'de, T: Decode<'de>>(mut bytes: &'de [u8]) -> Result<T, DecodeErro
 pub struct Context {
     /// Defines the (de)serialization style for structs.
     struct_style: StructStyle,
+    /// Overrides the decoding mode of derived [`Decode`] implementations.
+    decode_mode: Option<DecodeMode>,
     // TODO: parameter which allows encoding/decoding Vec<u8> as string and/or binary
     // TODO: maybe we should allow empty input to be decoded as `Option::None`,
     // but this should be configurable via context & not sure if this may break
@@ -65,6 +67,7 @@ impl Context {
     /// be constructed at compile time.
     pub const DEFAULT: Self = Self {
         struct_style: StructStyle::Default,
+        decode_mode: None,
     };
 }
 
@@ -89,6 +92,24 @@ impl Context {
     pub fn struct_style(&self) -> StructStyle {
         self.struct_style
     }
+
+    /// A builder-style method which sets `decode_mode` and returns `self` by
+    /// value.
+    ///
+    /// The mode overrides the one specified via struct level attributes such
+    /// as `#[decode(strict)]` for the struct and all nested structs.
+    #[inline(always)]
+    pub const fn with_decode_mode(mut self, decode_mode: DecodeMode) -> Self {
+        self.decode_mode = Some(decode_mode);
+        self
+    }
+
+    /// Returns the decoding mode set by this context or `None` if the struct
+    /// level attributes should be respected.
+    #[inline(always)]
+    pub fn decode_mode(&self) -> Option<DecodeMode> {
+        self.decode_mode
+    }
 }
 
 /// Defines the (de)serialization style for structs.
@@ -115,6 +136,39 @@ pub enum StructStyle {
     // TODO AllowDecodeAny - to allow decoding both arrays & maps
 }
 
+/// Defines how derived [`Decode`] implementations (as well as the
+/// implementations for tuples) handle the msgpack values which don't match the
+/// number of the struct's fields.
+///
+/// Can be specified for a struct via `#[decode(strict)]`, `#[decode(lenient)]`
+/// or `#[decode(truncate)]` (the default) attributes, or overridden for a
+/// single decoding via [`Context::with_decode_mode`] (e.g. see
+/// [`Tuple::decode_msgpack_with`]). The tuples require the exact number of
+/// the elements unless the mode is overridden by the context.
+///
+/// Missing required fields are reported as errors in any mode. Missing
+/// optional fields may be omitted from an MP_MAP, but must be explicitly null
+/// in an MP_ARRAY unless `allow_array_optionals` attribute is specified or
+/// the mode is [`DecodeMode::Lenient`].
+///
+/// See [`Decode`].
+///
+/// [`Tuple::decode_msgpack_with`]: crate::tuple::Tuple::decode_msgpack_with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeMode {
+    /// Extra MP_ARRAY elements or MP_MAP entries with unknown keys are
+    /// reported as errors.
+    Strict,
+    /// Same as [`DecodeMode::Truncate`], but the trailing optional fields are
+    /// allowed to be missing from an MP_ARRAY even if `allow_array_optionals`
+    /// attribute is not specified.
+    Lenient,
+    /// Extra MP_ARRAY elements or MP_MAP entries after the last field are
+    /// skipped.
+    #[default]
+    Truncate,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Decode
 ////////////////////////////////////////////////////////////////////////////////
@@ -145,6 +199,25 @@ pub enum StructStyle {
 /// In case of an MP_ARRAY (if `#[encode(allow_array_optionals)]` is enabled) only last fields
 /// with type of `Option<T>` can be skipped.
 ///
+/// A single field of type `HashMap<String, V>` (or `BTreeMap<String, V>`) can be marked with
+/// `#[decode(flatten_unknown)]` attribute. In this case when decoding an MP_MAP the keys may
+/// come in any order and all the entries with keys not corresponding to any other field are
+/// collected into the marked field instead of causing an error. Use [`msgpack::Value`] as `V`
+/// to accept values of any type. When encoding as an MP_MAP these entries are written back
+/// after the other fields. The field is ignored when the struct is represented as an MP_ARRAY.
+/// The attribute only affects decoding, so it can't be specified as `#[encode(flatten_unknown)]`.
+///
+/// The stored procedure arguments are decoded with `serde` by default, use
+/// `#[tarantool::proc(msgpack_args)]` to decode them with this trait.
+///
+/// [`msgpack::Value`]: crate::msgpack::Value
+///
+/// The way the mismatch between the number of fields in the struct and the
+/// length of the MP_ARRAY or MP_MAP is handled can be controlled with
+/// `#[decode(strict)]`, `#[decode(lenient)]` or `#[decode(truncate)]` (the
+/// default) attributes, see [`DecodeMode`] for details. Missing required
+/// fields are always reported as errors naming the field.
+///
 /// It should replace `tuple::Decode` when it's ready.
 ///
 /// # Example
@@ -210,6 +283,13 @@ impl DecodeError {
         self
     }
 
+    /// Returns `true` if the error was caused by a failure to read the
+    /// MessagePack marker of the value, e.g. because the data has ended.
+    #[inline]
+    pub fn is_marker_read_error(&self) -> bool {
+        self.source.ends_with("failed to read MessagePack marker")
+    }
+
     /// VRE is [`rmp::decode::ValueReadError`](https://docs.rs/rmp/latest/rmp/decode/enum.ValueReadError.html)
     #[inline(always)]
     pub fn from_vre<DecodedTy>(value: ValueReadError) -> Self {
@@ -301,6 +381,36 @@ where
     }
 }
 
+impl<'de, A, B> Decode<'de> for tlua::Either<A, B>
+where
+    A: Decode<'de>,
+    B: Decode<'de>,
+{
+    /// Tries decoding `A` first and if it fails, tries decoding `B`.
+    #[inline]
+    fn decode(r: &mut &'de [u8], context: &Context) -> Result<Self, DecodeError> {
+        let mut r_try = *r;
+        let left_err = match A::decode(&mut r_try, context) {
+            Ok(a) => {
+                *r = r_try;
+                return Ok(Self::Left(a));
+            }
+            Err(e) => e,
+        };
+        let mut r_try = *r;
+        let right_err = match B::decode(&mut r_try, context) {
+            Ok(b) => {
+                *r = r_try;
+                return Ok(Self::Right(b));
+            }
+            Err(e) => e,
+        };
+        Err(DecodeError::new::<Self>(format!(
+            "didn't match any of the alternatives: {left_err}; {right_err}"
+        )))
+    }
+}
+
 impl<'de, T> Decode<'de> for Vec<T>
 where
     T: Decode<'de>,
@@ -398,6 +508,52 @@ where
     }
 }
 
+macro_rules! impl_tuple_decode {
+    () => {};
+    ($h:ident $($t:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<'de, $h, $($t),*> Decode<'de> for ($h, $($t),*)
+        where
+            $h: Decode<'de>,
+            $($t: Decode<'de>,)*
+        {
+            #[allow(unused_assignments, unused_mut)]
+            fn decode(r: &mut &'de [u8], context: &Context) -> Result<Self, DecodeError> {
+                const LEN: usize = crate::expr_count!($h $(, $t)*);
+                let n = rmp::decode::read_array_len(r).map_err(DecodeError::from_vre::<Self>)? as usize;
+                let strict = context.decode_mode().unwrap_or(DecodeMode::Strict) == DecodeMode::Strict;
+                if n < LEN || n > LEN && strict {
+                    return Err(DecodeError::new::<Self>(format!(
+                        "expected array count {LEN}, got {n}"
+                    )));
+                }
+
+                let mut i = 0;
+                let $h = $h::decode(r, context)
+                    .map_err(|e| DecodeError::new::<Self>(e).with_part(format!("element {i}")))?;
+                $(
+                    i += 1;
+                    let $t = $t::decode(r, context)
+                        .map_err(|e| DecodeError::new::<Self>(e).with_part(format!("element {i}")))?;
+                )*
+                if n > LEN {
+                    let mut cursor = std::io::Cursor::new(*r);
+                    for _ in LEN..n {
+                        crate::msgpack::skip_value(&mut cursor)
+                            .map_err(|e| DecodeError::new::<Self>(e).with_part("extra element"))?;
+                    }
+                    *r = &r[(cursor.position() as usize)..];
+                }
+                Ok(($h, $($t),*))
+            }
+        }
+
+        impl_tuple_decode! { $($t)* }
+    }
+}
+
+impl_tuple_decode! { A B C D E F G H I J K L M N O P }
+
 impl<'a, 'de, T> Decode<'de> for Cow<'a, T>
 where
     T: Decode<'de> + ToOwned + ?Sized,
@@ -627,6 +783,84 @@ impl From<std::io::Error> for EncodeError {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// InternedStr
+////////////////////////////////////////////////////////////////////////////////
+
+/// A string encoded as msgpack in advance, see [`intern`].
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct InternedStr(std::rc::Rc<[u8]>);
+
+impl InternedStr {
+    /// Returns the msgpack encoding of the string.
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the string itself.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        let header_len = match self.0[0] {
+            0xa0..=0xbf => 1,
+            0xd9 => 2,
+            0xda => 3,
+            _ => 5,
+        };
+        // SAFETY: the bytes after the header are copied from a `str`.
+        unsafe { std::str::from_utf8_unchecked(&self.0[header_len..]) }
+    }
+}
+
+impl Debug for InternedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InternedStr").field(&self.as_str()).finish()
+    }
+}
+
+impl Encode for InternedStr {
+    #[inline(always)]
+    fn encode(&self, w: &mut impl Write, _context: &Context) -> Result<(), EncodeError> {
+        w.write_all(&self.0)?;
+        Ok(())
+    }
+}
+
+thread_local! {
+    static INTERNED: std::cell::RefCell<HashMap<Box<str>, InternedStr>> = Default::default();
+}
+
+/// Returns the msgpack encoding of `s` from a thread local cache, encoding
+/// it the first time the string is interned. Encoding an [`InternedStr`]
+/// just copies the bytes, which is faster for the strings written over and
+/// over, e.g. the keys of the maps built at runtime.
+///
+/// The cache is never cleared, so only the strings from a bounded set (e.g.
+/// the constants) should be interned. Note that the derived [`Encode`]
+/// implementations already write the field names encoded at compile time.
+///
+/// ```
+/// use tarantool::msgpack::{self, Encode};
+///
+/// let key = msgpack::intern("name");
+/// let mut buf = vec![];
+/// key.encode(&mut buf, &Default::default()).unwrap();
+/// assert_eq!(buf, msgpack::encode(&"name"));
+/// assert_eq!(key.as_str(), "name");
+/// ```
+pub fn intern(s: &str) -> InternedStr {
+    INTERNED.with(|interned| {
+        if let Some(res) = interned.borrow().get(s) {
+            return res.clone();
+        }
+        let mut bytes = Vec::with_capacity(s.len() + 5);
+        rmp::encode::write_str(&mut bytes, s).expect("writing to vec cannot fail");
+        let res = InternedStr(bytes.into());
+        interned.borrow_mut().insert(s.into(), res.clone());
+        res
+    })
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // impl Encode
 ////////////////////////////////////////////////////////////////////////////////
@@ -694,6 +928,20 @@ where
     }
 }
 
+impl<A, B> Encode for tlua::Either<A, B>
+where
+    A: Encode,
+    B: Encode,
+{
+    #[inline]
+    fn encode(&self, w: &mut impl Write, context: &Context) -> Result<(), EncodeError> {
+        match self {
+            Self::Left(a) => a.encode(w, context),
+            Self::Right(b) => b.encode(w, context),
+        }
+    }
+}
+
 impl<T> Encode for [T]
 where
     T: Encode,
@@ -880,6 +1128,21 @@ macro_rules! impl_tuple_encode {
 
 impl_tuple_encode! { A B C D E F G H I J K L M N O P }
 
+impl Encode for rmpv::Value {
+    #[inline]
+    fn encode(&self, w: &mut impl Write, _context: &Context) -> Result<(), EncodeError> {
+        rmpv::encode::write_value(w, self).map_err(|e| EncodeError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl<'de> Decode<'de> for rmpv::Value {
+    #[inline]
+    fn decode(r: &mut &'de [u8], _context: &Context) -> Result<Self, DecodeError> {
+        rmpv::decode::read_value(r).map_err(DecodeError::new::<Self>)
+    }
+}
+
 impl Encode for serde_json::Value {
     #[inline]
     fn encode(&self, w: &mut impl Write, _context: &Context) -> Result<(), EncodeError> {
@@ -1300,6 +1563,224 @@ mod tests {
         assert_eq!(decoded_arr, TestUnnamedAllowed(42, None, None));
     }
 
+    #[test]
+    fn flatten_unknown() {
+        #[derive(Clone, Encode, Decode, PartialEq, Debug)]
+        #[encode(tarantool = "crate", as_map)]
+        struct Test {
+            a: i32,
+            b: Option<String>,
+            #[decode(flatten_unknown)]
+            unknown: BTreeMap<String, Value>,
+        }
+
+        // keys in a different order, unknown keys are collected
+        let helper = Value::Map(vec![
+            (Value::from("c"), Value::from(true)),
+            (Value::from("b"), Value::from("hello")),
+            (Value::from("a"), Value::from(42)),
+            (Value::from("d"), Value::Array(vec![Value::from(1)])),
+        ]);
+        let mut encoded = Vec::new();
+        rmpv::encode::write_value(&mut encoded, &helper).unwrap();
+        let decoded: Test = decode(&encoded).unwrap();
+        let test = Test {
+            a: 42,
+            b: Some("hello".into()),
+            unknown: vec![
+                ("c".to_string(), Value::from(true)),
+                ("d".to_string(), Value::Array(vec![Value::from(1)])),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(decoded, test);
+
+        // unknown keys are encoded back
+        let bytes = encode(&test);
+        assert_value(
+            &bytes,
+            Value::Map(vec![
+                (Value::from("a"), Value::from(42)),
+                (Value::from("b"), Value::from("hello")),
+                (Value::from("c"), Value::from(true)),
+                (Value::from("d"), Value::Array(vec![Value::from(1)])),
+            ]),
+        );
+        assert_eq!(decode::<Test>(&bytes).unwrap(), test);
+
+        // optional field is missing
+        let helper = Value::Map(vec![(Value::from("a"), Value::from(1))]);
+        let mut encoded = Vec::new();
+        rmpv::encode::write_value(&mut encoded, &helper).unwrap();
+        let decoded: Test = decode(&encoded).unwrap();
+        assert_eq!(
+            decoded,
+            Test {
+                a: 1,
+                b: None,
+                unknown: BTreeMap::new(),
+            }
+        );
+
+        // required field is missing
+        let helper = Value::Map(vec![(Value::from("x"), Value::from(1))]);
+        let mut encoded = Vec::new();
+        rmpv::encode::write_value(&mut encoded, &helper).unwrap();
+        let err = decode::<Test>(&encoded).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::flatten_unknown::Test: missing field a"
+        );
+
+        // array context, unknown keys are not encoded
+        let mut bytes = vec![];
+        test.encode(&mut bytes, ARR_CTX).unwrap();
+        assert_value(
+            &bytes,
+            Value::Array(vec![Value::from(42), Value::from("hello")]),
+        );
+        let decoded = Test::decode(&mut bytes.as_slice(), ARR_CTX).unwrap();
+        assert_eq!(
+            decoded,
+            Test {
+                unknown: BTreeMap::new(),
+                ..test
+            }
+        );
+    }
+
+    #[test]
+    fn decode_modes() {
+        #[derive(Clone, Encode, Decode, PartialEq, Debug)]
+        #[encode(tarantool = "crate")]
+        struct Truncated {
+            a: i32,
+            b: Option<String>,
+        }
+
+        #[derive(Clone, Encode, Decode, PartialEq, Debug)]
+        #[encode(tarantool = "crate")]
+        #[decode(strict)]
+        struct Strict {
+            a: i32,
+            b: Option<String>,
+        }
+
+        #[derive(Clone, Encode, Decode, PartialEq, Debug)]
+        #[encode(tarantool = "crate")]
+        #[decode(lenient)]
+        struct Lenient {
+            a: i32,
+            b: Option<String>,
+        }
+
+        #[derive(Clone, Encode, Decode, PartialEq, Debug)]
+        #[encode(tarantool = "crate")]
+        struct Outer {
+            inner: Truncated,
+            c: bool,
+        }
+
+        // extra elements are skipped by default, even in nested structs
+        let bytes = encode(&((1, "x", 2, [3]), true));
+        let outer: Outer = decode(&bytes).unwrap();
+        assert_eq!(
+            outer,
+            Outer {
+                inner: Truncated {
+                    a: 1,
+                    b: Some("x".into()),
+                },
+                c: true,
+            }
+        );
+        let lenient: Lenient = decode(&bytes[1..]).unwrap();
+        assert_eq!(lenient.a, 1);
+
+        // extra elements are not allowed in strict mode
+        let err = decode::<Strict>(&bytes[1..]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::decode_modes::Strict: too many fields, expected 2, got 4"
+        );
+        let strict_ctx = Context::DEFAULT.with_decode_mode(DecodeMode::Strict);
+        let err = Outer::decode(&mut &bytes[..], &strict_ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::decode_modes::Outer (field inner): failed decoding tarantool::msgpack::encode::tests::decode_modes::Truncated: too many fields, expected 2, got 4"
+        );
+        let truncate_ctx = Context::DEFAULT.with_decode_mode(DecodeMode::Truncate);
+        let decoded = Strict::decode(&mut &bytes[1..], &truncate_ctx).unwrap();
+        assert_eq!(
+            decoded,
+            Strict {
+                a: 1,
+                b: Some("x".into()),
+            }
+        );
+
+        // tuples require the exact length unless overridden by the context
+        let err = decode::<((i32, String), bool)>(&bytes).unwrap_err();
+        assert!(
+            err.to_string().contains("expected array count 2, got 4"),
+            "{}",
+            err
+        );
+        let (inner, c) = <((i32, String), bool)>::decode(&mut &bytes[..], &truncate_ctx).unwrap();
+        assert_eq!(inner, (1, "x".into()));
+        assert!(c);
+
+        // unknown keys are reported in strict mode
+        let mut bytes = vec![];
+        rmpv::encode::write_value(
+            &mut bytes,
+            &Value::Map(vec![
+                (Value::from("a"), Value::from(1)),
+                (Value::from("b"), Value::Nil),
+                (Value::from("c"), Value::from(2)),
+            ]),
+        )
+        .unwrap();
+        let err = Strict::decode(&mut &bytes[..], MAP_CTX).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::decode_modes::Strict: unknown field c"
+        );
+        let decoded = Truncated::decode(&mut &bytes[..], MAP_CTX).unwrap();
+        assert_eq!(decoded, Truncated { a: 1, b: None });
+
+        // missing optional field
+        let bytes = encode(&(1,));
+        let err = decode::<Truncated>(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::decode_modes::Truncated: not enough fields, expected 2, got 1 (note: optional fields must be explicitly null unless `allow_array_optionals` attribute is passed)"
+        );
+        assert!(decode::<Strict>(&bytes).is_err());
+        let decoded: Lenient = decode(&bytes).unwrap();
+        assert_eq!(decoded, Lenient { a: 1, b: None });
+        let lenient_ctx = Context::DEFAULT.with_decode_mode(DecodeMode::Lenient);
+        let decoded = Strict::decode(&mut &bytes[..], &lenient_ctx).unwrap();
+        assert_eq!(decoded, Strict { a: 1, b: None });
+
+        // missing required field is always an error
+        let bytes = encode(&Vec::<i32>::new());
+        let err = decode::<Lenient>(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::decode_modes::Lenient: missing field a (expected 2 fields, got 0)"
+        );
+
+        // type mismatch names the field
+        let bytes = encode(&("a", "b"));
+        let err = decode::<Strict>(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed decoding tarantool::msgpack::encode::tests::decode_modes::Strict (field a): failed decoding i32 (got FixStr(1)): the type decoded isn't match with the expected one"
+        );
+    }
+
     #[test]
     fn encode_raw() {
         use serde::Serialize;
@@ -1738,6 +2219,23 @@ mod tests {
         assert_eq!(original, decoded);
     }
 
+    #[test]
+    fn decode_tuple() {
+        let bytes = encode(&(1, "two", [3]));
+        let decoded: (u8, String, Vec<u32>) = decode(&bytes).unwrap();
+        assert_eq!(decoded, (1, "two".into(), vec![3]));
+
+        let e = decode::<(u8, String)>(&bytes).unwrap_err();
+        assert!(
+            e.to_string().contains("expected array count 2, got 3"),
+            "{}",
+            e
+        );
+
+        let e = decode::<(u8, u8, Vec<u32>)>(&bytes).unwrap_err();
+        assert!(e.to_string().contains("(element 1)"), "{}", e);
+    }
+
     #[test]
     fn encode_unit_struct() {
         #[derive(Clone, Encode, Decode, PartialEq, Debug)]
@@ -1930,6 +2428,49 @@ mod tests {
         assert_eq!(err.to_string(), "failed decoding tarantool::msgpack::encode::tests::encode_enum_untagged::Foo: received stream didn't match any enum variant");
     }
 
+    #[test]
+    fn encode_either_and_one_of() {
+        type NumOrStr = tlua::Either<u32, String>;
+        let original = NumOrStr::Left(13);
+        let bytes = encode(&original);
+        assert_value(&bytes, Value::from(13));
+        assert_eq!(decode::<NumOrStr>(&bytes).unwrap(), original);
+
+        let original = NumOrStr::Right("foo".into());
+        let bytes = encode(&original);
+        assert_value(&bytes, Value::from("foo"));
+        assert_eq!(decode::<NumOrStr>(&bytes).unwrap(), original);
+
+        let err = decode::<NumOrStr>(&encode(&true)).unwrap_err().to_string();
+        assert!(
+            err.starts_with(
+                "failed decoding tlua::either::Either<u32, alloc::string::String>: \
+                didn't match any of the alternatives: failed decoding u32"
+            ),
+            "{}",
+            err
+        );
+
+        crate::one_of! {
+            #[derive(Debug, PartialEq)]
+            enum Key {
+                Id(u64),
+                Name(String),
+                Parts(Vec<u64>),
+            }
+        }
+        for original in [Key::Id(1), Key::Name("foo".into()), Key::Parts(vec![1, 2])] {
+            let bytes = encode(&original);
+            assert_eq!(decode::<Key>(&bytes).unwrap(), original);
+        }
+        assert_value(
+            &encode(&Key::Parts(vec![1, 2])),
+            Value::Array(vec![Value::from(1), Value::from(2)]),
+        );
+        let err = decode::<Key>(&encode(&true)).unwrap_err().to_string();
+        assert_eq!(err.matches("failed decoding").count(), 4, "{}", err);
+    }
+
     #[test]
     fn encode_named_with_raw_ident() {
         #[derive(Clone, Encode, Decode, PartialEq, Debug)]
@@ -2223,4 +2764,64 @@ mod tests {
         assert_eq!(decode::<u32>(b"\xce\xff\xff\xff\xff").unwrap(), u32::MAX);
         assert_eq!(decode::<u64>(b"\xcf\xff\xff\xff\xff\xff\xff\xff\xff").unwrap(), u64::MAX);
     }
+
+    #[test]
+    fn pre_encoded_keys() {
+        #[derive(Encode, Debug)]
+        #[encode(tarantool = "crate", as_map)]
+        struct Wide {
+            a: u8,
+            a_field_with_a_name_longer_than_31_bytes: u8,
+        }
+        #[derive(Encode, Debug)]
+        #[encode(tarantool = "crate")]
+        enum E {
+            A,
+            AVariantWithANameLongerThan31Bytes(u8),
+        }
+
+        let bytes = encode(&Wide {
+            a: 1,
+            a_field_with_a_name_longer_than_31_bytes: 2,
+        });
+        assert_value(
+            &bytes,
+            Value::Map(vec![
+                (Value::from("a"), Value::from(1)),
+                (
+                    Value::from("a_field_with_a_name_longer_than_31_bytes"),
+                    Value::from(2),
+                ),
+            ]),
+        );
+        // Uses str8 for the long name, same as rmp.
+        assert_eq!(&bytes[4..6], b"\xd9\x28");
+
+        assert_value(
+            &encode(&E::AVariantWithANameLongerThan31Bytes(3)),
+            Value::Map(vec![(
+                Value::from("AVariantWithANameLongerThan31Bytes"),
+                Value::Array(vec![Value::from(3)]),
+            )]),
+        );
+        assert_value(
+            &encode(&E::A),
+            Value::Map(vec![(Value::from("A"), Value::Nil)]),
+        );
+    }
+
+    #[test]
+    fn intern() {
+        for len in [0, 31, 32, 255, 256, 65536] {
+            let s = "x".repeat(len);
+            let interned = super::intern(&s);
+            assert_eq!(interned.as_bytes(), encode(&s));
+            assert_eq!(interned.as_str(), s);
+            assert_eq!(encode(&interned), encode(&s));
+        }
+        let a = super::intern("key");
+        let b = super::intern("key");
+        assert!(std::rc::Rc::ptr_eq(&a.0, &b.0));
+        assert_eq!(format!("{:?}", a), r#"InternedStr("key")"#);
+    }
 }
@@ -4,7 +4,7 @@ use std::vec::IntoIter;
 use crate::error::Error;
 use crate::index::IteratorType;
 use crate::network::protocol;
-use crate::tuple::{Encode, ToTupleBuffer, Tuple};
+use crate::tuple::{ToTupleBuffer, Tuple};
 
 use super::inner::ConnInner;
 use super::Options;
@@ -76,15 +76,15 @@ impl RemoteIndex {
     /// The remote-call equivalent of the local call `Space::update(...)`
     /// (see [details](../index/struct.Index.html#method.update)).
     #[inline(always)]
-    pub fn update<K, Op>(
+    pub fn update<K, Ops>(
         &self,
         key: &K,
-        ops: &[Op],
+        ops: &Ops,
         options: &Options,
     ) -> Result<Option<Tuple>, Error>
     where
         K: ToTupleBuffer + ?Sized,
-        Op: Encode,
+        Ops: ToTupleBuffer + ?Sized,
     {
         self.conn_inner.request(
             &protocol::Update {
@@ -100,15 +100,15 @@ impl RemoteIndex {
     /// The remote-call equivalent of the local call `Space::upsert(...)`
     /// (see [details](../index/struct.Index.html#method.upsert)).
     #[inline(always)]
-    pub fn upsert<T, Op>(
+    pub fn upsert<T, Ops>(
         &self,
         value: &T,
-        ops: &[Op],
+        ops: &Ops,
         options: &Options,
     ) -> Result<Option<Tuple>, Error>
     where
         T: ToTupleBuffer + ?Sized,
-        Op: Encode,
+        Ops: ToTupleBuffer + ?Sized,
     {
         self.conn_inner.request(
             &protocol::Upsert {
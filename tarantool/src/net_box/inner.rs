@@ -2,6 +2,7 @@ use core::cell::RefCell;
 use std::cell::Cell;
 use std::io::{self, Cursor, Read, Write};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::{Rc, Weak};
 use std::time::Duration;
 
@@ -11,7 +12,7 @@ use crate::error::Error;
 use crate::fiber;
 use crate::fiber::is_cancelled;
 use crate::fiber::Cond;
-use crate::net_box::stream::ConnStream;
+use crate::net_box::stream::{ConnStream, Transport};
 use crate::network::protocol;
 use crate::time::Instant;
 use crate::tuple::Decode;
@@ -35,8 +36,17 @@ enum ConnState {
     Closed,
 }
 
+/// Address of the remote instance.
+pub enum ConnAddress {
+    Tcp(Vec<SocketAddr>),
+    Unix(PathBuf),
+    /// An already connected socket. It can only be used once, so the
+    /// connection can't be re-established after it's lost.
+    Fd(RefCell<Option<CoIOStream>>),
+}
+
 pub struct ConnInner {
-    addrs: Vec<SocketAddr>,
+    address: ConnAddress,
     options: ConnOptions,
     state: Cell<ConnState>,
     state_change_cond: Cond,
@@ -60,14 +70,14 @@ impl ConnInner {
     #[inline(always)]
     #[track_caller]
     pub fn new(
-        addrs: Vec<SocketAddr>,
+        address: ConnAddress,
         options: ConnOptions,
         triggers: Option<Rc<dyn ConnTriggers>>,
     ) -> Result<Rc<Self>, Error> {
         #[cfg(feature = "picodata")]
-        if options.auth_method == crate::auth::AuthMethod::Ldap {
+        if options.auth_method == crate::auth::AuthMethod::Ldap && !options.is_tls() {
             crate::say_warn!(
-                "You're using the 'ldap' authentication method, which implies sending the password UNENCRYPTED over the TCP connection. TLS is not yet implemented for IPROTO connections so make sure your communication channel is secure by other means."
+                "You're using the 'ldap' authentication method, which implies sending the password UNENCRYPTED over the TCP connection. Enable TLS via `ConnOptions::tls` (requires the `tls` feature) or make sure your communication channel is secure by other means."
             )
         }
 
@@ -75,7 +85,7 @@ impl ConnInner {
         let conn_inner = Rc::new(ConnInner {
             state: Cell::new(ConnState::Init),
             state_change_cond: Cond::new(),
-            schema: ConnSchema::acquire(&addrs),
+            schema: ConnSchema::acquire(&address),
             schema_version: Cell::new(None),
             stream: RefCell::new(None),
             send_queue: SendQueue::new(
@@ -90,7 +100,7 @@ impl ConnInner {
 
             triggers: RefCell::new(triggers),
             error: RefCell::new(None),
-            addrs,
+            address,
             options,
         });
 
@@ -150,7 +160,7 @@ impl ConnInner {
                     self.init()?;
                 }
                 ConnState::Active => {
-                    return match self.send_queue.send(request) {
+                    return match self.send_queue.send(request, options.priority) {
                         Ok(sync) => {
                             let response = self.recv_queue.recv::<R>(sync, options)?;
                             self.schema_version
@@ -172,7 +182,11 @@ impl ConnInner {
         }
     }
 
-    pub(crate) fn request_async<I, O>(self: &Rc<Self>, request: &I) -> crate::Result<Promise<O>>
+    pub(crate) fn request_async<I, O>(
+        self: &Rc<Self>,
+        request: &I,
+        options: &Options,
+    ) -> crate::Result<Promise<O>>
     where
         I: protocol::Request,
         O: for<'de> Decode<'de> + 'static,
@@ -185,7 +199,7 @@ impl ConnInner {
                 ConnState::Active => {
                     let sync = self
                         .send_queue
-                        .send(request)
+                        .send(request, options.priority)
                         .map_err(|err| self.handle_error(err).err().unwrap())?;
                     let promise = Promise::new(Rc::downgrade(self));
                     self.recv_queue.add_consumer(sync, promise.downgrade());
@@ -250,11 +264,36 @@ impl ConnInner {
 
         // connect
         let connect_timeout = self.options.connect_timeout;
-        let mut stream = if connect_timeout.subsec_nanos() == 0 && connect_timeout.as_secs() == 0 {
-            CoIOStream::connect(&*self.addrs)?
-        } else {
-            CoIOStream::connect_timeout(self.addrs.first().unwrap(), connect_timeout)?
+        let stream = match &self.address {
+            ConnAddress::Tcp(addrs) => {
+                if connect_timeout.subsec_nanos() == 0 && connect_timeout.as_secs() == 0 {
+                    CoIOStream::connect(&**addrs)?
+                } else {
+                    CoIOStream::connect_timeout(addrs.first().unwrap(), connect_timeout)?
+                }
+            }
+            ConnAddress::Unix(path) => {
+                if connect_timeout.is_zero() {
+                    CoIOStream::connect_unix(path)?
+                } else {
+                    CoIOStream::connect_unix_timeout(path, connect_timeout)?
+                }
+            }
+            ConnAddress::Fd(stream) => stream.take().ok_or_else(|| {
+                Error::other("connection over a file descriptor can't be re-established")
+            })?,
+        };
+
+        #[cfg(feature = "tls")]
+        let mut stream = match &self.options.tls {
+            Some(tls) => {
+                let timeout = (!connect_timeout.is_zero()).then_some(connect_timeout);
+                Transport::Tls(super::tls::TlsStream::connect(stream, tls, timeout)?)
+            }
+            None => Transport::Plain(stream),
         };
+        #[cfg(not(feature = "tls"))]
+        let mut stream = Transport::Plain(stream);
 
         // receive greeting msg
         let salt = protocol::decode_greeting(&mut stream)?;
@@ -277,15 +316,14 @@ impl ConnInner {
         Ok(())
     }
 
-    fn auth(&self, stream: &mut CoIOStream, salt: &[u8]) -> Result<(), Error> {
+    fn auth(&self, stream: &mut Transport, salt: &[u8]) -> Result<(), Error> {
         // TODO: check the average auth request size
         let mut buf = Vec::new();
-        let mut cur = Cursor::new(&mut buf);
 
         // send auth request
         let sync = self.send_queue.next_sync();
         protocol::write_to_buffer(
-            &mut cur,
+            &mut buf,
             sync,
             &protocol::Auth {
                 user: self.options.user.as_str(),
@@ -294,6 +332,7 @@ impl ConnInner {
                 method: self.options.auth_method,
             },
         )?;
+        let mut cur = Cursor::new(&mut buf);
         stream.write_all(cur.get_ref())?;
 
         // handle response
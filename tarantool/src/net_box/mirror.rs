@@ -0,0 +1,398 @@
+//! Mirroring of the requests to a second connection.
+//!
+//! [`MirroringConn`] sends every request to the primary connection and a
+//! configurable share of them also to the mirror connection, e.g. a new
+//! cluster or a cluster running a new version. Responses from the mirror are
+//! discarded, only their latencies and errors are recorded in
+//! [`MirrorStats`], so that the mirror can be validated under the real
+//! traffic without affecting the clients.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::{Conn, Options};
+use crate::error::Error;
+use crate::fiber;
+use crate::time::Instant;
+use crate::tuple::{ToTupleBuffer, Tuple, TupleBuffer};
+
+/// Options of [`MirroringConn`].
+#[derive(Clone, Debug)]
+pub struct MirrorOptions {
+    /// Share of the requests sent to the mirror, from `0.0` (none) to `1.0`
+    /// (all). The requests are selected evenly, e.g. with `0.25` every
+    /// fourth request is mirrored.
+    ///
+    /// Default: `1.0`
+    pub ratio: f64,
+
+    /// Timeout of the mirrored requests.
+    ///
+    /// Default: 10 seconds
+    pub timeout: Duration,
+
+    /// Maximum number of the mirrored requests waiting for a response. If
+    /// it's reached, the requests are not mirrored (see
+    /// [`MirrorStats::dropped`]), so that a slow mirror doesn't consume the
+    /// resources of the instance.
+    ///
+    /// Default: 128
+    pub max_in_flight: usize,
+}
+
+impl Default for MirrorOptions {
+    fn default() -> Self {
+        Self {
+            ratio: 1.0,
+            timeout: Duration::from_secs(10),
+            max_in_flight: 128,
+        }
+    }
+}
+
+/// Latency statistics of a series of requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of the completed requests.
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    #[inline]
+    fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    /// Returns the mean latency or `None` if no requests were completed.
+    #[inline]
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.total.div_f64(self.count as f64))
+    }
+}
+
+/// Statistics of [`MirroringConn`], see [`MirroringConn::stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MirrorStats {
+    /// Latencies of the requests sent to the primary connection, only
+    /// including the requests which were also mirrored.
+    pub primary: LatencyStats,
+    /// Latencies of the successful mirrored requests.
+    pub mirror: LatencyStats,
+    /// Number of the mirrored requests.
+    pub mirrored: u64,
+    /// Number of the mirrored requests which failed.
+    pub errors: u64,
+    /// The last error returned by the mirror.
+    pub last_error: Option<String>,
+    /// Number of the requests which were not mirrored because
+    /// [`MirrorOptions::max_in_flight`] was reached.
+    pub dropped: u64,
+    /// Number of the mirrored requests waiting for a response.
+    pub in_flight: usize,
+}
+
+/// Request to be sent to the mirror.
+enum MirrorRequest {
+    Call {
+        fn_name: String,
+        args: TupleBuffer,
+    },
+    Eval {
+        expr: String,
+        args: TupleBuffer,
+    },
+    Execute {
+        sql: String,
+        bind_params: TupleBuffer,
+    },
+}
+
+/// A connection wrapper which mirrors a share of the requests to a second
+/// connection, see the [module level documentation](self).
+pub struct MirroringConn {
+    primary: Conn,
+    mirror: Rc<Conn>,
+    options: MirrorOptions,
+    /// Accumulates [`MirrorOptions::ratio`] to select the mirrored requests.
+    credit: Cell<f64>,
+    stats: Rc<RefCell<MirrorStats>>,
+}
+
+impl MirroringConn {
+    pub fn new(primary: Conn, mirror: Conn, options: MirrorOptions) -> Self {
+        Self {
+            primary,
+            mirror: Rc::new(mirror),
+            options,
+            credit: Cell::new(0.0),
+            stats: Default::default(),
+        }
+    }
+
+    /// Returns the primary connection. Requests sent directly through it are
+    /// not mirrored.
+    #[inline(always)]
+    pub fn primary(&self) -> &Conn {
+        &self.primary
+    }
+
+    /// Returns the mirror connection.
+    #[inline(always)]
+    pub fn mirror(&self) -> &Conn {
+        &self.mirror
+    }
+
+    /// Returns the statistics of the mirrored requests.
+    #[inline]
+    pub fn stats(&self) -> MirrorStats {
+        self.stats.borrow().clone()
+    }
+
+    /// Resets the statistics, except for [`MirrorStats::in_flight`].
+    #[inline]
+    pub fn reset_stats(&self) {
+        let mut stats = self.stats.borrow_mut();
+        *stats = MirrorStats {
+            in_flight: stats.in_flight,
+            ..Default::default()
+        };
+    }
+
+    /// Same as [`Conn::call`], but the request may also be sent to the
+    /// mirror.
+    pub fn call<T>(
+        &self,
+        fn_name: &str,
+        args: &T,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+    {
+        self.with_mirror(
+            || {
+                Ok(MirrorRequest::Call {
+                    fn_name: fn_name.into(),
+                    args: args.to_tuple_buffer()?,
+                })
+            },
+            || self.primary.call(fn_name, args, options),
+        )
+    }
+
+    /// Same as [`Conn::eval`], but the request may also be sent to the
+    /// mirror.
+    pub fn eval<T>(&self, expr: &str, args: &T, options: &Options) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+    {
+        self.with_mirror(
+            || {
+                Ok(MirrorRequest::Eval {
+                    expr: expr.into(),
+                    args: args.to_tuple_buffer()?,
+                })
+            },
+            || self.primary.eval(expr, args, options),
+        )
+    }
+
+    /// Same as [`Conn::execute`], but the request may also be sent to the
+    /// mirror.
+    pub fn execute<P>(
+        &self,
+        sql: &str,
+        bind_params: &P,
+        options: &Options,
+    ) -> Result<Vec<Tuple>, Error>
+    where
+        P: ToTupleBuffer + ?Sized,
+    {
+        self.with_mirror(
+            || {
+                Ok(MirrorRequest::Execute {
+                    sql: sql.into(),
+                    bind_params: bind_params.to_tuple_buffer()?,
+                })
+            },
+            || self.primary.execute(sql, bind_params, options),
+        )
+    }
+
+    /// Returns `true` if the next request should be mirrored.
+    fn select(&self) -> bool {
+        let credit = self.credit.get() + self.options.ratio.clamp(0.0, 1.0);
+        if credit >= 1.0 {
+            self.credit.set(credit - 1.0);
+            true
+        } else {
+            self.credit.set(credit);
+            false
+        }
+    }
+
+    fn with_mirror<R>(
+        &self,
+        make_request: impl FnOnce() -> Result<MirrorRequest, Error>,
+        primary: impl FnOnce() -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        if !self.select() {
+            return primary();
+        }
+
+        if self.stats.borrow().in_flight >= self.options.max_in_flight {
+            self.stats.borrow_mut().dropped += 1;
+            return primary();
+        }
+        // If the arguments can't be encoded the primary request fails too.
+        let request = make_request()?;
+        self.send_to_mirror(request);
+
+        let start = Instant::now_accurate();
+        let res = primary();
+        self.stats.borrow_mut().primary.record(start.elapsed());
+        res
+    }
+
+    /// Sends the request to the mirror in a separate fiber, so that the
+    /// primary request is sent without waiting for the mirror's response.
+    fn send_to_mirror(&self, request: MirrorRequest) {
+        let mirror = self.mirror.clone();
+        let stats = self.stats.clone();
+        let options = Options {
+            timeout: Some(self.options.timeout),
+            ..Default::default()
+        };
+        {
+            let mut stats = stats.borrow_mut();
+            stats.mirrored += 1;
+            stats.in_flight += 1;
+        }
+
+        let f = move || {
+            let start = Instant::now_accurate();
+            let res = match &request {
+                MirrorRequest::Call { fn_name, args } => {
+                    mirror.call(fn_name, args, &options).map(drop)
+                }
+                MirrorRequest::Eval { expr, args } => mirror.eval(expr, args, &options).map(drop),
+                MirrorRequest::Execute { sql, bind_params } => {
+                    mirror.execute(sql, bind_params, &options).map(drop)
+                }
+            };
+            let latency = start.elapsed();
+
+            let mut stats = stats.borrow_mut();
+            stats.in_flight -= 1;
+            match res {
+                Ok(()) => stats.mirror.record(latency),
+                Err(e) => {
+                    stats.errors += 1;
+                    stats.last_error = Some(e.to_string());
+                }
+            }
+        };
+
+        let res = fiber::Builder::new()
+            .name("net_box_mirror")
+            .func(f)
+            .start_non_joinable();
+        if let Err(e) = res {
+            let mut stats = self.stats.borrow_mut();
+            stats.in_flight -= 1;
+            stats.errors += 1;
+            stats.last_error = Some(e.to_string());
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::net_box::ConnOptions;
+    use crate::test::util::listen_port;
+
+    fn test_user_conn() -> Conn {
+        Conn::new(
+            ("localhost", listen_port()),
+            ConnOptions {
+                user: "test_user".into(),
+                password: "password".into(),
+                ..ConnOptions::default()
+            },
+            None,
+        )
+        .unwrap()
+    }
+
+    fn wait_in_flight(conn: &MirroringConn) {
+        for _ in 0..100 {
+            if conn.stats().in_flight == 0 {
+                return;
+            }
+            fiber::sleep(Duration::from_millis(10));
+        }
+        panic!("mirrored requests didn't finish in time");
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn mirror_ratio() {
+        let conn = MirroringConn::new(
+            test_user_conn(),
+            test_user_conn(),
+            MirrorOptions {
+                ratio: 0.5,
+                ..Default::default()
+            },
+        );
+
+        for i in 0..4 {
+            let res = conn.eval("return ...", &(i,), &Default::default());
+            assert_eq!(res.unwrap().unwrap().decode::<(i32,)>().unwrap(), (i,));
+        }
+        wait_in_flight(&conn);
+
+        let stats = conn.stats();
+        assert_eq!(stats.mirrored, 2);
+        assert_eq!(stats.mirror.count, 2);
+        assert_eq!(stats.primary.count, 2);
+        assert_eq!(stats.errors, 0);
+        assert_eq!(stats.dropped, 0);
+        assert!(stats.mirror.mean().is_some());
+
+        conn.reset_stats();
+        assert_eq!(conn.stats(), MirrorStats::default());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn mirror_errors() {
+        let conn = MirroringConn::new(
+            test_user_conn(),
+            test_user_conn(),
+            MirrorOptions {
+                max_in_flight: 1,
+                ..Default::default()
+            },
+        );
+
+        conn.call("no_such_function", &(), &Default::default())
+            .unwrap_err();
+        // The first mirrored request is still in flight.
+        conn.call("no_such_function", &(), &Default::default())
+            .unwrap_err();
+        wait_in_flight(&conn);
+
+        let stats = conn.stats();
+        assert_eq!(stats.mirrored + stats.dropped, 2);
+        assert_eq!(stats.errors, stats.mirrored);
+        assert!(stats.last_error.unwrap().contains("no_such_function"));
+    }
+}
@@ -2,9 +2,17 @@
 //!
 //! You can call the following methods:
 //! - [Conn::new()](struct.Conn.html#method.new) to connect and get a connection object (named `conn` for examples in this section),
+//!   or [Conn::new_unix()](struct.Conn.html#method.new_unix), [Conn::from_fd()](struct.Conn.html#method.from_fd) and
+//!   [Conn::from_uri()](struct.Conn.html#method.from_uri) to connect over a unix domain socket or an already connected
+//!   socket,
 //! - other `net_box` routines, to execute requests on the remote database system,
 //! - [conn.close()](struct.Conn.html#method.close) to disconnect.
 //!
+//! With the `tls` feature the connections can be encrypted, see [ConnOptions::tls](struct.ConnOptions.html#structfield.tls).
+//!
+//! [MirroringConn](struct.MirroringConn.html) can be used to mirror a share of the requests to a second connection, see
+//! the [mirror](mirror/index.html) module.
+//!
 //! All [Conn](struct.Conn.html) methods are fiber-safe, that is, it is safe to share and use the same connection object
 //! across multiple concurrent fibers. In fact that is perhaps the best programming practice with Tarantool. When
 //! multiple fibers use the same connection, all requests are pipelined through the same network socket, but each fiber
@@ -40,21 +48,33 @@
 #![cfg(feature = "net_box")]
 
 use core::time::Duration;
+use std::cell::RefCell;
 use std::net::ToSocketAddrs;
+use std::os::unix::io::IntoRawFd;
+use std::path::Path;
 use std::rc::Rc;
 
 pub use index::{RemoteIndex, RemoteIndexIterator};
-use inner::ConnInner;
-pub use options::{ConnOptions, ConnTriggers, Options};
+use inner::{ConnAddress, ConnInner};
+pub use mirror::MirroringConn;
+pub use named_args::NamedArgs;
+pub use options::{ConnOptions, ConnTriggers, Options, Priority};
 use promise::Promise;
 pub use space::RemoteSpace;
+#[cfg(feature = "tls")]
+pub use tls::TlsOptions;
 
+use crate::coio::CoIOStream;
 use crate::error::Error;
 use crate::network::protocol;
 use crate::tuple::{Decode, ToTupleBuffer, Tuple};
+use named_args::Named;
+use serde::Serialize;
 
 mod index;
 mod inner;
+pub mod mirror;
+mod named_args;
 mod options;
 pub mod promise;
 mod recv_queue;
@@ -62,6 +82,8 @@ mod schema;
 mod send_queue;
 mod space;
 mod stream;
+#[cfg(feature = "tls")]
+mod tls;
 
 #[deprecated = "use `TarantoolError` instead"]
 pub type ResponseError = crate::error::TarantoolError;
@@ -86,12 +108,69 @@ impl Conn {
         options: ConnOptions,
         triggers: Option<Rc<dyn ConnTriggers>>,
     ) -> Result<Self, Error> {
+        let address = ConnAddress::Tcp(addr.to_socket_addrs()?.collect());
+        Ok(Conn {
+            inner: ConnInner::new(address, options, triggers)?,
+            is_master: true,
+        })
+    }
+
+    /// Create a new connection over a unix domain socket located at `path`.
+    ///
+    /// Works the same way as [`Conn::new`].
+    #[inline(always)]
+    pub fn new_unix(
+        path: impl AsRef<Path>,
+        options: ConnOptions,
+        triggers: Option<Rc<dyn ConnTriggers>>,
+    ) -> Result<Self, Error> {
+        let address = ConnAddress::Unix(path.as_ref().into());
+        Ok(Conn {
+            inner: ConnInner::new(address, options, triggers)?,
+            is_master: true,
+        })
+    }
+
+    /// Create a new connection over an already connected socket `fd`, for
+    /// example one end of a `socketpair` or a socket passed by the parent
+    /// process. The connection takes the ownership of `fd`.
+    ///
+    /// The remote side is expected to send the greeting as usual. Because the
+    /// socket can't be reopened, the connection isn't re-established after a
+    /// disconnect, it goes to the `error` state instead.
+    #[inline(always)]
+    pub fn from_fd(
+        fd: impl IntoRawFd,
+        options: ConnOptions,
+        triggers: Option<Rc<dyn ConnTriggers>>,
+    ) -> Result<Self, Error> {
+        let stream = CoIOStream::new(fd)?;
+        let address = ConnAddress::Fd(RefCell::new(Some(stream)));
         Ok(Conn {
-            inner: ConnInner::new(addr.to_socket_addrs()?.collect(), options, triggers)?,
+            inner: ConnInner::new(address, options, triggers)?,
             is_master: true,
         })
     }
 
+    /// Create a new connection to the instance listening on `uri`.
+    ///
+    /// Supported formats are `host:port`, `port` (meaning `localhost:port`)
+    /// and `unix/:path` for the unix domain sockets, same as for the
+    /// `box.cfg.listen` option.
+    pub fn from_uri(
+        uri: &str,
+        options: ConnOptions,
+        triggers: Option<Rc<dyn ConnTriggers>>,
+    ) -> Result<Self, Error> {
+        if let Some(path) = uri.strip_prefix("unix/:") {
+            Self::new_unix(path, options, triggers)
+        } else if let Ok(port) = uri.parse::<u16>() {
+            Self::new(("localhost", port), options, triggers)
+        } else {
+            Self::new(uri, options, triggers)
+        }
+    }
+
     #[inline(always)]
     fn downgrade(inner: Rc<ConnInner>) -> Self {
         Conn {
@@ -158,10 +237,73 @@ impl Conn {
         A: ToTupleBuffer,
         R: for<'de> Decode<'de> + 'static,
     {
-        self.inner.request_async(&protocol::Call {
-            fn_name,
-            args: &args,
-        })
+        self.call_async_with_options(fn_name, args, &Options::default())
+    }
+
+    /// Same as [`Conn::call_async`], but with explicit `options`. Only
+    /// [`Options::priority`] is used, the timeout can be specified when
+    /// waiting for the [`Promise`].
+    pub fn call_async_with_options<A, R>(
+        &self,
+        fn_name: &str,
+        args: A,
+        options: &Options,
+    ) -> crate::Result<Promise<R>>
+    where
+        A: ToTupleBuffer,
+        R: for<'de> Decode<'de> + 'static,
+    {
+        self.inner.request_async(
+            &protocol::Call {
+                fn_name,
+                args: &args,
+            },
+            options,
+        )
+    }
+
+    /// Call a remote stored procedure with a single table of named arguments.
+    ///
+    /// `args` is encoded as a msgpack map (structs are encoded with the field
+    /// names as the keys), so `conn.call_named("func", &map! { "user_id" => 1, "limit" => 10 }, ..)`
+    /// is the remote-call equivalent of `func({user_id = 1, limit = 10})`.
+    /// See [`map!`](crate::map).
+    ///
+    /// On the server side a rust stored procedure can accept the table as an
+    /// argument of a type deserialized from a map:
+    /// ```no_run
+    /// #[derive(serde::Deserialize)]
+    /// struct Args {
+    ///     user_id: u64,
+    ///     #[serde(default)]
+    ///     limit: Option<u32>,
+    /// }
+    ///
+    /// #[tarantool::proc]
+    /// fn get_orders(args: Args) -> Vec<u64> {
+    ///     vec![args.user_id]
+    /// }
+    /// ```
+    pub fn call_named<T>(
+        &self,
+        fn_name: &str,
+        args: &T,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.call(fn_name, &Named(args), options)
+    }
+
+    /// Same as [`Conn::call_named`] but without yielding, see
+    /// [`Conn::call_async`].
+    pub fn call_named_async<A, R>(&self, fn_name: &str, args: A) -> crate::Result<Promise<R>>
+    where
+        A: Serialize,
+        R: for<'de> Decode<'de> + 'static,
+    {
+        self.call_async(fn_name, Named(&args))
     }
 
     /// Evaluates and executes the expression in Lua-string, which may be any statement or series of statements.
@@ -187,12 +329,28 @@ impl Conn {
     /// If enqueuing a request succeeded a [`Promise`] is returned which will be
     /// kept once a response is received.
     pub fn eval_async<A, R>(&self, expr: &str, args: A) -> crate::Result<Promise<R>>
+    where
+        A: ToTupleBuffer,
+        R: for<'de> Decode<'de> + 'static,
+    {
+        self.eval_async_with_options(expr, args, &Options::default())
+    }
+
+    /// Same as [`Conn::eval_async`], but with explicit `options`. Only
+    /// [`Options::priority`] is used, the timeout can be specified when
+    /// waiting for the [`Promise`].
+    pub fn eval_async_with_options<A, R>(
+        &self,
+        expr: &str,
+        args: A,
+        options: &Options,
+    ) -> crate::Result<Promise<R>>
     where
         A: ToTupleBuffer,
         R: for<'de> Decode<'de> + 'static,
     {
         self.inner
-            .request_async(&protocol::Eval { expr, args: &args })
+            .request_async(&protocol::Eval { expr, args: &args }, options)
     }
 
     /// Search space by name on remote server
@@ -283,6 +441,49 @@ mod tests {
         }
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn call_named() {
+        crate::lua_state()
+            .exec(
+                "function test_call_named(opts)
+                    return opts.user_id * opts.limit, opts.filter.status
+                end",
+            )
+            .unwrap();
+        let conn = test_user_conn();
+
+        let args = crate::map! { "user_id" => 3, "limit" => 10, "filter" => crate::map! { "status" => "new" } };
+        let res = conn
+            .call_named("test_call_named", &args, &Default::default())
+            .unwrap();
+        assert_eq!(
+            res.unwrap().decode::<(u32, String)>().unwrap(),
+            (30, "new".into())
+        );
+
+        #[derive(Serialize)]
+        struct Filter<'a> {
+            status: &'a str,
+        }
+        #[derive(Serialize)]
+        struct Args<'a> {
+            user_id: u32,
+            limit: u32,
+            filter: Filter<'a>,
+        }
+        let args = Args {
+            user_id: 2,
+            limit: 5,
+            filter: Filter { status: "old" },
+        };
+        let res: (u32, String) = conn
+            .call_named_async("test_call_named", args)
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(res, (10, "old".into()));
+    }
+
     #[cfg(feature = "picodata")]
     #[crate::test(tarantool = "crate")]
     async fn md5_auth_method() {
@@ -0,0 +1,164 @@
+use std::io::Write;
+
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Serialize, Serializer};
+
+use crate::tuple::ToTupleBuffer;
+
+/// Builds [`NamedArgs`] for [`Conn::call_named`].
+///
+/// ```no_run
+/// use tarantool::net_box::{Conn, Options};
+///
+/// # let conn: Conn = unimplemented!();
+/// let args = tarantool::map! { "user_id" => 1, "limit" => 10, "tags" => vec!["a", "b"] };
+/// conn.call_named("get_orders", &args, &Options::default()).unwrap();
+/// ```
+///
+/// [`Conn::call_named`]: crate::net_box::Conn::call_named
+#[macro_export]
+macro_rules! map {
+    ($($name:expr => $value:expr),* $(,)?) => {
+        $crate::net_box::NamedArgs::new() $(.with($name, $value))*
+    };
+}
+
+/// A table of named arguments, which is encoded as a msgpack map.
+///
+/// Values of different types can be added, each value is converted to
+/// msgpack as soon as it's added (structs are encoded as maps). The
+/// conversion errors are reported when the arguments are encoded.
+///
+/// See [`map!`](crate::map) and [`Conn::call_named`].
+///
+/// [`Conn::call_named`]: crate::net_box::Conn::call_named
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NamedArgs {
+    args: Vec<(String, rmpv::Value)>,
+    error: Option<String>,
+}
+
+impl NamedArgs {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the argument `name` with the `value`. If an argument with the
+    /// same name was already added, it's replaced.
+    pub fn with(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        let name = name.into();
+        let value = match to_value(&value) {
+            Ok(value) => value,
+            Err(e) => {
+                self.error
+                    .get_or_insert_with(|| format!("failed to encode argument '{}': {}", name, e));
+                return self;
+            }
+        };
+        match self.args.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.args.push((name, value)),
+        }
+        self
+    }
+
+    /// Returns the value of the argument `name`.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&rmpv::Value> {
+        self.args.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+}
+
+fn to_value(value: &impl Serialize) -> Result<rmpv::Value, String> {
+    let data = rmp_serde::to_vec_named(value).map_err(|e| e.to_string())?;
+    rmpv::decode::read_value(&mut &data[..]).map_err(|e| e.to_string())
+}
+
+impl Serialize for NamedArgs {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if let Some(e) = &self.error {
+            return Err(S::Error::custom(e));
+        }
+        let mut map = serializer.serialize_map(Some(self.args.len()))?;
+        for (name, value) in &self.args {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// The arguments of a call with a single argument encoded with the struct
+/// fields as the map keys.
+pub(crate) struct Named<'a, T: ?Sized>(pub &'a T);
+
+impl<T> ToTupleBuffer for Named<'_, T>
+where
+    T: Serialize + ?Sized,
+{
+    fn write_tuple_data(&self, w: &mut impl Write) -> crate::Result<()> {
+        rmp::encode::write_array_len(w, 1)?;
+        rmp_serde::encode::write_named(w, self.0)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[crate::test(tarantool = "crate")]
+    fn encode() {
+        #[derive(Serialize)]
+        struct Filter {
+            status: &'static str,
+        }
+        let args = crate::map! {
+            "user_id" => 1,
+            "limit" => 10,
+            "filter" => Filter { status: "new" },
+            "limit" => 20,
+        };
+        assert_eq!(args.len(), 3);
+        assert_eq!(args.get("limit"), Some(&rmpv::Value::from(20)));
+
+        let data = Named(&args).to_tuple_buffer().unwrap();
+        let (decoded,): (BTreeMap<String, rmpv::Value>,) =
+            rmp_serde::from_slice(data.as_ref()).unwrap();
+        assert_eq!(decoded["user_id"], rmpv::Value::from(1));
+        assert_eq!(decoded["limit"], rmpv::Value::from(20));
+        assert_eq!(
+            decoded["filter"],
+            rmpv::Value::Map(vec![("status".into(), "new".into())])
+        );
+
+        struct Fails;
+        impl Serialize for Fails {
+            fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(S::Error::custom("oops"))
+            }
+        }
+        let args = crate::map! { "ok" => 1, "fails" => Fails };
+        let e = Named(&args).to_tuple_buffer().unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("failed to encode argument 'fails': oops"),
+            "{}",
+            e
+        );
+    }
+}
@@ -27,6 +27,39 @@ pub struct Options {
     /// Treats as unlimited if `None` specified.
     /// Default: `None`
     pub limit: Option<u32>,
+
+    /// The `priority` option specifies the order in which the requests
+    /// waiting to be sent over the connection are written to the socket, see
+    /// [`Priority`].
+    ///
+    /// Default: [`Priority::Normal`]
+    pub priority: Priority,
+}
+
+/// Priority of a request relative to the other requests sent over the same
+/// connection.
+///
+/// The requests of each priority are queued separately and whenever the
+/// connection's writer fiber flushes the queues, the high priority requests
+/// are written first, then the normal ones and then the low ones. A high
+/// priority request is also sent without waiting for
+/// [`ConnOptions::send_buffer_flush_interval`], so e.g. health checks and
+/// small gets can be marked as [`Priority::High`] and bulk scans as
+/// [`Priority::Low`] to keep the former from waiting behind the latter.
+///
+/// Note that the priority only affects the sending side, the server still
+/// handles the requests in the order it receives them.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// All of the priorities from the highest to the lowest.
+    pub const ALL: [Self; 3] = [Self::High, Self::Normal, Self::Low];
 }
 
 /// Connection options; see [Conn::new()](struct.Conn.html#method.new)
@@ -91,6 +124,23 @@ pub struct ConnOptions {
     ///
     /// Default: 65536
     pub recv_buffer_size: usize,
+
+    /// Encrypt the connection with TLS using these options.
+    ///
+    /// Default: `None`
+    #[cfg(feature = "tls")]
+    pub tls: Option<super::TlsOptions>,
+}
+
+impl ConnOptions {
+    /// Returns `true` if the connection is encrypted.
+    #[inline(always)]
+    pub fn is_tls(&self) -> bool {
+        #[cfg(feature = "tls")]
+        return self.tls.is_some();
+        #[cfg(not(feature = "tls"))]
+        return false;
+    }
 }
 
 impl Default for ConnOptions {
@@ -105,6 +155,8 @@ impl Default for ConnOptions {
             send_buffer_limit: 64000,
             send_buffer_size: 65536,
             recv_buffer_size: 65536,
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 }
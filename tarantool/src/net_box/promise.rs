@@ -340,7 +340,7 @@ pub trait Consumer {
                         error.code = header.error_code;
                     }
                     protocol::iproto_key::ERROR_EXT => {
-                        if let Some(e) = protocol::decode_extended_error(&mut value)? {
+                        if let Some(e) = protocol::decode_extended_error(&mut Cursor::new(value))? {
                             error = Some(e);
                         }
                     }
@@ -7,6 +7,7 @@ use std::rc::{Rc, Weak};
 use refpool::{Pool, PoolRef};
 use rmp::decode;
 
+use crate::buffer::{self, Buffer};
 use crate::clock;
 use crate::error::Error;
 use crate::fiber;
@@ -22,7 +23,7 @@ type Consumers = HashMap<SyncIndex, Weak<dyn Consumer>>;
 
 pub struct RecvQueue {
     is_active: Cell<bool>,
-    buffer: RefCell<Cursor<Vec<u8>>>,
+    buffer: RefCell<Cursor<Buffer>>,
     chunks: RefCell<Vec<Range<usize>>>,
     cond_map: RefCell<HashMap<SyncIndex, PoolRef<Cond>>>,
     cond_pool: Pool<Cond>,
@@ -35,7 +36,8 @@ pub struct RecvQueue {
 
 impl RecvQueue {
     pub fn new(buffer_size: usize) -> Self {
-        let buffer = vec![0; buffer_size];
+        let mut buffer = buffer::pool().take(buffer_size);
+        buffer.resize(buffer_size, 0);
         RecvQueue {
             is_active: Cell::new(true),
             buffer: RefCell::new(Cursor::new(buffer)),
@@ -216,3 +218,9 @@ impl RecvQueue {
         }
     }
 }
+
+impl Drop for RecvQueue {
+    fn drop(&mut self) {
+        buffer::pool().recycle(std::mem::take(self.buffer.get_mut().get_mut()));
+    }
+}
@@ -3,6 +3,7 @@ use once_cell::unsync::Lazy;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::error::Error;
@@ -12,7 +13,7 @@ use crate::network::protocol;
 use crate::space::{self, SystemSpace, SYSTEM_ID_MAX};
 use crate::tuple::Tuple;
 
-use super::inner::ConnInner;
+use super::inner::{ConnAddress, ConnInner};
 use super::options::Options;
 
 pub struct ConnSchema {
@@ -24,13 +25,19 @@ pub struct ConnSchema {
 }
 
 impl ConnSchema {
-    pub fn acquire(addrs: &[SocketAddr]) -> Rc<ConnSchema> {
-        let addr = SCHEMA_CACHE.with(|cache| {
+    pub fn acquire(address: &ConnAddress) -> Rc<ConnSchema> {
+        let keys: Vec<_> = match address {
+            ConnAddress::Tcp(addrs) => addrs.iter().copied().map(CacheKey::Tcp).collect(),
+            ConnAddress::Unix(path) => vec![CacheKey::Unix(path.clone())],
+            // The remote instance is unknown, so the schema can't be shared.
+            ConnAddress::Fd(_) => vec![],
+        };
+        let schema = SCHEMA_CACHE.with(|cache| {
             let cache = cache.cache.borrow();
-            addrs.iter().find_map(|addr| cache.get(addr).cloned())
+            keys.iter().find_map(|key| cache.get(key).cloned())
         });
-        if let Some(addr) = addr {
-            return addr;
+        if let Some(schema) = schema {
+            return schema;
         }
 
         let schema = Rc::new(ConnSchema {
@@ -43,8 +50,8 @@ impl ConnSchema {
 
         SCHEMA_CACHE.with(|cache| {
             let mut cache = cache.cache.borrow_mut();
-            for addr in addrs {
-                cache.insert(*addr, schema.clone());
+            for key in keys {
+                cache.insert(key, schema.clone());
             }
         });
 
@@ -155,8 +162,14 @@ impl ConnSchema {
     }
 }
 
+#[derive(PartialEq, Eq, Hash)]
+enum CacheKey {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
 struct ConnSchemaCache {
-    cache: RefCell<HashMap<SocketAddr, Rc<ConnSchema>>>,
+    cache: RefCell<HashMap<CacheKey, Rc<ConnSchema>>>,
 }
 
 unsafe impl Sync for ConnSchemaCache {}
@@ -1,7 +1,9 @@
 use std::cell::{Cell, RefCell};
-use std::io::{self, Cursor, Write};
+use std::io::{self, Write};
 use std::time::{Duration, SystemTime};
 
+use super::options::Priority;
+use crate::buffer::{self, Buffer};
 use crate::error::Error;
 use crate::fiber::{reschedule, Cond};
 use crate::network::protocol;
@@ -10,8 +12,9 @@ use crate::network::protocol::SyncIndex;
 pub struct SendQueue {
     is_active: Cell<bool>,
     sync: Cell<SyncIndex>,
-    front_buffer: RefCell<Cursor<Vec<u8>>>,
-    back_buffer: RefCell<Cursor<Vec<u8>>>,
+    front_buffer: RefCell<Buffer>,
+    /// Requests waiting to be sent, one buffer per [`Priority`].
+    back_buffers: [RefCell<Buffer>; 3],
     swap_cond: Cond,
     buffer_limit: u64,
     flush_interval: Duration,
@@ -22,45 +25,57 @@ impl SendQueue {
         SendQueue {
             is_active: Cell::new(true),
             sync: Cell::new(SyncIndex(0)),
-            front_buffer: RefCell::new(Cursor::new(Vec::with_capacity(buffer_size))),
-            back_buffer: RefCell::new(Cursor::new(Vec::with_capacity(buffer_size))),
+            front_buffer: RefCell::new(buffer::pool().take(buffer_size)),
+            back_buffers: Priority::ALL.map(|priority| {
+                // most requests are expected to be of normal priority
+                let buffer = if priority == Priority::Normal {
+                    buffer::pool().take(buffer_size)
+                } else {
+                    Buffer::new()
+                };
+                RefCell::new(buffer)
+            }),
             swap_cond: Cond::new(),
             buffer_limit: buffer_limit as u64,
             flush_interval,
         }
     }
 
-    pub fn send<R>(&self, request: &R) -> Result<SyncIndex, Error>
+    pub fn send<R>(&self, request: &R, priority: Priority) -> Result<SyncIndex, Error>
     where
         R: protocol::Request,
     {
         let sync = self.next_sync();
 
-        if self.back_buffer.borrow().position() >= self.buffer_limit {
+        let data_size = self.data_size();
+        if data_size >= self.buffer_limit {
             self.swap_cond.signal();
         }
 
-        let mut buffer = self.back_buffer.borrow_mut();
-        // Convert cursor type `Cursor<Vec<u8>>` -> `Cursor<&mut Vec<u8>>`
-        let msg_start_offset = buffer.position();
-        let mut adapted_buffer = Cursor::new(buffer.get_mut());
-        adapted_buffer.set_position(msg_start_offset);
-
-        protocol::write_to_buffer(&mut adapted_buffer, sync, request)?;
-
-        // Advance the shared cursor's position,
-        // because now only the adapted one knows the correct position
-        let new_offset = adapted_buffer.position();
-        buffer.set_position(new_offset);
+        let mut buffer = self.back_buffer(priority).borrow_mut();
+        protocol::write_to_buffer(&mut *buffer, sync, request)?;
 
         // trigger swap condition if buffer was empty before
-        if msg_start_offset == 0 {
+        if data_size == 0 {
             self.swap_cond.signal();
         }
 
         Ok(sync)
     }
 
+    #[inline(always)]
+    fn back_buffer(&self, priority: Priority) -> &RefCell<Buffer> {
+        &self.back_buffers[priority as usize]
+    }
+
+    /// Total size of the requests waiting to be sent.
+    fn data_size(&self) -> u64 {
+        self.back_buffers
+            .iter()
+            .map(|b| b.borrow().len() as u64)
+            .sum()
+    }
+
     pub fn next_sync(&self) -> SyncIndex {
         let sync = self.sync.get();
         self.sync.set(SyncIndex(sync.0 + 1));
@@ -76,30 +91,39 @@ impl SendQueue {
                 return Err(io::Error::from(io::ErrorKind::TimedOut));
             }
 
-            let data_size = self.back_buffer.borrow().position();
+            let data_size = self.data_size();
             if data_size == 0 {
                 // await for data (if buffer is empty)
                 self.swap_cond.wait();
                 continue;
             }
 
+            // high priority requests are sent right away
+            let has_urgent = !self.back_buffer(Priority::High).borrow().is_empty();
             if let Ok(elapsed) = start_ts.elapsed() {
-                if data_size > prev_data_size && elapsed <= self.flush_interval {
+                if !has_urgent && data_size > prev_data_size && elapsed <= self.flush_interval {
                     prev_data_size = data_size;
                     reschedule();
                     continue;
                 }
             }
 
-            self.back_buffer.swap(&self.front_buffer);
             break;
         }
 
-        // write front buffer contents to stream + clear front buffer
-        let mut buffer = self.front_buffer.borrow_mut();
-        stream.write_all(buffer.get_ref())?;
-        buffer.set_position(0);
-        buffer.get_mut().clear();
+        // The writes may yield, so the requests enqueued meanwhile are also
+        // sent in this flush unless their priority has already been flushed.
+        for priority in Priority::ALL {
+            if self.back_buffer(priority).borrow().is_empty() {
+                continue;
+            }
+            self.back_buffer(priority).swap(&self.front_buffer);
+
+            // write front buffer contents to stream + clear front buffer
+            let mut buffer = self.front_buffer.borrow_mut();
+            stream.write_all(&buffer)?;
+            buffer.clear();
+        }
         Ok(())
     }
 
@@ -108,3 +132,13 @@ impl SendQueue {
         self.swap_cond.signal();
     }
 }
+
+impl Drop for SendQueue {
+    fn drop(&mut self) {
+        let pool = buffer::pool();
+        pool.recycle(std::mem::take(self.front_buffer.get_mut()));
+        for buffer in &mut self.back_buffers {
+            pool.recycle(std::mem::take(buffer.get_mut()));
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::rc::Rc;
 
 use crate::error::Error;
 use crate::index::IteratorType;
-use crate::tuple::{Encode, ToTupleBuffer, Tuple};
+use crate::tuple::{ToTupleBuffer, Tuple};
 
 use super::index::{RemoteIndex, RemoteIndexIterator};
 use super::inner::ConnInner;
@@ -98,15 +98,15 @@ impl RemoteSpace {
     /// The remote-call equivalent of the local call `Space::update(...)`
     /// (see [details](../space/struct.Space.html#method.update)).
     #[inline(always)]
-    pub fn update<K, Op>(
+    pub fn update<K, Ops>(
         &self,
         key: &K,
-        ops: &[Op],
+        ops: &Ops,
         options: &Options,
     ) -> Result<Option<Tuple>, Error>
     where
         K: ToTupleBuffer + ?Sized,
-        Op: Encode,
+        Ops: ToTupleBuffer + ?Sized,
     {
         self.primary_key().update(key, ops, options)
     }
@@ -114,15 +114,15 @@ impl RemoteSpace {
     /// The remote-call equivalent of the local call `Space::upsert(...)`
     /// (see [details](../space/struct.Space.html#method.upsert)).
     #[inline(always)]
-    pub fn upsert<T, Op>(
+    pub fn upsert<T, Ops>(
         &self,
         value: &T,
-        ops: &[Op],
+        ops: &Ops,
         options: &Options,
     ) -> Result<Option<Tuple>, Error>
     where
         T: ToTupleBuffer + ?Sized,
-        Op: Encode,
+        Ops: ToTupleBuffer + ?Sized,
     {
         self.primary_key().upsert(value, ops, options)
     }
@@ -1,23 +1,68 @@
 use std::cell::Cell;
 use std::io::{self, Read, Write};
-use std::os::unix::io::{IntoRawFd, RawFd};
+use std::os::unix::io::AsRawFd;
 use std::rc::Rc;
 
 use crate::coio::{read, write, CoIOStream};
 use crate::error::Error;
-use crate::ffi::tarantool as ffi;
 use crate::fiber::Cond;
 
+#[cfg(feature = "tls")]
+use super::tls::TlsStream;
+
+/// Connection to the server, either plain or encrypted.
+pub enum Transport {
+    Plain(CoIOStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+
+impl Transport {
+    #[inline]
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => read(stream.as_raw_fd(), buf, None),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.recv(buf),
+        }
+    }
+
+    #[inline]
+    fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => write(stream.as_raw_fd(), buf, None),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.send(buf),
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv(buf)
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 pub struct ConnStream {
-    fd: RawFd,
+    transport: Rc<Transport>,
     reader_guard: Rc<ConnStreamGuard>,
     writer_guard: Rc<ConnStreamGuard>,
 }
 
 impl ConnStream {
-    pub fn new(stream: CoIOStream) -> Result<Self, Error> {
+    pub fn new(transport: Transport) -> Result<Self, Error> {
         Ok(ConnStream {
-            fd: stream.into_raw_fd(),
+            transport: Rc::new(transport),
             reader_guard: Rc::new(ConnStreamGuard {
                 is_acquired: Cell::new(false),
                 drop_cond: Cond::new(),
@@ -37,7 +82,7 @@ impl ConnStream {
         self.reader_guard.wait();
         self.reader_guard.is_acquired.set(true);
         ConnStreamReader {
-            fd: self.fd,
+            transport: self.transport.clone(),
             reader_guard: self.reader_guard.clone(),
         }
     }
@@ -46,7 +91,7 @@ impl ConnStream {
         self.writer_guard.wait();
         self.writer_guard.is_acquired.set(true);
         ConnStreamWriter {
-            fd: self.fd,
+            transport: self.transport.clone(),
             writer_guard: self.writer_guard.clone(),
         }
     }
@@ -69,18 +114,18 @@ impl Drop for ConnStream {
     fn drop(&mut self) {
         self.reader_guard.wait();
         self.writer_guard.wait();
-        unsafe { ffi::coio_close(self.fd) };
+        // The transport is closed once the last reference to it is dropped.
     }
 }
 
 pub struct ConnStreamReader {
-    fd: RawFd,
+    transport: Rc<Transport>,
     reader_guard: Rc<ConnStreamGuard>,
 }
 
 impl Read for ConnStreamReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        read(self.fd, buf, None)
+        self.transport.recv(buf)
     }
 }
 
@@ -92,13 +137,13 @@ impl Drop for ConnStreamReader {
 }
 
 pub struct ConnStreamWriter {
-    fd: RawFd,
+    transport: Rc<Transport>,
     writer_guard: Rc<ConnStreamGuard>,
 }
 
 impl Write for ConnStreamWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        write(self.fd, buf, None)
+        self.transport.send(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -38,12 +38,16 @@ pub mod tcp;
 
 use std::collections::HashMap;
 use std::io::Cursor;
+#[cfg(feature = "iproto_chunked")]
+use std::marker::PhantomData;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Duration;
 
 use self::tcp::TcpStream;
 
+#[cfg(feature = "iproto_chunked")]
+use super::protocol::api::ServerFeatures;
 use super::protocol::api::{Call, Eval, Execute, Ping, Request};
 use super::protocol::{self, Protocol, SyncIndex};
 use crate::error;
@@ -227,6 +231,73 @@ impl Client {
         Ok(Self(client))
     }
 
+    /// Waits until a message for `sync` is received.
+    async fn wait_message(&self, sync: SyncIndex) -> Result<(), ClientError> {
+        let (tx, rx) = oneshot::channel();
+        self.0.borrow_mut().awaiting_response.insert(sync, tx);
+        // Cleanup `awaiting_response` entry in case of `send` future cancelation
+        // at this `.await`.
+        // `send` can be canceled for example with `Timeout`.
+        rx.on_drop(|| {
+            let _ = self.0.borrow_mut().awaiting_response.remove(&sync);
+        })
+        .await
+        .expect("Channel should be open")
+        .map_err(ClientError::ConnectionClosed)
+    }
+
+    /// Sends [`Request`] and returns a [`ChunkedResponse`], which yields
+    /// the parts of the response as they are received. Available with the
+    /// `iproto_chunked` feature.
+    ///
+    /// Currently the server only sends the response in several parts if
+    /// `box.session.push` is used, in which case the pushed values are
+    /// decoded as `R::Response`. Future versions of the server may send the
+    /// results of the long requests (e.g. [`Select`]) in several chunks,
+    /// which can then be processed without waiting for the whole result.
+    ///
+    /// **NOTE**: [`Select`] sent with [`AsClient::send`] doesn't use the
+    /// chunks yet, it always waits for the final response. Streaming the
+    /// select results is out of scope until the server starts sending them
+    /// in chunks.
+    ///
+    /// [`Select`]: super::protocol::api::Select
+    #[cfg(feature = "iproto_chunked")]
+    pub fn send_chunked<R: Request>(&self, request: &R) -> Result<ChunkedResponse<R>, ClientError> {
+        if let Err(e) = self.check_state() {
+            return Err(ClientError::ConnectionClosed(e));
+        }
+        let sync = self
+            .0
+            .borrow_mut()
+            .protocol
+            .send_chunked_request(request)
+            .map_err(ClientError::RequestEncode)?;
+        maybe_wake_sender(&self.0.borrow());
+        Ok(ChunkedResponse {
+            client: self.clone(),
+            sync,
+            done: false,
+            marker: PhantomData,
+        })
+    }
+
+    /// Returns the protocol version and features supported by the server,
+    /// which are negotiated when the connection is established.
+    ///
+    /// `None` is returned if the server doesn't support the negotiation
+    /// (i.e. it's older than 2.10) or if the connection isn't established
+    /// yet, which is the case until a response to the first request is
+    /// received.
+    ///
+    /// Available with the `iproto_chunked` feature, without it the features
+    /// aren't negotiated.
+    #[cfg(feature = "iproto_chunked")]
+    #[inline]
+    pub fn server_features(&self) -> Option<ServerFeatures> {
+        self.0.borrow().protocol.server_features().cloned()
+    }
+
     fn check_state(&self) -> Result<(), Arc<error::Error>> {
         match &self.0.borrow().state {
             State::Alive => Ok(()),
@@ -301,21 +372,8 @@ impl AsClient for Client {
             }
         );
 
-        let (tx, rx) = oneshot::channel();
-        self.0.borrow_mut().awaiting_response.insert(sync, tx);
         maybe_wake_sender(&self.0.borrow());
-        // Cleanup `awaiting_response` entry in case of `send` future cancelation
-        // at this `.await`.
-        // `send` can be canceled for example with `Timeout`.
-        let res = rx
-            .on_drop(|| {
-                let _ = self.0.borrow_mut().awaiting_response.remove(&sync);
-            })
-            .await
-            .expect("Channel should be open");
-        if let Err(e) = res {
-            return Err(ClientError::ConnectionClosed(e));
-        }
+        self.wait_message(sync).await?;
 
         let res = self
             .0
@@ -379,6 +437,64 @@ impl Clone for Client {
     }
 }
 
+/// Response to a request sent with [`Client::send_chunked`].
+#[cfg(feature = "iproto_chunked")]
+#[derive(Debug)]
+pub struct ChunkedResponse<R> {
+    client: Client,
+    sync: SyncIndex,
+    done: bool,
+    marker: PhantomData<fn(&R)>,
+}
+
+#[cfg(feature = "iproto_chunked")]
+impl<R: Request> ChunkedResponse<R> {
+    /// Waits for the next part of the response. The final response is
+    /// returned last, after that `None` is returned.
+    ///
+    /// This function yields.
+    pub async fn next_chunk(&mut self) -> Option<Result<R::Response, ClientError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            {
+                let mut client = self.client.0.borrow_mut();
+                if let Some(res) = client.protocol.take_chunk::<R>(self.sync) {
+                    return Some(res.map_err(ClientError::ResponseDecode));
+                }
+                if let Some(res) = client.protocol.take_response::<R>(self.sync) {
+                    self.done = true;
+                    return Some(res.map_err(|e| match e {
+                        error::Error::Remote(e) => ClientError::ErrorResponse(e),
+                        e => ClientError::ResponseDecode(e),
+                    }));
+                }
+            }
+            if let Err(e) = self.client.wait_message(self.sync).await {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+
+    /// Returns `true` if the final response was returned by
+    /// [`Self::next_chunk`].
+    #[inline(always)]
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(feature = "iproto_chunked")]
+impl<R> Drop for ChunkedResponse<R> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.client.0.borrow_mut().protocol.drop_response(self.sync);
+        }
+    }
+}
+
 macro_rules! handle_result {
     ($client:expr, $e:expr) => {
         match $e {
@@ -454,7 +570,14 @@ async fn receiver(client_cell: Rc<NoYieldsRefCell<ClientInner>>, mut reader: Tcp
                     .send(Ok(()))
                     .expect("cannot be closed at this point");
             } else {
-                crate::say_warn!("received unwaited message for {sync:?}");
+                #[cfg(feature = "iproto_chunked")]
+                let is_chunked = client.protocol.is_chunked(sync);
+                #[cfg(not(feature = "iproto_chunked"))]
+                let is_chunked = false;
+                // The messages are taken when the next one is awaited.
+                if !is_chunked {
+                    crate::say_warn!("received unwaited message for {sync:?}");
+                }
             }
         }
 
@@ -526,6 +649,19 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "iproto_chunked")]
+    #[crate::test(tarantool = "crate")]
+    async fn server_features() {
+        let client = test_client().await;
+        client.ping().timeout(Duration::from_secs(3)).await.unwrap();
+
+        let features = client.server_features().unwrap();
+        assert!(features.version > 0);
+        // IPROTO_FEATURE_STREAMS is supported since the features negotiation
+        // was introduced.
+        assert!(features.supports(0));
+    }
+
     #[crate::test(tarantool = "crate")]
     fn ping_concurrent() {
         let client = fiber::block_on(test_client());
@@ -576,6 +712,34 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "iproto_chunked")]
+    #[crate::test(tarantool = "crate")]
+    async fn send_chunked() {
+        use crate::network::protocol::api::Eval;
+
+        let client = test_client().await;
+        let mut response = client
+            .send_chunked(&Eval {
+                expr: "box.session.push({1, 2}) box.session.push({3}) return {4}",
+                args: &(),
+            })
+            .unwrap();
+        let mut chunks = vec![];
+        while let Some(chunk) = response.next_chunk().await {
+            chunks.push(chunk.unwrap().decode::<Vec<Vec<i32>>>().unwrap());
+        }
+        assert!(response.is_done());
+        assert_eq!(chunks, [vec![vec![1, 2]], vec![vec![3]], vec![vec![4]]]);
+
+        // The chunks are ignored by the usual requests.
+        let res = client
+            .eval("box.session.push(1) return 2", &())
+            .timeout(Duration::from_secs(3))
+            .await
+            .unwrap();
+        assert_eq!(res.decode::<(i32,)>().unwrap(), (2,));
+    }
+
     #[crate::test(tarantool = "crate")]
     async fn call() {
         let client = test_client().await;
@@ -998,6 +1162,42 @@ mod tests {
         assert_eq!(e.line(), Some(error_line));
     }
 
+    #[crate::test(tarantool = "crate")]
+    async fn error_fields_and_cause_from_proc() {
+        #[crate::proc(tarantool = "crate")]
+        fn proc_error_fields_and_cause() -> Result<(), BoxError> {
+            Err(BoxError::new(666666_u32, "na ah")
+                .with_field("retry_after", 3)
+                .with_cause(BoxError::new(TarantoolErrorCode::Timeout, "too slow")))
+        }
+
+        let proc = crate::define_stored_proc_for_tests!(proc_error_fields_and_cause);
+        let client = test_client().await;
+
+        let res = client
+            .call(&proc, &())
+            .timeout(Duration::from_secs(3))
+            .await;
+
+        let e = match error::Error::from(res.unwrap_err()) {
+            error::Error::Remote(e) => e,
+            other => {
+                panic!("unexpected error: {}", other);
+            }
+        };
+
+        assert_eq!(e.error_code(), 666666);
+        assert_eq!(e.message(), "na ah");
+        assert_eq!(e.error_type(), "ClientError");
+        assert_eq!(e.fields().len(), 1);
+        assert_eq!(e.fields()["retry_after"], rmpv::Value::from(3));
+        let cause = e.cause().unwrap();
+        assert_eq!(cause.error_code(), TarantoolErrorCode::Timeout as u32);
+        assert_eq!(cause.message(), "too slow");
+        assert!(cause.fields().is_empty());
+        assert!(cause.cause().is_none());
+    }
+
     #[crate::test(tarantool = "crate")]
     async fn check_error_location() {
         // The line number reported for the error will point to the #[proc]
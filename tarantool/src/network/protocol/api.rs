@@ -4,7 +4,6 @@ use crate::error::Error;
 use crate::index::IndexId;
 use crate::index::IteratorType;
 use crate::space::SpaceId;
-use crate::tuple::Encode;
 use crate::tuple::{ToTupleBuffer, Tuple};
 
 use super::codec::IProtoType;
@@ -27,7 +26,7 @@ pub trait Request {
         Ok(())
     }
 
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error>;
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error>;
 }
 
 // TODO: Implement `Request` for other types in `IProtoType`
@@ -44,11 +43,51 @@ impl Request for Ping {
     }
 
     #[inline(always)]
-    fn decode_response_body(_in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(_in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         Ok(())
     }
 }
 
+/// Negotiation of the protocol version and features, the response contains
+/// the ones supported by the server.
+///
+/// See `IPROTO_FEATURE_*` in \<tarantool>/src/box/iproto_features.h for the
+/// list of the features.
+pub struct Id<'a> {
+    pub version: u64,
+    pub features: &'a [u64],
+}
+
+/// Protocol version and features supported by the server, see [`Id`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerFeatures {
+    pub version: u64,
+    pub features: Vec<u64>,
+}
+
+impl ServerFeatures {
+    #[inline]
+    pub fn supports(&self, feature: u64) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+impl<'a> Request for Id<'a> {
+    const TYPE: IProtoType = IProtoType::Id;
+    type Response = ServerFeatures;
+
+    #[inline(always)]
+    fn encode_body(&self, out: &mut impl Write) -> Result<(), Error> {
+        codec::encode_id(out, self.version, self.features)
+    }
+
+    #[inline(always)]
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
+        let (version, features) = codec::decode_id(r#in)?;
+        Ok(ServerFeatures { version, features })
+    }
+}
+
 pub struct Call<'a, 'b, T: ?Sized> {
     pub fn_name: &'a str,
     pub args: &'b T,
@@ -67,7 +106,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_call(r#in)
     }
 }
@@ -90,7 +129,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_call(r#in)
     }
 }
@@ -113,7 +152,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_multiple_rows(r#in)
     }
 }
@@ -135,7 +174,7 @@ impl<'u, 'p, 's> Request for Auth<'u, 'p, 's> {
     }
 
     #[inline(always)]
-    fn decode_response_body(_in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(_in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         Ok(())
     }
 }
@@ -170,7 +209,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_multiple_rows(r#in)
     }
 }
@@ -197,7 +236,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_single_row(r#in)
     }
 }
@@ -224,25 +263,26 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_single_row(r#in)
     }
 }
 
-pub struct Update<'a, T, Op>
+pub struct Update<'a, T, Ops>
 where
     T: ?Sized,
+    Ops: ?Sized,
 {
     pub space_id: SpaceId,
     pub index_id: IndexId,
     pub key: &'a T,
-    pub ops: &'a [Op],
+    pub ops: &'a Ops,
 }
 
-impl<'a, T, Op> Request for Update<'a, T, Op>
+impl<'a, T, Ops> Request for Update<'a, T, Ops>
 where
     T: ToTupleBuffer + ?Sized,
-    Op: Encode,
+    Ops: ToTupleBuffer + ?Sized,
 {
     const TYPE: IProtoType = IProtoType::Update;
     // TODO: can this be just Tuple?
@@ -254,25 +294,26 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_single_row(r#in)
     }
 }
 
-pub struct Upsert<'a, T, Op>
+pub struct Upsert<'a, T, Ops>
 where
     T: ?Sized,
+    Ops: ?Sized,
 {
     pub space_id: SpaceId,
     pub index_id: IndexId,
     pub value: &'a T,
-    pub ops: &'a [Op],
+    pub ops: &'a Ops,
 }
 
-impl<'a, T, Op> Request for Upsert<'a, T, Op>
+impl<'a, T, Ops> Request for Upsert<'a, T, Ops>
 where
     T: ToTupleBuffer + ?Sized,
-    Op: Encode,
+    Ops: ToTupleBuffer + ?Sized,
 {
     const TYPE: IProtoType = IProtoType::Upsert;
     // TODO: can this be just Tuple?
@@ -284,7 +325,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_single_row(r#in)
     }
 }
@@ -312,7 +353,7 @@ where
     }
 
     #[inline(always)]
-    fn decode_response_body(r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_response_body(r#in: &mut Cursor<impl AsRef<[u8]>>) -> Result<Self::Response, Error> {
         codec::decode_single_row(r#in)
     }
 }
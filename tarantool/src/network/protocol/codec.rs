@@ -47,6 +47,9 @@ pub mod iproto_key {
     // ...
     pub const ERROR_EXT: u8 = 0x52;
     // ...
+    pub const VERSION: u8 = 0x54;
+    pub const FEATURES: u8 = 0x55;
+    // ...
 }
 use iproto_key::*;
 
@@ -81,6 +84,13 @@ crate::define_enum_with_introspection! {
         // ...
         Ping = 64,
         // ...
+        /// Negotiation of the protocol version and features.
+        Id = 73,
+        // ...
+        /// A part of the response which is followed by other parts and
+        /// eventually by the final response with the same sync.
+        Chunk = 128,
+        // ...
         /// Error marker. This value will be combined with the error code in the
         /// actual iproto response: `(IProtoType::Error | error_code)`.
         Error = 1 << 15,
@@ -208,6 +218,18 @@ pub fn encode_ping(stream: &mut impl Write) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn encode_id(stream: &mut impl Write, version: u64, features: &[u64]) -> Result<(), Error> {
+    rmp::encode::write_map_len(stream, 2)?;
+    rmp::encode::write_pfix(stream, VERSION)?;
+    rmp::encode::write_uint(stream, version)?;
+    rmp::encode::write_pfix(stream, FEATURES)?;
+    rmp::encode::write_array_len(stream, features.len() as _)?;
+    for &feature in features {
+        rmp::encode::write_uint(stream, feature)?;
+    }
+    Ok(())
+}
+
 pub fn encode_execute<P>(stream: &mut impl Write, sql: &str, bind_params: &P) -> Result<(), Error>
 where
     P: ToTupleBuffer + ?Sized,
@@ -490,7 +512,10 @@ mod error_field {
 }
 
 /// Reads a IPROTO packet from the `stream` (i.e. a msgpack map with integer keys)
-pub fn decode_error(stream: &mut impl Read, header: &Header) -> Result<TarantoolError, Error> {
+pub fn decode_error(
+    stream: &mut (impl Read + Seek),
+    header: &Header,
+) -> Result<TarantoolError, Error> {
     let mut error = TarantoolError::default();
 
     let map_len = rmp::decode::read_map_len(stream)?;
@@ -511,6 +536,7 @@ pub fn decode_error(stream: &mut impl Read, header: &Header) -> Result<Tarantool
             }
             _ => {
                 crate::say_verbose!("unhandled iproto key {key} when decoding error");
+                msgpack::skip_value(stream)?;
             }
         }
     }
@@ -526,7 +552,9 @@ pub fn decode_error(stream: &mut impl Read, header: &Header) -> Result<Tarantool
     Ok(error)
 }
 
-pub fn decode_extended_error(stream: &mut impl Read) -> Result<Option<TarantoolError>, Error> {
+pub fn decode_extended_error(
+    stream: &mut (impl Read + Seek),
+) -> Result<Option<TarantoolError>, Error> {
     let extended_error_n_fields = rmp::decode::read_map_len(stream)? as usize;
     if extended_error_n_fields == 0 {
         return Ok(None);
@@ -561,6 +589,7 @@ pub fn decode_extended_error(stream: &mut impl Read) -> Result<Option<TarantoolE
             }
             _ => {
                 crate::say_verbose!("unknown extended error key {key}");
+                msgpack::skip_value(stream)?;
             }
         }
     }
@@ -568,7 +597,9 @@ pub fn decode_extended_error(stream: &mut impl Read) -> Result<Option<TarantoolE
     Ok(error_info)
 }
 
-pub fn decode_error_stack_node(mut stream: &mut impl Read) -> Result<TarantoolError, Error> {
+pub fn decode_error_stack_node(
+    mut stream: &mut (impl Read + Seek),
+) -> Result<TarantoolError, Error> {
     let mut res = TarantoolError::default();
 
     let map_len = rmp::decode::read_map_len(stream)? as usize;
@@ -596,16 +627,12 @@ pub fn decode_error_stack_node(mut stream: &mut impl Read) -> Result<TarantoolEr
             error_field::CODE => {
                 res.code = rmp::decode::read_int(stream)?;
             }
-            error_field::FIELDS => match rmp_serde::from_read(&mut stream) {
-                Ok(f) => {
-                    res.fields = f;
-                }
-                Err(e) => {
-                    crate::say_verbose!("failed decoding error fields: {e}");
-                }
-            },
+            error_field::FIELDS => {
+                res.fields = rmp_serde::from_read(&mut stream)?;
+            }
             _ => {
                 crate::say_verbose!("unexpected error field {key}");
+                msgpack::skip_value(stream)?;
             }
         }
     }
@@ -613,6 +640,64 @@ pub fn decode_error_stack_node(mut stream: &mut impl Read) -> Result<TarantoolEr
     Ok(res)
 }
 
+/// Writes the extended error info for `error` and its chain of causes (i.e.
+/// the payload of the MP_ERROR msgpack extension) into `stream`.
+pub fn encode_extended_error(
+    stream: &mut impl Write,
+    error: &TarantoolError,
+) -> Result<(), msgpack::EncodeError> {
+    let mut stack = vec![error];
+    while let Some(cause) = stack[stack.len() - 1].cause() {
+        stack.push(cause);
+    }
+
+    rmp::encode::write_map_len(stream, 1)?;
+    rmp::encode::write_pfix(stream, extended_error_keys::STACK)?;
+    rmp::encode::write_array_len(stream, stack.len() as _)?;
+    for node in stack {
+        encode_error_stack_node(stream, node)?;
+    }
+    Ok(())
+}
+
+pub fn encode_error_stack_node(
+    stream: &mut impl Write,
+    error: &TarantoolError,
+) -> Result<(), msgpack::EncodeError> {
+    let mut map_len = 5;
+    if error.errno.is_some() {
+        map_len += 1;
+    }
+    if !error.fields.is_empty() {
+        map_len += 1;
+    }
+    rmp::encode::write_map_len(stream, map_len)?;
+
+    rmp::encode::write_pfix(stream, error_field::TYPE)?;
+    rmp::encode::write_str(stream, error.error_type.as_deref().unwrap_or("ClientError"))?;
+    rmp::encode::write_pfix(stream, error_field::FILE)?;
+    rmp::encode::write_str(stream, error.file().unwrap_or(""))?;
+    rmp::encode::write_pfix(stream, error_field::LINE)?;
+    rmp::encode::write_uint(stream, error.line().unwrap_or(0) as _)?;
+    rmp::encode::write_pfix(stream, error_field::MESSAGE)?;
+    rmp::encode::write_str(stream, error.message())?;
+    if let Some(errno) = error.errno {
+        rmp::encode::write_pfix(stream, error_field::ERRNO)?;
+        rmp::encode::write_uint(stream, errno as _)?;
+    }
+    rmp::encode::write_pfix(stream, error_field::CODE)?;
+    rmp::encode::write_uint(stream, error.error_code() as _)?;
+    if !error.fields.is_empty() {
+        rmp::encode::write_pfix(stream, error_field::FIELDS)?;
+        rmp::encode::write_map_len(stream, error.fields.len() as _)?;
+        for (key, value) in &error.fields {
+            rmp::encode::write_str(stream, key)?;
+            rmpv::encode::write_value(stream, value)?;
+        }
+    }
+    Ok(())
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // ...
 ////////////////////////////////////////////////////////////////////////////////
@@ -628,11 +713,11 @@ pub fn decode_string(stream: &mut impl Read) -> Result<String, Error> {
 pub fn decode_greeting(stream: &mut impl Read) -> Result<Vec<u8>, Error> {
     let mut buf = [0; 128];
     stream.read_exact(&mut buf)?;
-    let salt = base64::decode(&buf[64..108]).unwrap();
+    let salt = crate::util::base64_decode(&buf[64..108]).map_err(Error::other)?;
     Ok(salt)
 }
 
-pub fn decode_call(buffer: &mut Cursor<Vec<u8>>) -> Result<Tuple, Error> {
+pub fn decode_call(buffer: &mut Cursor<impl AsRef<[u8]>>) -> Result<Tuple, Error> {
     let payload_len = rmp::decode::read_map_len(buffer)?;
     for _ in 0..payload_len {
         let key = rmp::decode::read_pfix(buffer)?;
@@ -652,7 +737,36 @@ pub fn decode_call(buffer: &mut Cursor<Vec<u8>>) -> Result<Tuple, Error> {
     .into())
 }
 
-pub fn decode_multiple_rows(buffer: &mut Cursor<Vec<u8>>) -> Result<Vec<Tuple>, Error> {
+/// Decodes the response to the [`IProtoType::Id`] request, returns the
+/// protocol version and features supported by the server.
+pub fn decode_id(buffer: &mut Cursor<impl AsRef<[u8]>>) -> Result<(u64, Vec<u64>), Error> {
+    let mut version = None;
+    let mut features = Vec::new();
+    let payload_len = rmp::decode::read_map_len(buffer)?;
+    for _ in 0..payload_len {
+        let key = rmp::decode::read_pfix(buffer)?;
+        match key {
+            VERSION => version = Some(rmp::decode::read_int(buffer)?),
+            FEATURES => {
+                let count = rmp::decode::read_array_len(buffer)? as usize;
+                features.reserve(count);
+                for _ in 0..count {
+                    features.push(rmp::decode::read_int(buffer)?);
+                }
+            }
+            _ => {
+                msgpack::skip_value(buffer)?;
+            }
+        }
+    }
+    let version = version.ok_or(ProtocolError::ResponseFieldNotFound {
+        key: "VERSION",
+        context: "required for ID responses",
+    })?;
+    Ok((version, features))
+}
+
+pub fn decode_multiple_rows(buffer: &mut Cursor<impl AsRef<[u8]>>) -> Result<Vec<Tuple>, Error> {
     let payload_len = rmp::decode::read_map_len(buffer)?;
     for _ in 0..payload_len {
         let key = rmp::decode::read_pfix(buffer)?;
@@ -673,7 +787,7 @@ pub fn decode_multiple_rows(buffer: &mut Cursor<Vec<u8>>) -> Result<Vec<Tuple>,
     Ok(vec![])
 }
 
-pub fn decode_single_row(buffer: &mut Cursor<Vec<u8>>) -> Result<Option<Tuple>, Error> {
+pub fn decode_single_row(buffer: &mut Cursor<impl AsRef<[u8]>>) -> Result<Option<Tuple>, Error> {
     let payload_len = rmp::decode::read_map_len(buffer)?;
     for _ in 0..payload_len {
         let key = rmp::decode::read_pfix(buffer)?;
@@ -694,14 +808,14 @@ pub fn decode_single_row(buffer: &mut Cursor<Vec<u8>>) -> Result<Option<Tuple>,
     Ok(None)
 }
 
-pub fn decode_tuple(buffer: &mut Cursor<Vec<u8>>) -> Result<Tuple, Error> {
+pub fn decode_tuple(buffer: &mut Cursor<impl AsRef<[u8]>>) -> Result<Tuple, Error> {
     let payload_offset = buffer.position();
     msgpack::skip_value(buffer)?;
     let payload_len = buffer.position() - payload_offset;
-    let buf = buffer.get_mut();
+    let buf = buffer.get_ref().as_ref();
     unsafe {
         Ok(Tuple::from_raw_data(
-            buf.as_slice().as_ptr().add(payload_offset as usize) as *mut c_char,
+            buf.as_ptr().add(payload_offset as usize) as *mut c_char,
             payload_len as u32,
         ))
     }
@@ -15,7 +15,9 @@ use crate::auth::AuthMethod;
 use crate::error;
 use crate::error::TarantoolError;
 use std::collections::HashMap;
-use std::io::{Cursor, Read, Seek};
+#[cfg(feature = "iproto_chunked")]
+use std::collections::VecDeque;
+use std::io::{Cursor, Read, Seek, Write};
 use std::time::Duration;
 
 #[deprecated = "use `ProtocolError` instead"]
@@ -65,6 +67,9 @@ pub type ResponseError = TarantoolError;
 enum State {
     /// Awaits greeting
     Init,
+    /// Awaits the response to [`api::Id`]
+    #[cfg(feature = "iproto_chunked")]
+    Id,
     /// Awaits auth
     Auth,
     /// Ready to accept new messages
@@ -101,11 +106,33 @@ pub struct Protocol {
     sync: SyncIndex,
     // TODO: limit incoming size
     incoming: HashMap<SyncIndex, Result<Vec<u8>, TarantoolError>>,
+    /// Bodies of the [`IProtoType::Chunk`] messages which precede the final
+    /// responses. Only the requests sent with
+    /// [`Protocol::send_chunked_request`] have an entry here, the chunks for
+    /// the other requests are dropped.
+    #[cfg(feature = "iproto_chunked")]
+    chunks: HashMap<SyncIndex, VecDeque<Vec<u8>>>,
     /// (user, password)
     creds: Option<(String, String)>,
     auth_method: AuthMethod,
+    /// Salt from the greeting, kept until the auth request is sent.
+    #[cfg(feature = "iproto_chunked")]
+    salt: Vec<u8>,
+    /// `None` until the features are negotiated or if the server doesn't
+    /// support [`api::Id`].
+    #[cfg(feature = "iproto_chunked")]
+    server_features: Option<ServerFeatures>,
 }
 
+/// Version of the protocol sent to the server in [`api::Id`].
+#[cfg(feature = "iproto_chunked")]
+pub const PROTOCOL_VERSION: u64 = 3;
+
+/// The protocol features sent to the server in [`api::Id`]. None of the
+/// features which change the format of the responses are supported yet.
+#[cfg(feature = "iproto_chunked")]
+pub const PROTOCOL_FEATURES: &[u64] = &[];
+
 impl Default for Protocol {
     fn default() -> Self {
         Self::new()
@@ -123,6 +150,12 @@ impl Protocol {
             auth_method: AuthMethod::default(),
             outgoing: Vec::new(),
             incoming: HashMap::new(),
+            #[cfg(feature = "iproto_chunked")]
+            chunks: HashMap::new(),
+            #[cfg(feature = "iproto_chunked")]
+            salt: Vec::new(),
+            #[cfg(feature = "iproto_chunked")]
+            server_features: None,
             // Greeting is exactly 128 bytes
             msg_size_hint: Some(128),
         }
@@ -146,6 +179,18 @@ impl Protocol {
         matches!(self.state, State::Ready)
     }
 
+    /// Returns the protocol version and features supported by the server.
+    ///
+    /// The features are negotiated with [`api::Id`] right after the greeting,
+    /// before the authorization. `None` is returned until then or if the
+    /// server doesn't support [`api::Id`]. Available with the
+    /// `iproto_chunked` feature.
+    #[cfg(feature = "iproto_chunked")]
+    #[inline(always)]
+    pub fn server_features(&self) -> Option<&ServerFeatures> {
+        self.server_features.as_ref()
+    }
+
     /// Processes incoming request and buffers generated outgoing bytes.
     /// Outgoing bytes can be retrieved with [`Protocol::take_outgoing_data`]
     ///
@@ -153,26 +198,40 @@ impl Protocol {
     /// If the protocol is not ready data will be queued and eventually processed
     /// after auth is done.
     pub fn send_request(&mut self, request: &impl Request) -> Result<SyncIndex, error::Error> {
-        let end = self.pending_outgoing.len();
-        let mut buf = Cursor::new(&mut self.pending_outgoing);
-        buf.set_position(end as u64);
         // TODO: limit the pending vec size
         // FIXME: Theoretically an error can happen in `Request::encode`.
         // This shouldn't ever happen in practice, as we're just writing into a memory buffer,
         // but our interfaces allow for this. So in case this happens here we will likely end
         // up with corrupted data in `self.pending_outgoing`.
         // It's pretty easy to fix, so we probably should...
-        write_to_buffer(&mut buf, self.sync, request)?;
+        write_to_buffer(&mut self.pending_outgoing, self.sync, request)?;
         self.process_pending_data();
         Ok(self.sync.next_index())
     }
 
+    /// Same as [`Protocol::send_request`], but the [`IProtoType::Chunk`]
+    /// messages preceding the final response are kept until they're taken
+    /// with [`Protocol::take_chunk`] or dropped with
+    /// [`Protocol::drop_response`].
+    #[cfg(feature = "iproto_chunked")]
+    pub fn send_chunked_request(
+        &mut self,
+        request: &impl Request,
+    ) -> Result<SyncIndex, error::Error> {
+        let sync = self.send_request(request)?;
+        self.chunks.insert(sync, VecDeque::new());
+        Ok(sync)
+    }
+
     /// Take existing response by [`SyncIndex`].
     pub fn take_response<R: Request>(
         &mut self,
         sync: SyncIndex,
     ) -> Option<Result<R::Response, error::Error>> {
-        let response = match self.incoming.remove(&sync)? {
+        let response = self.incoming.remove(&sync)?;
+        #[cfg(feature = "iproto_chunked")]
+        self.chunks.remove(&sync);
+        let response = match response {
             Ok(response) => response,
             Err(err) => return Some(Err(error::Error::Remote(err))),
         };
@@ -180,8 +239,55 @@ impl Protocol {
     }
 
     /// Drop response by [`SyncIndex`] if it exists. If not - does nothing.
+    ///
+    /// The chunks received after this are dropped too.
     pub fn drop_response(&mut self, sync: SyncIndex) {
         self.incoming.remove(&sync);
+        #[cfg(feature = "iproto_chunked")]
+        self.chunks.remove(&sync);
+    }
+
+    /// Returns `true` if the final response for [`SyncIndex`] was received
+    /// and can be taken with [`Protocol::take_response`].
+    #[inline]
+    pub fn has_response(&self, sync: SyncIndex) -> bool {
+        self.incoming.contains_key(&sync)
+    }
+
+    /// Take the oldest of the received [`IProtoType::Chunk`] messages for
+    /// [`SyncIndex`] which weren't taken yet. The chunks are decoded the same
+    /// way as the final response. Only the chunks for the requests sent with
+    /// [`Protocol::send_chunked_request`] are kept.
+    ///
+    /// Currently the server only sends the chunks for `box.session.push`, but
+    /// future versions may send the results of the long requests (e.g.
+    /// SELECT) in several chunks.
+    #[cfg(feature = "iproto_chunked")]
+    pub fn take_chunk<R: Request>(
+        &mut self,
+        sync: SyncIndex,
+    ) -> Option<Result<R::Response, error::Error>> {
+        let chunk = self.chunks.get_mut(&sync)?.pop_front()?;
+        Some(R::decode_response_body(&mut Cursor::new(chunk)))
+    }
+
+    /// Returns `true` if there are [`IProtoType::Chunk`] messages for
+    /// [`SyncIndex`] which weren't taken yet.
+    #[cfg(feature = "iproto_chunked")]
+    #[inline]
+    pub fn has_chunks(&self, sync: SyncIndex) -> bool {
+        self.chunks
+            .get(&sync)
+            .is_some_and(|chunks| !chunks.is_empty())
+    }
+
+    /// Returns `true` if the request was sent with
+    /// [`Protocol::send_chunked_request`] and its response wasn't taken or
+    /// dropped yet.
+    #[cfg(feature = "iproto_chunked")]
+    #[inline]
+    pub fn is_chunked(&self, sync: SyncIndex) -> bool {
+        self.chunks.contains_key(&sync)
     }
 
     /// See [`Protocol::process_incoming`].
@@ -230,29 +336,44 @@ impl Protocol {
         message: &mut R,
     ) -> Result<Option<SyncIndex>, error::Error> {
         let sync = match self.state {
+            #[cfg(feature = "iproto_chunked")]
+            State::Init => {
+                self.salt = codec::decode_greeting(message)?;
+                // Write straight to outgoing, it should be empty
+                debug_assert!(self.outgoing.is_empty());
+                let sync = self.sync.next_index();
+                write_to_buffer(
+                    &mut self.outgoing,
+                    sync,
+                    &api::Id {
+                        version: PROTOCOL_VERSION,
+                        features: PROTOCOL_FEATURES,
+                    },
+                )?;
+                self.state = State::Id;
+                None
+            }
+            #[cfg(not(feature = "iproto_chunked"))]
             State::Init => {
                 let salt = codec::decode_greeting(message)?;
-                if let Some((user, pass)) = self.creds.as_ref() {
-                    // Auth
-                    self.state = State::Auth;
-                    // Write straight to outgoing, it should be empty
-                    debug_assert!(self.outgoing.is_empty());
-                    let mut buf = Cursor::new(&mut self.outgoing);
-                    let sync = self.sync.next_index();
-                    write_to_buffer(
-                        &mut buf,
-                        sync,
-                        &api::Auth {
-                            user,
-                            pass,
-                            salt: &salt,
-                            method: self.auth_method,
-                        },
-                    )?;
+                self.start_auth(&salt)?;
+                None
+            }
+            #[cfg(feature = "iproto_chunked")]
+            State::Id => {
+                let header = codec::Header::decode(message)?;
+                if header.iproto_type == IProtoType::Error as u32 {
+                    // The server doesn't support the features negotiation,
+                    // i.e. it's older than 2.10.
+                    codec::decode_error(message, &header)?;
                 } else {
-                    // No auth
-                    self.state = State::Ready;
+                    let mut buf = Vec::new();
+                    message.read_to_end(&mut buf)?;
+                    let features = api::Id::decode_response_body(&mut Cursor::new(buf))?;
+                    self.server_features = Some(features);
                 }
+                let salt = std::mem::take(&mut self.salt);
+                self.start_auth(&salt)?;
                 None
             }
             State::Auth => {
@@ -266,6 +387,17 @@ impl Protocol {
             }
             State::Ready => {
                 let header = codec::Header::decode(message)?;
+                #[cfg(feature = "iproto_chunked")]
+                if header.iproto_type == IProtoType::Chunk as u32 {
+                    let Some(chunks) = self.chunks.get_mut(&header.sync) else {
+                        // Nobody is going to take this chunk.
+                        return Ok(None);
+                    };
+                    let mut buf = Vec::new();
+                    message.read_to_end(&mut buf)?;
+                    chunks.push_back(buf);
+                    return Ok(Some(header.sync));
+                }
                 let response;
                 if header.iproto_type == IProtoType::Error as u32 {
                     response = Err(codec::decode_error(message, &header)?);
@@ -283,6 +415,32 @@ impl Protocol {
         Ok(sync)
     }
 
+    /// Sends the auth request if the credentials are set, otherwise the
+    /// connection becomes ready right away.
+    fn start_auth(&mut self, salt: &[u8]) -> Result<(), error::Error> {
+        if let Some((user, pass)) = self.creds.as_ref() {
+            // Auth
+            self.state = State::Auth;
+            // Write straight to outgoing, it should be empty
+            debug_assert!(self.outgoing.is_empty());
+            let sync = self.sync.next_index();
+            write_to_buffer(
+                &mut self.outgoing,
+                sync,
+                &api::Auth {
+                    user,
+                    pass,
+                    salt,
+                    method: self.auth_method,
+                },
+            )?;
+        } else {
+            // No auth
+            self.state = State::Ready;
+        }
+        Ok(())
+    }
+
     /// Returns a number of outgoing data bytes.
     pub fn ready_outgoing_len(&self) -> usize {
         self.outgoing.len()
@@ -305,24 +463,30 @@ impl Protocol {
     }
 }
 
-pub(crate) fn write_to_buffer(
-    buffer: &mut Cursor<&mut Vec<u8>>,
+/// Appends the `request` prefixed with its size to the `buffer`.
+pub(crate) fn write_to_buffer<W>(
+    buffer: &mut W,
     sync: SyncIndex,
     request: &impl Request,
-) -> Result<(), error::Error> {
+) -> Result<(), error::Error>
+where
+    W: Write + AsMut<[u8]>,
+{
     // write MSG_SIZE placeholder
-    let msg_start_offset = buffer.position();
+    let msg_start_offset = buffer.as_mut().len();
     rmp::encode::write_u32(buffer, 0)?;
 
     // write message payload
-    let payload_start_offset = buffer.position();
+    let payload_start_offset = buffer.as_mut().len();
     request.encode(buffer, sync)?;
-    let payload_end_offset = buffer.position();
+    let payload_end_offset = buffer.as_mut().len();
 
     // calculate and write MSG_SIZE
-    buffer.set_position(msg_start_offset);
-    rmp::encode::write_u32(buffer, (payload_end_offset - payload_start_offset) as u32)?;
-    buffer.set_position(payload_end_offset);
+    let mut msg_size = &mut buffer.as_mut()[msg_start_offset..payload_start_offset];
+    rmp::encode::write_u32(
+        &mut msg_size,
+        (payload_end_offset - payload_start_offset) as u32,
+    )?;
 
     Ok(())
 }
@@ -346,6 +510,38 @@ mod tests {
         greeting
     }
 
+    #[cfg(feature = "iproto_chunked")]
+    fn fake_header(res: &mut Vec<u8>, iproto_type: u32, sync: SyncIndex) {
+        rmp::encode::write_map_len(res, 3).unwrap();
+        rmp::encode::write_pfix(res, iproto_key::REQUEST_TYPE).unwrap();
+        rmp::encode::write_uint(res, iproto_type as _).unwrap();
+        rmp::encode::write_pfix(res, iproto_key::SYNC).unwrap();
+        rmp::encode::write_uint(res, sync.get()).unwrap();
+        rmp::encode::write_pfix(res, iproto_key::SCHEMA_VERSION).unwrap();
+        rmp::encode::write_uint(res, 1).unwrap();
+    }
+
+    /// Response to the [`api::Id`] sent after the greeting.
+    #[cfg(feature = "iproto_chunked")]
+    fn fake_id_response(version: u64, features: &[u64]) -> Vec<u8> {
+        let mut res = Vec::new();
+        fake_header(&mut res, IProtoType::Ok as _, SyncIndex(0));
+        codec::encode_id(&mut res, version, features).unwrap();
+        res
+    }
+
+    /// Establishes the connection without the authorization.
+    #[cfg(feature = "iproto_chunked")]
+    fn fake_connection() -> Protocol {
+        let mut conn = Protocol::new();
+        conn.process_incoming(&mut Cursor::new(fake_greeting()))
+            .unwrap();
+        conn.process_message(&mut Cursor::new(fake_id_response(3, &[0, 1])))
+            .unwrap();
+        conn
+    }
+
+    #[cfg(not(feature = "iproto_chunked"))]
     #[crate::test(tarantool = "crate")]
     fn connection_established() {
         let mut conn = Protocol::new();
@@ -359,6 +555,7 @@ mod tests {
         assert!(conn.is_ready())
     }
 
+    #[cfg(not(feature = "iproto_chunked"))]
     #[crate::test(tarantool = "crate")]
     fn send_bytes_generated() {
         let mut conn = Protocol::new();
@@ -367,4 +564,144 @@ mod tests {
         conn.send_request(&api::Ping).unwrap();
         assert!(conn.ready_outgoing_len() > 0);
     }
+
+    #[cfg(feature = "iproto_chunked")]
+    #[crate::test(tarantool = "crate")]
+    fn features_negotiated() {
+        let mut conn = Protocol::new();
+        assert!(!conn.is_ready());
+        assert_eq!(conn.msg_size_hint, Some(128));
+        assert_eq!(conn.read_size_hint(), 128);
+        conn.process_incoming(&mut Cursor::new(fake_greeting()))
+            .unwrap();
+        assert_eq!(conn.msg_size_hint, None);
+        assert_eq!(conn.read_size_hint(), 5);
+        // The features are negotiated first.
+        assert!(!conn.is_ready());
+        assert!(conn.ready_outgoing_len() > 0);
+        conn.take_outgoing_data();
+        conn.process_message(&mut Cursor::new(fake_id_response(3, &[0, 1])))
+            .unwrap();
+        assert!(conn.is_ready());
+        assert_eq!(conn.ready_outgoing_len(), 0);
+        assert_eq!(
+            conn.server_features(),
+            Some(&ServerFeatures {
+                version: 3,
+                features: vec![0, 1],
+            })
+        );
+    }
+
+    #[cfg(feature = "iproto_chunked")]
+    #[crate::test(tarantool = "crate")]
+    fn features_not_supported() {
+        let mut conn = Protocol::with_config(Config {
+            creds: Some(("user".into(), "password".into())),
+            ..Default::default()
+        });
+        conn.process_incoming(&mut Cursor::new(fake_greeting()))
+            .unwrap();
+        conn.take_outgoing_data();
+
+        let mut msg = Vec::new();
+        fake_header(&mut msg, IProtoType::Error as u32 | 48, SyncIndex(0));
+        rmp::encode::write_map_len(&mut msg, 1).unwrap();
+        rmp::encode::write_pfix(&mut msg, iproto_key::ERROR).unwrap();
+        rmp::encode::write_str(&mut msg, "Unknown request type 73").unwrap();
+        let res = conn.process_message(&mut Cursor::new(msg)).unwrap();
+        assert_eq!(res, None);
+        assert_eq!(conn.server_features(), None);
+
+        // The auth request is sent next.
+        assert!(!conn.is_ready());
+        assert!(conn.ready_outgoing_len() > 0);
+    }
+
+    #[cfg(feature = "iproto_chunked")]
+    #[crate::test(tarantool = "crate")]
+    fn send_bytes_generated_after_negotiation() {
+        let mut conn = fake_connection();
+        conn.send_request(&api::Ping).unwrap();
+        assert!(conn.ready_outgoing_len() > 0);
+    }
+
+    #[cfg(feature = "iproto_chunked")]
+    #[crate::test(tarantool = "crate")]
+    fn chunked_response() {
+        fn message(iproto_type: IProtoType, sync: SyncIndex, data: &[u32]) -> Vec<u8> {
+            let mut res = Vec::new();
+            fake_header(&mut res, iproto_type as _, sync);
+            rmp::encode::write_map_len(&mut res, 1).unwrap();
+            rmp::encode::write_pfix(&mut res, iproto_key::DATA).unwrap();
+            rmp::encode::write_array_len(&mut res, data.len() as _).unwrap();
+            for &v in data {
+                rmp::encode::write_array_len(&mut res, 1).unwrap();
+                rmp::encode::write_uint(&mut res, v as _).unwrap();
+            }
+            res
+        }
+
+        let mut conn = fake_connection();
+        let select = api::Select {
+            space_id: 512,
+            index_id: 0,
+            limit: u32::MAX,
+            offset: 0,
+            iterator_type: crate::index::IteratorType::All,
+            key: &(),
+        };
+        type Select = api::Select<'static, ()>;
+
+        // The chunks for the usual requests are dropped.
+        let sync = conn.send_request(&select).unwrap();
+        let msg = message(IProtoType::Chunk, sync, &[1]);
+        let res = conn.process_message(&mut Cursor::new(msg)).unwrap();
+        assert_eq!(res, None);
+        assert!(!conn.is_chunked(sync));
+        assert!(conn.take_chunk::<Select>(sync).is_none());
+        let msg = message(IProtoType::Ok, sync, &[1]);
+        let res = conn.process_message(&mut Cursor::new(msg)).unwrap();
+        assert_eq!(res, Some(sync));
+        conn.take_response::<Select>(sync).unwrap().unwrap();
+
+        let sync = conn.send_chunked_request(&select).unwrap();
+
+        for (iproto_type, data) in [
+            (IProtoType::Chunk, &[1, 2][..]),
+            (IProtoType::Chunk, &[3][..]),
+            (IProtoType::Ok, &[4][..]),
+        ] {
+            let msg = message(iproto_type, sync, data);
+            let res = conn.process_message(&mut Cursor::new(msg)).unwrap();
+            assert_eq!(res, Some(sync));
+        }
+        assert!(conn.has_response(sync));
+
+        let mut chunks = vec![];
+        while let Some(chunk) = conn.take_chunk::<Select>(sync) {
+            let chunk = chunk.unwrap();
+            chunks.push(
+                chunk
+                    .iter()
+                    .map(|t| t.get(0).unwrap())
+                    .collect::<Vec<u32>>(),
+            );
+        }
+        assert_eq!(chunks, [vec![1, 2], vec![3]]);
+        assert!(!conn.has_chunks(sync));
+        assert!(conn.is_chunked(sync));
+        let rows = conn.take_response::<Select>(sync).unwrap().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!(!conn.has_response(sync));
+        assert!(!conn.is_chunked(sync));
+
+        // The chunks received after the response is dropped are dropped too.
+        let sync = conn.send_chunked_request(&select).unwrap();
+        conn.drop_response(sync);
+        let msg = message(IProtoType::Chunk, sync, &[1]);
+        let res = conn.process_message(&mut Cursor::new(msg)).unwrap();
+        assert_eq!(res, None);
+        assert!(conn.chunks.is_empty());
+    }
 }
@@ -0,0 +1,518 @@
+//! Transactional outbox.
+//!
+//! A stored procedure which changes the data and notifies an external system
+//! about the change (e.g. sends an HTTP request or a message to a queue) can't
+//! do both atomically: the notification can be sent for a transaction which is
+//! then rolled back, or get lost if the instance restarts after the commit.
+//!
+//! The outbox pattern solves this by writing the notification to a space in
+//! the same transaction as the data, see [`enqueue`]. A [`Delivery`] fiber
+//! then forwards the committed entries to a user provided [`Sink`], retrying
+//! the failed deliveries, and deletes the entries once they're delivered.
+//!
+//! The guarantees are:
+//! - an entry is only enqueued if the transaction it was enqueued in is
+//!   committed,
+//! - each entry is delivered at least once, so the sink should be idempotent
+//!   (e.g. use [`Entry::id`] for deduplication),
+//! - the entries of the same topic are delivered in the order they were
+//!   enqueued, a failed entry blocks the next entries of its topic until it's
+//!   delivered or discarded (see [`DeliveryOptions::max_attempts`]).
+//!
+//! Note that the changes of a memtx transaction become visible to the other
+//! fibers while the transaction is being written to the WAL, so an entry can
+//! be delivered before the transaction is rolled back due to a WAL write
+//! error, unless the MVCC transaction manager is enabled.
+//!
+//! ```no_run
+//! use tarantool::outbox::{self, Delivery, DeliveryOptions, Entry};
+//! use tarantool::space::Space;
+//! use tarantool::transaction::transaction;
+//!
+//! // On startup.
+//! let delivery = Delivery::start(DeliveryOptions::default(), |entry: &Entry| {
+//!     let (user_id, email): (u64, String) = entry.payload()?;
+//!     // Send the notification to the external system ...
+//!     Ok(())
+//! })
+//! .unwrap();
+//!
+//! // In a stored procedure.
+//! transaction(|| -> tarantool::Result<()> {
+//!     Space::find("users").unwrap().insert(&(1, "alice@example.com"))?;
+//!     outbox::enqueue("user_created", &(1, "alice@example.com"))?;
+//!     Ok(())
+//! })
+//! .unwrap();
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BoxError, Error, TarantoolErrorCode};
+use crate::fiber::{self, Cond, FiberId};
+use crate::index::IteratorType;
+use crate::space::{Field, Space, UpdateOps};
+use crate::time::Instant;
+use crate::tuple::Encode;
+
+/// Name of the space [`enqueue`] writes to and [`Delivery`] reads from by
+/// default.
+pub const DEFAULT_SPACE_NAME: &str = "_rust_outbox";
+
+/// Index of the `attempts` field in the tuples of the outbox space.
+const ATTEMPTS_FIELD: u32 = 3;
+
+thread_local! {
+    /// Broadcast each time an entry is enqueued.
+    static WAKEUP: Cond = Cond::new();
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Entry
+////////////////////////////////////////////////////////////////////////////////
+
+/// An entry of the outbox space.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unique id of the entry, which grows in the order of the entries.
+    pub id: u64,
+    pub topic: String,
+    /// Time of the [`enqueue`] call as the number of seconds since the unix
+    /// epoch.
+    pub created_at: f64,
+    /// Number of the failed delivery attempts.
+    pub attempts: u32,
+    pub payload: rmpv::Value,
+}
+
+impl Encode for Entry {}
+
+impl Entry {
+    /// Deserializes the payload passed to [`enqueue`].
+    #[inline]
+    pub fn payload<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        rmpv::ext::from_value(self.payload.clone()).map_err(Error::other)
+    }
+}
+
+/// Creates the outbox space named `name` if it doesn't exist.
+///
+/// [`Delivery::start`] creates the space automatically, so this only needs
+/// to be called if the entries can be enqueued before the delivery is started.
+pub fn create_space(name: &str) -> Result<Space, Error> {
+    Space::builder(name)
+        .if_not_exists(true)
+        .field(Field::unsigned("id"))
+        .field(Field::string("topic"))
+        .field(Field::double("created_at"))
+        .field(Field::unsigned("attempts"))
+        .field(Field::any("payload"))
+        .index("pk", |i| i.part("id").sequence(true))
+        .create()
+}
+
+/// Writes an entry with the given `topic` and `payload` to the default outbox
+/// space ([`DEFAULT_SPACE_NAME`]). Returns the id of the entry.
+///
+/// If called inside a transaction, the entry is only delivered if the
+/// transaction is committed.
+///
+/// Returns an error if the space doesn't exist, see [`create_space`].
+pub fn enqueue<T>(topic: &str, payload: &T) -> Result<u64, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let Some(space) = Space::find_cached(DEFAULT_SPACE_NAME) else {
+        return Err(BoxError::new(
+            TarantoolErrorCode::NoSuchSpace,
+            format!(
+                "outbox space '{}' doesn't exist, start the delivery or call outbox::create_space first",
+                DEFAULT_SPACE_NAME
+            ),
+        )
+        .into());
+    };
+    enqueue_to(&space, topic, payload)
+}
+
+/// Same as [`enqueue`], but writes to the given outbox `space`.
+pub fn enqueue_to<T>(space: &Space, topic: &str, payload: &T) -> Result<u64, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    // The id is generated by the space's sequence.
+    let tuple = space.insert(&(None::<u64>, topic, created_at, 0_u32, payload))?;
+    let id = tuple.field(0)?.expect("id is generated by the sequence");
+    WAKEUP.with(Cond::broadcast);
+    Ok(id)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Sink
+////////////////////////////////////////////////////////////////////////////////
+
+/// Destination of the outbox entries, see [`Delivery`].
+///
+/// Implemented for the closures of the form `FnMut(&Entry) -> Result<(), Error>`.
+pub trait Sink {
+    /// Delivers the `entry`. If an error is returned, the delivery is retried
+    /// later.
+    ///
+    /// May yield, e.g. to make a network request.
+    fn deliver(&mut self, entry: &Entry) -> Result<(), Error>;
+}
+
+impl<F> Sink for F
+where
+    F: FnMut(&Entry) -> Result<(), Error>,
+{
+    #[inline(always)]
+    fn deliver(&mut self, entry: &Entry) -> Result<(), Error> {
+        self(entry)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Delivery
+////////////////////////////////////////////////////////////////////////////////
+
+/// Options of [`Delivery`].
+#[derive(Clone, Debug)]
+pub struct DeliveryOptions {
+    /// Name of the outbox space, which is created if it doesn't exist.
+    ///
+    /// Default: [`DEFAULT_SPACE_NAME`]
+    pub space_name: String,
+
+    /// Maximum number of entries read from the space at once.
+    ///
+    /// Default: 100
+    pub batch_size: usize,
+
+    /// How often the space is checked for the new entries if the fiber isn't
+    /// woken up by [`enqueue`], e.g. for the entries which came via
+    /// replication.
+    ///
+    /// Default: 1 second
+    pub poll_interval: Duration,
+
+    /// Delay before the first retry of a failed delivery. The delay doubles
+    /// with each subsequent failure of the same entry up to
+    /// [`Self::max_retry_delay`].
+    ///
+    /// Default: 100 milliseconds
+    pub retry_delay: Duration,
+
+    /// Maximum delay between the delivery attempts of an entry.
+    ///
+    /// Default: 30 seconds
+    pub max_retry_delay: Duration,
+
+    /// Number of the failed attempts after which an entry is discarded (and
+    /// the error is logged). If `None`, the delivery is retried forever.
+    ///
+    /// Default: `None`
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for DeliveryOptions {
+    fn default() -> Self {
+        Self {
+            space_name: DEFAULT_SPACE_NAME.into(),
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Statistics of [`Delivery`], see [`Delivery::stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    /// Number of the delivered entries.
+    pub delivered: u64,
+    /// Number of the failed delivery attempts.
+    pub failed_attempts: u64,
+    /// Number of the entries discarded after
+    /// [`DeliveryOptions::max_attempts`] failures.
+    pub discarded: u64,
+}
+
+/// A running delivery fiber, see the [module level documentation](self).
+///
+/// The fiber is cancelled once this value is dropped. The entries which
+/// aren't delivered by then remain in the space and will be delivered once
+/// the delivery is started again.
+#[derive(Debug)]
+pub struct Delivery {
+    fiber_id: FiberId,
+    stats: Rc<RefCell<DeliveryStats>>,
+}
+
+impl Delivery {
+    /// Creates the outbox space if needed and starts a fiber delivering its
+    /// entries to `sink`.
+    ///
+    /// There should be at most one delivery per outbox space, otherwise the
+    /// entries may be delivered out of order.
+    pub fn start(options: DeliveryOptions, sink: impl Sink + 'static) -> Result<Self, Error> {
+        let space = create_space(&options.space_name)?;
+        let stats = Rc::new(RefCell::new(DeliveryStats::default()));
+        let fiber_id = fiber::Builder::new()
+            .name("outbox_delivery")
+            .func({
+                let stats = stats.clone();
+                move || {
+                    let mut worker = Worker {
+                        space,
+                        options,
+                        sink,
+                        stats,
+                        retry_at: HashMap::new(),
+                    };
+                    worker.run()
+                }
+            })
+            .start_non_joinable()?;
+        Ok(Self { fiber_id, stats })
+    }
+
+    /// Returns the statistics of the delivery.
+    #[inline]
+    pub fn stats(&self) -> DeliveryStats {
+        self.stats.borrow().clone()
+    }
+
+    /// Stops the delivery. Equivalent to dropping it.
+    #[inline(always)]
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Delivery {
+    fn drop(&mut self) {
+        fiber::cancel(self.fiber_id);
+    }
+}
+
+struct Worker<S> {
+    space: Space,
+    options: DeliveryOptions,
+    sink: S,
+    stats: Rc<RefCell<DeliveryStats>>,
+    /// Topics blocked by a failed entry and the time of the next attempt.
+    retry_at: HashMap<String, Instant>,
+}
+
+impl<S: Sink> Worker<S> {
+    fn run(&mut self) {
+        while !fiber::is_cancelled() {
+            let more = match self.deliver_batch() {
+                Ok(more) => more,
+                Err(e) => {
+                    crate::say_error!("outbox delivery failed: {}", e);
+                    false
+                }
+            };
+            if fiber::is_cancelled() {
+                break;
+            }
+            if more {
+                fiber::reschedule();
+                continue;
+            }
+            let mut deadline = fiber::clock().saturating_add(self.options.poll_interval);
+            if let Some(&retry_at) = self.retry_at.values().min() {
+                deadline = deadline.min(retry_at);
+            }
+            WAKEUP.with(|wakeup| wakeup.wait_deadline(deadline));
+        }
+    }
+
+    /// Delivers a batch of entries. Returns `true` if there may be more
+    /// entries ready for delivery.
+    fn deliver_batch(&mut self) -> Result<bool, Error> {
+        let now = fiber::clock();
+        self.retry_at.retain(|_, &mut retry_at| retry_at > now);
+
+        let mut entries = Vec::with_capacity(self.options.batch_size);
+        for tuple in self.space.select(IteratorType::All, &())? {
+            let entry: Entry = tuple.decode()?;
+            if !self.retry_at.contains_key(&entry.topic) {
+                entries.push(entry);
+            }
+            if entries.len() >= self.options.batch_size {
+                break;
+            }
+        }
+        let is_full = entries.len() >= self.options.batch_size;
+
+        for entry in entries {
+            if fiber::is_cancelled() {
+                return Ok(false);
+            }
+            // A previous entry of the topic has failed during this batch.
+            if self.retry_at.contains_key(&entry.topic) {
+                continue;
+            }
+            match self.sink.deliver(&entry) {
+                Ok(()) => {
+                    self.space.delete(&(entry.id,))?;
+                    self.stats.borrow_mut().delivered += 1;
+                }
+                Err(e) => self.on_failure(entry, e)?,
+            }
+        }
+        Ok(is_full)
+    }
+
+    fn on_failure(&mut self, entry: Entry, error: Error) -> Result<(), Error> {
+        let attempts = entry.attempts.saturating_add(1);
+        self.stats.borrow_mut().failed_attempts += 1;
+        if self.options.max_attempts.is_some_and(|max| attempts >= max) {
+            crate::say_error!(
+                "discarding outbox entry {} of topic '{}' after {} failed attempts: {}",
+                entry.id,
+                entry.topic,
+                attempts,
+                error
+            );
+            self.space.delete(&(entry.id,))?;
+            self.stats.borrow_mut().discarded += 1;
+            return Ok(());
+        }
+
+        crate::say_warn!(
+            "failed to deliver outbox entry {} of topic '{}' (attempt {}): {}",
+            entry.id,
+            entry.topic,
+            attempts,
+            error
+        );
+        let mut ops = UpdateOps::new();
+        ops.assign(ATTEMPTS_FIELD, attempts)?;
+        self.space.update(&(entry.id,), ops)?;
+
+        let factor = 1_u32.checked_shl(attempts - 1).unwrap_or(u32::MAX);
+        let delay = self
+            .options
+            .retry_delay
+            .saturating_mul(factor)
+            .min(self.options.max_retry_delay);
+        self.retry_at
+            .insert(entry.topic, fiber::clock().saturating_add(delay));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::transaction;
+
+    #[crate::test(tarantool = "crate")]
+    fn delivery() {
+        let space_name = crate::temp_space_name!();
+        let space = create_space(&space_name).unwrap();
+
+        let delivered = Rc::new(RefCell::new(vec![]));
+        let flaky_failures = Rc::new(std::cell::Cell::new(2));
+        let sink = {
+            let delivered = delivered.clone();
+            let flaky_failures = flaky_failures.clone();
+            move |entry: &Entry| {
+                let payload: String = entry.payload()?;
+                if payload == "poison" {
+                    return Err(Error::other("poisoned"));
+                }
+                if entry.topic == "flaky" && flaky_failures.get() > 0 {
+                    flaky_failures.set(flaky_failures.get() - 1);
+                    return Err(Error::other("not now"));
+                }
+                delivered
+                    .borrow_mut()
+                    .push(format!("{}:{}", entry.topic, payload));
+                Ok(())
+            }
+        };
+
+        enqueue_to(&space, "a", "1").unwrap();
+        enqueue_to(&space, "flaky", "1").unwrap();
+        enqueue_to(&space, "poison", "poison").unwrap();
+        enqueue_to(&space, "flaky", "2").unwrap();
+        enqueue_to(&space, "poison", "1").unwrap();
+        enqueue_to(&space, "a", "2").unwrap();
+        // Rolled back entries are never delivered.
+        transaction::transaction(|| -> Result<(), Error> {
+            enqueue_to(&space, "a", "rolled back").unwrap();
+            Err(Error::other("rollback"))
+        })
+        .unwrap_err();
+        assert_eq!(space.len().unwrap(), 6);
+
+        let delivery = Delivery::start(
+            DeliveryOptions {
+                space_name: space_name.clone(),
+                batch_size: 2,
+                poll_interval: Duration::from_secs(10),
+                retry_delay: Duration::from_millis(1),
+                max_retry_delay: Duration::from_millis(5),
+                max_attempts: Some(3),
+            },
+            sink,
+        )
+        .unwrap();
+
+        // The delivery is woken up by new entries.
+        enqueue_to(&space, "b", "1").unwrap();
+
+        for _ in 0..100 {
+            if space.len().unwrap() == 0 {
+                break;
+            }
+            fiber::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(space.len().unwrap(), 0);
+
+        let delivered = delivered.borrow().clone();
+        let topic = |topic: &str| -> Vec<_> {
+            delivered
+                .iter()
+                .filter(|e| e.starts_with(topic))
+                .cloned()
+                .collect()
+        };
+        assert_eq!(delivered.len(), 6);
+        assert_eq!(topic("a:"), ["a:1", "a:2"]);
+        assert_eq!(topic("flaky:"), ["flaky:1", "flaky:2"]);
+        assert_eq!(topic("poison:"), ["poison:1"]);
+        assert_eq!(topic("b:"), ["b:1"]);
+
+        assert_eq!(
+            delivery.stats(),
+            DeliveryStats {
+                delivered: 6,
+                failed_attempts: 5,
+                discarded: 1,
+            }
+        );
+
+        delivery.stop();
+        space.drop().unwrap();
+    }
+}
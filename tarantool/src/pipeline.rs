@@ -0,0 +1,629 @@
+//! Producer/consumer pipelines for data processing.
+//!
+//! A pipeline consists of a producer, any number of transform stages and a
+//! consumer. Each stage runs in a separate fiber and passes the items to the
+//! next one via a bounded queue, so a fast producer is paused once the queue
+//! is full instead of piling up the items in memory (backpressure).
+//!
+//! If any of the stages fails, the rest of them are stopped: the
+//! [`Sender::send`] calls in the producer return an error and the other
+//! stages are not given any more items. The first error is returned from
+//! [`Pipeline::run`] or [`Running::join`].
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::index::IteratorType;
+//! use tarantool::pipeline::Builder;
+//! use tarantool::space::Space;
+//!
+//! let users = Space::find("users").unwrap();
+//! let stats = Space::find("stats").unwrap();
+//! Builder::from_iter(users.select(IteratorType::All, &()).unwrap())
+//!     .name("recalc_stats")
+//!     .capacity(256)
+//!     .map(|user| {
+//!         let id: u64 = user.field(0)?.unwrap();
+//!         let score: f64 = user.field(3)?.unwrap_or_default();
+//!         Ok((id, score * 2.0))
+//!     })
+//!     .batch(100)
+//!     .consume(move |batch| {
+//!         tarantool::transaction::transaction(|| -> tarantool::Result<()> {
+//!             for row in &batch {
+//!                 stats.replace(row)?;
+//!             }
+//!             Ok(())
+//!         })?;
+//!         Ok(())
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use crate::error::{BoxError, Error, TarantoolErrorCode};
+use crate::fiber::{self, Cond, JoinHandle};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Default capacity of the queues between the stages.
+pub const DEFAULT_CAPACITY: usize = 128;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builder
+////////////////////////////////////////////////////////////////////////////////
+
+/// Builder of a pipeline, `T` is the type of the items produced by the last
+/// stage so far.
+///
+/// Starts with a producer ([`Builder::produce`] or [`Builder::from_iter`]),
+/// continues with the transform stages ([`Builder::map`],
+/// [`Builder::filter_map`], [`Builder::batch`]) and ends with a consumer
+/// ([`Builder::consume`]).
+pub struct Builder<T> {
+    name: String,
+    capacity: usize,
+    start: StartFn<Queue<T>>,
+}
+
+/// Starts the fibers of the stages built so far and returns the output queue
+/// of the last one (if there is one).
+type StartFn<T> = Box<dyn FnOnce(&mut Context) -> crate::Result<T>>;
+
+impl<T: 'static> Builder<T> {
+    /// Creates a pipeline the items of which are sent by `f`.
+    ///
+    /// `f` should stop and return the error if [`Sender::send`] fails, which
+    /// means that the pipeline is stopped.
+    pub fn produce<F>(f: F) -> Self
+    where
+        F: FnOnce(&Sender<T>) -> crate::Result<()> + 'static,
+    {
+        Self {
+            name: "pipeline".into(),
+            capacity: DEFAULT_CAPACITY,
+            start: Box::new(move |ctx| {
+                let output = ctx.queue();
+                let sender = Sender(output.clone());
+                ctx.spawn("produce", Some(&output), move || f(&sender))?;
+                Ok(output)
+            }),
+        }
+    }
+
+    /// Creates a pipeline of the items of `iter`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T> + 'static,
+    {
+        Self::produce(move |tx| {
+            for item in iter {
+                tx.send(item)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Sets the name of the pipeline, which is used for the names of the
+    /// fibers of the stages. The default is `"pipeline"`.
+    #[inline(always)]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the maximal number of the items in each of the queues between
+    /// the stages. The default is [`DEFAULT_CAPACITY`].
+    #[inline(always)]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Adds a stage which transforms each item with `f`.
+    pub fn map<U, F>(self, mut f: F) -> Builder<U>
+    where
+        U: 'static,
+        F: FnMut(T) -> crate::Result<U> + 'static,
+    {
+        self.filter_map(move |item| f(item).map(Some))
+    }
+
+    /// Adds a stage which transforms each item with `f` and skips the ones
+    /// for which it returns `None`.
+    pub fn filter_map<U, F>(self, mut f: F) -> Builder<U>
+    where
+        U: 'static,
+        F: FnMut(T) -> crate::Result<Option<U>> + 'static,
+    {
+        self.stage("map", move |input, output| {
+            while let Some(item) = input.pop()? {
+                if let Some(item) = f(item)? {
+                    output.push(item)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Adds a stage which groups the items into batches of at most
+    /// `max_size` items.
+    ///
+    /// A batch is passed on as soon as there are no more items ready, i.e.
+    /// the stage doesn't wait for a batch to fill up if the previous stage is
+    /// slower.
+    pub fn batch(self, max_size: usize) -> Builder<Vec<T>> {
+        let max_size = max_size.max(1);
+        self.stage("batch", move |input, output| {
+            while let Some(first) = input.pop()? {
+                let mut batch = Vec::with_capacity(max_size);
+                batch.push(first);
+                while batch.len() < max_size {
+                    match input.try_pop() {
+                        Some(item) => batch.push(item),
+                        None => break,
+                    }
+                }
+                output.push(batch)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Finishes the pipeline with a consumer which is called with each item.
+    pub fn consume<F>(self, mut f: F) -> Pipeline
+    where
+        F: FnMut(T) -> crate::Result<()> + 'static,
+    {
+        let Self {
+            name,
+            capacity,
+            start,
+        } = self;
+        Pipeline {
+            name,
+            capacity,
+            start: Box::new(move |ctx| {
+                let input = start(ctx)?;
+                ctx.spawn::<(), _>("consume", None, move || {
+                    while let Some(item) = input.pop()? {
+                        f(item)?;
+                    }
+                    Ok(())
+                })
+            }),
+        }
+    }
+
+    fn stage<U, F>(self, kind: &'static str, f: F) -> Builder<U>
+    where
+        U: 'static,
+        F: FnOnce(&Queue<T>, &Queue<U>) -> crate::Result<()> + 'static,
+    {
+        let Self {
+            name,
+            capacity,
+            start,
+        } = self;
+        Builder {
+            name,
+            capacity,
+            start: Box::new(move |ctx| {
+                let input = start(ctx)?;
+                let output = ctx.queue();
+                let stage_output = output.clone();
+                ctx.spawn(kind, Some(&output), move || f(&input, &stage_output))?;
+                Ok(output)
+            }),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Builder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Builder")
+            .field("name", &self.name)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Pipeline
+////////////////////////////////////////////////////////////////////////////////
+
+/// A complete pipeline ready to be started, see [`Builder::consume`].
+pub struct Pipeline {
+    name: String,
+    capacity: usize,
+    start: StartFn<()>,
+}
+
+impl Pipeline {
+    /// Runs the pipeline and blocks the current fiber until all of the stages
+    /// are finished. Returns the first error of any of the stages.
+    #[inline]
+    pub fn run(self) -> crate::Result<()> {
+        self.start()?.join()
+    }
+
+    /// Starts the fibers of the pipeline and returns a handle which can be
+    /// used to wait for the pipeline to finish or to cancel it.
+    pub fn start(self) -> crate::Result<Running> {
+        let mut ctx = Context {
+            name: self.name,
+            capacity: self.capacity,
+            shared: Rc::new(Shared::default()),
+            fibers: vec![],
+        };
+        let res = (self.start)(&mut ctx);
+        let running = Running {
+            shared: ctx.shared,
+            fibers: ctx.fibers,
+        };
+        if let Err(e) = res {
+            // Stop the stages which have already started.
+            running.shared.fail(e);
+            // The error is returned by join.
+            return Err(running.join().unwrap_err());
+        }
+        Ok(running)
+    }
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("name", &self.name)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Running
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle of a started pipeline, see [`Pipeline::start`].
+///
+/// The pipeline is cancelled if the handle is dropped without calling
+/// [`Running::join`].
+pub struct Running {
+    shared: Rc<Shared>,
+    fibers: Vec<JoinHandle<'static, ()>>,
+}
+
+impl Running {
+    /// Stops all of the stages of the pipeline. [`Running::join`] returns an
+    /// error after this, unless the pipeline has already finished.
+    pub fn cancel(&self) {
+        self.shared.fail(cancelled());
+        for fiber in &self.fibers {
+            if let Some(id) = fiber.id_checked() {
+                fiber::cancel(id);
+            }
+        }
+    }
+
+    /// Blocks the current fiber until all of the stages are finished and
+    /// returns the first error of any of them.
+    pub fn join(mut self) -> crate::Result<()> {
+        for fiber in self.fibers.drain(..) {
+            fiber.join();
+        }
+        match self.shared.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if self.fibers.is_empty() {
+            return;
+        }
+        self.cancel();
+        for fiber in self.fibers.drain(..) {
+            fiber.join();
+        }
+    }
+}
+
+impl std::fmt::Debug for Running {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Running")
+            .field("fibers", &self.fibers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Sender
+////////////////////////////////////////////////////////////////////////////////
+
+/// The producer's side of the pipeline, see [`Builder::produce`].
+pub struct Sender<T>(Queue<T>);
+
+impl<T> Sender<T> {
+    /// Passes the `item` to the next stage, yields while the queue is full.
+    ///
+    /// Returns an error if the pipeline is stopped because some stage failed
+    /// or it was cancelled.
+    #[inline(always)]
+    pub fn send(&self, item: T) -> crate::Result<()> {
+        self.0.push(item)
+    }
+
+    /// Returns `true` if the pipeline is stopped.
+    #[inline(always)]
+    pub fn is_stopped(&self) -> bool {
+        self.0.shared.is_stopped()
+    }
+}
+
+impl<T> std::fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// impl
+////////////////////////////////////////////////////////////////////////////////
+
+#[inline]
+fn cancelled() -> Error {
+    BoxError::new(TarantoolErrorCode::ProcLua, "pipeline is cancelled").into()
+}
+
+/// State shared by all of the stages of a pipeline.
+#[derive(Default)]
+struct Shared {
+    /// Signalled when any of the queues changes or the pipeline is stopped.
+    cond: Cond,
+    stopped: Cell<bool>,
+    /// The first error of any of the stages.
+    error: RefCell<Option<Error>>,
+}
+
+impl Shared {
+    fn fail(&self, e: Error) {
+        if !self.stopped.replace(true) {
+            *self.error.borrow_mut() = Some(e);
+        }
+        self.cond.broadcast();
+    }
+
+    #[inline(always)]
+    fn is_stopped(&self) -> bool {
+        self.stopped.get()
+    }
+
+    /// Waits for a change in any of the queues. Returns an error if the
+    /// pipeline is stopped.
+    fn wait(&self) -> crate::Result<()> {
+        if self.is_stopped() {
+            return Err(cancelled());
+        }
+        if !self.cond.wait() && fiber::is_cancelled() {
+            return Err(cancelled());
+        }
+        if self.is_stopped() {
+            return Err(cancelled());
+        }
+        Ok(())
+    }
+}
+
+struct Context {
+    name: String,
+    capacity: usize,
+    shared: Rc<Shared>,
+    fibers: Vec<JoinHandle<'static, ()>>,
+}
+
+impl Context {
+    fn queue<T>(&self) -> Queue<T> {
+        Queue {
+            state: Rc::new(RefCell::new(QueueState {
+                items: VecDeque::with_capacity(self.capacity),
+                is_closed: false,
+            })),
+            capacity: self.capacity,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Starts a fiber for a stage. Once `f` returns, the `output` queue is
+    /// closed and the pipeline is stopped in case of an error.
+    fn spawn<T, F>(&mut self, kind: &str, output: Option<&Queue<T>>, f: F) -> crate::Result<()>
+    where
+        T: 'static,
+        F: FnOnce() -> crate::Result<()> + 'static,
+    {
+        let shared = self.shared.clone();
+        let output = output.cloned();
+        let fiber = fiber::Builder::new()
+            .name(format!("{}/{}#{}", self.name, kind, self.fibers.len()))
+            .func(move || {
+                if let Err(e) = f() {
+                    shared.fail(e);
+                }
+                if let Some(output) = output {
+                    output.close();
+                }
+            })
+            .start()?;
+        self.fibers.push(fiber);
+        Ok(())
+    }
+}
+
+/// A bounded queue between two stages.
+struct Queue<T> {
+    state: Rc<RefCell<QueueState<T>>>,
+    capacity: usize,
+    shared: Rc<Shared>,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    /// The stage writing to the queue is finished.
+    is_closed: bool,
+}
+
+impl<T> Clone for Queue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            capacity: self.capacity,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Queue<T> {
+    fn push(&self, item: T) -> crate::Result<()> {
+        loop {
+            if self.shared.is_stopped() {
+                return Err(cancelled());
+            }
+            let mut state = self.state.borrow_mut();
+            if state.items.len() < self.capacity {
+                state.items.push_back(item);
+                drop(state);
+                self.shared.cond.broadcast();
+                return Ok(());
+            }
+            drop(state);
+            self.shared.wait()?;
+        }
+    }
+
+    /// Returns `None` once the queue is closed and empty.
+    fn pop(&self) -> crate::Result<Option<T>> {
+        loop {
+            if self.shared.is_stopped() {
+                return Err(cancelled());
+            }
+            if let Some(item) = self.try_pop() {
+                return Ok(Some(item));
+            }
+            if self.state.borrow().is_closed {
+                return Ok(None);
+            }
+            self.shared.wait()?;
+        }
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        let item = self.state.borrow_mut().items.pop_front()?;
+        self.shared.cond.broadcast();
+        Some(item)
+    }
+
+    fn close(&self) {
+        self.state.borrow_mut().is_closed = true;
+        self.shared.cond.broadcast();
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn run() {
+        let batches = Rc::new(RefCell::new(vec![]));
+        let batches_clone = batches.clone();
+        Builder::from_iter(0..100)
+            .name("test_run")
+            .capacity(8)
+            .filter_map(|i| Ok((i % 2 == 0).then_some(i)))
+            .map(|i| Ok(i * 10))
+            .batch(10)
+            .consume(move |batch| {
+                batches_clone.borrow_mut().push(batch);
+                Ok(())
+            })
+            .run()
+            .unwrap();
+
+        let batches = batches.take();
+        assert!(batches.iter().all(|b| !b.is_empty() && b.len() <= 10));
+        let items: Vec<i32> = batches.into_iter().flatten().collect();
+        assert_eq!(
+            items,
+            (0..100).step_by(2).map(|i| i * 10).collect::<Vec<_>>()
+        );
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn error_propagation() {
+        let produced = Rc::new(Cell::new(0));
+        let produced_clone = produced.clone();
+        let consumed = Rc::new(Cell::new(0));
+        let consumed_clone = consumed.clone();
+        let e = Builder::produce(move |tx| {
+            for i in 0.. {
+                tx.send(i)?;
+                produced_clone.set(produced_clone.get() + 1);
+            }
+            Ok(())
+        })
+        .capacity(4)
+        .map(|i| {
+            if i == 50 {
+                return Err(Error::other("bad item"));
+            }
+            Ok(i)
+        })
+        .consume(move |_| {
+            consumed_clone.set(consumed_clone.get() + 1);
+            Ok(())
+        })
+        .run()
+        .unwrap_err();
+        assert_eq!(e.to_string(), "bad item");
+        assert!(consumed.get() <= 50);
+        // The producer is stopped by the backpressure and then by the error.
+        assert!(produced.get() <= 50 + 4 + 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancel() {
+        let running = Builder::produce(|tx| loop {
+            tx.send(())?;
+        })
+        .consume(|_| {
+            fiber::sleep(Duration::from_millis(1));
+            Ok(())
+        })
+        .start()
+        .unwrap();
+        fiber::sleep(Duration::from_millis(10));
+        running.cancel();
+        let e = running.join().unwrap_err();
+        assert_eq!(e.to_string(), "box error: ProcLua: pipeline is cancelled");
+
+        // Dropping the handle cancels the pipeline too.
+        let finished = Rc::new(Cell::new(false));
+        let finished_clone = finished.clone();
+        let running = Builder::produce(move |tx| {
+            let res = (|| loop {
+                tx.send(())?;
+            })();
+            finished_clone.set(true);
+            res
+        })
+        .consume(|_| Ok(()))
+        .start()
+        .unwrap();
+        fiber::sleep(Duration::from_millis(1));
+        drop(running);
+        assert!(finished.get());
+    }
+}
@@ -1,7 +1,13 @@
-use crate::error::IntoBoxError;
+use crate::error::{IntoBoxError, TarantoolError};
 use crate::ffi::tarantool as ffi;
-use crate::tuple::{FunctionCtx, RawByteBuf, RawBytes, Tuple, TupleBuffer};
+use crate::msgpack::ValueIter;
+use crate::tuple::{
+    Decode, DecodeOwned, FunctionArgs, FunctionCtx, RawByteBuf, RawBytes, ToTupleBuffer, Tuple,
+    TupleBuffer,
+};
 use serde::Serialize;
+use std::io::Cursor;
+use std::marker::PhantomData;
 use std::os::raw::c_int;
 use std::path::Path;
 
@@ -149,6 +155,295 @@ pub fn module_path(sym: *const ()) -> Option<&'static Path> {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// call_local
+////////////////////////////////////////////////////////////////////////////////
+
+/// Calls the stored procedure `name` registered in the tarantool's function
+/// registry (`box.func`) in the current process, i.e. without a network round
+/// trip.
+///
+/// The procedure can be defined in any module, e.g. a different shared
+/// library, but it must be registered beforehand via `box.schema.func.create`.
+/// The current user must have the privilege to execute it.
+///
+/// `args` are encoded into msgpack and passed to the procedure as its
+/// arguments as is, i.e. without converting them into Lua values, so `nil`
+/// and binary (MP_BIN) values are preserved. The values returned by the
+/// procedure are collected into a msgpack array, which is decoded as `T`.
+/// For example if the procedure returns a single value of type `V`, `T` should
+/// be `(V,)`. The `nil` values returned by the procedure are preserved as well,
+/// but because the results are passed through Lua, binary values are returned
+/// as strings (MP_STR) unless they are decoded as `varbinary` objects.
+///
+/// Returns an error with [`TarantoolErrorCode::NoSuchFunction`] if there's no
+/// function with the given `name`. If the procedure fails, the error it
+/// returned is propagated to the caller.
+///
+/// ```no_run
+/// use tarantool::proc::call_local;
+///
+/// #[tarantool::proc]
+/// fn add(x: i32, y: i32) -> i32 {
+///     x + y
+/// }
+///
+/// let (res,): (i32,) = call_local("my_library.add", &(1, 2)).unwrap();
+/// assert_eq!(res, 3);
+/// ```
+///
+/// [`TarantoolErrorCode::NoSuchFunction`]: crate::error::TarantoolErrorCode::NoSuchFunction
+pub fn call_local<T>(name: &str, args: &impl ToTupleBuffer) -> crate::Result<T>
+where
+    T: DecodeOwned,
+{
+    let args = args.to_tuple_buffer()?;
+    let res: Option<tlua::AnyLuaString> = crate::lua_state()
+        .eval_with(
+            "local name, args = ...
+            if box.func[name] == nil then
+                box.error.set(box.error.new(box.error.NO_SUCH_FUNCTION, name))
+                return nil
+            end
+            -- Each of the arguments is passed as a msgpack object, so that
+            -- it's encoded back into msgpack without any conversion.
+            local msgpack = require('msgpack')
+            local it = msgpack.object_from_raw(args):iterator()
+            local n = it:decode_array_header()
+            local args = {}
+            for i = 1, n do
+                args[i] = it:take()
+            end
+            local res = table.pack(pcall(box.schema.func.call, name, unpack(args, 1, n)))
+            if not res[1] then
+                local err = res[2]
+                if not box.error.is(err) then
+                    err = box.error.new(box.error.PROC_LUA, tostring(err))
+                end
+                box.error.set(err)
+                return nil
+            end
+            local ret = setmetatable({}, msgpack.array_mt)
+            for i = 2, res.n do
+                local v = res[i]
+                if v == nil then
+                    v = msgpack.NULL
+                end
+                ret[i - 1] = v
+            end
+            return msgpack.encode(ret)",
+            (name, tlua::AnyLuaString(args.into())),
+        )
+        .map_err(tlua::LuaError::from)?;
+    let Some(tlua::AnyLuaString(res)) = res else {
+        let error = TarantoolError::last_with_details().unwrap_or_else(TarantoolError::last);
+        return Err(error.into());
+    };
+    T::decode(&res)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// RawArgsTail
+////////////////////////////////////////////////////////////////////////////////
+
+/// The arguments of a stored procedure which follow the decoded ones,
+/// captured without decoding.
+///
+/// If the last parameter of a procedure has this type, the preceding
+/// parameters are decoded as usual and the rest of the arguments are
+/// borrowed from the request buffer. This is useful for the procedures which
+/// only inspect a prefix of the arguments, e.g. for routing the request:
+///
+/// ```no_run
+/// use tarantool::net_box::{Conn, Options};
+/// use tarantool::proc::RawArgsTail;
+/// use tarantool::tuple::Tuple;
+///
+/// fn connection(bucket_id: u64) -> &'static Conn {
+///     todo!()
+/// }
+///
+/// #[tarantool::proc]
+/// fn route(bucket_id: u64, func: String, args: RawArgsTail) -> tarantool::Result<Option<Tuple>> {
+///     connection(bucket_id).call(&func, &args, &Options::default())
+/// }
+/// ```
+///
+/// The parameter is recognized by its type, so it works with type aliases
+/// too. `RawArgsTail` doesn't implement [`Decode`], so using it anywhere but
+/// in the last parameter is a compile error. If fewer arguments are passed
+/// than there are parameters preceding it, the call fails with an error.
+///
+/// [`ToTupleBuffer`] encodes the captured arguments as a msgpack array, so
+/// they can be forwarded as is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RawArgsTail<'a> {
+    /// Msgpack values following each other without the array header.
+    data: &'a [u8],
+    len: u32,
+}
+
+impl<'a> RawArgsTail<'a> {
+    /// Captures the elements of the msgpack array `args` following the first
+    /// `skip` ones. Returns an error if `args` isn't a msgpack array or if it
+    /// has less than `skip` elements.
+    pub fn new(args: &'a RawBytes, skip: usize) -> crate::Result<Self> {
+        let mut cursor = Cursor::new(&args.0);
+        let len = rmp::decode::read_array_len(&mut cursor)?;
+        if (len as usize) < skip {
+            return Err(crate::error::Error::other(format!(
+                "expected at least {} arguments, got {}",
+                skip, len
+            )));
+        }
+        for _ in 0..skip {
+            crate::msgpack::skip_value(&mut cursor)?;
+        }
+        Ok(Self {
+            data: &args.0[cursor.position() as usize..],
+            len: len - skip as u32,
+        })
+    }
+
+    /// Returns the captured arguments as a sequence of msgpack values
+    /// without the array header.
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the number of the captured arguments.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len as _
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the captured arguments.
+    #[inline(always)]
+    pub fn iter(&self) -> ValueIter<'a> {
+        ValueIter::new(self.data)
+    }
+
+    /// Returns the captured argument at `index` or `None` if there are not
+    /// enough arguments.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&'a RawBytes> {
+        self.iter().nth(index).map(RawBytes::new)
+    }
+
+    /// Decodes the captured arguments as a msgpack array.
+    #[inline]
+    pub fn decode<T>(&self) -> crate::Result<T>
+    where
+        T: DecodeOwned,
+    {
+        let data = self.to_tuple_buffer()?;
+        T::decode(data.as_ref())
+    }
+}
+
+impl ToTupleBuffer for RawArgsTail<'_> {
+    #[inline]
+    fn write_tuple_data(&self, w: &mut impl std::io::Write) -> crate::Result<()> {
+        crate::msgpack::write_array_len(w, self.len)?;
+        w.write_all(self.data)?;
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for RawArgsTail<'a> {
+    type Item = &'a [u8];
+    type IntoIter = ValueIter<'a>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Used by the code generated by [`tarantool::proc`] to decode the arguments
+/// of a stored procedure, `L` is the type of its last parameter.
+///
+/// If `L` is [`RawArgsTail`], [`DecodeArgsWithRawTail::decode_args`] takes
+/// priority over [`DecodeArgs::decode_args`], because it's implemented for
+/// `&ArgsDecoder` and the method is called on `&&ArgsDecoder`.
+///
+/// [`tarantool::proc`]: macro@crate::proc
+#[doc(hidden)]
+pub struct ArgsDecoder<L>(pub PhantomData<L>);
+
+#[doc(hidden)]
+pub trait DecodeArgs {
+    #[inline(always)]
+    fn decode_args<'a, T>(&self, args: &'a FunctionArgs) -> crate::Result<T>
+    where
+        T: Decode<'a>,
+    {
+        args.decode()
+    }
+}
+
+impl<L> DecodeArgs for ArgsDecoder<L> {}
+
+#[doc(hidden)]
+pub trait DecodeArgsWithRawTail<'a> {
+    #[inline(always)]
+    fn decode_args<T>(&self, args: &'a FunctionArgs) -> crate::Result<T>
+    where
+        T: DecodeWithRawTail<'a>,
+    {
+        T::decode_with_raw_tail(args.decode()?)
+    }
+}
+
+impl<'a> DecodeArgsWithRawTail<'a> for &ArgsDecoder<RawArgsTail<'a>> {}
+
+/// A tuple of the arguments the last of which is [`RawArgsTail`]. The
+/// preceding ones are decoded one by one, so that the arguments captured by
+/// the tail don't have to match any type.
+#[doc(hidden)]
+pub trait DecodeWithRawTail<'a>: Sized {
+    fn decode_with_raw_tail(args: &'a RawBytes) -> crate::Result<Self>;
+}
+
+impl<'a> DecodeWithRawTail<'a> for (RawArgsTail<'a>,) {
+    #[inline(always)]
+    fn decode_with_raw_tail(args: &'a RawBytes) -> crate::Result<Self> {
+        Ok((RawArgsTail::new(args, 0)?,))
+    }
+}
+
+macro_rules! impl_decode_with_raw_tail {
+    () => {};
+    ($h:ident $($t:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<'a, $h, $($t),*> DecodeWithRawTail<'a> for ($h, $($t,)* RawArgsTail<'a>)
+        where
+            $h: Decode<'a>,
+            $($t: Decode<'a>,)*
+        {
+            fn decode_with_raw_tail(args: &'a RawBytes) -> crate::Result<Self> {
+                const LEN: usize = crate::expr_count!($h $(, $t)*);
+                let tail = RawArgsTail::new(args, LEN)?;
+                let mut iter = args.array_iter()?;
+                let mut next = || iter.next().expect("checked by RawArgsTail::new");
+                let $h = $h::decode(next())?;
+                $( let $t = $t::decode(next())?; )*
+                Ok(($h, $($t,)* tail))
+            }
+        }
+
+        impl_decode_with_raw_tail! { $($t)* }
+    }
+}
+
+impl_decode_with_raw_tail! { A B C D E F G H I J K L M N O }
+
 ////////////////////////////////////////////////////////////////////////////////
 // ReturnMsgpack
 ////////////////////////////////////////////////////////////////////////////////
@@ -207,6 +502,84 @@ impl<T: Serialize> Return for ReturnMsgpack<T> {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// ReturnRows
+////////////////////////////////////////////////////////////////////////////////
+
+/// A wrapper type for returning each element of a vector as a separate
+/// value (row) of the response, instead of a single msgpack array. Consider
+/// using the `rows` attribute parameter instead (see [`tarantool::proc`]
+/// docs).
+///
+/// This is what lua stored procedures returning multiple values (e.g.
+/// `return unpack(box.space.users:select())`) do, so the clients of such
+/// procedures receive the same response when they are rewritten in rust.
+///
+/// ```no_run
+/// use tarantool::{index::IteratorType, proc::ReturnRows, space::Space, tuple::Tuple};
+///
+/// #[tarantool::proc]
+/// fn active_users() -> tarantool::Result<ReturnRows<Vec<Tuple>>> {
+///     let space = Space::find("users").unwrap();
+///     let users = space.select(IteratorType::All, &())?.collect();
+///     Ok(ReturnRows(users))
+/// }
+/// ```
+///
+/// [`tarantool::proc`]: macro@crate::proc
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReturnRows<T>(pub T);
+
+impl<T> ReturnRows<Vec<T>>
+where
+    T: crate::msgpack::Encode,
+{
+    fn return_rows(&self, ctx: &FunctionCtx) -> crate::Result<c_int> {
+        let mut buf = Vec::with_capacity(128);
+        for row in &self.0 {
+            buf.clear();
+            row.encode(&mut buf, &crate::msgpack::Context::DEFAULT)?;
+            ctx.return_bytes(&buf)?;
+        }
+        Ok(0)
+    }
+}
+
+impl<T> Return for ReturnRows<Vec<T>>
+where
+    T: crate::msgpack::Encode,
+{
+    #[inline]
+    #[track_caller]
+    fn ret(self, ctx: FunctionCtx) -> c_int {
+        unwrap_or_report_err!(self.return_rows(&ctx))
+    }
+}
+
+impl<T, E> Return for ReturnRows<Result<Vec<T>, E>>
+where
+    T: crate::msgpack::Encode,
+    E: IntoBoxError,
+{
+    #[inline]
+    #[track_caller]
+    fn ret(self, ctx: FunctionCtx) -> c_int {
+        unwrap_or_report_err!(self.0.map(|rows| ReturnRows(rows).ret(ctx)))
+    }
+}
+
+impl<T, E> Return for Result<ReturnRows<Vec<T>>, E>
+where
+    T: crate::msgpack::Encode,
+    E: IntoBoxError,
+{
+    #[inline(always)]
+    #[track_caller]
+    fn ret(self, ctx: FunctionCtx) -> c_int {
+        unwrap_or_report_err!(self.map(|t| t.ret(ctx)))
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Return
 ////////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,548 @@
+//! Spawning and supervising child processes.
+//!
+//! This is a wrapper around the tarantool's built-in `popen` module. All of the
+//! blocking operations (reading the output, writing the input, waiting for the
+//! process to finish) only yield the current fiber, so the other fibers keep
+//! running meanwhile.
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use tarantool::process::{Command, Stdio};
+//!
+//! let child = Command::new("/bin/echo")
+//!     .arg("hello")
+//!     .stdout(Stdio::Piped)
+//!     .spawn()
+//!     .unwrap();
+//! let output = child.read_stdout(Duration::from_secs(1)).unwrap();
+//! assert_eq!(output, b"hello\n");
+//! let status = child.wait(Duration::from_secs(1)).unwrap();
+//! assert!(status.success());
+//! ```
+//!
+//! See also:
+//! - [Lua reference: Module popen](https://www.tarantool.io/en/doc/latest/reference/reference_lua/popen/)
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::fiber;
+use crate::tlua::{self, AnyLuaString};
+
+/// How often [`Child::wait`] checks if the process has finished.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+////////////////////////////////////////////////////////////////////////////////
+// Command
+////////////////////////////////////////////////////////////////////////////////
+
+crate::define_str_enum! {
+    /// Describes what to do with a standard stream of a child process.
+    pub enum Stdio {
+        /// The stream is inherited from the parent (the tarantool process).
+        Inherit = "inherit",
+        /// The stream is redirected to `/dev/null`.
+        Null = "devnull",
+        /// The stream is closed in the child process.
+        Closed = "close",
+        /// A pipe is created, so that the parent can write to or read from
+        /// the stream via [`Child`] methods.
+        Piped = "pipe",
+    }
+}
+
+/// A builder for spawning a child process.
+///
+/// By default the child inherits the environment and all of the standard
+/// streams of the parent.
+#[derive(Clone, Debug)]
+pub struct Command {
+    program: String,
+    args: Vec<String>,
+    envs: HashMap<String, String>,
+    env_clear: bool,
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+    setsid: bool,
+}
+
+impl Command {
+    /// Constructs a new `Command` for running `program`.
+    ///
+    /// Note that the program isn't looked up in the `PATH`, so a path to the
+    /// executable must be specified.
+    #[inline]
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            envs: HashMap::new(),
+            env_clear: false,
+            stdin: Stdio::Inherit,
+            stdout: Stdio::Inherit,
+            stderr: Stdio::Inherit,
+            setsid: false,
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    #[inline]
+    pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    #[inline]
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets an environment variable for the child process.
+    #[inline]
+    pub fn env(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.envs.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets multiple environment variables for the child process.
+    #[inline]
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.envs
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Clears the environment of the child process, so that only the
+    /// variables set via [`Command::env`] are passed to it.
+    #[inline]
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.env_clear = true;
+        self.envs.clear();
+        self
+    }
+
+    /// Configures the child's standard input.
+    #[inline]
+    pub fn stdin(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdin = cfg;
+        self
+    }
+
+    /// Configures the child's standard output.
+    #[inline]
+    pub fn stdout(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdout = cfg;
+        self
+    }
+
+    /// Configures the child's standard error.
+    #[inline]
+    pub fn stderr(&mut self, cfg: Stdio) -> &mut Self {
+        self.stderr = cfg;
+        self
+    }
+
+    /// If `true`, the child is started in a new session (see `setsid(2)`).
+    #[inline]
+    pub fn setsid(&mut self, setsid: bool) -> &mut Self {
+        self.setsid = setsid;
+        self
+    }
+
+    /// Spawns the child process.
+    pub fn spawn(&self) -> Result<Child, Error> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let key = format!(
+            "tarantool.process.{}",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut argv = Vec::with_capacity(self.args.len() + 1);
+        argv.push(self.program.as_str());
+        argv.extend(self.args.iter().map(String::as_str));
+
+        let pid: Option<u32> = crate::lua_state()
+            .eval_with(
+                "local key, argv, env_vars, env_clear, stdin, stdout, stderr, setsid = ...
+                local env
+                if env_clear or next(env_vars) ~= nil then
+                    env = env_clear and {} or os.environ()
+                    for k, v in pairs(env_vars) do
+                        env[k] = v
+                    end
+                end
+                local h, err = require('popen').new(argv, {
+                    env = env,
+                    stdin = stdin,
+                    stdout = stdout,
+                    stderr = stderr,
+                    setsid = setsid,
+                })
+                if h == nil then
+                    error(err)
+                end
+                debug.getregistry()[key] = h
+                return h.pid",
+                (
+                    &key,
+                    argv,
+                    &self.envs,
+                    self.env_clear,
+                    self.stdin.as_str(),
+                    self.stdout.as_str(),
+                    self.stderr.as_str(),
+                    self.setsid,
+                ),
+            )
+            .map_err(tlua::LuaError::from)?;
+
+        Ok(Child { key, pid })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Child
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle of a running or finished child process, see [`Command::spawn`].
+///
+/// The process is killed with `SIGKILL` once the handle is dropped, so
+/// [`Child::wait`] should be called beforehand if it's expected to finish
+/// by itself.
+///
+/// All of the methods take `&self`, so for example the standard output and
+/// the standard error can be read concurrently from different fibers.
+#[derive(Debug)]
+pub struct Child {
+    /// Key in the lua registry under which the popen handle is stored.
+    key: String,
+    pid: Option<u32>,
+}
+
+impl Child {
+    /// Returns the process id of the child.
+    #[inline(always)]
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Reads a chunk of data from the child's standard output. Yields until
+    /// some data is available. Returns an empty vector at the end of file.
+    ///
+    /// Returns an error if no data is available within `timeout`. The stream
+    /// must be configured as [`Stdio::Piped`].
+    #[inline(always)]
+    pub fn read_stdout(&self, timeout: Duration) -> Result<Vec<u8>, Error> {
+        self.read(false, timeout)
+    }
+
+    /// Reads a chunk of data from the child's standard error. Yields until
+    /// some data is available. Returns an empty vector at the end of file.
+    ///
+    /// Returns an error if no data is available within `timeout`. The stream
+    /// must be configured as [`Stdio::Piped`].
+    #[inline(always)]
+    pub fn read_stderr(&self, timeout: Duration) -> Result<Vec<u8>, Error> {
+        self.read(true, timeout)
+    }
+
+    fn read(&self, stderr: bool, timeout: Duration) -> Result<Vec<u8>, Error> {
+        let data: Option<AnyLuaString> = crate::lua_state()
+            .eval_with(
+                "local key, stderr, timeout = ...
+                local h = debug.getregistry()[key]
+                local data, err = h:read({
+                    stdout = not stderr,
+                    stderr = stderr,
+                    timeout = timeout,
+                })
+                if data == nil then
+                    if err.type == 'TimedOut' then
+                        return nil
+                    end
+                    error(err)
+                end
+                return data",
+                (&self.key, stderr, timeout.as_secs_f64()),
+            )
+            .map_err(tlua::LuaError::from)?;
+        let data = data.ok_or_else(|| io::Error::from(io::ErrorKind::TimedOut))?;
+        Ok(data.0)
+    }
+
+    /// Writes `data` to the child's standard input. Yields until all of the
+    /// data is written.
+    ///
+    /// Returns an error if the data couldn't be written within `timeout`. The
+    /// stream must be configured as [`Stdio::Piped`].
+    pub fn write_stdin(&self, data: &[u8], timeout: Duration) -> Result<(), Error> {
+        let ok: bool = crate::lua_state()
+            .eval_with(
+                "local key, data, timeout = ...
+                local h = debug.getregistry()[key]
+                local ok, err = h:write(data, {timeout = timeout})
+                if not ok then
+                    if err.type == 'TimedOut' then
+                        return false
+                    end
+                    error(err)
+                end
+                return true",
+                (&self.key, AnyLuaString(data.into()), timeout.as_secs_f64()),
+            )
+            .map_err(tlua::LuaError::from)?;
+        if !ok {
+            return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+        }
+        Ok(())
+    }
+
+    /// Closes the child's standard input, so that it receives the end of
+    /// file.
+    pub fn close_stdin(&self) -> Result<(), Error> {
+        crate::lua_state()
+            .exec_with(
+                "local key = ...
+                local h = debug.getregistry()[key]
+                local ok, err = h:shutdown({stdin = true})
+                if not ok then
+                    error(err)
+                end",
+                &self.key,
+            )
+            .map_err(tlua::LuaError::from)?;
+        Ok(())
+    }
+
+    /// Sends the signal `signo` to the child.
+    pub fn signal(&self, signo: i32) -> Result<(), Error> {
+        crate::lua_state()
+            .exec_with(
+                "local key, signo = ...
+                local h = debug.getregistry()[key]
+                local ok, err = h:signal(signo)
+                if not ok then
+                    error(err)
+                end",
+                (&self.key, signo),
+            )
+            .map_err(tlua::LuaError::from)?;
+        Ok(())
+    }
+
+    /// Sends `SIGKILL` to the child.
+    #[inline(always)]
+    pub fn kill(&self) -> Result<(), Error> {
+        self.signal(libc::SIGKILL)
+    }
+
+    /// Sends `SIGTERM` to the child.
+    #[inline(always)]
+    pub fn terminate(&self) -> Result<(), Error> {
+        self.signal(libc::SIGTERM)
+    }
+
+    /// Returns the exit status of the child if it has finished, or `None`
+    /// if it's still running. Doesn't yield.
+    pub fn try_wait(&self) -> Result<Option<ExitStatus>, Error> {
+        let (state, code): (String, Option<i32>) = crate::lua_state()
+            .eval_with(
+                "local key = ...
+                local status = debug.getregistry()[key].status
+                return status.state, status.exit_code or status.signo",
+                &self.key,
+            )
+            .map_err(tlua::LuaError::from)?;
+        let status = match (state.as_str(), code) {
+            ("alive", _) => None,
+            ("exited", Some(code)) => Some(ExitStatus::Exited(code)),
+            ("signaled", Some(signo)) => Some(ExitStatus::Signaled(signo)),
+            _ => {
+                return Err(Error::other(format!(
+                    "unexpected process status '{}'",
+                    state
+                )))
+            }
+        };
+        Ok(status)
+    }
+
+    /// Yields until the child finishes and returns its exit status.
+    ///
+    /// Returns an error if the child doesn't finish within `timeout`, the
+    /// child keeps running in this case.
+    pub fn wait(&self, timeout: Duration) -> Result<ExitStatus, Error> {
+        let deadline = fiber::clock().saturating_add(timeout);
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            if fiber::clock() >= deadline {
+                return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+            }
+            fiber::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        let res = crate::lua_state().exec_with(
+            "local key = ...
+            local registry = debug.getregistry()
+            local h = registry[key]
+            registry[key] = nil
+            h:close()",
+            &self.key,
+        );
+        if let Err(e) = res {
+            crate::say_warn!("failed to close process handle: {}", e);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ExitStatus
+////////////////////////////////////////////////////////////////////////////////
+
+/// Describes how a child process has finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExitStatus {
+    /// The process has exited with the given code.
+    Exited(i32),
+    /// The process was killed by the given signal.
+    Signaled(i32),
+}
+
+impl ExitStatus {
+    /// Returns `true` if the process has exited with code 0.
+    #[inline(always)]
+    pub fn success(&self) -> bool {
+        matches!(self, Self::Exited(0))
+    }
+
+    /// Returns the exit code if the process has exited by itself.
+    #[inline(always)]
+    pub fn code(&self) -> Option<i32> {
+        match *self {
+            Self::Exited(code) => Some(code),
+            Self::Signaled(_) => None,
+        }
+    }
+
+    /// Returns the number of the signal if the process was killed by one.
+    #[inline(always)]
+    pub fn signal(&self) -> Option<i32> {
+        match *self {
+            Self::Exited(_) => None,
+            Self::Signaled(signo) => Some(signo),
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exit code: {}", code),
+            Self::Signaled(signo) => write!(f, "signal: {}", signo),
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn read_to_end(mut read: impl FnMut() -> Result<Vec<u8>, Error>) -> Vec<u8> {
+        let mut res = vec![];
+        loop {
+            let chunk = read().unwrap();
+            if chunk.is_empty() {
+                return res;
+            }
+            res.extend(chunk);
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn output_and_exit_code() {
+        let child = Command::new("/bin/sh")
+            .args(["-c", "echo hello; echo oops >&2; exit 3"])
+            .stdout(Stdio::Piped)
+            .stderr(Stdio::Piped)
+            .spawn()
+            .unwrap();
+        assert!(child.pid().is_some());
+        assert_eq!(read_to_end(|| child.read_stdout(TIMEOUT)), b"hello\n");
+        assert_eq!(read_to_end(|| child.read_stderr(TIMEOUT)), b"oops\n");
+        let status = child.wait(TIMEOUT).unwrap();
+        assert_eq!(status, ExitStatus::Exited(3));
+        assert!(!status.success());
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn stdin() {
+        let child = Command::new("/bin/cat")
+            .stdin(Stdio::Piped)
+            .stdout(Stdio::Piped)
+            .spawn()
+            .unwrap();
+        child.write_stdin(b"foo ", TIMEOUT).unwrap();
+        child.write_stdin(b"bar", TIMEOUT).unwrap();
+        child.close_stdin().unwrap();
+        assert_eq!(read_to_end(|| child.read_stdout(TIMEOUT)), b"foo bar");
+        assert!(child.wait(TIMEOUT).unwrap().success());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn env() {
+        let child = Command::new("/bin/sh")
+            .args(["-c", "echo $FOO-$HOME"])
+            .env_clear()
+            .env("FOO", "bar")
+            .stdout(Stdio::Piped)
+            .spawn()
+            .unwrap();
+        assert_eq!(read_to_end(|| child.read_stdout(TIMEOUT)), b"bar-\n");
+        assert!(child.wait(TIMEOUT).unwrap().success());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn timeouts_and_signals() {
+        let child = Command::new("/bin/sleep")
+            .arg("100")
+            .stdout(Stdio::Piped)
+            .spawn()
+            .unwrap();
+        let e = child.read_stdout(Duration::from_millis(10)).unwrap_err();
+        assert!(matches!(e, Error::IO(ref e) if e.kind() == io::ErrorKind::TimedOut));
+        let e = child.wait(Duration::from_millis(10)).unwrap_err();
+        assert!(matches!(e, Error::IO(ref e) if e.kind() == io::ErrorKind::TimedOut));
+        assert_eq!(child.try_wait().unwrap(), None);
+
+        child.kill().unwrap();
+        let status = child.wait(TIMEOUT).unwrap();
+        assert_eq!(status, ExitStatus::Signaled(libc::SIGKILL));
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+}
@@ -0,0 +1,325 @@
+//! Random numbers for stored procedures and fibers.
+//!
+//! The `rand`-like crates keep the generator state in a `thread_local`, which
+//! in tarantool is shared by all of the fibers of the thread, so the sequence
+//! observed by a fiber depends on what the other fibers do in between its
+//! yields. This module instead keeps a separate fast non-cryptographic
+//! generator ([`Rng`]) for each fiber. The generators are seeded from a
+//! generator which is itself seeded once per instance from the system entropy
+//! source, unless the fiber sets the seed explicitly via [`seed`].
+//!
+//! For the values which must be unpredictable (tokens, salts, keys) use
+//! [`secure_bytes`] and [`secure_u64`] instead, which read the system entropy
+//! source directly.
+//!
+//! ```no_run
+//! use tarantool::random;
+//!
+//! let dice = random::range(1..7);
+//! let mut ids = vec![1, 2, 3, 4];
+//! random::shuffle(&mut ids);
+//!
+//! let mut token = [0; 16];
+//! random::secure_bytes(&mut token);
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::ffi::tarantool as ffi;
+use crate::fiber::{self, FiberId};
+
+////////////////////////////////////////////////////////////////////////////////
+// Rng
+////////////////////////////////////////////////////////////////////////////////
+
+/// A fast non-cryptographic pseudo random number generator (xoshiro256++).
+///
+/// Can be used directly if the state should be owned by the caller, e.g. for
+/// reproducible sequences in tests. Otherwise consider using the free
+/// functions of this module, which use the current fiber's generator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    s: [u64; 4],
+}
+
+impl Rng {
+    /// Creates a generator which always produces the same sequence for the
+    /// same `seed`.
+    pub fn from_seed(mut seed: u64) -> Self {
+        // The state is expanded with splitmix64 as recommended by the
+        // authors of xoshiro, which also guarantees it's not all zeros.
+        let mut next = || {
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Self {
+            s: [next(), next(), next(), next()],
+        }
+    }
+
+    /// Creates a generator seeded from the system entropy source.
+    #[inline]
+    pub fn from_entropy() -> Self {
+        Self::from_seed(secure_u64())
+    }
+
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let res = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        res
+    }
+
+    #[inline(always)]
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as _
+    }
+
+    /// Returns a value uniformly distributed in `[0, 1)`.
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1_u64 << 53) as f64)
+    }
+
+    /// Returns a value uniformly distributed in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is empty.
+    #[inline]
+    pub fn gen_range(&mut self, range: Range<u64>) -> u64 {
+        assert!(range.start < range.end, "empty range {:?}", range);
+        let span = range.end - range.start;
+        // Lemire's method: the values below `threshold` would make some of
+        // the results more likely than the others, so they're rejected.
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let m = self.next_u64() as u128 * span as u128;
+            if m as u64 >= threshold {
+                return range.start + (m >> 64) as u64;
+            }
+        }
+    }
+
+    /// Fills `buf` with random bytes.
+    pub fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    /// Shuffles `slice` in place, all permutations being equally likely.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.gen_range(0..i as u64 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// per-fiber generators
+////////////////////////////////////////////////////////////////////////////////
+
+struct State {
+    /// Seeds the generators of the fibers which haven't set one explicitly.
+    instance: Rng,
+    fibers: HashMap<FiberId, Rng>,
+}
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+}
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> T {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let state = state.get_or_insert_with(|| State {
+            instance: Rng::from_entropy(),
+            fibers: HashMap::new(),
+        });
+        f(state)
+    })
+}
+
+/// Calls `f` with the current fiber's generator, creating it if needed.
+///
+/// The generator is taken out of the shared state for the duration of the
+/// call, so `f` may call the other functions of this module, which will use
+/// a fresh generator in this case.
+pub fn with_rng<T>(f: impl FnOnce(&mut Rng) -> T) -> T {
+    let id = fiber::id();
+    let (mut rng, is_new) = with_state(|state| match state.fibers.remove(&id) {
+        Some(rng) => (rng, false),
+        None => (Rng::from_seed(state.instance.next_u64()), true),
+    });
+    let res = f(&mut rng);
+    put_rng(id, rng, is_new);
+    res
+}
+
+fn put_rng(id: FiberId, rng: Rng, is_new: bool) {
+    with_state(|state| {
+        if is_new {
+            // The generators of the fibers which exited are cleaned up lazily.
+            state.fibers.retain(|&other, _| fiber::exists(other));
+        }
+        state.fibers.insert(id, rng);
+    })
+}
+
+/// Sets the seed of the current fiber's generator, so that the values
+/// returned to the fiber from now on are reproducible.
+#[inline]
+pub fn seed(seed: u64) {
+    let id = fiber::id();
+    let is_new = with_state(|state| !state.fibers.contains_key(&id));
+    put_rng(id, Rng::from_seed(seed), is_new);
+}
+
+/// Returns a random `u64` from the current fiber's generator.
+#[inline]
+pub fn u64() -> u64 {
+    with_rng(Rng::next_u64)
+}
+
+/// Returns a random `u32` from the current fiber's generator.
+#[inline]
+pub fn u32() -> u32 {
+    with_rng(Rng::next_u32)
+}
+
+/// Returns a value uniformly distributed in `[0, 1)` from the current
+/// fiber's generator.
+#[inline]
+pub fn f64() -> f64 {
+    with_rng(Rng::next_f64)
+}
+
+/// Returns a value uniformly distributed in `range` from the current fiber's
+/// generator.
+///
+/// # Panics
+/// Panics if `range` is empty.
+#[inline]
+pub fn range(range: Range<u64>) -> u64 {
+    with_rng(|rng| rng.gen_range(range))
+}
+
+/// Fills `buf` with random bytes from the current fiber's generator. Use
+/// [`secure_bytes`] if the bytes must be unpredictable.
+#[inline]
+pub fn fill_bytes(buf: &mut [u8]) {
+    with_rng(|rng| rng.fill_bytes(buf))
+}
+
+/// Shuffles `slice` in place using the current fiber's generator.
+#[inline]
+pub fn shuffle<T>(slice: &mut [T]) {
+    with_rng(|rng| rng.shuffle(slice))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// cryptographically secure
+////////////////////////////////////////////////////////////////////////////////
+
+/// Fills `buf` with bytes from the system entropy source (`getrandom`,
+/// `arc4random` or `/dev/urandom`, whichever the instance uses).
+///
+/// **NOTE**: tarantool falls back to a weak generator if none of the entropy
+/// sources are available, which may be the case in a restricted sandbox.
+#[inline]
+pub fn secure_bytes(buf: &mut [u8]) {
+    // SAFETY: always safe
+    unsafe { ffi::random_bytes(buf.as_mut_ptr().cast(), buf.len()) }
+}
+
+/// Returns a `u64` from the system entropy source, see [`secure_bytes`].
+#[inline]
+pub fn secure_u64() -> u64 {
+    let mut buf = [0; 8];
+    secure_bytes(&mut buf);
+    u64::from_le_bytes(buf)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn rng() {
+        let mut a = Rng::from_seed(42);
+        let mut b = Rng::from_seed(42);
+        let seq: Vec<_> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(seq, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(Rng::from_seed(43).next_u64(), seq[0]);
+
+        for _ in 0..1000 {
+            let v = a.gen_range(10..13);
+            assert!((10..13).contains(&v), "{}", v);
+            let v = a.next_f64();
+            assert!((0.0..1.0).contains(&v), "{}", v);
+        }
+        assert_eq!(a.gen_range(7..8), 7);
+
+        let mut v: Vec<_> = (0..100).collect();
+        a.shuffle(&mut v);
+        assert_ne!(v, (0..100).collect::<Vec<_>>());
+        v.sort_unstable();
+        assert_eq!(v, (0..100).collect::<Vec<_>>());
+
+        let mut buf = [0; 13];
+        a.fill_bytes(&mut buf);
+        assert_ne!(buf, [0; 13]);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn per_fiber() {
+        let sequence = || {
+            seed(13);
+            let mut res = vec![];
+            for _ in 0..5 {
+                res.push(u64());
+                fiber::reschedule();
+            }
+            res
+        };
+        let f1 = fiber::start(sequence);
+        let f2 = fiber::start(sequence);
+        let (s1, s2) = (f1.join(), f2.join());
+        assert_eq!(s1, s2);
+        let mut rng = Rng::from_seed(13);
+        assert_eq!(s1, (0..5).map(|_| rng.next_u64()).collect::<Vec<_>>());
+
+        // Unseeded fibers get different sequences.
+        let f1 = fiber::start(|| (u64(), u64()));
+        let f2 = fiber::start(|| (u64(), u64()));
+        assert_ne!(f1.join(), f2.join());
+
+        // Reentrant calls don't panic.
+        let (a, b) = with_rng(|rng| (rng.next_u64(), u64()));
+        assert_ne!(a, b);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn secure() {
+        let mut a = [0; 32];
+        let mut b = [0; 32];
+        secure_bytes(&mut a);
+        secure_bytes(&mut b);
+        assert_ne!(a, b);
+        assert_ne!(secure_u64(), secure_u64());
+    }
+}
@@ -1,9 +1,38 @@
+//! Read views on memtx spaces.
+//!
+//! A [`ReadView`] freezes the contents of the selected spaces at the moment
+//! of its creation, so that they can be scanned while the spaces themselves
+//! are being modified. The read view must be opened and dropped in the tx
+//! thread, but its tuples can be iterated from any thread, e.g. from a
+//! [`coio_call`](crate::coio::coio_call) callback or a thread spawned via
+//! [`std::thread::scope`], which allows dumping large amounts of data without
+//! blocking the event loop. Read views are only available with the
+//! `picodata` feature.
+//!
+//! [`with`] runs a closure with a consistent [`Snapshot`] of the given spaces,
+//! which can be used for long scans which yield many times. It uses a read
+//! view when it's available and falls back to a transaction with the
+//! `read-confirmed` isolation level otherwise (which requires
+//! `box.cfg.memtx_use_mvcc_engine` to be enabled).
+
+use crate::error::{BoxError, TarantoolErrorCode};
+#[cfg(feature = "picodata")]
 use crate::ffi::tarantool as ffi;
+#[cfg(feature = "picodata")]
 use crate::index::IndexId;
+#[cfg(not(feature = "picodata"))]
+use crate::index::{IndexIterator, IteratorType};
+#[cfg(not(feature = "picodata"))]
+use crate::space::Space;
 use crate::space::SpaceId;
+use crate::tuple::Tuple;
+#[cfg(feature = "picodata")]
 use std::mem::align_of;
+#[cfg(feature = "picodata")]
 use std::mem::size_of;
+#[cfg(feature = "picodata")]
 use std::mem::MaybeUninit;
+#[cfg(feature = "picodata")]
 use std::ptr::NonNull;
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -13,12 +42,20 @@ use std::ptr::NonNull;
 /// An object which guards a read view on the selected set of spaces and
 /// indexes. Provides access to the frozen contents of the selected indexes at
 /// the moment of the read view's creation.
+///
+/// The snapshot is released once the read view is dropped.
+///
+/// `ReadView` is [`Sync`], so the iterators can be created and consumed in
+/// any thread via a shared reference, but it is not [`Send`], because it must
+/// be closed in the same (tx) thread in which it was opened.
+#[cfg(feature = "picodata")]
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct ReadView {
     inner: NonNull<ffi::box_read_view_t>,
     space_indexes: Vec<(SpaceId, IndexId)>,
 }
 
+#[cfg(feature = "picodata")]
 impl ReadView {
     /// Open a read view on the given space indexes.
     #[inline]
@@ -50,6 +87,13 @@ impl ReadView {
         })
     }
 
+    /// Open a read view on the primary indexes of the given spaces.
+    #[inline]
+    #[track_caller]
+    pub fn for_spaces(spaces: impl IntoIterator<Item = SpaceId>) -> crate::Result<Self> {
+        Self::for_space_indexes(spaces.into_iter().map(|space| (space, 0)).collect())
+    }
+
     /// Get the list of spaces and idexes for which the read view was opened, if
     /// it's available.
     #[inline(always)]
@@ -89,6 +133,13 @@ impl ReadView {
     }
 }
 
+// SAFETY: the read view is immutable and the read view iterator api is
+// allowed to be used from any thread. Opening and closing the read view on the
+// other hand is only allowed in the tx thread, hence no `Send` implementation.
+#[cfg(feature = "picodata")]
+unsafe impl Sync for ReadView {}
+
+#[cfg(feature = "picodata")]
 impl Drop for ReadView {
     #[inline(always)]
     fn drop(&mut self) {
@@ -96,12 +147,18 @@ impl Drop for ReadView {
     }
 }
 
+/// An iterator over the raw tuple data of an index read view, see
+/// [`ReadView::iter_all`].
+///
+/// The iterator can be sent to and consumed in another thread.
+#[cfg(feature = "picodata")]
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct ReadViewIterator<'a> {
     inner: NonNull<ffi::box_read_view_iterator_t>,
     _marker: std::marker::PhantomData<&'a ()>,
 }
 
+#[cfg(feature = "picodata")]
 impl<'a> Iterator for ReadViewIterator<'a> {
     type Item = &'a [u8];
 
@@ -129,6 +186,12 @@ impl<'a> Iterator for ReadViewIterator<'a> {
     }
 }
 
+// SAFETY: the iterator only refers to the data of the read view it was created
+// from, which is immutable and outlives the iterator.
+#[cfg(feature = "picodata")]
+unsafe impl Send for ReadViewIterator<'_> {}
+
+#[cfg(feature = "picodata")]
 impl<'a> Drop for ReadViewIterator<'a> {
     #[inline(always)]
     fn drop(&mut self) {
@@ -136,13 +199,179 @@ impl<'a> Drop for ReadViewIterator<'a> {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// with
+////////////////////////////////////////////////////////////////////////////////
+
+/// Runs `f` with a consistent snapshot of the primary indexes of the
+/// `spaces`. The tuples returned by the snapshot's iterators reflect the
+/// contents of the spaces at the moment of the call, even though `f` yields
+/// and other fibers modify the spaces in the meantime. The snapshot is
+/// released once `f` returns.
+///
+/// With the `picodata` feature the snapshot is a [`ReadView`], which doesn't
+/// block the writes in any way. Otherwise `f` is executed within a
+/// transaction with the `read-confirmed` isolation level, which is committed
+/// (it only reads the data) if `f` succeeds and rolled back if it fails or
+/// panics.
+///
+/// **NOTE**: without the `picodata` feature the snapshot is only consistent
+/// if `box.cfg.memtx_use_mvcc_engine` is enabled. Without MVCC a yield inside
+/// `f` aborts the memtx transaction, so the reads after the yield don't see
+/// the snapshot and the transaction abort error is returned once `f`
+/// returns.
+///
+/// ```no_run
+/// use tarantool::read_view;
+/// use tarantool::space::Space;
+///
+/// let orders = Space::find("orders").unwrap();
+/// let total = read_view::with([orders.id()], |snapshot| {
+///     let mut total = 0;
+///     for (i, tuple) in snapshot.iter_all(orders.id())?.enumerate() {
+///         total += tuple.get::<_, u64>(1).unwrap_or(0);
+///         if i % 1000 == 0 {
+///             tarantool::fiber::reschedule();
+///         }
+///     }
+///     Ok(total)
+/// })
+/// .unwrap();
+/// ```
+pub fn with<R>(
+    spaces: impl IntoIterator<Item = SpaceId>,
+    f: impl FnOnce(&Snapshot) -> crate::Result<R>,
+) -> crate::Result<R> {
+    let spaces: Vec<SpaceId> = spaces.into_iter().collect();
+
+    #[cfg(feature = "picodata")]
+    {
+        let read_view = ReadView::for_spaces(spaces.iter().copied())?;
+        f(&Snapshot { read_view, spaces })
+    }
+
+    #[cfg(not(feature = "picodata"))]
+    {
+        /// Rolls back the transaction if `f` panics.
+        struct RollbackGuard;
+        impl Drop for RollbackGuard {
+            fn drop(&mut self) {
+                let _ = crate::transaction::rollback();
+            }
+        }
+
+        crate::lua_state().exec("box.begin({txn_isolation = 'read-confirmed'})")?;
+        let guard = RollbackGuard;
+        let res = f(&Snapshot { spaces });
+        std::mem::forget(guard);
+        if res.is_ok() {
+            crate::transaction::commit()?;
+        } else {
+            crate::transaction::rollback()?;
+        }
+        res
+    }
+}
+
+/// A consistent snapshot of the spaces, see [`with`].
+#[derive(Debug)]
+pub struct Snapshot {
+    #[cfg(feature = "picodata")]
+    read_view: ReadView,
+    spaces: Vec<SpaceId>,
+}
+
+impl Snapshot {
+    /// Returns the ids of the spaces in the snapshot.
+    #[inline(always)]
+    pub fn spaces(&self) -> &[SpaceId] {
+        &self.spaces
+    }
+
+    /// Returns `true` if the snapshot is a [`ReadView`], otherwise it's a
+    /// transaction, see [`with`].
+    #[inline(always)]
+    pub fn is_read_view(&self) -> bool {
+        cfg!(feature = "picodata")
+    }
+
+    /// Returns an iterator over all of the tuples of the `space` in the order
+    /// of the primary index.
+    pub fn iter_all(&self, space: SpaceId) -> crate::Result<SnapshotIterator<'_>> {
+        if !self.spaces.contains(&space) {
+            return Err(BoxError::new(
+                TarantoolErrorCode::NoSuchSpace,
+                format!("space #{} is not in the snapshot", space),
+            )
+            .into());
+        }
+
+        #[cfg(feature = "picodata")]
+        let inner = self.read_view.iter_all(space, 0)?.ok_or_else(|| {
+            BoxError::new(
+                TarantoolErrorCode::NoSuchSpace,
+                format!("space #{} is not in the read view", space),
+            )
+        })?;
+
+        #[cfg(not(feature = "picodata"))]
+        let inner = {
+            // SAFETY: the iterator reports an error if the space doesn't
+            // exist.
+            let space = unsafe { Space::from_id_unchecked(space) };
+            space.select(IteratorType::All, &())?
+        };
+
+        Ok(SnapshotIterator {
+            inner,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+/// An iterator over the tuples of a [`Snapshot`].
+///
+/// The tuples of a [`ReadView`] are copied into new tuples.
+pub struct SnapshotIterator<'a> {
+    #[cfg(feature = "picodata")]
+    inner: ReadViewIterator<'a>,
+    #[cfg(not(feature = "picodata"))]
+    inner: IndexIterator,
+    _marker: std::marker::PhantomData<&'a Snapshot>,
+}
+
+impl Iterator for SnapshotIterator<'_> {
+    type Item = Tuple;
+
+    #[inline]
+    fn next(&mut self) -> Option<Tuple> {
+        #[cfg(feature = "picodata")]
+        {
+            let data = self.inner.next()?;
+            // SAFETY: the read view contains valid tuple data.
+            Some(unsafe { Tuple::from_slice(data) })
+        }
+
+        #[cfg(not(feature = "picodata"))]
+        self.inner.next()
+    }
+}
+
+impl std::fmt::Debug for SnapshotIterator<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotIterator").finish_non_exhaustive()
+    }
+}
+
 #[cfg(feature = "internal_test")]
 mod tests {
     use super::*;
     use crate::space::Space;
+    #[cfg(feature = "picodata")]
     use crate::space::SystemSpace;
     use crate::temp_space_name;
 
+    #[cfg(feature = "picodata")]
     #[crate::test(tarantool = "crate")]
     fn read_view() {
         let s = Space::builder(&temp_space_name!()).create().unwrap();
@@ -161,4 +390,104 @@ mod tests {
         assert_eq!(iter.next(), None);
         assert_eq!(iter.next(), None);
     }
+
+    #[cfg(feature = "picodata")]
+    #[crate::test(tarantool = "crate")]
+    fn read_view_from_other_thread() {
+        let s = Space::builder(&temp_space_name!()).create().unwrap();
+        s.index_builder("pk").create().unwrap();
+        for i in 0..100 {
+            s.insert(&(i, "foo")).unwrap();
+        }
+
+        let rv = ReadView::for_spaces([s.id()]).unwrap();
+        assert_eq!(rv.space_indexes(), Some(&[(s.id(), 0)][..]));
+
+        // Modifications after the read view was opened are not visible.
+        s.truncate().unwrap();
+        s.insert(&(1337, "bar")).unwrap();
+
+        let mut tuples = vec![];
+        let mut scan = |_: Box<()>| {
+            let thread = std::thread::current().id();
+            let iter = rv.iter_all(s.id(), 0).unwrap().unwrap();
+            tuples = iter.map(|data| (thread, data.to_vec())).collect();
+            0
+        };
+        assert_eq!(crate::coio::coio_call(&mut scan, ()), 0);
+
+        assert_eq!(tuples.len(), 100);
+        for (i, (thread, data)) in tuples.iter().enumerate() {
+            assert_ne!(*thread, std::thread::current().id());
+            let tuple: (i32, String) = rmp_serde::from_slice(data).unwrap();
+            assert_eq!(tuple, (i as i32, "foo".into()));
+        }
+
+        drop(rv);
+        assert_eq!(s.len().unwrap(), 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn with_snapshot() {
+        let s = Space::builder(&temp_space_name!()).create().unwrap();
+        s.index_builder("pk").create().unwrap();
+        for i in 0..10 {
+            s.insert(&(i, "foo")).unwrap();
+        }
+
+        let lua = crate::lua_state();
+        let mvcc: bool = lua.eval("return box.cfg.memtx_use_mvcc_engine").unwrap();
+        let s_id = s.id();
+        let writer = move || {
+            let s = unsafe { Space::from_id_unchecked(s_id) };
+            s.truncate().unwrap();
+            s.insert(&(1337, "bar")).unwrap();
+        };
+
+        let res = with([s.id()], |snapshot| {
+            assert_eq!(snapshot.spaces(), [s.id()]);
+            // The writes of other fibers are not visible.
+            crate::fiber::start(writer).join();
+            let tuples: Vec<(i32, String)> = snapshot
+                .iter_all(s.id())?
+                .map(|t| t.decode().unwrap())
+                .collect();
+            assert_eq!(tuples.len(), 10);
+            assert_eq!(tuples[9], (9, "foo".into()));
+
+            let e = snapshot.iter_all(s.id() + 1).unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                format!(
+                    "box error: NoSuchSpace: space #{} is not in the snapshot",
+                    s.id() + 1
+                )
+            );
+            Ok(tuples.len())
+        });
+        if cfg!(feature = "picodata") || mvcc {
+            assert_eq!(res.unwrap(), 10);
+        } else {
+            // Without mvcc the transaction is aborted by the yield.
+            res.unwrap_err();
+        }
+        assert!(!crate::transaction::is_in_transaction());
+        assert_eq!(s.len().unwrap(), 1);
+
+        // The error of the closure is returned.
+        let e = with([s.id()], |_| -> crate::Result<()> {
+            Err(crate::error::Error::other("oops"))
+        })
+        .unwrap_err();
+        assert_eq!(e.to_string(), "oops");
+        assert!(!crate::transaction::is_in_transaction());
+
+        // The transaction is rolled back if the closure panics.
+        let res =
+            std::panic::catch_unwind(|| with([s_id], |_| -> crate::Result<()> { panic!("oops") }));
+        assert!(res.is_err());
+        assert!(!crate::transaction::is_in_transaction());
+
+        s.drop().unwrap();
+    }
 }
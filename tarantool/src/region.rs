@@ -0,0 +1,320 @@
+//! Temporary allocations on the fiber's region.
+//!
+//! The region (also known as the box region or `fiber->gc`) is a per-fiber
+//! bump allocator, which tarantool uses for the temporary data of a request:
+//! an allocation costs a pointer increment and all of the memory allocated
+//! after a savepoint is freed at once by truncating the region back to it.
+//! C modules use it via `box_region_alloc` and `box_region_truncate` to avoid
+//! the heap allocations for per-request temporaries.
+//!
+//! [`scoped`] provides the same thing with checked lifetimes: the memory
+//! allocated via the [`Region`] passed to the closure can't outlive the
+//! closure, and the region is truncated back once the closure returns. It's
+//! still `unsafe`, because tarantool itself truncates the region in some
+//! cases (e.g. when a transaction is committed), see [`scoped`] for details.
+//!
+//! Only the types which don't need to be dropped (i.e. [`Copy`] ones) can be
+//! allocated, because the region never runs destructors.
+//!
+//! ```no_run
+//! use tarantool::region;
+//!
+//! // SAFETY: the closure doesn't commit or rollback any transactions.
+//! let sum: u64 = unsafe {
+//!     region::scoped(|region| {
+//!         let squares = region.alloc_slice::<u64>(1000)?;
+//!         for (i, v) in squares.iter_mut().enumerate() {
+//!             *v = (i * i) as _;
+//!         }
+//!         Ok::<_, tarantool::error::Error>(squares.iter().sum())
+//!     })
+//! }
+//! .unwrap();
+//! ```
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+
+use crate::error::{BoxError, TarantoolError, TarantoolErrorCode};
+use crate::ffi::tarantool as ffi;
+use crate::fiber::{self, FiberId};
+
+/// Returns the number of bytes currently allocated on the current fiber's
+/// region.
+#[inline(always)]
+pub fn used() -> usize {
+    // SAFETY: always safe
+    unsafe { ffi::box_region_used() }
+}
+
+/// Calls `f` with a [`Region`] for allocating the temporary data and frees
+/// the data once `f` returns (or panics).
+///
+/// The region is only truncated if it wasn't allocated from by anyone else
+/// since the last allocation via the [`Region`], e.g. by an outer scope or
+/// by the code which intentionally keeps the data on the region until the end
+/// of the request. Otherwise the memory is freed at the end of the request
+/// as usual.
+///
+/// # Safety
+/// The data allocated via the [`Region`] must not be accessed after the
+/// region is truncated by tarantool. This happens when a transaction is
+/// committed or rolled back (e.g. via [`transaction::commit`],
+/// [`transaction::rollback`], [`transaction::transaction`] or the
+/// corresponding lua functions) and may happen in the other cases which call
+/// `fiber_gc()`, including any lua code or stored procedures called from
+/// `f`.
+///
+/// The truncation is detected on a best-effort basis: the allocations via
+/// the [`Region`] return an error after it, but the data allocated before
+/// isn't valid anymore regardless.
+///
+/// [`transaction::commit`]: crate::transaction::commit
+/// [`transaction::rollback`]: crate::transaction::rollback
+/// [`transaction::transaction`]: crate::transaction::transaction
+pub unsafe fn scoped<T>(f: impl for<'r> FnOnce(&Region<'r>) -> T) -> T {
+    let svp = used();
+    let region = Region {
+        svp,
+        used: Cell::new(svp),
+        dirty: Cell::new(false),
+        truncated: Cell::new(false),
+        fiber_id: fiber::id(),
+        marker: PhantomData,
+    };
+    f(&region)
+}
+
+/// A scope of temporary allocations on the fiber's region, see [`scoped`].
+#[derive(Debug)]
+pub struct Region<'r> {
+    /// The savepoint to truncate the region to.
+    svp: usize,
+    /// The size of the region after the last allocation via `self`.
+    used: Cell<usize>,
+    /// Set if someone else allocated in between the allocations via `self`,
+    /// in which case the region mustn't be truncated.
+    dirty: Cell<bool>,
+    /// Set if the region was truncated below the last allocation via `self`,
+    /// in which case the allocations fail.
+    truncated: Cell<bool>,
+    /// The region is per fiber, so it can't be used from the other fibers.
+    fiber_id: FiberId,
+    /// Makes `'r` invariant, so that the data can't escape [`scoped`].
+    marker: PhantomData<Cell<&'r ()>>,
+}
+
+impl<'r> Region<'r> {
+    fn alloc_raw<T>(&self, len: usize) -> crate::Result<*mut T> {
+        if fiber::id() != self.fiber_id {
+            return Err(BoxError::new(
+                TarantoolErrorCode::Unsupported,
+                "region can only be used by the fiber which created it",
+            )
+            .into());
+        }
+        let Some(size) = len.checked_mul(size_of::<T>()) else {
+            return Err(BoxError::new(
+                TarantoolErrorCode::MemoryIssue,
+                format!("region allocation of {} values is too large", len),
+            )
+            .into());
+        };
+        if used() < self.used.get() {
+            self.truncated.set(true);
+        }
+        if self.truncated.get() {
+            return Err(BoxError::new(
+                TarantoolErrorCode::Unsupported,
+                "region was truncated during the scope, e.g. by a transaction commit",
+            )
+            .into());
+        }
+        if size == 0 {
+            return Ok(std::ptr::NonNull::dangling().as_ptr());
+        }
+        if used() != self.used.get() {
+            self.dirty.set(true);
+        }
+        // SAFETY: always safe
+        let ptr = unsafe { ffi::box_region_aligned_alloc(size, align_of::<T>()) };
+        if ptr.is_null() {
+            return Err(TarantoolError::last().into());
+        }
+        self.used.set(used());
+        Ok(ptr.cast())
+    }
+
+    /// Allocates `len` values of type `T` initialized with `T::default()`.
+    ///
+    /// Returns an error if the region is out of memory, if it was truncated
+    /// since the scope began or if called from a different fiber.
+    #[inline]
+    pub fn alloc_slice<T>(&self, len: usize) -> crate::Result<&'r mut [T]>
+    where
+        T: Copy + Default,
+    {
+        let ptr = self.alloc_raw::<T>(len)?;
+        // SAFETY: `ptr` points to `len` properly aligned values of `T` which
+        // are valid for `'r`, which is shorter than the scope, as long as the
+        // safety requirements of `scoped` are met.
+        unsafe {
+            for i in 0..len {
+                ptr.add(i).write(T::default());
+            }
+            Ok(std::slice::from_raw_parts_mut(ptr, len))
+        }
+    }
+
+    /// Allocates a copy of `src`.
+    ///
+    /// Returns an error in the same cases as [`Self::alloc_slice`].
+    #[inline]
+    pub fn alloc_slice_copy<T>(&self, src: &[T]) -> crate::Result<&'r mut [T]>
+    where
+        T: Copy,
+    {
+        let ptr = self.alloc_raw::<T>(src.len())?;
+        // SAFETY: same as in `alloc_slice`.
+        unsafe {
+            std::ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            Ok(std::slice::from_raw_parts_mut(ptr, src.len()))
+        }
+    }
+
+    /// Allocates a copy of `s`.
+    #[inline]
+    pub fn alloc_str(&self, s: &str) -> crate::Result<&'r mut str> {
+        let bytes = self.alloc_slice_copy(s.as_bytes())?;
+        // SAFETY: the bytes are copied from a valid `str`.
+        unsafe { Ok(std::str::from_utf8_unchecked_mut(bytes)) }
+    }
+
+    /// Allocates a single `value`.
+    #[inline]
+    pub fn alloc<T>(&self, value: T) -> crate::Result<&'r mut T>
+    where
+        T: Copy,
+    {
+        let res = self.alloc_slice_copy(std::slice::from_ref(&value))?;
+        Ok(&mut res[0])
+    }
+}
+
+impl Drop for Region<'_> {
+    fn drop(&mut self) {
+        if !self.dirty.get()
+            && !self.truncated.get()
+            && used() == self.used.get()
+            && fiber::id() == self.fiber_id
+        {
+            // SAFETY: nothing was allocated after the data of this scope,
+            // which can't be referenced anymore.
+            unsafe { ffi::box_region_truncate(self.svp) }
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn scoped_allocations() {
+        let before = used();
+        let sum = unsafe {
+            scoped(|region| {
+                let numbers = region.alloc_slice::<u64>(100).unwrap();
+                assert_eq!(numbers, &[0; 100][..]);
+                for (i, n) in numbers.iter_mut().enumerate() {
+                    *n = i as _;
+                }
+                let s = region.alloc_str("hello").unwrap();
+                s.make_ascii_uppercase();
+                assert_eq!(s, "HELLO");
+                let x = region.alloc(0x77_u8).unwrap();
+                assert_eq!(*x, 0x77);
+                assert!(used() > before);
+                numbers.iter().sum::<u64>()
+            })
+        };
+        assert_eq!(sum, 4950);
+        assert_eq!(used(), before);
+
+        let e = unsafe { scoped(|region| region.alloc_slice::<u64>(usize::MAX).unwrap_err()) };
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "box error: MemoryIssue: region allocation of {} values is too large",
+                usize::MAX
+            )
+        );
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn nested_scopes() {
+        let before = used();
+        unsafe {
+            scoped(|outer| {
+                let a = outer.alloc_slice_copy(b"outer").unwrap();
+                let after_a = used();
+
+                scoped(|inner| {
+                    inner.alloc_slice::<u8>(16).unwrap();
+                });
+                assert_eq!(used(), after_a);
+
+                // The outer scope allocates while the inner one is active, so
+                // the inner one doesn't truncate the region.
+                let b = scoped(|inner| {
+                    inner.alloc_slice::<u8>(16).unwrap();
+                    outer.alloc_slice_copy(b"still valid").unwrap()
+                });
+                assert!(used() > after_a);
+                assert_eq!(a, b"outer");
+                assert_eq!(b, b"still valid");
+            });
+        }
+        // The outer scope has been dirtied by the inner one.
+        assert!(used() > before);
+        unsafe { ffi::box_region_truncate(before) };
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn transaction_inside_scope() {
+        // Make sure the region isn't empty when the scope begins.
+        let before = used();
+        let _ = unsafe { ffi::box_region_alloc(64) };
+        let svp = used();
+        assert!(svp > before);
+
+        let e = unsafe {
+            scoped(|region| {
+                region.alloc_slice::<u8>(64).unwrap();
+                assert!(used() > svp);
+
+                // Committing a transaction truncates the whole region, the
+                // data allocated above isn't valid anymore.
+                crate::transaction::begin().unwrap();
+                crate::transaction::commit().unwrap();
+                assert!(used() < svp);
+
+                // This is detected by the following allocations.
+                let e = region.alloc_slice::<u8>(64).unwrap_err();
+                // Even if the region grows past the scope's allocations.
+                let _ = ffi::box_region_alloc(1024);
+                region.alloc(1).unwrap_err();
+                e
+            })
+        };
+        assert_eq!(
+            e.to_string(),
+            "box error: Unsupported: region was truncated during the scope, e.g. by a transaction commit"
+        );
+        // The scope doesn't truncate the region, which was allocated from by
+        // someone else.
+        assert!(used() >= 1024);
+        unsafe { ffi::box_region_truncate(0) };
+    }
+}
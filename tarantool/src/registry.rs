@@ -0,0 +1,488 @@
+//! Typed registry of the values shared by the code running in the tx thread.
+//!
+//! Modules often need some global state: [`Space`] handles, connections to
+//! other instances, parsed configuration, etc. Keeping it in a `static mut` or
+//! a `lazy_static` is error prone: the initialization may yield (e.g. when
+//! connecting to a remote instance) and another fiber observes a half
+//! initialized value, a recursive access results in a panic or a deadlock and
+//! there's no way to drop the values when the module is reloaded.
+//!
+//! This module provides a registry of values identified by typed [`Key`]s:
+//! - [`get_or_init`] initializes a value at most once, the other fibers
+//!   requesting the same value wait for the initialization to finish, and a
+//!   recursive initialization returns an error instead of a deadlock,
+//! - values are returned as reference counted [`Handle`]s, so no borrows are
+//!   held across yields, and the number of outstanding handles can be checked
+//!   via [`borrow_count`],
+//! - [`reload`] runs the hooks registered with [`on_reload`] and drops all of
+//!   the values, which should be done before the module is unloaded.
+//!
+//! The registry is local to the thread, i.e. it should only be used from the
+//! tx thread.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::registry::{self, Key};
+//! use tarantool::space::Space;
+//!
+//! static BANDS: Key<Space> = Key::new("my_module.bands");
+//!
+//! fn bands() -> tarantool::Result<registry::Handle<Space>> {
+//!     registry::get_or_try_init(&BANDS, || {
+//!         Space::find("bands").ok_or_else(|| tarantool::error::Error::other("no space 'bands'"))
+//!     })
+//! }
+//! ```
+//!
+//! [`Space`]: crate::space::Space
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::error::{BoxError, Error, TarantoolErrorCode};
+use crate::fiber::{self, Cond, FiberId, WaitError};
+
+////////////////////////////////////////////////////////////////////////////////
+// Key
+////////////////////////////////////////////////////////////////////////////////
+
+/// Identifies a value of type `T` in the registry.
+///
+/// Keys with the same name but different types refer to different values.
+pub struct Key<T> {
+    name: &'static str,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Key<T> {
+    #[inline(always)]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T: 'static> Key<T> {
+    #[inline(always)]
+    fn id(&self) -> SlotId {
+        (TypeId::of::<T>(), self.name)
+    }
+}
+
+impl<T> fmt::Debug for Key<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Key").field(&self.name).finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Handle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A reference to a value stored in the registry.
+///
+/// The value stays alive while there are handles referencing it, even if it
+/// was removed from the registry or replaced with another value.
+pub struct Handle<T>(Rc<T>);
+
+impl<T> Handle<T> {
+    /// Returns `true` if both handles reference the same value.
+    #[inline(always)]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Rc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Registry
+////////////////////////////////////////////////////////////////////////////////
+
+type SlotId = (TypeId, &'static str);
+
+enum Slot {
+    Initializing { fiber_id: FiberId, cond: Rc<Cond> },
+    Ready(Rc<dyn Any>),
+}
+
+#[derive(Default)]
+struct Registry {
+    slots: HashMap<SlotId, Slot>,
+    reload_hooks: Vec<Box<dyn FnOnce()>>,
+    /// Incremented on every [`reload`], so that the values initialized
+    /// before the reload are not stored after it.
+    generation: u64,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+fn downcast<T: 'static>(value: Rc<dyn Any>) -> Handle<T> {
+    match value.downcast() {
+        Ok(value) => Handle(value),
+        Err(_) => unreachable!("registry slots are identified by the type id"),
+    }
+}
+
+/// Returns the value for `key` if it's initialized.
+pub fn get<T: 'static>(key: &Key<T>) -> Option<Handle<T>> {
+    REGISTRY.with(|r| match r.borrow().slots.get(&key.id()) {
+        Some(Slot::Ready(value)) => Some(downcast(value.clone())),
+        _ => None,
+    })
+}
+
+/// Returns the value for `key`, initializing it with `f` if needed.
+///
+/// See [`get_or_try_init`] for details.
+pub fn get_or_init<T, F>(key: &Key<T>, f: F) -> crate::Result<Handle<T>>
+where
+    T: 'static,
+    F: FnOnce() -> T,
+{
+    get_or_try_init(key, || Ok::<_, Error>(f()))
+}
+
+/// Returns the value for `key`, initializing it with `f` if needed.
+///
+/// `f` may yield, in which case the other fibers requesting the same key
+/// wait until it finishes. If `f` returns an error (or panics) the value is
+/// left uninitialized and the next call will try initializing it again.
+///
+/// Returns an error if `f` tries getting the value for the same `key`
+/// (directly or indirectly), or if the fiber is cancelled while waiting for
+/// another fiber to initialize the value.
+pub fn get_or_try_init<T, F, E>(key: &Key<T>, f: F) -> crate::Result<Handle<T>>
+where
+    T: 'static,
+    F: FnOnce() -> Result<T, E>,
+    E: Into<Error>,
+{
+    enum Action {
+        Ready(Rc<dyn Any>),
+        Wait(Rc<Cond>),
+        Init(InitGuard),
+    }
+
+    let id = key.id();
+    let mut f = Some(f);
+    loop {
+        let action = REGISTRY.with(|r| {
+            let mut r = r.borrow_mut();
+            let generation = r.generation;
+            match r.slots.get(&id) {
+                Some(Slot::Ready(value)) => Ok(Action::Ready(value.clone())),
+                Some(Slot::Initializing { fiber_id, .. }) if *fiber_id == fiber::id() => {
+                    Err(BoxError::new(
+                        TarantoolErrorCode::IllegalParams,
+                        format!("recursive initialization of registry entry '{}'", key.name),
+                    ))
+                }
+                Some(Slot::Initializing { cond, .. }) => Ok(Action::Wait(cond.clone())),
+                None => {
+                    let cond = Rc::new(Cond::new());
+                    let slot = Slot::Initializing {
+                        fiber_id: fiber::id(),
+                        cond: cond.clone(),
+                    };
+                    r.slots.insert(id, slot);
+                    Ok(Action::Init(InitGuard {
+                        id,
+                        cond,
+                        generation,
+                        value: None,
+                    }))
+                }
+            }
+        })?;
+
+        match action {
+            Action::Ready(value) => return Ok(downcast(value)),
+            Action::Wait(cond) => {
+                cond.wait();
+                if fiber::is_cancelled() {
+                    return Err(WaitError::Cancelled.into());
+                }
+            }
+            Action::Init(guard) => {
+                let f = f.take().expect("only initialized once");
+                // The guard resets the slot if `f` fails or panics.
+                let value = Rc::new(f().map_err(Into::into)?);
+                guard.finish(value.clone());
+                return Ok(Handle(value));
+            }
+        }
+    }
+}
+
+/// Marks a slot as being initialized by the current fiber.
+struct InitGuard {
+    id: SlotId,
+    cond: Rc<Cond>,
+    generation: u64,
+    value: Option<Rc<dyn Any>>,
+}
+
+impl InitGuard {
+    fn finish(mut self, value: Rc<dyn Any>) {
+        self.value = Some(value);
+    }
+}
+
+impl Drop for InitGuard {
+    /// Stores the value if the initialization succeeded or resets the slot
+    /// otherwise, and wakes up the waiting fibers.
+    fn drop(&mut self) {
+        REGISTRY.with(|r| {
+            let mut r = r.borrow_mut();
+            if r.generation != self.generation {
+                // The registry was reloaded while the value was initializing.
+                return;
+            }
+            match self.value.take() {
+                Some(value) => {
+                    r.slots.insert(self.id, Slot::Ready(value));
+                }
+                None => {
+                    r.slots.remove(&self.id);
+                }
+            }
+        });
+        self.cond.broadcast();
+    }
+}
+
+/// Stores `value` for `key` and returns the previous value if there was one.
+///
+/// The existing handles keep referencing the previous value.
+///
+/// Returns an error if the value is being initialized by [`get_or_init`].
+pub fn set<T: 'static>(key: &Key<T>, value: T) -> crate::Result<Option<Handle<T>>> {
+    REGISTRY.with(|r| {
+        let mut r = r.borrow_mut();
+        if let Some(Slot::Initializing { .. }) = r.slots.get(&key.id()) {
+            return Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                format!("registry entry '{}' is being initialized", key.name),
+            )
+            .into());
+        }
+        let old = r.slots.insert(key.id(), Slot::Ready(Rc::new(value)));
+        match old {
+            Some(Slot::Ready(old)) => Ok(Some(downcast(old))),
+            _ => Ok(None),
+        }
+    })
+}
+
+/// Removes the value for `key` from the registry and returns it.
+///
+/// Values which are being initialized are not removed.
+pub fn remove<T: 'static>(key: &Key<T>) -> Option<Handle<T>> {
+    REGISTRY.with(|r| {
+        let mut r = r.borrow_mut();
+        match r.slots.get(&key.id()) {
+            Some(Slot::Ready(_)) => match r.slots.remove(&key.id()) {
+                Some(Slot::Ready(value)) => Some(downcast(value)),
+                _ => unreachable!(),
+            },
+            _ => None,
+        }
+    })
+}
+
+/// Returns the number of outstanding [`Handle`]s referencing the value for
+/// `key`.
+pub fn borrow_count<T: 'static>(key: &Key<T>) -> usize {
+    REGISTRY.with(|r| match r.borrow().slots.get(&key.id()) {
+        // Minus the reference held by the registry.
+        Some(Slot::Ready(value)) => Rc::strong_count(value) - 1,
+        _ => 0,
+    })
+}
+
+/// Registers a hook to be called on the next [`reload`].
+///
+/// The hooks can use the values from the registry, e.g. to gracefully close
+/// the connections.
+pub fn on_reload(f: impl FnOnce() + 'static) {
+    REGISTRY.with(|r| r.borrow_mut().reload_hooks.push(Box::new(f)))
+}
+
+/// Runs the hooks registered with [`on_reload`] and drops all of the values
+/// in the registry.
+///
+/// This should be called before the code which put the values into the
+/// registry is unloaded. Values which still have outstanding [`Handle`]s are
+/// reported to the log, because they outlive the reload.
+pub fn reload() {
+    let hooks = REGISTRY.with(|r| std::mem::take(&mut r.borrow_mut().reload_hooks));
+    for hook in hooks {
+        hook();
+    }
+
+    let slots = REGISTRY.with(|r| {
+        let mut r = r.borrow_mut();
+        r.generation += 1;
+        std::mem::take(&mut r.slots)
+    });
+    for ((_, name), slot) in slots {
+        match slot {
+            Slot::Ready(value) => {
+                let count = Rc::strong_count(&value) - 1;
+                if count > 0 {
+                    crate::say_warn!(
+                        "registry entry '{}' is still referenced by {} handle(s) after reload",
+                        name,
+                        count
+                    );
+                }
+            }
+            // Waiters retry and initialize the value again.
+            Slot::Initializing { cond, .. } => cond.broadcast(),
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn init_once() {
+        static KEY: Key<String> = Key::new("test.init_once");
+        assert!(get(&KEY).is_none());
+
+        let calls = Cell::new(0);
+        let init = || {
+            calls.set(calls.get() + 1);
+            "foo".to_string()
+        };
+        let a = get_or_init(&KEY, init).unwrap();
+        let b = get_or_init(&KEY, init).unwrap();
+        assert_eq!(*a, "foo");
+        assert!(Handle::ptr_eq(&a, &b));
+        assert_eq!(calls.get(), 1);
+        assert_eq!(borrow_count(&KEY), 2);
+        drop(b);
+        assert_eq!(borrow_count(&KEY), 1);
+
+        // Same name, different type.
+        static OTHER: Key<i32> = Key::new("test.init_once");
+        assert!(get(&OTHER).is_none());
+
+        assert!(Handle::ptr_eq(&remove(&KEY).unwrap(), &a));
+        assert!(get(&KEY).is_none());
+        assert_eq!(borrow_count(&KEY), 0);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn concurrent_init() {
+        static KEY: Key<i32> = Key::new("test.concurrent_init");
+        let calls = Rc::new(Cell::new(0));
+
+        let jh = fiber::defer({
+            let calls = calls.clone();
+            move || {
+                get_or_init(&KEY, || {
+                    calls.set(calls.get() + 1);
+                    fiber::sleep(Duration::from_millis(10));
+                    42
+                })
+                .unwrap()
+            }
+        });
+        fiber::reschedule();
+        assert!(get(&KEY).is_none());
+
+        let value = get_or_init(&KEY, || unreachable!()).unwrap();
+        assert_eq!(*value, 42);
+        assert!(Handle::ptr_eq(&value, &jh.join()));
+        assert_eq!(calls.get(), 1);
+        remove(&KEY);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn recursive_init() {
+        static KEY: Key<i32> = Key::new("test.recursive_init");
+        let e = get_or_try_init(&KEY, || get_or_init(&KEY, || 1).map(|v| *v)).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IllegalParams: recursive initialization of registry entry 'test.recursive_init'"
+        );
+        // The failed initialization doesn't leave the entry locked.
+        assert_eq!(*get_or_init(&KEY, || 2).unwrap(), 2);
+        remove(&KEY);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn failed_init() {
+        static KEY: Key<i32> = Key::new("test.failed_init");
+        let e = get_or_try_init(&KEY, || Err(Error::other("oops"))).unwrap_err();
+        assert_eq!(e.to_string(), "oops");
+        assert!(get(&KEY).is_none());
+        assert_eq!(*get_or_try_init(&KEY, || Ok::<_, Error>(3)).unwrap(), 3);
+        remove(&KEY);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn set_and_reload() {
+        static KEY: Key<i32> = Key::new("test.set_and_reload");
+        assert!(set(&KEY, 1).unwrap().is_none());
+        let old = set(&KEY, 2).unwrap().unwrap();
+        assert_eq!(*old, 1);
+        assert_eq!(*get(&KEY).unwrap(), 2);
+
+        let seen = Rc::new(Cell::new(None));
+        on_reload({
+            let seen = seen.clone();
+            move || seen.set(get(&KEY).map(|v| *v))
+        });
+        reload();
+        assert_eq!(seen.get(), Some(2));
+        assert!(get(&KEY).is_none());
+
+        // Hooks are only called once.
+        set(&KEY, 3).unwrap();
+        reload();
+        assert_eq!(seen.get(), Some(2));
+        assert!(get(&KEY).is_none());
+    }
+}
@@ -0,0 +1,243 @@
+//! Scans of the indexes with filtering of the tuples.
+//!
+//! A [`Scan`] iterates over an index the same way `space:pairs()` does and
+//! only returns the tuples which pass the filter. The filter can be either a
+//! rust closure (see [`Scan::filter`]) or a lua expression (see
+//! [`Scan::filter_lua`]), which is compiled once and then called for each of
+//! the tuples.
+//!
+//! Which one is faster depends on the filter: a lua expression accesses the
+//! tuple fields without decoding them, while a rust closure avoids the
+//! overhead of calling into lua. The `full_scan` benchmarks in the
+//! `perf-test` crate compare both kinds of filters on a numeric and a string
+//! field, run them with `make bench` to get the numbers for your setup.
+//!
+//! ```no_run
+//! use tarantool::space::Space;
+//!
+//! let space = Space::find("orders").unwrap();
+//!
+//! let big_orders = space.scan().filter_lua("tuple[2] > 100").unwrap();
+//! for tuple in big_orders.iter().unwrap() {
+//!     let tuple = tuple.unwrap();
+//!     println!("{:?}", tuple);
+//! }
+//!
+//! let big_orders = space
+//!     .scan()
+//!     .filter(|tuple| tuple.get::<_, u64>(1).map_or(false, |v| v > 100));
+//! let count = big_orders.iter().unwrap().count();
+//! ```
+
+use crate::error::Error;
+use crate::index::{Index, IndexIterator, IteratorType};
+use crate::tuple::{ToTupleBuffer, Tuple, TupleBuffer};
+
+/// A lua expression compiled into a function of `tuple`.
+pub struct LuaFilter {
+    f: tlua::LuaFunction<tlua::PushGuard<tlua::LuaThread>>,
+}
+
+impl LuaFilter {
+    /// Compiles `expr`, in which the tuple is accessible as `tuple`, e.g.
+    /// `"tuple[2] > 100 and tuple.name ~= 'admin'"`.
+    pub fn new(expr: &str) -> crate::Result<Self> {
+        let code = format!("local tuple = ...\nreturn not not ({}\n)", expr);
+        let f = tlua::LuaFunction::load(crate::lua_state(), &code)?;
+        Ok(Self { f })
+    }
+
+    /// Returns `true` if the expression is truthy for `tuple`.
+    #[inline]
+    pub fn matches(&self, tuple: &Tuple) -> crate::Result<bool> {
+        let res = self.f.call_with_args(tuple).map_err(tlua::LuaError::from)?;
+        Ok(res)
+    }
+}
+
+impl std::fmt::Debug for LuaFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LuaFilter").finish_non_exhaustive()
+    }
+}
+
+enum Filter {
+    None,
+    Lua(LuaFilter),
+    Rust(Box<dyn FnMut(&Tuple) -> bool>),
+}
+
+/// A scan of an index, see the [module level documentation](self).
+pub struct Scan {
+    index: Index,
+    iterator_type: IteratorType,
+    key: TupleBuffer,
+    filter: Filter,
+    limit: Option<usize>,
+}
+
+impl Scan {
+    /// Creates a scan of all of the tuples in `index`.
+    #[inline]
+    pub fn new(index: Index) -> Self {
+        Self {
+            index,
+            iterator_type: IteratorType::All,
+            // An empty msgpack array.
+            key: unsafe { TupleBuffer::from_vec_unchecked(vec![0x90]) },
+            filter: Filter::None,
+            limit: None,
+        }
+    }
+
+    /// Scans the tuples matching `key` according to `iterator_type`, the
+    /// same way `index:pairs(key, {iterator = iterator_type})` does.
+    #[inline]
+    pub fn range<K>(mut self, iterator_type: IteratorType, key: &K) -> crate::Result<Self>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        self.iterator_type = iterator_type;
+        self.key = key.to_tuple_buffer()?;
+        Ok(self)
+    }
+
+    /// Only returns the tuples for which `expr` is truthy, see
+    /// [`LuaFilter::new`]. Replaces the previously set filter.
+    ///
+    /// Returns an error if `expr` isn't a valid lua expression.
+    #[inline]
+    pub fn filter_lua(mut self, expr: &str) -> crate::Result<Self> {
+        self.filter = Filter::Lua(LuaFilter::new(expr)?);
+        Ok(self)
+    }
+
+    /// Only returns the tuples for which `f` returns `true`. Replaces the
+    /// previously set filter.
+    #[inline]
+    pub fn filter(mut self, f: impl FnMut(&Tuple) -> bool + 'static) -> Self {
+        self.filter = Filter::Rust(Box::new(f));
+        self
+    }
+
+    /// Stops the scan after `limit` tuples are returned.
+    #[inline(always)]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Starts the scan.
+    ///
+    /// The iterator returns an error if the lua filter fails, in which case
+    /// the scan can be continued.
+    pub fn iter(self) -> crate::Result<ScanIter> {
+        let iter = self.index.select(self.iterator_type, &self.key)?;
+        Ok(ScanIter {
+            iter,
+            filter: self.filter,
+            remaining: self.limit,
+        })
+    }
+}
+
+impl std::fmt::Debug for Scan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scan")
+            .field("index", &self.index)
+            .field("iterator_type", &self.iterator_type)
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Iterator over the filtered tuples, see [`Scan::iter`].
+pub struct ScanIter {
+    iter: IndexIterator,
+    filter: Filter,
+    remaining: Option<usize>,
+}
+
+impl Iterator for ScanIter {
+    type Item = Result<Tuple, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        loop {
+            let tuple = self.iter.next()?;
+            let matches = match &mut self.filter {
+                Filter::None => true,
+                Filter::Lua(f) => match f.matches(&tuple) {
+                    Ok(matches) => matches,
+                    Err(e) => return Some(Err(e)),
+                },
+                Filter::Rust(f) => f(&tuple),
+            };
+            if matches {
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+                return Some(Ok(tuple));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::space::{FieldType, Space};
+
+    fn create_space() -> Space {
+        let space = Space::builder(&crate::temp_space_name!())
+            .field(("id", FieldType::Unsigned))
+            .field(("value", FieldType::Unsigned))
+            .create()
+            .unwrap();
+        space.index_builder("pk").create().unwrap();
+        for id in 0..10 {
+            space.insert(&(id, id * 10)).unwrap();
+        }
+        space
+    }
+
+    fn ids(scan: Scan) -> Vec<u32> {
+        scan.iter()
+            .unwrap()
+            .map(|t| t.unwrap().get(0).unwrap())
+            .collect()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn filters() {
+        let space = create_space();
+
+        assert_eq!(ids(space.scan()), (0..10).collect::<Vec<_>>());
+        let scan = space.scan().filter_lua("tuple[2] > 60").unwrap();
+        assert_eq!(ids(scan), [7, 8, 9]);
+        let scan = space.scan().filter_lua("tuple.value % 20 == 0").unwrap();
+        assert_eq!(ids(scan.limit(2)), [0, 2]);
+        let scan = space
+            .scan()
+            .range(IteratorType::LT, &(5,))
+            .unwrap()
+            .filter(|t| t.get::<_, u32>(1).unwrap() > 10);
+        assert_eq!(ids(scan), [4, 3, 2]);
+
+        let e = space.scan().filter_lua("tuple[2] >").unwrap_err();
+        assert!(e.to_string().contains("unexpected symbol"), "{}", e);
+
+        let mut iter = space
+            .scan()
+            .filter_lua("tuple[2] > nil")
+            .unwrap()
+            .iter()
+            .unwrap();
+        let e = iter.next().unwrap().unwrap_err();
+        assert!(e.to_string().contains("attempt to compare"), "{}", e);
+
+        space.drop().unwrap();
+    }
+}
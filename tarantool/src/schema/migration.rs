@@ -0,0 +1,574 @@
+//! Versioned schema migrations.
+//!
+//! The migrations are declared in rust as an ordered list of [`Migration`]s,
+//! each of which has a unique version. The versions of the applied migrations
+//! are recorded in a dedicated space (`_rust_migrations` by default), so that
+//! calling [`Migrations::run`] on startup only applies the pending ones.
+//!
+//! Each migration is applied in a transaction along with the record of its
+//! version, so either both the changes and the record are committed or
+//! neither of them is. Before applying a migration the last applied version
+//! is checked to be the version of the previous migration in the list (a
+//! compare-and-set guard), so if several instances or fibers try to apply the
+//! migrations at the same time, each migration is only applied once and the
+//! others see it as already applied.
+//!
+//! Note that a transaction in tarantool can't contain yields, so a migration
+//! which yields (e.g. builds an index on a non-empty space or backfills a lot
+//! of data in batches) must be marked as [`Migration::non_transactional`]. In
+//! that case the version is first recorded as "in progress", then the
+//! migration is applied and only then the record is marked as done.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::index::{IndexOptions, Part};
+//! use tarantool::schema::migration::{Migration, Migrations};
+//! use tarantool::space::{Field, Space};
+//!
+//! Migrations::new()
+//!     .with(Migration::new(1, "create users", || {
+//!         Space::builder("users")
+//!             .field(Field::unsigned("id"))
+//!             .field(Field::string("name"))
+//!             .index("pk", |i| i.part("id"))
+//!             .create()?;
+//!         Ok(())
+//!     }))
+//!     .with(Migration::alter_format(
+//!         2,
+//!         "add users.email",
+//!         "users",
+//!         vec![
+//!             Field::unsigned("id"),
+//!             Field::string("name"),
+//!             Field::string("email").is_nullable(true),
+//!         ],
+//!     ))
+//!     .with(Migration::create_index(
+//!         3,
+//!         "index users by name",
+//!         "users",
+//!         "name",
+//!         IndexOptions {
+//!             parts: Some(vec![Part::field("name")]),
+//!             ..Default::default()
+//!         },
+//!     ))
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BoxError, Error, TarantoolErrorCode};
+use crate::index::{IndexOptions, IteratorType};
+use crate::space::{Field, Space, UpdateOps};
+use crate::transaction;
+use crate::tuple::Encode;
+
+/// Name of the space in which the applied migrations are recorded by default.
+pub const DEFAULT_SPACE_NAME: &str = "_rust_migrations";
+
+/// Index of the `done` field in the tuples of the migrations space.
+const DONE_FIELD: u32 = 3;
+
+////////////////////////////////////////////////////////////////////////////////
+// Migration
+////////////////////////////////////////////////////////////////////////////////
+
+/// A single migration, see the [module level documentation](self).
+pub struct Migration {
+    version: u64,
+    name: String,
+    transactional: bool,
+    apply: Box<dyn Fn() -> Result<(), Error>>,
+}
+
+impl Migration {
+    /// Creates a migration which calls `f` when applied. `f` can modify the
+    /// schema as well as the data.
+    ///
+    /// `version` must be unique and greater than the versions of all of the
+    /// previous migrations.
+    pub fn new<F>(version: u64, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn() -> Result<(), Error> + 'static,
+    {
+        Self {
+            version,
+            name: name.into(),
+            transactional: true,
+            apply: Box::new(f),
+        }
+    }
+
+    /// Creates a migration which changes the format of the space named
+    /// `space` to `format`.
+    ///
+    /// Checking the existing tuples against the new format may yield if the
+    /// space is large, in which case the migration should be marked as
+    /// [non-transactional](Self::non_transactional).
+    pub fn alter_format(
+        version: u64,
+        name: impl Into<String>,
+        space: impl Into<String>,
+        format: Vec<Field>,
+    ) -> Self {
+        let space = space.into();
+        Self::new(version, name, move || {
+            let space = find_space(&space)?;
+            super::space::alter_space_format(space.id(), &format)
+        })
+    }
+
+    /// Creates a migration which creates an index named `index` in the space
+    /// named `space`.
+    ///
+    /// The migration is [non-transactional](Self::non_transactional), because
+    /// building an index on a non-empty space yields.
+    pub fn create_index(
+        version: u64,
+        name: impl Into<String>,
+        space: impl Into<String>,
+        index: impl Into<String>,
+        opts: IndexOptions,
+    ) -> Self {
+        let space = space.into();
+        let index = index.into();
+        Self::new(version, name, move || {
+            find_space(&space)?.create_index(&index, &opts)?;
+            Ok(())
+        })
+        .non_transactional()
+    }
+
+    /// Marks the migration as non-transactional, which is required if it
+    /// yields. See the [module level documentation](self) for details.
+    #[inline(always)]
+    pub fn non_transactional(mut self) -> Self {
+        self.transactional = false;
+        self
+    }
+
+    /// Returns the version of the migration.
+    #[inline(always)]
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the name of the migration.
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Debug for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migration")
+            .field("version", &self.version)
+            .field("name", &self.name)
+            .field("transactional", &self.transactional)
+            .finish_non_exhaustive()
+    }
+}
+
+fn find_space(name: &str) -> Result<Space, Error> {
+    Space::find(name).ok_or_else(|| BoxError::new(TarantoolErrorCode::NoSuchSpace, name).into())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// AppliedMigration
+////////////////////////////////////////////////////////////////////////////////
+
+/// A record of a migration stored in the migrations space.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: u64,
+    pub name: String,
+    /// Time at which the migration started to be applied in seconds since the
+    /// unix epoch.
+    pub applied_at: f64,
+    /// `false` if a [non-transactional](Migration::non_transactional)
+    /// migration is still being applied (or failed in the middle and must be
+    /// fixed up manually).
+    pub done: bool,
+}
+
+impl Encode for AppliedMigration {}
+
+////////////////////////////////////////////////////////////////////////////////
+// Migrations
+////////////////////////////////////////////////////////////////////////////////
+
+/// An ordered list of migrations, see the [module level documentation](self).
+#[derive(Debug)]
+pub struct Migrations {
+    space_name: String,
+    migrations: Vec<Migration>,
+}
+
+impl Default for Migrations {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrations {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            space_name: DEFAULT_SPACE_NAME.into(),
+            migrations: vec![],
+        }
+    }
+
+    /// Sets the name of the space in which the applied migrations are
+    /// recorded. [`DEFAULT_SPACE_NAME`] is used by default.
+    #[inline(always)]
+    pub fn space_name(mut self, space_name: impl Into<String>) -> Self {
+        self.space_name = space_name.into();
+        self
+    }
+
+    /// Appends a migration to the list.
+    #[inline(always)]
+    pub fn with(mut self, migration: Migration) -> Self {
+        self.migrations.push(migration);
+        self
+    }
+
+    /// Returns the records of the applied migrations ordered by version.
+    pub fn applied(&self) -> Result<Vec<AppliedMigration>, Error> {
+        let Some(space) = Space::find(&self.space_name) else {
+            return Ok(vec![]);
+        };
+        space
+            .select(IteratorType::All, &())?
+            .map(|t| t.decode())
+            .collect()
+    }
+
+    /// Returns the version of the last applied migration or `0` if no
+    /// migrations were applied.
+    pub fn applied_version(&self) -> Result<u64, Error> {
+        let Some(space) = Space::find(&self.space_name) else {
+            return Ok(0);
+        };
+        Ok(last_applied(&space)?.map_or(0, |m| m.version))
+    }
+
+    /// Applies the pending migrations in order, creating the migrations space
+    /// if needed. Returns the versions of the migrations applied by this call.
+    ///
+    /// Stops at the first error, in which case the failed migration's changes
+    /// are rolled back (unless it's
+    /// [non-transactional](Migration::non_transactional)) and the subsequent
+    /// migrations are not applied.
+    ///
+    /// Returns an error if the migrations are not ordered by version, if the
+    /// last applied version doesn't match the version of the migration
+    /// preceding a pending one or if a non-transactional migration is being
+    /// applied concurrently.
+    pub fn run(&self) -> Result<Vec<u64>, Error> {
+        for pair in self.migrations.windows(2) {
+            if pair[0].version >= pair[1].version {
+                return Err(BoxError::new(
+                    TarantoolErrorCode::IllegalParams,
+                    format!(
+                        "migrations must be ordered by version, but {} goes after {}",
+                        pair[1].version, pair[0].version
+                    ),
+                )
+                .into());
+            }
+        }
+
+        let space = self.create_space()?;
+        let mut applied = vec![];
+        let mut prev = None;
+        for migration in &self.migrations {
+            if apply(&space, migration, prev)? {
+                crate::say_info!(
+                    "applied migration {} '{}'",
+                    migration.version,
+                    migration.name
+                );
+                applied.push(migration.version);
+            }
+            prev = Some(migration.version);
+        }
+        Ok(applied)
+    }
+
+    fn create_space(&self) -> Result<Space, Error> {
+        Space::builder(&self.space_name)
+            .if_not_exists(true)
+            .field(Field::unsigned("version"))
+            .field(Field::string("name"))
+            .field(Field::double("applied_at"))
+            .field(Field::boolean("done"))
+            .index("pk", |i| i.part("version"))
+            .create()
+    }
+}
+
+fn last_applied(space: &Space) -> Result<Option<AppliedMigration>, Error> {
+    space
+        .primary_key()
+        .max(&())?
+        .map(|t| t.decode())
+        .transpose()
+}
+
+/// Checks if the `migration` must be applied. `prev` is the version of the
+/// previous migration in the list.
+fn is_pending(space: &Space, migration: &Migration, prev: Option<u64>) -> Result<bool, Error> {
+    if let Some(t) = space.get(&(migration.version,))? {
+        let record: AppliedMigration = t.decode()?;
+        if record.done {
+            return Ok(false);
+        }
+        return Err(in_progress(&record));
+    }
+
+    let Some(last) = last_applied(space)? else {
+        return Ok(true);
+    };
+    if !last.done {
+        return Err(in_progress(&last));
+    }
+    let expected = match prev {
+        Some(prev) => last.version == prev,
+        None => last.version < migration.version,
+    };
+    if !expected {
+        return Err(BoxError::new(
+            TarantoolErrorCode::IllegalParams,
+            format!(
+                "can't apply migration {} '{}': last applied migration is {} '{}'",
+                migration.version, migration.name, last.version, last.name
+            ),
+        )
+        .into());
+    }
+    Ok(true)
+}
+
+fn in_progress(record: &AppliedMigration) -> Error {
+    BoxError::new(
+        TarantoolErrorCode::TupleFound,
+        format!(
+            "migration {} '{}' is being applied concurrently or has failed",
+            record.version, record.name
+        ),
+    )
+    .into()
+}
+
+/// Applies the `migration` if it's pending. Returns `true` if it was applied.
+fn apply(space: &Space, migration: &Migration, prev: Option<u64>) -> Result<bool, Error> {
+    let applied_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    let record = AppliedMigration {
+        version: migration.version,
+        name: migration.name.clone(),
+        applied_at,
+        done: migration.transactional,
+    };
+
+    if migration.transactional {
+        return Ok(transaction::transaction(|| -> Result<bool, Error> {
+            if !is_pending(space, migration, prev)? {
+                return Ok(false);
+            }
+            (migration.apply)()?;
+            space.insert(&record)?;
+            Ok(true)
+        })?);
+    }
+
+    let pending = transaction::transaction(|| -> Result<bool, Error> {
+        if !is_pending(space, migration, prev)? {
+            return Ok(false);
+        }
+        space.insert(&record)?;
+        Ok(true)
+    })?;
+    if !pending {
+        return Ok(false);
+    }
+
+    if let Err(e) = (migration.apply)() {
+        if let Err(e) = space.delete(&(migration.version,)) {
+            crate::say_error!(
+                "failed to remove the record of migration {}: {}",
+                migration.version,
+                e
+            );
+        }
+        return Err(e);
+    }
+    let mut ops = UpdateOps::new();
+    ops.assign(DONE_FIELD, true)?;
+    space.update(&(migration.version,), ops)?;
+    Ok(true)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::index::Part;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[crate::test(tarantool = "crate")]
+    fn run_migrations() {
+        let migrations_space = crate::temp_space_name!();
+        let data_space = crate::temp_space_name!();
+        let backfills = Rc::new(Cell::new(0));
+
+        let migrations = || {
+            let backfills = backfills.clone();
+            let data_space_clone = data_space.clone();
+            Migrations::new()
+                .space_name(&migrations_space)
+                .with(Migration::new(1, "create space", {
+                    let data_space = data_space.clone();
+                    move || {
+                        Space::builder(&data_space)
+                            .field(Field::unsigned("id"))
+                            .field(Field::string("name"))
+                            .index("pk", |i| i.part("id"))
+                            .create()?;
+                        Ok(())
+                    }
+                }))
+                .with(Migration::alter_format(
+                    2,
+                    "add value",
+                    &data_space,
+                    vec![
+                        Field::unsigned("id"),
+                        Field::string("name"),
+                        Field::unsigned("value").is_nullable(true),
+                    ],
+                ))
+                .with(Migration::new(3, "backfill", move || {
+                    backfills.set(backfills.get() + 1);
+                    let space = Space::find(&data_space_clone).unwrap();
+                    space.insert(&(1, "foo", 10))?;
+                    space.insert(&(2, "bar", 20))?;
+                    Ok(())
+                }))
+        };
+
+        assert_eq!(migrations().applied_version().unwrap(), 0);
+        assert_eq!(migrations().run().unwrap(), [1, 2, 3]);
+        assert_eq!(migrations().applied_version().unwrap(), 3);
+        assert_eq!(backfills.get(), 1);
+
+        let data = Space::find(&data_space).unwrap();
+        assert_eq!(data.len().unwrap(), 2);
+        let format = data.meta().unwrap().format;
+        assert_eq!(format.len(), 3);
+
+        // Already applied migrations are skipped.
+        assert_eq!(migrations().run().unwrap(), Vec::<u64>::new());
+        assert_eq!(backfills.get(), 1);
+
+        // New migrations are applied.
+        let migrations = migrations().with(Migration::create_index(
+            4,
+            "index by name",
+            &data_space,
+            "name",
+            IndexOptions {
+                parts: Some(vec![Part::field("name")]),
+                ..Default::default()
+            },
+        ));
+        assert_eq!(migrations.run().unwrap(), [4]);
+        let by_name = data.index("name").unwrap();
+        let t = by_name.get(&("bar",)).unwrap().unwrap();
+        assert_eq!(t.field::<u64>(0).unwrap(), Some(2));
+
+        let applied = migrations.applied().unwrap();
+        let versions: Vec<_> = applied.iter().map(|m| m.version).collect();
+        assert_eq!(versions, [1, 2, 3, 4]);
+        assert!(applied.iter().all(|m| m.done));
+        assert_eq!(applied[3].name, "index by name");
+
+        data.drop().unwrap();
+        Space::find(&migrations_space).unwrap().drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn failed_migration_is_rolled_back() {
+        let migrations_space = crate::temp_space_name!();
+        let data = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        data.index_builder("pk").create().unwrap();
+
+        let migrations = Migrations::new()
+            .space_name(&migrations_space)
+            .with(Migration::new(1, "ok", || Ok(())))
+            .with(Migration::new(2, "fails", {
+                let data = data.clone();
+                move || {
+                    data.insert(&(1,))?;
+                    Err(BoxError::new(TarantoolErrorCode::ProcC, "oops").into())
+                }
+            }))
+            .with(Migration::new(3, "never applied", || Ok(())));
+        let e = migrations.run().unwrap_err();
+        assert_eq!(e.to_string(), "box error: ProcC: oops");
+        assert_eq!(migrations.applied_version().unwrap(), 1);
+        assert_eq!(data.select(IteratorType::All, &()).unwrap().count(), 0);
+
+        data.drop().unwrap();
+        Space::find(&migrations_space).unwrap().drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn out_of_order() {
+        let migrations_space = crate::temp_space_name!();
+
+        let e = Migrations::new()
+            .space_name(&migrations_space)
+            .with(Migration::new(2, "two", || Ok(())))
+            .with(Migration::new(1, "one", || Ok(())))
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IllegalParams: migrations must be ordered by version, but 1 goes after 2"
+        );
+
+        Migrations::new()
+            .space_name(&migrations_space)
+            .with(Migration::new(1, "one", || Ok(())))
+            .with(Migration::new(3, "three", || Ok(())))
+            .run()
+            .unwrap();
+
+        // Migration 2 was added between the already applied ones.
+        let e = Migrations::new()
+            .space_name(&migrations_space)
+            .with(Migration::new(1, "one", || Ok(())))
+            .with(Migration::new(2, "two", || Ok(())))
+            .with(Migration::new(3, "three", || Ok(())))
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IllegalParams: can't apply migration 2 'two': last applied migration is 3 'three'"
+        );
+
+        Space::find(&migrations_space).unwrap().drop().unwrap();
+    }
+}
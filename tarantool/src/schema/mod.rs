@@ -1,13 +1,19 @@
 #[cfg(feature = "picodata")]
 pub mod function;
 pub mod index;
+pub mod migration;
+pub mod role;
 pub mod sequence;
 pub mod space;
+pub mod user;
 
-use crate::error::Error;
+use crate::error::{BoxError, Error, IntoBoxError, TarantoolErrorCode};
+use crate::ffi::tarantool as ffi;
+use crate::fiber;
 use crate::index::IteratorType;
 use crate::space::{Space, SystemSpace};
 use crate::tuple::Tuple;
+use std::time::Duration;
 
 fn resolve_user_or_role(user: &str) -> Result<Option<u32>, Error> {
     let space_vuser: Space = SystemSpace::VUser.into();
@@ -38,3 +44,300 @@ fn revoke_object_privileges(obj_type: &str, obj_id: u32) -> Result<(), Error> {
 
     Ok(())
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// privileges
+////////////////////////////////////////////////////////////////////////////////
+
+crate::define_str_enum! {
+    /// A privilege which can be granted to a user or a role, see [`user::grant`]
+    /// and [`role::grant`].
+    ///
+    /// See also [access control](https://www.tarantool.io/en/doc/latest/admin/access_control/#privileges).
+    pub enum Privilege {
+        /// Allows reading data of the object (e.g. `select` from a space).
+        Read = "read",
+        /// Allows modifying data of the object (e.g. `insert` into a space).
+        Write = "write",
+        /// Allows calling a function or using a role.
+        Execute = "execute",
+        /// Allows connecting to the instance.
+        Session = "session",
+        /// Allows using the granted privileges.
+        Usage = "usage",
+        /// Allows creating objects.
+        Create = "create",
+        /// Allows dropping objects.
+        Drop = "drop",
+        /// Allows altering objects.
+        Alter = "alter",
+    }
+}
+
+crate::define_str_enum! {
+    /// Type of an object on which the privileges are granted, see
+    /// [`user::grant`] and [`role::grant`].
+    pub enum ObjectType {
+        /// The whole database, object name must be `None`.
+        Universe = "universe",
+        Space = "space",
+        Function = "function",
+        Sequence = "sequence",
+        /// A role, only [`Privilege::Execute`] can be granted on it.
+        Role = "role",
+        User = "user",
+    }
+}
+
+/// Options for [`user::grant`] and [`role::grant`].
+#[derive(Clone, Debug, Default, tlua::Push)]
+pub struct GrantOptions {
+    /// Don't return an error if the privilege is already granted.
+    pub if_not_exists: bool,
+    /// Name of the user on whose behalf the privilege is granted. The current
+    /// user is used by default.
+    pub grantor: Option<String>,
+}
+
+/// Options for [`user::revoke`] and [`role::revoke`].
+#[derive(Clone, Debug, Default, tlua::Push)]
+pub struct RevokeOptions {
+    /// Don't return an error if the privilege isn't granted.
+    pub if_exists: bool,
+}
+
+/// Returns privileges joined into a string accepted by
+/// `box.schema.{user,role}.{grant,revoke}`.
+fn privileges_to_string(privileges: &[Privilege]) -> String {
+    let privileges: Vec<_> = privileges.iter().map(Privilege::as_str).collect();
+    privileges.join(",")
+}
+
+/// Calls `box.schema.{entity}.grant` (`entity` is either "user" or "role").
+fn grant_impl(
+    entity: &str,
+    name: &str,
+    privileges: &[Privilege],
+    object_type: ObjectType,
+    object_name: Option<&str>,
+    opts: &GrantOptions,
+) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with(
+            "local entity, name, privileges, object_type, object_name, opts = ...
+            box.schema[entity].grant(name, privileges, object_type, object_name, opts)",
+            (
+                entity,
+                name,
+                privileges_to_string(privileges),
+                object_type,
+                object_name,
+                opts,
+            ),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Calls `box.schema.{entity}.revoke` (`entity` is either "user" or "role").
+fn revoke_impl(
+    entity: &str,
+    name: &str,
+    privileges: &[Privilege],
+    object_type: ObjectType,
+    object_name: Option<&str>,
+    opts: &RevokeOptions,
+) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with(
+            "local entity, name, privileges, object_type, object_name, opts = ...
+            box.schema[entity].revoke(name, privileges, object_type, object_name, opts)",
+            (
+                entity,
+                name,
+                privileges_to_string(privileges),
+                object_type,
+                object_name,
+                opts,
+            ),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// schema version
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the current version of the database schema.
+///
+/// The version is incremented by tarantool every time a DDL operation is
+/// committed (space, index, user, function, etc. is created, altered or
+/// dropped).
+#[inline(always)]
+pub fn version() -> u64 {
+    unsafe { ffi::box_schema_version() }
+}
+
+/// How often [`wait_version`] checks the schema version.
+const WAIT_VERSION_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Block the current fiber until the schema version becomes greater than or
+/// equal to `version`, or until `timeout` expires.
+///
+/// Returns the actual schema version on success. Returns a
+/// [`TarantoolErrorCode::Timeout`] error if the timeout expires first.
+///
+/// > **Note:** this is a cancellation point. If the fiber is cancelled while
+/// > waiting, the function returns an error.
+pub fn wait_version(version: u64, timeout: Duration) -> Result<u64, Error> {
+    let deadline = fiber::clock().saturating_add(timeout);
+    loop {
+        let current = self::version();
+        if current >= version {
+            return Ok(current);
+        }
+
+        let now = fiber::clock();
+        if now >= deadline {
+            return Err(BoxError::new(
+                TarantoolErrorCode::Timeout,
+                format!("timed out waiting for schema version {version}, current is {current}"),
+            )
+            .into());
+        }
+
+        fiber::sleep(deadline.duration_since(now).min(WAIT_VERSION_POLL_INTERVAL));
+        if fiber::is_cancelled() {
+            return Err(BoxError::new(TarantoolErrorCode::ProcLua, "fiber is cancelled").into());
+        }
+    }
+}
+
+/// Maximum number of attempts made by [`retry_on_schema_change`].
+pub const SCHEMA_CHANGE_MAX_ATTEMPTS: u32 = 8;
+
+/// Execute `f` and re-execute it if it failed because of a concurrent schema
+/// change (DDL).
+///
+/// An error is considered to be caused by a concurrent schema change if
+/// either
+/// - it has one of the error codes tarantool uses to report schema races
+///   (see [`is_schema_change_error`]), or
+/// - the schema version has changed while `f` was executing.
+///
+/// The operation is attempted at most [`SCHEMA_CHANGE_MAX_ATTEMPTS`] times,
+/// after which the last error is returned. Any other error is returned
+/// immediately.
+///
+/// Note that `f` may be called several times, so it must be safe to retry
+/// (e.g. it should either be idempotent or execute within a transaction).
+///
+/// # Example
+/// ```no_run
+/// use tarantool::schema::retry_on_schema_change;
+/// use tarantool::space::Space;
+///
+/// let len = retry_on_schema_change(|| {
+///     let space = Space::find("users").expect("space exists");
+///     space.len()
+/// })
+/// .unwrap();
+/// ```
+pub fn retry_on_schema_change<T, F>(mut f: F) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let mut attempt = 1;
+    loop {
+        let version_before = version();
+        let e = match f() {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        };
+
+        let schema_changed = version() != version_before;
+        let is_tarantool_error = matches!(e, Error::Tarantool(_));
+        let should_retry = is_schema_change_error(&e) || (schema_changed && is_tarantool_error);
+        if !should_retry || attempt >= SCHEMA_CHANGE_MAX_ATTEMPTS {
+            return Err(e);
+        }
+
+        crate::say_verbose!(
+            "retrying operation after concurrent schema change (attempt {attempt}): {e}"
+        );
+        attempt += 1;
+        fiber::reschedule();
+    }
+}
+
+/// Returns `true` if `error` has one of the error codes tarantool uses to
+/// report that an operation has raced with a concurrent schema change.
+pub fn is_schema_change_error(error: &Error) -> bool {
+    let code = match error {
+        Error::Tarantool(_) | Error::Remote(_) => error.error_code(),
+        _ => return false,
+    };
+    code == TarantoolErrorCode::WrongSchemaVersion as u32
+        || code == TarantoolErrorCode::SchemaUpdateInProgress as u32
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn version_changes_on_ddl() {
+        let before = version();
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        let after = version();
+        assert!(after > before);
+
+        assert_eq!(wait_version(after, Duration::ZERO).unwrap(), after);
+
+        let e = wait_version(after + 1000, Duration::from_millis(30)).unwrap_err();
+        assert_eq!(e.error_code(), TarantoolErrorCode::Timeout as u32);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn wait_version_wakes_up_on_ddl() {
+        let target = version() + 1;
+        let space_name = crate::temp_space_name!();
+        let jh = fiber::start(|| wait_version(target, Duration::from_secs(5)));
+        let space = Space::builder(&space_name).create().unwrap();
+        assert!(jh.join().unwrap() >= target);
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn retry_on_schema_change_retries() {
+        let mut attempts = 0;
+        let res = retry_on_schema_change(|| {
+            attempts += 1;
+            if attempts < 3 {
+                return Err(BoxError::new(TarantoolErrorCode::WrongSchemaVersion, "boo").into());
+            }
+            Ok(attempts)
+        });
+        assert_eq!(res.unwrap(), 3);
+
+        let mut attempts = 0;
+        let res: Result<(), _> = retry_on_schema_change(|| {
+            attempts += 1;
+            Err(BoxError::new(TarantoolErrorCode::SchemaUpdateInProgress, "boo").into())
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, SCHEMA_CHANGE_MAX_ATTEMPTS);
+
+        // Other errors are not retried.
+        let mut attempts = 0;
+        let res: Result<(), _> = retry_on_schema_change(|| {
+            attempts += 1;
+            Err(BoxError::new(TarantoolErrorCode::NoSuchSpace, "boo").into())
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
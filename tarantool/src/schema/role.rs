@@ -0,0 +1,107 @@
+//! Managing roles.
+//!
+//! This is a typed wrapper over the `box.schema.role.*` Lua API, see
+//! [box.schema.role reference](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_schema/role_create/).
+
+use super::{GrantOptions, ObjectType, Privilege, RevokeOptions};
+use crate::error::Error;
+
+/// Options for [`create`].
+#[derive(Clone, Debug, Default, tlua::Push)]
+pub struct CreateOptions {
+    /// Don't return an error if the role already exists.
+    pub if_not_exists: bool,
+}
+
+/// Options for [`drop`].
+#[derive(Clone, Debug, Default, tlua::Push)]
+pub struct DropOptions {
+    /// Don't return an error if the role doesn't exist.
+    pub if_exists: bool,
+}
+
+/// Creates a role named `name`.
+pub fn create(name: &str, opts: &CreateOptions) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with("box.schema.role.create(...)", (name, opts))
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Drops the role named `name`.
+pub fn drop(name: &str, opts: &DropOptions) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with("box.schema.role.drop(...)", (name, opts))
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Returns `true` if a role named `name` exists.
+///
+/// Note that this returns `false` for users, see [`user::exists`].
+///
+/// [`user::exists`]: super::user::exists
+pub fn exists(name: &str) -> Result<bool, Error> {
+    let exists = crate::lua_state()
+        .eval_with("return box.schema.role.exists(...)", name)
+        .map_err(tlua::LuaError::from)?;
+    Ok(exists)
+}
+
+/// Grants `privileges` on the object of type `object_type` named
+/// `object_name` to the role named `name`.
+///
+/// See [`user::grant`] for details.
+///
+/// [`user::grant`]: super::user::grant
+#[inline(always)]
+pub fn grant(
+    name: &str,
+    privileges: &[Privilege],
+    object_type: ObjectType,
+    object_name: Option<&str>,
+    opts: &GrantOptions,
+) -> Result<(), Error> {
+    super::grant_impl("role", name, privileges, object_type, object_name, opts)
+}
+
+/// Revokes `privileges` on the object of type `object_type` named
+/// `object_name` from the role named `name`.
+///
+/// See [`user::grant`] for details.
+///
+/// [`user::grant`]: super::user::grant
+#[inline(always)]
+pub fn revoke(
+    name: &str,
+    privileges: &[Privilege],
+    object_type: ObjectType,
+    object_name: Option<&str>,
+    opts: &RevokeOptions,
+) -> Result<(), Error> {
+    super::revoke_impl("role", name, privileges, object_type, object_name, opts)
+}
+
+/// Grants the role named `role` to the role named `name`.
+#[inline(always)]
+pub fn grant_role(name: &str, role: &str, opts: &GrantOptions) -> Result<(), Error> {
+    grant(
+        name,
+        &[Privilege::Execute],
+        ObjectType::Role,
+        Some(role),
+        opts,
+    )
+}
+
+/// Revokes the role named `role` from the role named `name`.
+#[inline(always)]
+pub fn revoke_role(name: &str, role: &str, opts: &RevokeOptions) -> Result<(), Error> {
+    revoke(
+        name,
+        &[Privilege::Execute],
+        ObjectType::Role,
+        Some(role),
+        opts,
+    )
+}
@@ -1,17 +1,18 @@
 use crate::error::{Error, TarantoolError, TarantoolErrorCode};
-use crate::index::IteratorType;
+use crate::index::{Index, IteratorType};
 use crate::schema;
 use crate::schema::sequence as schema_seq;
 use crate::session;
 use crate::set_error;
 use crate::space;
 use crate::space::space_id_temporary_min;
-use crate::space::{Metadata, SpaceCreateOptions};
+use crate::space::{Field, Metadata, SpaceCreateOptions, SpaceEngineType};
 use crate::space::{Space, SpaceId, SpaceType, SystemSpace};
 use crate::transaction;
-use crate::tuple::Tuple;
+use crate::tuple::{Encode, Tuple};
 use crate::unwrap_or;
 use crate::util::Value;
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 
 /// Create a space.
@@ -81,15 +82,8 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
         .format
         .iter()
         .flat_map(|f| f.iter())
-        .map(|f| {
-            IntoIterator::into_iter([
-                ("name".into(), Value::Str(f.name.as_str().into())),
-                ("type".into(), Value::Str(f.field_type.as_str().into())),
-                ("is_nullable".into(), Value::Bool(f.is_nullable)),
-            ])
-            .collect()
-        })
-        .collect();
+        .map(|f| encode_field(f, name, id))
+        .collect::<Result<_, _>>()?;
 
     let nested_transaction = transaction::is_in_transaction();
     if !nested_transaction {
@@ -98,10 +92,10 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
 
     let res = (|| -> Result<_, Error> {
         let sys_space = SystemSpace::Space.as_space();
-        sys_space.insert(&Metadata {
+        sys_space.insert(&SpaceDef {
             id,
             user_id,
-            name: name.into(),
+            name,
             engine: opts.engine,
             field_count: opts.field_count,
             flags,
@@ -144,6 +138,75 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
     Ok(space)
 }
 
+/// Same as [`Metadata`], but allows nested values in the space format, which
+/// are needed for field constraints and foreign keys.
+#[derive(serde::Serialize)]
+struct SpaceDef<'a> {
+    id: SpaceId,
+    user_id: u32,
+    name: &'a str,
+    engine: SpaceEngineType,
+    field_count: u32,
+    flags: BTreeMap<Cow<'a, str>, Value<'a>>,
+    format: Vec<rmpv::Value>,
+}
+impl Encode for SpaceDef<'_> {}
+
+/// Converts the field definition into the representation expected by the
+/// `_space` system space, resolving the names of the constraint functions and
+/// the referenced spaces into ids.
+///
+/// - `space_name`, `space_id` - name and id of the space being created, which
+///   can be referenced by the field's foreign keys.
+fn encode_field(field: &Field, space_name: &str, space_id: SpaceId) -> Result<rmpv::Value, Error> {
+    let mut res = vec![
+        ("name".into(), field.name.as_str().into()),
+        ("type".into(), field.field_type.as_str().into()),
+        ("is_nullable".into(), field.is_nullable.into()),
+    ];
+
+    if let Some(constraint) = &field.constraint {
+        let mut constraints = Vec::with_capacity(constraint.len());
+        for (name, func) in constraint {
+            let func_index = Index::new(SystemSpace::Func as _, FUNC_NAME_INDEX_ID);
+            let func_id: u32 = match func_index.get(&(func,))? {
+                Some(t) => t.field(0)?.expect("function id should always be present"),
+                None => {
+                    set_error!(TarantoolErrorCode::NoSuchFunction, "{}", func);
+                    return Err(TarantoolError::last().into());
+                }
+            };
+            constraints.push((name.as_str().into(), func_id.into()));
+        }
+        res.push(("constraint".into(), rmpv::Value::Map(constraints)));
+    }
+
+    if let Some(foreign_key) = &field.foreign_key {
+        let mut foreign_keys = Vec::with_capacity(foreign_key.len());
+        for (name, fk) in foreign_key {
+            let fk_space_id = if fk.space == space_name {
+                space_id
+            } else if let Some(space) = Space::find(&fk.space) {
+                space.id()
+            } else {
+                set_error!(TarantoolErrorCode::NoSuchSpace, "{}", fk.space);
+                return Err(TarantoolError::last().into());
+            };
+            let fk = rmpv::Value::Map(vec![
+                ("space".into(), fk_space_id.into()),
+                ("field".into(), fk.field.as_str().into()),
+            ]);
+            foreign_keys.push((name.as_str().into(), fk));
+        }
+        res.push(("foreign_key".into(), rmpv::Value::Map(foreign_keys)));
+    }
+
+    Ok(rmpv::Value::Map(res))
+}
+
+/// Id of the `name` index of the `_func` system space.
+const FUNC_NAME_INDEX_ID: u32 = 2;
+
 #[deprecated = "use `tarantool::space::Metadata` instead"]
 pub type SpaceMetadata<'a> = Metadata<'a>;
 
@@ -212,6 +275,25 @@ pub fn space_metadata(space_id: SpaceId) -> Result<Metadata<'static>, Error> {
     tuple.decode::<Metadata>()
 }
 
+/// Index of the `format` field in the tuples of the `_space` system space.
+const SPACE_FORMAT_FIELD: u32 = 6;
+
+/// Change the format of an existing space
+/// (for details see [space_object:format()](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/format/)).
+///
+/// Returns an error if the existing tuples don't conform to the new format.
+pub fn alter_space_format(space_id: SpaceId, format: &[Field]) -> Result<(), Error> {
+    let meta = space_metadata(space_id)?;
+    let format = format
+        .iter()
+        .map(|field| encode_field(field, &meta.name, space_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut ops = space::UpdateOps::new();
+    ops.assign(SPACE_FORMAT_FIELD, format)?;
+    SystemSpace::Space.as_space().update(&(space_id,), ops)?;
+    Ok(())
+}
+
 /// Drop a space.
 pub fn drop_space(space_id: SpaceId) -> Result<(), Error> {
     // Delete automatically generated sequence.
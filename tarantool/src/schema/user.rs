@@ -0,0 +1,217 @@
+//! Managing users.
+//!
+//! This is a typed wrapper over the `box.schema.user.*` Lua API, see
+//! [box.schema.user reference](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_schema/user_create/).
+
+use super::{GrantOptions, ObjectType, Privilege, RevokeOptions};
+use crate::error::Error;
+
+/// Options for [`create`].
+#[derive(Clone, Debug, Default, tlua::Push)]
+pub struct CreateOptions {
+    /// Don't return an error if the user already exists.
+    pub if_not_exists: bool,
+    /// Password of the new user. A user without a password can't be
+    /// authenticated via the network.
+    pub password: Option<String>,
+}
+
+/// Options for [`drop`].
+#[derive(Clone, Debug, Default, tlua::Push)]
+pub struct DropOptions {
+    /// Don't return an error if the user doesn't exist.
+    pub if_exists: bool,
+}
+
+/// Creates a user named `name`.
+pub fn create(name: &str, opts: &CreateOptions) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with("box.schema.user.create(...)", (name, opts))
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Drops the user named `name` along with all of the objects it owns.
+pub fn drop(name: &str, opts: &DropOptions) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with("box.schema.user.drop(...)", (name, opts))
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Returns `true` if a user named `name` exists.
+///
+/// Note that this returns `false` for roles, see [`role::exists`].
+///
+/// [`role::exists`]: super::role::exists
+pub fn exists(name: &str) -> Result<bool, Error> {
+    let exists = crate::lua_state()
+        .eval_with("return box.schema.user.exists(...)", name)
+        .map_err(tlua::LuaError::from)?;
+    Ok(exists)
+}
+
+/// Changes the password of the user named `name`.
+pub fn passwd(name: &str, password: &str) -> Result<(), Error> {
+    crate::lua_state()
+        .exec_with("box.schema.user.passwd(...)", (name, password))
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Grants `privileges` on the object of type `object_type` named
+/// `object_name` to the user named `name`.
+///
+/// If `object_name` is `None` the privileges are granted on all objects of
+/// the given type (must be `None` for [`ObjectType::Universe`]).
+///
+/// ```no_run
+/// use tarantool::schema::{user, GrantOptions, ObjectType, Privilege};
+///
+/// user::grant(
+///     "alice",
+///     &[Privilege::Read, Privilege::Write],
+///     ObjectType::Space,
+///     Some("accounts"),
+///     &GrantOptions::default(),
+/// )
+/// .unwrap();
+/// ```
+#[inline(always)]
+pub fn grant(
+    name: &str,
+    privileges: &[Privilege],
+    object_type: ObjectType,
+    object_name: Option<&str>,
+    opts: &GrantOptions,
+) -> Result<(), Error> {
+    super::grant_impl("user", name, privileges, object_type, object_name, opts)
+}
+
+/// Revokes `privileges` on the object of type `object_type` named
+/// `object_name` from the user named `name`.
+///
+/// See [`grant`] for details.
+#[inline(always)]
+pub fn revoke(
+    name: &str,
+    privileges: &[Privilege],
+    object_type: ObjectType,
+    object_name: Option<&str>,
+    opts: &RevokeOptions,
+) -> Result<(), Error> {
+    super::revoke_impl("user", name, privileges, object_type, object_name, opts)
+}
+
+/// Grants the role named `role` to the user named `name`.
+#[inline(always)]
+pub fn grant_role(name: &str, role: &str, opts: &GrantOptions) -> Result<(), Error> {
+    grant(
+        name,
+        &[Privilege::Execute],
+        ObjectType::Role,
+        Some(role),
+        opts,
+    )
+}
+
+/// Revokes the role named `role` from the user named `name`.
+#[inline(always)]
+pub fn revoke_role(name: &str, role: &str, opts: &RevokeOptions) -> Result<(), Error> {
+    revoke(
+        name,
+        &[Privilege::Execute],
+        ObjectType::Role,
+        Some(role),
+        opts,
+    )
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::schema::role;
+
+    #[crate::test(tarantool = "crate")]
+    fn users_and_roles() {
+        let opts = CreateOptions {
+            password: Some("secret".into()),
+            ..Default::default()
+        };
+        create("test_user", &opts).unwrap();
+        assert!(exists("test_user").unwrap());
+        assert!(create("test_user", &opts).is_err());
+        let opts = CreateOptions {
+            if_not_exists: true,
+            ..Default::default()
+        };
+        create("test_user", &opts).unwrap();
+        passwd("test_user", "new secret").unwrap();
+
+        role::create("test_role", &Default::default()).unwrap();
+        assert!(role::exists("test_role").unwrap());
+        assert!(!exists("test_role").unwrap());
+        assert!(!role::exists("test_user").unwrap());
+
+        let privileges = [Privilege::Read, Privilege::Write];
+        role::grant(
+            "test_role",
+            &privileges,
+            ObjectType::Space,
+            Some("_space"),
+            &Default::default(),
+        )
+        .unwrap();
+        grant_role("test_user", "test_role", &Default::default()).unwrap();
+        // Already granted.
+        assert!(grant_role("test_user", "test_role", &Default::default()).is_err());
+        let opts = GrantOptions {
+            if_not_exists: true,
+            ..Default::default()
+        };
+        grant_role("test_user", "test_role", &opts).unwrap();
+
+        grant(
+            "test_user",
+            &[Privilege::Execute],
+            ObjectType::Universe,
+            None,
+            &Default::default(),
+        )
+        .unwrap();
+        revoke(
+            "test_user",
+            &[Privilege::Execute],
+            ObjectType::Universe,
+            None,
+            &Default::default(),
+        )
+        .unwrap();
+        // Already revoked.
+        assert!(revoke(
+            "test_user",
+            &[Privilege::Execute],
+            ObjectType::Universe,
+            None,
+            &Default::default(),
+        )
+        .is_err());
+
+        revoke_role("test_user", "test_role", &Default::default()).unwrap();
+        role::revoke(
+            "test_role",
+            &privileges,
+            ObjectType::Space,
+            Some("_space"),
+            &Default::default(),
+        )
+        .unwrap();
+
+        role::drop("test_role", &Default::default()).unwrap();
+        assert!(!role::exists("test_role").unwrap());
+        drop("test_user", &Default::default()).unwrap();
+        assert!(!exists("test_user").unwrap());
+        let opts = DropOptions { if_exists: true };
+        drop("test_user", &opts).unwrap();
+    }
+}
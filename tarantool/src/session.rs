@@ -85,6 +85,12 @@ mod vanilla {
 
         Ok(())
     }
+
+    /// Get the unique identifier of the current session.
+    pub fn id() -> Result<u64, Error> {
+        let id = crate::lua_state().eval("return box.session.id()")?;
+        Ok(id)
+    }
 }
 
 #[cfg(feature = "picodata")]
@@ -118,6 +124,13 @@ mod picodata {
         unsafe { Ok(box_effective_user_id()) }
     }
 
+    /// Get the unique identifier of the current session.
+    #[inline]
+    pub fn id() -> Result<u64, Error> {
+        // In picodata this is actually infallible.
+        unsafe { Ok(crate::ffi::tarantool::box_session_id()) }
+    }
+
     pub(super) fn su_impl(uid: UserId) -> Result<(), Error> {
         let err = unsafe { box_session_su(uid) };
         if err < 0 {
@@ -140,7 +153,10 @@ mod picodata {
     }
 }
 
-use crate::error::Error;
+use crate::error::{BoxError, Error, IntoBoxError, TarantoolError, TarantoolErrorCode};
+use crate::msgpack::{self, Encode};
+use crate::network::protocol::codec::{iproto_key, IProtoType};
+use crate::trigger::TriggerHandle;
 
 #[cfg(feature = "picodata")]
 pub use picodata::*;
@@ -171,3 +187,257 @@ pub fn with_su<T>(uid: UserId, f: impl FnOnce() -> T) -> Result<T, Error> {
     let _su = su(uid)?;
     Ok(f())
 }
+
+/// Registers a trigger which is called each time a new client connects,
+/// after the authentication.
+///
+/// If the closure returns an error, the connection is closed. Note that the
+/// triggers aren't called for the admin console connections.
+///
+/// Returns a handle which can be used to unregister the trigger.
+///
+/// See also [`crate::trigger`].
+#[inline]
+pub fn on_connect<F, E>(f: F) -> Result<TriggerHandle, Error>
+where
+    F: FnMut() -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    crate::trigger::on_session(crate::trigger::TriggerKind::OnConnect, f)
+}
+
+/// Registers a trigger which is called each time a client disconnects.
+///
+/// Errors returned from the closure are logged and ignored.
+///
+/// Returns a handle which can be used to unregister the trigger.
+///
+/// See also [`crate::trigger`].
+#[inline]
+pub fn on_disconnect<F, E>(f: F) -> Result<TriggerHandle, Error>
+where
+    F: FnMut() -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    crate::trigger::on_session(crate::trigger::TriggerKind::OnDisconnect, f)
+}
+
+/// Get the synchronization id of the request which is being processed in the
+/// current fiber, e.g. the IPROTO_CALL request which invoked the current
+/// stored procedure.
+///
+/// The sync id is unique within a session, so together with the session id
+/// (see [`id`]) it identifies the request, which can be used for correlating
+/// log entries or [out-of-band pushes] with the client requests.
+///
+/// Returns `None` if the current fiber isn't processing a client request.
+///
+/// [out-of-band pushes]: crate::tuple::session_push
+pub fn sync() -> Result<Option<u64>, Error> {
+    let sync: Option<u64> = crate::lua_state().eval("return box.session.sync()")?;
+    Ok(sync.filter(|&sync| sync != 0))
+}
+
+/// Get the address of the remote peer of the current session in the form
+/// `"host:port"` or `"unix/:path"`.
+///
+/// Returns `None` if the session isn't associated with a network connection,
+/// e.g. for the background fibers or the admin console.
+pub fn peer() -> Result<Option<String>, Error> {
+    let peer = crate::lua_state().eval("return box.session.peer()")?;
+    Ok(peer)
+}
+
+/// Information about the client request being processed in the current fiber,
+/// see [`current_request`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestInfo {
+    /// Id of the session the request was received in, see [`id`].
+    pub session_id: u64,
+    /// Synchronization id of the request, see [`sync`].
+    pub sync: Option<u64>,
+    /// Address of the client, see [`peer`].
+    pub peer: Option<String>,
+}
+
+/// Get the information about the client request being processed in the
+/// current fiber. This is mostly useful inside a [`proc`] for audit logs or
+/// correlating the responses with the requests.
+///
+/// ```no_run
+/// use tarantool::session;
+///
+/// #[tarantool::proc]
+/// fn transfer(from: u64, to: u64, amount: u64) -> Result<(), String> {
+///     let request = session::current_request().map_err(|e| e.to_string())?;
+///     tarantool::say_info!(
+///         "transfer {} from {} to {} requested by {:?} (session {}, sync {:?})",
+///         amount, from, to, request.peer, request.session_id, request.sync,
+///     );
+///     // ...
+///     Ok(())
+/// }
+/// ```
+///
+/// [`proc`]: macro@crate::proc
+pub fn current_request() -> Result<RequestInfo, Error> {
+    Ok(RequestInfo {
+        session_id: id()?,
+        sync: sync()?,
+        peer: peer()?,
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Packet
+////////////////////////////////////////////////////////////////////////////////
+
+/// A custom iproto packet which can be sent to the client of any IPROTO
+/// session via [`Packet::send`], e.g. for notifying the specific clients about
+/// the events they're subscribed to.
+///
+/// The packet consists of a header with the request type, sync and schema
+/// version and a body, which is a map from iproto keys (see [`iproto_key`]) to
+/// arbitrary msgpack values. It's the client's responsibility to interpret
+/// the packet, so the request type and the sync must be ones the client
+/// expects to receive.
+///
+/// ```no_run
+/// use tarantool::network::protocol::codec::IProtoType;
+/// use tarantool::session::Packet;
+///
+/// # let session_id = 0;
+/// Packet::new(IProtoType::Chunk)
+///     .sync(42)
+///     .data(&("price_changed", 13.37))
+///     .send(session_id)
+///     .unwrap();
+/// ```
+///
+/// [`iproto_key`]: crate::network::protocol::codec::iproto_key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Packet {
+    request_type: u32,
+    sync: u64,
+    schema_version: Option<u64>,
+    /// Pairs of iproto keys and msgpack encoded values.
+    body: Vec<(u8, Vec<u8>)>,
+}
+
+impl Packet {
+    /// Creates a packet of the given type with an empty body and sync `0`.
+    #[inline(always)]
+    pub fn new(request_type: IProtoType) -> Self {
+        Self::with_raw_type(request_type as _)
+    }
+
+    /// Creates a packet with a request type which isn't in [`IProtoType`],
+    /// e.g. one supported by a newer version of the protocol.
+    #[inline(always)]
+    pub fn with_raw_type(request_type: u32) -> Self {
+        Self {
+            request_type,
+            sync: 0,
+            schema_version: None,
+            body: Vec::new(),
+        }
+    }
+
+    /// Sets the sync of the packet, e.g. the one of the request the packet
+    /// relates to (see [`sync`]).
+    #[inline(always)]
+    pub fn sync(mut self, sync: u64) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Sets the schema version in the header of the packet, which isn't sent
+    /// by default.
+    #[inline(always)]
+    pub fn schema_version(mut self, schema_version: u64) -> Self {
+        self.schema_version = Some(schema_version);
+        self
+    }
+
+    /// Adds the `value` under the iproto `key` to the body of the packet.
+    #[inline]
+    pub fn field(mut self, key: u8, value: &impl Encode) -> Self {
+        self.body.push((key, msgpack::encode(value)));
+        self
+    }
+
+    /// Adds the `value` to the body of the packet under the `IPROTO_DATA`
+    /// key, which is where the clients expect the payload of the responses
+    /// and pushes to be.
+    #[inline(always)]
+    pub fn data(self, value: &impl Encode) -> Self {
+        self.field(iproto_key::DATA, value)
+    }
+
+    /// Encodes the header of the packet as a msgpack map.
+    pub fn encode_header(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(32);
+        let len = if self.schema_version.is_some() { 3 } else { 2 };
+        rmp::encode::write_map_len(&mut res, len).expect("Can't fail for a Vec");
+        rmp::encode::write_pfix(&mut res, iproto_key::REQUEST_TYPE).expect("Can't fail for a Vec");
+        rmp::encode::write_uint(&mut res, self.request_type as _).expect("Can't fail for a Vec");
+        rmp::encode::write_pfix(&mut res, iproto_key::SYNC).expect("Can't fail for a Vec");
+        rmp::encode::write_uint(&mut res, self.sync).expect("Can't fail for a Vec");
+        if let Some(schema_version) = self.schema_version {
+            rmp::encode::write_pfix(&mut res, iproto_key::SCHEMA_VERSION)
+                .expect("Can't fail for a Vec");
+            rmp::encode::write_uint(&mut res, schema_version).expect("Can't fail for a Vec");
+        }
+        res
+    }
+
+    /// Encodes the body of the packet as a msgpack map.
+    pub fn encode_body(&self) -> Vec<u8> {
+        let size = self.body.iter().map(|(_, v)| v.len() + 1).sum::<usize>();
+        let mut res = Vec::with_capacity(size + 5);
+        rmp::encode::write_map_len(&mut res, self.body.len() as _).expect("Can't fail for a Vec");
+        for (key, value) in &self.body {
+            rmp::encode::write_uint(&mut res, *key as _).expect("Can't fail for a Vec");
+            res.extend_from_slice(value);
+        }
+        res
+    }
+
+    /// Sends the packet to the client of the session with the given id (see
+    /// [`id`]).
+    ///
+    /// Returns an error if the session doesn't exist or isn't an IPROTO
+    /// session (e.g. it's the admin console or a background fiber) or if the
+    /// tarantool executable doesn't support this (see
+    /// [`crate::ffi::has_iproto_send`]).
+    ///
+    /// The packet is sent asynchronously, so the function returning `Ok`
+    /// doesn't mean the client has received it.
+    pub fn send(&self, session_id: u64) -> Result<(), Error> {
+        if !crate::ffi::has_iproto_send() {
+            return Err(BoxError::new(
+                TarantoolErrorCode::Unsupported,
+                "sending iproto packets is not supported by this version of tarantool",
+            )
+            .into());
+        }
+        let header = self.encode_header();
+        let body = self.encode_body();
+        let header = header.as_ptr_range();
+        let body = body.as_ptr_range();
+        // SAFETY: the header and the body are valid msgpack maps.
+        let rc = unsafe {
+            crate::ffi::tarantool::box_iproto_send(
+                session_id,
+                header.start.cast(),
+                header.end.cast(),
+                body.start.cast(),
+                body.end.cast(),
+            )
+        };
+        if rc < 0 {
+            return Err(TarantoolError::last().into());
+        }
+        Ok(())
+    }
+}
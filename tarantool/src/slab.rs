@@ -0,0 +1,250 @@
+//! Memtx slab allocator statistics.
+//!
+//! Tarantool stores the memtx tuples in a slab arena, the state of which can
+//! be observed with the `box.slab.*` Lua API. This module provides typed
+//! wrappers over that API:
+//!
+//! - [`info`] returns the aggregated memory usage of the arena ([`SlabInfo`]),
+//! - [`stats`] returns the detailed memory usage per slab class
+//!   ([`SlabStats`]),
+//! - [`check`] validates the consistency of the slab cache.
+//!
+//! Memory fragmentation occurs when tuples of different sizes are inserted
+//! and deleted, which leaves the allocated slabs partially empty. Tarantool
+//! doesn't provide a way to defragment the memtx arena in place, so the
+//! fragmentation should be monitored (see [`SlabInfo::fragmentation_ratio`],
+//! [`watch_fragmentation`] and [`register_metrics`]) and the instance
+//! restarted from a snapshot once it becomes too high.
+//!
+//! See also [box.slab reference](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_slab/).
+
+use std::time::Duration;
+
+use crate::fiber::{self, FiberId};
+
+/// Aggregated memory usage of the memtx slab arena as returned by
+/// `box.slab.info()`.
+///
+/// All of the sizes are in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, tlua::LuaRead)]
+pub struct SlabInfo {
+    /// Total amount of memory available for the tuples and index keys
+    /// (including the free memory in the allocated slabs).
+    pub items_size: u64,
+    /// Amount of memory used by the tuples and index keys.
+    pub items_used: u64,
+    /// Maximum amount of memory the slab allocator can use (`box.cfg.memtx_memory`).
+    pub quota_size: u64,
+    /// Amount of memory already allocated by the slab allocator from the quota.
+    pub quota_used: u64,
+    /// Total amount of memory available in the arena.
+    pub arena_size: u64,
+    /// Amount of memory used by the allocated slabs.
+    pub arena_used: u64,
+}
+
+impl SlabInfo {
+    /// Returns the fraction of the memory used by the allocated slabs which
+    /// isn't occupied by the tuples or index keys, i.e. the memory lost to
+    /// fragmentation. The value is in the range `0.0..=1.0`.
+    #[inline]
+    pub fn fragmentation_ratio(&self) -> f64 {
+        if self.arena_used == 0 {
+            return 0.0;
+        }
+        1.0 - (self.items_used.min(self.arena_used) as f64 / self.arena_used as f64)
+    }
+
+    /// Returns the fraction of the memtx memory quota which is already
+    /// allocated. Once the value reaches `1.0` the inserts will start failing
+    /// with an out of memory error, unless there's enough free memory in the
+    /// allocated slabs.
+    #[inline]
+    pub fn quota_used_ratio(&self) -> f64 {
+        if self.quota_size == 0 {
+            return 0.0;
+        }
+        self.quota_used as f64 / self.quota_size as f64
+    }
+
+    /// Returns the fraction of the memory in the arena which is used by the
+    /// tuples and index keys.
+    #[inline]
+    pub fn items_used_ratio(&self) -> f64 {
+        if self.items_size == 0 {
+            return 0.0;
+        }
+        self.items_used as f64 / self.items_size as f64
+    }
+}
+
+/// Memory usage of a single slab class as returned by `box.slab.stats()`.
+///
+/// All of the sizes are in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, tlua::LuaRead)]
+pub struct SlabStats {
+    /// Size of an item in this slab class.
+    pub item_size: u64,
+    /// Number of items stored in this slab class.
+    pub item_count: u64,
+    /// Size of a slab in this slab class.
+    pub slab_size: u64,
+    /// Number of slabs allocated for this slab class.
+    pub slab_count: u64,
+    /// Amount of memory used by the items.
+    pub mem_used: u64,
+    /// Amount of memory allocated for this slab class, but not used by any
+    /// items.
+    pub mem_free: u64,
+}
+
+/// Returns the aggregated memory usage of the memtx slab arena.
+///
+/// Returns an error if `box.cfg{ .. }` was not called yet.
+#[inline]
+pub fn info() -> crate::Result<SlabInfo> {
+    let info = crate::lua_state().eval("return box.slab.info()")?;
+    Ok(info)
+}
+
+/// Returns the memory usage of the memtx slab arena for each slab class.
+///
+/// Returns an error if `box.cfg{ .. }` was not called yet.
+#[inline]
+pub fn stats() -> crate::Result<Vec<SlabStats>> {
+    let stats = crate::lua_state().eval("return box.slab.stats()")?;
+    Ok(stats)
+}
+
+/// Checks the consistency of the slab cache of the current thread.
+///
+/// **NOTE**: tarantool aborts the process if an inconsistency is found, so
+/// this should only be used for debugging.
+#[inline]
+pub fn check() -> crate::Result<()> {
+    crate::lua_state().exec("box.slab.check()")?;
+    Ok(())
+}
+
+/// Starts a fiber which checks the memtx slab arena fragmentation every
+/// `interval` and calls `on_exceeded` each time the
+/// [`fragmentation_ratio`](SlabInfo::fragmentation_ratio) is greater than
+/// `threshold`.
+///
+/// The fiber stops once it's cancelled (see [`fiber::cancel`]).
+///
+/// Returns the id of the started fiber.
+pub fn watch_fragmentation<F>(
+    threshold: f64,
+    interval: Duration,
+    mut on_exceeded: F,
+) -> crate::Result<FiberId>
+where
+    F: FnMut(&SlabInfo) + 'static,
+{
+    fiber::Builder::new()
+        .name("slab_fragmentation_watcher")
+        .func(move || {
+            while !fiber::is_cancelled() {
+                match info() {
+                    Ok(info) if info.fragmentation_ratio() > threshold => on_exceeded(&info),
+                    Ok(_) => {}
+                    Err(e) => crate::say_warn!("failed getting slab info: {}", e),
+                }
+                fiber::sleep(interval);
+            }
+        })
+        .start_non_joinable()
+}
+
+/// Registers the memtx slab arena fragmentation metrics in the
+/// [`metrics`](https://github.com/tarantool/metrics) module:
+///
+/// - `tnt_slab_fragmentation_ratio` - the current
+///   [`fragmentation_ratio`](SlabInfo::fragmentation_ratio),
+/// - `tnt_slab_fragmentation_warning` - `1` if the fragmentation ratio is
+///   greater than `warn_threshold`, `0` otherwise.
+///
+/// A warning is also written to the log each time the fragmentation ratio
+/// exceeds the `warn_threshold`.
+///
+/// Returns `Ok(false)` if the `metrics` module is not available.
+pub fn register_metrics(warn_threshold: f64) -> crate::Result<bool> {
+    let registered = crate::lua_state()
+        .eval_with(
+            r#"
+        local threshold = ...
+        local ok, metrics = pcall(require, 'metrics')
+        if not ok then
+            return false
+        end
+        local log = require('log')
+        local ratio_gauge = metrics.gauge(
+            'tnt_slab_fragmentation_ratio',
+            'Fraction of the memtx arena memory not occupied by the data'
+        )
+        local warning_gauge = metrics.gauge(
+            'tnt_slab_fragmentation_warning',
+            'Whether the memtx arena fragmentation exceeds the threshold'
+        )
+        local exceeded = false
+        metrics.register_callback(function()
+            local info = box.slab.info()
+            local ratio = 0
+            if info.arena_used > 0 then
+                ratio = 1 - math.min(info.items_used, info.arena_used) / info.arena_used
+            end
+            ratio_gauge:set(ratio)
+            if ratio > threshold then
+                warning_gauge:set(1)
+                if not exceeded then
+                    log.warn('memtx arena fragmentation ratio %.3f exceeds %.3f', ratio, threshold)
+                end
+                exceeded = true
+            else
+                warning_gauge:set(0)
+                exceeded = false
+            end
+        end)
+        return true
+        "#,
+            warn_threshold,
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(registered)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[crate::test(tarantool = "crate")]
+    fn info_and_stats() {
+        let info = info().unwrap();
+        assert!(info.arena_used >= info.items_used);
+        assert!(info.quota_size >= info.quota_used);
+        let ratio = info.fragmentation_ratio();
+        assert!((0.0..=1.0).contains(&ratio), "{}", ratio);
+
+        let stats = stats().unwrap();
+        let mem_used: u64 = stats.iter().map(|s| s.mem_used).sum();
+        assert!(mem_used <= info.arena_used);
+
+        check().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn fragmentation_watcher() {
+        let calls = Rc::new(Cell::new(0));
+        let calls_clone = calls.clone();
+        let id = watch_fragmentation(-1.0, Duration::from_millis(1), move |_| {
+            calls_clone.set(calls_clone.get() + 1)
+        })
+        .unwrap();
+        fiber::sleep(Duration::from_millis(10));
+        fiber::cancel(id);
+        assert!(calls.get() > 0);
+    }
+}
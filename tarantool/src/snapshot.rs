@@ -0,0 +1,265 @@
+//! Consistent reads from several spaces and joins of their tuples.
+//!
+//! The tuples read by a fiber are consistent with each other as long as the
+//! fiber doesn't yield, because no other fiber can modify the spaces
+//! meanwhile. [`read`] runs a closure within a transaction and checks that
+//! it didn't yield, so that the results of several selects can be combined
+//! without worrying about concurrent modifications. The closure receives a
+//! [`Snapshot`] which can be used to check the consistency along the way.
+//!
+//! The selected tuples can then be combined with [`hash_join`] or
+//! [`merge_join`], which match the tuples by keys described by a
+//! [`KeyDef`].
+//!
+//! ```no_run
+//! use tarantool::index::IteratorType;
+//! use tarantool::snapshot::{self, hash_join};
+//! use tarantool::space::Space;
+//!
+//! let users = Space::find("users").unwrap();
+//! let orders = Space::find("orders").unwrap();
+//! let user_id = users.primary_key().meta().unwrap().to_key_def();
+//! let order_user_id = orders.index("user_id").unwrap().meta().unwrap().to_key_def();
+//!
+//! let pairs = snapshot::read(|_| {
+//!     let users = users.select(IteratorType::All, &())?;
+//!     let orders = orders.select(IteratorType::All, &())?;
+//!     hash_join(users, &user_id, orders, &order_user_id)
+//! })
+//! .unwrap();
+//! for (user, order) in pairs {
+//!     println!("{:?} ordered {:?}", user, order);
+//! }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber;
+use crate::transaction;
+use crate::tuple::{KeyDef, Tuple};
+
+/// A consistent view of the spaces for the duration of [`read`].
+#[derive(Debug)]
+pub struct Snapshot {
+    csw: u64,
+}
+
+impl Snapshot {
+    /// Returns an error with [`TarantoolErrorCode::TransactionYield`] if the
+    /// fiber has yielded since the snapshot was started, in which case the
+    /// tuples read before and after the yield may be inconsistent.
+    #[inline]
+    pub fn check(&self) -> crate::Result<()> {
+        if fiber::csw() != self.csw {
+            return Err(BoxError::new(
+                TarantoolErrorCode::TransactionYield,
+                "fiber yielded during a consistent read",
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f` within a transaction (unless there's one already) and returns
+/// its result, if the fiber didn't yield meanwhile.
+///
+/// Returns an error with [`TarantoolErrorCode::TransactionYield`] if the
+/// fiber has yielded, e.g. because a vinyl space was read from disk or `f`
+/// has called a yielding function. The transaction is always rolled back, so
+/// `f` should only read from the spaces.
+pub fn read<T, F>(f: F) -> crate::Result<T>
+where
+    F: FnOnce(&Snapshot) -> crate::Result<T>,
+{
+    let started = !transaction::is_in_transaction();
+    if started {
+        transaction::begin()?;
+    }
+    let snapshot = Snapshot { csw: fiber::csw() };
+    let res = f(&snapshot);
+    if started {
+        transaction::rollback()?;
+    }
+    let res = res?;
+    snapshot.check()?;
+    Ok(res)
+}
+
+/// Returns the pairs of tuples from `left` and `right` with equal keys
+/// described by `left_key` and `right_key` respectively.
+///
+/// The `left` tuples are collected into a hash table, so it should be the
+/// smaller of the two. The pairs are returned in the order of the `right`
+/// tuples. Returns an error if a tuple doesn't match its key definition.
+pub fn hash_join<L, R>(
+    left: L,
+    left_key: &KeyDef,
+    right: R,
+    right_key: &KeyDef,
+) -> crate::Result<Vec<(Tuple, Tuple)>>
+where
+    L: IntoIterator<Item = Tuple>,
+    R: IntoIterator<Item = Tuple>,
+{
+    let mut table: HashMap<Vec<u8>, Vec<Tuple>> = HashMap::new();
+    for tuple in left {
+        let key = normalized_key(left_key, &tuple)?;
+        table.entry(key).or_default().push(tuple);
+    }
+
+    let mut res = Vec::new();
+    for tuple in right {
+        let key = normalized_key(right_key, &tuple)?;
+        if let Some(matches) = table.get(&key) {
+            res.extend(matches.iter().map(|l| (l.clone(), tuple.clone())));
+        }
+    }
+    Ok(res)
+}
+
+/// Extracts the key from `tuple` and re-encodes it, so that the equal values
+/// encoded differently (e.g. `1` as a fixint and as an uint64) have equal
+/// bytes.
+fn normalized_key(key_def: &KeyDef, tuple: &Tuple) -> crate::Result<Vec<u8>> {
+    let key = key_def.extract_key(tuple)?;
+    let value = rmpv::decode::read_value(&mut key.as_ref()).map_err(crate::error::Error::other)?;
+    let mut res = Vec::with_capacity(key.as_ref().len());
+    rmpv::encode::write_value(&mut res, &value)?;
+    Ok(res)
+}
+
+/// Returns the pairs of tuples from `left` and `right` with equal keys
+/// described by `left_key` and `right_key` respectively.
+///
+/// Both `left` and `right` must be sorted by their keys in ascending order,
+/// e.g. be the results of selects from TREE indexes with these keys, in
+/// which case no tuples are collected into memory except for the groups of
+/// tuples with equal keys. The pairs are returned in the order of the keys.
+/// Returns an error if a tuple doesn't match its key definition.
+pub fn merge_join<L, R>(
+    left: L,
+    left_key: &KeyDef,
+    right: R,
+    right_key: &KeyDef,
+) -> crate::Result<Vec<(Tuple, Tuple)>>
+where
+    L: IntoIterator<Item = Tuple>,
+    R: IntoIterator<Item = Tuple>,
+{
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    let mut res = Vec::new();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        let key = right_key.extract_key(r)?;
+        match left_key.compare_with_key(l, &key) {
+            Ordering::Less => {
+                left.next();
+            }
+            Ordering::Greater => {
+                right.next();
+            }
+            Ordering::Equal => {
+                let mut left_group = Vec::new();
+                while let Some(l) = left.next_if(|l| left_key.compare_with_key(l, &key).is_eq()) {
+                    left_group.push(l);
+                }
+                while let Some(r) = right.next_if(|r| right_key.compare_with_key(r, &key).is_eq()) {
+                    res.extend(left_group.iter().map(|l| (l.clone(), r.clone())));
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::index::IteratorType;
+    use crate::space::{FieldType, Space};
+
+    fn create_spaces() -> (Space, Space) {
+        let users = Space::builder(&crate::temp_space_name!())
+            .field(("id", FieldType::Unsigned))
+            .field(("name", FieldType::String))
+            .create()
+            .unwrap();
+        users.index_builder("pk").create().unwrap();
+        for (id, name) in [(1, "alice"), (2, "bob"), (3, "carol")] {
+            users.insert(&(id, name)).unwrap();
+        }
+
+        let orders = Space::builder(&crate::temp_space_name!())
+            .field(("id", FieldType::Unsigned))
+            .field(("user_id", FieldType::Unsigned))
+            .create()
+            .unwrap();
+        orders.index_builder("pk").create().unwrap();
+        orders
+            .index_builder("user_id")
+            .unique(false)
+            .part("user_id")
+            .create()
+            .unwrap();
+        for (id, user_id) in [(10, 2), (11, 1), (12, 2), (13, 4)] {
+            orders.insert(&(id, user_id)).unwrap();
+        }
+        (users, orders)
+    }
+
+    fn ids(pairs: Vec<(Tuple, Tuple)>) -> Vec<(u32, u32)> {
+        let mut res: Vec<_> = pairs
+            .into_iter()
+            .map(|(l, r)| (l.get(0).unwrap(), r.get(0).unwrap()))
+            .collect();
+        res.sort_unstable();
+        res
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn joins() {
+        let (users, orders) = create_spaces();
+        let user_id = users.primary_key().meta().unwrap().to_key_def();
+        let by_user_id = orders.index("user_id").unwrap();
+        let order_user_id = by_user_id.meta().unwrap().to_key_def();
+        let expected = vec![(1, 11), (2, 10), (2, 12)];
+
+        let pairs = read(|_| {
+            let users = users.select(IteratorType::All, &())?;
+            let orders = orders.select(IteratorType::All, &())?;
+            hash_join(users, &user_id, orders, &order_user_id)
+        })
+        .unwrap();
+        assert_eq!(ids(pairs), expected);
+
+        let pairs = read(|snapshot| {
+            let users = users.select(IteratorType::All, &())?;
+            let orders = by_user_id.select(IteratorType::All, &())?;
+            let res = merge_join(users, &user_id, orders, &order_user_id)?;
+            snapshot.check()?;
+            Ok(res)
+        })
+        .unwrap();
+        assert_eq!(ids(pairs), expected);
+
+        users.drop().unwrap();
+        orders.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn yield_is_detected() {
+        let e = read(|_| {
+            fiber::reschedule();
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: TransactionYield: fiber yielded during a consistent read"
+        );
+        assert!(!transaction::is_in_transaction());
+    }
+}
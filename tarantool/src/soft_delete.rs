@@ -0,0 +1,430 @@
+//! Soft deletion of tuples.
+//!
+//! A space with soft deletion enabled has a nullable field (e.g.
+//! `deleted_at`) which is set to the deletion time instead of removing the
+//! tuple. [`enable`] registers a `before_replace` trigger on the space, which
+//! turns every delete into an update of this field, no matter where it comes
+//! from (rust, lua or a remote client). The reads via [`SoftDeleteSpace`]
+//! skip the deleted tuples, and [`SoftDeleteSpace::purge`] is the escape
+//! hatch for removing a tuple for real.
+//!
+//! The deletes which come from the replication or are replayed from the WAL
+//! during the recovery are let through, because they were already processed
+//! on the instance they originated from, e.g. they're the purges.
+//!
+//! ```no_run
+//! use tarantool::{index::IteratorType, soft_delete, space::Space};
+//!
+//! let space = Space::find("users").unwrap();
+//! let users = soft_delete::enable(&space, "deleted_at").unwrap();
+//!
+//! users.delete(&[1]).unwrap();
+//! assert!(users.get(&[1]).unwrap().is_none());
+//! // The tuple is still there.
+//! assert!(space.get(&[1]).unwrap().is_some());
+//!
+//! users.restore(&[1]).unwrap();
+//! assert!(users.get(&[1]).unwrap().is_some());
+//!
+//! for user in users.select(IteratorType::All, &()).unwrap() {
+//!     // Only the tuples which aren't deleted ...
+//! }
+//!
+//! users.purge(&[1]).unwrap();
+//! assert!(space.get(&[1]).unwrap().is_none());
+//! ```
+
+use crate::error::{BoxError, TarantoolErrorCode};
+use crate::fiber::{self, FiberId};
+use crate::index::{Index, IndexIterator, IteratorType};
+use crate::space::{Space, SpaceId, UpdateOps};
+use crate::trigger::{BeforeReplace, TriggerHandle};
+use crate::tuple::{ToTupleBuffer, Tuple};
+use crate::util::Value;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+thread_local! {
+    static SPACES: RefCell<HashMap<SpaceId, Config>> = RefCell::new(HashMap::new());
+    /// The spaces and the fibers in which [`SoftDeleteSpace::purge`] is
+    /// deleting a tuple, so that the trigger lets the delete through.
+    static PURGING: RefCell<HashSet<(SpaceId, FiberId)>> = RefCell::new(HashSet::new());
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    field: u32,
+    trigger: TriggerHandle,
+}
+
+/// Enables soft deletion for the `space`, `field` is the name of the nullable
+/// field which is set to the deletion time (see [`clock::time`]).
+///
+/// If soft deletion is already enabled for the space, the field is replaced.
+///
+/// [`clock::time`]: crate::clock::time
+pub fn enable(space: &Space, field: &str) -> crate::Result<SoftDeleteSpace> {
+    let meta = space.meta()?;
+    let field_name = Value::Str(field.into());
+    let Some(field_no) = meta
+        .format
+        .iter()
+        .position(|f| f.get("name") == Some(&field_name))
+    else {
+        return Err(BoxError::new(
+            TarantoolErrorCode::NoSuchFieldNameInSpace,
+            format!("space '{}' has no field '{}'", meta.name, field),
+        )
+        .into());
+    };
+    let field_no = field_no as u32;
+
+    disable(space)?;
+    let space_id = space.id();
+    let trigger = space.before_replace(move |old, new, _| -> crate::Result<_> {
+        // Not a delete, or a delete of a tuple which doesn't exist.
+        let Some(old) = old.filter(|_| new.is_none()) else {
+            return Ok(BeforeReplace::Keep);
+        };
+        if is_purging(space_id) || is_replicated()? {
+            return Ok(BeforeReplace::Keep);
+        }
+        if is_deleted(&old, field_no) {
+            // Keep the original deletion time.
+            return Ok(BeforeReplace::Skip);
+        }
+        let mut fields: Vec<rmpv::Value> = old.decode()?;
+        if fields.len() <= field_no as usize {
+            fields.resize(field_no as usize + 1, rmpv::Value::Nil);
+        }
+        fields[field_no as usize] = crate::clock::time().into();
+        Ok(BeforeReplace::Replace(Tuple::new(&fields)?))
+    })?;
+    let config = Config {
+        field: field_no,
+        trigger,
+    };
+    SPACES.with(|s| s.borrow_mut().insert(space.id(), config));
+    Ok(SoftDeleteSpace {
+        space: space.clone(),
+        field: field_no,
+    })
+}
+
+/// Disables soft deletion for the `space`. The tuples which were soft
+/// deleted stay in the space as they are.
+///
+/// Returns `false` if soft deletion wasn't enabled.
+pub fn disable(space: &Space) -> crate::Result<bool> {
+    let Some(config) = SPACES.with(|s| s.borrow_mut().remove(&space.id())) else {
+        return Ok(false);
+    };
+    config.trigger.unregister()?;
+    Ok(true)
+}
+
+/// Returns the soft deleting view of the `space` if soft deletion is enabled
+/// for it, see [`enable`].
+pub fn find(space: &Space) -> Option<SoftDeleteSpace> {
+    let field = SPACES.with(|s| s.borrow().get(&space.id()).map(|c| c.field))?;
+    Some(SoftDeleteSpace {
+        space: space.clone(),
+        field,
+    })
+}
+
+#[inline]
+fn is_purging(space_id: SpaceId) -> bool {
+    PURGING.with(|p| p.borrow().contains(&(space_id, fiber::id())))
+}
+
+/// Returns `true` if the current change comes from a replica or is replayed
+/// from the WAL during the recovery.
+fn is_replicated() -> crate::Result<bool> {
+    let res = crate::lua_state().eval(
+        "return box.session.type() == 'applier'
+            or box.ctl.is_recovery_finished ~= nil and not box.ctl.is_recovery_finished()",
+    )?;
+    Ok(res)
+}
+
+#[inline]
+fn is_deleted(tuple: &Tuple, field: u32) -> bool {
+    matches!(tuple.field::<rmpv::Value>(field), Ok(Some(v)) if !v.is_nil())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// SoftDeleteSpace
+////////////////////////////////////////////////////////////////////////////////
+
+/// A view of a space with soft deletion enabled, which hides the deleted
+/// tuples. Use [`SoftDeleteSpace::space`] to access all of the tuples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoftDeleteSpace {
+    space: Space,
+    field: u32,
+}
+
+impl SoftDeleteSpace {
+    /// Returns the underlying space, the reads from which include the
+    /// deleted tuples.
+    #[inline(always)]
+    pub fn space(&self) -> &Space {
+        &self.space
+    }
+
+    /// Returns the number of the deletion time field.
+    #[inline(always)]
+    pub fn field(&self) -> u32 {
+        self.field
+    }
+
+    /// Returns `true` if the `tuple` is soft deleted.
+    #[inline(always)]
+    pub fn is_deleted(&self, tuple: &Tuple) -> bool {
+        is_deleted(tuple, self.field)
+    }
+
+    /// Returns the time the `tuple` was deleted at or `None` if it isn't
+    /// deleted.
+    #[inline]
+    pub fn deleted_at(&self, tuple: &Tuple) -> crate::Result<Option<f64>> {
+        Ok(tuple.field::<Option<f64>>(self.field)?.flatten())
+    }
+
+    /// Same as [`Space::get`], but returns `None` for a deleted tuple.
+    #[inline]
+    pub fn get<K>(&self, key: &K) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        let tuple = self.space.get(key)?;
+        Ok(tuple.filter(|t| !self.is_deleted(t)))
+    }
+
+    /// Same as [`Space::select`], but skips the deleted tuples.
+    #[inline]
+    pub fn select<K>(&self, iterator_type: IteratorType, key: &K) -> crate::Result<Live>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        self.select_by(&self.space.primary_key(), iterator_type, key)
+    }
+
+    /// Same as [`Index::select`], but skips the deleted tuples. The `index`
+    /// must belong to the space.
+    #[inline]
+    pub fn select_by<K>(
+        &self,
+        index: &Index,
+        iterator_type: IteratorType,
+        key: &K,
+    ) -> crate::Result<Live>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        Ok(Live {
+            inner: index.select(iterator_type, key)?,
+            field: self.field,
+        })
+    }
+
+    /// Soft deletes the tuple identified by a primary `key`.
+    ///
+    /// Returns the deleted tuple or `Ok(None)` if there's no such tuple or
+    /// it's already deleted.
+    pub fn delete<K>(&self, key: &K) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        if self.get(key)?.is_none() {
+            return Ok(None);
+        }
+        let mut ops = UpdateOps::new();
+        ops.assign(self.field, crate::clock::time())?;
+        self.space.update(key, ops)
+    }
+
+    /// Restores the soft deleted tuple identified by a primary `key`.
+    ///
+    /// Returns the restored tuple or `Ok(None)` if there's no such tuple.
+    pub fn restore<K>(&self, key: &K) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        let mut ops = UpdateOps::new();
+        ops.assign(self.field, ())?;
+        self.space.update(key, ops)
+    }
+
+    /// Deletes the tuple identified by a primary `key` from the space for
+    /// real, whether it's soft deleted or not.
+    ///
+    /// Returns the deleted tuple or `Ok(None)` if there's no such tuple.
+    pub fn purge<K>(&self, key: &K) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        let _guard = PurgeGuard::new(self.space.id());
+        self.space.delete(key)
+    }
+}
+
+/// Marks the space as being purged in the current fiber until dropped.
+struct PurgeGuard {
+    key: (SpaceId, FiberId),
+}
+
+impl PurgeGuard {
+    #[inline]
+    fn new(space_id: SpaceId) -> Self {
+        let key = (space_id, fiber::id());
+        PURGING.with(|p| p.borrow_mut().insert(key));
+        Self { key }
+    }
+}
+
+impl Drop for PurgeGuard {
+    #[inline]
+    fn drop(&mut self) {
+        PURGING.with(|p| p.borrow_mut().remove(&self.key));
+    }
+}
+
+/// An iterator over the tuples which aren't soft deleted, see
+/// [`SoftDeleteSpace::select`].
+pub struct Live {
+    inner: IndexIterator,
+    field: u32,
+}
+
+impl Iterator for Live {
+    type Item = Tuple;
+
+    #[inline]
+    fn next(&mut self) -> Option<Tuple> {
+        let field = self.field;
+        self.inner.by_ref().find(|t| !is_deleted(t, field))
+    }
+}
+
+impl std::fmt::Debug for Live {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Live").field("field", &self.field).finish()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::space::Field;
+
+    fn create_space() -> Space {
+        Space::builder(&crate::temp_space_name!())
+            .field(Field::unsigned("id"))
+            .field(Field::string("name"))
+            .field(Field::number("deleted_at").is_nullable(true))
+            .create()
+            .unwrap()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn soft_delete() {
+        let space = create_space();
+        space.create_index("pk", &Default::default()).unwrap();
+        space.insert(&(1, "a", ())).unwrap();
+        space.insert(&(2, "b", ())).unwrap();
+        space.insert(&(3, "c")).unwrap();
+
+        assert!(find(&space).is_none());
+        let view = enable(&space, "deleted_at").unwrap();
+        assert_eq!(find(&space), Some(view.clone()));
+        assert_eq!(view.field(), 2);
+
+        // Delete via the view.
+        let deleted = view.delete(&[1]).unwrap().unwrap();
+        assert!(view.is_deleted(&deleted));
+        assert!(view.deleted_at(&deleted).unwrap().unwrap() > 0.);
+        assert!(view.get(&[1]).unwrap().is_none());
+        assert!(view.delete(&[1]).unwrap().is_none());
+        assert!(space.get(&[1]).unwrap().is_some());
+
+        // A delete from elsewhere becomes an update too, even if the tuple
+        // is shorter than the format.
+        crate::lua_state()
+            .exec_with("box.space[...]:delete(3)", space.id())
+            .unwrap();
+        let tuple = space.get(&[3]).unwrap().unwrap();
+        assert!(view.is_deleted(&tuple));
+        let deleted_at = view.deleted_at(&tuple).unwrap();
+        space.delete(&[3]).unwrap();
+        let tuple = space.get(&[3]).unwrap().unwrap();
+        assert_eq!(view.deleted_at(&tuple).unwrap(), deleted_at);
+
+        let ids: Vec<u32> = view
+            .select(IteratorType::All, &())
+            .unwrap()
+            .map(|t| t.field(0).unwrap().unwrap())
+            .collect();
+        assert_eq!(ids, [2]);
+
+        let restored = view.restore(&[1]).unwrap().unwrap();
+        assert!(!view.is_deleted(&restored));
+        assert_eq!(view.deleted_at(&restored).unwrap(), None);
+        assert!(view.get(&[1]).unwrap().is_some());
+
+        // Purging removes the tuples for real.
+        assert!(view.purge(&[1]).unwrap().is_some());
+        assert!(view.purge(&[3]).unwrap().is_some());
+        assert!(space.get(&[1]).unwrap().is_none());
+        assert!(space.get(&[3]).unwrap().is_none());
+        assert_eq!(space.len().unwrap(), 1);
+
+        // The purge mark is removed even if the delete fails.
+        view.purge(&["not an id"]).unwrap_err();
+        assert!(!is_purging(space.id()));
+
+        assert!(disable(&space).unwrap());
+        assert!(!disable(&space).unwrap());
+        space.delete(&[2]).unwrap();
+        assert!(space.is_empty().unwrap());
+
+        let e = enable(&space, "no_such_field").unwrap_err();
+        assert!(e.to_string().contains("has no field 'no_such_field'"));
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn purge_is_per_space_and_fiber() {
+        let space = create_space();
+        space.create_index("pk", &Default::default()).unwrap();
+        let other = create_space();
+        other.create_index("pk", &Default::default()).unwrap();
+        space.insert(&(1, "a", ())).unwrap();
+        other.insert(&(1, "a", ())).unwrap();
+        let view = enable(&space, "deleted_at").unwrap();
+        let other_view = enable(&other, "deleted_at").unwrap();
+
+        {
+            let _guard = PurgeGuard::new(space.id());
+            // A delete in another space is still soft.
+            other.delete(&[1]).unwrap();
+            assert!(other_view.get(&[1]).unwrap().is_none());
+            assert!(other.get(&[1]).unwrap().is_some());
+            // So is a delete in another fiber.
+            fiber::start(|| space.delete(&[1]).unwrap()).join();
+            assert!(view.get(&[1]).unwrap().is_none());
+            assert!(space.get(&[1]).unwrap().is_some());
+        }
+        assert!(!is_purging(space.id()));
+
+        // Deletes outside of the recovery and the replication are soft.
+        assert!(!is_replicated().unwrap());
+
+        disable(&space).unwrap();
+        disable(&other).unwrap();
+        space.drop().unwrap();
+        other.drop().unwrap();
+    }
+}
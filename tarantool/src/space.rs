@@ -6,10 +6,15 @@
 //! See also:
 //! - [Lua reference: Submodule box.space](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/)
 //! - [C API reference: Module box](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/box/)
-use crate::error::{Error, TarantoolError};
+use crate::error::{Error, IntoBoxError, TarantoolError};
 use crate::ffi::tarantool as ffi;
-use crate::index::{Index, IndexIterator, IteratorType};
-use crate::tuple::{Encode, ToTupleBuffer, Tuple, TupleBuffer};
+use crate::index::{Index, IndexIterator, IndexOptions, IteratorType, RangeIterator};
+#[cfg(feature = "picodata")]
+pub use crate::read_view::{ReadView, ReadViewIterator};
+use crate::scan::Scan;
+use crate::stream::TupleStream;
+use crate::trigger::{BeforeReplace, RequestType, TriggerHandle};
+use crate::tuple::{DecodeOwned, Encode, ToTupleBuffer, Tuple, TupleBuffer};
 use crate::unwrap_or;
 use crate::util::Value;
 use crate::{msgpack, tuple_from_box_api};
@@ -19,7 +24,7 @@ use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::ops::Range;
+use std::ops::{Range, RangeBounds};
 use std::os::raw::c_char;
 
 /// End of the reserved range of system spaces.
@@ -233,6 +238,18 @@ pub struct Field {
     #[serde(alias = "type")]
     pub field_type: FieldType,
     pub is_nullable: bool,
+    /// Field constraints: mapping from constraint name to the name of the
+    /// function which checks the field value.
+    ///
+    /// See [`Field::constraint`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<BTreeMap<String, String>>,
+    /// Field foreign keys: mapping from foreign key name to the referenced
+    /// space and field.
+    ///
+    /// See [`Field::foreign_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreign_key: Option<BTreeMap<String, ForeignKey>>,
 }
 
 impl<S> From<(S, FieldType, IsNullable)> for Field
@@ -248,6 +265,8 @@ where
             name,
             field_type,
             is_nullable,
+            constraint: None,
+            foreign_key: None,
         }
     }
 }
@@ -265,6 +284,8 @@ where
             name,
             field_type,
             is_nullable,
+            constraint: None,
+            foreign_key: None,
         }
     }
 }
@@ -282,6 +303,8 @@ macro_rules! define_constructors {
                     name: name.into(),
                     field_type: $type,
                     is_nullable: false,
+                    constraint: None,
+                    foreign_key: None,
                 }
             }
         )+
@@ -299,6 +322,8 @@ impl Field {
             name: name.to_string(),
             field_type: ft,
             is_nullable: false,
+            constraint: None,
+            foreign_key: None,
         }
     }
 
@@ -315,6 +340,53 @@ impl Field {
         self
     }
 
+    /// Add a constraint named `name` to the field. The constraint is checked
+    /// by calling the function `func` (which must already exist) with the
+    /// field value and the constraint name every time the field is written.
+    ///
+    /// ```no_run
+    /// use tarantool::space::Field;
+    /// let f = Field::unsigned("age").constraint("adult", "check_age");
+    /// ```
+    ///
+    /// See [field constraints](https://www.tarantool.io/en/doc/latest/concepts/data_model/value_store/#constraints)
+    /// for details.
+    #[inline]
+    pub fn constraint(mut self, name: impl Into<String>, func: impl Into<String>) -> Self {
+        self.constraint
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.into(), func.into());
+        self
+    }
+
+    /// Add a foreign key named `name` to the field, which references the
+    /// `field` of the space `space`. Space name may be the same as the one of
+    /// the space which is being created.
+    ///
+    /// ```no_run
+    /// use tarantool::space::Field;
+    /// let f = Field::unsigned("owner_id").foreign_key("owner", "users", "id");
+    /// ```
+    ///
+    /// See [foreign keys](https://www.tarantool.io/en/doc/latest/concepts/data_model/value_store/#foreign-keys)
+    /// for details.
+    #[inline]
+    pub fn foreign_key(
+        mut self,
+        name: impl Into<String>,
+        space: impl Into<String>,
+        field: impl Into<String>,
+    ) -> Self {
+        self.foreign_key.get_or_insert_with(BTreeMap::new).insert(
+            name.into(),
+            ForeignKey {
+                space: space.into(),
+                field: field.into(),
+            },
+        );
+        self
+    }
+
     define_constructors! {
         any(FieldType::Any)
         unsigned(FieldType::Unsigned)
@@ -334,6 +406,20 @@ impl Field {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// ForeignKey
+////////////////////////////////////////////////////////////////////////////////
+
+/// Field referenced by a foreign key, see [`Field::foreign_key`].
+#[derive(Clone, Debug, Serialize, Deserialize, msgpack::Encode, msgpack::Decode, PartialEq, Eq)]
+#[encode(tarantool = "crate", as_map)]
+pub struct ForeignKey {
+    /// Name of the referenced space.
+    pub space: String,
+    /// Name of the referenced field.
+    pub field: String,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // FieldType
 ////////////////////////////////////////////////////////////////////////////////
@@ -654,9 +740,8 @@ impl Space {
     {
         let buf;
         let data = unwrap_or!(value.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = value.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(value)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         tuple_from_box_api!(
@@ -688,9 +773,8 @@ impl Space {
     {
         let buf;
         let data = unwrap_or!(value.tuple_data(), {
-            // TODO: use region allocation for this
-            buf = value.to_tuple_buffer()?;
-            buf.as_ref()
+            buf = crate::tuple::lease_tuple_data(value)?;
+            buf.as_slice()
         });
         let Range { start, end } = data.as_ptr_range();
         tuple_from_box_api!(
@@ -727,6 +811,46 @@ impl Space {
         Ok(())
     }
 
+    /// Registers a trigger which is called after each replace, insert,
+    /// update, upsert or delete in the space with the old and the new tuple
+    /// and the request type.
+    ///
+    /// If the closure returns an error, the transaction is rolled back.
+    /// The closure must not yield.
+    ///
+    /// Returns a handle which can be used to unregister the trigger.
+    ///
+    /// See also [`crate::trigger`].
+    #[inline]
+    pub fn on_replace<F, E>(&self, f: F) -> Result<TriggerHandle, Error>
+    where
+        F: FnMut(Option<Tuple>, Option<Tuple>, RequestType) -> Result<(), E> + 'static,
+        E: IntoBoxError,
+    {
+        crate::trigger::on_replace(self.id, f)
+    }
+
+    /// Registers a trigger which is called before each replace, insert,
+    /// update, upsert or delete in the space with the old and the new tuple
+    /// and the request type. The returned [`BeforeReplace`] value specifies
+    /// how the operation should proceed, e.g. the new tuple can be modified
+    /// or the operation can be skipped.
+    ///
+    /// If the closure returns an error, the operation fails with this error.
+    /// The closure must not yield.
+    ///
+    /// Returns a handle which can be used to unregister the trigger.
+    ///
+    /// See also [`crate::trigger`].
+    #[inline]
+    pub fn before_replace<F, E>(&self, f: F) -> Result<TriggerHandle, Error>
+    where
+        F: FnMut(Option<Tuple>, Option<Tuple>, RequestType) -> Result<BeforeReplace, E> + 'static,
+        E: IntoBoxError,
+    {
+        crate::trigger::before_replace(self.id, f)
+    }
+
     /// Return the number of tuples in the space.
     ///
     /// Compared with [space.count()](#method.count), this method works faster because [space.len()](#method.len)
@@ -772,6 +896,52 @@ impl Space {
         self.primary_key().select(iterator_type, key)
     }
 
+    /// Iterates over the tuples with the primary keys within the `range` in
+    /// ascending order, see [`Index::range`].
+    #[inline(always)]
+    pub fn range<K, R>(&self, range: R) -> Result<RangeIterator, Error>
+    where
+        K: ToTupleBuffer,
+        R: RangeBounds<K>,
+    {
+        self.primary_key().range(range)
+    }
+
+    /// Iterates over the tuples with the primary keys within the `range` in
+    /// descending order, see [`Index::range_rev`].
+    #[inline(always)]
+    pub fn range_rev<K, R>(&self, range: R) -> Result<RangeIterator, Error>
+    where
+        K: ToTupleBuffer,
+        R: RangeBounds<K>,
+    {
+        self.primary_key().range_rev(range)
+    }
+
+    /// Iterates over all of the tuples in descending order of the primary
+    /// key, see [`Index::pairs_reverse`].
+    #[inline(always)]
+    pub fn pairs_reverse(&self) -> Result<IndexIterator, Error> {
+        self.primary_key().pairs_reverse()
+    }
+
+    /// Returns an async stream of all of the tuples in the order of the
+    /// primary key decoded into `T`, see [`Index::stream`].
+    #[inline]
+    pub fn stream<T>(&self) -> Result<TupleStream<T>, Error>
+    where
+        T: DecodeOwned,
+    {
+        self.primary_key().stream::<T, ()>(..)
+    }
+
+    /// Returns a scan of the primary index which can filter the tuples with
+    /// a lua expression or a rust closure, see [`crate::scan`].
+    #[inline(always)]
+    pub fn scan(&self) -> Scan {
+        self.primary_key().scan()
+    }
+
     /// Return the number of tuples. Compared with [space.len()](#method.len), this method works slower because
     /// [space.count()](#method.count) scans the entire space to count the tuples.
     ///
@@ -799,6 +969,22 @@ impl Space {
         self.primary_key().delete(key)
     }
 
+    /// Delete a tuple identified by a primary `key` and return the deleted
+    /// tuple.
+    ///
+    /// Same as [`Space::delete`], but returns an error with code
+    /// [`TarantoolErrorCode::TupleNotFound`] if there's no such tuple.
+    ///
+    /// [`TarantoolErrorCode::TupleNotFound`]: crate::error::TarantoolErrorCode::TupleNotFound
+    #[inline(always)]
+    #[track_caller]
+    pub fn delete_ret<K>(&self, key: &K) -> Result<Tuple, Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        self.primary_key().delete_ret(key)
+    }
+
     /// Update a tuple.
     ///
     /// The `update` function supports operations on fields — assignment, arithmetic (if the field is numeric),
@@ -829,6 +1015,22 @@ impl Space {
         self.primary_key().update(key, ops)
     }
 
+    /// Update a tuple and return the updated tuple.
+    ///
+    /// Same as [`Space::update`], but returns an error with code
+    /// [`TarantoolErrorCode::TupleNotFound`] if there's no such tuple.
+    ///
+    /// [`TarantoolErrorCode::TupleNotFound`]: crate::error::TarantoolErrorCode::TupleNotFound
+    #[inline(always)]
+    #[track_caller]
+    pub fn update_ret<K, Op>(&self, key: &K, ops: impl AsRef<[Op]>) -> Result<Tuple, Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+        Op: ToTupleBuffer,
+    {
+        self.primary_key().update_ret(key, ops)
+    }
+
     /// Update a tuple using `ops` already encoded in the message pack format.
     ///
     /// This function is similar to [`update`](#method.update) but instead
@@ -887,6 +1089,24 @@ impl Space {
         self.primary_key().upsert(value, ops)
     }
 
+    /// Update or insert a tuple and return the resulting tuple, i.e. either
+    /// the inserted or the updated one.
+    ///
+    /// Tarantool doesn't return the resulting tuple from UPSERT requests, so
+    /// this is emulated by looking up the tuple by its primary key right after
+    /// the upsert. Both operations are done within a single transaction, which
+    /// is started if there's no active one already.
+    ///
+    /// See also: [space.upsert()](#method.upsert)
+    #[inline(always)]
+    pub fn upsert_ret<T, Op>(&self, value: &T, ops: impl AsRef<[Op]>) -> Result<Tuple, Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+        Op: ToTupleBuffer,
+    {
+        self.primary_key().upsert_ret(value, ops)
+    }
+
     /// Upsert a tuple using `ops` already encoded in the message pack format.
     ///
     /// This function is similar to [`upsert`](#method.upsert) but instead
@@ -955,6 +1175,7 @@ impl Encode for Metadata<'_> {}
 pub struct Builder<'a> {
     name: &'a str,
     opts: SpaceCreateOptions,
+    indexes: Vec<(&'a str, IndexOptions)>,
 }
 
 macro_rules! define_setters {
@@ -975,6 +1196,7 @@ impl<'a> Builder<'a> {
         Self {
             name,
             opts: Default::default(),
+            indexes: Vec::new(),
         }
     }
 
@@ -1058,19 +1280,82 @@ impl<'a> Builder<'a> {
         self
     }
 
-    /// Create a space with the current configuration.
+    /// Add an index to be created along with the space. The index is
+    /// configured by the function `f` which receives an index builder.
+    ///
+    /// Indexes are created in the order they were added, so the first one
+    /// becomes the primary key.
+    ///
+    /// ```no_run
+    /// use tarantool::space::{Space, Field};
+    /// use tarantool::index::{Part, FieldType as IFT, RtreeIndexDistanceType};
+    ///
+    /// let space = Space::builder("places")
+    ///     .if_not_exists(true)
+    ///     .field(Field::unsigned("id"))
+    ///     .field(Field::array("coords"))
+    ///     .field(Field::array("tags"))
+    ///     .index("pk", |i| i.part("id").sequence(true))
+    ///     .index("coords", |i| {
+    ///         i.rtree()
+    ///             .unique(false)
+    ///             .part("coords")
+    ///             .dimension(3)
+    ///             .distance(RtreeIndexDistanceType::Manhattan)
+    ///     })
+    ///     .index("tags", |i| {
+    ///         i.unique(false)
+    ///             .part(Part::field("tags").field_type(IFT::String).path("[*]"))
+    ///     })
+    ///     .create()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn index<F>(mut self, name: &'a str, f: F) -> Self
+    where
+        F: FnOnce(crate::index::Builder<'a>) -> crate::index::Builder<'a>,
+    {
+        // Space id is not known yet, it's filled in when the index is created.
+        let (_, name, opts) = f(crate::index::Builder::new(0, name)).into_parts();
+        self.indexes.push((name, opts));
+        self
+    }
+
+    /// Create a space with the current configuration along with all of the
+    /// indexes added with [`Self::index`].
+    ///
+    /// If [`if_not_exists`] is set, it also applies to the indexes, unless
+    /// the index specifies it explicitly. If creation of any of the indexes
+    /// fails, the newly created space is dropped.
     ///
     /// **NOTE:** This function will initiate a transaction if there's isn't an
     /// active one, and if there is the active transaction may be aborted in case
     /// of an error. This shouldn't be a problem if you always consider this
     /// function returning an error to be worthy of a transcation roll back,
     /// which you should.
-    #[inline(always)]
+    ///
+    /// [`if_not_exists`]: Self::if_not_exists
     pub fn create(self) -> crate::Result<Space> {
-        crate::schema::space::create_space(self.name, &self.opts)
+        let is_new = Space::find(self.name).is_none();
+        let space = crate::schema::space::create_space(self.name, &self.opts)?;
+        for (name, mut opts) in self.indexes {
+            if self.opts.if_not_exists && opts.if_not_exists.is_none() {
+                opts.if_not_exists = Some(true);
+            }
+            if let Err(e) = crate::schema::index::create_index(space.id(), name, &opts) {
+                if is_new {
+                    // The index creation error is more important.
+                    let _ = space.drop();
+                }
+                return Err(e);
+            }
+        }
+        Ok(space)
     }
 
     /// Destructure the builder struct into a tuple of name and space options.
+    ///
+    /// Note that the indexes added with [`Self::index`] are not included.
     #[inline(always)]
     pub fn into_parts(self) -> (&'a str, SpaceCreateOptions) {
         (self.name, self.opts)
@@ -1102,12 +1387,28 @@ impl<'a> Builder<'a> {
 /// .unwrap();
 /// ```
 ///
+/// The fields are specified by their numbers, names or JSON paths, see
+/// [`UpdateField`].
+///
+/// The same operations can be sent to a remote instance via
+/// [`net_box::RemoteSpace::update`]:
+/// ```no_run
+/// use tarantool::net_box::{Conn, ConnOptions, Options};
+/// use tarantool::space::UpdateOps;
+/// let conn = Conn::new("localhost:3301", ConnOptions::default(), None).unwrap();
+/// let space = conn.space("employee").unwrap().unwrap();
+/// let mut ops = UpdateOps::new();
+/// ops.add("strikes", 1).unwrap().splice("[3].name", 0, 1, "J").unwrap();
+/// space.update(&[1337], &ops, &Options::default()).unwrap();
+/// ```
+///
 /// [`new`]: UpdateOps::new
 /// [`add`]: UpdateOps::add
 /// [`assign`]: UpdateOps::assign
 /// [`insert`]: UpdateOps::insert
 /// [`encode`]: UpdateOps::encode
 /// [`into_inner`]: UpdateOps::into_inner
+/// [`net_box::RemoteSpace::update`]: crate::net_box::RemoteSpace::update
 pub struct UpdateOps {
     ops: Vec<TupleBuffer>,
 }
@@ -1119,7 +1420,7 @@ macro_rules! define_bin_ops {
             #[inline(always)]
             pub fn $op_name<K, V>(&mut self, field: K, value: V) -> crate::Result<&mut Self>
             where
-                K: Serialize,
+                K: UpdateField,
                 V: Serialize,
             {
                 self.ops.push(($op_code, field, value).to_tuple_buffer()?);
@@ -1170,27 +1471,48 @@ impl UpdateOps {
         /// Field indexing is zero based (first field has index 0).
         /// Negative indexes are offset from array's end (last field has index -1).
         sub, '-';
+    }
 
-        /// Bitwise AND operation.
-        /// Corresponds to tarantool's `{'&', field, value}`.
-        ///
-        /// Field indexing is zero based (first field has index 0).
-        /// Negative indexes are offset from array's end (last field has index -1).
-        and, '&';
+    /// Bitwise AND operation.
+    /// Corresponds to tarantool's `{'&', field, value}`.
+    ///
+    /// Field indexing is zero based (first field has index 0).
+    /// Negative indexes are offset from array's end (last field has index -1).
+    #[inline(always)]
+    pub fn and<K>(&mut self, field: K, value: u64) -> crate::Result<&mut Self>
+    where
+        K: UpdateField,
+    {
+        self.ops.push(('&', field, value).to_tuple_buffer()?);
+        Ok(self)
+    }
 
-        /// Bitwise OR operation.
-        /// Corresponds to tarantool's `{'|', field, value}`.
-        ///
-        /// Field indexing is zero based (first field has index 0).
-        /// Negative indexes are offset from array's end (last field has index -1).
-        or, '|';
+    /// Bitwise OR operation.
+    /// Corresponds to tarantool's `{'|', field, value}`.
+    ///
+    /// Field indexing is zero based (first field has index 0).
+    /// Negative indexes are offset from array's end (last field has index -1).
+    #[inline(always)]
+    pub fn or<K>(&mut self, field: K, value: u64) -> crate::Result<&mut Self>
+    where
+        K: UpdateField,
+    {
+        self.ops.push(('|', field, value).to_tuple_buffer()?);
+        Ok(self)
+    }
 
-        /// Bitwise XOR operation.
-        /// Corresponds to tarantool's `{'^', field, value}`.
-        ///
-        /// Field indexing is zero based (first field has index 0).
-        /// Negative indexes are offset from array's end (last field has index -1).
-        xor, '^';
+    /// Bitwise XOR operation.
+    /// Corresponds to tarantool's `{'^', field, value}`.
+    ///
+    /// Field indexing is zero based (first field has index 0).
+    /// Negative indexes are offset from array's end (last field has index -1).
+    #[inline(always)]
+    pub fn xor<K>(&mut self, field: K, value: u64) -> crate::Result<&mut Self>
+    where
+        K: UpdateField,
+    {
+        self.ops.push(('^', field, value).to_tuple_buffer()?);
+        Ok(self)
     }
 
     /// Deletion operation.
@@ -1201,7 +1523,7 @@ impl UpdateOps {
     #[inline]
     pub fn delete<K>(&mut self, field: K, count: usize) -> crate::Result<&mut Self>
     where
-        K: Serialize,
+        K: UpdateField,
     {
         self.ops.push(('#', field, count).to_tuple_buffer()?);
         Ok(self)
@@ -1221,13 +1543,24 @@ impl UpdateOps {
         value: &str,
     ) -> crate::Result<&mut Self>
     where
-        K: Serialize,
+        K: UpdateField,
     {
         self.ops
             .push((':', field, start, count, value).to_tuple_buffer()?);
         Ok(self)
     }
 
+    /// Returns the number of operations.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
     #[inline(always)]
     pub fn as_slice(&self) -> &[TupleBuffer] {
         &self.ops
@@ -1269,6 +1602,15 @@ impl AsRef<[TupleBuffer]> for UpdateOps {
     }
 }
 
+/// Encodes the operations as a msgpack array, e.g. for
+/// [`net_box::RemoteSpace::update`](crate::net_box::RemoteSpace::update).
+impl ToTupleBuffer for UpdateOps {
+    #[inline(always)]
+    fn write_tuple_data(&self, w: &mut impl std::io::Write) -> crate::Result<()> {
+        self.encode_to(w)
+    }
+}
+
 impl From<UpdateOps> for Vec<TupleBuffer> {
     #[inline(always)]
     fn from(ops: UpdateOps) -> Vec<TupleBuffer> {
@@ -1286,6 +1628,27 @@ impl IntoIterator for UpdateOps {
     }
 }
 
+/// Identifier of the field an [`UpdateOps`] operation is applied to.
+///
+/// It's either
+/// - a field number: indexing is zero based (first field has index 0),
+///   negative numbers are offset from the tuple's end (last field has
+///   index -1);
+/// - a field name from the space format, e.g. `"name"`;
+/// - a JSON path into the field, e.g. `"[3].address.city"` or
+///   `"tags[1]"`.
+pub trait UpdateField: Serialize {}
+
+macro_rules! impl_update_field {
+    ($($t:ty)+) => {
+        $( impl UpdateField for $t {} )+
+    }
+}
+
+impl_update_field! { i8 i16 i32 i64 isize u8 u16 u32 u64 usize str String }
+
+impl<T> UpdateField for &T where T: UpdateField + ?Sized {}
+
 ////////////////////////////////////////////////////////////////////////////////
 // macros
 ////////////////////////////////////////////////////////////////////////////////
@@ -1398,6 +1761,45 @@ mod test {
         space.drop().unwrap();
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn modify_ret() {
+        use crate::error::TarantoolErrorCode;
+
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let t = space.upsert_ret(&(1, 10), [("+", 1, 5)]).unwrap();
+        assert_eq!(t.decode::<(u32, u32)>().unwrap(), (1, 10));
+        let t = space.upsert_ret(&(1, 10), [("+", 1, 5)]).unwrap();
+        assert_eq!(t.decode::<(u32, u32)>().unwrap(), (1, 15));
+
+        // Reuses the active transaction.
+        crate::transaction::transaction(|| -> crate::Result<()> {
+            let t = space.upsert_ret(&(1, 10), [("+", 1, 5)])?;
+            assert_eq!(t.decode::<(u32, u32)>().unwrap(), (1, 20));
+            Ok(())
+        })
+        .unwrap();
+
+        let t = space.update_ret(&(1,), [("=", 1, 0)]).unwrap();
+        assert_eq!(t.decode::<(u32, u32)>().unwrap(), (1, 0));
+
+        let t = space.delete_ret(&(1,)).unwrap();
+        assert_eq!(t.decode::<(u32, u32)>().unwrap(), (1, 0));
+
+        for e in [
+            space.update_ret(&(1,), [("=", 1, 0)]).unwrap_err(),
+            space.delete_ret(&(1,)).unwrap_err(),
+        ] {
+            let Error::Tarantool(e) = e else {
+                panic!("unexpected error: {}", e);
+            };
+            assert_eq!(e.error_code(), TarantoolErrorCode::TupleNotFound as u32);
+        }
+
+        space.drop().unwrap();
+    }
+
     #[crate::test(tarantool = "crate")]
     fn sys_space_metadata() {
         let sys_space = Space::from(SystemSpace::Space);
@@ -2,10 +2,15 @@
 
 use crate::error::TarantoolError;
 use crate::ffi;
-use crate::ffi::sql::ObufWrapper;
+use crate::ffi::sql::{ObufWrapper, IPROTO_DATA};
+use crate::trigger::TriggerHandle;
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::io::Read;
 use std::os::raw::c_char;
+use std::rc::Rc;
 use std::str;
 
 /// Returns the hash, used as the statement ID, generated from the SQL query text.
@@ -46,6 +51,23 @@ where
     Ok(buf)
 }
 
+/// Executes an SQL query without storing the prepared statement in the instance
+/// cache and decodes the resulting rows into values of type `OUT`.
+///
+/// See [`Statement::execute`] for details.
+pub fn prepare_and_execute<IN, OUT>(
+    query: &str,
+    bind_params: &IN,
+    vdbe_max_steps: u64,
+) -> crate::Result<Vec<OUT>>
+where
+    IN: Serialize,
+    OUT: DeserializeOwned,
+{
+    let stream = prepare_and_execute_raw(query, bind_params, vdbe_max_steps)?;
+    decode_rows(stream)
+}
+
 /// Creates new SQL prepared statement and stores it in the session.
 /// query - SQL query.
 ///
@@ -139,4 +161,132 @@ impl Statement {
         }
         Ok(buf)
     }
+
+    /// Executes prepared statement and decodes each of the resulting rows
+    /// into a value of type `OUT`.
+    ///
+    /// Rows are msgpack arrays, so `OUT` would usually be a tuple or a struct
+    /// with fields in the same order as the columns of the query.
+    ///
+    /// If the statement doesn't return any rows (e.g. it's an `INSERT` or
+    /// `UPDATE` statement) an empty vector is returned.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use serde::Deserialize;
+    /// #[derive(Deserialize)]
+    /// struct Row {
+    ///     id: u64,
+    ///     value: String,
+    /// }
+    ///
+    /// let stmt = tarantool::sql::prepare("SELECT * FROM T WHERE ID > ?".into()).unwrap();
+    /// let rows: Vec<Row> = stmt.execute(&(42,), 0).unwrap();
+    /// ```
+    pub fn execute<IN, OUT>(&self, bind_params: &IN, vdbe_max_steps: u64) -> crate::Result<Vec<OUT>>
+    where
+        IN: Serialize,
+        OUT: DeserializeOwned,
+    {
+        let stream = self.execute_raw(bind_params, vdbe_max_steps)?;
+        decode_rows(stream)
+    }
+}
+
+/// Decodes the `IPROTO_DATA` section of an SQL execution result into a vector
+/// of rows.
+fn decode_rows<OUT>(mut stream: impl Read) -> crate::Result<Vec<OUT>>
+where
+    OUT: DeserializeOwned,
+{
+    let map_len = rmp::decode::read_map_len(&mut stream)?;
+    for _ in 0..map_len {
+        let key: u8 = rmp::decode::read_int(&mut stream)?;
+        if key != IPROTO_DATA {
+            let _: IgnoredAny = rmp_serde::from_read(&mut stream)?;
+            continue;
+        }
+        let rows = rmp_serde::from_read(&mut stream)?;
+        return Ok(rows);
+    }
+    Ok(Vec::new())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// statement cache
+////////////////////////////////////////////////////////////////////////////////
+
+thread_local! {
+    /// Prepared statements keyed by the session id and the query text.
+    static STATEMENT_CACHE: RefCell<HashMap<u64, HashMap<String, Rc<Statement>>>> =
+        RefCell::new(HashMap::new());
+    /// The trigger evicting the statements of the closed sessions.
+    static ON_DISCONNECT: Cell<Option<TriggerHandle>> = const { Cell::new(None) };
+}
+
+/// Memorized version of [`prepare`] function.
+///
+/// The statement is only prepared the first time this function is called with
+/// the given `query` in the current session, subsequent calls return the
+/// cached statement, so using this function for the same parameterized query
+/// avoids parsing the query text on each execution.
+///
+/// Cached statements stay prepared until [`clear_cache`] is called or the
+/// session is closed.
+pub fn prepare_cached(query: &str) -> crate::Result<Rc<Statement>> {
+    let session_id = unsafe { ffi::tarantool::box_session_id() };
+
+    let cached = STATEMENT_CACHE.with(|cache| {
+        let cache = cache.borrow();
+        cache.get(&session_id)?.get(query).cloned()
+    });
+    if let Some(stmt) = cached {
+        return Ok(stmt);
+    }
+
+    if ON_DISCONNECT.with(Cell::get).is_none() {
+        let handle = crate::session::on_disconnect(|| {
+            evict_session(unsafe { ffi::tarantool::box_session_id() });
+            Ok::<_, crate::error::Error>(())
+        })?;
+        ON_DISCONNECT.with(|h| h.set(Some(handle)));
+    }
+
+    let stmt = Rc::new(prepare(query.into())?);
+    STATEMENT_CACHE.with(|cache| {
+        cache
+            .borrow_mut()
+            .entry(session_id)
+            .or_default()
+            .insert(query.into(), stmt.clone())
+    });
+    Ok(stmt)
+}
+
+/// Removes the statements of the closed session from the cache.
+fn evict_session(session_id: u64) {
+    let statements = STATEMENT_CACHE.with(|cache| cache.borrow_mut().remove(&session_id));
+    for stmt in statements.into_iter().flat_map(HashMap::into_values) {
+        // The statements are removed along with the session anyway.
+        unsafe { ffi::sql::sql_unprepare_ext(stmt.id(), stmt.session_id()) };
+    }
+}
+
+/// Clear the prepared statement cache used by [`prepare_cached`], removing
+/// the cached statements from their sessions.
+///
+/// Returns the first error which happened when removing the statements, the
+/// cache is cleared regardless.
+pub fn clear_cache() -> crate::Result<()> {
+    let statements = STATEMENT_CACHE.with(|cache| std::mem::take(&mut *cache.borrow_mut()));
+    let mut res = Ok(());
+    if let Some(handle) = ON_DISCONNECT.with(Cell::take) {
+        res = handle.unregister().map(drop);
+    }
+    for stmt in statements.into_values().flat_map(HashMap::into_values) {
+        if unsafe { ffi::sql::sql_unprepare_ext(stmt.id(), stmt.session_id()) } < 0 && res.is_ok() {
+            res = Err(TarantoolError::last().into());
+        }
+    }
+    res
 }
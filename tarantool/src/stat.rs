@@ -0,0 +1,417 @@
+//! Instance load statistics (`box.stat` and `box.stat.net`).
+//!
+//! [`Sample::current`] reads the cumulative request and network counters.
+//! [`watch`] starts a fiber which samples them every given interval and
+//! publishes the [`Load`] over the last interval to a
+//! [`watch`](crate::fiber::r#async::watch) channel, [`stream`] returns the
+//! same loads as an async stream. [`Ewma`] and [`MovingAverage`] smooth out
+//! the spikes, so that background jobs can adapt their intensity to the
+//! current load of the instance.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tarantool::{fiber, stat};
+//!
+//! let mut load = stat::watch(Duration::from_secs(1)).unwrap();
+//! let mut rps = stat::Ewma::new(Duration::from_secs(10));
+//! let mut batch_size = 1000;
+//! fiber::block_on(async {
+//!     while load.changed().await.is_ok() {
+//!         let load = load.get_cloned();
+//!         let rps = rps.update(load.rps(), load.interval);
+//!         batch_size = if rps > 10_000. { 100 } else { 1000 };
+//!         // process the next batch ...
+//!     }
+//! });
+//! ```
+//!
+//! See also [box.stat reference](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_stat/).
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use futures::Stream;
+
+use crate::fiber::{self, r#async::watch};
+use crate::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// Sample
+////////////////////////////////////////////////////////////////////////////////
+
+/// Cumulative counters of the requests processed by the instance since it
+/// started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, tlua::LuaRead)]
+pub struct Counters {
+    pub select: u64,
+    pub insert: u64,
+    pub replace: u64,
+    pub update: u64,
+    pub upsert: u64,
+    pub delete: u64,
+    pub call: u64,
+    pub eval: u64,
+    /// SQL requests.
+    pub execute: u64,
+    pub auth: u64,
+    /// Number of requests which failed.
+    pub error: u64,
+    /// Number of bytes sent via iproto.
+    pub net_sent: u64,
+    /// Number of bytes received via iproto.
+    pub net_received: u64,
+}
+
+impl Counters {
+    /// Returns the total number of requests of all of the types.
+    #[inline]
+    pub fn requests(&self) -> u64 {
+        self.select
+            + self.insert
+            + self.replace
+            + self.update
+            + self.upsert
+            + self.delete
+            + self.call
+            + self.eval
+            + self.execute
+            + self.auth
+    }
+
+    /// Returns the increments of the counters since `earlier`. The counters
+    /// which went down (e.g. after `box.stat.reset()`) are zero.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            select: self.select.saturating_sub(earlier.select),
+            insert: self.insert.saturating_sub(earlier.insert),
+            replace: self.replace.saturating_sub(earlier.replace),
+            update: self.update.saturating_sub(earlier.update),
+            upsert: self.upsert.saturating_sub(earlier.upsert),
+            delete: self.delete.saturating_sub(earlier.delete),
+            call: self.call.saturating_sub(earlier.call),
+            eval: self.eval.saturating_sub(earlier.eval),
+            execute: self.execute.saturating_sub(earlier.execute),
+            auth: self.auth.saturating_sub(earlier.auth),
+            error: self.error.saturating_sub(earlier.error),
+            net_sent: self.net_sent.saturating_sub(earlier.net_sent),
+            net_received: self.net_received.saturating_sub(earlier.net_received),
+        }
+    }
+}
+
+/// A snapshot of the instance statistics, see [`Sample::current`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    /// The moment the sample was taken at (see [`fiber::clock`]).
+    pub at: Instant,
+    pub counters: Counters,
+    /// Number of the currently open iproto connections.
+    pub connections: u64,
+    /// Number of the iproto requests being processed right now.
+    pub requests_in_progress: u64,
+}
+
+#[derive(tlua::LuaRead)]
+struct RawSample {
+    counters: Counters,
+    connections: u64,
+    requests_in_progress: u64,
+}
+
+impl Sample {
+    /// Reads the current statistics.
+    pub fn current() -> crate::Result<Self> {
+        let raw: RawSample = crate::lua_state().eval(
+            "local stat, net = box.stat(), box.stat.net()
+                local function total(t) return t and t.total or 0 end
+                local function current(t) return t and t.current or 0 end
+                return {
+                    counters = {
+                        select = total(stat.SELECT),
+                        insert = total(stat.INSERT),
+                        replace = total(stat.REPLACE),
+                        update = total(stat.UPDATE),
+                        upsert = total(stat.UPSERT),
+                        delete = total(stat.DELETE),
+                        call = total(stat.CALL),
+                        eval = total(stat.EVAL),
+                        execute = total(stat.EXECUTE),
+                        auth = total(stat.AUTH),
+                        error = total(stat.ERROR),
+                        net_sent = total(net.SENT),
+                        net_received = total(net.RECEIVED),
+                    },
+                    connections = current(net.CONNECTIONS),
+                    requests_in_progress = current(net.REQUESTS),
+                }",
+        )?;
+        Ok(Self {
+            at: fiber::clock(),
+            counters: raw.counters,
+            connections: raw.connections,
+            requests_in_progress: raw.requests_in_progress,
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Load
+////////////////////////////////////////////////////////////////////////////////
+
+/// The load of the instance over the interval between two samples, see
+/// [`Load::between`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Load {
+    /// The latest sample.
+    pub sample: Sample,
+    /// The increments of the counters over the interval.
+    pub delta: Counters,
+    /// Time between the samples.
+    pub interval: Duration,
+}
+
+impl Load {
+    /// Computes the load between the `earlier` and the `later` samples.
+    pub fn between(earlier: &Sample, later: &Sample) -> Self {
+        Self {
+            sample: *later,
+            delta: later.counters.since(&earlier.counters),
+            interval: later.at.duration_since(earlier.at),
+        }
+    }
+
+    /// Returns the load with zero increments, i.e. the one which only knows
+    /// about the `sample`.
+    #[inline]
+    pub fn idle(sample: Sample) -> Self {
+        Self {
+            sample,
+            delta: Counters::default(),
+            interval: Duration::ZERO,
+        }
+    }
+
+    /// Returns the rate of a counter selected by `f`, e.g.
+    /// `load.per_second(|c| c.select)`. The rate is zero if the interval is
+    /// empty.
+    #[inline]
+    pub fn per_second(&self, f: impl FnOnce(&Counters) -> u64) -> f64 {
+        if self.interval.is_zero() {
+            return 0.;
+        }
+        f(&self.delta) as f64 / self.interval.as_secs_f64()
+    }
+
+    /// Returns the number of requests of all of the types per second.
+    #[inline(always)]
+    pub fn rps(&self) -> f64 {
+        self.per_second(Counters::requests)
+    }
+
+    /// Returns the number of the failed requests per second.
+    #[inline(always)]
+    pub fn errors_per_second(&self) -> f64 {
+        self.per_second(|c| c.error)
+    }
+
+    /// Returns the number of bytes sent and received via iproto per second.
+    #[inline(always)]
+    pub fn net_bytes_per_second(&self) -> f64 {
+        self.per_second(|c| c.net_sent + c.net_received)
+    }
+}
+
+/// Starts a fiber which samples the statistics every `interval` and sends
+/// the [`Load`] over the last interval to the returned channel.
+///
+/// The initial value of the channel is [`Load::idle`], use
+/// [`Receiver::changed`] to wait for the next one. A value is skipped if a
+/// receiver holds a reference to the previous one at the moment. The fiber
+/// stops once all of the receivers are dropped.
+///
+/// [`Receiver::changed`]: watch::Receiver::changed
+pub fn watch(interval: Duration) -> crate::Result<watch::Receiver<Load>> {
+    let mut last = Sample::current()?;
+    let (tx, rx) = watch::channel(Load::idle(last));
+    fiber::Builder::new()
+        .name("stat_sampler")
+        .func(move || loop {
+            fiber::sleep(interval);
+            if tx.is_closed() || fiber::is_cancelled() {
+                break;
+            }
+            let sample = match Sample::current() {
+                Ok(sample) => sample,
+                Err(e) => {
+                    crate::say_warn!("failed sampling box.stat: {}", e);
+                    continue;
+                }
+            };
+            let _ = tx.send(Load::between(&last, &sample));
+            last = sample;
+        })
+        .start_non_joinable()?;
+    Ok(rx)
+}
+
+/// Same as [`watch`], but returns an async stream which yields the loads
+/// as they're sampled, starting with the first full interval.
+pub fn stream(interval: Duration) -> crate::Result<impl Stream<Item = Load>> {
+    let rx = watch(interval)?;
+    Ok(futures::stream::unfold(rx, |mut rx| async move {
+        rx.changed().await.ok()?;
+        let load = rx.get();
+        Some((load, rx))
+    }))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// moving averages
+////////////////////////////////////////////////////////////////////////////////
+
+/// Exponentially weighted moving average. The weight of a value halves each
+/// `half_life`, so the average reacts to the changes in the same time no
+/// matter how often it's updated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ewma {
+    half_life: Duration,
+    value: Option<f64>,
+}
+
+impl Ewma {
+    /// Creates an empty average.
+    #[inline(always)]
+    pub const fn new(half_life: Duration) -> Self {
+        Self {
+            half_life,
+            value: None,
+        }
+    }
+
+    /// Adds the `value` observed over the `elapsed` time since the previous
+    /// update and returns the new average. The first value is taken as is, as
+    /// well as every value if the half-life is zero.
+    pub fn update(&mut self, value: f64, elapsed: Duration) -> f64 {
+        let new = match self.value {
+            Some(old) if !self.half_life.is_zero() => {
+                let decay = 0.5_f64.powf(elapsed.as_secs_f64() / self.half_life.as_secs_f64());
+                old * decay + value * (1. - decay)
+            }
+            _ => value,
+        };
+        self.value = Some(new);
+        new
+    }
+
+    /// Returns the current average or `None` if there were no updates.
+    #[inline(always)]
+    pub fn get(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// Arithmetic mean of the last `window` values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MovingAverage {
+    window: usize,
+    values: VecDeque<f64>,
+}
+
+impl MovingAverage {
+    /// Creates an empty average over the last `window` values.
+    ///
+    /// # Panics
+    /// If `window` is zero.
+    #[inline]
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window must not be empty");
+        Self {
+            window,
+            values: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Adds the `value` and returns the new average.
+    pub fn push(&mut self, value: f64) -> f64 {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    /// Returns the current average or `None` if there are no values.
+    #[inline]
+    pub fn get(&self) -> Option<f64> {
+        if self.values.is_empty() {
+            return None;
+        }
+        Some(self.values.iter().sum::<f64>() / self.values.len() as f64)
+    }
+
+    /// Returns the number of values the average is computed over, at most
+    /// the window.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if there are no values.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[crate::test(tarantool = "crate")]
+    fn sample_and_watch() {
+        let before = Sample::current().unwrap();
+        crate::lua_state()
+            .exec("for i = 1, 10 do box.space._space:select({}, {limit = 1}) end")
+            .unwrap();
+        let after = Sample::current().unwrap();
+        let load = Load::between(&before, &after);
+        assert!(load.delta.select >= 10, "{:?}", load);
+        assert!(load.rps() > 0.);
+        assert_eq!(Load::between(&after, &before).delta, Counters::default());
+        assert_eq!(Load::idle(after).rps(), 0.);
+
+        let mut rx = watch(Duration::from_millis(10)).unwrap();
+        assert_eq!(rx.get().interval, Duration::ZERO);
+        let load = fiber::block_on(async {
+            rx.changed().await.unwrap();
+            rx.get()
+        });
+        assert!(load.interval >= Duration::from_millis(10), "{:?}", load);
+
+        let loads: Vec<_> =
+            fiber::block_on(stream(Duration::from_millis(1)).unwrap().take(3).collect());
+        assert_eq!(loads.len(), 3);
+        assert!(loads.windows(2).all(|w| w[0].sample.at <= w[1].sample.at));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn moving_averages() {
+        let mut ewma = Ewma::new(Duration::from_secs(1));
+        assert_eq!(ewma.get(), None);
+        assert_eq!(ewma.update(10., Duration::from_secs(1)), 10.);
+        assert_eq!(ewma.update(20., Duration::from_secs(1)), 15.);
+        assert_eq!(ewma.update(15., Duration::from_secs(2)), 15.);
+        assert_eq!(ewma.update(100., Duration::ZERO), 15.);
+
+        let mut avg = MovingAverage::new(3);
+        assert!(avg.is_empty());
+        assert_eq!(avg.get(), None);
+        assert_eq!(avg.push(3.), 3.);
+        assert_eq!(avg.push(6.), 4.5);
+        assert_eq!(avg.push(9.), 6.);
+        assert_eq!(avg.push(12.), 9.);
+        assert_eq!(avg.len(), 3);
+        assert_eq!(avg.get(), Some(9.));
+    }
+}
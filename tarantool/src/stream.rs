@@ -0,0 +1,227 @@
+//! Async streams of the tuples of the spaces and indexes.
+//!
+//! A [`TupleStream`] iterates over an index (see [`Index::stream`] and
+//! [`Space::stream`]) and implements [`futures::Stream`], so the tuples can be
+//! consumed with the standard stream combinators by the async code running
+//! within [`fiber::block_on`].
+//!
+//! The tuples are fetched from the index in batches (see
+//! [`TupleStream::batch_size`]) without yielding and the stream gives way to
+//! the other fibers between the batches, so a long scan doesn't block the
+//! event loop. Each of the tuples is decoded into `T` as it's returned from
+//! the stream.
+//!
+//! **NOTE**: the stream must only be polled by the [`fiber::block_on`]
+//! runtime, the behaviour is undefined otherwise.
+//!
+//! ```no_run
+//! use tarantool::fiber;
+//! use tarantool::fiber::r#async::futures::{StreamExt, TryStreamExt};
+//! use tarantool::space::Space;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Order {
+//!     id: u64,
+//!     amount: u64,
+//! }
+//!
+//! let orders = Space::find("orders").unwrap();
+//! let total: u64 = fiber::block_on(async {
+//!     orders
+//!         .stream::<Order>()?
+//!         .map_ok(|order| order.amount)
+//!         .try_fold(0, |total, amount| async move { Ok(total + amount) })
+//!         .await
+//! })
+//! .unwrap();
+//! ```
+//!
+//! [`Index::stream`]: crate::index::Index::stream
+//! [`Space::stream`]: crate::space::Space::stream
+//! [`fiber::block_on`]: crate::fiber::block_on
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::stream::{FusedStream, Stream};
+
+use crate::fiber;
+use crate::fiber::r#async::context::ContextExt;
+use crate::index::RangeIterator;
+use crate::tuple::{DecodeOwned, Tuple};
+
+/// The default value of [`TupleStream::batch_size`].
+pub const DEFAULT_BATCH_SIZE: usize = 1024;
+
+/// A stream of the tuples of an index decoded into `T`, see the
+/// [module level documentation](self).
+pub struct TupleStream<T> {
+    iter: RangeIterator,
+    batch: VecDeque<Tuple>,
+    batch_size: usize,
+    /// Whether the next batch must be fetched after a yield.
+    should_yield: bool,
+    exhausted: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TupleStream<T> {
+    #[inline]
+    pub(crate) fn new(iter: RangeIterator) -> Self {
+        Self {
+            iter,
+            batch: VecDeque::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            should_yield: false,
+            exhausted: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the number of the tuples fetched from the index without yielding,
+    /// [`DEFAULT_BATCH_SIZE`] by default. The batch size of 0 is treated as 1.
+    #[inline]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn fetch_batch(&mut self) {
+        debug_assert!(self.batch.is_empty());
+        self.batch.extend(self.iter.by_ref().take(self.batch_size));
+        if self.batch.len() < self.batch_size {
+            self.exhausted = true;
+        }
+    }
+}
+
+impl<T> Stream for TupleStream<T>
+where
+    T: DecodeOwned,
+{
+    type Item = crate::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.batch.is_empty() {
+            if this.exhausted {
+                return Poll::Ready(None);
+            }
+            if this.should_yield {
+                this.should_yield = false;
+                // Wake up as soon as the other fibers had a chance to run.
+                //
+                // SAFETY: Safe as long as this stream is polled by
+                // `fiber::block_on` async executor.
+                unsafe { ContextExt::set_deadline(cx, fiber::clock()) }
+                return Poll::Pending;
+            }
+            this.fetch_batch();
+            this.should_yield = true;
+        }
+        Poll::Ready(this.batch.pop_front().map(|tuple| tuple.decode()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = if self.exhausted {
+            Some(self.batch.len())
+        } else {
+            None
+        };
+        (self.batch.len(), upper)
+    }
+}
+
+impl<T> FusedStream for TupleStream<T>
+where
+    T: DecodeOwned,
+{
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.exhausted && self.batch.is_empty()
+    }
+}
+
+impl<T> std::fmt::Debug for TupleStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TupleStream")
+            .field("batch_size", &self.batch_size)
+            .field("buffered", &self.batch.len())
+            .field("exhausted", &self.exhausted)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber::r#async::futures::{StreamExt, TryStreamExt};
+    use crate::space::Space;
+    use crate::temp_space_name;
+
+    #[crate::test(tarantool = "crate")]
+    fn stream() {
+        let space = Space::builder(&temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+        for i in 0..10 {
+            space.insert(&(i, format!("value {}", i))).unwrap();
+        }
+
+        // Other fibers run between the batches.
+        let counter = std::rc::Rc::new(std::cell::Cell::new(0));
+        let c = counter.clone();
+        let jh = fiber::defer(move || loop {
+            c.set(c.get() + 1);
+            if c.get() > 100 {
+                break;
+            }
+            fiber::reschedule();
+        });
+        let res = fiber::check_yield(|| {
+            fiber::block_on(async {
+                space
+                    .stream::<(u32, String)>()
+                    .unwrap()
+                    .batch_size(3)
+                    .map_ok(|(id, _)| (id, counter.get()))
+                    .try_collect::<Vec<_>>()
+                    .await
+            })
+        });
+        let fiber::YieldResult::Yielded(res) = res else {
+            panic!("stream didn't yield");
+        };
+        let res = res.unwrap();
+        assert_eq!(
+            res.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        // Tuples within a batch are returned without yielding.
+        assert_eq!(res[0].1, res[2].1);
+        assert!(res[2].1 < res[3].1);
+        jh.join();
+
+        // Index stream within a range.
+        let values: Vec<String> = fiber::block_on(
+            space
+                .primary_key()
+                .stream::<(u32, String), _>((3,)..(6,))
+                .unwrap()
+                .map(|res| res.unwrap().1)
+                .collect(),
+        );
+        assert_eq!(values, ["value 3", "value 4", "value 5"]);
+
+        // Decoding errors are returned as items.
+        let mut stream = space.primary_key().stream::<(String,), _>(..=(0,)).unwrap();
+        let e = fiber::block_on(stream.next()).unwrap().unwrap_err();
+        assert!(e.to_string().contains("failed to decode tuple"), "{}", e);
+        assert!(fiber::block_on(stream.next()).is_none());
+        assert!(stream.is_terminated());
+
+        space.drop().unwrap();
+    }
+}
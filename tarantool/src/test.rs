@@ -354,18 +354,11 @@ pub mod util {
     }
 
     pub fn sha256_hex(s: &str) -> String {
-        use std::io::Write;
-
         let tlua::AnyLuaString(bytes) = crate::lua_state()
             .eval_with("return require 'digest'.sha256(...)", s)
             .unwrap();
 
-        let mut buffer = Vec::new();
-        for b in bytes {
-            write!(&mut buffer, "{b:02x}").unwrap();
-        }
-
-        String::from_utf8(buffer).unwrap()
+        crate::util::hex_encode(bytes)
     }
 
     /// Defines the native tarantool stored procedure with name given in `proc_name`.
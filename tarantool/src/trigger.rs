@@ -1,7 +1,49 @@
-use crate::error::{TarantoolError, TarantoolErrorCode};
+//! Box: triggers
+//!
+//! Functions for registering rust closures as tarantool triggers:
+//! - [`on_shutdown`]
+//! - [`on_schema_init`]
+//! - [`Space::on_replace`] and [`Space::before_replace`]
+//! - [`session::on_connect`] and [`session::on_disconnect`]
+//! - [`cfg::on_change`]
+//! - [`on_commit`] and [`on_rollback`]
+//!
+//! The closures (except for the [`on_shutdown`] one) are wrapped into lua
+//! functions which are kept alive until the trigger is unregistered via the
+//! returned [`TriggerHandle`].
+//!
+//! The triggers registered by a module are kept in a registry of this module
+//! identified by its name (see [`set_module_name`]):
+//! - the triggers of the same kind (and the same space) are executed by a
+//!   single lua trigger in the order set via [`TriggerHandle::with_order`]
+//!   (registration order by default),
+//! - a trigger can be given a name via [`TriggerHandle::with_name`] and
+//!   found by it via [`find`],
+//! - [`list`] returns all of the registered triggers,
+//! - [`unregister_all`] unregisters all of them, which must be done before
+//!   the module is unloaded (e.g. on hot reload), so that the triggers don't
+//!   call into the unloaded code.
+//!
+//! **NOTE**: the triggers are executed within the transaction or the session
+//! handling code and must not yield.
+//!
+//! See also:
+//! - [Lua reference: Triggers](https://www.tarantool.io/en/doc/latest/concepts/triggers/)
+//!
+//! [`Space::on_replace`]: crate::space::Space::on_replace
+//! [`Space::before_replace`]: crate::space::Space::before_replace
+//! [`session::on_connect`]: crate::session::on_connect
+//! [`session::on_disconnect`]: crate::session::on_disconnect
+//! [`cfg::on_change`]: crate::cfg::on_change
+use crate::cfg::Cfg;
+use crate::error::{BoxError, IntoBoxError, TarantoolError, TarantoolErrorCode};
 use crate::ffi::tarantool as ffi;
 use crate::set_error;
+use crate::space::SpaceId;
+use crate::tuple::Tuple;
+use once_cell::sync::OnceCell;
 use std::io;
+use std::panic::{self, AssertUnwindSafe};
 
 /// Set a callback to be called on Tarantool shutdown.
 pub fn on_shutdown<F: FnOnce() + 'static>(cb: F) -> Result<(), TarantoolError> {
@@ -25,3 +67,1021 @@ pub fn on_shutdown<F: FnOnce() + 'static>(cb: F) -> Result<(), TarantoolError> {
         0
     }
 }
+
+/// Set a callback to be called when the system spaces are created or
+/// recovered, i.e. before any of the user data is loaded. This is the place
+/// to set [`Space::before_replace`] triggers on the system spaces.
+///
+/// Must be called before `box.cfg{ .. }`.
+///
+/// If the closure returns an error, `box.cfg{ .. }` fails with this error.
+///
+/// [`Space::before_replace`]: crate::space::Space::before_replace
+pub fn on_schema_init<F, E>(mut f: F) -> crate::Result<TriggerHandle>
+where
+    F: FnMut() -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    register(
+        TriggerKind::OnSchemaInit,
+        None,
+        tlua::function0(move || call_trigger(&mut f).is_some()),
+    )
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// RequestType
+////////////////////////////////////////////////////////////////////////////////
+
+crate::define_str_enum! {
+    /// Type of the request which caused a [`Space::on_replace`] or
+    /// [`Space::before_replace`] trigger to fire.
+    ///
+    /// [`Space::on_replace`]: crate::space::Space::on_replace
+    /// [`Space::before_replace`]: crate::space::Space::before_replace
+    pub enum RequestType {
+        Insert = "INSERT",
+        Replace = "REPLACE",
+        Update = "UPDATE",
+        Upsert = "UPSERT",
+        Delete = "DELETE",
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// BeforeReplace
+////////////////////////////////////////////////////////////////////////////////
+
+/// The outcome of a [`Space::before_replace`] trigger.
+///
+/// [`Space::before_replace`]: crate::space::Space::before_replace
+#[derive(Debug, Clone)]
+pub enum BeforeReplace {
+    /// Proceed with the operation as is.
+    Keep,
+    /// Replace the new tuple with the given one.
+    Replace(Tuple),
+    /// Make the operation a no-op, i.e. keep the old tuple.
+    Skip,
+    /// Delete the old tuple instead of replacing it.
+    Delete,
+}
+
+impl BeforeReplace {
+    fn into_lua_result(self) -> (&'static str, Option<Tuple>) {
+        match self {
+            Self::Keep => ("keep", None),
+            Self::Replace(tuple) => ("replace", Some(tuple)),
+            Self::Skip => ("skip", None),
+            Self::Delete => ("delete", None),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TriggerKind
+////////////////////////////////////////////////////////////////////////////////
+
+crate::define_str_enum! {
+    /// The event a registered trigger is fired on, see [`TriggerInfo`].
+    pub enum TriggerKind {
+        OnReplace = "on_replace",
+        BeforeReplace = "before_replace",
+        OnConnect = "on_connect",
+        OnDisconnect = "on_disconnect",
+        OnSchemaInit = "on_schema_init",
+        OnCfg = "on_cfg",
+        OnCommit = "on_commit",
+        OnRollback = "on_rollback",
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TriggerHandle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle of a registered trigger, which can be used to unregister it.
+///
+/// Dropping the handle doesn't unregister the trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TriggerHandle {
+    id: u64,
+}
+
+impl TriggerHandle {
+    /// Returns the unique id of the trigger.
+    #[inline(always)]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Sets the name of the trigger by which it can be found via [`find`].
+    /// The names are unique among the triggers registered by this module.
+    ///
+    /// Returns the handle, so that the call can be chained with the
+    /// registration:
+    /// ```no_run
+    /// # use tarantool::space::Space;
+    /// # let space = Space::find("audit").unwrap();
+    /// space
+    ///     .on_replace(|_, _, _| Ok::<_, String>(()))?
+    ///     .with_name("audit")?
+    ///     .with_order(-10)?;
+    /// # Ok::<(), tarantool::error::Error>(())
+    /// ```
+    pub fn with_name(self, name: impl Into<String>) -> crate::Result<Self> {
+        let name = name.into();
+        match self.configure(Some(&name), None)? {
+            Configured::Ok => Ok(self),
+            Configured::NotFound => Err(self.not_found()),
+            Configured::Duplicate => Err(BoxError::new(
+                TarantoolErrorCode::IllegalParams,
+                format!("trigger '{}' is already registered", name),
+            )
+            .into()),
+        }
+    }
+
+    /// Sets the order of the trigger among the triggers of the same kind
+    /// (and the same space) registered by this module. The triggers are
+    /// executed in ascending order, the ones with the same order are executed
+    /// in the order of registration. The default order is `0`.
+    ///
+    /// Doesn't affect the [`on_commit`] and [`on_rollback`] triggers, which
+    /// are executed in the order defined by tarantool.
+    pub fn with_order(self, order: i32) -> crate::Result<Self> {
+        match self.configure(None, Some(order))? {
+            Configured::NotFound => Err(self.not_found()),
+            _ => Ok(self),
+        }
+    }
+
+    /// Returns the information about the trigger or `None` if it's not
+    /// registered.
+    pub fn info(&self) -> crate::Result<Option<TriggerInfo>> {
+        call_registry("info", self.id)
+    }
+
+    /// Unregisters the trigger and releases the closure.
+    ///
+    /// Returns `false` if the trigger was already unregistered. Unregistering
+    /// a trigger of a space which has been dropped is not an error.
+    pub fn unregister(self) -> crate::Result<bool> {
+        call_registry("unregister", self.id)
+    }
+
+    fn configure(&self, name: Option<&str>, order: Option<i32>) -> crate::Result<Configured> {
+        call_registry("configure", (self.id, name, order))
+    }
+
+    fn not_found(&self) -> crate::error::Error {
+        BoxError::new(
+            TarantoolErrorCode::IllegalParams,
+            format!("trigger {} is not registered", self.id),
+        )
+        .into()
+    }
+}
+
+crate::define_str_enum! {
+    enum Configured {
+        Ok = "ok",
+        NotFound = "not_found",
+        Duplicate = "duplicate",
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TriggerInfo
+////////////////////////////////////////////////////////////////////////////////
+
+/// The information about a registered trigger, see [`list`].
+#[derive(Debug, Clone, PartialEq, Eq, tlua::LuaRead)]
+pub struct TriggerInfo {
+    pub id: u64,
+    pub kind: TriggerKind,
+    /// The id of the space for the [`TriggerKind::OnReplace`] and
+    /// [`TriggerKind::BeforeReplace`] triggers.
+    pub space_id: Option<SpaceId>,
+    /// The name set via [`TriggerHandle::with_name`].
+    pub name: Option<String>,
+    /// The order set via [`TriggerHandle::with_order`].
+    pub order: i32,
+}
+
+impl TriggerInfo {
+    /// Returns the handle of the trigger.
+    #[inline(always)]
+    pub fn handle(&self) -> TriggerHandle {
+        TriggerHandle { id: self.id }
+    }
+}
+
+/// Returns the triggers registered by this module, grouped by the kind and
+/// the space in the order of execution.
+pub fn list() -> crate::Result<Vec<TriggerInfo>> {
+    call_registry("list", ())
+}
+
+/// Returns the handle of the trigger registered by this module with the given
+/// `name` (see [`TriggerHandle::with_name`]).
+pub fn find(name: &str) -> crate::Result<Option<TriggerHandle>> {
+    let id: Option<u64> = call_registry("find", name)?;
+    Ok(id.map(|id| TriggerHandle { id }))
+}
+
+/// Unregisters all of the triggers registered by this module and returns
+/// their number.
+///
+/// Should be called when the module is unloaded (e.g. before it's reloaded
+/// with the new version), otherwise the triggers keep calling into the code
+/// which is no longer there. The triggers are found by the module name (see
+/// [`set_module_name`]), so the new version can also call it on load.
+pub fn unregister_all() -> crate::Result<usize> {
+    call_registry("unregister_all", ())
+}
+
+/// The module name used if [`set_module_name`] isn't called.
+pub const DEFAULT_MODULE_NAME: &str = "default";
+
+static MODULE_NAME: OnceCell<String> = OnceCell::new();
+
+/// Sets the name of this module, which identifies its trigger registry.
+///
+/// Each module (i.e. each copy of this crate loaded into the process) should
+/// have a distinct name, so that [`list`], [`find`] and [`unregister_all`]
+/// don't touch the triggers of the other modules. The modules which don't
+/// call this function share the registry named [`DEFAULT_MODULE_NAME`].
+///
+/// The name is kept by the reloaded version of the module, so it can find and
+/// unregister the triggers left by the previous one. The trigger ids are
+/// allocated by the registry and stay unique across the reloads.
+///
+/// Must be called before any trigger is registered or any other function of
+/// this module is called, returns an error if a different name is already
+/// in use.
+pub fn set_module_name(name: impl Into<String>) -> crate::Result<()> {
+    let name = name.into();
+    let current = MODULE_NAME.get_or_init(|| name.clone());
+    if *current != name {
+        return Err(BoxError::new(
+            TarantoolErrorCode::IllegalParams,
+            format!("trigger module name is already set to '{}'", current),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[inline]
+fn module_name() -> &'static str {
+    MODULE_NAME.get_or_init(|| DEFAULT_MODULE_NAME.into())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// on_commit & on_rollback
+////////////////////////////////////////////////////////////////////////////////
+
+/// Set a callback to be called when the current transaction is committed.
+///
+/// The trigger is unregistered automatically once the transaction ends
+/// (either way). It's an error to call this function outside of a
+/// transaction.
+///
+/// The commit can't be undone, so panics in the callback are logged and
+/// ignored.
+pub fn on_commit<F: FnOnce() + 'static>(f: F) -> crate::Result<TriggerHandle> {
+    register(
+        TriggerKind::OnCommit,
+        None,
+        txn_trigger(TriggerKind::OnCommit, f),
+    )
+}
+
+/// Set a callback to be called when the current transaction is rolled back.
+///
+/// The trigger is unregistered automatically once the transaction ends
+/// (either way). It's an error to call this function outside of a
+/// transaction.
+///
+/// Panics in the callback are logged and ignored.
+pub fn on_rollback<F: FnOnce() + 'static>(f: F) -> crate::Result<TriggerHandle> {
+    register(
+        TriggerKind::OnRollback,
+        None,
+        txn_trigger(TriggerKind::OnRollback, f),
+    )
+}
+
+fn txn_trigger<F: FnOnce() + 'static>(
+    kind: TriggerKind,
+    f: F,
+) -> impl tlua::PushOneInto<tlua::LuaState, Err = tlua::Void> {
+    let mut f = Some(f);
+    tlua::function0(move || {
+        let Some(f) = f.take() else {
+            return;
+        };
+        if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+            crate::say_error!("{} trigger panicked", kind);
+        }
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// impl
+////////////////////////////////////////////////////////////////////////////////
+
+pub(crate) fn on_replace<F, E>(space_id: SpaceId, mut f: F) -> crate::Result<TriggerHandle>
+where
+    F: FnMut(Option<Tuple>, Option<Tuple>, RequestType) -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    register(
+        TriggerKind::OnReplace,
+        Some(space_id),
+        tlua::function3(
+            move |old: Option<Tuple>, new: Option<Tuple>, op: RequestType| {
+                call_trigger(|| f(old, new, op)).is_some()
+            },
+        ),
+    )
+}
+
+pub(crate) fn before_replace<F, E>(space_id: SpaceId, mut f: F) -> crate::Result<TriggerHandle>
+where
+    F: FnMut(Option<Tuple>, Option<Tuple>, RequestType) -> Result<BeforeReplace, E> + 'static,
+    E: IntoBoxError,
+{
+    register(
+        TriggerKind::BeforeReplace,
+        Some(space_id),
+        tlua::function3(
+            move |old: Option<Tuple>, new: Option<Tuple>, op: RequestType| match call_trigger(
+                || f(old, new, op),
+            ) {
+                Some(res) => {
+                    let (action, tuple) = res.into_lua_result();
+                    (true, action, tuple)
+                }
+                None => (false, "", None),
+            },
+        ),
+    )
+}
+
+pub(crate) fn on_session<F, E>(kind: TriggerKind, mut f: F) -> crate::Result<TriggerHandle>
+where
+    F: FnMut() -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    register(
+        kind,
+        None,
+        tlua::function0(move || call_trigger(&mut f).is_some()),
+    )
+}
+
+pub(crate) fn on_cfg<F, E>(mut f: F) -> crate::Result<TriggerHandle>
+where
+    F: FnMut(Vec<String>, Cfg, Cfg) -> Result<(), E> + 'static,
+    E: IntoBoxError,
+{
+    register(
+        TriggerKind::OnCfg,
+        None,
+        tlua::function3(move |changed: Vec<String>, current: Cfg, requested: Cfg| {
+            call_trigger(|| f(changed, current, requested)).is_some()
+        }),
+    )
+}
+
+/// Calls the trigger closure converting the error or the panic into the
+/// current diagnostics error. Returns `None` in this case, in which case the
+/// lua wrapper rethrows the error.
+fn call_trigger<R, E>(f: impl FnOnce() -> Result<R, E>) -> Option<R>
+where
+    E: IntoBoxError,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(res)) => Some(res),
+        Ok(Err(e)) => {
+            e.set_last_error();
+            None
+        }
+        Err(payload) => {
+            let message = crate::util::panic_message(&*payload);
+            BoxError::new(
+                TarantoolErrorCode::ProcC,
+                format!("trigger panicked: {}", message),
+            )
+            .set_last();
+            None
+        }
+    }
+}
+
+fn register<F>(kind: TriggerKind, space_id: Option<SpaceId>, f: F) -> crate::Result<TriggerHandle>
+where
+    F: tlua::PushOneInto<tlua::LuaState, Err = tlua::Void>,
+{
+    let id = call_registry("register", (kind, space_id, f))?;
+    Ok(TriggerHandle { id })
+}
+
+/// Calls the `method` of the trigger registry of this module, which is found
+/// by the [module name](set_module_name).
+fn call_registry<R, A>(method: &'static str, args: A) -> crate::Result<R>
+where
+    (&'static str, &'static str, A): tlua::PushInto<tlua::LuaState>,
+    <(&'static str, &'static str, A) as tlua::PushInto<tlua::LuaState>>::Err: Into<tlua::Void>,
+    R: tlua::LuaRead<tlua::PushGuard<tlua::LuaFunction<tlua::PushGuard<tlua::LuaThread>>>>,
+{
+    let res = trigger_registry()
+        .call_with_args((module_name(), method, args))
+        .map_err(tlua::LuaError::from)?;
+    Ok(res)
+}
+
+fn trigger_registry() -> &'static crate::lua::Chunk {
+    crate::lua!(
+        tarantool = "crate",
+        r#"
+local key, method = ...
+local registry = debug.getregistry()
+registry.tarantool_rust_trigger_registries = registry.tarantool_rust_trigger_registries or {}
+local module = registry.tarantool_rust_trigger_registries[key]
+if module ~= nil then
+    return module[method](select(3, ...))
+end
+
+-- Registered triggers by id.
+local triggers = {}
+-- Triggers of the same kind (and the same space) are executed by a single
+-- dispatcher function, which is installed while the chain isn't empty.
+local chains = {}
+local seq = 0
+
+local function space_trigger(name, space_id)
+    return function(new, old)
+        local space = box.space[space_id]
+        if space == nil then
+            if new == nil then
+                -- space was dropped along with its triggers
+                return
+            end
+            box.error(box.error.NO_SUCH_SPACE, '#' .. space_id)
+        end
+        return space[name](space, new, old)
+    end
+end
+
+local function call_all(chain, ...)
+    -- The list is never modified in place, so the triggers may unregister
+    -- themselves.
+    for _, trigger in ipairs(chain.triggers) do
+        if not trigger.f(...) then
+            box.error()
+        end
+    end
+end
+
+local function void_dispatcher(chain)
+    return function()
+        call_all(chain)
+    end
+end
+
+local kinds = {
+    on_replace = {
+        set = function(space_id)
+            return space_trigger('on_replace', space_id)
+        end,
+        dispatcher = function(chain)
+            return function(old, new, _, op)
+                call_all(chain, old, new, op)
+            end
+        end,
+    },
+    before_replace = {
+        set = function(space_id)
+            return space_trigger('before_replace', space_id)
+        end,
+        dispatcher = function(chain)
+            return function(old, new, _, op)
+                for _, trigger in ipairs(chain.triggers) do
+                    local ok, action, tuple = trigger.f(old, new, op)
+                    if not ok then
+                        box.error()
+                    end
+                    if action == 'replace' then
+                        new = tuple
+                    elseif action == 'skip' then
+                        new = old
+                    elseif action == 'delete' then
+                        new = nil
+                    end
+                end
+                return new
+            end
+        end,
+    },
+    on_connect = {
+        set = function() return box.session.on_connect end,
+        dispatcher = void_dispatcher,
+    },
+    on_disconnect = {
+        set = function() return box.session.on_disconnect end,
+        dispatcher = void_dispatcher,
+    },
+    on_schema_init = {
+        set = function() return box.ctl.on_schema_init end,
+        dispatcher = void_dispatcher,
+    },
+    on_cfg = {
+        set = function() return registry.tarantool_rust_on_cfg end,
+        dispatcher = function(chain)
+            return function(changed, current, requested)
+                call_all(chain, changed, current, requested)
+            end
+        end,
+    },
+    on_commit = { txn = box.on_commit, other = box.on_rollback },
+    on_rollback = { txn = box.on_rollback, other = box.on_commit },
+}
+
+local function sorted(list)
+    table.sort(list, function(a, b)
+        if a.order ~= b.order then
+            return a.order < b.order
+        end
+        return a.seq < b.seq
+    end)
+    return list
+end
+
+local function attach(trigger)
+    local chain = chains[trigger.chain]
+    if chain == nil then
+        local kind = kinds[trigger.kind]
+        chain = { triggers = {}, set = kind.set(trigger.space_id) }
+        chain.dispatcher = kind.dispatcher(chain)
+    end
+    -- The dispatcher is reinstalled in case the space has been recreated.
+    pcall(chain.set, nil, chain.dispatcher)
+    chain.set(chain.dispatcher)
+    chains[trigger.chain] = chain
+    local list = { trigger }
+    for _, t in ipairs(chain.triggers) do
+        table.insert(list, t)
+    end
+    chain.triggers = sorted(list)
+end
+
+local function detach(trigger)
+    local chain = chains[trigger.chain]
+    local list = {}
+    for _, t in ipairs(chain.triggers) do
+        if t ~= trigger then
+            table.insert(list, t)
+        end
+    end
+    chain.triggers = list
+    if #list == 0 then
+        chains[trigger.chain] = nil
+        pcall(chain.set, nil, chain.dispatcher)
+    end
+end
+
+local function info(trigger)
+    return {
+        id = trigger.id,
+        kind = trigger.kind,
+        space_id = trigger.space_id,
+        name = trigger.name,
+        order = trigger.order,
+    }
+end
+
+module = {}
+
+function module.register(kind, space_id, f)
+    seq = seq + 1
+    local id = seq
+    local trigger = {
+        id = id,
+        kind = kind,
+        space_id = space_id,
+        order = 0,
+        seq = seq,
+        f = f,
+        chain = kind .. '#' .. tostring(space_id),
+    }
+    local txn = kinds[kind].txn
+    if txn ~= nil then
+        -- Transaction triggers fire at most once, the trigger of the other
+        -- outcome forgets the trigger if it doesn't fire.
+        trigger.func = function()
+            triggers[id] = nil
+            f()
+        end
+        trigger.cleanup = function()
+            triggers[id] = nil
+        end
+        txn(trigger.func)
+        kinds[kind].other(trigger.cleanup)
+    else
+        attach(trigger)
+    end
+    triggers[id] = trigger
+    return id
+end
+
+function module.unregister(id)
+    local trigger = triggers[id]
+    if trigger == nil then
+        return false
+    end
+    triggers[id] = nil
+    if trigger.func ~= nil then
+        local kind = kinds[trigger.kind]
+        pcall(kind.txn, nil, trigger.func)
+        pcall(kind.other, nil, trigger.cleanup)
+    else
+        detach(trigger)
+    end
+    return true
+end
+
+function module.configure(id, name, order)
+    local trigger = triggers[id]
+    if trigger == nil then
+        return 'not_found'
+    end
+    if name ~= nil then
+        for _, t in pairs(triggers) do
+            if t ~= trigger and t.name == name then
+                return 'duplicate'
+            end
+        end
+        trigger.name = name
+    end
+    if order ~= nil then
+        trigger.order = order
+        local chain = chains[trigger.chain]
+        if chain ~= nil then
+            local list = {}
+            for i, t in ipairs(chain.triggers) do
+                list[i] = t
+            end
+            chain.triggers = sorted(list)
+        end
+    end
+    return 'ok'
+end
+
+function module.info(id)
+    local trigger = triggers[id]
+    return trigger and info(trigger)
+end
+
+function module.list()
+    local list = {}
+    for _, trigger in pairs(triggers) do
+        table.insert(list, trigger)
+    end
+    table.sort(list, function(a, b)
+        if a.chain ~= b.chain then
+            return a.chain < b.chain
+        end
+        if a.order ~= b.order then
+            return a.order < b.order
+        end
+        return a.seq < b.seq
+    end)
+    for i, trigger in ipairs(list) do
+        list[i] = info(trigger)
+    end
+    return list
+end
+
+function module.find(name)
+    for id, trigger in pairs(triggers) do
+        if trigger.name == name then
+            return id
+        end
+    end
+    return nil
+end
+
+function module.unregister_all()
+    local ids = {}
+    for id in pairs(triggers) do
+        table.insert(ids, id)
+    end
+    for _, id in ipairs(ids) do
+        module.unregister(id)
+    end
+    return #ids
+end
+
+registry.tarantool_rust_trigger_registries[key] = module
+return module[method](select(3, ...))
+"#
+    )
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::space::Space;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    #[crate::test(tarantool = "crate")]
+    fn space_on_replace() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let log_clone = log.clone();
+        let handle = space
+            .on_replace(move |old, new, op| {
+                let old = old.map(|t| t.decode::<(u32, String)>().unwrap());
+                let new = new.map(|t| t.decode::<(u32, String)>().unwrap());
+                if matches!(&new, Some((_, s)) if s == "forbidden") {
+                    return Err("forbidden value");
+                }
+                log_clone.borrow_mut().push((old, new, op));
+                Ok(())
+            })
+            .unwrap();
+
+        space.insert(&(1, "foo")).unwrap();
+        space.replace(&(1, "bar")).unwrap();
+        space.delete(&[1]).unwrap();
+        let e = space.insert(&(2, "forbidden")).unwrap_err();
+        assert_eq!(e.to_string(), "box error: ProcC: forbidden value");
+        assert_eq!(space.len().unwrap(), 0);
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                (None, Some((1, "foo".into())), RequestType::Insert),
+                (
+                    Some((1, "foo".into())),
+                    Some((1, "bar".into())),
+                    RequestType::Replace
+                ),
+                (Some((1, "bar".into())), None, RequestType::Delete),
+            ]
+        );
+
+        assert!(handle.unregister().unwrap());
+        assert!(!handle.unregister().unwrap());
+        space.insert(&(3, "forbidden")).unwrap();
+        assert_eq!(log.borrow().len(), 3);
+
+        // Unregistering the trigger of a dropped space is fine.
+        let handle = space.on_replace(|_, _, _| Ok::<_, String>(())).unwrap();
+        space.drop().unwrap();
+        assert!(handle.unregister().unwrap());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn space_before_replace() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let handle = space
+            .before_replace(|old, new, _| {
+                let Some(new) = new else {
+                    return Ok(BeforeReplace::Keep);
+                };
+                let (id, value): (u32, String) = new.decode()?;
+                let res = match value.as_str() {
+                    "skip" => BeforeReplace::Skip,
+                    "delete" if old.is_some() => BeforeReplace::Delete,
+                    "upper" => BeforeReplace::Replace(Tuple::new(&(id, value.to_uppercase()))?),
+                    "panic" => panic!("oh no"),
+                    _ => BeforeReplace::Keep,
+                };
+                Ok::<_, crate::error::Error>(res)
+            })
+            .unwrap();
+
+        let get = |id: u32| {
+            space
+                .get(&[id])
+                .unwrap()
+                .map(|t| t.decode::<(u32, String)>().unwrap().1)
+        };
+
+        space.insert(&(1, "foo")).unwrap();
+        assert_eq!(get(1).as_deref(), Some("foo"));
+        space.replace(&(1, "skip")).unwrap();
+        assert_eq!(get(1).as_deref(), Some("foo"));
+        space.replace(&(1, "upper")).unwrap();
+        assert_eq!(get(1).as_deref(), Some("UPPER"));
+        space.replace(&(1, "delete")).unwrap();
+        assert_eq!(get(1), None);
+
+        let e = space.insert(&(2, "panic")).unwrap_err();
+        assert_eq!(e.to_string(), "box error: ProcC: trigger panicked: oh no");
+        assert_eq!(get(2), None);
+
+        assert!(handle.unregister().unwrap());
+        space.insert(&(2, "skip")).unwrap();
+        assert_eq!(get(2).as_deref(), Some("skip"));
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn execution_order() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let push = |name: &'static str| {
+            let log = log.clone();
+            move |_: Option<Tuple>, _: Option<Tuple>, _: RequestType| {
+                log.borrow_mut().push(name);
+                Ok::<_, String>(())
+            }
+        };
+        let a = space.on_replace(push("a")).unwrap();
+        let b = space.on_replace(push("b")).unwrap();
+        let c = space.on_replace(push("c")).unwrap();
+        space.insert(&(1,)).unwrap();
+        assert_eq!(*log.borrow(), ["a", "b", "c"]);
+
+        assert!(b.unregister().unwrap());
+        log.borrow_mut().clear();
+        space.insert(&(2,)).unwrap();
+        assert_eq!(*log.borrow(), ["a", "c"]);
+
+        assert!(a.unregister().unwrap());
+        assert!(c.unregister().unwrap());
+        log.borrow_mut().clear();
+        space.insert(&(3,)).unwrap();
+        assert!(log.borrow().is_empty());
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn ordering_and_introspection() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let push = |name: &'static str| {
+            let log = log.clone();
+            move |_: Option<Tuple>, _: Option<Tuple>, _: RequestType| {
+                log.borrow_mut().push(name);
+                Ok::<_, String>(())
+            }
+        };
+        let name = "trigger_ordering_and_introspection";
+        let a = space
+            .on_replace(push("a"))
+            .unwrap()
+            .with_name(name)
+            .unwrap();
+        let b = space.on_replace(push("b")).unwrap();
+        let c = space.on_replace(push("c")).unwrap().with_order(-1).unwrap();
+        space.insert(&(1,)).unwrap();
+        assert_eq!(*log.borrow(), ["c", "a", "b"]);
+
+        a.with_order(10).unwrap();
+        log.borrow_mut().clear();
+        space.insert(&(2,)).unwrap();
+        assert_eq!(*log.borrow(), ["c", "b", "a"]);
+
+        let e = b.with_name(name).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "box error: IllegalParams: trigger '{}' is already registered",
+                name
+            )
+        );
+        assert_eq!(find(name).unwrap(), Some(a));
+        assert_eq!(find("no such trigger").unwrap(), None);
+        assert_eq!(
+            a.info().unwrap(),
+            Some(TriggerInfo {
+                id: a.id(),
+                kind: TriggerKind::OnReplace,
+                space_id: Some(space.id()),
+                name: Some(name.into()),
+                order: 10,
+            })
+        );
+        let ids: Vec<_> = list()
+            .unwrap()
+            .into_iter()
+            .filter(|info| info.space_id == Some(space.id()))
+            .map(|info| info.handle())
+            .collect();
+        assert_eq!(ids, [c, b, a]);
+
+        // The trigger may unregister itself.
+        assert!(c.unregister().unwrap());
+        let this = Rc::new(Cell::new(None::<TriggerHandle>));
+        let this_clone = this.clone();
+        let d = space
+            .on_replace(move |_, _, _| this_clone.get().unwrap().unregister().map(drop))
+            .unwrap();
+        this.set(Some(d));
+        log.borrow_mut().clear();
+        space.insert(&(3,)).unwrap();
+        space.insert(&(4,)).unwrap();
+        assert_eq!(*log.borrow(), ["b", "a", "b", "a"]);
+        assert_eq!(d.info().unwrap(), None);
+        assert!(!c.unregister().unwrap());
+        c.with_order(1).unwrap_err();
+
+        let count = list().unwrap().len();
+        assert!(count >= 2);
+        assert_eq!(unregister_all().unwrap(), count);
+        assert_eq!(list().unwrap(), []);
+        log.borrow_mut().clear();
+        space.insert(&(5,)).unwrap();
+        assert!(log.borrow().is_empty());
+
+        // The registry has been used, so the name can't be changed anymore.
+        set_module_name(DEFAULT_MODULE_NAME).unwrap();
+        let e = set_module_name("other").unwrap_err();
+        assert!(e.to_string().contains("already set to 'default'"), "{}", e);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn before_replace_chain() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let map = |f: fn(&str) -> String| {
+            move |_: Option<Tuple>, new: Option<Tuple>, _: RequestType| {
+                let (id, value): (u32, String) = new.unwrap().decode()?;
+                Ok::<_, crate::error::Error>(BeforeReplace::Replace(Tuple::new(&(id, f(&value)))?))
+            }
+        };
+        let exclaim = space.before_replace(map(|s| format!("{}!", s))).unwrap();
+        let upper = space
+            .before_replace(map(str::to_uppercase))
+            .unwrap()
+            .with_order(-1)
+            .unwrap();
+        space.insert(&(1, "foo")).unwrap();
+        let (_, value): (u32, String) = space.get(&[1]).unwrap().unwrap().decode().unwrap();
+        assert_eq!(value, "FOO!");
+
+        assert!(upper.unregister().unwrap());
+        assert!(exclaim.unregister().unwrap());
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn transaction_triggers() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let push = |name: &'static str| {
+            let log = log.clone();
+            move || log.borrow_mut().push(name)
+        };
+        let is_txn_trigger = |info: &TriggerInfo| {
+            matches!(info.kind, TriggerKind::OnCommit | TriggerKind::OnRollback)
+        };
+
+        crate::transaction::transaction(|| -> crate::Result<()> {
+            space.insert(&(1,))?;
+            on_commit(push("commit"))?;
+            on_rollback(push("rollback"))?;
+            assert!(on_commit(push("unregistered"))?.unregister()?);
+            assert_eq!(list()?.iter().filter(|i| is_txn_trigger(i)).count(), 2);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(*log.borrow(), ["commit"]);
+        assert!(!list().unwrap().iter().any(is_txn_trigger));
+
+        crate::transaction::transaction(|| -> crate::Result<()> {
+            space.insert(&(2,))?;
+            on_commit(push("commit"))?;
+            on_rollback(push("rollback"))?;
+            Err(crate::error::Error::other("rollback"))
+        })
+        .unwrap_err();
+        assert_eq!(*log.borrow(), ["commit", "rollback"]);
+        assert!(!list().unwrap().iter().any(is_txn_trigger));
+
+        on_commit(|| {}).unwrap_err();
+        space.drop().unwrap();
+    }
+}
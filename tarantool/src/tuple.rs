@@ -21,6 +21,7 @@ use std::ptr::{null, NonNull};
 use rmp::Marker;
 use serde::Serialize;
 
+use crate::buffer::Lease;
 use crate::error::{self, Error, Result, TarantoolError};
 use crate::ffi::tarantool as ffi;
 use crate::index;
@@ -275,6 +276,76 @@ impl Tuple {
         return Decode::decode(&self.to_vec());
     }
 
+    /// Decode tuple contents as `T` without copying the tuple data.
+    ///
+    /// Unlike [`Tuple::decode`], `T` is allowed to borrow from the tuple, e.g.
+    /// contain `&str` or `&[u8]` fields, which point directly into the tuple's
+    /// data. The tuple is kept referenced as long as `T` is alive, so this is
+    /// useful for avoiding allocations when reading lots of tuples.
+    ///
+    /// ```no_run
+    /// use tarantool::tuple::Tuple;
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct User<'a> {
+    ///     id: u64,
+    ///     name: &'a str,
+    ///     #[serde(with = "serde_bytes")]
+    ///     avatar: &'a [u8],
+    /// }
+    ///
+    /// let tuple: Tuple;
+    /// # tuple = Tuple::new(&(1, "joe", serde_bytes::Bytes::new(b"\x89PNG"))).unwrap();
+    /// let user: User = tuple.decode_borrowed().unwrap();
+    /// assert_eq!(user.name, "joe");
+    /// ```
+    #[cfg(feature = "picodata")]
+    #[inline]
+    pub fn decode_borrowed<'a, T>(&'a self) -> Result<T>
+    where
+        T: Decode<'a>,
+    {
+        Decode::decode(self.data())
+    }
+
+    /// Decode tuple contents as `T` using the [`msgpack::Decode`] trait with
+    /// the given `context` instead of `serde`.
+    ///
+    /// Unlike [`Tuple::decode`] this allows choosing how the tuple fields which
+    /// don't match the struct's fields are handled, e.g. to reject the tuples
+    /// with the extra fields instead of skipping them:
+    /// ```no_run
+    /// use tarantool::msgpack::{Context, Decode, DecodeMode};
+    /// use tarantool::tuple::Tuple;
+    ///
+    /// #[derive(Decode)]
+    /// struct User {
+    ///     id: u64,
+    ///     name: String,
+    /// }
+    ///
+    /// let tuple: Tuple;
+    /// # tuple = Tuple::new(&(1, "joe", "extra")).unwrap();
+    /// let user: User = tuple.decode_msgpack_with(&Context::DEFAULT).unwrap();
+    /// assert_eq!(user.name, "joe");
+    ///
+    /// let context = Context::DEFAULT.with_decode_mode(DecodeMode::Strict);
+    /// assert!(tuple.decode_msgpack_with::<User>(&context).is_err());
+    /// ```
+    ///
+    /// [`msgpack::Decode`]: crate::msgpack::Decode
+    #[inline]
+    pub fn decode_msgpack_with<T>(&self, context: &crate::msgpack::Context) -> Result<T>
+    where
+        T: for<'de> crate::msgpack::Decode<'de>,
+    {
+        #[cfg(feature = "picodata")]
+        let res = T::decode(&mut self.data(), context);
+        #[cfg(not(feature = "picodata"))]
+        let res = T::decode(&mut &self.to_vec()[..], context);
+        Ok(res?)
+    }
+
     /// Get tuple contents as a vector of raw bytes.
     ///
     /// Returns tuple bytes in msgpack encoding.
@@ -422,6 +493,22 @@ impl<'de> serde_bytes::Deserialize<'de> for Tuple {
     }
 }
 
+/// Writes the msgpack array contained in the tuple as is.
+impl crate::msgpack::Encode for Tuple {
+    #[inline(always)]
+    fn encode(
+        &self,
+        w: &mut impl std::io::Write,
+        _context: &crate::msgpack::Context,
+    ) -> std::result::Result<(), crate::msgpack::EncodeError> {
+        #[cfg(feature = "picodata")]
+        w.write_all(self.data())?;
+        #[cfg(not(feature = "picodata"))]
+        w.write_all(&self.to_vec())?;
+        Ok(())
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// ToTupleBuffer
 ////////////////////////////////////////////////////////////////////////////////
@@ -689,6 +776,333 @@ impl<'de> serde_bytes::Deserialize<'de> for TupleBuffer {
     }
 }
 
+/// Writes the msgpack array contained in the buffer as is.
+impl crate::msgpack::Encode for TupleBuffer {
+    #[inline(always)]
+    fn encode(
+        &self,
+        w: &mut impl std::io::Write,
+        _context: &crate::msgpack::Context,
+    ) -> std::result::Result<(), crate::msgpack::EncodeError> {
+        w.write_all(&self.0)?;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// AsArray
+////////////////////////////////////////////////////////////////////////////////
+
+/// A wrapper for [`Tuple`] and [`TupleBuffer`], which implements
+/// [`Serialize`] and [`Deserialize`] for them as for the msgpack arrays they
+/// contain, so that the tuples can be nested into other serializable types:
+///
+/// ```no_run
+/// use tarantool::tuple::{AsArray, Tuple};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Page {
+///     rows: Vec<AsArray<Tuple>>,
+///     next: Option<u64>,
+/// }
+/// ```
+///
+/// The tuples themselves don't implement the serde traits, because [`Decode`]
+/// and [`Return`] are implemented for them separately to copy the msgpack as
+/// is, which would conflict with the blanket implementations for the serde
+/// types.
+///
+/// When serialized, the strings and binary fields are passed to the
+/// serializer without copying. When deserialized, the values are encoded into
+/// a new buffer as they are visited by the deserializer and the buffer is
+/// then owned by the result.
+///
+/// [`Deserialize`]: serde::Deserialize
+/// [`Return`]: crate::proc::Return
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsArray<T>(pub T);
+
+impl Serialize for AsArray<Tuple> {
+    #[inline(always)]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[cfg(feature = "picodata")]
+        return serialize_msgpack(self.0.data(), serializer);
+        #[cfg(not(feature = "picodata"))]
+        return serialize_msgpack(&self.0.to_vec(), serializer);
+    }
+}
+
+impl Serialize for AsArray<TupleBuffer> {
+    #[inline(always)]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_msgpack(self.0.as_ref(), serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for AsArray<Tuple> {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let AsArray(buf) = AsArray::<TupleBuffer>::deserialize(deserializer)?;
+        Ok(Self(Tuple::from(&buf)))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for AsArray<TupleBuffer> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ArrayVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ArrayVisitor {
+            type Value = TupleBuffer;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                f.write_str("an array")
+            }
+
+            fn visit_seq<A>(self, seq: A) -> std::result::Result<TupleBuffer, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut data = Vec::with_capacity(128);
+                Transcode(&mut data).visit_seq(seq)?;
+                // SAFETY: an array was just written into `data`.
+                unsafe { Ok(TupleBuffer::from_vec_unchecked(data)) }
+            }
+        }
+
+        deserializer.deserialize_seq(ArrayVisitor).map(Self)
+    }
+}
+
+impl<T> From<T> for AsArray<T> {
+    #[inline(always)]
+    fn from(t: T) -> Self {
+        Self(t)
+    }
+}
+
+/// Encodes the values visited by a deserializer into the buffer as msgpack.
+struct Transcode<'a>(&'a mut Vec<u8>);
+
+impl<'de> serde::de::DeserializeSeed<'de> for Transcode<'_> {
+    type Value = ();
+
+    #[inline(always)]
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<(), D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for Transcode<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("any msgpack value")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, v: bool) -> std::result::Result<(), E> {
+        rmp::encode::write_bool(self.0, v).map_err(E::custom)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> std::result::Result<(), E> {
+        rmp::encode::write_sint(self.0, v).map_err(E::custom)?;
+        Ok(())
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> std::result::Result<(), E> {
+        rmp::encode::write_uint(self.0, v).map_err(E::custom)?;
+        Ok(())
+    }
+
+    fn visit_f32<E: serde::de::Error>(self, v: f32) -> std::result::Result<(), E> {
+        rmp::encode::write_f32(self.0, v).map_err(E::custom)
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> std::result::Result<(), E> {
+        rmp::encode::write_f64(self.0, v).map_err(E::custom)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> std::result::Result<(), E> {
+        rmp::encode::write_str(self.0, v).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> std::result::Result<(), E> {
+        rmp::encode::write_bin(self.0, v).map_err(E::custom)
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> std::result::Result<(), E> {
+        rmp::encode::write_nil(self.0).map_err(E::custom)
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> std::result::Result<(), E> {
+        self.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<(), D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    /// `rmp_serde` passes the extension values as newtype structs.
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<(), D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error as _;
+
+        let (tag, data): (i8, serde_bytes::ByteBuf) =
+            serde::Deserialize::deserialize(deserializer)?;
+        rmp::encode::write_ext_meta(self.0, data.len() as _, tag).map_err(D::Error::custom)?;
+        self.0.extend_from_slice(&data);
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<(), A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de::Error as _;
+
+        // The length is usually known in advance, otherwise the elements are
+        // written into a temporary buffer.
+        let expected_len = seq.size_hint();
+        let out = self.0;
+        let mut tmp = Vec::new();
+        let buf = match expected_len {
+            Some(len) => {
+                rmp::encode::write_array_len(out, len as _).map_err(A::Error::custom)?;
+                &mut *out
+            }
+            None => &mut tmp,
+        };
+        let mut len = 0;
+        while seq.next_element_seed(Transcode(&mut *buf))?.is_some() {
+            len += 1;
+        }
+        match expected_len {
+            Some(expected_len) if expected_len != len => {
+                return Err(A::Error::invalid_length(len, &"the size hint"));
+            }
+            Some(_) => {}
+            None => {
+                rmp::encode::write_array_len(out, len as _).map_err(A::Error::custom)?;
+                out.extend_from_slice(&tmp);
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<(), A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error as _;
+
+        let expected_len = map.size_hint();
+        let out = self.0;
+        let mut tmp = Vec::new();
+        let buf = match expected_len {
+            Some(len) => {
+                rmp::encode::write_map_len(out, len as _).map_err(A::Error::custom)?;
+                &mut *out
+            }
+            None => &mut tmp,
+        };
+        let mut len = 0;
+        while map.next_key_seed(Transcode(&mut *buf))?.is_some() {
+            map.next_value_seed(Transcode(&mut *buf))?;
+            len += 1;
+        }
+        match expected_len {
+            Some(expected_len) if expected_len != len => {
+                return Err(A::Error::invalid_length(len, &"the size hint"));
+            }
+            Some(_) => {}
+            None => {
+                rmp::encode::write_map_len(out, len as _).map_err(A::Error::custom)?;
+                out.extend_from_slice(&tmp);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Passes the msgpack `data` to `serializer` without copying the strings and
+/// binary values.
+fn serialize_msgpack<S>(data: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::ser::Error as _;
+
+    let value = rmpv::decode::read_value_ref(&mut &*data).map_err(S::Error::custom)?;
+    SerializeValueRef(&value).serialize(serializer)
+}
+
+/// Same as the implementation of [`Serialize`] for [`rmpv::Value`], but for
+/// the borrowed [`rmpv::ValueRef`].
+struct SerializeValueRef<'a>(&'a rmpv::ValueRef<'a>);
+
+impl Serialize for SerializeValueRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use rmpv::ValueRef;
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        match self.0 {
+            ValueRef::Nil => serializer.serialize_unit(),
+            ValueRef::Boolean(v) => serializer.serialize_bool(*v),
+            ValueRef::Integer(v) => match v.as_u64() {
+                Some(v) => serializer.serialize_u64(v),
+                None => serializer.serialize_i64(v.as_i64().expect("not an u64")),
+            },
+            ValueRef::F32(v) => serializer.serialize_f32(*v),
+            ValueRef::F64(v) => serializer.serialize_f64(*v),
+            ValueRef::String(v) => match v.as_str() {
+                Some(v) => serializer.serialize_str(v),
+                None => serializer.serialize_bytes(v.as_bytes()),
+            },
+            ValueRef::Binary(v) => serializer.serialize_bytes(v),
+            ValueRef::Array(array) => {
+                let mut state = serializer.serialize_seq(Some(array.len()))?;
+                for item in array {
+                    state.serialize_element(&SerializeValueRef(item))?;
+                }
+                state.end()
+            }
+            ValueRef::Map(map) => {
+                let mut state = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    state.serialize_entry(&SerializeValueRef(key), &SerializeValueRef(value))?;
+                }
+                state.end()
+            }
+            ValueRef::Ext(tag, data) => {
+                let value = (tag, serde_bytes::Bytes::new(data));
+                serializer.serialize_newtype_struct(rmpv::MSGPACK_EXT_STRUCT_NAME, &value)
+            }
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// TupleFormat
 ////////////////////////////////////////////////////////////////////////////////
@@ -1010,10 +1424,14 @@ impl KeyDef {
     where
         K: ToTupleBuffer + ?Sized,
     {
-        let key_buf = key.to_tuple_buffer().unwrap();
-        let key_buf_ptr = key_buf.as_ptr() as _;
+        let key_buf;
+        let key_data = crate::unwrap_or!(key.tuple_data(), {
+            key_buf = key.to_tuple_buffer().unwrap();
+            key_buf.as_ref()
+        });
+        let key_ptr = key_data.as_ptr() as _;
         unsafe {
-            ffi::box_tuple_compare_with_key(tuple.ptr.as_ptr(), key_buf_ptr, self.inner.as_ptr())
+            ffi::box_tuple_compare_with_key(tuple.ptr.as_ptr(), key_ptr, self.inner.as_ptr())
                 .cmp(&0)
         }
     }
@@ -1254,6 +1672,19 @@ impl FunctionArgs {
         };
         T::decode(slice)
     }
+
+    /// Decode the msgpack value represented by the function args using the
+    /// [`msgpack::Decode`](crate::msgpack::Decode) trait instead of `serde`.
+    #[inline(always)]
+    pub fn decode_msgpack<'a, T>(&'a self) -> Result<T>
+    where
+        T: crate::msgpack::Decode<'a>,
+    {
+        let slice = unsafe {
+            std::slice::from_raw_parts(self.start, self.end.offset_from(self.start) as _)
+        };
+        Ok(crate::msgpack::decode(slice)?)
+    }
 }
 
 /// Push MessagePack data into a session data channel - socket,
@@ -1274,6 +1705,19 @@ where
     }
 }
 
+/// Writes the tuple data of `value` into a buffer leased from the
+/// [`buffer::pool`](crate::buffer::pool), so that encoding temporary tuples
+/// and keys doesn't allocate.
+pub(crate) fn lease_tuple_data<T>(value: &T) -> Result<Lease>
+where
+    T: ToTupleBuffer + ?Sized,
+{
+    let mut buf = crate::buffer::pool().lease(128);
+    value.write_tuple_data(&mut *buf)?;
+    validate_msgpack(buf.as_slice())?;
+    Ok(buf)
+}
+
 #[inline]
 fn validate_msgpack<T>(data: T) -> Result<T>
 where
@@ -1436,6 +1880,35 @@ impl RawBytes {
         // SAFETY: this is safe, because `RawBytes` has `#[repr(transparent)]`
         unsafe { &*(data as *const [u8] as *const RawBytes) }
     }
+
+    /// Decode the msgpack value into a rust type, which may borrow from
+    /// `self`, e.g. `&str` or [`serde_bytes::Bytes`].
+    #[inline(always)]
+    pub fn decode<'de, T>(&'de self) -> Result<T>
+    where
+        T: Decode<'de>,
+    {
+        T::decode(&self.0)
+    }
+
+    /// Returns an iterator over the elements of the msgpack array, or an
+    /// error if the bytes don't start with a msgpack array.
+    ///
+    /// This is useful for accessing the arguments of a stored procedure
+    /// without decoding all of them:
+    /// ```no_run
+    /// use tarantool::tuple::RawBytes;
+    ///
+    /// #[tarantool::proc(packed_args)]
+    /// fn second_arg(args: &RawBytes) -> tarantool::Result<&RawBytes> {
+    ///     let second = args.array_iter()?.nth(1).unwrap_or(&[0xc0]);
+    ///     Ok(RawBytes::new(second))
+    /// }
+    /// ```
+    #[inline(always)]
+    pub fn array_iter(&self) -> Result<crate::msgpack::ValueIter<'_>> {
+        Ok(crate::msgpack::ValueIter::from_array(&self.0)?)
+    }
 }
 
 impl<'a> From<&'a [u8]> for &'a RawBytes {
@@ -1982,6 +2455,47 @@ mod test {
         assert_eq!(svp, unsafe { ffi::box_region_used() });
     }
 
+    #[cfg(feature = "picodata")]
+    #[crate::test(tarantool = "crate")]
+    fn decode_borrowed() {
+        #[derive(::serde::Deserialize, PartialEq, Eq, Debug)]
+        struct S<'a> {
+            id: u32,
+            name: &'a str,
+            #[serde(with = "serde_bytes")]
+            data: &'a [u8],
+        }
+
+        let tuples = vec![
+            // fixarray header
+            Tuple::try_from_slice(b"\x93\x01\xa3foo\xc4\x03bar").unwrap(),
+            // array16 header
+            Tuple::try_from_slice(b"\xdc\x00\x03\x01\xa3foo\xc4\x03bar").unwrap(),
+            // array32 header
+            Tuple::try_from_slice(b"\xdd\x00\x00\x00\x03\x01\xa3foo\xc4\x03bar").unwrap(),
+        ];
+        for tuple in &tuples {
+            assert_eq!(tuple.data(), tuple.to_vec());
+            let s: S = tuple.decode_borrowed().unwrap();
+            assert_eq!(
+                s,
+                S {
+                    id: 1,
+                    name: "foo",
+                    data: b"bar",
+                }
+            );
+            let data = tuple.data().as_ptr_range();
+            assert!(data.contains(&s.name.as_ptr()));
+            assert!(data.contains(&s.data.as_ptr()));
+        }
+
+        let empty = Tuple::try_from_slice(b"\x90").unwrap();
+        assert_eq!(empty.data(), b"\x90");
+        let v: Vec<&str> = empty.decode_borrowed().unwrap();
+        assert!(v.is_empty());
+    }
+
     #[crate::test(tarantool = "crate")]
     fn decode_error() {
         use super::*;
@@ -2163,6 +2677,65 @@ mod test {
         assert_eq!(e.to_string(), "failed to encode tuple: invalid msgpack value (expected array, found Integer(PosInt(69)))");
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn serialize_deserialize_as_array() {
+        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        struct Wrapper {
+            tuples: Vec<AsArray<Tuple>>,
+            buf: AsArray<TupleBuffer>,
+        }
+
+        // \x91\xd4\x01\x02 -- array with an ext value of type 1
+        let ext = Tuple::try_from_slice(b"\x91\xd4\x01\x02").unwrap();
+        let w = Wrapper {
+            tuples: vec![AsArray(Tuple::new(&(0x77, "hello")).unwrap()), AsArray(ext)],
+            buf: AsArray(TupleBuffer::try_from_vec(b"\x92\xc4\x01\x2a\xc0".to_vec()).unwrap()),
+        };
+
+        // Tuples are serialized as arrays, not as binary data.
+        let data = rmp_serde::to_vec(&w).unwrap();
+        assert_eq!(
+            &data,
+            b"\x92\x92\x92\x77\xa5hello\x91\xd4\x01\x02\x92\xc4\x01\x2a\xc0"
+        );
+
+        let w: Wrapper = rmp_serde::from_slice(&data).unwrap();
+        assert_eq!(w.tuples[0].0.to_vec(), b"\x92\x77\xa5hello");
+        assert_eq!(w.tuples[1].0.to_vec(), b"\x91\xd4\x01\x02");
+        assert_eq!(w.buf.0.as_ref(), b"\x92\xc4\x01\x2a\xc0");
+
+        // Nested maps and arrays, negative integers and floats.
+        let data = b"\x93\x81\xa1k\x92\xff\xcb\x3f\xf8\0\0\0\0\0\0\xc0\xc3";
+        let AsArray(buf) = rmp_serde::from_slice::<AsArray<TupleBuffer>>(data).unwrap();
+        assert_eq!(buf.as_ref(), data);
+
+        // Tuples can be put into other tuples.
+        let tuple = Tuple::new(&(1, &w.tuples)).unwrap();
+        assert_eq!(
+            tuple.to_vec(),
+            b"\x92\x01\x92\x92\x77\xa5hello\x91\xd4\x01\x02"
+        );
+
+        // Other serde formats are supported too, the arrays and maps of
+        // unknown length included.
+        let json = serde_json::to_string(&w.tuples[0]).unwrap();
+        assert_eq!(json, r#"[119,"hello"]"#);
+        let AsArray(buf) = serde_json::from_str::<AsArray<TupleBuffer>>(&json).unwrap();
+        assert_eq!(buf.as_ref(), b"\x92\x77\xa5hello");
+        let AsArray(buf) = serde_json::from_str::<AsArray<TupleBuffer>>(r#"[{"k":[-1]}]"#).unwrap();
+        assert_eq!(buf.as_ref(), b"\x91\x81\xa1k\x91\xff");
+
+        let e = rmp_serde::from_slice::<AsArray<Tuple>>(b"\x45").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid type: integer `69`, expected an array"
+        );
+
+        // The tuples are still decoded and returned as is.
+        let tuple = <Tuple as Decode>::decode(b"\x92\x01\x02").unwrap();
+        assert_eq!(tuple.to_vec(), b"\x92\x01\x02");
+    }
+
     #[cfg(feature = "picodata")]
     #[crate::test(tarantool = "crate")]
     fn rust_allocated_tuples() {
@@ -0,0 +1,380 @@
+//! Running closures in the tx thread from other threads.
+//!
+//! Most of the tarantool API (spaces, indexes, fibers, etc.) can only be used
+//! from the tx thread. A [`Dispatcher`] allows any OS thread (or tokio task,
+//! with the `tokio_components` feature) to submit a closure to be executed in
+//! the tx thread and get its result back.
+//!
+//! The dispatcher is built on top of the [`cbus`](crate::cbus) channels. The
+//! number of closures waiting to be executed is bounded, once the limit is
+//! reached the submitting thread (or task) is blocked until some of them are
+//! executed.
+//!
+//! # Example
+//! ```no_run
+//! #[cfg(feature = "picodata")] {
+//! use std::num::NonZeroUsize;
+//! use tarantool::space::Space;
+//! use tarantool::tx::Dispatcher;
+//!
+//! // In the tx thread.
+//! let dispatcher = Dispatcher::new(NonZeroUsize::new(128).unwrap()).unwrap();
+//!
+//! let d = dispatcher.clone();
+//! std::thread::spawn(move || {
+//!     let len = d
+//!         .call(|| Space::find("users").map(|s| s.len().unwrap()))
+//!         .unwrap();
+//!     println!("{:?}", len);
+//! });
+//! }
+//! ```
+
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::thread;
+
+use futures::channel::oneshot;
+
+use crate::cbus::{self, sync};
+use crate::fiber::{self, FiberId};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// An error returned from the [`Dispatcher`] methods.
+#[derive(Debug, thiserror::Error)]
+pub enum DispatchError {
+    /// The dispatcher's fiber has stopped, so the closure will never be
+    /// executed.
+    #[error("tx dispatcher is stopped")]
+    Disconnected,
+    /// The closure has panicked in the tx thread.
+    #[error("closure panicked in the tx thread: {0}")]
+    Panicked(String),
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Dispatcher
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle for submitting closures to the tx thread, see the
+/// [module level documentation](self).
+///
+/// The dispatcher is created in the tx thread and can then be cloned and sent
+/// to other threads. Each thread should use its own clone. The dispatcher's
+/// fibers are stopped once all of the clones are dropped.
+///
+/// The closures are executed one by one in a dedicated fiber, in the order
+/// they were submitted. A closure which yields delays the execution of the
+/// following ones, so long running work should be moved to a separate fiber.
+pub struct Dispatcher {
+    sender: sync::std::Sender<Job>,
+    #[cfg(feature = "tokio_components")]
+    async_sender: sync::tokio::Sender<Job>,
+    /// Senders use a `RefCell` inside, so the dispatcher must not be shared
+    /// between threads.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl Dispatcher {
+    /// Creates a new dispatcher. At most `capacity` closures can be waiting
+    /// for the execution at any moment.
+    ///
+    /// Must be called from the tx thread.
+    pub fn new(capacity: NonZeroUsize) -> crate::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let endpoint_name = format!(
+            "tarantool_rust_tx_dispatcher_{}",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let endpoint = cbus::Endpoint::new(&endpoint_name).map_err(crate::error::Error::other)?;
+        let endpoint_fiber = fiber::Builder::new()
+            .name("tx_dispatcher_endpoint")
+            .func(move || endpoint.cbus_loop())
+            .start_non_joinable()?;
+
+        let workers = Rc::new(WorkerSet {
+            endpoint_fiber,
+            running: Cell::new(0),
+        });
+
+        let (sender, receiver) = sync::std::channel(&endpoint_name, capacity);
+        workers.spawn(move || receiver.receive().ok())?;
+
+        #[cfg(feature = "tokio_components")]
+        let async_sender = {
+            let (sender, receiver) = sync::tokio::channel(&endpoint_name, capacity);
+            workers.spawn(move || receiver.receive().ok())?;
+            sender
+        };
+
+        Ok(Self {
+            sender,
+            #[cfg(feature = "tokio_components")]
+            async_sender,
+            _not_sync: PhantomData,
+        })
+    }
+
+    /// Submits `f` to be executed in the tx thread. Returns a [`Response`]
+    /// which can be used to wait for the result.
+    ///
+    /// Blocks the current thread if too many closures are already waiting
+    /// for the execution, so it must not be called from the tx thread or from
+    /// an async runtime's worker thread.
+    pub fn submit<F, R>(&self, f: F) -> Result<Response<R>, DispatchError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (job, response) = job(f);
+        self.sender
+            .send(job)
+            .map_err(|_| DispatchError::Disconnected)?;
+        Ok(response)
+    }
+
+    /// Executes `f` in the tx thread and returns its result. Blocks the
+    /// current thread until the result is ready.
+    ///
+    /// See also [`Dispatcher::submit`].
+    #[inline(always)]
+    pub fn call<F, R>(&self, f: F) -> Result<R, DispatchError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit(f)?.wait()
+    }
+
+    /// Submits `f` to be executed in the tx thread from an async task.
+    /// Returns a [`Response`] which can be awaited to get the result.
+    ///
+    /// Instead of blocking the thread, the task is suspended if too many
+    /// closures are already waiting for the execution.
+    #[cfg(feature = "tokio_components")]
+    pub async fn submit_async<F, R>(&self, f: F) -> Result<Response<R>, DispatchError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (job, response) = job(f);
+        self.async_sender
+            .send(job)
+            .await
+            .map_err(|_| DispatchError::Disconnected)?;
+        Ok(response)
+    }
+
+    /// Executes `f` in the tx thread and returns its result. Suspends the
+    /// current task until the result is ready.
+    ///
+    /// See also [`Dispatcher::submit_async`].
+    #[cfg(feature = "tokio_components")]
+    #[inline(always)]
+    pub async fn call_async<F, R>(&self, f: F) -> Result<R, DispatchError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit_async(f).await?.await
+    }
+}
+
+impl Clone for Dispatcher {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            #[cfg(feature = "tokio_components")]
+            async_sender: self.async_sender.clone(),
+            _not_sync: PhantomData,
+        }
+    }
+}
+
+impl std::fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dispatcher").finish_non_exhaustive()
+    }
+}
+
+fn job<F, R>(f: F) -> (Job, Response<R>)
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let job = Box::new(move || {
+        let res = panic::catch_unwind(AssertUnwindSafe(f));
+        // The receiver may have been dropped, that's fine.
+        let _ = tx.send(res);
+    });
+    (job, Response { rx })
+}
+
+/// The fibers executing the jobs. The cbus endpoint is stopped once all of
+/// the workers are finished.
+struct WorkerSet {
+    endpoint_fiber: FiberId,
+    running: Cell<usize>,
+}
+
+impl WorkerSet {
+    fn spawn(
+        self: &Rc<Self>,
+        receive: impl Fn() -> Option<Job> + 'static,
+    ) -> crate::Result<FiberId> {
+        let workers = self.clone();
+        workers.running.set(workers.running.get() + 1);
+        let res = fiber::Builder::new()
+            .name("tx_dispatcher")
+            .func(move || {
+                while let Some(job) = receive() {
+                    job();
+                }
+                workers.running.set(workers.running.get() - 1);
+                if workers.running.get() == 0 {
+                    fiber::cancel(workers.endpoint_fiber);
+                }
+            })
+            .start_non_joinable();
+        if res.is_err() {
+            self.running.set(self.running.get() - 1);
+        }
+        res
+    }
+}
+
+impl Drop for WorkerSet {
+    fn drop(&mut self) {
+        // In case a worker failed to start.
+        if self.running.get() == 0 {
+            fiber::cancel(self.endpoint_fiber);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Response
+////////////////////////////////////////////////////////////////////////////////
+
+/// The result of a closure submitted to the [`Dispatcher`].
+///
+/// Use [`Response::wait`] to block the current thread until the result is
+/// ready, or `.await` it from an async task.
+#[must_use = "the result of the closure is lost if the response is dropped"]
+#[derive(Debug)]
+pub struct Response<R> {
+    rx: oneshot::Receiver<thread::Result<R>>,
+}
+
+impl<R> Response<R> {
+    /// Blocks the current thread until the closure is executed and returns
+    /// its result.
+    #[inline(always)]
+    pub fn wait(self) -> Result<R, DispatchError> {
+        futures::executor::block_on(self)
+    }
+}
+
+impl<R> Future for Response<R> {
+    type Output = Result<R, DispatchError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = match Pin::new(&mut self.rx).poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(res) => res,
+        };
+        let res = match res {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(payload)) => {
+                let msg = crate::util::panic_message(&*payload);
+                Err(DispatchError::Panicked(msg.into()))
+            }
+            Err(oneshot::Canceled) => Err(DispatchError::Disconnected),
+        };
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait_thread<T>(thread: thread::JoinHandle<T>) -> T {
+        while !thread.is_finished() {
+            fiber::sleep(Duration::from_millis(1));
+        }
+        thread.join().unwrap()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn call_from_thread() {
+        let dispatcher = Dispatcher::new(NonZeroUsize::new(4).unwrap()).unwrap();
+        let tx_thread = thread::current().id();
+
+        let d = dispatcher.clone();
+        let thread = thread::spawn(move || {
+            let mut results = vec![];
+            for i in 0..100 {
+                results.push(
+                    d.call(move || {
+                        assert_eq!(thread::current().id(), tx_thread);
+                        fiber::reschedule();
+                        i * 2
+                    })
+                    .unwrap(),
+                );
+            }
+            let responses: Vec<_> = (0..10).map(|i| d.submit(move || i).unwrap()).collect();
+            let sum: i32 = responses.into_iter().map(|r| r.wait().unwrap()).sum();
+            (results, sum)
+        });
+        let (results, sum) = wait_thread(thread);
+        assert_eq!(results, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(sum, 45);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn panic_in_closure() {
+        let dispatcher = Dispatcher::new(NonZeroUsize::new(1).unwrap()).unwrap();
+        let thread = thread::spawn(move || {
+            let e = dispatcher.call(|| panic!("oops")).unwrap_err();
+            // The dispatcher keeps working after a panic.
+            let ok = dispatcher.call(|| 42).unwrap();
+            (e, ok)
+        });
+        let (e, ok) = wait_thread(thread);
+        assert_eq!(e.to_string(), "closure panicked in the tx thread: oops");
+        assert_eq!(ok, 42);
+    }
+
+    #[cfg(feature = "tokio_components")]
+    #[crate::test(tarantool = "crate")]
+    fn call_from_tokio() {
+        let dispatcher = Dispatcher::new(NonZeroUsize::new(2).unwrap()).unwrap();
+        let thread = thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async move {
+                    let mut sum = 0;
+                    for i in 0..10 {
+                        sum += dispatcher.call_async(move || i).await.unwrap();
+                    }
+                    sum
+                })
+        });
+        assert_eq!(wait_thread(thread), 45);
+    }
+}
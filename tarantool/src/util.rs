@@ -174,6 +174,185 @@ macro_rules! unwrap_ok_or {
     }
 }
 
+/// Defines an enum which is a union of several types, i.e. a value of it is
+/// a value of one of the variant types without any tags. The enum implements
+/// [`tlua::Push`], [`tlua::LuaRead`], [`Encode`] and [`Decode`], as well as
+/// `From` for each of the variant types, so the variant types must be
+/// distinct.
+///
+/// When a value is read from lua or decoded from msgpack, the variants are
+/// tried in the order of declaration and the first one which succeeds is
+/// returned. If none of them succeed, the error contains the reasons of each
+/// failure.
+///
+/// For unions of two types see also [`tlua::Either`].
+///
+/// ```no_run
+/// tarantool::one_of! {
+///     #[derive(Debug, PartialEq)]
+///     pub enum Key {
+///         Id(u64),
+///         Name(String),
+///         Parts(Vec<u64>),
+///     }
+/// }
+///
+/// let lua = tarantool::lua_state();
+/// let key: Key = lua.eval("return 'foo'").unwrap();
+/// assert_eq!(key, Key::Name("foo".into()));
+/// ```
+///
+/// [`Encode`]: crate::msgpack::Encode
+/// [`Decode`]: crate::msgpack::Decode
+#[macro_export]
+macro_rules! one_of {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $( $(#[$variant_meta:meta])* $variant:ident($ty:ty) ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $( $(#[$variant_meta])* $variant($ty), )+
+        }
+
+        $(
+            impl ::std::convert::From<$ty> for $name {
+                #[inline(always)]
+                fn from(v: $ty) -> Self {
+                    Self::$variant(v)
+                }
+            }
+        )+
+
+        impl<L> $crate::tlua::Push<L> for $name
+        where
+            L: $crate::tlua::AsLua,
+            $(
+                $ty: $crate::tlua::Push<L>,
+                <$ty as $crate::tlua::Push<L>>::Err: ::std::convert::Into<$crate::tlua::Void>,
+            )+
+        {
+            type Err = $crate::tlua::Void;
+
+            #[inline]
+            fn push_to_lua(
+                &self,
+                lua: L,
+            ) -> ::std::result::Result<$crate::tlua::PushGuard<L>, (Self::Err, L)> {
+                match self {
+                    $( Self::$variant(v) => Ok($crate::tlua::Push::push_no_err(v, lua)), )+
+                }
+            }
+        }
+
+        impl<L> $crate::tlua::PushOne<L> for $name
+        where
+            L: $crate::tlua::AsLua,
+            $(
+                $ty: $crate::tlua::PushOne<L>,
+                <$ty as $crate::tlua::Push<L>>::Err: ::std::convert::Into<$crate::tlua::Void>,
+            )+
+        {
+        }
+
+        impl<L> $crate::tlua::PushInto<L> for $name
+        where
+            L: $crate::tlua::AsLua,
+            $(
+                $ty: $crate::tlua::PushInto<L>,
+                <$ty as $crate::tlua::PushInto<L>>::Err: ::std::convert::Into<$crate::tlua::Void>,
+            )+
+        {
+            type Err = $crate::tlua::Void;
+
+            #[inline]
+            fn push_into_lua(
+                self,
+                lua: L,
+            ) -> ::std::result::Result<$crate::tlua::PushGuard<L>, (Self::Err, L)> {
+                match self {
+                    $( Self::$variant(v) => Ok($crate::tlua::PushInto::push_into_no_err(v, lua)), )+
+                }
+            }
+        }
+
+        impl<L> $crate::tlua::PushOneInto<L> for $name
+        where
+            L: $crate::tlua::AsLua,
+            $(
+                $ty: $crate::tlua::PushOneInto<L>,
+                <$ty as $crate::tlua::PushInto<L>>::Err: ::std::convert::Into<$crate::tlua::Void>,
+            )+
+        {
+        }
+
+        impl<L> $crate::tlua::LuaRead<L> for $name
+        where
+            L: $crate::tlua::AsLua,
+            $( $ty: $crate::tlua::LuaRead<L>, )+
+        {
+            fn lua_read_at_position(
+                lua: L,
+                index: ::std::num::NonZeroI32,
+            ) -> $crate::tlua::ReadResult<Self, L> {
+                let mut errors = ::std::collections::LinkedList::new();
+                $(
+                    let lua = match <$ty as $crate::tlua::LuaRead<L>>::lua_read_at_position(lua, index) {
+                        Ok(v) => return Ok(Self::$variant(v)),
+                        Err((lua, e)) => {
+                            errors.push_back(e);
+                            lua
+                        }
+                    };
+                )+
+                let e = $crate::tlua::WrongType::info("reading any of the alternatives")
+                    .expected_type::<Self>()
+                    .actual_single_lua(&lua, index)
+                    .subtypes(errors);
+                Err((lua, e))
+            }
+        }
+
+        impl $crate::msgpack::Encode for $name {
+            #[inline]
+            fn encode(
+                &self,
+                w: &mut impl ::std::io::Write,
+                context: &$crate::msgpack::Context,
+            ) -> ::std::result::Result<(), $crate::msgpack::EncodeError> {
+                match self {
+                    $( Self::$variant(v) => $crate::msgpack::Encode::encode(v, w, context), )+
+                }
+            }
+        }
+
+        impl<'de> $crate::msgpack::Decode<'de> for $name {
+            fn decode(
+                r: &mut &'de [u8],
+                context: &$crate::msgpack::Context,
+            ) -> ::std::result::Result<Self, $crate::msgpack::DecodeError> {
+                let mut errors = ::std::vec::Vec::new();
+                $(
+                    let mut r_try = *r;
+                    match <$ty as $crate::msgpack::Decode>::decode(&mut r_try, context) {
+                        Ok(v) => {
+                            *r = r_try;
+                            return Ok(Self::$variant(v));
+                        }
+                        Err(e) => errors.push(e.to_string()),
+                    }
+                )+
+                Err($crate::msgpack::DecodeError::new::<Self>(format!(
+                    "didn't match any of the alternatives: {}",
+                    errors.join("; "),
+                )))
+            }
+        }
+    };
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // DisplayAsHexBytes
 ////////////////////////////////////////////////////////////////////////////////
@@ -245,6 +424,21 @@ pub const fn str_eq(lhs: &str, rhs: &str) -> bool {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// panic_message
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the message of a caught panic, i.e. its payload if it's a string
+/// (which is the case for `panic!` with a message) or a placeholder otherwise.
+#[inline]
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<unknown panic payload>")
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // to_cstring
 ////////////////////////////////////////////////////////////////////////////////
@@ -279,6 +473,209 @@ pub fn into_cstring_lossy(s: String) -> CString {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// base64 & hex
+////////////////////////////////////////////////////////////////////////////////
+
+/// Error returned when decoding a base64 or a hex string.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CodecError {
+    #[error("invalid {encoding} symbol {} at offset {offset}", DisplayAsHexBytes(std::slice::from_ref(.byte)))]
+    InvalidByte {
+        encoding: &'static str,
+        offset: usize,
+        byte: u8,
+    },
+
+    #[error("invalid {encoding} length {len}")]
+    InvalidLength { encoding: &'static str, len: usize },
+}
+
+/// The alphabet used by [`base64_encode_with`] and [`base64_decode_with`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Base64Alphabet {
+    /// The standard alphabet (RFC 4648) with `+` and `/`.
+    #[default]
+    Standard,
+    /// The URL and filename safe alphabet (RFC 4648) with `-` and `_`.
+    UrlSafe,
+}
+
+impl Base64Alphabet {
+    #[inline(always)]
+    fn config(self, pad: bool) -> base64::Config {
+        match self {
+            Self::Standard => base64::STANDARD.pad(pad),
+            Self::UrlSafe => base64::URL_SAFE.pad(pad),
+        }
+    }
+}
+
+/// Encodes `data` as base64 using the standard alphabet with padding.
+/// ```no_run
+/// # use tarantool::util::base64_encode;
+/// assert_eq!(base64_encode(b"\xfb\xff"), "+/8=");
+/// ```
+#[inline(always)]
+pub fn base64_encode(data: impl AsRef<[u8]>) -> String {
+    base64_encode_with(data, Base64Alphabet::Standard, true)
+}
+
+/// Encodes `data` as base64 using the given `alphabet`, the trailing `=`
+/// symbols are omitted if `pad` is `false`.
+/// ```no_run
+/// # use tarantool::util::{base64_encode_with, Base64Alphabet};
+/// assert_eq!(base64_encode_with(b"\xfb\xff", Base64Alphabet::UrlSafe, false), "-_8");
+/// ```
+#[inline(always)]
+pub fn base64_encode_with(data: impl AsRef<[u8]>, alphabet: Base64Alphabet, pad: bool) -> String {
+    base64::encode_config(data, alphabet.config(pad))
+}
+
+/// Decodes a base64 string which uses the standard alphabet. The padding is
+/// optional.
+#[inline(always)]
+pub fn base64_decode(s: impl AsRef<[u8]>) -> Result<Vec<u8>, CodecError> {
+    base64_decode_with(s, Base64Alphabet::Standard)
+}
+
+/// Decodes a base64 string which uses the given `alphabet`. The padding is
+/// optional.
+pub fn base64_decode_with(
+    s: impl AsRef<[u8]>,
+    alphabet: Base64Alphabet,
+) -> Result<Vec<u8>, CodecError> {
+    let s = s.as_ref();
+    base64::decode_config(s, alphabet.config(true)).map_err(|e| match e {
+        base64::DecodeError::InvalidByte(offset, byte)
+        | base64::DecodeError::InvalidLastSymbol(offset, byte) => CodecError::InvalidByte {
+            encoding: "base64",
+            offset,
+            byte,
+        },
+        base64::DecodeError::InvalidLength => CodecError::InvalidLength {
+            encoding: "base64",
+            len: s.len(),
+        },
+    })
+}
+
+/// Encodes `data` as a string of lowercase hexadecimal digits.
+/// ```no_run
+/// # use tarantool::util::hex_encode;
+/// assert_eq!(hex_encode(b"\x01\xab"), "01ab");
+/// ```
+pub fn hex_encode(data: impl AsRef<[u8]>) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let data = data.as_ref();
+    let mut res = String::with_capacity(data.len() * 2);
+    for byte in data {
+        res.push(DIGITS[(byte >> 4) as usize] as char);
+        res.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+    res
+}
+
+/// Decodes a string of hexadecimal digits in either case.
+pub fn hex_decode(s: impl AsRef<[u8]>) -> Result<Vec<u8>, CodecError> {
+    let s = s.as_ref();
+    if s.len() % 2 != 0 {
+        return Err(CodecError::InvalidLength {
+            encoding: "hex",
+            len: s.len(),
+        });
+    }
+    let digit = |offset: usize| {
+        let byte = s[offset];
+        match byte {
+            b'0'..=b'9' => Ok(byte - b'0'),
+            b'a'..=b'f' => Ok(byte - b'a' + 10),
+            b'A'..=b'F' => Ok(byte - b'A' + 10),
+            _ => Err(CodecError::InvalidByte {
+                encoding: "hex",
+                offset,
+                byte,
+            }),
+        }
+    };
+    (0..s.len())
+        .step_by(2)
+        .map(|i| Ok(digit(i)? << 4 | digit(i + 1)?))
+        .collect()
+}
+
+/// Serde helpers for binary fields, which are represented as base64 strings
+/// in human readable formats (JSON, YAML, etc.) and as binary strings
+/// otherwise (e.g. MP_BIN in msgpack).
+///
+/// Use it via the `#[serde(with = ...)]` attribute:
+/// ```no_run
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Blob {
+///     id: u64,
+///     #[serde(with = "tarantool::util::serde_base64")]
+///     data: Vec<u8>,
+/// }
+/// ```
+pub mod serde_base64 {
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S>(data: impl AsRef<[u8]>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&super::base64_encode(data))
+        } else {
+            serializer.serialize_bytes(data.as_ref())
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a base64 string or a byte array")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+                super::base64_decode(v).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+                Ok(v)
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                let mut res = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    res.push(byte);
+                }
+                Ok(res)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Visitor)
+        } else {
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // test
 ////////////////////////////////////////////////////////////////////////////////
@@ -320,4 +717,95 @@ b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10\x11\x12\x
 
         assert_eq!(into_cstring_lossy(message).as_ref(), crate::c_str!("hell� w�rld�"));
     }
+
+    #[test]
+    fn base64() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"\xfb\xff"), "+/8=");
+        assert_eq!(
+            base64_encode_with(b"\xfb\xff", Base64Alphabet::UrlSafe, true),
+            "-_8="
+        );
+        assert_eq!(
+            base64_encode_with(b"\xfb\xff", Base64Alphabet::UrlSafe, false),
+            "-_8"
+        );
+
+        assert_eq!(base64_decode("+/8=").unwrap(), b"\xfb\xff");
+        assert_eq!(base64_decode("+/8").unwrap(), b"\xfb\xff");
+        assert_eq!(
+            base64_decode_with("-_8", Base64Alphabet::UrlSafe).unwrap(),
+            b"\xfb\xff"
+        );
+
+        let e = base64_decode("-_8").unwrap_err();
+        assert_eq!(e.to_string(), r#"invalid base64 symbol b"-" at offset 0"#);
+        let e = base64_decode("+/8=a").unwrap_err();
+        assert_eq!(e.to_string(), "invalid base64 length 5");
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(hex_encode(b""), "");
+        assert_eq!(hex_encode(b"\x00\x01\xab\xff"), "0001abff");
+        assert_eq!(hex_decode("0001abff").unwrap(), b"\x00\x01\xab\xff");
+        assert_eq!(hex_decode("0001ABFF").unwrap(), b"\x00\x01\xab\xff");
+
+        let e = hex_decode("abc").unwrap_err();
+        assert_eq!(e.to_string(), "invalid hex length 3");
+        let e = hex_decode("0g").unwrap_err();
+        assert_eq!(e.to_string(), r#"invalid hex symbol b"g" at offset 1"#);
+    }
+
+    #[test]
+    fn serde_base64() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Blob {
+            #[serde(with = "super::serde_base64")]
+            data: Vec<u8>,
+        }
+
+        let blob = Blob {
+            data: vec![0xfb, 0xff],
+        };
+        let json = serde_json::to_string(&blob).unwrap();
+        assert_eq!(json, r#"{"data":"+/8="}"#);
+        assert_eq!(serde_json::from_str::<Blob>(&json).unwrap(), blob);
+
+        let mp = rmp_serde::to_vec(&blob).unwrap();
+        assert_eq!(mp, b"\x91\xc4\x02\xfb\xff");
+        assert_eq!(rmp_serde::from_slice::<Blob>(&mp).unwrap(), blob);
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    crate::one_of! {
+        #[derive(Debug, PartialEq)]
+        enum Key {
+            Id(u64),
+            Name(String),
+            Parts(Vec<u64>),
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn one_of_lua() {
+        let lua = crate::lua_state();
+        let key: Key = lua.eval("return 1").unwrap();
+        assert_eq!(key, Key::Id(1));
+        let key: Key = lua.eval("return 'foo'").unwrap();
+        assert_eq!(key, Key::Name("foo".into()));
+        let key: Key = lua.eval("return {1, 2}").unwrap();
+        assert_eq!(key, Key::Parts(vec![1, 2]));
+
+        let t: String = lua
+            .eval_with("return type(...)", Key::from(vec![1, 2]))
+            .unwrap();
+        assert_eq!(t, "table");
+
+        let e = lua.eval::<Key>("return true").unwrap_err().to_string();
+        assert!(e.contains("variant #3: "), "{}", e);
+        assert!(e.contains("while reading any of the alternatives"), "{}", e);
+    }
 }
@@ -0,0 +1,327 @@
+//! Detection of the tx thread stalls.
+//!
+//! All of the stored procedures and fibers are executed in the tx thread, so
+//! a single blocking call (e.g. a synchronous network request, a
+//! [`std::thread::sleep`] or a long computation without yields) stops the
+//! processing of all of the other requests.
+//!
+//! A [`Watchdog`] runs in a separate OS thread and periodically pings the tx
+//! thread via [`cbus`](crate::cbus). If the event loop doesn't respond within
+//! [`Config::threshold`], a warning is written to the log or the process is
+//! aborted, see [`OnStall`].
+//!
+//! Code which is known to block the tx thread for a while can declare it via
+//! [`long_operation`], in which case the stall isn't reported until the
+//! declared timeout expires.
+//!
+//! # Example
+//! ```no_run
+//! #[cfg(feature = "picodata")] {
+//! use std::time::Duration;
+//! use tarantool::watchdog::{self, Config, OnStall, Watchdog};
+//!
+//! let watchdog = Watchdog::start(Config {
+//!     threshold: Duration::from_millis(500),
+//!     on_stall: OnStall::Abort,
+//!     ..Default::default()
+//! })
+//! .unwrap();
+//!
+//! // Somewhere in a stored procedure.
+//! let _guard = watchdog::long_operation(Duration::from_secs(5));
+//! // do some blocking work ...
+//! }
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::cbus::{self, LCPipe, Message};
+use crate::fiber::{self, FiberId};
+
+////////////////////////////////////////////////////////////////////////////////
+// Config
+////////////////////////////////////////////////////////////////////////////////
+
+/// Action taken by the [`Watchdog`] once a tx thread stall is detected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OnStall {
+    /// Write a warning to the log. Another message is written once the tx
+    /// thread becomes responsive again.
+    #[default]
+    Log,
+    /// Write an error to the log and abort the process. The `SIGABRT` signal
+    /// is delivered to the tx thread, so that the crash report and the core
+    /// dump contain the backtrace of the code which blocked the event loop.
+    Abort,
+}
+
+/// Parameters of the [`Watchdog`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Config {
+    /// The tx thread is considered stalled if it doesn't respond to a ping
+    /// within this time.
+    pub threshold: Duration,
+    /// Time between the consecutive pings.
+    pub ping_interval: Duration,
+    /// What to do once the stall is detected.
+    pub on_stall: OnStall,
+}
+
+impl Default for Config {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            threshold: Duration::from_secs(1),
+            ping_interval: Duration::from_millis(100),
+            on_stall: OnStall::Log,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Watchdog
+////////////////////////////////////////////////////////////////////////////////
+
+/// A running watchdog, see the [module level documentation](self).
+///
+/// The watchdog is stopped once this value is dropped.
+#[derive(Debug)]
+pub struct Watchdog {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+    endpoint_fiber: FiberId,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    stop: AtomicBool,
+    /// Sequence number of the last ping handled by the tx thread.
+    pong: AtomicU64,
+    stalls: AtomicU64,
+}
+
+/// Handle of the tx thread used for delivering the `SIGABRT`.
+struct TxThread(libc::pthread_t);
+
+// SAFETY: `pthread_t` is just an identifier of the thread, it's only used
+// with `pthread_kill` which can be called from any thread.
+unsafe impl Send for TxThread {}
+
+impl Watchdog {
+    /// Starts the watchdog thread along with a fiber handling its pings.
+    ///
+    /// Must be called from the tx thread.
+    pub fn start(config: Config) -> crate::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let endpoint_name = format!(
+            "tarantool_rust_watchdog_{}",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let endpoint = cbus::Endpoint::new(&endpoint_name).map_err(crate::error::Error::other)?;
+        let endpoint_fiber = fiber::Builder::new()
+            .name("watchdog_endpoint")
+            .func(move || endpoint.cbus_loop())
+            .start_non_joinable()?;
+
+        let shared = Arc::new(Shared::default());
+        let tx_thread = TxThread(unsafe { libc::pthread_self() });
+        let thread = thread::Builder::new().name("watchdog".into()).spawn({
+            let shared = shared.clone();
+            move || watch(config, &endpoint_name, tx_thread, shared)
+        });
+        let thread = match thread {
+            Ok(thread) => thread,
+            Err(e) => {
+                fiber::cancel(endpoint_fiber);
+                return Err(e.into());
+            }
+        };
+
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+            endpoint_fiber,
+        })
+    }
+
+    /// Returns the number of the tx thread stalls detected so far.
+    #[inline(always)]
+    pub fn stall_count(&self) -> u64 {
+        self.shared.stalls.load(Ordering::Relaxed)
+    }
+
+    /// Stops the watchdog. Equivalent to dropping it.
+    #[inline(always)]
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            if thread.join().is_err() {
+                crate::say_error!("watchdog thread panicked");
+            }
+        }
+        fiber::cancel(self.endpoint_fiber);
+    }
+}
+
+fn watch(config: Config, endpoint_name: &str, tx_thread: TxThread, shared: Arc<Shared>) {
+    let mut pipe = LCPipe::new(endpoint_name);
+    let mut seq = 0;
+    while !shared.stop.load(Ordering::Acquire) {
+        seq += 1;
+        let sent_at = Instant::now();
+        pipe.push_message(Message::new({
+            let shared = shared.clone();
+            let watchdog = thread::current();
+            move || {
+                shared.pong.store(seq, Ordering::Release);
+                watchdog.unpark();
+            }
+        }));
+
+        let mut stalled = false;
+        while shared.pong.load(Ordering::Acquire) < seq {
+            if shared.stop.load(Ordering::Acquire) {
+                return;
+            }
+            let elapsed = sent_at.elapsed();
+            if elapsed < config.threshold {
+                thread::park_timeout(config.threshold - elapsed);
+                continue;
+            }
+            if !stalled && !long_operation_in_progress() {
+                stalled = true;
+                shared.stalls.fetch_add(1, Ordering::Relaxed);
+                report_stall(&config, elapsed, &tx_thread);
+            }
+            thread::park_timeout(config.ping_interval);
+        }
+
+        if stalled {
+            crate::say_warn!(
+                "tx thread is responsive again after {:?}",
+                sent_at.elapsed()
+            );
+        }
+        thread::park_timeout(config.ping_interval);
+    }
+}
+
+fn report_stall(config: &Config, elapsed: Duration, tx_thread: &TxThread) {
+    match config.on_stall {
+        OnStall::Log => {
+            crate::say_warn!(
+                "tx thread is not responding for {:?}, the event loop is probably blocked",
+                elapsed
+            );
+        }
+        OnStall::Abort => {
+            crate::say_error!(
+                "tx thread is not responding for {:?}, aborting the process",
+                elapsed
+            );
+            let rc = unsafe { libc::pthread_kill(tx_thread.0, libc::SIGABRT) };
+            if rc == 0 {
+                // Give the signal handler a chance to run in the tx thread.
+                thread::sleep(config.threshold);
+            }
+            std::process::abort();
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// long_operation
+////////////////////////////////////////////////////////////////////////////////
+
+/// Deadlines of the currently active [`LongOperation`]s by their ids. `None`
+/// means no deadline.
+static LONG_OPERATIONS: Mutex<Vec<(u64, Option<Instant>)>> = Mutex::new(Vec::new());
+
+fn long_operations() -> MutexGuard<'static, Vec<(u64, Option<Instant>)>> {
+    LONG_OPERATIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn long_operation_in_progress() -> bool {
+    let now = Instant::now();
+    long_operations()
+        .iter()
+        .any(|(_, deadline)| deadline.map_or(true, |deadline| deadline > now))
+}
+
+/// Declares that the tx thread is expected to be blocked for up to `timeout`,
+/// so the [`Watchdog`] must not report a stall until either the timeout
+/// expires or the returned guard is dropped.
+#[inline]
+pub fn long_operation(timeout: Duration) -> LongOperation {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let deadline = Instant::now().checked_add(timeout);
+    long_operations().push((id, deadline));
+    LongOperation { id }
+}
+
+/// A guard returned from [`long_operation`]. The long operation ends once the
+/// guard is dropped.
+#[must_use = "the long operation ends once the guard is dropped"]
+#[derive(Debug)]
+pub struct LongOperation {
+    id: u64,
+}
+
+impl Drop for LongOperation {
+    fn drop(&mut self) {
+        long_operations().retain(|(id, _)| *id != self.id);
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn detects_stalls() {
+        let watchdog = Watchdog::start(Config {
+            threshold: Duration::from_millis(100),
+            ping_interval: Duration::from_millis(5),
+            on_stall: OnStall::Log,
+        })
+        .unwrap();
+        fiber::sleep(Duration::from_millis(50));
+        assert_eq!(watchdog.stall_count(), 0);
+
+        // Block the event loop.
+        thread::sleep(Duration::from_millis(300));
+        fiber::sleep(Duration::from_millis(20));
+        assert_eq!(watchdog.stall_count(), 1);
+
+        // Expected long operation.
+        {
+            let _guard = long_operation(Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(300));
+        }
+        fiber::sleep(Duration::from_millis(20));
+        assert_eq!(watchdog.stall_count(), 1);
+
+        // Long operation taking longer than declared.
+        {
+            let _guard = long_operation(Duration::from_millis(10));
+            thread::sleep(Duration::from_millis(300));
+        }
+        fiber::sleep(Duration::from_millis(20));
+        assert_eq!(watchdog.stall_count(), 2);
+
+        watchdog.stop();
+    }
+}
@@ -0,0 +1,452 @@
+//! Combining of small writes into batched transactions.
+//!
+//! Every committed transaction costs a WAL write, so a write-heavy stored
+//! procedure doing one small update per request is limited by the number of
+//! WAL writes per second. [`WriteCombiner`] collects the writes to a space
+//! made by concurrent fibers for a short time (see
+//! [`CombinerOptions::max_delay`]) and applies them in a single transaction,
+//! trading a bit of latency for a much higher throughput.
+//!
+//! Each write method blocks the calling fiber until the batch containing the
+//! write is committed. The batch is applied atomically: if any of the writes
+//! fails, the whole batch is rolled back and every writer receives the error.
+//!
+//! ```no_run
+//! use std::rc::Rc;
+//! use tarantool::space::Space;
+//! use tarantool::write_combiner::{CombinerOptions, WriteCombiner};
+//!
+//! let space = Space::find("counters").unwrap();
+//! let combiner = Rc::new(WriteCombiner::new(space, CombinerOptions::default()));
+//!
+//! // Called concurrently from many fibers.
+//! combiner.upsert(&("hits", 1), [("+", 1, 1)]).unwrap();
+//!
+//! let stats = combiner.stats();
+//! println!("{} writes in {} batches", stats.writes, stats.batches);
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::error::{BoxError, Error, IntoBoxError, TarantoolErrorCode};
+use crate::fiber::{self, Cond};
+use crate::space::Space;
+use crate::time::Instant;
+use crate::transaction;
+use crate::tuple::{ToTupleBuffer, TupleBuffer};
+
+/// Options of [`WriteCombiner`].
+#[derive(Clone, Debug)]
+pub struct CombinerOptions {
+    /// For how long the writes are collected before the batch is flushed.
+    /// The delay starts with the first write of the batch.
+    ///
+    /// Default: 500 microseconds
+    pub max_delay: Duration,
+
+    /// Maximum number of writes in a batch. The batch is flushed without
+    /// waiting for [`Self::max_delay`] once it's reached.
+    ///
+    /// Default: 1000
+    pub max_batch: usize,
+
+    /// Flush the batch as soon as the fibers which were ready to run when the
+    /// batch was started have yielded, instead of waiting for
+    /// [`Self::max_delay`]. This combines the writes made during one event
+    /// loop iteration without adding a fixed delay.
+    ///
+    /// Default: `false`
+    pub flush_on_yield: bool,
+}
+
+impl Default for CombinerOptions {
+    fn default() -> Self {
+        Self {
+            max_delay: Duration::from_micros(500),
+            max_batch: 1000,
+            flush_on_yield: false,
+        }
+    }
+}
+
+/// Statistics of [`WriteCombiner`], see [`WriteCombiner::stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CombinerStats {
+    /// Number of the flushed batches, including the failed ones.
+    pub batches: u64,
+    /// Number of the writes in the flushed batches.
+    pub writes: u64,
+    /// Number of the batches which were rolled back.
+    pub failed_batches: u64,
+    /// Number of the writes in the largest batch.
+    pub max_batch_len: usize,
+    /// Total time spent applying and committing the batches.
+    pub total_flush_time: Duration,
+    /// The longest time spent applying and committing a batch.
+    pub max_flush_time: Duration,
+    /// Number of the writes waiting for the next flush.
+    pub pending: usize,
+}
+
+impl CombinerStats {
+    /// Returns the mean number of writes per batch or `None` if no batches
+    /// were flushed.
+    #[inline]
+    pub fn mean_batch_len(&self) -> Option<f64> {
+        if self.batches == 0 {
+            return None;
+        }
+        Some(self.writes as f64 / self.batches as f64)
+    }
+}
+
+/// A write collected into a batch.
+enum WriteOp {
+    Insert(TupleBuffer),
+    Replace(TupleBuffer),
+    Delete(TupleBuffer),
+    Update(TupleBuffer, Vec<TupleBuffer>),
+    Upsert(TupleBuffer, Vec<TupleBuffer>),
+}
+
+/// Outcome of a batch shared by all of its writers, `None` until the batch
+/// is flushed.
+type BatchResult = Rc<RefCell<Option<Result<(), BoxError>>>>;
+
+#[derive(Default)]
+struct Batch {
+    ops: Vec<WriteOp>,
+    result: BatchResult,
+    /// Whether a fiber is already waiting to flush this batch.
+    has_leader: bool,
+}
+
+/// Combines the writes to a space made by concurrent fibers into batched
+/// transactions, see the [module level documentation](self).
+///
+/// The combiner is usually shared between the fibers via an [`Rc`].
+pub struct WriteCombiner {
+    space: Space,
+    options: CombinerOptions,
+    batch: RefCell<Batch>,
+    /// Broadcast when the pending batch becomes full or when a batch is
+    /// flushed.
+    wakeup: Cond,
+    stats: RefCell<CombinerStats>,
+}
+
+impl WriteCombiner {
+    pub fn new(space: Space, options: CombinerOptions) -> Self {
+        Self {
+            space,
+            options,
+            batch: Default::default(),
+            wakeup: Cond::new(),
+            stats: Default::default(),
+        }
+    }
+
+    /// Returns the space the writes are applied to.
+    #[inline(always)]
+    pub fn space(&self) -> &Space {
+        &self.space
+    }
+
+    /// Returns the statistics of the flushed batches.
+    #[inline]
+    pub fn stats(&self) -> CombinerStats {
+        CombinerStats {
+            pending: self.batch.borrow().ops.len(),
+            ..self.stats.borrow().clone()
+        }
+    }
+
+    /// Resets the statistics.
+    #[inline]
+    pub fn reset_stats(&self) {
+        *self.stats.borrow_mut() = Default::default();
+    }
+
+    /// Same as [`Space::insert`], but the tuple is inserted as part of a
+    /// batch. Blocks until the batch is committed.
+    pub fn insert<T>(&self, value: &T) -> Result<(), Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+    {
+        self.write(WriteOp::Insert(value.to_tuple_buffer()?))
+    }
+
+    /// Same as [`Space::replace`], but the tuple is replaced as part of a
+    /// batch. Blocks until the batch is committed.
+    pub fn replace<T>(&self, value: &T) -> Result<(), Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+    {
+        self.write(WriteOp::Replace(value.to_tuple_buffer()?))
+    }
+
+    /// Same as [`Space::delete`], but the tuple is deleted as part of a
+    /// batch. Blocks until the batch is committed.
+    pub fn delete<K>(&self, key: &K) -> Result<(), Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        self.write(WriteOp::Delete(key.to_tuple_buffer()?))
+    }
+
+    /// Same as [`Space::update`], but the tuple is updated as part of a
+    /// batch. Blocks until the batch is committed.
+    pub fn update<K, Op>(&self, key: &K, ops: impl AsRef<[Op]>) -> Result<(), Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+        Op: ToTupleBuffer,
+    {
+        let ops = encode_ops(ops.as_ref())?;
+        self.write(WriteOp::Update(key.to_tuple_buffer()?, ops))
+    }
+
+    /// Same as [`Space::upsert`], but the tuple is upserted as part of a
+    /// batch. Blocks until the batch is committed.
+    pub fn upsert<T, Op>(&self, value: &T, ops: impl AsRef<[Op]>) -> Result<(), Error>
+    where
+        T: ToTupleBuffer + ?Sized,
+        Op: ToTupleBuffer,
+    {
+        let ops = encode_ops(ops.as_ref())?;
+        self.write(WriteOp::Upsert(value.to_tuple_buffer()?, ops))
+    }
+
+    /// Flushes the pending batch immediately. Returns the result of the
+    /// batch or `Ok(())` if there were no pending writes.
+    ///
+    /// The writers of the batch are woken up.
+    pub fn flush(&self) -> Result<(), Error> {
+        let batch = std::mem::take(&mut *self.batch.borrow_mut());
+        if batch.ops.is_empty() {
+            return Ok(());
+        }
+        let result = batch.result.clone();
+        self.apply(batch);
+        let res = result.borrow().clone().expect("batch was just flushed");
+        res.map_err(Error::from)
+    }
+
+    fn write(&self, op: WriteOp) -> Result<(), Error> {
+        if transaction::is_in_transaction() {
+            // The fiber must yield while the batch is collected, which
+            // aborts the active transaction.
+            return Err(BoxError::new(
+                TarantoolErrorCode::ActiveTransaction,
+                "WriteCombiner can't be used inside a transaction",
+            )
+            .into());
+        }
+
+        let (result, is_leader, is_full) = {
+            let mut batch = self.batch.borrow_mut();
+            batch.ops.push(op);
+            let is_leader = !batch.has_leader;
+            batch.has_leader = true;
+            (
+                batch.result.clone(),
+                is_leader,
+                batch.ops.len() >= self.options.max_batch,
+            )
+        };
+
+        if is_leader {
+            let deadline = fiber::clock().saturating_add(self.options.max_delay);
+            if self.options.flush_on_yield && !is_full {
+                fiber::reschedule();
+            }
+            loop {
+                let is_pending = {
+                    let batch = self.batch.borrow();
+                    // The batch could have been flushed by `Self::flush`.
+                    Rc::ptr_eq(&batch.result, &result)
+                        .then_some(batch.ops.len() >= self.options.max_batch)
+                };
+                match is_pending {
+                    None => break,
+                    Some(true) => return self.flush(),
+                    Some(false) if self.options.flush_on_yield => return self.flush(),
+                    Some(false) => {}
+                }
+                if !self.wakeup.wait_deadline(deadline) {
+                    // Deadline is reached or the fiber is cancelled.
+                    return self.flush();
+                }
+            }
+        } else if is_full {
+            self.wakeup.broadcast();
+        }
+
+        loop {
+            if let Some(res) = &*result.borrow() {
+                return res.clone().map_err(Error::from);
+            }
+            // The write is already a part of the batch, so the fiber waits
+            // for the result even if it's cancelled.
+            self.wakeup.wait();
+        }
+    }
+
+    /// Applies the batch in a single transaction and wakes up its writers.
+    fn apply(&self, batch: Batch) {
+        let start = Instant::now_accurate();
+        let res = transaction::transaction(|| -> Result<(), Error> {
+            for op in &batch.ops {
+                match op {
+                    WriteOp::Insert(value) => self.space.insert(value).map(drop)?,
+                    WriteOp::Replace(value) => self.space.replace(value).map(drop)?,
+                    WriteOp::Delete(key) => self.space.delete(key).map(drop)?,
+                    WriteOp::Update(key, ops) => self.space.update(key, ops).map(drop)?,
+                    WriteOp::Upsert(value, ops) => self.space.upsert(value, ops)?,
+                }
+            }
+            Ok(())
+        });
+        let elapsed = start.elapsed();
+
+        {
+            let mut stats = self.stats.borrow_mut();
+            stats.batches += 1;
+            stats.writes += batch.ops.len() as u64;
+            stats.max_batch_len = stats.max_batch_len.max(batch.ops.len());
+            stats.total_flush_time += elapsed;
+            stats.max_flush_time = stats.max_flush_time.max(elapsed);
+            if res.is_err() {
+                stats.failed_batches += 1;
+            }
+        }
+
+        let res = res.map_err(|e| Error::from(e).into_box_error());
+        *batch.result.borrow_mut() = Some(res);
+        self.wakeup.broadcast();
+    }
+}
+
+fn encode_ops<Op>(ops: &[Op]) -> Result<Vec<TupleBuffer>, Error>
+where
+    Op: ToTupleBuffer,
+{
+    ops.iter().map(|op| op.to_tuple_buffer()).collect()
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::space::FieldType;
+
+    fn create_space() -> Space {
+        Space::builder(&crate::temp_space_name!())
+            .field(("id", FieldType::Unsigned))
+            .field(("value", FieldType::Unsigned))
+            .create()
+            .unwrap()
+    }
+
+    fn spawn_writers(
+        combiner: &Rc<WriteCombiner>,
+        ids: std::ops::Range<u32>,
+    ) -> Vec<fiber::JoinHandle<'static, Result<(), Error>>> {
+        ids.map(|id| {
+            let combiner = combiner.clone();
+            fiber::start(move || combiner.insert(&(id, id * 10)))
+        })
+        .collect()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn combine_writes() {
+        let space = create_space();
+        space.index_builder("pk").create().unwrap();
+        let combiner = Rc::new(WriteCombiner::new(
+            space.clone(),
+            CombinerOptions {
+                max_delay: Duration::from_secs(10),
+                max_batch: 5,
+                ..Default::default()
+            },
+        ));
+
+        let writers = spawn_writers(&combiner, 0..5);
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(space.len().unwrap(), 5);
+
+        let stats = combiner.stats();
+        assert_eq!(stats.batches, 1);
+        assert_eq!(stats.writes, 5);
+        assert_eq!(stats.max_batch_len, 5);
+        assert_eq!(stats.failed_batches, 0);
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.mean_batch_len(), Some(5.0));
+
+        let combiner = Rc::new(WriteCombiner::new(
+            space.clone(),
+            CombinerOptions {
+                flush_on_yield: true,
+                ..Default::default()
+            },
+        ));
+        let writers = spawn_writers(&combiner, 5..8);
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        combiner.update(&(5,), [("=", 1, 0)]).unwrap();
+        combiner.delete(&(6,)).unwrap();
+        assert_eq!(space.len().unwrap(), 7);
+        assert_eq!(
+            space.get(&(5,)).unwrap().unwrap().field(1).unwrap(),
+            Some(0)
+        );
+        assert_eq!(combiner.stats().writes, 5);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn failed_batch() {
+        let space = create_space();
+        space.index_builder("pk").create().unwrap();
+        space.insert(&(2, 0)).unwrap();
+        let combiner = Rc::new(WriteCombiner::new(
+            space.clone(),
+            CombinerOptions {
+                max_delay: Duration::from_secs(10),
+                ..Default::default()
+            },
+        ));
+
+        // The writes are only flushed explicitly.
+        let writers = spawn_writers(&combiner, 0..3);
+        fiber::reschedule();
+        assert_eq!(combiner.stats().pending, 3);
+        let e = combiner.flush().unwrap_err();
+        assert!(e.to_string().contains("Duplicate key exists"), "{}", e);
+        for writer in writers {
+            let e = writer.join().unwrap_err();
+            assert!(e.to_string().contains("Duplicate key exists"), "{}", e);
+        }
+        // The batch is rolled back.
+        assert_eq!(space.len().unwrap(), 1);
+
+        let stats = combiner.stats();
+        assert_eq!(stats.batches, 1);
+        assert_eq!(stats.failed_batches, 1);
+        assert_eq!(stats.pending, 0);
+
+        transaction::transaction(|| {
+            let e = combiner.insert(&(5, 0)).unwrap_err();
+            assert!(e.to_string().contains("inside a transaction"), "{}", e);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+
+        space.drop().unwrap();
+    }
+}
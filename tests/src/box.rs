@@ -931,6 +931,8 @@ pub fn space_meta() {
                 name: "f3".to_string(),
                 field_type: space::FieldType::String,
                 is_nullable: true,
+                constraint: None,
+                foreign_key: None,
             },
         ]),
         ..Default::default()
@@ -998,6 +1000,83 @@ pub fn index_parts() {
     assert!(iter.next().is_none());
 }
 
+pub fn space_builder_with_indexes() {
+    let lua = tarantool::lua_state();
+    lua.exec(
+        "box.schema.func.create('check_positive', {
+            language = 'LUA',
+            is_deterministic = true,
+            body = 'function(x) return x > 0 end',
+        })",
+    )
+    .unwrap();
+    let _guard = on_scope_exit(|| {
+        tarantool::lua_state()
+            .exec("box.schema.func.drop('check_positive')")
+            .unwrap();
+    });
+
+    let build = || {
+        Space::builder("builder_with_indexes")
+            .if_not_exists(true)
+            .field(Field::unsigned("id").constraint("positive", "check_positive"))
+            .field(Field::unsigned("parent").is_nullable(true).foreign_key(
+                "parent",
+                "builder_with_indexes",
+                "id",
+            ))
+            .field(Field::array("coords"))
+            .field(Field::array("tags"))
+            .index("pk", |i| i.part("id"))
+            .index("coords", |i| {
+                i.rtree()
+                    .unique(false)
+                    .part("coords")
+                    .dimension(2)
+                    .distance(index::RtreeIndexDistanceType::Manhattan)
+            })
+            .index("tags", |i| {
+                i.unique(false).part(
+                    index::Part::field("tags")
+                        .field_type(index::FieldType::String)
+                        .path("[*]"),
+                )
+            })
+            .create()
+    };
+    let space = build().unwrap();
+    // if_not_exists applies to indexes as well
+    assert_eq!(build().unwrap().id(), space.id());
+
+    space.insert(&(1, None::<u32>, [0, 0], ["a", "b"])).unwrap();
+    space.insert(&(2, Some(1), [1, 1], ["b"])).unwrap();
+    // constraint fails
+    space.insert(&(0, None::<u32>, [2, 2], ["c"])).unwrap_err();
+    // foreign key fails
+    space.insert(&(3, Some(69), [3, 3], ["c"])).unwrap_err();
+
+    let by_tag = space.index("tags").unwrap();
+    let ids: Vec<u32> = by_tag
+        .select(IteratorType::Eq, &("b",))
+        .unwrap()
+        .map(|t| t.field(0).unwrap().unwrap())
+        .collect();
+    assert_eq!(ids, [1, 2]);
+
+    let by_coords = space.index("coords").unwrap();
+    assert_eq!(by_coords.meta().unwrap().r#type, index::IndexType::Rtree);
+
+    space.drop().unwrap();
+
+    // space is dropped if index creation fails
+    Space::builder("builder_bad_index")
+        .field(Field::unsigned("id"))
+        .index("pk", |i| i.part("no_such_field"))
+        .create()
+        .unwrap_err();
+    assert!(Space::find("builder_bad_index").is_none());
+}
+
 pub fn fully_temporary_space() {
     let lua = tarantool::lua_state();
     lua.exec("box.cfg { read_only = true }").unwrap();
@@ -308,6 +308,7 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 tlua::values::read_i32s,
                 tlua::values::write_i32s,
                 tlua::values::int64,
+                tlua::values::number_policy,
                 tlua::values::cdata_numbers,
                 tlua::values::push_cdata,
                 tlua::values::cdata_on_stack,
@@ -439,6 +440,7 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 r#box::space_drop,
                 r#box::index_create_drop,
                 r#box::index_parts,
+                r#box::space_builder_with_indexes,
                 tuple::tuple_new_from_struct,
                 tuple::new_tuple_from_flatten_struct,
                 tuple::tuple_field_count,
@@ -479,6 +481,7 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 net_box::ping,
                 net_box::ping_timeout,
                 net_box::ping_concurrent,
+                net_box::priority,
                 net_box::call,
                 net_box::call_async,
                 net_box::call_async_error,
@@ -498,20 +501,27 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 net_box::replace,
                 net_box::update,
                 net_box::upsert,
+                net_box::update_ops,
                 net_box::delete,
                 net_box::cancel_recv,
                 net_box::triggers_connect,
                 net_box::triggers_reject,
                 net_box::triggers_schema_sync,
                 net_box::execute,
+                net_box::from_fd,
+                net_box::unix_socket,
                 proc::simple,
                 proc::return_tuple,
                 proc::return_raw_bytes,
+                proc::raw_args,
+                proc::raw_args_tail,
+                proc::msgpack_args,
                 proc::with_error,
                 proc::packed,
                 proc::debug,
                 proc::tarantool_reimport,
                 proc::custom_ret,
+                proc::return_rows,
                 proc::inject,
                 proc::inject_with_packed,
                 uuid::to_tuple,
@@ -543,6 +553,8 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                     sql::prepared_with_unnamed_params,
                     sql::prepared_with_named_params,
                     sql::prepared_invalid_params,
+                    sql::prepared_typed_rows,
+                    sql::prepared_cached,
                     tuple_picodata::tuple_format_get_names,
                     tuple_picodata::tuple_as_named_buffer,
                     tuple_picodata::tuple_hash,
@@ -8,8 +8,8 @@ use tarantool::fiber::reschedule;
 use tarantool::fiber::sleep;
 use tarantool::fiber::Cond;
 use tarantool::index::IteratorType;
-use tarantool::net_box::{promise::State, Conn, ConnOptions, ConnTriggers, Options};
-use tarantool::space::Space;
+use tarantool::net_box::{promise::State, Conn, ConnOptions, ConnTriggers, Options, Priority};
+use tarantool::space::{Space, UpdateOps};
 use tarantool::test::util::listen_port;
 use tarantool::tuple::Tuple;
 
@@ -111,6 +111,60 @@ pub fn ping_concurrent() {
     fiber_b.join();
 }
 
+pub fn priority() {
+    let conn = Rc::new(test_user_conn());
+    conn.ping(&Options::default()).unwrap();
+
+    let lua = tarantool::lua_state();
+    lua.exec("_G.net_box_priority_order = {}").unwrap();
+
+    // The requests are enqueued before the writer fiber wakes up, so they're
+    // sent and hence handled in the order of their priority.
+    let fibers: Vec<_> = [Priority::Low, Priority::Normal, Priority::High]
+        .iter()
+        .map(|&priority| {
+            let conn = conn.clone();
+            fiber::start(move || {
+                let options = Options {
+                    priority,
+                    ..Options::default()
+                };
+                let expr = "table.insert(_G.net_box_priority_order, ...)";
+                conn.eval(expr, &(format!("{:?}", priority),), &options)
+                    .unwrap();
+            })
+        })
+        .collect();
+    for f in fibers {
+        f.join();
+    }
+
+    let order: Vec<String> = lua.eval("return _G.net_box_priority_order").unwrap();
+    assert_eq!(order, ["High", "Normal", "Low"]);
+
+    // Same for the async requests.
+    lua.exec("_G.net_box_priority_order = {}").unwrap();
+    let promises: Vec<_> = [Priority::Low, Priority::Normal, Priority::High]
+        .iter()
+        .map(|&priority| {
+            let options = Options {
+                priority,
+                ..Options::default()
+            };
+            let expr = "table.insert(_G.net_box_priority_order, ...)";
+            conn.eval_async_with_options::<_, Tuple>(expr, (format!("{:?}", priority),), &options)
+                .unwrap()
+        })
+        .collect();
+    for p in promises {
+        p.wait().unwrap();
+    }
+
+    let order: Vec<String> = lua.eval("return _G.net_box_priority_order").unwrap();
+    assert_eq!(order, ["High", "Normal", "Low"]);
+    lua.exec("_G.net_box_priority_order = nil").unwrap();
+}
+
 pub fn call() {
     let conn = test_user_conn();
     let result = conn
@@ -505,6 +559,52 @@ pub fn upsert() {
     assert_eq!(output.unwrap().decode::<S1Record>().unwrap().text, "New");
 }
 
+pub fn update_ops() {
+    let local_space = Space::find("test_s1").unwrap();
+    local_space.truncate().unwrap();
+    local_space
+        .insert(&S1Record {
+            id: 1,
+            text: "Original".to_string(),
+        })
+        .unwrap();
+
+    let conn = test_user_conn();
+    let remote_space = conn.space("test_s1").unwrap().unwrap();
+
+    let mut ops = UpdateOps::new();
+    ops.splice("text", 0, 1, "Not o").unwrap();
+    let res = remote_space
+        .update(&(1,), &ops, &Options::default())
+        .unwrap();
+    assert_eq!(
+        res.unwrap().decode::<S1Record>().unwrap().text,
+        "Not original"
+    );
+
+    let mut ops = UpdateOps::new();
+    ops.assign(1, "Upserted").unwrap();
+    for id in [1, 2] {
+        let value = S1Record {
+            id,
+            text: "Inserted".to_string(),
+        };
+        remote_space
+            .upsert(&value, &ops, &Options::default())
+            .unwrap();
+    }
+
+    let output = local_space.get(&(1,)).unwrap();
+    assert_eq!(
+        output.unwrap().decode::<S1Record>().unwrap().text,
+        "Upserted"
+    );
+    let output = local_space.get(&(2,)).unwrap();
+    assert_eq!(
+        output.unwrap().decode::<S1Record>().unwrap().text,
+        "Inserted"
+    );
+}
 pub fn delete() {
     let local_space = Space::find("test_s1").unwrap();
     local_space.truncate().unwrap();
@@ -656,3 +756,179 @@ pub fn triggers_schema_sync() {
 
     assert_eq!(is_trigger_called.get(), true);
 }
+
+#[::tarantool::test]
+fn proc_current_request() {
+    use tarantool::session::{self, RequestInfo};
+
+    #[tarantool::proc]
+    fn proc_current_request() -> tarantool::Result<(u64, Option<u64>, Option<String>)> {
+        let RequestInfo {
+            session_id,
+            sync,
+            peer,
+        } = session::current_request()?;
+        Ok((session_id, sync, peer))
+    }
+
+    let name = format!("{}.proc_current_request", crate::common::lib_name());
+    tarantool::lua_state()
+        .exec_with(
+            "local name = ...
+            if box.func[name] == nil then
+                box.schema.func.create(name, { language = 'C' })
+            end",
+            &name,
+        )
+        .unwrap();
+
+    let local = session::current_request().unwrap();
+    assert_eq!(local.sync, None);
+    assert_eq!(local.peer, None);
+
+    let conn = test_user_conn();
+    let ((session_id_1, sync_1, peer_1),): ((u64, Option<u64>, Option<String>),) = conn
+        .call(&name, &(), &Options::default())
+        .unwrap()
+        .unwrap()
+        .decode()
+        .unwrap();
+    assert_ne!(session_id_1, local.session_id);
+    assert!(sync_1.is_some());
+    assert!(peer_1.is_some());
+
+    let ((session_id_2, sync_2, _),): ((u64, Option<u64>, Option<String>),) = conn
+        .call(&name, &(), &Options::default())
+        .unwrap()
+        .unwrap()
+        .decode()
+        .unwrap();
+    assert_eq!(session_id_2, session_id_1);
+    assert_ne!(sync_2, sync_1);
+}
+
+#[::tarantool::test]
+fn session_triggers() {
+    use tarantool::session;
+
+    let connected = Rc::new(Cell::new(0));
+    let disconnected = Rc::new(Cell::new(0));
+    let reject = Rc::new(Cell::new(false));
+
+    let on_connect = session::on_connect({
+        let connected = connected.clone();
+        let reject = reject.clone();
+        move || {
+            if reject.get() {
+                return Err("connection rejected");
+            }
+            connected.set(connected.get() + 1);
+            Ok(())
+        }
+    })
+    .unwrap();
+    let on_disconnect = session::on_disconnect({
+        let disconnected = disconnected.clone();
+        move || {
+            disconnected.set(disconnected.get() + 1);
+            Ok::<_, String>(())
+        }
+    })
+    .unwrap();
+
+    let conn = test_user_conn();
+    conn.ping(&Options::default()).unwrap();
+    assert_eq!(connected.get(), 1);
+    conn.close();
+    for _ in 0..100 {
+        if disconnected.get() > 0 {
+            break;
+        }
+        fiber::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(disconnected.get(), 1);
+
+    reject.set(true);
+    let conn = test_user_conn();
+    conn.ping(&Options::default()).unwrap_err();
+    assert_eq!(connected.get(), 1);
+
+    assert!(on_connect.unregister().unwrap());
+    assert!(on_disconnect.unregister().unwrap());
+    let conn = test_user_conn();
+    conn.ping(&Options::default()).unwrap();
+    assert_eq!(connected.get(), 1);
+}
+
+pub fn from_fd() {
+    let stream = std::net::TcpStream::connect(("localhost", listen_port())).unwrap();
+    let conn = Conn::from_fd(stream, ConnOptions::default(), None).unwrap();
+    conn.ping(&Options::default()).unwrap();
+    let res = conn.eval("return 1 + 2", &(), &Options::default()).unwrap();
+    assert_eq!(res.unwrap().decode::<(i32,)>().unwrap(), (3,));
+
+    // The connection can't be re-established once the socket is closed.
+    let (stream, peer) = std::os::unix::net::UnixStream::pair().unwrap();
+    drop(peer);
+    let conn = Conn::from_fd(
+        stream,
+        ConnOptions {
+            reconnect_after: Duration::from_millis(10),
+            ..ConnOptions::default()
+        },
+        None,
+    )
+    .unwrap();
+    let options = Options {
+        timeout: Some(Duration::from_secs(1)),
+        ..Options::default()
+    };
+    conn.ping(&options).unwrap_err();
+    conn.ping(&options).unwrap_err();
+    assert!(!conn.is_connected());
+}
+
+pub fn unix_socket() {
+    let lua = tarantool::lua_state();
+    let path = format!("/tmp/tarantool-module-test-{}.sock", std::process::id());
+    let old_listen: String = lua.eval("return box.cfg.listen").unwrap();
+    lua.exec_with("box.cfg{listen = {box.cfg.listen, 'unix/:' .. ...}}", &path)
+        .unwrap();
+
+    let result = std::panic::catch_unwind(|| {
+        let conn = Conn::new_unix(&path, ConnOptions::default(), None).unwrap();
+        conn.ping(&Options::default()).unwrap();
+        let res = conn.eval("return 1 + 2", &(), &Options::default()).unwrap();
+        assert_eq!(res.unwrap().decode::<(i32,)>().unwrap(), (3,));
+
+        let conn =
+            Conn::from_uri(&format!("unix/:{}", path), ConnOptions::default(), None).unwrap();
+        conn.ping(&Options::default()).unwrap();
+
+        let conn_options = ConnOptions {
+            connect_timeout: Duration::from_secs(1),
+            ..ConnOptions::default()
+        };
+        let conn = Conn::new_unix(&path, conn_options.clone(), None).unwrap();
+        conn.ping(&Options::default()).unwrap();
+
+        let missing = format!("{}.missing", path);
+        let conn = Conn::new_unix(&missing, conn_options, None).unwrap();
+        conn.ping(&Options::default()).unwrap_err();
+        assert!(!conn.is_connected());
+    });
+
+    lua.exec_with("box.cfg{listen = ...}", &old_listen).unwrap();
+    if let Err(e) = result {
+        std::panic::resume_unwind(e);
+    }
+
+    // `host:port` is also supported
+    let conn = Conn::from_uri(
+        &format!("localhost:{}", listen_port()),
+        ConnOptions::default(),
+        None,
+    )
+    .unwrap();
+    conn.ping(&Options::default()).unwrap();
+}
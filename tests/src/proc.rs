@@ -2,7 +2,7 @@ use crate::common::lib_name;
 use rmpv::Value;
 use std::ffi::OsStr;
 use tarantool::{
-    proc::ReturnMsgpack,
+    proc::{RawArgsTail, ReturnMsgpack, ReturnRows},
     tlua::{
         self, AsTable, Call, CallError, LuaFunction, LuaRead, LuaState, LuaThread, PushGuard,
         PushInto,
@@ -143,6 +143,147 @@ pub fn return_raw_bytes() {
     );
 }
 
+pub fn raw_args() {
+    #[tarantool::proc(packed_args)]
+    fn proc_raw_args(args: &RawBytes) -> tarantool::Result<(usize, String)> {
+        let mut iter = args.array_iter()?;
+        let name: &str = iter.decode_next().unwrap()?;
+        let sum = iter
+            .map(|v| RawBytes::new(v).decode::<usize>())
+            .sum::<tarantool::Result<usize>>()?;
+        Ok((args.array_iter()?.count(), format!("{}: {}", name, sum)))
+    }
+
+    assert_eq!(
+        call_proc("proc_raw_args", ("sum", 1, 2, 3)).ok(),
+        Some(AsTable((4, "sum: 6".to_string())))
+    );
+
+    #[tarantool::proc(packed_args)]
+    fn proc_raw_return(args: &RawBytes) -> tarantool::Result<&RawBytes> {
+        Ok(RawBytes::new(args.array_iter()?.nth(1).unwrap_or(&[0xc0])))
+    }
+
+    assert_eq!(
+        call_proc("proc_raw_return", ("a", ["b", "c"])).ok(),
+        Some(["b".to_string(), "c".to_string()])
+    );
+    assert_eq!(
+        call_proc("proc_raw_return", ("a",)).ok(),
+        Some(Option::<i32>::None)
+    );
+
+    let not_array = RawBytes::new(b"\x01");
+    assert!(not_array.array_iter().is_err());
+    assert_eq!(not_array.decode::<u8>().unwrap(), 1);
+}
+
+pub fn raw_args_tail() {
+    #[tarantool::proc]
+    fn proc_raw_args_tail(kind: String, n: usize, rest: RawArgsTail) -> (String, usize, Value) {
+        let rest_value = rest.decode().unwrap();
+        assert_eq!(rest.iter().count(), rest.len());
+        (kind, n + rest.len(), rest_value)
+    }
+
+    assert_eq!(
+        call_proc("proc_raw_args_tail", ("x", 1, "a", [2, 3])).ok(),
+        Some(AsTable((
+            "x".to_string(),
+            3,
+            AsTable(("a".to_string(), [2, 3]))
+        )))
+    );
+    assert_eq!(
+        call_proc("proc_raw_args_tail", ("y", 1)).ok(),
+        Some(AsTable(("y".to_string(), 1, Vec::<i32>::new())))
+    );
+    let e = call_proc::<_, ()>("proc_raw_args_tail", ("z",)).unwrap_err();
+    assert!(
+        e.to_string()
+            .contains("expected at least 2 arguments, got 1"),
+        "{}",
+        e
+    );
+
+    // The parameter is recognized by its type, not by its name.
+    type Rest<'a> = RawArgsTail<'a>;
+    #[tarantool::proc]
+    fn proc_raw_args_tail_alias<'a>(kind: &'a str, rest: Rest<'a>) -> (&'a str, usize) {
+        (kind, rest.len())
+    }
+
+    assert_eq!(
+        call_proc("proc_raw_args_tail_alias", ("x", 1, 2)).ok(),
+        Some(("x".to_string(), 2))
+    );
+
+    #[tarantool::proc]
+    fn proc_raw_args_tail_only(rest: RawArgsTail) -> &RawBytes {
+        rest.get(1).unwrap_or_else(|| RawBytes::new(&[0xc0]))
+    }
+
+    assert_eq!(
+        call_proc("proc_raw_args_tail_only", (1, "second")).ok(),
+        Some("second".to_string())
+    );
+}
+
+pub fn msgpack_args() {
+    use std::collections::BTreeMap;
+    use tarantool::msgpack;
+
+    #[derive(msgpack::Decode, Debug)]
+    #[encode(as_map)]
+    struct Options {
+        name: String,
+        limit: Option<u32>,
+        #[decode(flatten_unknown)]
+        unknown: BTreeMap<String, msgpack::Value>,
+    }
+
+    #[tarantool::proc(msgpack_args)]
+    fn proc_msgpack_args(n: u32, opts: Options) -> (u32, String, Option<u32>, Vec<String>) {
+        let unknown = opts.unknown.keys().cloned().collect();
+        (n, opts.name, opts.limit, unknown)
+    }
+
+    assert_eq!(
+        call_proc(
+            "proc_msgpack_args",
+            (1, AsTable((("name", "foo"), ("color", "red"), ("size", 3))))
+        )
+        .ok(),
+        Some(AsTable((
+            1,
+            "foo".to_string(),
+            None::<u32>,
+            ["color".to_string(), "size".to_string()]
+        )))
+    );
+    assert_eq!(
+        call_proc(
+            "proc_msgpack_args",
+            (2, AsTable((("limit", 10), ("name", "bar"))))
+        )
+        .ok(),
+        Some(AsTable((
+            2,
+            "bar".to_string(),
+            Some(10),
+            Vec::<String>::new()
+        )))
+    );
+
+    // The number of the arguments must match.
+    let e = call_proc::<_, ()>("proc_msgpack_args", (3,)).unwrap_err();
+    assert!(
+        e.to_string().contains("expected array count 2, got 1"),
+        "{}",
+        e
+    );
+}
+
 pub fn debug() {
     #[tarantool::proc(debug, packed_args)]
     fn proc_debug(v: Value) -> String {
@@ -211,6 +352,56 @@ pub fn custom_ret() {
     );
 }
 
+pub fn return_rows() {
+    #[tarantool::proc]
+    fn proc_return_vec(n: i32) -> Vec<i32> {
+        (0..n).collect()
+    }
+
+    #[tarantool::proc(rows)]
+    fn proc_return_rows(n: i32) -> Vec<i32> {
+        (0..n).collect()
+    }
+
+    #[tarantool::proc]
+    fn proc_return_rows_explicit(n: i32) -> ReturnRows<Vec<Tuple>> {
+        ReturnRows((0..n).map(|i| Tuple::new(&[i, i * 10]).unwrap()).collect())
+    }
+
+    #[tarantool::proc(rows)]
+    fn proc_return_rows_result(fail: bool) -> Result<Vec<[i32; 2]>, String> {
+        if fail {
+            return Err("failed".into());
+        }
+        Ok(vec![[1, 2], [3, 4]])
+    }
+
+    assert_eq!(
+        call_proc::<_, Vec<i32>>("proc_return_vec", 3).unwrap(),
+        [0, 1, 2]
+    );
+    assert_eq!(
+        call_proc::<_, (i32, i32, i32)>("proc_return_rows", 3).unwrap(),
+        (0, 1, 2)
+    );
+    assert_eq!(
+        call_proc::<_, Option<i32>>("proc_return_rows", 0).unwrap(),
+        None
+    );
+    assert_eq!(
+        call_proc::<_, ([i32; 2], [i32; 2])>("proc_return_rows_explicit", 2).unwrap(),
+        ([0, 0], [1, 10])
+    );
+    assert_eq!(
+        call_proc::<_, ([i32; 2], [i32; 2])>("proc_return_rows_result", false).unwrap(),
+        ([1, 2], [3, 4])
+    );
+    assert_eq!(
+        call_proc("proc_return_rows_result", true).map_err(|e| e.to_string()),
+        Err::<(), _>("Lua error: failed".into()),
+    );
+}
+
 pub fn inject() {
     #[tarantool::proc]
     fn proc_inject<'a>(
@@ -318,6 +509,94 @@ pub fn inject_with_packed() {
     );
 }
 
+#[::tarantool::test]
+fn call_local() {
+    use tarantool::error::{Error, TarantoolErrorCode};
+    use tarantool::proc::call_local;
+
+    #[tarantool::proc]
+    fn proc_call_local(x: i32, y: String) -> Result<(i32, String), String> {
+        if x < 0 {
+            return Err(y);
+        }
+        Ok((x * 2, y.repeat(2)))
+    }
+
+    let name = format!("{}.proc_call_local", lib_name());
+    tarantool::lua_state()
+        .exec_with(
+            "local name = ...
+            if box.func[name] == nil then
+                box.schema.func.create(name, { language = 'C' })
+            end",
+            &name,
+        )
+        .unwrap();
+
+    let ((x, y),): ((i32, String),) = call_local(&name, &(21, "foo")).unwrap();
+    assert_eq!(x, 42);
+    assert_eq!(y, "foofoo");
+
+    let e = call_local::<()>(&name, &(-1, "oops")).unwrap_err();
+    assert!(e.to_string().contains("oops"), "{}", e);
+
+    // nil and binary arguments are passed as is
+    #[tarantool::proc(packed_args)]
+    fn proc_call_local_kinds(args: Vec<Value>) -> (Vec<&'static str>, Option<i32>, i32) {
+        let kinds = args
+            .iter()
+            .map(|v| match v {
+                Value::Nil => "nil",
+                Value::Binary(_) => "binary",
+                Value::String(_) => "string",
+                _ => "other",
+            })
+            .collect();
+        (kinds, None, 1)
+    }
+
+    let name = format!("{}.proc_call_local_kinds", lib_name());
+    tarantool::lua_state()
+        .exec_with(
+            "local name = ...
+            if box.func[name] == nil then
+                box.schema.func.create(name, { language = 'C' })
+            end",
+            &name,
+        )
+        .unwrap();
+
+    let ((kinds, none, one),): ((Vec<String>, Option<i32>, i32),) = call_local(
+        &name,
+        &(
+            (),
+            serde_bytes::Bytes::new(b"\x00\xff"),
+            "str",
+            Option::<i32>::None,
+        ),
+    )
+    .unwrap();
+    assert_eq!(kinds, ["nil", "binary", "string", "nil"]);
+    assert_eq!(none, None);
+    assert_eq!(one, 1);
+
+    // nil results are preserved
+    tarantool::lua_state()
+        .exec(
+            "function test_call_local_nils() return nil, nil, 3 end
+            box.schema.func.create('test_call_local_nils', { if_not_exists = true })",
+        )
+        .unwrap();
+    let res: (Option<i32>, Option<i32>, i32) = call_local("test_call_local_nils", &()).unwrap();
+    assert_eq!(res, (None, None, 3));
+
+    let e = call_local::<()>("no_such_proc", &()).unwrap_err();
+    let Error::Tarantool(e) = e else {
+        panic!("unexpected error: {}", e);
+    };
+    assert_eq!(e.error_code(), TarantoolErrorCode::NoSuchFunction as u32);
+}
+
 #[::tarantool::test]
 #[cfg(target_os = "linux")]
 fn module_path() {
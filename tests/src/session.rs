@@ -49,3 +49,80 @@ pub fn user_id_by_name() {
     assert_eq!(session::user_id_by_name("guest").unwrap(), GUEST_UID);
     assert_eq!(session::user_id_by_name("admin").unwrap(), ADMIN_UID);
 }
+
+#[tarantool::test]
+pub fn send_packet() {
+    use std::cell::Cell;
+    use std::io::Read;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use tarantool::coio::CoIOStream;
+    use tarantool::error::{IntoBoxError, TarantoolErrorCode};
+    use tarantool::fiber;
+    use tarantool::network::protocol::codec::{iproto_key, IProtoType};
+    use tarantool::test::util::listen_port;
+
+    if !tarantool::ffi::has_iproto_send() {
+        return;
+    }
+
+    let session_id = Rc::new(Cell::new(None));
+    let on_connect = session::on_connect({
+        let session_id = session_id.clone();
+        move || {
+            session_id.set(Some(session::id()?));
+            Ok::<_, tarantool::error::Error>(())
+        }
+    })
+    .unwrap();
+
+    let mut stream = CoIOStream::connect(("localhost", listen_port())).unwrap();
+    let mut greeting = [0; 128];
+    stream.read_exact(&mut greeting).unwrap();
+    for _ in 0..100 {
+        if session_id.get().is_some() {
+            break;
+        }
+        fiber::sleep(Duration::from_millis(10));
+    }
+    assert!(on_connect.unregister().unwrap());
+    let session_id = session_id.get().unwrap();
+
+    session::Packet::new(IProtoType::Chunk)
+        .sync(42)
+        .schema_version(7)
+        .data(&("hello", 13))
+        .send(session_id)
+        .unwrap();
+
+    let size = rmp::decode::read_u32(&mut stream).unwrap();
+    let mut packet = vec![0; size as usize];
+    stream.read_exact(&mut packet).unwrap();
+    let mut packet = &packet[..];
+    let header = rmpv::decode::read_value(&mut packet).unwrap();
+    let body = rmpv::decode::read_value(&mut packet).unwrap();
+    assert!(packet.is_empty());
+    assert_eq!(
+        header,
+        rmpv::Value::Map(vec![
+            (
+                iproto_key::REQUEST_TYPE.into(),
+                (IProtoType::Chunk as u32).into()
+            ),
+            (iproto_key::SYNC.into(), 42.into()),
+            (iproto_key::SCHEMA_VERSION.into(), 7.into()),
+        ])
+    );
+    assert_eq!(
+        body,
+        rmpv::Value::Map(vec![(
+            iproto_key::DATA.into(),
+            rmpv::Value::Array(vec!["hello".into(), 13.into()]),
+        )])
+    );
+
+    let e = session::Packet::new(IProtoType::Chunk)
+        .send(u64::MAX)
+        .unwrap_err();
+    assert_eq!(e.error_code(), TarantoolErrorCode::NoSuchSession as u32);
+}
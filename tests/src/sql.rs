@@ -252,3 +252,77 @@ pub fn prepared_with_named_params() {
 
     drop_sql_test_space(sp).unwrap();
 }
+
+pub fn prepared_typed_rows() {
+    let sp = create_sql_test_space("SQL_TEST").unwrap();
+
+    sp.insert(&(1, "one")).unwrap();
+    sp.insert(&(2, "two")).unwrap();
+    sp.insert(&(3, "three")).unwrap();
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Row {
+        id: u64,
+        value: String,
+    }
+
+    let stmt = tarantool::sql::prepare("SELECT * FROM SQL_TEST WHERE ID > ?".to_string()).unwrap();
+    let rows: Vec<Row> = stmt.execute(&(1,), 0).unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            Row {
+                id: 2,
+                value: "two".into()
+            },
+            Row {
+                id: 3,
+                value: "three".into()
+            },
+        ]
+    );
+
+    let rows: Vec<(u64, String)> =
+        tarantool::sql::prepare_and_execute("SELECT * FROM SQL_TEST WHERE ID = ?", &(3,), 0)
+            .unwrap();
+    assert_eq!(rows, vec![(3, "three".to_string())]);
+
+    // Statements without a result set return no rows.
+    let rows: Vec<(u64, String)> =
+        tarantool::sql::prepare_and_execute("INSERT INTO SQL_TEST VALUES (?, ?)", &(4, "four"), 0)
+            .unwrap();
+    assert!(rows.is_empty());
+
+    drop_sql_test_space(sp).unwrap();
+}
+
+pub fn prepared_cached() {
+    let sp = create_sql_test_space("SQL_TEST").unwrap();
+
+    sp.insert(&(1, "one")).unwrap();
+    sp.insert(&(2, "two")).unwrap();
+
+    let query = "SELECT * FROM SQL_TEST WHERE ID = ?";
+    let stmt1 = tarantool::sql::prepare_cached(query).unwrap();
+    let stmt2 = tarantool::sql::prepare_cached(query).unwrap();
+    assert!(std::rc::Rc::ptr_eq(&stmt1, &stmt2));
+    assert_eq!(stmt1.id(), tarantool::sql::calculate_hash(query));
+
+    let rows: Vec<(u64, String)> = stmt2.execute(&(2,), 0).unwrap();
+    assert_eq!(rows, vec![(2, "two".to_string())]);
+
+    tarantool::sql::clear_cache().unwrap();
+    let stmt3 = tarantool::sql::prepare_cached(query).unwrap();
+    assert!(!std::rc::Rc::ptr_eq(&stmt1, &stmt3));
+
+    // Statements are cached by the query text.
+    let other = "SELECT * FROM SQL_TEST WHERE ID > ?";
+    let stmt4 = tarantool::sql::prepare_cached(other).unwrap();
+    assert_eq!(stmt4.source(), other);
+    assert_eq!(stmt3.source(), query);
+    let rows: Vec<(u64, String)> = stmt4.execute(&(1,), 0).unwrap();
+    assert_eq!(rows, vec![(2, "two".to_string())]);
+    tarantool::sql::clear_cache().unwrap();
+
+    drop_sql_test_space(sp).unwrap();
+}
@@ -1,10 +1,11 @@
 use std::ffi::{CStr, CString, OsStr, OsString};
 use std::os::raw::{c_char, c_void};
 use std::path::{Path, PathBuf};
+use tarantool::tlua::{self, with_number_policy};
 use tarantool::tlua::{
     c_ptr, c_str, ffi, function0, AnyLuaString, AnyLuaValue, AsCData, AsLua, AsTable, CData,
-    CDataOnStack, False, Lua, LuaFunction, LuaTable, Nil, Null, Strict, StringInLua, ToString,
-    True, Typename,
+    CDataOnStack, False, Lua, LuaError, LuaFunction, LuaTable, Nil, Null, NumberError,
+    NumberPolicy, Strict, StringInLua, ToString, True, Typename,
 };
 
 pub fn read_i32s() {
@@ -479,6 +480,96 @@ pub fn int64() {
     assert_eq!((&lua).read::<CData<f64>>().ok(), None);
 }
 
+pub fn number_policy() {
+    let lua = Lua::new();
+    assert_eq!(lua.number_policy(), NumberPolicy::Truncate);
+
+    // Truncate is the default.
+    assert_eq!(lua.eval::<u8>("return 300.5").unwrap(), 44);
+    assert_eq!(lua.eval::<i8>("return -129").unwrap(), 127);
+
+    macro_rules! read {
+        ($lua:expr, $policy:ident, $code:expr) => {
+            with_number_policy(NumberPolicy::$policy, || $lua.eval($code).ok())
+        };
+    }
+    assert_eq!(read!(lua, Saturate, "return 300.5"), Some(255_u8));
+    assert_eq!(read!(lua, Saturate, "return -1"), Some(0_u32));
+    assert_eq!(read!(lua, Saturate, "return -129.9"), Some(-128_i8));
+    assert_eq!(read!(lua, Saturate, "return 0/0"), Some(0_i32));
+    assert_eq!(read!(lua, Saturate, "return 1/0"), Some(i64::MAX));
+    assert_eq!(read!(lua, Saturate, "return -1/0"), Some(u64::MIN));
+    // Floats are unaffected.
+    assert_eq!(read!(lua, Saturate, "return 300.5"), Some(300.5_f64));
+
+    lua.set_number_policy(NumberPolicy::Strict);
+    assert_eq!(lua.number_policy(), NumberPolicy::Strict);
+    assert_eq!(tlua::number_policy(&lua), NumberPolicy::Strict);
+    assert_eq!(lua.eval::<u8>("return 255").unwrap(), 255);
+    assert_eq!(lua.eval::<i64>("return -3").unwrap(), -3);
+
+    let e = lua.eval::<u8>("return 256").unwrap_err();
+    let LuaError::WrongType(e) = e else {
+        panic!("unexpected error {}", e);
+    };
+    assert_eq!(
+        e.number_error(),
+        Some(&NumberError::OutOfRange("256".into()))
+    );
+    assert_eq!(
+        e.to_string(),
+        "failed reading value(s) returned by Lua: u8 expected, got number (number 256 is out of range)"
+    );
+    let e = lua.eval::<i32>("return 3.5").unwrap_err();
+    assert!(
+        matches!(e, LuaError::WrongType(e) if e.number_error() == Some(&NumberError::Fractional(3.5)))
+    );
+    let e = lua.eval::<Vec<u16>>("return {1, 2, -3}").unwrap_err();
+    assert!(
+        matches!(e, LuaError::WrongType(e) if e.number_error() == Some(&NumberError::OutOfRange("-3".into())))
+    );
+    let e = lua.eval::<u32>("return 0/0").unwrap_err();
+    assert!(
+        matches!(e, LuaError::WrongType(e) if matches!(e.number_error(), Some(NumberError::NotFinite(_))))
+    );
+
+    // The per call policy takes precedence.
+    let i: i32 = with_number_policy(NumberPolicy::Truncate, || lua.eval("return 3.5").unwrap());
+    assert_eq!(i, 3);
+    assert_eq!(tlua::number_policy(&lua), NumberPolicy::Strict);
+
+    // The 64 bit cdata are checked as well.
+    let lua = tarantool::lua_state();
+    assert_eq!(read!(lua, Strict, "return 1ULL * 2^63"), Some(1_u64 << 63));
+    assert_eq!(read!(lua, Strict, "return 1ULL * 2^63"), None::<i64>);
+    assert_eq!(read!(lua, Strict, "return -5LL"), Some(-5_i8));
+    assert_eq!(read!(lua, Strict, "return -5LL"), None::<u64>);
+    assert_eq!(read!(lua, Saturate, "return 1ULL * 2^63"), Some(i64::MAX));
+    assert_eq!(read!(lua, Saturate, "return -5LL"), Some(0_u64));
+
+    // Numeric strings aren't read as integers with any of the policies.
+    assert_eq!(read!(lua, Truncate, "return '42'"), None::<i32>);
+    assert_eq!(read!(lua, Truncate, "return '42'"), None::<u64>);
+    assert_eq!(read!(lua, Saturate, "return '42'"), None::<i32>);
+    assert_eq!(read!(lua, Saturate, "return '42'"), None::<u64>);
+    assert_eq!(read!(lua, Strict, "return '42'"), None::<i32>);
+    assert_eq!(read!(lua, Strict, "return '42'"), None::<u64>);
+
+    // The per call policy doesn't leak into the other fibers if `f` yields.
+    let i: u8 = with_number_policy(NumberPolicy::Saturate, || {
+        let other = tarantool::fiber::start(|| {
+            let lua = tarantool::lua_state();
+            assert_eq!(tlua::number_policy(&lua), NumberPolicy::Truncate);
+            lua.eval::<u8>("return 300").unwrap()
+        });
+        let other = other.join();
+        assert_eq!(other, 44);
+        lua.eval("return 300").unwrap()
+    });
+    assert_eq!(i, 255);
+    assert_eq!(tlua::number_policy(&lua), NumberPolicy::Truncate);
+}
+
 pub fn cdata_numbers() {
     let lua = tarantool::lua_state();
 
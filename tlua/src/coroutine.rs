@@ -0,0 +1,255 @@
+use std::mem::MaybeUninit;
+
+use crate::{
+    ffi, AsLua, CallError, LuaError, LuaFunction, LuaRead, LuaState, LuaThread, PushGuard,
+    PushInto, StaticLua, ToString, WrongType,
+};
+
+/// Status of a coroutine, see [`LuaThread::status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CoroutineStatus {
+    /// The coroutine hasn't started yet or is suspended in a call to yield.
+    Suspended,
+    /// The coroutine is running, or it has resumed another coroutine and is
+    /// waiting for it to yield or finish.
+    Running,
+    /// The coroutine has finished its body function, or it has stopped with an
+    /// error.
+    Dead,
+}
+
+/// The outcome of [`LuaThread::resume`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Resume<T> {
+    /// The coroutine yielded these values and can be resumed again.
+    Yield(T),
+    /// The coroutine finished returning these values.
+    Return(T),
+}
+
+impl<T> Resume<T> {
+    /// Returns the values passed to yield or returned from the coroutine.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Yield(v) | Self::Return(v) => v,
+        }
+    }
+
+    /// Returns `true` if the coroutine has yielded.
+    #[inline(always)]
+    pub fn is_yield(&self) -> bool {
+        matches!(self, Self::Yield(_))
+    }
+}
+
+impl StaticLua {
+    /// Creates a new coroutine which will run the function `f` once resumed
+    /// (see [`LuaThread::resume`]). This is the equivalent of
+    /// `coroutine.create(f)` in lua.
+    ///
+    /// `f` must belong to the same lua state as `self` or to one of its
+    /// threads.
+    pub fn new_coroutine<L>(&self, f: &LuaFunction<L>) -> LuaThread
+    where
+        L: AsLua,
+    {
+        let thread = self.new_thread();
+        unsafe {
+            let guard = f.as_lua().push_one(f);
+            ffi::lua_xmove(f.as_lua(), thread.as_lua(), guard.forget_internal());
+        }
+        thread
+    }
+}
+
+impl LuaThread {
+    /// Returns the status of the coroutine. This is the equivalent of
+    /// `coroutine.status(co)` in lua.
+    ///
+    /// Note that a thread which isn't a coroutine (e.g. one created with
+    /// [`StaticLua::new_thread`]) is considered [`CoroutineStatus::Dead`]
+    /// while its stack is empty.
+    pub fn status(&self) -> CoroutineStatus {
+        let l = self.as_lua();
+        unsafe {
+            match ffi::lua_status(l) {
+                ffi::LUA_YIELD => CoroutineStatus::Suspended,
+                ffi::LUA_OK => {
+                    let mut ar = MaybeUninit::<ffi::lua_Debug>::zeroed();
+                    if ffi::lua_getstack(l, 0, ar.as_mut_ptr()) > 0 {
+                        CoroutineStatus::Running
+                    } else if ffi::lua_gettop(l) == 0 {
+                        CoroutineStatus::Dead
+                    } else {
+                        CoroutineStatus::Suspended
+                    }
+                }
+                _ => CoroutineStatus::Dead,
+            }
+        }
+    }
+
+    /// Starts or continues the execution of the coroutine. This is the
+    /// equivalent of `coroutine.resume(co, ...)` in lua.
+    ///
+    /// The first time the coroutine is resumed, `args` are passed as the
+    /// arguments to its body function. When the coroutine is resumed after a
+    /// yield, `args` are passed as the results from the yield.
+    ///
+    /// Returns the values passed to yield or returned from the body function,
+    /// see [`Resume`]. Returns an error if the coroutine isn't suspended, if it
+    /// stops with an error or if the values can't be converted to `R`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use tlua::{Lua, LuaFunction, Resume};
+    ///
+    /// # let lua = Lua::new();
+    /// # let lua = unsafe { Lua::from_static(lua.as_lua()) };
+    /// let gen: LuaFunction<_> = lua
+    ///     .eval("return function(n) for i = 1, n do coroutine.yield(i) end end")
+    ///     .unwrap();
+    /// let co = lua.new_coroutine(&gen);
+    /// assert_eq!(co.resume::<_, i32>(2).unwrap(), Resume::Yield(1));
+    /// assert_eq!(co.resume::<_, i32>(()).unwrap(), Resume::Yield(2));
+    /// assert_eq!(co.resume::<_, ()>(()).unwrap(), Resume::Return(()));
+    /// ```
+    #[track_caller]
+    pub fn resume<'lua, A, R>(&'lua self, args: A) -> Result<Resume<R>, CallError<A::Err>>
+    where
+        A: PushInto<LuaState>,
+        R: LuaRead<PushGuard<&'lua Self>>,
+    {
+        if self.status() != CoroutineStatus::Suspended {
+            return Err(
+                LuaError::ExecutionError("cannot resume non-suspended coroutine".into()).into(),
+            );
+        }
+
+        let l = self.as_lua();
+        let (rc, pushed_value) = unsafe {
+            let old_top = if ffi::lua_status(l) == ffi::LUA_YIELD {
+                ffi::lua_gettop(l)
+            } else {
+                // the body function stays on the stack
+                ffi::lua_gettop(l) - 1
+            };
+            let num_pushed = match l.try_push(args) {
+                Ok(g) => g.forget_internal(),
+                Err((err, _)) => return Err(CallError::PushError(err)),
+            };
+            let rc = ffi::lua_resume(l, num_pushed);
+            let n_results = ffi::lua_gettop(l) - old_top;
+            (rc, PushGuard::new(self, n_results))
+        };
+
+        let is_yield = match rc {
+            ffi::LUA_YIELD => true,
+            ffi::LUA_OK => false,
+            ffi::LUA_ERRMEM => panic!("lua_resume returned LUA_ERRMEM"),
+            _ => {
+                let error_msg = ToString::lua_read(pushed_value)
+                    .map_err(|(_, e)| e)
+                    .expect("can't find error message at the top of the Lua stack");
+                return Err(LuaError::ExecutionError(error_msg.into()).into());
+            }
+        };
+
+        let n_results = pushed_value.size();
+        let values = LuaRead::lua_read_at_maybe_zero_position(pushed_value, -n_results).map_err(
+            |(lua, e)| {
+                LuaError::from(
+                    WrongType::info("reading value(s) passed from coroutine")
+                        .expected_type::<R>()
+                        .actual_multiple_lua(lua, n_results)
+                        .subtype(e),
+                )
+            },
+        )?;
+        if is_yield {
+            Ok(Resume::Yield(values))
+        } else {
+            Ok(Resume::Return(values))
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::{Lua, TempLua};
+
+    fn with_static_lua(f: impl FnOnce(StaticLua)) {
+        let temp = TempLua::new();
+        temp.openlibs();
+        f(unsafe { Lua::from_static(temp.as_lua()) })
+    }
+
+    #[crate::test]
+    fn resume_and_yield() {
+        with_static_lua(|lua| {
+            let f: LuaFunction<_> = lua
+                .eval(
+                    "return function(a, b)
+                        local c = coroutine.yield(a + b)
+                        local d, e = coroutine.yield(c * 2, 'foo')
+                        return d .. e
+                    end",
+                )
+                .unwrap();
+            let co = lua.new_coroutine(&f);
+            assert_eq!(co.status(), CoroutineStatus::Suspended);
+
+            assert_eq!(co.resume::<_, i32>((1, 2)).unwrap(), Resume::Yield(3));
+            assert_eq!(co.status(), CoroutineStatus::Suspended);
+
+            let res: Resume<(i32, String)> = co.resume(10).unwrap();
+            assert_eq!(res, Resume::Yield((20, "foo".into())));
+
+            let res: Resume<String> = co.resume(("bar", "baz")).unwrap();
+            assert_eq!(res, Resume::Return("barbaz".into()));
+            assert_eq!(co.status(), CoroutineStatus::Dead);
+
+            let e = co.resume::<_, ()>(()).unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                "Lua error: cannot resume non-suspended coroutine"
+            );
+        })
+    }
+
+    #[crate::test]
+    fn error_in_coroutine() {
+        with_static_lua(|lua| {
+            let f: LuaFunction<_> = lua
+                .eval("return function() coroutine.yield(); error('oops', 0) end")
+                .unwrap();
+            let co = lua.new_coroutine(&f);
+            assert_eq!(co.resume::<_, ()>(()).unwrap(), Resume::Yield(()));
+            let e = co.resume::<_, ()>(()).unwrap_err();
+            assert_eq!(e.to_string(), "Lua error: oops");
+            assert_eq!(co.status(), CoroutineStatus::Dead);
+        })
+    }
+
+    #[crate::test]
+    fn wrong_type() {
+        with_static_lua(|lua| {
+            let f: LuaFunction<_> = lua
+                .eval("return function() coroutine.yield('not a number') end")
+                .unwrap();
+            let co = lua.new_coroutine(&f);
+            let e = co.resume::<_, i32>(()).unwrap_err();
+            assert!(
+                e.to_string()
+                    .starts_with("Lua error: reading value(s) passed from coroutine"),
+                "{}",
+                e
+            );
+            // the coroutine can still be resumed
+            assert_eq!(co.resume::<_, ()>(()).unwrap(), Resume::Return(()));
+        })
+    }
+}
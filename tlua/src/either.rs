@@ -0,0 +1,211 @@
+use std::collections::LinkedList;
+use std::num::NonZeroI32;
+
+use crate::{AsLua, LuaRead, Push, PushGuard, PushInto, PushOne, PushOneInto};
+use crate::{ReadResult, Void, WrongType};
+
+/// A value which is either of type `A` or of type `B`.
+///
+/// When read from lua, reading `A` is tried first and if it fails, `B` is
+/// tried. If both fail, the error contains the reasons of both failures. So
+/// `Either<u32, String>` can be used for an argument which may be either a
+/// number or a string:
+///
+/// ```no_run
+/// use tlua::{Either, Lua};
+///
+/// let lua = Lua::new();
+/// let v: Either<u32, String> = lua.eval("return 'foo'").unwrap();
+/// assert_eq!(v, Either::Right("foo".into()));
+/// ```
+///
+/// The value is pushed onto the lua stack as is, without any tags.
+///
+/// The same goes for serde: the value is (de)serialized as `A` or `B`
+/// without any tags.
+///
+/// For unions of more than two types see `tarantool::one_of`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> Either<A, B> {
+    #[inline(always)]
+    pub fn is_left(&self) -> bool {
+        matches!(self, Self::Left(_))
+    }
+
+    #[inline(always)]
+    pub fn is_right(&self) -> bool {
+        matches!(self, Self::Right(_))
+    }
+
+    /// Converts `self` into an `Option<A>`, discarding the right value.
+    #[inline]
+    pub fn left(self) -> Option<A> {
+        match self {
+            Self::Left(a) => Some(a),
+            Self::Right(_) => None,
+        }
+    }
+
+    /// Converts `self` into an `Option<B>`, discarding the left value.
+    #[inline]
+    pub fn right(self) -> Option<B> {
+        match self {
+            Self::Left(_) => None,
+            Self::Right(b) => Some(b),
+        }
+    }
+
+    #[inline]
+    pub fn as_ref(&self) -> Either<&A, &B> {
+        match self {
+            Self::Left(a) => Either::Left(a),
+            Self::Right(b) => Either::Right(b),
+        }
+    }
+
+    /// Converts both of the alternatives into `T`.
+    #[inline]
+    pub fn either<T>(self, f: impl FnOnce(A) -> T, g: impl FnOnce(B) -> T) -> T {
+        match self {
+            Self::Left(a) => f(a),
+            Self::Right(b) => g(b),
+        }
+    }
+}
+
+impl<L, A, B> Push<L> for Either<A, B>
+where
+    L: AsLua,
+    A: Push<L>,
+    B: Push<L>,
+    A::Err: Into<Void>,
+    B::Err: Into<Void>,
+{
+    type Err = Void;
+
+    #[inline]
+    fn push_to_lua(&self, lua: L) -> Result<PushGuard<L>, (Void, L)> {
+        match self {
+            Self::Left(a) => Ok(a.push_no_err(lua)),
+            Self::Right(b) => Ok(b.push_no_err(lua)),
+        }
+    }
+}
+
+impl<L, A, B> PushOne<L> for Either<A, B>
+where
+    L: AsLua,
+    A: PushOne<L>,
+    B: PushOne<L>,
+    A::Err: Into<Void>,
+    B::Err: Into<Void>,
+{
+}
+
+impl<L, A, B> PushInto<L> for Either<A, B>
+where
+    L: AsLua,
+    A: PushInto<L>,
+    B: PushInto<L>,
+    A::Err: Into<Void>,
+    B::Err: Into<Void>,
+{
+    type Err = Void;
+
+    #[inline]
+    fn push_into_lua(self, lua: L) -> Result<PushGuard<L>, (Void, L)> {
+        match self {
+            Self::Left(a) => Ok(a.push_into_no_err(lua)),
+            Self::Right(b) => Ok(b.push_into_no_err(lua)),
+        }
+    }
+}
+
+impl<L, A, B> PushOneInto<L> for Either<A, B>
+where
+    L: AsLua,
+    A: PushOneInto<L>,
+    B: PushOneInto<L>,
+    A::Err: Into<Void>,
+    B::Err: Into<Void>,
+{
+}
+
+impl<L, A, B> LuaRead<L> for Either<A, B>
+where
+    L: AsLua,
+    A: LuaRead<L>,
+    B: LuaRead<L>,
+{
+    fn lua_read_at_position(lua: L, index: NonZeroI32) -> ReadResult<Self, L> {
+        let mut errors = LinkedList::new();
+        let lua = match A::lua_read_at_position(lua, index) {
+            Ok(a) => return Ok(Self::Left(a)),
+            Err((lua, e)) => {
+                errors.push_back(e);
+                lua
+            }
+        };
+        let lua = match B::lua_read_at_position(lua, index) {
+            Ok(b) => return Ok(Self::Right(b)),
+            Err((lua, e)) => {
+                errors.push_back(e);
+                lua
+            }
+        };
+        let e = WrongType::info("reading any of the alternatives")
+            .expected_type::<Self>()
+            .actual_single_lua(&lua, index)
+            .subtypes(errors);
+        Err((lua, e))
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::Either;
+    use crate::{AsLua, Lua};
+
+    #[crate::test]
+    fn read_and_push() {
+        let lua = Lua::new();
+
+        let v: Either<u32, String> = lua.eval("return 13").unwrap();
+        assert_eq!(v, Either::Left(13));
+        let v: Either<u32, String> = lua.eval("return 'foo'").unwrap();
+        assert_eq!(v, Either::Right("foo".into()));
+
+        let t: String = lua
+            .eval_with("return type(...)", Either::<u32, &str>::Left(1))
+            .unwrap();
+        assert_eq!(t, "number");
+        let t: String = lua
+            .eval_with("return type(...)", Either::<u32, &str>::Right("foo"))
+            .unwrap();
+        assert_eq!(t, "string");
+
+        // Nested unions.
+        let v: Either<u32, Either<bool, String>> = lua.eval("return true").unwrap();
+        assert_eq!(v, Either::Right(Either::Left(true)));
+
+        let lua = lua.push(vec![1, 2, 3]);
+        let e = lua
+            .read::<Either<u32, String>>()
+            .map(|_| ())
+            .unwrap_err()
+            .1
+            .to_string();
+        assert_eq!(
+            e,
+            "variant #1: failed reading Lua value: u32 expected, got table
+variant #2: failed reading Lua value: alloc::string::String expected, got table
+    while reading any of the alternatives: tlua::either::Either<u32, alloc::string::String> expected, got table"
+        );
+    }
+}
@@ -70,6 +70,27 @@ pub struct luaL_Reg {
 pub type lua_Number = libc::c_double;
 pub type lua_Integer = libc::ptrdiff_t;
 
+pub const LUA_IDSIZE: usize = 60;
+
+/// A structure used to carry different pieces of information about an active
+/// function. [`lua_getstack`] fills only the private part of this structure,
+/// for later use.
+#[repr(C)]
+pub struct lua_Debug {
+    pub event: c_int,
+    pub name: *const c_char,
+    pub namewhat: *const c_char,
+    pub what: *const c_char,
+    pub source: *const c_char,
+    pub currentline: c_int,
+    pub nups: c_int,
+    pub linedefined: c_int,
+    pub lastlinedefined: c_int,
+    pub short_src: [c_char; LUA_IDSIZE],
+    /* private part */
+    i_ci: c_int,
+}
+
 /// Type for C functions.
 ///
 /// In order to communicate properly with Lua, a C function must use the
@@ -373,6 +394,64 @@ extern "C" {
     /// - [`LUA_ERRERR`]: error while running the error handler function.
     pub fn lua_pcall(l: *mut lua_State, nargs: c_int, nresults: c_int, errfunc: c_int) -> c_int;
 
+    /// Starts and resumes a coroutine in a given thread.
+    ///
+    /// To start a coroutine, you first create a new thread (see
+    /// [`lua_newthread`]); then you push onto its stack the main function plus
+    /// any arguments; then you call `lua_resume`, with `narg` being the number
+    /// of arguments. This call returns when the coroutine suspends or finishes
+    /// its execution. When it returns, the stack contains all values passed to
+    /// [`lua_yield`], or all values returned by the body function.
+    /// `lua_resume` returns [`LUA_YIELD`] if the coroutine yields, 0 if the
+    /// coroutine finishes its execution without errors, or an error code in
+    /// case of errors (see [`lua_pcall`]). In case of errors, the stack is not
+    /// unwound, so you can use the debug API over it. The error message is on
+    /// the top of the stack. To restart a coroutine, you put on its stack only
+    /// the values to be passed as results from yield, and then call
+    /// `lua_resume`.
+    /// *[-?, +?, -]*
+    pub fn lua_resume(l: *mut lua_State, narg: c_int) -> c_int;
+
+    /// Yields a coroutine.
+    ///
+    /// This function should only be called as the return expression of a C
+    /// function, as follows:
+    /// ```ignore
+    /// return lua_yield (L, nresults);
+    /// ```
+    /// When a C function calls `lua_yield` in that way, the running coroutine
+    /// suspends its execution, and the call to [`lua_resume`] that started this
+    /// coroutine returns. The parameter `nresults` is the number of values from
+    /// the stack that are passed as results to `lua_resume`.
+    /// *[-?, +?, -]*
+    pub fn lua_yield(l: *mut lua_State, nresults: c_int) -> c_int;
+
+    /// Returns the status of the thread `l`.
+    ///
+    /// The status can be 0 for a normal thread, an error code if the thread
+    /// finished its execution with an error, or [`LUA_YIELD`] if the thread is
+    /// suspended.
+    /// *[-0, +0, -]*
+    pub fn lua_status(l: *mut lua_State) -> c_int;
+
+    /// Exchange values between different threads of the same global state.
+    ///
+    /// This function pops `n` values from the stack `from`, and pushes them
+    /// onto the stack `to`.
+    /// *[-?, +?, -]*
+    pub fn lua_xmove(from: *mut lua_State, to: *mut lua_State, n: c_int);
+
+    /// Get information about the interpreter runtime stack.
+    ///
+    /// This function fills parts of a [`lua_Debug`] structure with an
+    /// identification of the activation record of the function executing at a
+    /// given level. Level 0 is the current running function, whereas level
+    /// *n+1* is the function that has called level *n*. When there are no
+    /// errors, `lua_getstack` returns 1; when called with a level greater than
+    /// the stack depth, it returns 0.
+    /// *[-0, +0, -]*
+    pub fn lua_getstack(l: *mut lua_State, level: c_int, ar: *mut lua_Debug) -> c_int;
+
     /// Calls the C function `func` in protected mode. `func` starts with only
     /// one element in its stack, a light userdata containing `ud`. In case of
     /// errors, lua_cpcall returns the same error codes as lua_pcall, plus the
@@ -493,6 +572,19 @@ extern "C" {
     pub fn luaL_error(l: *mut lua_State, fmt: *const c_char, ...) -> c_int;
     pub fn luaL_openlibs(L: *mut lua_State);
 
+    /// Loads a buffer as a lua chunk. This function uses [`lua_load`] to load
+    /// the chunk in the buffer pointed to by `buff` with size `sz`.
+    /// *[-0, +1, m]*
+    ///
+    /// This function returns the same results as [`lua_load`]. `name` is the
+    /// chunk name, used for debug information and error messages.
+    pub fn luaL_loadbuffer(
+        l: *mut lua_State,
+        buff: *const c_char,
+        sz: usize,
+        name: *const c_char,
+    ) -> c_int;
+
     /// Creates and returns a reference, in the table at index `t`, for the
     /// object at the top of the stack (and pops the object).
     /// *[-1, +0, m]*
@@ -731,3 +823,8 @@ extern "C" {
     /// *[-0, +1, m]*
     pub fn luaT_tolstring(l: *mut lua_State, idx: c_int, len: *mut usize) -> *const c_char;
 }
+
+extern "C" {
+    /// Returns the current fiber, used as an opaque key.
+    pub fn fiber_self() -> *mut c_void;
+}
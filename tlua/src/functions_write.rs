@@ -224,35 +224,7 @@ macro_rules! impl_function_ext {
 
             #[inline]
             fn push_into_lua(self, lua: L) -> Result<PushGuard<L>, (Void, L)> {
-                unsafe {
-                    // pushing the function pointer as a userdata
-                    let ud = ffi::lua_newuserdata(lua.as_lua(), mem::size_of::<Self>() as _);
-                    ptr::write(ud.cast(), self);
-
-                    if std::mem::needs_drop::<Self>() {
-                        // Creating a metatable.
-                        ffi::lua_newtable(lua.as_lua());
-
-                        // Index "__gc" in the metatable calls the object's destructor.
-                        lua.as_lua().push("__gc").forget_internal();
-                        ffi::lua_pushcfunction(lua.as_lua(), wrap_gc::<Self>);
-                        ffi::lua_settable(lua.as_lua(), -3);
-
-                        ffi::lua_setmetatable(lua.as_lua(), -2);
-                    }
-
-                    // pushing wrapper as a closure
-                    ffi::lua_pushcclosure(lua.as_lua(), wrapper::<Self, _, R>, 1);
-                    return Ok(PushGuard::new(lua, 1));
-
-                    extern "C-unwind" fn wrap_gc<T>(lua: LuaState) -> i32 {
-                        unsafe {
-                            let obj = ffi::lua_touserdata(lua, -1);
-                            ptr::drop_in_place(obj.cast::<T>());
-                            0
-                        }
-                    }
-                }
+                Ok(push_callback(lua, self))
             }
         }
 
@@ -451,6 +423,47 @@ where
 {
 }
 
+/// Pushes `callback` as a lua function, which calls it with the arguments
+/// passed from lua. The callback is dropped once the lua function is garbage
+/// collected.
+pub(crate) fn push_callback<L, T, A, R>(lua: L, callback: T) -> PushGuard<L>
+where
+    L: AsLua,
+    T: FnMutExt<A, Output = R> + 'static,
+    A: for<'p> LuaRead<&'p InsideCallback> + 'static,
+    R: PushInto<InsideCallback>,
+{
+    unsafe {
+        // pushing the function pointer as a userdata
+        let ud = ffi::lua_newuserdata(lua.as_lua(), mem::size_of::<T>() as _);
+        ptr::write(ud.cast(), callback);
+
+        if std::mem::needs_drop::<T>() {
+            // Creating a metatable.
+            ffi::lua_newtable(lua.as_lua());
+
+            // Index "__gc" in the metatable calls the object's destructor.
+            lua.as_lua().push("__gc").forget_internal();
+            ffi::lua_pushcfunction(lua.as_lua(), wrap_gc::<T>);
+            ffi::lua_settable(lua.as_lua(), -3);
+
+            ffi::lua_setmetatable(lua.as_lua(), -2);
+        }
+
+        // pushing wrapper as a closure
+        ffi::lua_pushcclosure(lua.as_lua(), wrapper::<T, _, R>, 1);
+        return PushGuard::new(lua, 1);
+    }
+
+    extern "C-unwind" fn wrap_gc<T>(lua: LuaState) -> i32 {
+        unsafe {
+            let obj = ffi::lua_touserdata(lua, -1);
+            ptr::drop_in_place(obj.cast::<T>());
+            0
+        }
+    }
+}
+
 // this function is called when Lua wants to call one of our functions
 extern "C-unwind" fn wrapper<T, A, R>(lua: LuaState) -> libc::c_int
 where
@@ -135,6 +135,8 @@ pub use ::tlua_derive::test;
 
 pub use any::{AnyHashableLuaValue, AnyLuaString, AnyLuaValue};
 pub use cdata::{AsCData, CData, CDataOnStack};
+pub use coroutine::{CoroutineStatus, Resume};
+pub use either::Either;
 pub use functions_write::{
     function0, function1, function10, function2, function3, function4, function5, function6,
     function7, function8, function9, protected_call, CFunction, Function, InsideCallback, Throw,
@@ -145,11 +147,16 @@ pub use lua_tables::{LuaTable, LuaTableIterator};
 pub use object::{
     Call, CallError, Callable, Index, Indexable, IndexableRW, MethodCallError, NewIndex, Object,
 };
+pub use result::{LuaConventionError, LuaResult};
 pub use rust_tables::{PushIterError, PushIterErrorOf, TableFromIter};
+pub use scope::{Scope, ScopedFunction};
 pub use tuples::{AsTable, TuplePushError};
 pub use userdata::UserdataOnStack;
 pub use userdata::{push_some_userdata, push_userdata, read_userdata};
-pub use values::{False, Nil, Null, Strict, StringInLua, ToString, True, Typename};
+pub use values::{
+    number_policy, with_number_policy, False, Nil, Null, NumberError, NumberPolicy, Strict,
+    StringInLua, ToString, True, Typename,
+};
 
 #[deprecated = "Use `CallError` instead"]
 pub type LuaFunctionCallError<E> = CallError<E>;
@@ -158,14 +165,18 @@ pub type LuaSequence = Vec<AnyLuaValue>;
 
 mod any;
 mod cdata;
+mod coroutine;
 pub mod debug;
+mod either;
 pub mod ffi;
 mod functions_write;
 mod lua_functions;
 mod lua_tables;
 mod macros;
 mod object;
+mod result;
 mod rust_tables;
+mod scope;
 #[cfg(feature = "internal_test")]
 pub mod test;
 mod tuples;
@@ -789,6 +800,7 @@ pub struct WrongType {
     rust_expected: String,
     lua_actual: String,
     subtypes: LinkedList<WrongType>,
+    number_error: Option<NumberError>,
 }
 
 impl<E> From<WrongType> for CallError<E> {
@@ -804,6 +816,7 @@ impl Default for WrongType {
             rust_expected: Default::default(),
             lua_actual: Default::default(),
             subtypes: Default::default(),
+            number_error: None,
         }
     }
 }
@@ -923,6 +936,21 @@ impl WrongType {
         self.subtypes = subtypes;
         self
     }
+
+    #[inline(always)]
+    pub fn with_number_error(mut self, e: NumberError) -> Self {
+        self.number_error = Some(e);
+        self
+    }
+
+    /// Returns the reason a number couldn't be read with
+    /// [`NumberPolicy::Strict`] if that's why the error happened, including
+    /// when reading a part of a composite value (e.g. a table field).
+    pub fn number_error(&self) -> Option<&NumberError> {
+        self.number_error
+            .as_ref()
+            .or_else(|| self.subtypes.iter().find_map(Self::number_error))
+    }
 }
 
 pub fn typename(lua: impl AsLua, index: i32) -> &'static CStr {
@@ -1403,6 +1431,58 @@ where
         }
     }
 
+    /// Sets the policy of reading lua numbers into rust integers for this
+    /// lua state and all of its threads, see [`NumberPolicy`].
+    #[inline]
+    pub fn set_number_policy(&self, policy: NumberPolicy) {
+        values::set_state_number_policy(self.lua, policy)
+    }
+
+    /// Returns the policy of reading lua numbers into rust integers set for
+    /// this lua state, see [`NumberPolicy`].
+    ///
+    /// Unlike [`number_policy`] this doesn't take [`with_number_policy`] into
+    /// account.
+    #[inline]
+    pub fn number_policy(&self) -> NumberPolicy {
+        values::state_number_policy(self.lua)
+    }
+
+    /// Creates a [`Scope`] in which rust closures which borrow the local
+    /// state (i.e. which are not `'static`) can be passed to lua. The
+    /// closures are dropped when `f` returns, calling them from lua after
+    /// that raises a lua error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use tlua::Lua;
+    /// let lua = Lua::new();
+    ///
+    /// let mut sum = 0;
+    /// lua.scope(|scope| {
+    ///     scope.set("add", tlua::function1(|x: i32| sum += x));
+    ///     lua.exec("add(1); add(2)").unwrap();
+    /// });
+    /// assert_eq!(sum, 3);
+    /// ```
+    ///
+    /// # Aborts
+    ///
+    /// The process is aborted if a scoped closure is still running when the
+    /// scope ends, which can only happen if the closure yields (e.g. the
+    /// current fiber in tarantool) and the scope ends in another coroutine.
+    /// The reason is reported via the panic hook before aborting, unwinding
+    /// isn't possible, because the closure would still access the borrowed
+    /// values afterwards.
+    pub fn scope<'lua, 'scope, F, T>(&'lua self, f: F) -> T
+    where
+        F: FnOnce(&Scope<'lua, 'scope, OnDrop>) -> T,
+    {
+        let scope = Scope::new(self);
+        f(&scope)
+    }
+
     /// Modifies the value of a global variable.
     // TODO: docs
     #[inline]
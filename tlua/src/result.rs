@@ -0,0 +1,184 @@
+use std::fmt::Display;
+use std::num::NonZeroI32;
+
+use crate::values::is_null_or_nil;
+use crate::{ffi, AsLua, LuaRead, Nil, Push, PushGuard, PushInto, ReadResult, ToString};
+
+/// A result of a lua function which follows the common lua convention of
+/// returning `nil, err` (or `false, err`) on failure.
+///
+/// When read from lua, the values `nil, err` and `false, err` (where `err`
+/// isn't `nil`) are read as `Err` with the error converted to a string (using
+/// the `__tostring` metamethod if there's one). Anything else is read as
+/// `Ok(T)`, so `LuaResult<Option<T>>` can be used for a function which
+/// returns `nil` without an error if there's no value.
+///
+/// When pushed onto the lua stack `Ok(v)` is pushed as `v` and `Err(e)` is
+/// pushed as 2 values: `nil` and `e` converted to a string.
+///
+/// ```no_run
+/// use tlua::{Lua, LuaResult};
+///
+/// let lua = Lua::new();
+/// lua.exec("function open(path) return nil, path .. ': no such file' end").unwrap();
+/// let res: LuaResult<String> = lua.eval("return open('foo')").unwrap();
+/// assert_eq!(res.into_result().unwrap_err().to_string(), "foo: no such file");
+///
+/// let res: LuaResult<u32> = lua.eval("return 42").unwrap();
+/// assert_eq!(res.into_result().unwrap(), 42);
+///
+/// let (value, err): (Option<u32>, String) = lua
+///     .eval_with("return ...", LuaResult::<u32, _>(Err("oops")))
+///     .unwrap();
+/// assert_eq!((value, err.as_str()), (None, "oops"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LuaResult<T, E = LuaConventionError>(pub Result<T, E>);
+
+impl<T, E> LuaResult<T, E> {
+    #[inline(always)]
+    pub fn into_result(self) -> Result<T, E> {
+        self.0
+    }
+}
+
+impl<T, E> From<Result<T, E>> for LuaResult<T, E> {
+    #[inline(always)]
+    fn from(res: Result<T, E>) -> Self {
+        Self(res)
+    }
+}
+
+impl<T, E> From<LuaResult<T, E>> for Result<T, E> {
+    #[inline(always)]
+    fn from(res: LuaResult<T, E>) -> Self {
+        res.0
+    }
+}
+
+/// The error returned from a lua function as the second value after `nil` or
+/// `false`, see [`LuaResult`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("{message}")]
+pub struct LuaConventionError {
+    /// The error converted to a string.
+    pub message: String,
+}
+
+impl<L, T, E> Push<L> for LuaResult<T, E>
+where
+    L: AsLua,
+    T: Push<L>,
+    E: Display,
+{
+    type Err = T::Err;
+
+    #[inline]
+    fn push_to_lua(&self, lua: L) -> Result<PushGuard<L>, (T::Err, L)> {
+        match &self.0 {
+            Ok(v) => v.push_to_lua(lua),
+            Err(e) => Ok((Nil, e.to_string()).push_into_no_err(lua)),
+        }
+    }
+}
+
+impl<L, T, E> PushInto<L> for LuaResult<T, E>
+where
+    L: AsLua,
+    T: PushInto<L>,
+    E: Display,
+{
+    type Err = T::Err;
+
+    #[inline]
+    fn push_into_lua(self, lua: L) -> Result<PushGuard<L>, (T::Err, L)> {
+        match self.0 {
+            Ok(v) => v.push_into_lua(lua),
+            Err(e) => Ok((Nil, e.to_string()).push_into_no_err(lua)),
+        }
+    }
+}
+
+impl<L, T> LuaRead<L> for LuaResult<T>
+where
+    L: AsLua,
+    T: LuaRead<L>,
+{
+    #[inline(always)]
+    fn n_values_expected() -> i32 {
+        2
+    }
+
+    fn lua_read_at_position(lua: L, index: NonZeroI32) -> ReadResult<Self, L> {
+        let l = lua.as_lua();
+        let i = index.get();
+        // The index of the error if there's one.
+        let err_index = unsafe {
+            if i < -1 || i > 0 && i < ffi::lua_gettop(l) {
+                Some(i + 1)
+            } else {
+                None
+            }
+        };
+        let is_failure = unsafe {
+            let is_falsy =
+                is_null_or_nil(l, i) || ffi::lua_isboolean(l, i) && ffi::lua_toboolean(l, i) == 0;
+            is_falsy && err_index.map_or(false, |e| !is_null_or_nil(l, e))
+        };
+        if let (true, Some(err_index)) = (is_failure, err_index) {
+            let err_index = NonZeroI32::new(err_index).expect("index is not zero");
+            return match ToString::lua_read_at_position(&lua, err_index) {
+                Ok(ToString(message)) => Ok(Self(Err(LuaConventionError { message }))),
+                Err((_, e)) => Err((lua, e)),
+            };
+        }
+        T::lua_read_at_position(lua, index).map(|v| Self(Ok(v)))
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::{LuaConventionError, LuaResult};
+    use crate::{AsLua, Lua, Nil};
+
+    #[crate::test]
+    fn read_and_push() {
+        let lua = Lua::new();
+
+        fn err<T>(message: &str) -> LuaResult<T> {
+            LuaResult(Err(LuaConventionError {
+                message: message.into(),
+            }))
+        }
+        let v: LuaResult<u32> = lua.eval("return 13").unwrap();
+        assert_eq!(v, LuaResult(Ok(13)));
+        let v: LuaResult<u32> = lua.eval("return nil, 'oops'").unwrap();
+        assert_eq!(v, err("oops"));
+        let v: LuaResult<bool> = lua.eval("return false, 'oops'").unwrap();
+        assert_eq!(v, err("oops"));
+        let v: LuaResult<bool> = lua.eval("return false").unwrap();
+        assert_eq!(v, LuaResult(Ok(false)));
+        let v: LuaResult<Option<u32>> = lua.eval("return nil").unwrap();
+        assert_eq!(v, LuaResult(Ok(None)));
+        let v: LuaResult<Option<u32>> = lua.eval("return nil, nil").unwrap();
+        assert_eq!(v, LuaResult(Ok(None)));
+        let v: LuaResult<u32> = lua
+            .eval("return nil, setmetatable({}, {__tostring = function() return 'custom' end})")
+            .unwrap();
+        assert_eq!(v, err("custom"));
+        lua.eval::<LuaResult<u32>>("return 'foo'").unwrap_err();
+
+        let (v, e): (Option<u32>, Option<String>) = lua
+            .eval_with("return ...", LuaResult::<_, &str>(Ok(1)))
+            .unwrap();
+        assert_eq!((v, e), (Some(1), None));
+        let (v, e): (Option<u32>, Option<String>) = lua
+            .eval_with("return ...", LuaResult::<u32, _>(Err("oops")))
+            .unwrap();
+        assert_eq!((v, e), (None, Some("oops".into())));
+
+        // Reading from the stack.
+        let lua = lua.push((Nil, "oops"));
+        assert_eq!(lua.read::<LuaResult<u32>>().ok(), Some(err("oops")));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::functions_write::{push_callback, FnMutExt};
+use crate::{AsLua, Function, InsideCallback, Lua, LuaRead, Push, PushGuard, PushInto, PushOne};
+use crate::{PushOneInto, Throw, Void};
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::mem;
+use std::rc::Rc;
+
+/// A scope in which rust closures borrowing the local state can be passed to
+/// lua, see [`Lua::scope`].
+///
+/// The closures are dropped when the scope ends. If lua calls such a
+/// function afterwards (e.g. if it was saved into a global variable), a lua
+/// error is raised.
+pub struct Scope<'lua, 'scope, OnDrop>
+where
+    OnDrop: crate::on_drop::OnDrop,
+{
+    lua: &'lua Lua<OnDrop>,
+    slots: RefCell<Vec<Rc<dyn Slot>>>,
+    /// Makes `'scope` invariant, so that it can't be shortened to a lifetime
+    /// which ends before the scope.
+    _scope: PhantomData<fn(&'scope ()) -> &'scope ()>,
+}
+
+impl<'lua, 'scope, OnDrop> Scope<'lua, 'scope, OnDrop>
+where
+    OnDrop: crate::on_drop::OnDrop,
+{
+    pub(crate) fn new(lua: &'lua Lua<OnDrop>) -> Self {
+        Self {
+            lua,
+            slots: Default::default(),
+            _scope: PhantomData,
+        }
+    }
+
+    /// Creates a lua function from a closure which may borrow the local
+    /// state, the returned value can be pushed onto the lua stack.
+    ///
+    /// The closure is dropped when the scope ends.
+    pub fn create_function<Z, P, R>(&self, f: Function<Z, P, R>) -> ScopedFunction<P, R>
+    where
+        Function<Z, P, R>: FnMutExt<P, Output = R> + 'scope,
+        P: for<'p> LuaRead<&'p InsideCallback> + 'static,
+        R: PushInto<InsideCallback> + 'static,
+    {
+        let f: Box<dyn FnMutExt<P, Output = R> + 'scope> = Box::new(f);
+        // SAFETY: the closure is dropped in `Scope::drop`, i.e. before
+        // `'scope` ends, after that it's no longer accessible from lua.
+        let f: Box<dyn FnMutExt<P, Output = R> + 'static> = unsafe { mem::transmute(f) };
+        let slot = Rc::new(RefCell::new(Some(f)));
+        self.slots.borrow_mut().push(slot.clone());
+        ScopedFunction { slot }
+    }
+
+    /// Sets the value of a global variable to a lua function created from a
+    /// closure which may borrow the local state.
+    ///
+    /// See also [`Lua::set`] and [`Self::create_function`].
+    #[inline]
+    pub fn set<I, Z, P, R>(&self, index: I, f: Function<Z, P, R>)
+    where
+        I: Borrow<str>,
+        Function<Z, P, R>: FnMutExt<P, Output = R> + 'scope,
+        P: for<'p> LuaRead<&'p InsideCallback> + 'static,
+        R: PushInto<InsideCallback> + 'static,
+    {
+        self.lua.set(index, self.create_function(f))
+    }
+}
+
+impl<OnDrop> Drop for Scope<'_, '_, OnDrop>
+where
+    OnDrop: crate::on_drop::OnDrop,
+{
+    fn drop(&mut self) {
+        for slot in self.slots.get_mut().drain(..) {
+            slot.invalidate();
+        }
+    }
+}
+
+/// Type erased storage of a scoped closure.
+///
+/// The closure may only be dropped when it isn't running, otherwise the values
+/// it borrows would be accessed after the scope ends once the closure
+/// continues (which is only possible if it has yielded).
+trait Slot {
+    /// Drops the closure.
+    ///
+    /// # Aborts
+    ///
+    /// The process is aborted if the closure is still running, see the
+    /// invariant above. Unwinding can't be used instead, because it would drop
+    /// the borrowed values all the same.
+    fn invalidate(&self);
+}
+
+type Callback<P, R> = Box<dyn FnMutExt<P, Output = R>>;
+
+impl<P, R> Slot for RefCell<Option<Callback<P, R>>> {
+    fn invalidate(&self) {
+        let callback = match self.try_borrow_mut() {
+            Ok(mut callback) => callback.take(),
+            Err(_) => {
+                /// Turns the panic below into an abort once the panic message
+                /// is reported by the panic hook.
+                struct AbortOnUnwind;
+                impl Drop for AbortOnUnwind {
+                    fn drop(&mut self) {
+                        std::process::abort();
+                    }
+                }
+                let _guard = AbortOnUnwind;
+                panic!(
+                    "scoped function is still running at the end of its scope, \
+                    the values it borrows would be accessed after they're dropped"
+                );
+            }
+        };
+        drop(callback);
+    }
+}
+
+/// A lua function created from a closure which may borrow the local state,
+/// see [`Scope::create_function`].
+pub struct ScopedFunction<P, R> {
+    slot: Rc<RefCell<Option<Callback<P, R>>>>,
+}
+
+impl<P, R> Clone for ScopedFunction<P, R> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<P, R> std::fmt::Debug for ScopedFunction<P, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopedFunction")
+            .field("is_alive", &self.is_alive())
+            .finish()
+    }
+}
+
+impl<P, R> ScopedFunction<P, R> {
+    /// Returns `false` if the scope of the function has ended.
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        self.slot.try_borrow().map_or(true, |slot| slot.is_some())
+    }
+}
+
+impl<P, R> FnMutExt<P> for ScopedFunction<P, R> {
+    type Output = Result<R, Throw<&'static str>>;
+
+    fn call_mut(&mut self, params: P) -> Self::Output {
+        let mut slot = match self.slot.try_borrow_mut() {
+            Ok(slot) => slot,
+            Err(_) => return Err(Throw("scoped function called recursively")),
+        };
+        match slot.as_mut() {
+            Some(f) => Ok(f.call_mut(params)),
+            None => Err(Throw("scoped function called after its scope ended")),
+        }
+    }
+}
+
+impl<L, P, R> PushInto<L> for ScopedFunction<P, R>
+where
+    L: AsLua,
+    P: for<'p> LuaRead<&'p InsideCallback> + 'static,
+    R: PushInto<InsideCallback> + 'static,
+{
+    type Err = Void;
+
+    #[inline]
+    fn push_into_lua(self, lua: L) -> Result<PushGuard<L>, (Void, L)> {
+        Ok(push_callback(lua, self))
+    }
+}
+
+impl<L, P, R> PushOneInto<L> for ScopedFunction<P, R>
+where
+    L: AsLua,
+    P: for<'p> LuaRead<&'p InsideCallback> + 'static,
+    R: PushInto<InsideCallback> + 'static,
+{
+}
+
+impl<L, P, R> Push<L> for ScopedFunction<P, R>
+where
+    L: AsLua,
+    P: for<'p> LuaRead<&'p InsideCallback> + 'static,
+    R: PushInto<InsideCallback> + 'static,
+{
+    type Err = Void;
+
+    #[inline]
+    fn push_to_lua(&self, lua: L) -> Result<PushGuard<L>, (Void, L)> {
+        Ok(push_callback(lua, self.clone()))
+    }
+}
+
+impl<L, P, R> PushOne<L> for ScopedFunction<P, R>
+where
+    L: AsLua,
+    P: for<'p> LuaRead<&'p InsideCallback> + 'static,
+    R: PushInto<InsideCallback> + 'static,
+{
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use crate::Lua;
+
+    #[crate::test]
+    fn borrow_local_state() {
+        let lua = Lua::new();
+
+        let mut calls = vec![];
+        let prefix = String::from("got ");
+        lua.scope(|scope| {
+            scope.set(
+                "cb",
+                crate::function1(|x: i32| {
+                    calls.push(format!("{}{}", prefix, x));
+                    x * 2
+                }),
+            );
+            let res: i32 = lua.eval("return cb(1) + cb(2)").unwrap();
+            assert_eq!(res, 6);
+        });
+        assert_eq!(calls, ["got 1", "got 2"]);
+
+        let e = lua.exec("cb(3)").unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("scoped function called after its scope ended"),
+            "{}",
+            e
+        );
+    }
+
+    #[crate::test]
+    fn create_function() {
+        let lua = Lua::new();
+
+        let total = std::cell::Cell::new(0);
+        let f = lua.scope(|scope| {
+            let f = scope.create_function(crate::function1(|x: i32| total.set(total.get() + x)));
+            lua.exec_with("local f = ...; f(1); f(2)", &f).unwrap();
+            assert!(f.is_alive());
+            f
+        });
+        assert_eq!(total.get(), 3);
+        assert!(!f.is_alive());
+    }
+}
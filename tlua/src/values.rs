@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::ffi::{CStr, CString, OsStr, OsString};
 use std::mem::MaybeUninit;
@@ -10,6 +12,7 @@ use std::path::{Path, PathBuf};
 use std::ptr::null_mut;
 use std::slice;
 use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::{
     ffi, AnyLuaString, AsLua, LuaRead, Push, PushGuard, PushInto, PushOne, PushOneInto, ReadResult,
@@ -17,7 +20,21 @@ use crate::{
 };
 
 macro_rules! numeric_impl {
-    ($t:ident, $push:path, $read:path $(, coerce: $coerce:expr)?) => {
+    (@check_policy int $t:ident, $lua:ident, $index:ident) => {
+        let policy = number_policy(&$lua);
+        if policy != NumberPolicy::Truncate {
+            return read_integer_checked::<$t, _>(
+                $lua,
+                $index,
+                policy,
+                <$t>::MIN as _,
+                <$t>::MAX as _,
+            )
+            .map(|v| v as _);
+        }
+    };
+    (@check_policy float $t:ident, $lua:ident, $index:ident) => {};
+    ($k:ident $t:ident, $push:path, $read:path $(, coerce: $coerce:expr)?) => {
         impl<L> Push<L> for $t
         where
             L: AsLua,
@@ -63,6 +80,7 @@ macro_rules! numeric_impl {
         {
             #[inline(always)]
             fn lua_read_at_position(lua: L, index: NonZeroI32) -> ReadResult<$t, L> {
+                numeric_impl!(@check_policy $k $t, lua, index);
                 return if let Some(v) = unsafe { read_numeric(lua.as_lua(), index.into()) } {
                     Ok(v as _)
                 } else {
@@ -108,13 +126,13 @@ macro_rules! numeric_impl {
     }
 }
 
-numeric_impl! {isize, ffi::luaL_pushint64, ffi::lua_tonumber}
-numeric_impl! {i64, ffi::luaL_pushint64, ffi::lua_tonumber}
-numeric_impl! {i32, ffi::lua_pushinteger, ffi::lua_tointeger}
-numeric_impl! {i16, ffi::lua_pushinteger, ffi::lua_tointeger}
-numeric_impl! {i8, ffi::lua_pushinteger, ffi::lua_tointeger}
+numeric_impl! {int isize, ffi::luaL_pushint64, ffi::lua_tonumber}
+numeric_impl! {int i64, ffi::luaL_pushint64, ffi::lua_tonumber}
+numeric_impl! {int i32, ffi::lua_pushinteger, ffi::lua_tointeger}
+numeric_impl! {int i16, ffi::lua_pushinteger, ffi::lua_tointeger}
+numeric_impl! {int i8, ffi::lua_pushinteger, ffi::lua_tointeger}
 
-numeric_impl! {usize, ffi::luaL_pushuint64, ffi::lua_tonumber,
+numeric_impl! {int usize, ffi::luaL_pushuint64, ffi::lua_tonumber,
     coerce: |n| {
         if n >= 0. {
             n as usize
@@ -123,7 +141,7 @@ numeric_impl! {usize, ffi::luaL_pushuint64, ffi::lua_tonumber,
         }
     }
 }
-numeric_impl! {u64, ffi::luaL_pushuint64, ffi::lua_tonumber,
+numeric_impl! {int u64, ffi::luaL_pushuint64, ffi::lua_tonumber,
     coerce: |n| {
         if n >= 0. {
             n as u64
@@ -132,12 +150,301 @@ numeric_impl! {u64, ffi::luaL_pushuint64, ffi::lua_tonumber,
         }
     }
 }
-numeric_impl! {u32, ffi::lua_pushinteger, ffi::lua_tointeger}
-numeric_impl! {u16, ffi::lua_pushinteger, ffi::lua_tointeger}
-numeric_impl! {u8, ffi::lua_pushinteger, ffi::lua_tointeger}
+numeric_impl! {int u32, ffi::lua_pushinteger, ffi::lua_tointeger}
+numeric_impl! {int u16, ffi::lua_pushinteger, ffi::lua_tointeger}
+numeric_impl! {int u8, ffi::lua_pushinteger, ffi::lua_tointeger}
+
+numeric_impl! {float f64, ffi::lua_pushnumber, ffi::lua_tonumber}
+numeric_impl! {float f32, ffi::lua_pushnumber, ffi::lua_tonumber}
+
+////////////////////////////////////////////////////////////////////////////////
+// NumberPolicy
+////////////////////////////////////////////////////////////////////////////////
+
+/// Specifies how a lua number is read into a rust integer type (`i8`..`i64`,
+/// `u8`..`u64`, `isize`, `usize`) if the target type can't represent it
+/// exactly.
+///
+/// The policy is taken from the innermost [`with_number_policy`] call if
+/// there's one, otherwise from the lua state (see
+/// [`Lua::set_number_policy`](crate::Lua::set_number_policy)), otherwise
+/// it's [`NumberPolicy::Truncate`].
+///
+/// Numeric strings are never read as integers regardless of the policy, only
+/// lua numbers and numeric cdata are.
+///
+/// The policy doesn't affect the floating point types and pushing the
+/// integers, which is always exact: the 64 bit integers which don't fit into
+/// a lua number without losing precision are pushed as `int64_t` or
+/// `uint64_t` cdata.
+///
+/// ```no_run
+/// use tlua::{Lua, NumberPolicy};
+/// let lua = Lua::new();
+/// let i: u8 = lua.eval("return 300.5").unwrap();
+/// assert_eq!(i, 44);
+///
+/// let i: u8 = tlua::with_number_policy(NumberPolicy::Saturate, || {
+///     lua.eval("return 300.5").unwrap()
+/// });
+/// assert_eq!(i, 255);
+///
+/// lua.set_number_policy(NumberPolicy::Strict);
+/// assert!(lua.eval::<u8>("return 300").is_err());
+/// assert!(lua.eval::<i32>("return 3.5").is_err());
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum NumberPolicy {
+    /// The fractional part is discarded and the values out of the range of
+    /// the target type are wrapped around, same as with the `as` cast
+    /// between the integer types.
+    #[default]
+    Truncate,
+    /// The fractional part is discarded and the values out of the range of
+    /// the target type are clamped to its bounds. `NaN` is read as 0.
+    Saturate,
+    /// Reading fails with a [`NumberError`] unless the value is an integer
+    /// within the range of the target type.
+    Strict,
+}
+
+impl NumberPolicy {
+    fn from_raw(raw: ffi::lua_Integer) -> Self {
+        match raw {
+            1 => Self::Saturate,
+            2 => Self::Strict,
+            _ => Self::Truncate,
+        }
+    }
+
+    fn to_raw(self) -> ffi::lua_Integer {
+        match self {
+            Self::Truncate => 0,
+            Self::Saturate => 1,
+            Self::Strict => 2,
+        }
+    }
+}
+
+/// The reason a lua number couldn't be read into a rust integer type with
+/// [`NumberPolicy::Strict`], see [`WrongType::number_error`].
+///
+/// [`WrongType::number_error`]: crate::WrongType::number_error
+#[derive(Debug, PartialEq, Clone)]
+pub enum NumberError {
+    /// The number has a non zero fractional part.
+    Fractional(f64),
+    /// The number is `NaN` or infinite.
+    NotFinite(f64),
+    /// The number doesn't fit into the target type, the value is formatted
+    /// as a string as it can be either a lua number or a 64 bit cdata.
+    OutOfRange(String),
+}
+
+impl std::fmt::Display for NumberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Fractional(v) => write!(f, "number {} has a fractional part", v),
+            Self::NotFinite(v) => write!(f, "number {} is not finite", v),
+            Self::OutOfRange(v) => write!(f, "number {} is out of range", v),
+        }
+    }
+}
+
+thread_local! {
+    /// The policies set by [`with_number_policy`] keyed by the address of the
+    /// fiber, as the fibers may yield within the calls.
+    static CALL_POLICY: RefCell<HashMap<usize, NumberPolicy>> = RefCell::new(HashMap::new());
+}
+
+/// Set once any lua state gets a non default policy, so that the registry
+/// isn't checked on every read otherwise.
+static STATE_POLICY_SET: AtomicBool = AtomicBool::new(false);
+
+/// The address of this static is the registry key of the lua state's policy.
+static STATE_POLICY_KEY: u8 = 0;
+
+#[inline(always)]
+fn current_fiber() -> usize {
+    unsafe { ffi::fiber_self() as usize }
+}
+
+/// Calls `f` with the number `policy` overriding the policy of the lua
+/// states in the current fiber, see [`NumberPolicy`].
+///
+/// The policy only applies to the current fiber even if `f` yields, the
+/// other fibers running meanwhile aren't affected.
+pub fn with_number_policy<F, R>(policy: NumberPolicy, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Restore {
+        fiber: usize,
+        prev: Option<NumberPolicy>,
+    }
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CALL_POLICY.with(|p| {
+                let mut p = p.borrow_mut();
+                match self.prev {
+                    Some(prev) => p.insert(self.fiber, prev),
+                    None => p.remove(&self.fiber),
+                };
+            });
+        }
+    }
+    let fiber = current_fiber();
+    let prev = CALL_POLICY.with(|p| p.borrow_mut().insert(fiber, policy));
+    let _restore = Restore { fiber, prev };
+    f()
+}
 
-numeric_impl! {f64, ffi::lua_pushnumber, ffi::lua_tonumber}
-numeric_impl! {f32, ffi::lua_pushnumber, ffi::lua_tonumber}
+/// Returns the number policy in effect for the lua state in the current
+/// fiber, see [`NumberPolicy`].
+pub fn number_policy(lua: impl AsLua) -> NumberPolicy {
+    let call_policy = CALL_POLICY.with(|p| {
+        let p = p.borrow();
+        if p.is_empty() {
+            return None;
+        }
+        p.get(&current_fiber()).copied()
+    });
+    if let Some(policy) = call_policy {
+        return policy;
+    }
+    if !STATE_POLICY_SET.load(Ordering::Relaxed) {
+        return NumberPolicy::Truncate;
+    }
+    state_number_policy(lua.as_lua())
+}
+
+pub(crate) fn state_number_policy(l: crate::LuaState) -> NumberPolicy {
+    unsafe {
+        ffi::lua_pushlightuserdata(l, &STATE_POLICY_KEY as *const u8 as *mut c_void);
+        ffi::lua_rawget(l, ffi::LUA_REGISTRYINDEX);
+        let raw = ffi::lua_tointeger(l, -1);
+        ffi::lua_pop(l, 1);
+        NumberPolicy::from_raw(raw)
+    }
+}
+
+pub(crate) fn set_state_number_policy(l: crate::LuaState, policy: NumberPolicy) {
+    if policy != NumberPolicy::Truncate {
+        STATE_POLICY_SET.store(true, Ordering::Relaxed);
+    }
+    unsafe {
+        ffi::lua_pushlightuserdata(l, &STATE_POLICY_KEY as *const u8 as *mut c_void);
+        ffi::lua_pushinteger(l, policy.to_raw());
+        ffi::lua_rawset(l, ffi::LUA_REGISTRYINDEX);
+    }
+}
+
+/// A lua number or a numeric cdata.
+#[derive(Clone, Copy)]
+enum RawNumber {
+    Float(f64),
+    Int(i128),
+}
+
+unsafe fn read_raw_number(l: crate::LuaState, idx: c_int) -> Option<RawNumber> {
+    match ffi::lua_type(l, idx) {
+        ffi::LUA_TNUMBER => Some(RawNumber::Float(ffi::lua_tonumber(l, idx))),
+        ffi::LUA_TCDATA => {
+            let mut ctypeid = MaybeUninit::uninit();
+            let cdata = ffi::luaL_checkcdata(l, idx, ctypeid.as_mut_ptr());
+            let int = match ctypeid.assume_init() {
+                ffi::CTID_CCHAR => *cdata.cast::<std::os::raw::c_char>() as i128,
+                ffi::CTID_INT8 => *cdata.cast::<i8>() as i128,
+                ffi::CTID_INT16 => *cdata.cast::<i16>() as i128,
+                ffi::CTID_INT32 => *cdata.cast::<i32>() as i128,
+                ffi::CTID_INT64 => *cdata.cast::<i64>() as i128,
+                ffi::CTID_UINT8 => *cdata.cast::<u8>() as i128,
+                ffi::CTID_UINT16 => *cdata.cast::<u16>() as i128,
+                ffi::CTID_UINT32 => *cdata.cast::<u32>() as i128,
+                ffi::CTID_UINT64 => *cdata.cast::<u64>() as i128,
+                ffi::CTID_FLOAT => return Some(RawNumber::Float(*cdata.cast::<f32>() as f64)),
+                ffi::CTID_DOUBLE => return Some(RawNumber::Float(*cdata.cast::<f64>())),
+                _ => return None,
+            };
+            Some(RawNumber::Int(int))
+        }
+        _ => None,
+    }
+}
+
+/// Converts the `number` to an integer in the range `min..=max` according to
+/// the `policy`, which is either [`NumberPolicy::Saturate`] or
+/// [`NumberPolicy::Strict`].
+fn convert_integer(
+    number: RawNumber,
+    policy: NumberPolicy,
+    min: i128,
+    max: i128,
+) -> Result<i128, NumberError> {
+    let strict = policy == NumberPolicy::Strict;
+    let int = match number {
+        RawNumber::Int(int) => int,
+        RawNumber::Float(f) if f.is_nan() => {
+            if strict {
+                return Err(NumberError::NotFinite(f));
+            }
+            0
+        }
+        RawNumber::Float(f) if f.is_infinite() => {
+            if strict {
+                return Err(NumberError::NotFinite(f));
+            }
+            if f > 0. {
+                max
+            } else {
+                min
+            }
+        }
+        RawNumber::Float(f) => {
+            if strict && f.fract() != 0. {
+                return Err(NumberError::Fractional(f));
+            }
+            // Saturates if the value doesn't fit into i128.
+            f.trunc() as i128
+        }
+    };
+    if (min..=max).contains(&int) {
+        return Ok(int);
+    }
+    if strict {
+        let value = match number {
+            RawNumber::Int(int) => int.to_string(),
+            RawNumber::Float(f) => f.to_string(),
+        };
+        return Err(NumberError::OutOfRange(value));
+    }
+    Ok(int.clamp(min, max))
+}
+
+/// Reads an integer in the range `min..=max` according to the `policy`.
+fn read_integer_checked<T, L>(
+    lua: L,
+    index: NonZeroI32,
+    policy: NumberPolicy,
+    min: i128,
+    max: i128,
+) -> ReadResult<i128, L>
+where
+    L: AsLua,
+{
+    let number = unsafe { read_raw_number(lua.as_lua(), index.into()) };
+    let e = match number.map(|n| convert_integer(n, policy, min, max)) {
+        Some(Ok(int)) => return Ok(int),
+        Some(Err(e)) => WrongType::default()
+            .expected_type::<T>()
+            .actual(format!("number ({})", e))
+            .with_number_error(e),
+        None => WrongType::default()
+            .expected_type::<T>()
+            .actual_single_lua(&lua, index),
+    };
+    Err((lua, e))
+}
 
 macro_rules! strict_numeric_impl {
     (@is_valid int $num:tt $t:ty) => {